@@ -1,6 +1,6 @@
-use std::env;
-
 fn main() {
+    emit_build_metadata();
+
     // Windows SQLCipher with pre-built static libraries
     // Libraries are in project root: lib/ and include/
     // See docs/vcpkg-openssl-static-linking.md for build instructions
@@ -8,7 +8,7 @@ fn main() {
     #[cfg(all(windows, target_env = "msvc"))]
     {
         // Get project root directory
-        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
 
         // Link OpenSSL libraries (order matters - libssl depends on libcrypto)
         println!("cargo:rustc-link-lib=static=libcrypto");
@@ -31,3 +31,34 @@ fn main() {
         println!("cargo:warning=SQLCipher lib dir: {}/lib", manifest_dir);
     }
 }
+
+/// Build metadata for `ccm version` (git commit, build time, target triple)
+fn emit_build_metadata() {
+    let commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=CCM_GIT_COMMIT={}", commit);
+
+    // Reproducible builds pin the date with SOURCE_DATE_EPOCH
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs().to_string())
+            .unwrap_or_default()
+    });
+    println!("cargo:rustc-env=CCM_BUILD_TIMESTAMP={}", timestamp);
+
+    println!(
+        "cargo:rustc-env=CCM_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
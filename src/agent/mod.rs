@@ -0,0 +1,272 @@
+// ccm agent: keeps the unlocked master key in a background process
+//
+// Like ssh-agent, the agent listens on a Unix socket in the private runtime
+// directory (0700, owner-checked) and hands the master key to later `ccm`
+// processes of the same user (both ends check the peer's uid), so a PIN is
+// entered once per session instead of once per command. The key lives in a
+// `KeyCache` (locked into RAM, zeroized on expiry); the agent exits when the
+// key expires (`CCM_KEY_TTL`) or on `ccm agent stop`.
+//
+// Protocol: one request line per connection, one reply line.
+//   KEY  -> OK <hex key> <instance id>   (or ERR <reason>)
+//   PING -> OK <pid>
+//   STOP -> OK
+
+use crate::utils::{CcmError, Result};
+use std::path::PathBuf;
+
+/// Environment variable: socket of the agent to use (and to create)
+pub const AGENT_SOCK_ENV: &str = "CCM_AGENT_SOCK";
+
+/// Socket path: `CCM_AGENT_SOCK`, else `agent.sock` in the user's private
+/// runtime directory
+pub fn socket_path() -> Result<PathBuf> {
+    match std::env::var_os(AGENT_SOCK_ENV).filter(|p| !p.is_empty()) {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(crate::utils::files::private_runtime_dir("agent")?.join("agent.sock")),
+    }
+}
+
+/// Parse a reply to `KEY`
+fn parse_key_reply(reply: &str) -> Option<([u8; 32], String)> {
+    let mut parts = reply.trim().split(' ');
+    if parts.next()? != "OK" {
+        return None;
+    }
+    let key: [u8; 32] = hex::decode(parts.next()?).ok()?.try_into().ok()?;
+    let instance_id = parts.next()?.to_string();
+    Some((key, instance_id))
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use crate::secrets::key_cache::KeyCache;
+    use crate::utils::files::create_private_dir;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::time::Duration;
+    use zeroize::Zeroize;
+
+    const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn current_uid() -> u32 {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        unsafe { libc::geteuid() }
+    }
+
+    /// Effective uid of the process at the other end of `stream`
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(super) fn peer_uid(stream: &UnixStream) -> Result<u32> {
+        let mut cred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        // SAFETY: cred and len are valid for writes of the sizes passed
+        let rc = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if rc != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(cred.uid)
+    }
+
+    /// Effective uid of the process at the other end of `stream`
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub(super) fn peer_uid(stream: &UnixStream) -> Result<u32> {
+        let (mut uid, mut gid) = (0, 0);
+        // SAFETY: uid and gid are valid for writes
+        if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(uid)
+    }
+
+    /// Refuse a connection to or from a process of another user
+    fn check_peer(stream: &UnixStream) -> Result<()> {
+        let uid = peer_uid(stream)?;
+        if uid != current_uid() {
+            return Err(CcmError::Unknown(format!(
+                "Agent socket peer belongs to another user (uid {})",
+                uid
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether the socket file at `path` belongs to the current user
+    pub fn owned_by_current_user(path: &Path) -> bool {
+        std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.uid() == current_uid())
+    }
+
+    /// Send one request to the agent at `path` and return its reply
+    pub fn request_at(path: &Path, request: &str) -> Result<String> {
+        let mut stream = UnixStream::connect(path)?;
+        check_peer(&stream)?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        writeln!(stream, "{}", request)?;
+
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        Ok(reply)
+    }
+
+    /// Bind the agent socket, replacing a stale one; fails if an agent is
+    /// already listening there
+    pub fn bind(path: &Path) -> Result<UnixListener> {
+        if path.exists() {
+            if request_at(path, "PING").is_ok() {
+                return Err(CcmError::InvalidArgument(format!(
+                    "An agent is already running on {}",
+                    path.display()
+                )));
+            }
+            std::fs::remove_file(path)?;
+        }
+        if let Some(dir) = path.parent() {
+            create_private_dir(dir)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    /// Answer requests until the key expires or a `STOP` arrives
+    pub fn serve(listener: UnixListener, cache: &KeyCache) -> Result<()> {
+        listener.set_nonblocking(true)?;
+        loop {
+            if !cache.is_cached() {
+                return Ok(());
+            }
+            let stream = match listener.accept() {
+                Ok((stream, _)) if check_peer(&stream).is_ok() => stream,
+                Ok(_) => continue,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if handle(stream, cache).unwrap_or(false) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Answer one connection; true when the agent should stop
+    fn handle(stream: UnixStream, cache: &KeyCache) -> Result<bool> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        let mut request = String::new();
+        BufReader::new(&stream).read_line(&mut request)?;
+
+        let (mut reply, stop) = match request.trim() {
+            "KEY" => match (cache.get(), cache.instance_id()) {
+                (Some(key), Some(id)) => (format!("OK {} {}\n", hex::encode(key), id), false),
+                _ => ("ERR locked\n".to_string(), true),
+            },
+            "PING" => (format!("OK {}\n", std::process::id()), false),
+            "STOP" => ("OK\n".to_string(), true),
+            _ => ("ERR unknown request\n".to_string(), false),
+        };
+        let result = (&stream).write_all(reply.as_bytes());
+        reply.zeroize();
+        result?;
+        Ok(stop)
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{bind, owned_by_current_user, request_at, serve};
+
+/// Send one request to the agent
+#[cfg(unix)]
+pub fn request(request: &str) -> Result<String> {
+    request_at(&socket_path()?, request)
+}
+
+#[cfg(not(unix))]
+pub fn request(_request: &str) -> Result<String> {
+    Err(CcmError::InvalidArgument(
+        "ccm agent is only supported on Unix systems".to_string(),
+    ))
+}
+
+/// The master key from a running agent, if there is one
+pub fn fetch_key() -> Option<([u8; 32], String)> {
+    let path = socket_path().ok()?;
+    if !path.exists() {
+        return None;
+    }
+    // A socket someone else planted is not our agent
+    #[cfg(unix)]
+    if !owned_by_current_user(&path) {
+        return None;
+    }
+    let mut reply = request("KEY").ok()?;
+    let key = parse_key_reply(&reply);
+    zeroize::Zeroize::zeroize(&mut reply);
+    key
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::secrets::key_cache::KeyCache;
+
+    #[test]
+    fn test_parse_key_reply() {
+        let reply = format!("OK {} abc123\n", hex::encode([5u8; 32]));
+        assert_eq!(
+            parse_key_reply(&reply),
+            Some(([5u8; 32], "abc123".to_string()))
+        );
+        assert_eq!(parse_key_reply("ERR locked\n"), None);
+        assert_eq!(parse_key_reply("OK 1234 abc\n"), None);
+    }
+
+    #[test]
+    fn test_serve() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.sock");
+        let listener = bind(&path).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let cache = KeyCache::new(None);
+            cache.set([3u8; 32], "abc123");
+            serve(listener, &cache).unwrap();
+        });
+
+        let reply = request_at(&path, "KEY").unwrap();
+        assert_eq!(
+            parse_key_reply(&reply),
+            Some(([3u8; 32], "abc123".to_string()))
+        );
+        assert!(request_at(&path, "PING").unwrap().starts_with("OK "));
+        // A second agent on the same socket is refused
+        assert!(bind(&path).is_err());
+        assert!(owned_by_current_user(&path));
+        let (ours, _) = std::os::unix::net::UnixStream::pair().unwrap();
+        // SAFETY: geteuid has no preconditions and cannot fail
+        assert_eq!(unix::peer_uid(&ours).unwrap(), unsafe { libc::geteuid() });
+
+        assert_eq!(request_at(&path, "STOP").unwrap(), "OK\n");
+        server.join().unwrap();
+    }
+}
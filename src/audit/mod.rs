@@ -0,0 +1,222 @@
+// Audit log of sensitive operations and its SIEM export formats
+
+use crate::db::{AuditEvent, StorageBackend};
+use crate::utils::{CcmError, Result};
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, Utc};
+
+/// Entry created
+pub const ENTRY_CREATE: &str = "entry.create";
+/// Entry metadata changed
+pub const ENTRY_UPDATE: &str = "entry.update";
+/// Entry and its secret deleted
+pub const ENTRY_DELETE: &str = "entry.delete";
+/// Secret decrypted
+pub const SECRET_READ: &str = "secret.read";
+/// Secret replaced with a new value
+pub const SECRET_UPDATE: &str = "secret.update";
+/// Secret restored to an earlier version
+pub const SECRET_ROLLBACK: &str = "secret.rollback";
+/// Entry restored from a backup
+pub const ENTRY_RESTORE: &str = "entry.restore";
+/// Entry protected with `ccm lock`
+pub const ENTRY_LOCK: &str = "entry.lock";
+/// Entry protection removed with `ccm unlock`
+pub const ENTRY_UNLOCK: &str = "entry.unlock";
+/// Master key exported to an escrow file
+pub const MASTER_KEY_EXPORT: &str = "master_key.export";
+
+/// Append an event to the audit log
+/// Best effort: a failure here must not fail the operation being audited
+pub fn record(db: &dyn StorageBackend, action: &str, entry_name: Option<&str>) {
+    let timestamp = format_timestamp(Utc::now());
+
+    if let Err(e) = db.append_audit_event(&timestamp, action, entry_name, current_user().as_deref())
+    {
+        crate::debug_log!("Failed to record audit event '{}': {}", action, e);
+    }
+}
+
+/// Append the same event for many entries (bulk operations such as import)
+pub fn record_many(db: &dyn StorageBackend, action: &str, entry_names: &[&str]) {
+    let timestamp = format_timestamp(Utc::now());
+
+    if let Err(e) =
+        db.append_audit_events(&timestamp, action, entry_names, current_user().as_deref())
+    {
+        crate::debug_log!("Failed to record audit events '{}': {}", action, e);
+    }
+}
+
+/// OS user running the command
+fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+/// Timestamp format used for stored events (fixed width, so it sorts as text)
+pub fn format_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parse a `--since` value: a relative age (`30d`, `12h`, `45m`, `2w`),
+/// a date (`2026-01-31`) or an RFC 3339 timestamp
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    let invalid = || {
+        CcmError::InvalidArgument(format!(
+            "Invalid --since value '{}'. Use e.g. 30d, 12h, 2w, 2026-01-31 or an RFC 3339 timestamp",
+            value
+        ))
+    };
+
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<i64>() {
+            let age = match unit {
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                'd' => Duration::days(amount),
+                'w' => Duration::weeks(amount),
+                _ => return Err(invalid()),
+            };
+            return Ok(now - age);
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?.and_utc());
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| invalid())
+}
+
+/// CEF severity (0-10) of an action
+fn severity(action: &str) -> u8 {
+    match action {
+        MASTER_KEY_EXPORT => 8,
+        ENTRY_DELETE | SECRET_ROLLBACK | ENTRY_RESTORE => 6,
+        SECRET_UPDATE | ENTRY_UPDATE | ENTRY_CREATE => 4,
+        _ => 3,
+    }
+}
+
+/// Human-readable event name for the CEF header
+fn describe(action: &str) -> &str {
+    match action {
+        ENTRY_CREATE => "Entry created",
+        ENTRY_UPDATE => "Entry updated",
+        ENTRY_DELETE => "Entry deleted",
+        SECRET_READ => "Secret read",
+        SECRET_UPDATE => "Secret updated",
+        SECRET_ROLLBACK => "Secret rolled back",
+        ENTRY_RESTORE => "Entry restored from backup",
+        MASTER_KEY_EXPORT => "Master key exported",
+        other => other,
+    }
+}
+
+/// Render an event as one JSON line
+pub fn to_jsonl(event: &AuditEvent) -> Result<String> {
+    serde_json::to_string(event).map_err(CcmError::Serialization)
+}
+
+/// Render an event as an ArcSight Common Event Format line
+pub fn to_cef(event: &AuditEvent) -> String {
+    let mut extension = Vec::new();
+    if let Ok(time) = DateTime::parse_from_rfc3339(&event.timestamp) {
+        extension.push(format!("rt={}", time.timestamp_millis()));
+    }
+    extension.push(format!("act={}", cef_extension(&event.action)));
+    extension.push(format!("externalId={}", event.id));
+    if let Some(user) = &event.user {
+        extension.push(format!("suser={}", cef_extension(user)));
+    }
+    if let Some(entry) = &event.entry {
+        extension.push("cs1Label=entry".to_string());
+        extension.push(format!("cs1={}", cef_extension(entry)));
+    }
+
+    format!(
+        "CEF:0|ccm|ccm|{}|{}|{}|{}|{}",
+        cef_header(env!("CARGO_PKG_VERSION")),
+        cef_header(&event.action),
+        cef_header(describe(&event.action)),
+        severity(&event.action),
+        extension.join(" ")
+    )
+}
+
+/// Escape a CEF header field (`\` and `|`)
+fn cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Escape a CEF extension value (`\`, `=` and line breaks)
+fn cef_extension(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(entry: &str) -> AuditEvent {
+        AuditEvent {
+            id: 7,
+            timestamp: "2026-03-01T12:00:00.000Z".to_string(),
+            action: SECRET_READ.to_string(),
+            entry: Some(entry.to_string()),
+            user: Some("alice".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = DateTime::parse_from_rfc3339("2026-03-31T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            format_timestamp(parse_since("30d", now).unwrap()),
+            "2026-03-01T00:00:00.000Z"
+        );
+        assert_eq!(
+            format_timestamp(parse_since("12h", now).unwrap()),
+            "2026-03-30T12:00:00.000Z"
+        );
+        assert_eq!(
+            format_timestamp(parse_since("2026-01-31", now).unwrap()),
+            "2026-01-31T00:00:00.000Z"
+        );
+        assert_eq!(
+            format_timestamp(parse_since("2026-01-31T10:00:00+02:00", now).unwrap()),
+            "2026-01-31T08:00:00.000Z"
+        );
+        assert!(parse_since("30y", now).is_err());
+        assert!(parse_since("soon", now).is_err());
+    }
+
+    #[test]
+    fn test_to_cef_escapes_fields() {
+        let line = to_cef(&event("team|prod=a\nb"));
+        assert!(line.starts_with("CEF:0|ccm|ccm|"));
+        assert!(line.contains("|secret.read|Secret read|3|rt=1772366400000 act=secret.read"));
+        assert!(line.contains("suser=alice"));
+        assert!(line.ends_with("cs1Label=entry cs1=team|prod\\=a\\nb"));
+    }
+
+    #[test]
+    fn test_to_jsonl() {
+        let line = to_jsonl(&event("openai")).unwrap();
+        assert_eq!(
+            line,
+            r#"{"id":7,"timestamp":"2026-03-01T12:00:00.000Z","action":"secret.read","entry":"openai","user":"alice"}"#
+        );
+    }
+}
@@ -0,0 +1,107 @@
+// PBKDF2 cost for the PIN-derived key that wraps the master key
+//
+// The iteration count used for a wrap is stored next to the wrapped key in
+// the keyring, so changing `kdf.iterations` only affects the next re-wrap
+// (`ccm auth calibrate`, `ccm auth change`).
+
+use crate::db::Database;
+use crate::utils::crypto::PBKDF2_ITERATIONS;
+use crate::utils::{CcmError, Result};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+use std::time::{Duration, Instant};
+
+/// Setting: PBKDF2 iterations for new wraps of the master key
+pub const KDF_ITERATIONS_KEY: &str = "kdf.iterations";
+
+/// Lowest accepted iteration count: the default, so neither a setting nor a
+/// calibration on a slow machine weakens the wrap
+pub const MIN_ITERATIONS: u32 = PBKDF2_ITERATIONS;
+
+/// How long unlocking should take on this machine after calibration
+pub const CALIBRATION_TARGET: Duration = Duration::from_millis(250);
+
+/// Parse `kdf.iterations`
+pub fn parse_iterations(value: &str) -> Result<u32> {
+    match value.trim().replace('_', "").parse::<u32>() {
+        Ok(n) if n >= MIN_ITERATIONS => Ok(n),
+        _ => Err(CcmError::InvalidArgument(format!(
+            "Invalid value for {}: '{}' (expected a number of at least {})",
+            KDF_ITERATIONS_KEY, value, MIN_ITERATIONS
+        ))),
+    }
+}
+
+/// Iterations to use for a new wrap: `kdf.iterations`, else the default
+pub fn configured_iterations(db: &Database) -> Result<u32> {
+    Ok(db
+        .get_setting::<String>(KDF_ITERATIONS_KEY)?
+        .and_then(|v| parse_iterations(&v).ok())
+        .unwrap_or(PBKDF2_ITERATIONS))
+}
+
+/// Scale a measurement (`iterations` took `elapsed`) to `target`, rounded
+/// to 10,000 and never below `MIN_ITERATIONS`
+pub fn iterations_for(target: Duration, iterations: u32, elapsed: Duration) -> u32 {
+    let per_iteration = elapsed.as_secs_f64() / iterations as f64;
+    let scaled = (target.as_secs_f64() / per_iteration.max(f64::MIN_POSITIVE)).round() as u64;
+    let rounded = ((scaled + 5_000) / 10_000 * 10_000).min(u32::MAX as u64) as u32;
+    rounded.max(MIN_ITERATIONS)
+}
+
+/// Measure PBKDF2-SHA256 on this machine and pick the iteration count
+/// that takes about `target`
+pub fn calibrate(target: Duration) -> u32 {
+    let salt = [0u8; 32];
+    let mut key = [0u8; 32];
+    let mut iterations = 10_000u32;
+
+    // Grow the sample until it is long enough to time reliably
+    loop {
+        let started = Instant::now();
+        pbkdf2_hmac::<Sha256>(b"calibration", &salt, iterations, &mut key);
+        let elapsed = started.elapsed();
+        if elapsed >= Duration::from_millis(50) || iterations >= 10_000_000 {
+            return iterations_for(target, iterations, elapsed);
+        }
+        iterations *= 4;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_iterations() {
+        assert_eq!(parse_iterations("600_000").unwrap(), 600_000);
+        assert!(parse_iterations("1000").is_err());
+        assert!(parse_iterations("100000").is_err());
+        assert_eq!(
+            parse_iterations(&PBKDF2_ITERATIONS.to_string()).unwrap(),
+            PBKDF2_ITERATIONS
+        );
+        assert!(parse_iterations("many").is_err());
+    }
+
+    #[test]
+    fn test_iterations_for() {
+        // 100k iterations in 50ms: 250ms takes 500k
+        let n = iterations_for(
+            Duration::from_millis(250),
+            100_000,
+            Duration::from_millis(50),
+        );
+        assert_eq!(n, 500_000);
+
+        // A slow machine still gets the default
+        let n = iterations_for(Duration::from_millis(250), 10_000, Duration::from_secs(1));
+        assert_eq!(n, PBKDF2_ITERATIONS);
+        let n = iterations_for(
+            Duration::from_millis(250),
+            100_000,
+            Duration::from_millis(200),
+        );
+        assert_eq!(n, PBKDF2_ITERATIONS);
+    }
+}
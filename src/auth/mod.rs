@@ -1,10 +1,57 @@
 // Authentication and PIN management
 
+pub mod kdf;
 pub mod pin;
 
+use crate::utils::files::{check_private_file, create_private_dir, write_private_file};
 use crate::utils::{CcmError, Result};
 use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use zeroize::Zeroize;
+
+/// Environment variable: PIN for non-interactive use (CI, cron)
+pub const PIN_ENV: &str = "CCM_PIN";
+
+/// File to read the PIN from (global --pin-file)
+static PIN_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Read the PIN from `path` instead of prompting (set once at startup)
+pub fn set_pin_file(path: &Path) {
+    let _ = PIN_FILE.set(path.to_path_buf());
+}
+
+/// A PIN given without a prompt: the --pin-file contents, else `CCM_PIN`
+pub fn non_interactive_pin() -> Result<Option<String>> {
+    if let Some(path) = PIN_FILE.get() {
+        if let Err(e) = check_private_file(path) {
+            eprintln!("⚠️  PIN file: {}", e);
+        }
+        let mut content = fs::read_to_string(path).map_err(|e| {
+            CcmError::InvalidArgument(format!("Cannot read PIN file {}: {}", path.display(), e))
+        })?;
+        let pin = content.trim_end_matches(['\r', '\n']).to_string();
+        content.zeroize();
+        return Ok(Some(pin));
+    }
+
+    Ok(std::env::var(PIN_ENV).ok().filter(|pin| !pin.is_empty()))
+}
+
+/// The PIN from --pin-file or `CCM_PIN`, else a prompt (only on a terminal)
+pub fn read_pin(prompt: &str) -> Result<String> {
+    if let Some(pin) = non_interactive_pin()? {
+        return Ok(pin);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(CcmError::InvalidArgument(format!(
+            "A PIN is required. Set {} or pass --pin-file when running without a terminal",
+            PIN_ENV
+        )));
+    }
+    Ok(dialoguer::Password::new().with_prompt(prompt).interact()?)
+}
 
 /// Get shell process ID
 pub fn get_shell_pid() -> Option<u32> {
@@ -20,11 +67,46 @@ pub fn get_shell_pid() -> Option<u32> {
         })
 }
 
+/// Directory for per-shell session files
+/// CCM_RUNTIME_DIR if set, else $XDG_RUNTIME_DIR/ccm, else the system temp directory
+pub fn runtime_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("CCM_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join("ccm");
+    }
+
+    std::env::temp_dir()
+}
+
 /// Get authentication state file path for current shell
 pub fn auth_state_path() -> PathBuf {
     let pid = get_shell_pid().unwrap_or_else(std::process::id);
-    let temp_dir = std::env::temp_dir();
-    temp_dir.join(format!("ccm-auth-shell-{}.json", pid))
+    runtime_dir().join(format!("ccm-auth-shell-{}.json", pid))
+}
+
+/// Whether a process is still running
+/// Only checked on Unix; elsewhere processes are assumed alive
+pub fn is_process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        use std::process::Command;
+        let result = Command::new("kill")
+            .env_remove(PIN_ENV)
+            .arg("-0")
+            .arg(pid.to_string())
+            .output();
+        if let Ok(output) = result {
+            return output.status.success();
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = pid;
+
+    true
 }
 
 /// Check if current session is authenticated
@@ -36,22 +118,20 @@ pub fn is_authenticated() -> bool {
     }
 
     // Check if shell process is still running
-    if let Some(_pid) = get_shell_pid() {
-        // Try to check if process exists (platform-specific)
-        #[cfg(unix)]
-        {
-            use std::process::Command;
-            let result = Command::new("kill").arg("-0").arg(_pid.to_string()).output();
-            if let Ok(output) = result {
-                if !output.status.success() {
-                    // Process doesn't exist, remove auth file
-                    let _ = fs::remove_file(&auth_file);
-                    return false;
-                }
-            }
+    if let Some(pid) = get_shell_pid() {
+        if !is_process_alive(pid) {
+            // Process doesn't exist, remove auth file
+            let _ = fs::remove_file(&auth_file);
+            return false;
         }
     }
 
+    // Never trust a session file another user could have planted or read
+    if let Err(e) = check_private_file(&auth_file) {
+        crate::debug_log!("Ignoring session file: {}", e);
+        return false;
+    }
+
     // Read and validate auth state
     match fs::read_to_string(&auth_file) {
         Ok(content) => {
@@ -75,8 +155,19 @@ pub fn set_authenticated(authenticated: bool) -> Result<()> {
         pid: get_shell_pid().unwrap_or_else(std::process::id),
     };
 
+    if let Some(dir) = auth_file.parent() {
+        if !dir.exists() {
+            create_private_dir(dir)?;
+        }
+    }
+
+    // Replace anything we would not trust on read (symlinks, foreign or loose files)
+    if fs::symlink_metadata(&auth_file).is_ok() && check_private_file(&auth_file).is_err() {
+        fs::remove_file(&auth_file)?;
+    }
+
     let content = serde_json::to_string_pretty(&state)?;
-    fs::write(&auth_file, content)?;
+    write_private_file(&auth_file, content.as_bytes())?;
 
     Ok(())
 }
@@ -109,22 +200,22 @@ pub fn require_authenticated() -> Result<()> {
 /// (e.g., when a new command process starts after 'auth on')
 pub async fn ensure_master_key_loaded() -> Result<()> {
     use crate::secrets::master_key::{get_cached_master_key, load_master_key_for_session};
-    use dialoguer::Password;
 
     // Try to get master key
     match get_cached_master_key() {
         Ok(_) => Ok(()),
         Err(CcmError::PinRequired) => {
-            // PIN required - prompt for it
-            let pin = Password::new().with_prompt("Enter your PIN").interact()?;
+            // PIN required - from --pin-file / CCM_PIN, else prompt for it
+            let mut pin = read_pin("Enter your PIN")?;
 
             // Verify PIN
-            if !pin::verify_pin(&pin)? {
-                return Err(CcmError::InvalidPin);
-            }
-
-            // Load master key with PIN
-            load_master_key_for_session(Some(&pin)).await
+            let result = match pin::verify_pin(&pin) {
+                // Load master key with PIN
+                Ok(()) => load_master_key_for_session(Some(&pin)).await,
+                Err(e) => Err(e),
+            };
+            pin.zeroize();
+            result
         }
         Err(e) => Err(e),
     }
@@ -2,6 +2,7 @@
 // Compatible with TypeScript version's config keys
 
 use crate::db::get_database;
+use crate::utils::timings::{self, Phase};
 use crate::utils::{CcmError, Result};
 use keyring;
 
@@ -11,15 +12,61 @@ const PIN_HASH_KEY: &str = "pinHash";
 const PIN_SALT_KEY: &str = "pinSalt";
 /// Keychain entry name for PIN set flag
 const PIN_SET_FLAG: &str = "ccm-pin-set";
+/// Internal setting: consecutive failed PIN attempts
+const PIN_FAILURES_KEY: &str = "__pin_failures__";
+
+/// Wrong PINs allowed before each further attempt has to wait
+pub const FREE_ATTEMPTS: u32 = 5;
+/// Wait after the first attempt beyond `FREE_ATTEMPTS`; doubles each time
+const BASE_DELAY_SECS: i64 = 30;
+/// Longest wait between attempts
+const MAX_DELAY_SECS: i64 = 3600;
+
+/// Consecutive failed attempts and when the last one happened
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+struct PinFailures {
+    count: u32,
+    #[serde(rename = "lastFailure")]
+    last_failure: Option<String>,
+}
+
+/// How long to wait after `failures` consecutive wrong PINs (None: no wait)
+fn lockout_delay(failures: u32) -> Option<chrono::Duration> {
+    let over = failures.checked_sub(FREE_ATTEMPTS)?;
+    let secs = BASE_DELAY_SECS
+        .saturating_mul(1i64 << over.min(20))
+        .min(MAX_DELAY_SECS);
+    Some(chrono::Duration::seconds(secs))
+}
+
+/// Time left before another attempt is allowed
+fn time_left(
+    failures: &PinFailures,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<chrono::Duration> {
+    let delay = lockout_delay(failures.count)?;
+    let last = chrono::DateTime::parse_from_rfc3339(failures.last_failure.as_deref()?).ok()?;
+    let left = last.with_timezone(&chrono::Utc) + delay - now;
+    (left > chrono::Duration::zero()).then_some(left)
+}
+
+fn format_wait(wait: chrono::Duration) -> String {
+    let secs = wait.num_seconds().max(1);
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
 
 /// Check if a PIN is set
-/// Uses OS keychain to avoid circular dependency with Database::new()
+/// Uses OS keychain to avoid circular dependency with db::get_database()
 /// (get_cached_master_key needs has_pin, has_pin needs Database -> circular!)
 pub fn has_pin() -> Result<bool> {
     // Check OS keychain directly - no database access needed
     let entry = keyring::Entry::new("ccm", PIN_SET_FLAG)?;
 
-    match entry.get_password() {
+    match timings::measure(Phase::Keyring, || entry.get_password()) {
         Ok(_) => Ok(true),   // Flag exists = PIN is set
         Err(keyring::Error::NoEntry) => Ok(false),  // No flag = no PIN
         Err(e) => Err(CcmError::Unknown(format!("Failed to check PIN status: {}", e))),
@@ -29,17 +76,15 @@ pub fn has_pin() -> Result<bool> {
 /// Set the PIN set flag in keychain (called when PIN is created)
 fn set_pin_flag() -> Result<()> {
     let entry = keyring::Entry::new("ccm", PIN_SET_FLAG)?;
-    entry
-        .set_password("1")
+    timings::measure(Phase::Keyring, || entry.set_password("1"))
         .map_err(|e| CcmError::Unknown(format!("Failed to set PIN flag: {}", e)))?;
     Ok(())
 }
 
 /// Clear the PIN set flag from keychain (called when PIN is removed)
-fn clear_pin_flag() -> Result<()> {
+pub(crate) fn clear_pin_flag() -> Result<()> {
     let entry = keyring::Entry::new("ccm", PIN_SET_FLAG)?;
-    entry
-        .delete_password()
+    timings::measure(Phase::Keyring, || entry.delete_password())
         .map_err(|e| CcmError::Unknown(format!("Failed to clear PIN flag: {}", e)))?;
     Ok(())
 }
@@ -74,7 +119,9 @@ pub fn set_pin(pin: &str) -> Result<()> {
     use pbkdf2::pbkdf2_hmac;
     use sha2::Sha256;
     let mut pin_hash = [0u8; 32];
-    pbkdf2_hmac::<Sha256>(pin.as_bytes(), &salt_bytes, 200_000, &mut pin_hash);
+    timings::measure(Phase::Kdf, || {
+        pbkdf2_hmac::<Sha256>(pin.as_bytes(), &salt_bytes, 200_000, &mut pin_hash)
+    });
     let hash_hex = hex::encode(pin_hash);
 
     // Store both hash and salt
@@ -88,10 +135,19 @@ pub fn set_pin(pin: &str) -> Result<()> {
 }
 
 /// Verify a PIN against the stored hash
-/// Uses PBKDF2 with stored salt to match TypeScript implementation
-pub fn verify_pin(pin: &str) -> Result<bool> {
+/// Uses PBKDF2 with stored salt to match TypeScript implementation.
+/// After `FREE_ATTEMPTS` wrong PINs in a row each attempt has to wait
+/// (30s, doubling up to an hour); a correct PIN resets the count
+pub fn verify_pin(pin: &str) -> Result<()> {
     let db = get_database()?;
 
+    let failures = db
+        .get_setting::<PinFailures>(PIN_FAILURES_KEY)?
+        .unwrap_or_default();
+    if let Some(left) = time_left(&failures, chrono::Utc::now()) {
+        return Err(CcmError::PinLocked(format_wait(left)));
+    }
+
     let stored_hash_hex = db
         .get_setting::<String>(PIN_HASH_KEY)?
         .ok_or_else(|| CcmError::PinRequired)?;
@@ -110,19 +166,40 @@ pub fn verify_pin(pin: &str) -> Result<bool> {
     use pbkdf2::pbkdf2_hmac;
     use sha2::Sha256;
     let mut provided_hash = [0u8; 32];
-    pbkdf2_hmac::<Sha256>(pin.as_bytes(), &salt_bytes, 200_000, &mut provided_hash);
+    timings::measure(Phase::Kdf, || {
+        pbkdf2_hmac::<Sha256>(pin.as_bytes(), &salt_bytes, 200_000, &mut provided_hash)
+    });
 
     // Timing-safe comparison
-    Ok(stored_hash == provided_hash.to_vec())
+    if stored_hash == provided_hash.to_vec() {
+        if failures.count > 0 {
+            db.delete_setting(PIN_FAILURES_KEY)?;
+        }
+        return Ok(());
+    }
+
+    let failures = PinFailures {
+        count: failures.count + 1,
+        last_failure: Some(chrono::Utc::now().to_rfc3339()),
+    };
+    db.save_setting(PIN_FAILURES_KEY, &failures)?;
+
+    Err(CcmError::InvalidPin(Some(
+        match lockout_delay(failures.count) {
+            None => format!(
+                "{} attempt(s) left before a delay",
+                FREE_ATTEMPTS - failures.count
+            ),
+            Some(delay) => format!("next attempt allowed in {}", format_wait(delay)),
+        },
+    )))
 }
 
 /// Change the PIN
 /// Generates new salt and re-hashes with PBKDF2
 pub fn change_pin(old_pin: &str, new_pin: &str) -> Result<()> {
     // Verify old PIN first
-    if !verify_pin(old_pin)? {
-        return Err(CcmError::InvalidPin);
-    }
+    verify_pin(old_pin)?;
 
     if new_pin.is_empty() {
         return Err(CcmError::InvalidArgument("PIN cannot be empty".to_string()));
@@ -146,7 +223,9 @@ pub fn change_pin(old_pin: &str, new_pin: &str) -> Result<()> {
     use pbkdf2::pbkdf2_hmac;
     use sha2::Sha256;
     let mut pin_hash = [0u8; 32];
-    pbkdf2_hmac::<Sha256>(new_pin.as_bytes(), &salt_bytes, 200_000, &mut pin_hash);
+    timings::measure(Phase::Kdf, || {
+        pbkdf2_hmac::<Sha256>(new_pin.as_bytes(), &salt_bytes, 200_000, &mut pin_hash)
+    });
     let hash_hex = hex::encode(pin_hash);
 
     // Update stored hash and salt
@@ -159,9 +238,7 @@ pub fn change_pin(old_pin: &str, new_pin: &str) -> Result<()> {
 /// Remove the PIN (allows resetting to no PIN mode)
 pub fn remove_pin(pin: &str) -> Result<()> {
     // Verify PIN first
-    if !verify_pin(pin)? {
-        return Err(CcmError::InvalidPin);
-    }
+    verify_pin(pin)?;
 
     let db = get_database()?;
     db.delete_setting(PIN_HASH_KEY)?;
@@ -205,18 +282,20 @@ pub fn get_pin_salt_bytes() -> Result<Option<Vec<u8>>> {
 
 /// Derive a 32-byte key from PIN using PBKDF2-SHA256
 /// This is used for encrypting/decrypting the master key
-pub fn derive_key_from_pin(pin: &str, salt: &[u8]) -> [u8; 32] {
+pub fn derive_key_from_pin(pin: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
     use pbkdf2::pbkdf2_hmac;
     use sha2::Sha256;
 
     let mut derived_key = [0u8; 32];
-    pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt, 200_000, &mut derived_key);
+    timings::measure(Phase::Kdf, || {
+        pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt, iterations, &mut derived_key)
+    });
     derived_key
 }
 
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
     use pbkdf2::pbkdf2_hmac;
     use sha2::Sha256;
 
@@ -233,6 +312,27 @@ mod tests {
         assert_eq!(hash1, hash2); // Same input produces same hash
     }
 
+    #[test]
+    fn test_lockout_delay() {
+        assert!(lockout_delay(0).is_none());
+        assert!(lockout_delay(FREE_ATTEMPTS - 1).is_none());
+        assert_eq!(lockout_delay(FREE_ATTEMPTS).unwrap().num_seconds(), 30);
+        assert_eq!(lockout_delay(FREE_ATTEMPTS + 2).unwrap().num_seconds(), 120);
+        assert_eq!(
+            lockout_delay(FREE_ATTEMPTS + 40).unwrap().num_seconds(),
+            3600
+        );
+
+        let now = chrono::Utc::now();
+        let failures = PinFailures {
+            count: FREE_ATTEMPTS + 1,
+            last_failure: Some((now - chrono::Duration::seconds(20)).to_rfc3339()),
+        };
+        assert_eq!(time_left(&failures, now).unwrap().num_seconds(), 40);
+        assert!(time_left(&failures, now + chrono::Duration::seconds(60)).is_none());
+        assert_eq!(format_wait(chrono::Duration::seconds(90)), "1m 30s");
+    }
+
     #[test]
     fn test_pin_validation() {
         // These are basic validation tests - need to test against the function logic
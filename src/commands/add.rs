@@ -1,42 +1,67 @@
 // Add command implementation
 
+use crate::presets::{self, Preset};
 use crate::types::Entry;
-use crate::utils::{validate_name, CcmError, Result};
-use crate::Commands;
+use crate::utils::clipboard::copy_to_clipboard;
+use crate::utils::input::{read_secret_file, resolve_secret};
+use crate::utils::password_gen::{self, GenerateSpec, PasswordOptions};
+use crate::utils::{output, CcmError, Result};
+use crate::{Commands, GenerateArgs};
 use colored::Colorize;
+use dialoguer::Input;
 use std::collections::HashMap;
+use std::io::{self, IsTerminal};
+use std::path::Path;
 
 pub async fn execute(command: Commands) -> Result<()> {
     if let Commands::Add {
         name,
         secret,
         secret_flag,
+        secret_file,
         env,
         tags,
         notes,
+        generate,
+        preset,
     } = command
     {
         // Ensure master key is loaded (prompts for PIN if needed)
         crate::auth::ensure_master_key_loaded().await?;
-        do_add(&name, secret, secret_flag, env, tags, notes).await
+        let source = SecretSource {
+            value: secret_flag.or(secret),
+            file: secret_file.as_deref(),
+        };
+        let preset = preset.as_deref().map(presets::get_preset).transpose()?;
+        do_add(&name, source, &generate, env, preset.as_ref(), tags, notes).await
     } else {
         unreachable!()
     }
 }
 
+/// Where the secret of `add` comes from, unless it is generated
+struct SecretSource<'a> {
+    value: Option<String>,
+    file: Option<&'a Path>,
+}
+
 async fn do_add(
     name: &str,
-    secret: Option<String>,
-    secret_flag: Option<String>,
+    source: SecretSource<'_>,
+    generate: &GenerateArgs,
     env_args: Vec<String>,
+    preset: Option<&Preset>,
     tags: Option<String>,
     notes: Option<String>,
 ) -> Result<()> {
-    // Validate name
-    validate_name(name)?;
-
-    // Determine secret value (priority: --secret > positional)
-    let secret_value = secret_flag.or(secret);
+    // Normalize and validate name against the configured policy
+    let policy = crate::db::get_database()?.get_name_policy()?;
+    let normalized = policy.normalize(name);
+    policy.validate(&normalized)?;
+    if normalized != name {
+        println!("{} Name normalized: {} → {}", "ℹ️".blue(), name, normalized);
+    }
+    let name = normalized.as_str();
 
     // Build metadata from --env arguments
     let mut metadata = HashMap::new();
@@ -46,27 +71,38 @@ async fn do_add(
         metadata.insert(var_name, value);
     }
 
-    // If no env vars specified and we have a secret, add default mapping
-    if metadata.is_empty() && secret_value.is_some() {
+    if let Some(preset) = preset {
+        preset.fill_metadata(&mut metadata, |var| ask_preset_field(preset, var))?;
+    }
+
+    // If no env vars specified, add default mapping
+    if metadata.is_empty() {
         // Use a default environment variable name based on the entry name
-        let default_var_name = name.to_uppercase().replace('-', "_");
+        let default_var_name: String = name
+            .to_uppercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
         metadata.insert(default_var_name, "SECRET".to_string());
     }
 
-    // Validate that at least one env var has SECRET placeholder if we have a secret value
+    // Validate that at least one env var has SECRET placeholder
     let has_secret_placeholder = metadata.values().any(|v| v == "SECRET");
 
-    if !has_secret_placeholder && secret_value.is_some() {
+    if !has_secret_placeholder {
         return Err(CcmError::InvalidArgument(
             "No environment variable mapping has SECRET value. Use --env VAR=SECRET to indicate which variable should contain the secret.".to_string()
         ));
     }
 
-    if has_secret_placeholder && secret_value.is_none() {
-        return Err(CcmError::InvalidArgument(
-            "SECRET placeholder found but no secret value provided. Provide secret via positional argument or --secret flag.".to_string()
-        ));
-    }
+    // Determine secret value (priority: --generate > --secret-file > --secret > positional)
+    // Prompts with hidden input if omitted, reads stdin for "-"
+    let generated = generate_secret(generate)?;
+    let secret_value = match (&generated, source.file) {
+        (Some(value), _) => value.clone(),
+        (None, Some(path)) => read_secret_file(path)?,
+        (None, None) => resolve_secret(source.value)?,
+    };
 
     // Create entry
     let mut entry = Entry::new(name.to_string(), metadata);
@@ -80,19 +116,67 @@ async fn do_add(
     // Add notes
     entry.notes = notes;
 
-    // Get secret value for encryption
-    let secret_for_encryption = secret_value.ok_or_else(|| {
-        CcmError::InvalidArgument("Secret value is required".to_string())
-    })?;
-
     // Save entry (encrypts the secret)
-    crate::secrets::add_entry(name, entry, &secret_for_encryption)?;
+    crate::secrets::add_entry(name, entry, &secret_value)?;
 
-    println!("{} Added entry: {}", "✅".green(), name.cyan().bold());
+    output::success(format!("Added entry: {}", name.cyan().bold()));
+
+    if let Some(value) = generated {
+        show_generated_secret(&value, generate.copy);
+    }
 
     Ok(())
 }
 
+/// Ask for a required preset field that --env did not give
+fn ask_preset_field(preset: &Preset, var: &str) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        return Err(CcmError::InvalidArgument(format!(
+            "The {} preset needs {}; pass --env {}=VALUE",
+            preset.name, var, var
+        )));
+    }
+    Ok(Input::new().with_prompt(var).interact_text()?)
+}
+
+/// Generate a random secret if --generate was given
+pub(crate) fn generate_secret(args: &GenerateArgs) -> Result<Option<String>> {
+    if !args.generate {
+        return Ok(None);
+    }
+
+    let spec = match args.words {
+        Some(words) => GenerateSpec::Passphrase {
+            words,
+            separator: "-".to_string(),
+        },
+        None => GenerateSpec::Password(PasswordOptions {
+            length: args.length,
+            lowercase: true,
+            uppercase: !args.no_uppercase,
+            digits: !args.no_digits,
+            symbols: args.symbols,
+        }),
+    };
+
+    password_gen::generate(&spec).map(Some)
+}
+
+/// Print or copy a freshly generated secret
+pub(crate) fn show_generated_secret(secret: &str, copy: bool) {
+    if copy {
+        if copy_to_clipboard(secret) {
+            output::success("Generated secret copied to clipboard");
+            return;
+        }
+        println!(
+            "{} Failed to copy to clipboard. Displaying instead:",
+            "⚠️".yellow()
+        );
+    }
+    println!("Generated secret: {}", secret);
+}
+
 /// Parse KEY=VALUE format
 fn parse_key_value(s: &str) -> Result<(String, String)> {
     let parts: Vec<&str> = s.splitn(2, '=').collect();
@@ -102,5 +186,12 @@ fn parse_key_value(s: &str) -> Result<(String, String)> {
             s
         )));
     }
-    Ok((parts[0].trim().to_string(), parts[1].trim().to_string()))
+    let key = parts[0].trim();
+    if !crate::presets::file::is_var_name(key) {
+        return Err(CcmError::InvalidArgument(format!(
+            "'{}' is not a valid environment variable name (letters, digits and _)",
+            key
+        )));
+    }
+    Ok((key.to_string(), parts[1].trim().to_string()))
 }
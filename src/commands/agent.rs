@@ -0,0 +1,156 @@
+// Agent command implementation (start, stop and status of `ccm agent`)
+
+use crate::agent;
+use crate::secrets::master_key;
+use crate::utils::{output, CcmError, Result};
+use crate::{AgentAction, Commands};
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Agent { action } = command {
+        match action {
+            AgentAction::Start { foreground } => start(foreground).await,
+            AgentAction::Stop => stop(),
+            AgentAction::Status => status(),
+            AgentAction::Serve => serve(),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+/// The running agent's PID, if one answers
+fn running_pid() -> Option<String> {
+    let reply = agent::request("PING").ok()?;
+    reply.trim().strip_prefix("OK ").map(str::to_string)
+}
+
+#[cfg(unix)]
+async fn start(foreground: bool) -> Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    use zeroize::Zeroize;
+
+    if let Some(pid) = running_pid() {
+        println!("{} Agent already running (pid {})", "ℹ️".blue(), pid);
+        return Ok(());
+    }
+
+    // Ensure master key is loaded (prompts for PIN if needed)
+    crate::auth::ensure_master_key_loaded().await?;
+    let path = agent::socket_path()?;
+
+    if foreground {
+        let listener = agent::bind(&path)?;
+        output::success(format!("Agent listening on {}", path.display()));
+        let result = agent::serve(listener, master_key::master_key_cache());
+        let _ = std::fs::remove_file(&path);
+        master_key::master_key_cache().clear();
+        return result;
+    }
+
+    let mut child = Command::new(std::env::current_exe()?)
+        .env_remove(crate::auth::PIN_ENV)
+        .args(["agent", "serve"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Hand the key over on stdin, never on the command line
+    let mut line = format!(
+        "{} {}\n",
+        hex::encode(master_key::get_cached_master_key()?),
+        master_key::get_instance_id()?
+    );
+    let written = child
+        .stdin
+        .take()
+        .ok_or_else(|| CcmError::Unknown("Agent stdin unavailable".to_string()))
+        .and_then(|mut stdin| Ok(stdin.write_all(line.as_bytes())?));
+    line.zeroize();
+    written?;
+
+    // Wait for the socket to answer
+    for _ in 0..30 {
+        if let Some(pid) = running_pid() {
+            output::success(format!("Agent started (pid {})", pid));
+            println!("   Socket: {}", path.display());
+            return Ok(());
+        }
+        if let Some(status) = child.try_wait()? {
+            return Err(CcmError::Unknown(format!("Agent exited ({})", status)));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    Err(CcmError::Unknown("Agent did not start in time".to_string()))
+}
+
+#[cfg(not(unix))]
+async fn start(_foreground: bool) -> Result<()> {
+    agent::request("PING").map(|_| ())
+}
+
+/// Body of the detached agent process: read the key from stdin and serve it
+#[cfg(unix)]
+fn serve() -> Result<()> {
+    use std::io::BufRead;
+    use zeroize::Zeroize;
+
+    // Leave the terminal's session so closing it does not stop the agent
+    // SAFETY: setsid has no preconditions; failure only means we stay attached
+    unsafe {
+        libc::setsid();
+    }
+
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    let parsed = {
+        let mut parts = line.split_whitespace();
+        let key = parts
+            .next()
+            .and_then(|k| hex::decode(k).ok())
+            .and_then(|k| <[u8; 32]>::try_from(k).ok());
+        key.zip(parts.next().map(str::to_string))
+    };
+    line.zeroize();
+    let (mut key, instance_id) =
+        parsed.ok_or_else(|| CcmError::InvalidArgument("Expected a key on stdin".to_string()))?;
+    master_key::master_key_cache().set(key, &instance_id);
+    key.zeroize();
+
+    let path = agent::socket_path()?;
+    let listener = agent::bind(&path)?;
+    let result = agent::serve(listener, master_key::master_key_cache());
+    let _ = std::fs::remove_file(&path);
+    master_key::master_key_cache().clear();
+    result
+}
+
+#[cfg(not(unix))]
+fn serve() -> Result<()> {
+    agent::request("PING").map(|_| ())
+}
+
+fn stop() -> Result<()> {
+    if running_pid().is_none() {
+        println!("{} No agent running", "ℹ️".blue());
+        return Ok(());
+    }
+    agent::request("STOP")?;
+    output::success("Agent stopped; the key has been zeroized");
+    Ok(())
+}
+
+fn status() -> Result<()> {
+    match running_pid() {
+        Some(pid) => println!(
+            "{} Agent running (pid {}) on {}",
+            "✅".green(),
+            pid,
+            agent::socket_path()?.display()
+        ),
+        None => println!("{} No agent running", "ℹ️".blue()),
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+// Allow command implementation
+//
+// `ccm allow` trusts the nearest .ccm.toml as it is now, so `ccm run`,
+// `npm-run` and `cargo` may inject the entries it names; `--revoke` takes
+// that back.
+
+use crate::env::project::{self, PROJECT_FILE};
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Allow { path, revoke } = command {
+        let file = project_file(path.as_deref())?;
+        let allowed = project::allowed_path();
+        if revoke {
+            if project::revoke(&allowed, &file)? {
+                output::success(format!("Revoked {}", file.display()));
+            } else {
+                println!("{} {} was not allowed", "ℹ️".blue(), file.display());
+            }
+        } else {
+            let file = project::allow(&allowed, &file)?;
+            output::success(format!("Allowed {}", file.display()));
+            println!("   Changing it needs another 'ccm allow'.");
+        }
+        Ok(())
+    } else {
+        unreachable!()
+    }
+}
+
+/// The .ccm.toml given, the one in a given directory or the nearest one
+fn project_file(path: Option<&str>) -> Result<PathBuf> {
+    let start = match path {
+        Some(path) if !Path::new(path).is_dir() => return Ok(PathBuf::from(path)),
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir()?,
+    };
+    project::find_project_file(&start).ok_or_else(|| {
+        CcmError::InvalidArgument(format!(
+            "No {} in {} or above",
+            PROJECT_FILE,
+            start.display()
+        ))
+    })
+}
@@ -0,0 +1,59 @@
+// Audit command implementation
+
+use crate::audit;
+use crate::db;
+use crate::utils::files::write_private_file;
+use crate::utils::Result;
+use crate::{AuditAction, Commands};
+use colored::Colorize;
+use std::path::Path;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Audit { action } = command {
+        crate::auth::ensure_master_key_loaded().await?;
+
+        match action {
+            AuditAction::Export {
+                format,
+                since,
+                output,
+            } => export_events(&format, since.as_deref(), output.as_deref()),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+/// Write audit events as JSON lines or CEF, to a file or stdout
+fn export_events(format: &str, since: Option<&str>, output: Option<&str>) -> Result<()> {
+    let since = since
+        .map(|s| audit::parse_since(s, chrono::Utc::now()).map(audit::format_timestamp))
+        .transpose()?;
+
+    let events = db::get_database()?.get_audit_events(since.as_deref())?;
+
+    let mut content = String::new();
+    for event in &events {
+        let line = match format {
+            "cef" => audit::to_cef(event),
+            _ => audit::to_jsonl(event)?,
+        };
+        content.push_str(&line);
+        content.push('\n');
+    }
+
+    match output {
+        Some(path) => {
+            write_private_file(Path::new(path), content.as_bytes())?;
+            eprintln!(
+                "{} Exported {} audit event(s) to: {}",
+                "✅".green(),
+                events.len(),
+                path
+            );
+        }
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
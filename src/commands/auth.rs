@@ -1,22 +1,57 @@
 // Auth command implementation
 
-use crate::auth::pin;
+use crate::audit;
 use crate::auth::{self, clear_authentication, set_authenticated};
+use crate::auth::{kdf, pin};
+use crate::commands::export::{decrypt_data, encrypt_data};
 use crate::secrets::master_key;
-use crate::utils::Result;
+use crate::utils::files::write_private_file;
+use crate::utils::paper_key;
+use crate::utils::{output, CcmError, Result};
 use crate::Commands;
 use colored::Colorize;
 use dialoguer::Password;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Format tag of master key escrow files
+const ESCROW_FORMAT: &str = "ccm-master-key-v1";
+
+/// Phrase the user must type to confirm exporting the master key
+const EXPORT_CONFIRMATION: &str = "export master key";
+
+/// Minimum length of the escrow passphrase
+const MIN_ESCROW_PASSPHRASE_LEN: usize = 12;
+
+/// Master key escrow file
+#[derive(Debug, Serialize, Deserialize)]
+struct MasterKeyEscrow {
+    format: String,
+    #[serde(rename = "instanceId")]
+    instance_id: String,
+    #[serde(rename = "exportedAt")]
+    exported_at: String,
+    algorithm: String,
+    /// Hex-encoded master key, encrypted with the escrow passphrase
+    data: String,
+}
 
 pub async fn execute(command: Commands) -> Result<()> {
-    if let Commands::Auth { action, pin } = command {
-        do_auth(&action, pin.as_deref()).await
+    if let Commands::Auth {
+        action,
+        pin,
+        file,
+        format,
+    } = command
+    {
+        do_auth(&action, pin.as_deref(), file.as_deref(), &format).await
     } else {
         unreachable!()
     }
 }
 
-async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
+async fn do_auth(action: &str, pin: Option<&str>, file: Option<&str>, format: &str) -> Result<()> {
     match action.to_lowercase().as_str() {
         "on" | "login" => {
             // Check if already authenticated
@@ -37,9 +72,7 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
                 };
 
                 // Verify PIN
-                if !pin::verify_pin(&entered_pin)? {
-                    return Err(crate::utils::CcmError::InvalidPin);
-                }
+                pin::verify_pin(&entered_pin)?;
 
                 // Load master key with PIN
                 master_key::load_master_key_for_session(Some(&entered_pin)).await?;
@@ -52,49 +85,17 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
             // Set authenticated
             set_authenticated(true)?;
 
-            println!("{} Authenticated successfully", "✅".green());
+            output::success("Authenticated successfully");
         }
         "off" | "logout" => {
             clear_authentication()?;
-            println!("{} Logged out successfully", "✅".green());
-        }
-        "set" => {
-            // Set new PIN
-            if pin::has_pin()? {
-                return Err(crate::utils::CcmError::InvalidArgument(
-                    "PIN is already set. Use 'ccm auth change' to change it.".to_string(),
-                ));
+            // A running agent would hand the key out again
+            if crate::agent::request("STOP").is_ok() {
+                output::success("Agent stopped");
             }
-
-            let new_pin = if let Some(p) = pin {
-                p.to_string()
-            } else {
-                Password::new()
-                    .with_prompt("Enter new PIN (min 4 characters)")
-                    .with_confirmation("Confirm PIN", "PINs do not match")
-                    .interact()?
-            };
-
-            // First, ensure master key is loaded (with ZERO_KEY since no PIN yet)
-            master_key::load_master_key_for_session(None).await?;
-
-            // Set PIN in database (this generates and stores the salt)
-            pin::set_pin(&new_pin)?;
-
-            // Get the salt that was just created
-            let salt = pin::get_pin_salt()?.ok_or_else(|| {
-                crate::utils::CcmError::Unknown("Failed to get PIN salt".to_string())
-            })?;
-
-            // Re-encrypt master key with PIN-derived key
-            master_key::reencrypt_master_key(None, Some(&new_pin), Some(&salt))?;
-
-            println!("{} PIN set successfully", "✅".green());
-            println!(
-                "{} Master key has been re-encrypted with your PIN.",
-                "🔐".blue()
-            );
+            output::success("Logged out successfully");
         }
+        "set" => set_new_pin(pin).await?,
         "change" => {
             // Change existing PIN
             if !pin::has_pin()? {
@@ -108,9 +109,7 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
                 .interact()?;
 
             // Verify old PIN first
-            if !pin::verify_pin(&old_pin)? {
-                return Err(crate::utils::CcmError::InvalidPin);
-            }
+            pin::verify_pin(&old_pin)?;
 
             let new_pin = if let Some(p) = pin {
                 p.to_string()
@@ -136,7 +135,7 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
             // Note: We use old_pin for decryption since the master key is still encrypted with old PIN's derived key
             master_key::reencrypt_master_key(Some(&old_pin), Some(&new_pin), Some(&new_salt))?;
 
-            println!("{} PIN changed successfully", "✅".green());
+            output::success("PIN changed successfully");
             println!(
                 "{} Master key has been re-encrypted with your new PIN.",
                 "🔐".blue()
@@ -154,9 +153,7 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
                 .interact()?;
 
             // Verify PIN
-            if !pin::verify_pin(&current_pin)? {
-                return Err(crate::utils::CcmError::InvalidPin);
-            }
+            pin::verify_pin(&current_pin)?;
 
             // Load master key with current PIN
             master_key::load_master_key_for_session(Some(&current_pin)).await?;
@@ -167,7 +164,7 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
             // Remove PIN from database
             pin::remove_pin(&current_pin)?;
 
-            println!("{} PIN removed successfully", "✅".green());
+            output::success("PIN removed successfully");
             println!(
                 "{} Master key is now protected by ZERO_KEY (less secure).",
                 "⚠️".yellow()
@@ -201,9 +198,25 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
                 println!("   Consider enabling password verification: ccm auth on");
             }
         }
+        "calibrate" => calibrate(pin).await?,
+        "export-master-key" => {
+            crate::auth::ensure_master_key_loaded().await?;
+            export_master_key(file)?;
+        }
+        "import-master-key" => {
+            let path = file.ok_or_else(|| {
+                CcmError::InvalidArgument(
+                    "Specify the escrow file: ccm auth import-master-key --file <FILE>".to_string(),
+                )
+            })?;
+            import_master_key(path)?;
+        }
+        "export-key" => export_paper_key(pin, format).await?,
+        "import-key" => import_paper_key()?,
         _ => {
             return Err(crate::utils::CcmError::InvalidArgument(format!(
-                "Unknown auth action: {}. Use: on, off, set, change, remove, check",
+                "Unknown auth action: {}. Use: on, off, set, change, remove, check, \
+                 calibrate, export-master-key, import-master-key, export-key, import-key",
                 action
             )));
         }
@@ -211,3 +224,315 @@ async fn do_auth(action: &str, pin: Option<&str>) -> Result<()> {
 
     Ok(())
 }
+
+/// Set the first PIN and re-wrap the master key with it (`ccm auth set`,
+/// `ccm init`)
+pub(crate) async fn set_new_pin(pin: Option<&str>) -> Result<()> {
+    if pin::has_pin()? {
+        return Err(crate::utils::CcmError::InvalidArgument(
+            "PIN is already set. Use 'ccm auth change' to change it.".to_string(),
+        ));
+    }
+
+    let new_pin = if let Some(p) = pin {
+        p.to_string()
+    } else {
+        Password::new()
+            .with_prompt("Enter new PIN (min 4 characters)")
+            .with_confirmation("Confirm PIN", "PINs do not match")
+            .interact()?
+    };
+
+    // First, ensure master key is loaded (with ZERO_KEY since no PIN yet)
+    master_key::load_master_key_for_session(None).await?;
+
+    // Set PIN in database (this generates and stores the salt)
+    pin::set_pin(&new_pin)?;
+
+    // Get the salt that was just created
+    let salt = pin::get_pin_salt()?
+        .ok_or_else(|| crate::utils::CcmError::Unknown("Failed to get PIN salt".to_string()))?;
+
+    // Re-encrypt master key with PIN-derived key
+    master_key::reencrypt_master_key(None, Some(&new_pin), Some(&salt))?;
+
+    output::success("PIN set successfully");
+    println!(
+        "{} Master key has been re-encrypted with your PIN.",
+        "🔐".blue()
+    );
+    Ok(())
+}
+
+/// Time PBKDF2 on this machine, store the iteration count that takes about
+/// 250ms in `kdf.iterations` and re-wrap the master key with it
+async fn calibrate(pin: Option<&str>) -> Result<()> {
+    let has_pin = pin::has_pin()?;
+    let current_pin = if has_pin {
+        let entered = match pin {
+            Some(p) => p.to_string(),
+            None => auth::read_pin("Enter your PIN")?,
+        };
+        pin::verify_pin(&entered)?;
+        master_key::load_master_key_for_session(Some(&entered)).await?;
+        Some(entered)
+    } else {
+        crate::auth::ensure_master_key_loaded().await?;
+        None
+    };
+
+    println!("⏱️  Measuring PBKDF2-SHA256 on this machine...");
+    let iterations = kdf::calibrate(kdf::CALIBRATION_TARGET);
+    crate::db::get_database()?.save_setting(kdf::KDF_ITERATIONS_KEY, &iterations.to_string())?;
+    output::success(format!(
+        "{} = {} (about {} ms to unlock)",
+        kdf::KDF_ITERATIONS_KEY.bold(),
+        iterations,
+        kdf::CALIBRATION_TARGET.as_millis()
+    ));
+
+    match current_pin {
+        Some(current_pin) => {
+            let salt = pin::get_pin_salt()?.ok_or(CcmError::PinRequired)?;
+            master_key::reencrypt_master_key(Some(&current_pin), Some(&current_pin), Some(&salt))?;
+            println!(
+                "{} Master key has been re-wrapped with the new parameters.",
+                "🔐".blue()
+            );
+        }
+        None => println!(
+            "{} No PIN is set; the new parameters apply once you run ccm auth set.",
+            "ℹ️".blue()
+        ),
+    }
+    Ok(())
+}
+
+/// Prompt for a new escrow passphrase (with confirmation and minimum length)
+pub(crate) fn prompt_new_escrow_passphrase() -> Result<String> {
+    let passphrase = Password::new()
+        .with_prompt(format!(
+            "Escrow passphrase (min {} characters)",
+            MIN_ESCROW_PASSPHRASE_LEN
+        ))
+        .with_confirmation("Confirm passphrase", "Passphrases do not match")
+        .interact()?;
+    if passphrase.chars().count() < MIN_ESCROW_PASSPHRASE_LEN {
+        return Err(CcmError::InvalidArgument(format!(
+            "Escrow passphrase must be at least {} characters",
+            MIN_ESCROW_PASSPHRASE_LEN
+        )));
+    }
+    Ok(passphrase)
+}
+
+/// Encrypt the master key under a passphrase
+pub(crate) fn wrap_master_key(master_key: &[u8; 32], passphrase: &str) -> Result<String> {
+    encrypt_data(&hex::encode(master_key), passphrase)
+}
+
+/// Decrypt a master key wrapped by wrap_master_key()
+pub(crate) fn unwrap_master_key(data: &str, passphrase: &str) -> Result<[u8; 32]> {
+    let key_hex = decrypt_data(data, passphrase)?;
+    hex::decode(key_hex.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| CcmError::Decryption("File does not contain a valid master key".to_string()))
+}
+
+/// Export the master key wrapped under a passphrase for offline escrow
+fn export_master_key(file: Option<&str>) -> Result<()> {
+    let master_key = master_key::get_cached_master_key()?;
+    let instance_id = master_key::get_instance_id()?;
+
+    println!("{}", "DANGER: Exporting the master key".red().bold());
+    println!();
+    println!("  Anyone holding the exported file and its passphrase can decrypt");
+    println!("  every secret in this vault, including any future backup of it.");
+    println!("  Store the file offline and never next to the passphrase.");
+    println!();
+    print!("Type '{}' to continue: ", EXPORT_CONFIRMATION.bold());
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if input.trim() != EXPORT_CONFIRMATION {
+        println!("Export cancelled.");
+        return Ok(());
+    }
+
+    let passphrase = prompt_new_escrow_passphrase()?;
+
+    let escrow = MasterKeyEscrow {
+        format: ESCROW_FORMAT.to_string(),
+        instance_id: instance_id.clone(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        algorithm: "aes-256-gcm".to_string(),
+        data: wrap_master_key(&master_key, &passphrase)?,
+    };
+
+    let path = match file {
+        Some(f) => PathBuf::from(f),
+        None => PathBuf::from(format!("ccm-master-key-{}.json", instance_id)),
+    };
+    let json = serde_json::to_string_pretty(&escrow)?;
+    write_private_file(&path, json.as_bytes())?;
+    audit::record(&crate::db::get_database()?, audit::MASTER_KEY_EXPORT, None);
+
+    output::success(format!("Master key exported to: {}", path.display()));
+    println!(
+        "{} Move this file to offline storage and delete the local copy.",
+        "⚠️".yellow()
+    );
+
+    Ok(())
+}
+
+/// Restore a master key from an escrow file into the OS keyring
+fn import_master_key(file: &str) -> Result<()> {
+    let content = std::fs::read_to_string(file)?;
+    let escrow: MasterKeyEscrow = serde_json::from_str(&content)
+        .map_err(|e| CcmError::InvalidArgument(format!("Invalid escrow file: {}", e)))?;
+
+    if escrow.format != ESCROW_FORMAT {
+        return Err(CcmError::InvalidArgument(format!(
+            "Unsupported escrow format: {}",
+            escrow.format
+        )));
+    }
+
+    ensure_no_pin()?;
+
+    let passphrase = Password::new()
+        .with_prompt("Escrow passphrase")
+        .interact()?;
+    let master_key = unwrap_master_key(&escrow.data, &passphrase)?;
+
+    if !confirm_replace_master_key()? {
+        println!("Import cancelled.");
+        return Ok(());
+    }
+
+    master_key::store_master_key(&master_key, &escrow.instance_id)?;
+
+    output::success(format!(
+        "Master key restored for instance {}",
+        escrow.instance_id
+    ));
+    println!(
+        "{} The key is protected by ZERO_KEY. Run 'ccm auth set' to add a PIN.",
+        "🔐".blue()
+    );
+
+    Ok(())
+}
+
+/// The keyring entry written on import is wrapped with ZERO_KEY, which
+/// would not match an existing PIN
+fn ensure_no_pin() -> Result<()> {
+    if pin::has_pin()? {
+        return Err(CcmError::InvalidArgument(
+            "A PIN is set on this machine. Remove it first with 'ccm auth remove'.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Ask before overwriting a master key already in the keyring
+fn confirm_replace_master_key() -> Result<bool> {
+    if !master_key::has_master_key()? {
+        return Ok(true);
+    }
+
+    println!(
+        "{} A master key already exists in the keyring. Replacing it makes",
+        "⚠️".yellow()
+    );
+    println!("   secrets encrypted with the current key unreadable.");
+    print!("Replace the existing master key? (y/N): ");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y") || input.trim().eq_ignore_ascii_case("yes"))
+}
+
+/// Print the master key as recovery words or base58, after verifying the PIN
+async fn export_paper_key(pin: Option<&str>, format: &str) -> Result<()> {
+    let master_key = if pin::has_pin()? {
+        let entered = match pin {
+            Some(p) => p.to_string(),
+            None => auth::read_pin("Enter your PIN")?,
+        };
+        pin::verify_pin(&entered)?;
+        master_key::get_cached_master_key_with_pin(&entered)?
+    } else {
+        crate::auth::ensure_master_key_loaded().await?;
+        master_key::get_cached_master_key()?
+    };
+
+    println!("{}", "DANGER: Printing the master key".red().bold());
+    println!();
+    println!("  Anyone who reads this key can decrypt every secret in this vault");
+    println!("  and every backup of it. Write it down and keep it offline.");
+    println!();
+    print!("Type '{}' to continue: ", EXPORT_CONFIRMATION.bold());
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if input.trim() != EXPORT_CONFIRMATION {
+        println!("Export cancelled.");
+        return Ok(());
+    }
+    audit::record(&crate::db::get_database()?, audit::MASTER_KEY_EXPORT, None);
+
+    println!();
+    if format == "base58" {
+        println!("  {}", paper_key::to_base58(&master_key).bold());
+    } else {
+        let words = paper_key::to_mnemonic(&master_key);
+        let words: Vec<&str> = words.split(' ').collect();
+        for (row, chunk) in words.chunks(6).enumerate() {
+            let line: Vec<String> = chunk
+                .iter()
+                .enumerate()
+                .map(|(i, word)| format!("{:>2}. {:<9}", row * 6 + i + 1, word))
+                .collect();
+            println!("  {}", line.join(" ").trim_end());
+        }
+    }
+    println!();
+    println!("Restore on a new machine with: ccm auth import-key");
+
+    Ok(())
+}
+
+/// Restore the master key from recovery words or a base58 paper key
+fn import_paper_key() -> Result<()> {
+    ensure_no_pin()?;
+
+    let input = Password::new()
+        .with_prompt("Recovery words or base58 paper key")
+        .interact()?;
+    let master_key = paper_key::parse(&input)?;
+
+    if !confirm_replace_master_key()? {
+        println!("Import cancelled.");
+        return Ok(());
+    }
+
+    let instance_id = match master_key::get_instance_id_from_config()? {
+        Some(id) => id,
+        None => crate::utils::generate_instance_id(),
+    };
+    master_key::store_master_key(&master_key, &instance_id)?;
+
+    output::success("Master key restored");
+    println!(
+        "{} The key is protected by ZERO_KEY. Run 'ccm auth set' to add a PIN.",
+        "🔐".blue()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+// Backup command implementation
+
+use crate::db::{self, snapshots};
+use crate::utils::{output, Result};
+use crate::{BackupAction, Commands};
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Backup { action } = command {
+        match action {
+            BackupAction::Now => {
+                // Ensure master key is loaded (prompts for PIN if needed)
+                crate::auth::ensure_master_key_loaded().await?;
+                now()
+            }
+            BackupAction::List => list(),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+fn now() -> Result<()> {
+    let db = db::get_database()?;
+    let snapshot = snapshots::snapshot_now(&db)?;
+
+    output::success(format!("Snapshot written to {}", snapshot.path.display()));
+    println!(
+        "   Keeping the newest {} (ccm config {} <N>)",
+        snapshots::keep_count(&db)?,
+        snapshots::BACKUP_KEEP_KEY
+    );
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let dir = snapshots::backups_dir();
+    let snapshots = snapshots::list_snapshots(&dir)?;
+
+    if snapshots.is_empty() {
+        println!("No snapshots in {}", dir.display());
+        println!(
+            "Take one with {} or enable {}",
+            "ccm backup now".yellow(),
+            format!("ccm config {} daily", snapshots::AUTO_BACKUP_KEY).yellow()
+        );
+        return Ok(());
+    }
+
+    println!("{} ({})", "Snapshots:".bold(), dir.display());
+    for snapshot in &snapshots {
+        let name = snapshot
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        println!(
+            "  {}  {:>8} KB  {}",
+            snapshot
+                .created_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            snapshot.size.div_ceil(1024),
+            name.dimmed()
+        );
+    }
+    println!();
+    println!(
+        "Snapshots are copies of the encrypted database; they open with this vault's master key."
+    );
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+// Claude command implementation
+//
+// `ccm claude use <entry>` switches Claude Code to an entry by rewriting its
+// settings.json (see `crate::launch::claude`); `ccm claude helper <entry>`
+// prints the entry's key for Claude Code's apiKeyHelper.
+
+use crate::launch::{self, claude};
+use crate::utils::files::{write_file_atomic, write_private_file};
+use crate::utils::{output, CcmError, Result};
+use crate::{ClaudeAction, Commands};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Claude { action } = command {
+        match action {
+            ClaudeAction::Use {
+                name,
+                inline,
+                settings,
+            } => use_entry(&name, inline, settings).await,
+            ClaudeAction::Helper { name } => helper(&name).await,
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+async fn use_entry(name: &str, inline: bool, settings: Option<PathBuf>) -> Result<()> {
+    let (entry, secret) = super::launch::entry_with_secret(name).await?;
+    let env_vars = crate::env::get_env_mappings_with_secret(&entry, &secret);
+    let tool = launch::tool_profile("claude")?;
+    let (env_vars, _) = launch::tool_env(&tool, &entry.metadata, &env_vars);
+
+    let mut env: BTreeMap<String, String> = env_vars.into_iter().collect();
+    let helper = if inline {
+        None
+    } else {
+        env.retain(|var, _| !claude::KEY_VARS.contains(&var.as_str()));
+        Some(helper_command(name))
+    };
+
+    let path = match settings {
+        Some(path) => path,
+        None => claude::settings_path()?,
+    };
+    let mut document = read_settings(&path)?;
+    claude::apply(&mut document, &env, helper.as_deref())?;
+
+    let mut content = serde_json::to_string_pretty(&document)?;
+    content.push('\n');
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if inline {
+        // The key is in the file now
+        write_private_file(&path, content.as_bytes())?;
+    } else {
+        write_file_atomic(&path, content.as_bytes())?;
+    }
+
+    output::success(format!("Claude Code now uses '{}'", name.cyan().bold()));
+    if !output::is_quiet() {
+        println!("   Updated {}", path.display());
+        for var in env.keys().filter(|var| var.starts_with("ANTHROPIC_")) {
+            println!("   env.{}", var);
+        }
+        match &helper {
+            Some(helper) => println!("   apiKeyHelper: {}", helper),
+            None => println!(
+                "   {} The key is stored in plain text in this file",
+                "⚠️".yellow()
+            ),
+        }
+        println!("   Restart Claude Code to pick up the change.");
+    }
+    Ok(())
+}
+
+/// Print the key only, for apiKeyHelper (never prompts without a terminal:
+/// use quick access, `ccm agent` or CCM_PIN)
+async fn helper(name: &str) -> Result<()> {
+    let (_, secret) = super::launch::entry_with_secret(name).await?;
+    println!("{}", secret);
+    Ok(())
+}
+
+/// The apiKeyHelper command for an entry (this executable, by full path)
+fn helper_command(name: &str) -> String {
+    let program = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.to_str().map(claude::shell_quote))
+        .unwrap_or_else(|| "ccm".to_string());
+    format!("{} claude helper {}", program, claude::shell_quote(name))
+}
+
+/// The settings document, `{}` when the file does not exist yet
+fn read_settings(path: &Path) -> Result<serde_json::Value> {
+    match std::fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => Ok(serde_json::json!({})),
+        Ok(content) => serde_json::from_str(&content).map_err(|e| {
+            CcmError::InvalidArgument(format!("{} is not valid JSON: {}", path.display(), e))
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(serde_json::json!({})),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,61 @@
+// Clone command implementation
+
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::input::resolve_secret;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Clone {
+        source,
+        destination,
+        secret,
+    } = command
+    {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        do_clone(&source, &destination, secret)
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_clone(source: &str, destination: &str, secret: Option<String>) -> Result<()> {
+    // Normalize and validate the new name against the configured policy
+    let policy = crate::db::get_database()?.get_name_policy()?;
+    let normalized = policy.normalize(destination);
+    policy.validate(&normalized)?;
+    let destination = normalized.as_str();
+
+    if secrets::get_entry(destination).is_ok() {
+        return Err(CcmError::InvalidArgument(format!(
+            "Entry '{}' already exists",
+            destination
+        )));
+    }
+
+    let (source_entry, source_secret) = secrets::get_entry_with_secret(source)?;
+
+    // Keep the env mapping structure; only the secret may change
+    let secret_value = match secret {
+        Some(value) => resolve_secret(Some(value))?,
+        None => source_secret,
+    };
+
+    let mut entry = Entry::new(destination.to_string(), source_entry.metadata.clone());
+    entry.tags = source_entry.tags.clone();
+    entry.notes = source_entry.notes.clone();
+
+    // Encrypted afresh, so the copy shares no ciphertext with the source
+    secrets::add_entry(destination, entry, &secret_value)?;
+
+    output::success(format!(
+        "Cloned {} → {}",
+        source.bold(),
+        destination.cyan().bold()
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+// Completion command implementation
+//
+// The scripts are generated by clap_complete from the CLI definition, then
+// extended so that commands taking an entry name complete the names of
+// existing entries by running `ccm list --quieter`. Without an unlocked vault
+// (PIN set and no agent running) that prints nothing, and only the static
+// completions are offered; it never prompts.
+
+use crate::utils::Result;
+use crate::Commands;
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+
+/// Top-level commands whose first argument is an existing entry
+const ENTRY_COMMANDS: &[&str] = &[
+    "get", "update", "model", "delete", "use", "lint", "export", "history", "rollback", "clone",
+    "launch", "docker", "test", "models", "usage",
+];
+
+/// Commands that take any number of entry names
+const MULTI_ENTRY_COMMANDS: &[&str] = &["delete"];
+
+/// How the scripts list entry names (stdin closed so a PIN is never asked)
+const LIST_ENTRIES: &str = "ccm list --quieter 2>/dev/null </dev/null";
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Completion { shell } = command {
+        do_completion(shell)
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_completion(shell: Shell) -> Result<()> {
+    print!("{}", completion_script(shell));
+    Ok(())
+}
+
+/// clap's script for `shell` with entry-name completion added
+fn completion_script(shell: Shell) -> String {
+    let mut cli = crate::Cli::command();
+    cli.build();
+
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cli, "ccm", &mut script);
+    let script = String::from_utf8_lossy(&script);
+
+    let entry_commands = with_aliases(&cli, ENTRY_COMMANDS);
+    match shell {
+        Shell::Bash => extend_bash(&script, &cli, &entry_commands),
+        Shell::Zsh => extend_zsh(&script, &entry_commands),
+        Shell::Fish => extend_fish(&script, &entry_commands),
+        Shell::PowerShell => extend_powershell(&script, &entry_commands),
+        _ => script.into_owned(),
+    }
+}
+
+/// Command names plus their aliases
+fn with_aliases(cli: &Command, names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .filter_map(|name| cli.find_subcommand(name))
+        .flat_map(|sub| {
+            std::iter::once(sub.get_name().to_string())
+                .chain(sub.get_all_aliases().map(str::to_string))
+        })
+        .collect()
+}
+
+fn extend_bash(script: &str, cli: &Command, entry_commands: &[String]) -> String {
+    // Global options whose value must not be mistaken for the command
+    let value_options: Vec<String> = cli
+        .get_arguments()
+        .filter(|arg| arg.is_global_set() && arg.get_action().takes_values())
+        .filter_map(|arg| arg.get_long().map(|long| format!("--{}", long)))
+        .collect();
+    let multi = with_aliases(cli, MULTI_ENTRY_COMMANDS);
+
+    let function = format!(
+        r#"_ccm_with_entries() {{
+    local i cmd="" pos=0 cur="${{COMP_WORDS[COMP_CWORD]}}"
+    for (( i = 1; i < COMP_CWORD; i++ )); do
+        case "${{COMP_WORDS[i]}}" in
+            {value_options}) (( i++ )) ;;
+            -*) ;;
+            *) if [[ -z "$cmd" ]]; then cmd="${{COMP_WORDS[i]}}"; else (( pos++ )); fi ;;
+        esac
+    done
+    case "$cmd" in
+        {multi}) pos=0 ;;
+    esac
+    if [[ "$cur" != -* && $pos -eq 0 ]]; then
+        case "$cmd" in
+            {entries})
+                COMPREPLY=( $(compgen -W "$({list})" -- "$cur") )
+                return 0
+                ;;
+        esac
+    fi
+    _ccm "$@"
+}}
+
+"#,
+        value_options = value_options.join("|"),
+        multi = multi.join("|"),
+        entries = entry_commands.join("|"),
+        list = LIST_ENTRIES,
+    );
+
+    debug_assert!(
+        script.contains("complete -F _ccm "),
+        "clap's bash script no longer registers _ccm"
+    );
+    let script = script.replace("complete -F _ccm ", "complete -F _ccm_with_entries ");
+    insert_before(&script, "if [[ \"${BASH_VERSINFO[0]}\"", &function)
+}
+
+fn extend_zsh(script: &str, entry_commands: &[String]) -> String {
+    // In the case block of an entry command, complete its first positional
+    // argument (`':name -- ...:_default'`) from the entry list
+    let mut out = String::with_capacity(script.len());
+    let mut in_entry_block = false;
+    let mut hooked = false;
+    for line in script.lines() {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            in_entry_block = entry_commands.iter().any(|c| c == name);
+        } else if trimmed == ";;" {
+            in_entry_block = false;
+        }
+
+        let is_positional = trimmed.starts_with("':") || trimmed.starts_with("'*::");
+        if in_entry_block && is_positional && trimmed.contains(":_default'") {
+            out.push_str(&line.replacen(":_default'", ":_ccm_entries'", 1));
+            in_entry_block = false;
+            hooked = true;
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+
+    debug_assert!(hooked, "no entry argument found in clap's zsh script");
+
+    let function = format!(
+        r#"(( $+functions[_ccm_entries] )) ||
+_ccm_entries() {{
+    local -a entries
+    entries=(${{(f)"$({})"}})
+    compadd -a entries
+}}
+
+"#,
+        LIST_ENTRIES
+    );
+    insert_before(&out, "if [ \"$funcstack[1]\" = \"_ccm\" ]", &function)
+}
+
+fn extend_fish(script: &str, entry_commands: &[String]) -> String {
+    debug_assert!(
+        script.contains("function __fish_ccm_using_subcommand"),
+        "clap's fish script no longer defines __fish_ccm_using_subcommand"
+    );
+    format!(
+        "{}complete -c ccm -n \"__fish_ccm_using_subcommand {}\" -f -a \"({})\"\n",
+        script,
+        entry_commands.join(" "),
+        LIST_ENTRIES
+    )
+}
+
+fn extend_powershell(script: &str, entry_commands: &[String]) -> String {
+    let list = "ccm list --quieter 2>$null | ForEach-Object { \
+                [CompletionResult]::new($_, $_, [CompletionResultType]::ParameterValue, $_) }";
+    let mut out = String::with_capacity(script.len());
+    let mut hooked = false;
+    for line in script.lines() {
+        out.push_str(line);
+        out.push('\n');
+        let is_entry_case = entry_commands
+            .iter()
+            .any(|c| line.trim() == format!("'ccm;{}' {{", c));
+        if is_entry_case {
+            out.push_str("            ");
+            out.push_str(list);
+            out.push('\n');
+            hooked = true;
+        }
+    }
+    debug_assert!(
+        hooked,
+        "no entry command case found in clap's PowerShell script"
+    );
+    out
+}
+
+/// Insert `text` before the first line starting with `marker` (or append it;
+/// debug builds fail instead, so a change in clap's output shows in tests)
+fn insert_before(script: &str, marker: &str, text: &str) -> String {
+    match script.find(&format!("\n{}", marker)) {
+        Some(i) => format!("{}\n{}{}", &script[..i], text, &script[i + 1..]),
+        None => {
+            debug_assert!(
+                false,
+                "marker {:?} not found in the completion script",
+                marker
+            );
+            format!("{}\n{}", script, text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_scripts() {
+        let bash = completion_script(Shell::Bash);
+        assert!(bash.contains("complete -F _ccm_with_entries "));
+        assert!(!bash.contains("complete -F _ccm -"));
+        let hook = bash.find("_ccm_with_entries() {").unwrap();
+        assert!(hook < bash.find("\nif [[ \"${BASH_VERSINFO[0]}\"").unwrap());
+        assert!(bash.contains(LIST_ENTRIES));
+
+        let zsh = completion_script(Shell::Zsh);
+        assert!(zsh.contains("_ccm_entries() {"));
+        assert!(zsh.contains(":_ccm_entries'"));
+        let hook = zsh.find("_ccm_entries() {").unwrap();
+        assert!(hook < zsh.find("\nif [ \"$funcstack[1]\" = \"_ccm\" ]").unwrap());
+
+        let fish = completion_script(Shell::Fish);
+        assert!(fish.contains("complete -c ccm -n \"__fish_ccm_using_subcommand get "));
+        assert!(fish
+            .trim_end()
+            .ends_with(&format!("-f -a \"({})\"", LIST_ENTRIES)));
+
+        let powershell = completion_script(Shell::PowerShell);
+        let case = powershell.find("'ccm;use' {").unwrap();
+        assert!(powershell[case..]
+            .lines()
+            .nth(1)
+            .unwrap()
+            .contains("ccm list --quieter"));
+    }
+
+    #[test]
+    #[should_panic(expected = "not found")]
+    fn test_insert_before_missing_marker() {
+        insert_before("a\nb\n", "c", "x");
+    }
+}
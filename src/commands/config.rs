@@ -1,9 +1,34 @@
 // Config command implementation
 
+use crate::auth::kdf;
+use crate::commands::secret::run_editor;
+use crate::core::health::HEALTH_CHECK_KEY;
+use crate::core::report::{self, Frequency};
 use crate::db;
-use crate::utils::Result;
+use crate::db::snapshots::{self, AutoBackup};
+use crate::mcp;
+use crate::net::proxy;
+use crate::utils::files::write_private_file;
+use crate::utils::input::FORBID_ARGV_SECRETS_KEY;
+use crate::utils::{output, parse_bool, CcmError, NamePolicy, Result, NAME_POLICY_KEYS};
 use crate::Commands;
 use colored::Colorize;
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+
+/// Settings maintained by ccm itself: shown, but not editable with `config edit`
+const MANAGED_KEYS: &[&str] = &[
+    "pinHash",
+    "pinSalt",
+    "active_entry",
+    "entry_activations",
+    "default_type",
+    "schema_migration_unified",
+    "migrated_from_json",
+    "defaults_created",
+];
 
 pub async fn execute(command: Commands) -> Result<()> {
     if let Commands::Config { key, value } = command {
@@ -18,14 +43,18 @@ fn do_config(key: Option<&str>, value: Option<&str>) -> Result<()> {
 
     match (key, value) {
         (Some(k), Some(v)) => {
+            validate_setting(k, v)?;
+
             // Set a config value
             db.save_setting(k, &v)?;
-            println!("{} Set config: {} = {}", "✅".green(), k.bold(), v);
+            output::success(format!("Set config: {} = {}", k.bold(), v));
         }
         (Some(k), None) => {
             // "show" is an alias for listing all config
             if k == "show" {
                 show_all_config(&db)?;
+            } else if k == "edit" {
+                edit_config(&db)?;
             } else {
                 // Get a config value
                 if let Some(v) = db.get_setting::<String>(k)? {
@@ -50,6 +79,186 @@ fn do_config(key: Option<&str>, value: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+/// Reject invalid values for known keys before saving them
+pub(crate) fn validate_setting(k: &str, v: &str) -> Result<()> {
+    if NAME_POLICY_KEYS.contains(&k) {
+        NamePolicy::from_settings(|key| (key == k).then(|| v.to_string()))
+            .map_err(|e| CcmError::InvalidArgument(e.to_string()))?;
+    }
+    let boolean = [
+        FORBID_ARGV_SECRETS_KEY,
+        HEALTH_CHECK_KEY,
+        mcp::REVEAL_SECRETS_KEY,
+    ];
+    if boolean.contains(&k) && parse_bool(v).is_none() {
+        return Err(CcmError::InvalidArgument(format!(
+            "Invalid value for {}: '{}' (expected true or false)",
+            k, v
+        )));
+    }
+    if k == snapshots::AUTO_BACKUP_KEY && AutoBackup::parse(v).is_none() {
+        return Err(CcmError::InvalidArgument(format!(
+            "Invalid value for {}: '{}' (expected off, change or daily)",
+            k, v
+        )));
+    }
+    if k == snapshots::BACKUP_KEEP_KEY {
+        snapshots::parse_keep(v)?;
+    }
+    if k == report::REPORT_FREQUENCY_KEY && Frequency::parse(v).is_none() {
+        return Err(CcmError::InvalidArgument(format!(
+            "Invalid value for {}: '{}' (expected off, daily or weekly)",
+            k, v
+        )));
+    }
+    if k == report::REPORT_MAX_AGE_KEY {
+        report::parse_max_age(v)?;
+    }
+    if k == kdf::KDF_ITERATIONS_KEY {
+        kdf::parse_iterations(v)?;
+    }
+    if k == proxy::PROXY_KEY {
+        proxy::validate(v)?;
+    }
+    Ok(())
+}
+
+/// Whether a setting can be changed with `config edit`
+pub(crate) fn is_editable(key: &str) -> bool {
+    !key.starts_with("__") && !MANAGED_KEYS.contains(&key)
+}
+
+/// The editable settings, which can be carried to another machine
+/// (`ccm export --include config`)
+pub(crate) fn portable_settings(db: &crate::db::Database) -> Result<BTreeMap<String, String>> {
+    Ok(db
+        .get_all_settings()?
+        .into_iter()
+        .filter(|(k, _)| is_editable(k))
+        .filter_map(|(k, v)| serde_json::from_str::<String>(&v).ok().map(|v| (k, v)))
+        .collect())
+}
+
+/// Open the editable settings as TOML in $EDITOR and apply the changes
+/// all at once; nothing is saved if any value is invalid
+fn edit_config(db: &crate::db::Database) -> Result<()> {
+    let current = portable_settings(db)?;
+
+    let mut random = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut random);
+    let path = std::env::temp_dir().join(format!("ccm-config-{}.toml", hex::encode(random)));
+
+    let mut content = format!(
+        "# ccm settings: edit, add or remove keys, then save and close the editor.\n\
+         # Removed keys are unset. Nothing is saved if a value is invalid.\n\n{}",
+        toml::to_string(&current)
+            .map_err(|e| CcmError::Unknown(format!("Failed to write TOML: {}", e)))?
+    );
+
+    let edited = loop {
+        write_private_file(&path, content.as_bytes())?;
+        let result = run_editor(&path).and_then(|()| Ok(fs::read_to_string(&path)?));
+        let _ = fs::remove_file(&path);
+        content = result?;
+
+        match parse_settings(&content) {
+            Ok(edited) => break edited,
+            Err(e) => {
+                println!("{} {}", "❌".red(), e);
+                print!("Edit again? [y/N]: ");
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer)?;
+                if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                    println!("No changes saved.");
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    let set: Vec<(String, String)> = edited
+        .iter()
+        .filter(|(k, v)| current.get(*k) != Some(v))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let delete: Vec<String> = current
+        .keys()
+        .filter(|k| !edited.contains_key(*k))
+        .cloned()
+        .collect();
+
+    if set.is_empty() && delete.is_empty() {
+        println!("{} No changes.", "ℹ️".blue());
+        return Ok(());
+    }
+
+    db.apply_settings(&set, &delete)?;
+
+    for (k, v) in &set {
+        output::success(format!("Set config: {} = {}", k.bold(), v));
+    }
+    for k in &delete {
+        output::success(format!("Unset config: {}", k.bold()));
+    }
+
+    Ok(())
+}
+
+/// Parse and validate edited settings (nested tables become dotted keys)
+fn parse_settings(content: &str) -> Result<BTreeMap<String, String>> {
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e| CcmError::InvalidArgument(format!("Invalid TOML: {}", e)))?;
+
+    let mut settings = BTreeMap::new();
+    flatten_table("", table, &mut settings)?;
+
+    for (k, v) in &settings {
+        if !is_editable(k) {
+            return Err(CcmError::InvalidArgument(format!(
+                "'{}' is managed by ccm and cannot be set here",
+                k
+            )));
+        }
+        validate_setting(k, v)?;
+    }
+
+    Ok(settings)
+}
+
+fn flatten_table(
+    prefix: &str,
+    table: toml::Table,
+    settings: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    for (k, v) in table {
+        let key = if prefix.is_empty() {
+            k
+        } else {
+            format!("{}.{}", prefix, k)
+        };
+        let value = match v {
+            toml::Value::Table(inner) => {
+                flatten_table(&key, inner, settings)?;
+                continue;
+            }
+            toml::Value::String(s) => s,
+            toml::Value::Boolean(b) => b.to_string(),
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Float(f) => f.to_string(),
+            _ => {
+                return Err(CcmError::InvalidArgument(format!(
+                    "Invalid value for {}: expected a string, number or boolean",
+                    key
+                )))
+            }
+        };
+        settings.insert(key, value);
+    }
+    Ok(())
+}
+
 fn show_all_config(db: &crate::db::Database) -> Result<()> {
     let settings = db.get_all_settings()?;
     if settings.is_empty() {
@@ -0,0 +1,195 @@
+// Db command implementation (integrity check, repair and row verification)
+
+use crate::db::integrity::IntegrityReport;
+use crate::db::{self, snapshots};
+use crate::utils::{output, CcmError, Result};
+use crate::{Commands, DbAction};
+use colored::Colorize;
+use std::io::{self, Write};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Db { action } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+
+        match action {
+            DbAction::Check => check(),
+            DbAction::Repair { force } => repair(force),
+            DbAction::Verify { accept, force } => verify(accept, force),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+fn check() -> Result<()> {
+    let report = db::integrity::check_integrity()?;
+    print_report(&report);
+
+    if report.is_ok() {
+        output::success("Database is consistent.");
+        return Ok(());
+    }
+    if report.has_orphans() {
+        println!(
+            "Run {} to remove the orphaned rows.",
+            "ccm db repair".yellow()
+        );
+    }
+    Err(CcmError::Integrity(format!(
+        "{} problem(s) found",
+        problem_count(&report)
+    )))
+}
+
+fn repair(force: bool) -> Result<()> {
+    let db = db::get_database()?;
+    let report = db.check_integrity()?;
+    print_report(&report);
+
+    if !report.has_orphans() {
+        if report.sqlite_errors.is_empty() {
+            output::success("Nothing to repair.");
+            return Ok(());
+        }
+        return Err(CcmError::Integrity(format!(
+            "SQLite reported corruption that repair cannot fix. Copy a snapshot from {} over {}",
+            snapshots::backups_dir().display(),
+            db::db_path().display()
+        )));
+    }
+
+    if !force {
+        print!("Remove these orphaned rows? (y/N): ");
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let answer = input.trim().to_lowercase();
+        if answer != "y" && answer != "yes" {
+            println!("Cancelled");
+            return Ok(());
+        }
+    }
+
+    let snapshot = snapshots::snapshot_now(&db)?;
+    println!("Snapshot taken: {}", snapshot.path.display());
+
+    let summary = db.repair()?;
+    output::success(format!(
+        "Removed {} entr{}, {} secret(s) and {} history row(s).",
+        summary.entries_removed,
+        if summary.entries_removed == 1 {
+            "y"
+        } else {
+            "ies"
+        },
+        summary.secrets_removed,
+        summary.history_removed
+    ));
+
+    if !report.sqlite_errors.is_empty() {
+        println!(
+            "{} SQLite still reports corruption; consider restoring a snapshot.",
+            "⚠️".yellow()
+        );
+    }
+    Ok(())
+}
+
+fn verify(accept: bool, force: bool) -> Result<()> {
+    let db = db::get_database()?;
+    let modified = db.verify_rows()?;
+
+    let problem = || {
+        CcmError::Integrity(format!(
+            "{} modified entr{}",
+            modified.len(),
+            if modified.len() == 1 { "y" } else { "ies" }
+        ))
+    };
+
+    if output::is_json() && !accept {
+        output::print_json(&serde_json::json!({ "modified": modified }))?;
+        return if modified.is_empty() {
+            Ok(())
+        } else {
+            Err(problem())
+        };
+    }
+    if modified.is_empty() {
+        output::success("Every entry and secret row matches its MAC.");
+        return Ok(());
+    }
+
+    print_section(
+        "Entries changed outside ccm",
+        "their metadata, tags, notes, lock or secret do not match the MAC",
+        &modified,
+    );
+    if !accept {
+        println!(
+            "Check them, then run {} to keep them as they are, or restore a snapshot.",
+            "ccm db verify --accept".yellow()
+        );
+        return Err(problem());
+    }
+
+    if !force {
+        print!("Accept these rows as they are now? (y/N): ");
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let answer = input.trim().to_lowercase();
+        if answer != "y" && answer != "yes" {
+            println!("Cancelled");
+            return Ok(());
+        }
+    }
+
+    let snapshot = snapshots::snapshot_now(&db)?;
+    println!("Snapshot taken: {}", snapshot.path.display());
+
+    let sealed = db.reseal_rows(&modified)?;
+    output::success(format!("Signed {} row(s) again.", sealed));
+    Ok(())
+}
+
+fn problem_count(report: &IntegrityReport) -> usize {
+    report.sqlite_errors.len()
+        + report.entries_without_secret.len()
+        + report.secrets_without_entry.len()
+        + report.orphaned_history.len()
+}
+
+fn print_section(title: &str, hint: &str, names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+    println!("{} {} ({})", "⚠️".yellow(), title.bold(), hint);
+    for name in names {
+        println!("   - {}", name);
+    }
+}
+
+fn print_report(report: &IntegrityReport) {
+    print_section(
+        "SQLite integrity check",
+        "page-level corruption",
+        &report.sqlite_errors,
+    );
+    print_section(
+        "Entries without a secret",
+        "their value is lost; repair deletes them",
+        &report.entries_without_secret,
+    );
+    print_section(
+        "Secrets without an entry",
+        "left behind by a failed delete; repair deletes them",
+        &report.secrets_without_entry,
+    );
+    print_section(
+        "History without a secret",
+        "old values of deleted entries; repair deletes them",
+        &report.orphaned_history,
+    );
+}
@@ -1,20 +1,25 @@
 // Delete command implementation
 
 use crate::secrets;
-use crate::utils::{CcmError, Result};
+use crate::utils::{glob, output, CcmError, Result};
 use crate::Commands;
 use colored::Colorize;
 use std::io::{self, Write};
 
 pub async fn execute(command: Commands) -> Result<()> {
-    if let Commands::Delete { names, force } = command {
-        do_delete(names, force)
+    if let Commands::Delete {
+        names,
+        force,
+        force_unlock,
+    } = command
+    {
+        do_delete(names, force, force_unlock)
     } else {
         unreachable!()
     }
 }
 
-fn do_delete(names: Vec<String>, force: bool) -> Result<()> {
+fn do_delete(names: Vec<String>, force: bool, force_unlock: bool) -> Result<()> {
     // Handle multiple names deletion
     if names.is_empty() {
         println!("Usage: ccm delete <name> [<name2> <name3> ...]");
@@ -22,6 +27,7 @@ fn do_delete(names: Vec<String>, force: bool) -> Result<()> {
         println!("Examples:");
         println!("  ccm delete myentry");
         println!("  ccm delete entry1 entry2 entry3");
+        println!("  ccm delete 'test-*'");
         println!();
         return Err(CcmError::InvalidArgument(
             "No entry names specified".to_string(),
@@ -29,20 +35,51 @@ fn do_delete(names: Vec<String>, force: bool) -> Result<()> {
     }
 
     // Single entry deletion
-    if names.len() == 1 {
-        return delete_single_entry(&names[0], force);
+    if names.len() == 1 && !glob::is_pattern(&names[0]) {
+        return delete_single_entry(&names[0], force, force_unlock);
     }
 
-    // Multiple entries deletion
-    delete_multiple_entries(&names, force)
+    // Multiple entries deletion (patterns always list what they matched)
+    let names = expand_patterns(names)?;
+    delete_multiple_entries(&names, force, force_unlock)
 }
 
-/// Delete a single entry
-fn delete_single_entry(name: &str, force: bool) -> Result<()> {
-    // Check if entry exists
-    if secrets::get_entry(name).is_err() {
-        return Err(CcmError::EntryNotFound(name.to_string()));
+/// Replace glob patterns with the entries they match, in name order
+/// A pattern matching nothing is an error, so a typo deletes nothing
+fn expand_patterns(names: Vec<String>) -> Result<Vec<String>> {
+    if !names.iter().any(|n| glob::is_pattern(n)) {
+        return Ok(names);
+    }
+
+    let mut all: Vec<String> = secrets::list_entries()?.into_keys().collect();
+    all.sort();
+
+    let mut expanded: Vec<String> = Vec::new();
+    for name in names {
+        if !glob::is_pattern(&name) {
+            if !expanded.contains(&name) {
+                expanded.push(name);
+            }
+            continue;
+        }
+        let matching: Vec<&String> = all.iter().filter(|n| glob::matches(&name, n)).collect();
+        if matching.is_empty() {
+            return Err(CcmError::EntryNotFound(name));
+        }
+        for found in matching {
+            if !expanded.contains(found) {
+                expanded.push(found.clone());
+            }
+        }
     }
+    Ok(expanded)
+}
+
+/// Delete a single entry
+fn delete_single_entry(name: &str, force: bool, force_unlock: bool) -> Result<()> {
+    // Check if entry exists (suggesting close names if not)
+    secrets::lookup_name(name, false)?;
+    secrets::ensure_unlocked(name, force_unlock)?;
 
     // Confirm deletion
     if !force {
@@ -61,7 +98,7 @@ fn delete_single_entry(name: &str, force: bool) -> Result<()> {
     let deleted = secrets::delete_entry(name)?;
 
     if deleted {
-        println!("{} Deleted entry: {}", "✅".green(), name.bold());
+        output::success(format!("Deleted entry: {}", name.bold()));
     } else {
         println!("{} Entry not found: {}", "⚠️".yellow(), name);
     }
@@ -70,12 +107,16 @@ fn delete_single_entry(name: &str, force: bool) -> Result<()> {
 }
 
 /// Delete multiple entries by name
-fn delete_multiple_entries(names: &[String], force: bool) -> Result<()> {
-    // Check which entries exist
+fn delete_multiple_entries(names: &[String], force: bool, force_unlock: bool) -> Result<()> {
+    // Check which entries exist, and which are locked (kept unless forced)
     let mut entries_info: Vec<(String, Option<String>)> = Vec::new();
+    let mut locked: Vec<String> = Vec::new();
     for name in names {
         match secrets::get_entry(name) {
             Ok(entry) => {
+                if entry.locked && !force_unlock {
+                    locked.push(name.clone());
+                }
                 let display_name = if let Some(notes) = &entry.notes {
                     if !notes.is_empty() {
                         format!("{} ({})", name, notes)
@@ -97,20 +138,20 @@ fn delete_multiple_entries(names: &[String], force: bool) -> Result<()> {
     println!(
         "{} WARNING: This will delete {} entries:",
         "⚠️".yellow(),
-        names.len()
+        names.len() - locked.len()
     );
 
-    for (name, display) in entries_info.iter().take(10) {
-        if let Some(d) = display {
-            println!("   - {}", d);
-        } else {
-            println!("   - {} (NOT FOUND)", name);
+    for (name, display) in &entries_info {
+        match display {
+            Some(d) if locked.contains(name) => println!("   - {} (LOCKED, kept)", d),
+            Some(d) => println!("   - {}", d),
+            None => println!("   - {} (NOT FOUND)", name),
         }
     }
-    if entries_info.len() > 10 {
-        println!("   ... and {} more", entries_info.len() - 10);
-    }
     println!();
+    if locked.len() == names.len() {
+        return Err(CcmError::EntryLocked(locked.join(", ")));
+    }
 
     // Confirm deletion
     if !force {
@@ -131,12 +172,12 @@ fn delete_multiple_entries(names: &[String], force: bool) -> Result<()> {
     let mut success_count = 0;
     let mut fail_count = 0;
 
-    for name in names {
+    for name in names.iter().filter(|n| !locked.contains(n)) {
         match secrets::delete_entry(name) {
             Ok(deleted) => {
                 if deleted {
                     success_count += 1;
-                    println!("{} Deleted: {}", "✅".green(), name);
+                    output::success(format!("Deleted: {}", name));
                 } else {
                     fail_count += 1;
                     println!("{} Not found: {}", "⚠️".yellow(), name);
@@ -156,6 +197,9 @@ fn delete_multiple_entries(names: &[String], force: bool) -> Result<()> {
     if fail_count > 0 {
         println!("   Failed: {} entries", fail_count);
     }
+    if !locked.is_empty() {
+        println!("   Kept (locked): {} entries", locked.len());
+    }
 
     Ok(())
 }
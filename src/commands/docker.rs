@@ -0,0 +1,25 @@
+// Docker command implementation
+//
+// `ccm docker <ENTRY> -- run ...` runs docker with the entry's variables
+// passed into the container by name (see `crate::launch::docker`).
+
+use crate::launch::docker;
+use crate::utils::Result;
+use crate::Commands;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Docker { name, args } = command {
+        // Reject a command without run/create/exec before asking for the PIN
+        docker::with_env_flags(&args, &[])?;
+
+        let (entry, secret) = super::launch::entry_with_secret(&name).await?;
+        let env_vars = crate::env::get_env_mappings_with_secret(&entry, &secret);
+
+        let mut names: Vec<String> = env_vars.keys().cloned().collect();
+        names.sort();
+        let args = docker::with_env_flags(&args, &names)?;
+        super::run::run_with_env("docker", &args, &env_vars)
+    } else {
+        unreachable!()
+    }
+}
@@ -0,0 +1,164 @@
+// Doctor command implementation
+
+use crate::commands::version::{build_info, BuildInfo};
+use crate::core::diagnostics::{self, Diagnostics};
+use crate::db::{self, snapshots};
+use crate::secrets::master_key;
+use crate::utils::files::write_private_file;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Bug-report bundle written by `ccm doctor --bundle`
+#[derive(Serialize)]
+struct Bundle {
+    format: &'static str,
+    #[serde(rename = "generatedAt")]
+    generated_at: String,
+    ccm: BuildInfo,
+    diagnostics: Diagnostics,
+}
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Doctor { bundle, output } = command {
+        // Inspecting the database needs the master key; a locked or broken
+        // vault is itself a diagnostic, not a reason to stop. Never create a
+        // key or database that does not exist yet
+        let opened = if !db::db_path().exists() {
+            Err(CcmError::Unknown("No database yet".to_string()))
+        } else if !master_key::has_master_key().unwrap_or(false) {
+            Err(CcmError::MasterKeyNotAvailable)
+        } else {
+            match crate::auth::ensure_master_key_loaded().await {
+                Ok(()) => db::get_database(),
+                Err(e) => Err(e),
+            }
+        };
+        let (database, db_error) = match opened {
+            Ok(database) => (Some(database), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let diagnostics = diagnostics::collect(database.as_ref(), db_error);
+
+        print_report(&diagnostics);
+        if bundle {
+            write_bundle(diagnostics, output)?;
+        }
+        Ok(())
+    } else {
+        unreachable!()
+    }
+}
+
+fn check(ok: bool, label: &str, detail: &str) {
+    let mark = if ok { "✅".green() } else { "⚠️".yellow() };
+    println!("  {} {:<14} {}", mark, label, detail);
+}
+
+fn print_report(d: &Diagnostics) {
+    println!("{}", "ccm doctor".bold());
+    println!();
+
+    check(
+        true,
+        "System",
+        &format!(
+            "{} {} ({})",
+            d.system.os,
+            d.system.arch,
+            d.system.os_version.as_deref().unwrap_or("unknown version")
+        ),
+    );
+    check(
+        d.keyring.available,
+        "Keyring",
+        &match &d.keyring.error {
+            None => format!("{} available", d.keyring.backend),
+            Some(e) => format!("{}: {}", d.keyring.backend, e),
+        },
+    );
+    check(
+        d.vault.master_key_present,
+        "Master key",
+        if d.vault.master_key_present {
+            "present"
+        } else {
+            "missing (a new vault is created on first use)"
+        },
+    );
+    check(
+        true,
+        "PIN",
+        if d.vault.pin_set {
+            "set"
+        } else {
+            "not set (ccm auth set adds one)"
+        },
+    );
+
+    match (&d.vault.error, &d.vault.schema) {
+        (Some(e), _) => check(false, "Database", e),
+        (None, Some(schema)) => check(
+            true,
+            "Database",
+            &format!(
+                "{}, {} entries, {} secrets, SQLCipher {}",
+                d.vault.database_path,
+                d.vault.entry_count.unwrap_or(0),
+                d.vault.secret_count.unwrap_or(0),
+                schema.cipher_version.as_deref().unwrap_or("not in use")
+            ),
+        ),
+        (None, None) => check(false, "Database", "not inspected"),
+    }
+    if let (Some(entries), Some(secrets)) = (d.vault.entry_count, d.vault.secret_count) {
+        check(
+            entries == secrets,
+            "Consistency",
+            &if entries == secrets {
+                "every entry has a secret".to_string()
+            } else {
+                format!("{} entries but {} secrets", entries, secrets)
+            },
+        );
+    }
+    check(
+        true,
+        "Snapshots",
+        &format!(
+            "{} in {}",
+            d.vault.snapshots,
+            diagnostics::redact_home(&snapshots::backups_dir().display().to_string())
+        ),
+    );
+}
+
+fn write_bundle(diagnostics: Diagnostics, output: Option<String>) -> Result<()> {
+    let now = chrono::Utc::now();
+    let bundle = Bundle {
+        format: "ccm-doctor-v1",
+        generated_at: now.to_rfc3339(),
+        ccm: build_info(),
+        diagnostics,
+    };
+
+    let path = match output {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_dir()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?
+            .join(format!(
+                "ccm-doctor-{}.json",
+                now.format("%Y-%m-%dT%H-%M-%S")
+            )),
+    };
+    write_private_file(&path, serde_json::to_string_pretty(&bundle)?.as_bytes())?;
+
+    println!();
+    output::success(format!("Diagnostics bundle written to: {}", path.display()));
+    println!(
+        "   It contains no secrets or entry names; review it before attaching it to a bug report."
+    );
+    Ok(())
+}
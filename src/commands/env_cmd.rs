@@ -0,0 +1,165 @@
+// Env command implementation
+//
+// `ccm env file NAME` writes an entry's variables to a .env file only the
+// user can read, for tools that take an env file path rather than an
+// environment. With --ttl a detached `ccm env expire` deletes it later.
+
+use crate::secrets::{self, key_cache::parse_ttl};
+use crate::utils::{dotenv, files, output, CcmError, Result};
+use crate::{Commands, EnvAction};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Env { action } = command {
+        match action {
+            EnvAction::File { name, path, ttl } => {
+                // Ensure master key is loaded (prompts for PIN if needed)
+                crate::auth::ensure_master_key_loaded().await?;
+                let name = super::pick::lookup_name(&name, true)?;
+                file(&name, path, ttl.as_deref())
+            }
+            EnvAction::Expire {
+                path,
+                after,
+                written,
+            } => expire(&path, Duration::from_secs(after), written),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+fn file(name: &str, path: Option<PathBuf>, ttl: Option<&str>) -> Result<()> {
+    let ttl = ttl.map(parse_ttl).transpose()?.flatten();
+    let (entry, secret) = secrets::use_entry(name)?;
+    let mut vars: Vec<(String, String)> = crate::env::get_env_mappings_with_secret(&entry, &secret)
+        .into_iter()
+        .collect();
+    if vars.is_empty() {
+        return Err(CcmError::InvalidArgument(format!(
+            "Entry '{}' has no variables",
+            name
+        )));
+    }
+    vars.sort();
+
+    let path = match path {
+        Some(path) => path,
+        None => default_path(name)?,
+    };
+    files::write_private_file(&path, dotenv::format_dotenv(&vars).as_bytes())?;
+
+    let expires_at = match ttl {
+        Some(ttl) => {
+            schedule_expiry(&path, ttl)?;
+            Some(chrono::Utc::now() + ttl)
+        }
+        None => None,
+    };
+
+    // Only the path on stdout, so that --env-file "$(ccm env file NAME)" works
+    if output::is_json() {
+        output::print_json(&json!({
+            "path": path,
+            "expires_at": expires_at.map(|t| t.to_rfc3339()),
+        }))?;
+    } else {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// `<name>.env` in the user's private runtime directory
+fn default_path(name: &str) -> Result<PathBuf> {
+    Ok(files::private_runtime_dir("env")?.join(format!("{}.env", files::safe_file_name(name))))
+}
+
+/// Modification time of a file, in ms since the epoch
+fn written_ms(path: &Path) -> Result<u64> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Ok(since_epoch.as_millis() as u64)
+}
+
+/// Start a detached `ccm env expire` for the file
+fn schedule_expiry(path: &Path, ttl: Duration) -> Result<()> {
+    use std::process::{Command, Stdio};
+
+    Command::new(std::env::current_exe()?)
+        .env_remove(crate::auth::PIN_ENV)
+        .args(["env", "expire"])
+        .arg(path)
+        .arg("--after")
+        .arg(ttl.as_secs().to_string())
+        .arg("--written")
+        .arg(written_ms(path)?.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Body of the detached process: wait, then delete the file unless it was
+/// written again meanwhile (a newer `env file` schedules its own expiry)
+fn expire(path: &Path, after: Duration, written: u64) -> Result<()> {
+    // Leave the terminal's session so closing it does not stop the timer
+    #[cfg(unix)]
+    // SAFETY: setsid has no preconditions; failure only means we stay attached
+    unsafe {
+        libc::setsid();
+    }
+
+    std::thread::sleep(after);
+    delete_if_unchanged(path, written)?;
+    Ok(())
+}
+
+/// Delete the file if it is still the one written at `written` (ms since
+/// the epoch); returns whether it was deleted
+fn delete_if_unchanged(path: &Path, written: u64) -> Result<bool> {
+    if !path.exists() || written_ms(path)? != written {
+        return Ok(false);
+    }
+    files::shred_file(path)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_path() {
+        let path = default_path("team/openai prod").unwrap();
+        assert_eq!(path.file_name().unwrap(), "team_openai_prod.env");
+        assert_eq!(
+            path.parent().unwrap(),
+            files::private_runtime_dir("env").unwrap()
+        );
+        assert_eq!(
+            default_path("../x").unwrap().parent(),
+            path.parent(),
+            "names cannot leave the directory"
+        );
+    }
+
+    #[test]
+    fn test_delete_if_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("work.env");
+
+        files::write_private_file(&path, b"A=1\n").unwrap();
+        let written = written_ms(&path).unwrap();
+        // Written again by a later `env file`: left for its own expiry
+        assert!(!delete_if_unchanged(&path, written - 1).unwrap());
+        assert!(path.exists());
+
+        assert!(delete_if_unchanged(&path, written).unwrap());
+        assert!(!path.exists());
+        // Already gone (deleted by hand) is fine
+        assert!(!delete_if_unchanged(&path, written).unwrap());
+    }
+}
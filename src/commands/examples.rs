@@ -0,0 +1,56 @@
+// Examples command implementation
+
+use crate::examples::{self, Example, EXAMPLES};
+use crate::utils::{CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Examples { topic } = command {
+        match topic {
+            Some(topic) => {
+                let example = examples::find(&topic).ok_or_else(|| {
+                    CcmError::InvalidArgument(format!(
+                        "Unknown topic: {}. Available: {}",
+                        topic,
+                        topic_names()
+                    ))
+                })?;
+                print_example(example);
+            }
+            None => list_topics(),
+        }
+        Ok(())
+    } else {
+        unreachable!()
+    }
+}
+
+/// Comma-separated topic names
+pub fn topic_names() -> String {
+    EXAMPLES
+        .iter()
+        .map(|e| e.topic)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn list_topics() {
+    println!("{}", "Examples:".bold());
+    println!();
+    for example in EXAMPLES {
+        println!("  {:<10} {}", example.topic.cyan().bold(), example.title);
+    }
+    println!();
+    println!("Use {} to show one", "ccm examples <TOPIC>".yellow());
+}
+
+fn print_example(example: &Example) {
+    println!("{}", example.title.bold().underline());
+    println!("{}", example.summary);
+    for step in example.steps {
+        println!();
+        println!("{}", format!("# {}", step.comment).dimmed());
+        println!("{}", step.command);
+    }
+}
@@ -1,7 +1,14 @@
 // Export command implementation
 
+use crate::commands::config;
+use crate::db::{self, EntryFilter};
 use crate::secrets;
-use crate::utils::{CcmError, Result};
+use crate::secrets::master_key::get_cached_master_key;
+use crate::utils::timings::{self, Phase};
+use crate::utils::{csv_parser, dotenv, files, glob, output, recipients};
+use crate::utils::{
+    derive_backup_signing_key, hmac_sha256, hmac_sha256_verify, signing_key_id, CcmError, Result,
+};
 use crate::Commands;
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
@@ -13,44 +20,85 @@ use pbkdf2::pbkdf2_hmac;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 /// Export file format
 #[derive(Debug, Serialize, Deserialize)]
-struct ExportFile {
-    format: String,
-    encrypted: bool,
-    algorithm: String,
-    data: String,
+pub struct ExportFile {
+    pub format: String,
+    pub encrypted: bool,
+    pub algorithm: String,
+    pub data: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub signature: Option<BackupSignature>,
+}
+
+/// Integrity signature of an encrypted backup: HMAC-SHA256 over the format,
+/// algorithm and ciphertext, keyed off the master key of the exporting vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSignature {
+    pub algorithm: String,
+    /// Identifies the signing key without revealing it
+    #[serde(rename = "keyId")]
+    pub key_id: String,
+    /// Hex-encoded tag
+    pub value: String,
+}
+
+/// Result of checking a backup's signature
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Signed by this vault and unchanged since
+    Valid,
+    /// Signed by this vault but modified afterwards
+    Tampered,
+    /// Signed by another vault; cannot be checked here
+    UnknownKey,
 }
 
+const SIGNATURE_ALGORITHM: &str = "HMAC-SHA256";
+
 /// Export data structure
 #[derive(Debug, Serialize, Deserialize)]
-struct ExportData {
-    version: String,
+pub struct ExportData {
+    pub version: String,
     #[serde(rename = "exportedAt")]
-    exported_at: String,
-    entries: HashMap<String, ExportEntry>,
+    pub exported_at: String,
+    pub entries: HashMap<String, ExportEntry>,
+    /// Settings, with `--include config`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<BTreeMap<String, String>>,
 }
 
 /// Single exported entry
 #[derive(Debug, Serialize, Deserialize)]
-struct ExportEntry {
-    metadata: HashMap<String, String>,
+pub struct ExportEntry {
+    pub metadata: HashMap<String, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    secret: Option<String>,
+    pub secret: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    tags: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    notes: Option<String>,
+    pub notes: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "createdAt")]
-    created_at: Option<String>,
+    pub created_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "updatedAt")]
-    updated_at: Option<String>,
+    pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+}
+
+/// Public-key encryption of a backup, instead of a password
+enum RecipientEncryption {
+    /// age X25519 public keys
+    Age(Vec<String>),
+    /// GnuPG key IDs, fingerprints or emails
+    Gpg(Vec<String>),
 }
 
 pub async fn execute(command: Commands) -> Result<()> {
@@ -58,13 +106,71 @@ pub async fn execute(command: Commands) -> Result<()> {
         name,
         output,
         decrypt,
+        tags,
+        untagged,
+        group,
+        format,
+        no_secret,
+        age_recipients,
+        gpg_recipients,
+        include,
     } = command
     {
+        if no_secret && format != "env" {
+            return Err(CcmError::InvalidArgument(
+                "--no-secret is only supported with --format env".to_string(),
+            ));
+        }
+        let recipients = if !age_recipients.is_empty() {
+            Some(RecipientEncryption::Age(age_recipients))
+        } else if !gpg_recipients.is_empty() {
+            Some(RecipientEncryption::Gpg(gpg_recipients))
+        } else {
+            None
+        };
+        if recipients.is_some() && format == "env" {
+            return Err(CcmError::InvalidArgument(
+                "--age-recipient and --gpg-recipient are not supported with --format env"
+                    .to_string(),
+            ));
+        }
+        if !include.is_empty() && (format == "env" || format == "csv") {
+            return Err(CcmError::InvalidArgument(format!(
+                "--include is not supported with --format {}",
+                format
+            )));
+        }
+        if format == "csv" && !decrypt && recipients.is_none() {
+            return Err(CcmError::InvalidArgument(
+                "CSV exports cannot be password-encrypted; pass --decrypt for plaintext CSV, \
+                 --age-recipient or --gpg-recipient"
+                    .to_string(),
+            ));
+        }
+
         // Ensure master key is loaded (prompts for PIN if needed)
         // NOTE: We ALWAYS need the master key to decrypt secrets from the database,
         // regardless of whether we encrypt the output file with --decrypt flag
         crate::auth::ensure_master_key_loaded().await?;
-        do_export(name.as_deref(), output.as_deref(), decrypt)
+        let filter = EntryFilter {
+            tags,
+            untagged,
+            group,
+            ..EntryFilter::default()
+        };
+        if format == "env" {
+            do_export_env(name.as_deref(), &filter, output.as_deref(), !no_secret)
+        } else {
+            do_export(
+                name.as_deref(),
+                &filter,
+                output.as_deref(),
+                decrypt,
+                &format,
+                recipients.as_ref(),
+                include.iter().any(|part| part == "config"),
+            )
+        }
     } else {
         unreachable!()
     }
@@ -72,15 +178,23 @@ pub async fn execute(command: Commands) -> Result<()> {
 
 fn do_export(
     name_filter: Option<&str>,
+    filter: &EntryFilter,
     output_dir: Option<&str>,
     plaintext: bool,
+    format: &str,
+    recipients: Option<&RecipientEncryption>,
+    include_config: bool,
 ) -> Result<()> {
-    // Get all entries
-    let all_entries = secrets::list_entries()?;
+    // Get all entries (narrowed by tag filters)
+    let all_entries = secrets::list_entries_filtered(filter)?;
 
-    // Filter entries by name if specified
-    let filtered_entries: HashMap<String, crate::types::Entry> = if let Some(name) = name_filter {
-        all_entries.into_iter().filter(|(n, _)| n == name).collect()
+    // Filter entries by name or glob pattern if specified
+    let filtered_entries: HashMap<String, crate::types::Entry> = if let Some(pattern) = name_filter
+    {
+        all_entries
+            .into_iter()
+            .filter(|(n, _)| glob::matches(pattern, n))
+            .collect()
     } else {
         // Export all entries
         all_entries
@@ -140,6 +254,8 @@ fn do_export(
             notes: entry.notes.clone(),
             created_at: entry.created_at.clone(),
             updated_at: entry.updated_at.clone(),
+            locked: entry.locked,
+            favorite: entry.favorite,
         };
 
         export_entries.insert(entry_name, export_entry);
@@ -152,6 +268,11 @@ fn do_export(
         version: "2.0.0".to_string(),
         exported_at: chrono::Utc::now().to_rfc3339(),
         entries: export_entries,
+        config: if include_config {
+            Some(config::portable_settings(&db::get_database()?)?)
+        } else {
+            None
+        },
     };
 
     // Determine output directory
@@ -170,23 +291,65 @@ fn do_export(
     // Generate timestamp for filename
     let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string();
 
-    if plaintext {
+    let render = |export_data: &ExportData| {
+        if format == "csv" {
+            Ok(format_csv(export_data))
+        } else {
+            serialize(export_data, format)
+        }
+    };
+
+    if let Some(encryption) = recipients {
+        // Encrypted to public keys: no password to share
+        let data = render(&export_data)?;
+        let (encrypted, kind, count, import_hint) = match encryption {
+            RecipientEncryption::Age(keys) => (
+                recipients::age_encrypt(data.as_bytes(), keys)?,
+                "age",
+                keys.len(),
+                " --identity <KEY_FILE>",
+            ),
+            RecipientEncryption::Gpg(keys) => (
+                recipients::gpg_encrypt(data.as_bytes(), keys)?,
+                "gpg",
+                keys.len(),
+                "",
+            ),
+        };
+
+        let filename = format!("ccm-backup-{}.{}.{}", timestamp, format, kind);
+        let filepath = output_directory.join(&filename);
+        files::write_private_file(&filepath, &encrypted)?;
+
+        output::success(format!(
+            "Backup exported ({}, {} recipient(s)) to: {}",
+            kind,
+            count,
+            filepath.display()
+        ));
+        println!("   Entries: {}", export_data.entries.len());
+        if let Some(config) = &export_data.config {
+            println!("   Settings: {}", config.len());
+        }
+        println!("   Import with: ccm import {}{}", filename, import_hint);
+    } else if plaintext {
         // Plaintext export
-        let filename = format!("ccm-backup-{}.json", timestamp);
+        let filename = format!("ccm-backup-{}.{}", timestamp, format);
         let filepath = output_directory.join(&filename);
 
-        let json_data =
-            serde_json::to_string_pretty(&export_data).map_err(CcmError::Serialization)?;
+        let data = render(&export_data)?;
 
-        fs::write(&filepath, &json_data)
+        files::write_private_file(&filepath, data.as_bytes())
             .map_err(|e| CcmError::Unknown(format!("Failed to write file: {}", e)))?;
 
-        println!(
-            "{} Backup exported (unencrypted) to: {}",
-            "✅".green(),
+        output::success(format!(
+            "Backup exported (unencrypted) to: {}",
             filepath.display()
-        );
+        ));
         println!("   Entries: {}", export_data.entries.len());
+        if let Some(config) = &export_data.config {
+            println!("   Settings: {}", config.len());
+        }
         println!(
             "   {} This file contains plaintext secrets!",
             "⚠️  WARNING:".yellow()
@@ -218,34 +381,21 @@ fn do_export(
             ));
         }
 
-        // Encrypt the data
-        let json_data =
-            serde_json::to_string_pretty(&export_data).map_err(CcmError::Serialization)?;
-
-        let encrypted = encrypt_data(&json_data, &password)?;
-
-        let export_file = ExportFile {
-            format: "ccm-backup-v2".to_string(),
-            encrypted: true,
-            algorithm: "AES-256-GCM".to_string(),
-            data: encrypted,
-        };
+        let export_file = encrypt_backup(&export_data, &password)?;
 
-        let filename = format!("ccm-backup-{}.encrypted.json", timestamp);
+        let filename = format!("ccm-backup-{}.encrypted.{}", timestamp, format);
         let filepath = output_directory.join(&filename);
 
-        let file_data =
-            serde_json::to_string_pretty(&export_file).map_err(CcmError::Serialization)?;
+        let file_data = serialize(&export_file, format)?;
 
-        fs::write(&filepath, &file_data)
+        files::write_file_atomic(&filepath, file_data.as_bytes())
             .map_err(|e| CcmError::Unknown(format!("Failed to write file: {}", e)))?;
 
-        println!(
-            "{} Backup exported to: {}",
-            "✅".green(),
-            filepath.display()
-        );
+        output::success(format!("Backup exported to: {}", filepath.display()));
         println!("   Entries: {}", export_data.entries.len());
+        if let Some(config) = &export_data.config {
+            println!("   Settings: {}", config.len());
+        }
         println!(
             "   {} Keep the password safe! You'll need it to restore the backup.",
             "⚠️".yellow()
@@ -255,8 +405,255 @@ fn do_export(
     Ok(())
 }
 
+/// Entries (all when `names` is empty) with their secrets, as `ccm export`
+/// writes them
+pub fn backup_data(names: &[String]) -> Result<ExportData> {
+    let names = if names.is_empty() {
+        secrets::list_entries()?.into_keys().collect()
+    } else {
+        names.to_vec()
+    };
+    let mut entries = HashMap::new();
+    for name in names {
+        let (entry, secret) = secrets::get_entry_with_secret(&name)?;
+        entries.insert(
+            name,
+            ExportEntry {
+                metadata: entry.metadata,
+                secret: Some(secret),
+                tags: entry.tags,
+                notes: entry.notes,
+                created_at: entry.created_at,
+                updated_at: entry.updated_at,
+                locked: entry.locked,
+                favorite: entry.favorite,
+            },
+        );
+    }
+    Ok(ExportData {
+        version: "2.0.0".to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        entries,
+        config: None,
+    })
+}
+
+/// Encrypt export data with a password into a signed backup
+pub fn encrypt_backup(export_data: &ExportData, password: &str) -> Result<ExportFile> {
+    // Always JSON inside, whatever the wrapper format, so the payload stays
+    // readable by `ccm import`
+    let json_data = serde_json::to_string_pretty(export_data).map_err(CcmError::Serialization)?;
+
+    let mut export_file = ExportFile {
+        format: "ccm-backup-v2".to_string(),
+        encrypted: true,
+        algorithm: "AES-256-GCM".to_string(),
+        data: encrypt_data(&json_data, password)?,
+        signature: None,
+    };
+
+    // Sign the ciphertext so tampering is detected on import
+    export_file.signature = Some(sign_backup(
+        &export_file.format,
+        &export_file.algorithm,
+        &export_file.data,
+    )?);
+    Ok(export_file)
+}
+
+/// Decrypt a password-encrypted backup (its signature is not checked)
+pub fn decrypt_backup(file: &ExportFile, password: &str) -> Result<ExportData> {
+    if file.format != "ccm-backup-v2" {
+        return Err(CcmError::InvalidArgument(format!(
+            "Unsupported backup format: {}",
+            file.format
+        )));
+    }
+    let decrypted = decrypt_data(&file.data, password)?;
+    serde_json::from_str(&decrypted)
+        .map_err(|e| CcmError::Decryption(format!("Failed to parse decrypted data: {}", e)))
+}
+
+/// The bytes a backup signature covers
+fn signed_message(format: &str, algorithm: &str, data: &str) -> String {
+    format!("{}\n{}\n{}", format, algorithm, data)
+}
+
+/// Sign an encrypted backup with this vault's signing key
+fn sign_backup(format: &str, algorithm: &str, data: &str) -> Result<BackupSignature> {
+    let key = derive_backup_signing_key(&get_cached_master_key()?);
+    let tag = hmac_sha256(&key, signed_message(format, algorithm, data).as_bytes());
+    Ok(BackupSignature {
+        algorithm: SIGNATURE_ALGORITHM.to_string(),
+        key_id: signing_key_id(&key),
+        value: hex::encode(tag),
+    })
+}
+
+/// Check an encrypted backup's signature against this vault's signing key
+pub fn verify_backup(
+    format: &str,
+    algorithm: &str,
+    data: &str,
+    signature: &BackupSignature,
+) -> Result<SignatureStatus> {
+    if signature.algorithm != SIGNATURE_ALGORITHM {
+        return Err(CcmError::Decryption(format!(
+            "Unsupported backup signature algorithm: {}",
+            signature.algorithm
+        )));
+    }
+
+    let key = derive_backup_signing_key(&get_cached_master_key()?);
+    if signature.key_id != signing_key_id(&key) {
+        return Ok(SignatureStatus::UnknownKey);
+    }
+
+    let valid = hex::decode(&signature.value).is_ok_and(|tag| {
+        hmac_sha256_verify(
+            &key,
+            signed_message(format, algorithm, data).as_bytes(),
+            &tag,
+        )
+    });
+    Ok(if valid {
+        SignatureStatus::Valid
+    } else {
+        SignatureStatus::Tampered
+    })
+}
+
+/// Serialize export data as json, yaml or toml
+pub(crate) fn serialize<T: Serialize>(value: &T, format: &str) -> Result<String> {
+    match format {
+        "yaml" => serde_yaml::to_string(value)
+            .map_err(|e| CcmError::Unknown(format!("Failed to write YAML: {}", e))),
+        "toml" => toml::to_string_pretty(value)
+            .map_err(|e| CcmError::Unknown(format!("Failed to write TOML: {}", e))),
+        _ => serde_json::to_string_pretty(value).map_err(CcmError::Serialization),
+    }
+}
+
+/// Render export data as CSV, one row per entry sorted by name
+/// (metadata is kept as a JSON object, tags are separated by `;`)
+fn format_csv(export_data: &ExportData) -> String {
+    let mut names: Vec<&String> = export_data.entries.keys().collect();
+    names.sort();
+
+    let mut csv = csv_parser::format_csv_row(&[
+        "name",
+        "secret",
+        "tags",
+        "notes",
+        "createdAt",
+        "updatedAt",
+        "metadata",
+    ]);
+    csv.push('\n');
+
+    for name in names {
+        let entry = &export_data.entries[name];
+        let tags = entry.tags.as_deref().unwrap_or_default().join(";");
+        let metadata = serde_json::to_string(&entry.metadata).unwrap_or_default();
+        csv.push_str(&csv_parser::format_csv_row(&[
+            name,
+            entry.secret.as_deref().unwrap_or_default(),
+            &tags,
+            entry.notes.as_deref().unwrap_or_default(),
+            entry.created_at.as_deref().unwrap_or_default(),
+            entry.updated_at.as_deref().unwrap_or_default(),
+            &metadata,
+        ]));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Write one `<name>.env` file per entry, with SECRET placeholders substituted
+/// by the decrypted secret unless `with_secret` is false
+fn do_export_env(
+    name_filter: Option<&str>,
+    filter: &EntryFilter,
+    output_dir: Option<&str>,
+    with_secret: bool,
+) -> Result<()> {
+    let mut entries: Vec<(String, crate::types::Entry)> = secrets::list_entries_filtered(filter)?
+        .into_iter()
+        .filter(|(n, _)| name_filter.is_none_or(|pattern| glob::matches(pattern, n)))
+        .collect();
+
+    if entries.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "No entries found matching the criteria.".to_string(),
+        ));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let output_directory = match output_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::current_dir().map_err(|e| CcmError::Unknown(e.to_string()))?,
+    };
+
+    if !output_directory.exists() {
+        return Err(CcmError::InvalidArgument(format!(
+            "Output directory does not exist: {}",
+            output_directory.display()
+        )));
+    }
+
+    for (entry_name, entry) in &entries {
+        let mut vars: Vec<(String, String)> = if with_secret {
+            let (_, secret) = secrets::get_entry_with_secret(entry_name)?;
+            crate::env::get_env_mappings_with_secret(entry, &secret)
+                .into_iter()
+                .collect()
+        } else {
+            entry
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        };
+        vars.sort();
+
+        let filepath = output_directory.join(format!("{}.env", env_file_stem(entry_name)));
+        let content = format!(
+            "# Exported by ccm from entry '{}'\n{}",
+            entry_name,
+            dotenv::format_dotenv(&vars)
+        );
+        files::write_private_file(&filepath, content.as_bytes())?;
+
+        output::success(format!("{} → {}", entry_name.bold(), filepath.display()));
+    }
+
+    if with_secret {
+        println!(
+            "   {} These files contain plaintext secrets!",
+            "⚠️  WARNING:".yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// File name for an entry's .env export (path separators and the like replaced)
+fn env_file_stem(entry_name: &str) -> String {
+    entry_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
 /// Encrypt data using AES-256-GCM with PBKDF2 key derivation
-fn encrypt_data(data: &str, password: &str) -> Result<String> {
+pub fn encrypt_data(data: &str, password: &str) -> Result<String> {
     // Generate random salt (16 bytes) and IV (12 bytes)
     let mut salt = [0u8; 16];
     let mut iv = [0u8; 12];
@@ -265,7 +662,9 @@ fn encrypt_data(data: &str, password: &str) -> Result<String> {
 
     // Derive key from password using PBKDF2
     let mut key = [0u8; 32];
-    pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, 100_000, &mut key);
+    timings::measure(Phase::Kdf, || {
+        pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, 100_000, &mut key)
+    });
 
     // Create cipher and encrypt
     let cipher =
@@ -308,7 +707,9 @@ pub fn decrypt_data(encrypted: &str, password: &str) -> Result<String> {
 
     // Derive key from password using PBKDF2
     let mut key = [0u8; 32];
-    pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, 100_000, &mut key);
+    timings::measure(Phase::Kdf, || {
+        pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, 100_000, &mut key)
+    });
 
     // Create cipher and decrypt
     let cipher =
@@ -0,0 +1,34 @@
+// Fav command implementation
+//
+// `ccm fav NAME` pins an entry, or unpins it when it already is a favorite.
+// Favorites come first in `ccm list` (marked ★) and `ccm list --fav` shows
+// only them.
+
+use crate::secrets;
+use crate::utils::{output, Result};
+use crate::Commands;
+use colored::Colorize;
+use serde_json::json;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Fav { name } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+
+        let name = super::pick::lookup_name(&name, true)?;
+        let favorite = secrets::toggle_favorite(&name)?;
+
+        if output::is_json() {
+            output::print_json(&json!({ "name": name, "favorite": favorite }))
+        } else {
+            if favorite {
+                output::success(format!("Pinned: {} ★", name.cyan().bold()));
+            } else {
+                output::success(format!("Unpinned: {}", name.cyan().bold()));
+            }
+            Ok(())
+        }
+    } else {
+        unreachable!()
+    }
+}
@@ -1,35 +1,151 @@
 // Get command implementation
 
 use crate::secrets;
-use crate::utils::{clipboard::copy_to_clipboard, CcmError, Result};
+use crate::types::Entry;
+use crate::utils::{clipboard::copy_to_clipboard, files, output, CcmError, Result};
 use crate::Commands;
 use colored::Colorize;
+use serde_json::json;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
 
 pub async fn execute(command: Commands) -> Result<()> {
-    if let Commands::Get { name, field, copy } = command {
+    if let Commands::Get {
+        name,
+        field,
+        copy,
+        raw,
+        force_tty,
+        out,
+    } = command
+    {
+        // Refuse before prompting for the PIN, so nothing leaks onto a terminal
+        if raw && !force_tty && io::stdout().is_terminal() {
+            return Err(CcmError::InvalidArgument(
+                "--raw writes the secret to a terminal; pipe the output or add --force-tty"
+                    .to_string(),
+            ));
+        }
+
         // Ensure master key is loaded (prompts for PIN if needed)
         crate::auth::ensure_master_key_loaded().await?;
-        do_get(&name, field.as_deref(), copy)
+        let Some(name) = super::pick::resolve_name(name)? else {
+            eprintln!("Pick cancelled.");
+            return Ok(());
+        };
+        if let Some(path) = out {
+            do_get_out(&name, field.as_deref(), &path)
+        } else if raw {
+            do_get_raw(&name, field.as_deref())
+        } else if output::is_json() {
+            do_get_json(&name, field.as_deref(), copy)
+        } else {
+            do_get(&name, field.as_deref(), copy)
+        }
     } else {
         unreachable!()
     }
 }
 
-fn do_get(name: &str, field: Option<&str>, copy: bool) -> Result<()> {
-    let (entry, secret) = secrets::get_entry_with_secret(name)?;
+/// Whether a --field name refers to the secret itself
+fn is_secret_field(field: &str) -> bool {
+    matches!(
+        field.to_lowercase().as_str(),
+        "secret" | "key" | "password" | "private-key" | "api-key"
+    )
+}
+
+/// The secret, or the value of a metadata field (SECRET resolved)
+pub(crate) fn field_value(entry: &Entry, secret: String, field: Option<&str>) -> Result<String> {
+    match field {
+        None => Ok(secret),
+        Some(f) if is_secret_field(f) => Ok(secret),
+        Some(f) => entry
+            .metadata
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(f))
+            .map(|(_, v)| {
+                if v == "SECRET" {
+                    secret.clone()
+                } else {
+                    v.clone()
+                }
+            })
+            .ok_or_else(|| CcmError::InvalidArgument(format!("Field '{}' not found", f))),
+    }
+}
+
+/// Write just the value bytes to stdout: no newline, colors or decoration
+fn do_get_raw(name: &str, field: Option<&str>) -> Result<()> {
+    let (entry, secret) = secrets::use_entry(name)?;
+    let value = field_value(&entry, secret, field)?;
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(value.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Write the value to a file only the user can read, exactly as stored
+fn do_get_out(name: &str, field: Option<&str>, path: &Path) -> Result<()> {
+    let (entry, secret) = secrets::use_entry(name)?;
+    let value = field_value(&entry, secret, field)?;
+    files::write_private_file(path, value.as_bytes())?;
+
+    if output::is_json() {
+        output::print_json(&json!({ "name": name, "field": field, "path": path }))
+    } else {
+        output::success(format!(
+            "Wrote {} to {}",
+            name.cyan().bold(),
+            path.display()
+        ));
+        Ok(())
+    }
+}
+
+/// The entry (or one field) as JSON; with --copy the value is copied
+/// instead of printed
+pub(crate) fn do_get_json(name: &str, field: Option<&str>, copy: bool) -> Result<()> {
+    let (entry, secret) = secrets::use_entry(name)?;
+
+    let value = match field {
+        None => secret.clone(),
+        Some(f) if is_secret_field(f) => secret.clone(),
+        Some(f) => entry
+            .metadata
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(f))
+            .map(|(_, v)| v.clone())
+            .ok_or_else(|| CcmError::InvalidArgument(format!("Field '{}' not found", f)))?,
+    };
+    let copied = copy && copy_to_clipboard(&value);
+    let shown = if copied { None } else { Some(value) };
+
+    let document = match field {
+        Some(f) => json!({ "name": name, "field": f, "value": shown, "copied": copied }),
+        None => json!({
+            "name": name,
+            "metadata": entry.metadata,
+            "tags": entry.tags,
+            "notes": entry.notes,
+            "secret": shown,
+            "copied": copied,
+        }),
+    };
+    output::print_json(&document)
+}
+
+pub(crate) fn do_get(name: &str, field: Option<&str>, copy: bool) -> Result<()> {
+    let (entry, secret) = secrets::use_entry(name)?;
 
     if let Some(field_name) = field {
         // Get specific field
         let field_lower = field_name.to_lowercase();
-        if field_lower == "secret"
-            || field_lower == "key"
-            || field_lower == "password"
-            || field_lower == "private-key"
-            || field_lower == "api-key"
-        {
+        if is_secret_field(field_name) {
             if copy {
                 if copy_to_clipboard(&secret) {
-                    println!("{} Secret copied to clipboard", "✅".green());
+                    output::success("Secret copied to clipboard");
                 } else {
                     println!(
                         "{} Failed to copy to clipboard. Displaying instead:",
@@ -51,7 +167,7 @@ fn do_get(name: &str, field: Option<&str>, copy: bool) -> Result<()> {
             if let Some(value_str) = value {
                 if copy {
                     if copy_to_clipboard(&value_str) {
-                        println!("{} Copied to clipboard: {}", "✅".green(), field_name);
+                        output::success(format!("Copied to clipboard: {}", field_name));
                     } else {
                         println!(
                             "{} Failed to copy to clipboard. Value: {}",
@@ -105,10 +221,7 @@ fn do_get(name: &str, field: Option<&str>, copy: bool) -> Result<()> {
 
         if copy {
             if copy_to_clipboard(&secret) {
-                println!(
-                    "{} Secret copied to clipboard (not displayed for security)",
-                    "✅".green()
-                );
+                output::success("Secret copied to clipboard (not displayed for security)");
             } else {
                 println!(
                     "{} Failed to copy to clipboard. Secret: {}",
@@ -0,0 +1,90 @@
+// History command implementation
+
+use crate::secrets;
+use crate::utils::{output, Result};
+use crate::Commands;
+use colored::Colorize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::History { name, show } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        do_history(&name, show)
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_history(name: &str, show: bool) -> Result<()> {
+    let history = secrets::get_secret_history(name)?;
+
+    if output::is_json() {
+        let versions: Vec<_> = history
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, item)| {
+                json!({
+                    "version": item.version,
+                    "current": i + 1 == history.len(),
+                    "createdAt": item.created_at,
+                    "replacedAt": item.replaced_at,
+                    "fingerprint": fingerprint(&item.value),
+                    "value": show.then_some(&item.value),
+                })
+            })
+            .collect();
+        return output::print_json(&json!({ "name": name, "versions": versions }));
+    }
+
+    if history.is_empty() {
+        println!("No secret stored for '{}'", name);
+        return Ok(());
+    }
+
+    println!("{} {}", "Secret history:".bold(), name.cyan().bold());
+    println!();
+
+    let current = history.len() - 1;
+    for (i, item) in history.iter().enumerate().rev() {
+        let value = if show {
+            item.value.clone()
+        } else {
+            fingerprint(&item.value)
+        };
+        let marker = if i == current {
+            " (current)".green().to_string()
+        } else {
+            String::new()
+        };
+
+        println!("  {}{}", format!("v{}", item.version).bold(), marker);
+        println!("    Set:      {}", item.created_at.dimmed());
+        if let Some(replaced_at) = &item.replaced_at {
+            println!("    Replaced: {}", replaced_at.dimmed());
+        }
+        println!("    Value:    {}", value);
+    }
+
+    if history.len() > 1 {
+        println!();
+        println!(
+            "Restore an earlier value with: {}",
+            format!("ccm rollback {} --version <N>", name).yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// Short, non-reversible identifier for telling values apart
+fn fingerprint(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    format!(
+        "sha256:{} ({} chars)",
+        &hex::encode(digest)[..12],
+        value.chars().count()
+    )
+}
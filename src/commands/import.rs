@@ -1,21 +1,25 @@
 // Import command implementation
 
-use crate::commands::export::decrypt_data;
+use crate::commands::export::{decrypt_data, verify_backup, BackupSignature, SignatureStatus};
+use crate::presets::file::is_var_name;
 use crate::secrets;
 use crate::types::Entry;
 use crate::utils::{
     csv_parser::{
-        decode_csv_content, detect_browser_format, map_csv_to_entries, parse_csv,
-        resolve_duplicate_names, MappedEntry,
+        decode_csv_content, dedupe_logins, detect_browser_format, login_key, map_csv_to_entries,
+        parse_csv, resolve_conflicts, ConflictStrategy, MappedEntry,
     },
-    CcmError, Result,
+    dotenv::parse_dotenv,
+    output, recipients, CcmError, NamePolicy, Result,
 };
 use crate::Commands;
 use colored::Colorize;
-use dialoguer::Password;
+use dialoguer::{Password, Select};
+use indicatif::{ProgressBar, ProgressStyle};
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{self, Write};
 use std::path::Path;
 
 /// JSON export file format
@@ -25,6 +29,7 @@ struct ImportFile {
     encrypted: Option<bool>,
     algorithm: Option<String>,
     data: Option<String>,
+    signature: Option<BackupSignature>,
     // For plaintext JSON backups
     version: Option<String>,
     #[serde(rename = "exportedAt")]
@@ -48,16 +53,61 @@ struct ImportEntry {
 }
 
 pub async fn execute(command: Commands) -> Result<()> {
-    if let Commands::Import { file, format: _ } = command {
+    if let Commands::Import {
+        file,
+        format,
+        secret_var,
+        name,
+        source_tag,
+        on_conflict,
+        force,
+        force_unlock,
+        identity,
+    } = command
+    {
         // Ensure master key is loaded (prompts for PIN if needed)
         crate::auth::ensure_master_key_loaded().await?;
-        do_import(&file)
+        let env_options = EnvImportOptions { secret_var, name };
+        let options = ImportOptions {
+            source_tag,
+            on_conflict: ConflictStrategy::parse(&on_conflict).ok_or_else(|| {
+                CcmError::InvalidArgument(format!("Invalid --on-conflict value: {}", on_conflict))
+            })?,
+            force,
+            force_unlock,
+            identity,
+        };
+        do_import(&file, format.as_deref(), &env_options, &options)
     } else {
         unreachable!()
     }
 }
 
-fn do_import(file_path: &str) -> Result<()> {
+/// Options that apply to every import format
+struct ImportOptions {
+    source_tag: Option<String>,
+    on_conflict: ConflictStrategy,
+    /// Overwrite without asking
+    force: bool,
+    /// Overwrite locked entries too
+    force_unlock: bool,
+    /// age identity file for age-encrypted files
+    identity: Option<String>,
+}
+
+/// Options that only apply to .env imports
+struct EnvImportOptions {
+    secret_var: Option<String>,
+    name: Option<String>,
+}
+
+fn do_import(
+    file_path: &str,
+    format: Option<&str>,
+    env_options: &EnvImportOptions,
+    options: &ImportOptions,
+) -> Result<()> {
+    let source_tag = options.source_tag.as_deref();
     // 1. Validate file exists
     let path = Path::new(file_path);
     if !path.exists() {
@@ -68,13 +118,34 @@ fn do_import(file_path: &str) -> Result<()> {
     }
 
     // 2. Read file content
-    let file_bytes =
+    let mut file_bytes =
         fs::read(path).map_err(|e| CcmError::Unknown(format!("Failed to read file: {}", e)))?;
 
+    if recipients::is_age_file(&file_bytes) {
+        println!("🔒 age-encrypted file detected");
+        let identity = options.identity.as_deref().ok_or_else(|| {
+            CcmError::InvalidArgument(
+                "This file is encrypted with age. Pass your identity file with --identity <FILE>"
+                    .to_string(),
+            )
+        })?;
+        file_bytes = recipients::age_decrypt(&file_bytes, Path::new(identity))?;
+    } else if recipients::is_gpg_file(&file_bytes) {
+        println!("🔒 OpenPGP-encrypted file detected, decrypting with gpg");
+        file_bytes = recipients::gpg_decrypt(&file_bytes)?;
+    }
+
     let file_content = decode_csv_content(&file_bytes);
 
     // 3. Auto-detect format and parse
-    let mapped_entries: Vec<MappedEntry> = if file_content.trim().starts_with('{') {
+    let is_env = match format {
+        Some(f) => f == "env",
+        None => is_dotenv_path(path),
+    };
+    let mapped_entries: Vec<MappedEntry> = if is_env {
+        println!("📄 Detected format: .env file");
+        import_from_dotenv(path, &file_content, env_options)?
+    } else if format == Some("json") || (format.is_none() && file_content.trim().starts_with('{')) {
         // JSON format
         println!("📄 Detected format: JSON backup");
         import_from_json(&file_content)?
@@ -95,6 +166,25 @@ fn do_import(file_path: &str) -> Result<()> {
     // 4. Validate entries
     let (valid, invalid) = validate_import_entries(&mapped_entries);
 
+    // Bring names in line with the name policy (browser titles often contain
+    // characters it rejects)
+    let policy = crate::db::get_database()?.get_name_policy()?;
+    let (valid, normalized_list) = apply_name_policy(valid, &policy);
+
+    if !normalized_list.is_empty() {
+        println!(
+            "ℹ️  {} names adjusted to the name policy:",
+            normalized_list.len()
+        );
+        for (original, renamed) in normalized_list.iter().take(5) {
+            println!("   {} → {}", original, renamed);
+        }
+        if normalized_list.len() > 5 {
+            println!("   ... and {} more", normalized_list.len() - 5);
+        }
+        println!();
+    }
+
     if !invalid.is_empty() {
         println!(
             "{} {} entries failed validation:",
@@ -116,52 +206,159 @@ fn do_import(file_path: &str) -> Result<()> {
         ));
     }
 
-    // 5. Handle duplicates
+    // 5. Handle duplicates: the same login (domain + username + password) is
+    // merged rather than imported again under a suffixed name
+    let mut valid = valid;
+    if let Some(tag) = source_tag {
+        for entry in &mut valid {
+            if !entry.tags.iter().any(|t| t == tag) {
+                entry.tags.push(tag.to_string());
+            }
+        }
+    }
+    let (valid, merged_count) = dedupe_logins(valid);
+
     let existing_entries = secrets::list_entries()?;
+    let (valid, already_present) = skip_existing_logins(valid, &existing_entries, source_tag)?;
+
+    if merged_count > 0 || !already_present.is_empty() {
+        println!(
+            "ℹ️  {} duplicate logins merged, {} already in the vault",
+            merged_count,
+            already_present.len()
+        );
+        for name in already_present.iter().take(5) {
+            println!("   = {}", name);
+        }
+        if already_present.len() > 5 {
+            println!("   ... and {} more", already_present.len() - 5);
+        }
+        println!();
+    }
+
+    if valid.is_empty() {
+        output::success("Nothing new to import.");
+        return Ok(());
+    }
+
     let existing_names: HashSet<String> = existing_entries.keys().cloned().collect();
 
-    let (resolved_entries, renamed_count, renamed_list) =
-        resolve_duplicate_names(valid, &existing_names);
+    let mut resolution = resolve_conflicts(valid, &existing_names, options.on_conflict);
+    let renamed_count = resolution.renamed.len();
 
     if renamed_count > 0 {
         println!("ℹ️  {} duplicate names auto-renamed:", renamed_count);
-        for (original, renamed) in renamed_list.iter().take(5) {
+        for (original, renamed) in resolution.renamed.iter().take(5) {
             println!("   {} → {}", original, renamed);
         }
-        if renamed_list.len() > 5 {
-            println!("   ... and {} more", renamed_list.len() - 5);
+        if resolution.renamed.len() > 5 {
+            println!("   ... and {} more", resolution.renamed.len() - 5);
         }
         println!();
     }
 
+    if !resolution.skipped.is_empty() {
+        println!(
+            "ℹ️  {} entries already exist and were skipped",
+            resolution.skipped.len()
+        );
+        println!();
+    }
+
+    // Replacing secrets is destructive (the old value only survives in history)
+    if !resolution.overwrites.is_empty() && !options.force {
+        println!(
+            "{} {} existing entries will be overwritten:",
+            "⚠️".yellow(),
+            resolution.overwrites.len()
+        );
+        for entry in resolution.overwrites.iter().take(10) {
+            println!("   - {}", entry.name);
+        }
+        if resolution.overwrites.len() > 10 {
+            println!("   ... and {} more", resolution.overwrites.len() - 10);
+        }
+        print!("Replace their secrets and metadata? (y/N): ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        if !input.trim().eq_ignore_ascii_case("y") && !input.trim().eq_ignore_ascii_case("yes") {
+            println!("Keeping the existing entries.");
+            let declined = std::mem::take(&mut resolution.overwrites);
+            resolution
+                .skipped
+                .extend(declined.into_iter().map(|e| e.name));
+        }
+        println!();
+    }
+
+    let total =
+        resolution.new_entries.len() + resolution.overwrites.len() + resolution.merges.len();
+    if total == 0 {
+        output::success("Nothing new to import.");
+        return Ok(());
+    }
+
     // 6. Import entries
     println!("💾 Importing entries...\n");
 
     let mut success_count = 0;
     let mut failed_count = 0;
 
-    for entry in &resolved_entries {
-        match import_single_entry(entry) {
+    // New entries are added in bulk: one transaction, so a failure
+    // leaves none of them behind
+    if !resolution.new_entries.is_empty() {
+        let count = resolution.new_entries.len();
+        match import_new_entries(&resolution.new_entries) {
             Ok(()) => {
-                success_count += 1;
-                println!("{} Imported: {}", "✅".green(), entry.name);
+                success_count += count;
+                output::success(format!("Imported {} new entries", count));
             }
             Err(e) => {
-                failed_count += 1;
-                println!("{} Failed to import {}: {}", "❌".red(), entry.name, e);
+                failed_count += count;
+                println!(
+                    "{} Failed to import {} new entries (nothing was written): {}",
+                    "❌".red(),
+                    count,
+                    e
+                );
             }
         }
     }
 
+    let mut report = |entry: &MappedEntry, verb: &str, result: Result<()>| match result {
+        Ok(()) => {
+            success_count += 1;
+            output::success(format!("{}: {}", verb, entry.name));
+        }
+        Err(e) => {
+            failed_count += 1;
+            println!("{} Failed to import {}: {}", "❌".red(), entry.name, e);
+        }
+    };
+
+    for entry in &resolution.overwrites {
+        report(
+            entry,
+            "Overwritten",
+            overwrite_entry(entry, options.force_unlock),
+        );
+    }
+    for entry in &resolution.merges {
+        report(entry, "Merged", merge_entry(entry));
+    }
+
     // 7. Report results
     println!();
     if failed_count > 0 || success_count == 0 {
         println!("{} Import completed with errors:", "⚠️".yellow());
         println!("   Successfully imported: {} entries", success_count);
         println!("   Failed: {} entries", failed_count);
-        println!("   Total: {} entries", resolved_entries.len());
+        println!("   Total: {} entries", total);
     } else {
-        println!("{} Import completed successfully!", "✅".green());
+        output::success("Import completed successfully!");
         println!("   Imported: {} entries", success_count);
     }
     if !invalid.is_empty() {
@@ -170,10 +367,74 @@ fn do_import(file_path: &str) -> Result<()> {
     if renamed_count > 0 {
         println!("   Renamed: {} entries (duplicate names)", renamed_count);
     }
+    if !resolution.skipped.is_empty() {
+        println!(
+            "   Skipped: {} entries (already exist)",
+            resolution.skipped.len()
+        );
+    }
+    if merged_count > 0 || !already_present.is_empty() {
+        println!(
+            "   Merged: {} entries (same login)",
+            merged_count + already_present.len()
+        );
+    }
 
     Ok(())
 }
 
+/// Drop entries whose login (domain + username) already exists in the vault
+/// with the same password, tagging the existing entry with `source_tag`
+/// Returns the remaining entries and the names of the matching existing entries
+fn skip_existing_logins(
+    entries: Vec<MappedEntry>,
+    existing: &HashMap<String, Entry>,
+    source_tag: Option<&str>,
+) -> Result<(Vec<MappedEntry>, Vec<String>)> {
+    let mut by_login: HashMap<(String, String), Vec<&str>> = HashMap::new();
+    for (name, entry) in existing {
+        if let Some(key) = login_key(&entry.metadata) {
+            by_login.entry(key).or_default().push(name);
+        }
+    }
+
+    let mut remaining = Vec::new();
+    let mut present = Vec::new();
+
+    for mapped in entries {
+        let candidates = login_key(&mapped.metadata)
+            .and_then(|key| by_login.get(&key))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut matched = None;
+        for name in candidates {
+            if secrets::secret_matches(name, &mapped.secret)? {
+                matched = Some(secrets::get_entry(name)?);
+                break;
+            }
+        }
+
+        match matched {
+            Some(mut entry) => {
+                let name = entry.name.clone();
+                let mut tags = entry.tags.take().unwrap_or_default();
+                if let Some(tag) = source_tag.filter(|t| !tags.iter().any(|x| x == t)) {
+                    tags.push(tag.to_string());
+                    entry.tags = Some(tags);
+                    secrets::update_entry(&name, entry)?;
+                }
+                if !present.contains(&name) {
+                    present.push(name);
+                }
+            }
+            None => remaining.push(mapped),
+        }
+    }
+
+    Ok((remaining, present))
+}
+
 /// Import from JSON backup file
 fn import_from_json(content: &str) -> Result<Vec<MappedEntry>> {
     let json_data: ImportFile = serde_json::from_str(content)
@@ -183,6 +444,12 @@ fn import_from_json(content: &str) -> Result<Vec<MappedEntry>> {
     if json_data.encrypted == Some(true) {
         if let Some(encrypted_data) = &json_data.data {
             println!("🔒 Encrypted backup detected");
+            check_backup_signature(
+                json_data.format.as_deref().unwrap_or_default(),
+                json_data.algorithm.as_deref().unwrap_or_default(),
+                encrypted_data,
+                json_data.signature.as_ref(),
+            )?;
 
             let password = Password::new()
                 .with_prompt("Decryption password")
@@ -202,6 +469,56 @@ fn import_from_json(content: &str) -> Result<Vec<MappedEntry>> {
     map_json_entries(&json_data)
 }
 
+/// Verify an encrypted backup's signature, asking before importing one that
+/// was modified after export
+pub fn check_backup_signature(
+    format: &str,
+    algorithm: &str,
+    data: &str,
+    signature: Option<&BackupSignature>,
+) -> Result<()> {
+    let Some(signature) = signature else {
+        println!("ℹ️  Backup is not signed; its integrity cannot be verified");
+        return Ok(());
+    };
+
+    let status = verify_backup(format, algorithm, data, signature)?;
+    match status {
+        SignatureStatus::Valid => output::success("Backup signature verified"),
+        SignatureStatus::UnknownKey => println!(
+            "{} Backup was signed by another vault (key {}); its integrity cannot be verified here",
+            "⚠️".yellow(),
+            signature.key_id
+        ),
+        SignatureStatus::Tampered => {
+            eprintln!();
+            eprintln!("{}", "🚨 WARNING: BACKUP SIGNATURE MISMATCH".red().bold());
+            eprintln!(
+                "{}",
+                "This backup was modified after it was exported by this vault.".red()
+            );
+            eprintln!(
+                "{}",
+                "It may have been tampered with. Only continue if you know why.".red()
+            );
+            eprintln!();
+            print!("Import it anyway? (y/N): ");
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            if !input.trim().eq_ignore_ascii_case("y") && !input.trim().eq_ignore_ascii_case("yes")
+            {
+                return Err(CcmError::Decryption(
+                    "Import aborted: backup signature mismatch".to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Map JSON entries to MappedEntry
 fn map_json_entries(data: &ImportFile) -> Result<Vec<MappedEntry>> {
     let entries = data.entries.as_ref().ok_or_else(|| {
@@ -233,12 +550,126 @@ fn map_json_entries(data: &ImportFile) -> Result<Vec<MappedEntry>> {
             entry_type: entry.entry_type.clone(),
             secret,
             metadata,
+            tags: entry.tags.clone().unwrap_or_default(),
+            notes: entry.notes.clone(),
         });
     }
 
     Ok(mapped)
 }
 
+/// Whether a path looks like a .env file (.env, .env.local, prod.env)
+fn is_dotenv_path(path: &Path) -> bool {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    file_name == ".env"
+        || file_name.starts_with(".env.")
+        || path.extension().map(|e| e == "env").unwrap_or(false)
+}
+
+/// Default entry name for a .env file
+/// `.env` takes the directory name, `.env.production` becomes `<dir>-production`,
+/// `prod.env` becomes `prod`
+fn dotenv_entry_name(path: &Path) -> String {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let dir_name = || {
+        path.canonicalize()
+            .ok()
+            .and_then(|p| {
+                p.parent()
+                    .and_then(|d| d.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "env".to_string())
+    };
+
+    if file_name == ".env" {
+        dir_name()
+    } else if let Some(suffix) = file_name.strip_prefix(".env.") {
+        format!("{}-{}", dir_name(), suffix)
+    } else {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "env".to_string())
+    }
+}
+
+/// Import a .env file as a single entry mapping every variable
+fn import_from_dotenv(
+    path: &Path,
+    content: &str,
+    options: &EnvImportOptions,
+) -> Result<Vec<MappedEntry>> {
+    let vars = parse_dotenv(content)?;
+    if vars.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let keys: Vec<&str> = vars.iter().map(|(k, _)| k.as_str()).collect();
+    let secret_var = match &options.secret_var {
+        Some(var) => {
+            if !keys.contains(&var.as_str()) {
+                return Err(CcmError::InvalidArgument(format!(
+                    "Variable '{}' not found in {}. Available: {}",
+                    var,
+                    path.display(),
+                    keys.join(", ")
+                )));
+            }
+            var.clone()
+        }
+        None => {
+            // Preselect the first variable that looks like a credential
+            let default = keys
+                .iter()
+                .position(|k| {
+                    let upper = k.to_uppercase();
+                    ["KEY", "TOKEN", "SECRET", "PASSWORD"]
+                        .iter()
+                        .any(|hint| upper.contains(hint))
+                })
+                .unwrap_or(0);
+            let selection = Select::new()
+                .with_prompt("Which variable holds the secret?")
+                .items(&keys)
+                .default(default)
+                .interact()?;
+            keys[selection].to_string()
+        }
+    };
+
+    let mut secret = String::new();
+    let mut metadata = HashMap::new();
+    for (key, value) in vars {
+        if key == secret_var {
+            secret = value;
+            metadata.insert(key, "SECRET".to_string());
+        } else {
+            metadata.insert(key, value);
+        }
+    }
+
+    let name = options
+        .name
+        .clone()
+        .unwrap_or_else(|| dotenv_entry_name(path));
+
+    Ok(vec![MappedEntry {
+        name,
+        entry_type: "env".to_string(),
+        secret,
+        metadata,
+        tags: Vec::new(),
+        notes: None,
+    }])
+}
+
 /// Import from CSV file
 fn import_from_csv(content: &str) -> Result<Vec<MappedEntry>> {
     let rows = parse_csv(content);
@@ -279,19 +710,123 @@ fn validate_import_entries(entries: &[MappedEntry]) -> (Vec<MappedEntry>, Vec<(S
             continue;
         }
 
+        // Variable names end up in shell code (`ccm use --shell`)
+        if let Some(key) = entry.metadata.keys().find(|key| !is_var_name(key)) {
+            invalid.push((
+                entry.name.clone(),
+                format!("Invalid variable name '{}'", key),
+            ));
+            continue;
+        }
+
         valid.push(entry.clone());
     }
 
     (valid, invalid)
 }
 
-/// Import a single entry
-fn import_single_entry(mapped: &MappedEntry) -> Result<()> {
-    // Create unified Entry with metadata as env var mappings
-    let entry = Entry::new(mapped.name.clone(), mapped.metadata.clone());
+/// Normalize entry names, sanitizing any that still violate the policy
+/// Returns the entries and the (original, new) pairs that changed
+fn apply_name_policy(
+    entries: Vec<MappedEntry>,
+    policy: &NamePolicy,
+) -> (Vec<MappedEntry>, Vec<(String, String)>) {
+    let mut changed = Vec::new();
+
+    let entries = entries
+        .into_iter()
+        .map(|mut entry| {
+            let mut name = policy.normalize(&entry.name);
+            if policy.validate(&name).is_err() {
+                name = policy.sanitize(&name);
+            }
+            if name.is_empty() {
+                name = "imported".to_string();
+            }
+            if name != entry.name {
+                changed.push((entry.name.clone(), name.clone()));
+                entry.name = name;
+            }
+            entry
+        })
+        .collect();
 
-    // Save entry
-    secrets::add_entry(&mapped.name, entry, &mapped.secret)?;
+    (entries, changed)
+}
+
+/// Replace an existing entry's metadata, tags, notes and secret; a locked
+/// entry needs `force_unlock` and stays locked
+fn overwrite_entry(mapped: &MappedEntry, force_unlock: bool) -> Result<()> {
+    secrets::ensure_unlocked(&mapped.name, force_unlock)?;
+    let existing = secrets::get_entry(&mapped.name)?;
+
+    let mut entry = Entry::new(mapped.name.clone(), mapped.metadata.clone());
+    entry.tags = (!mapped.tags.is_empty()).then(|| mapped.tags.clone());
+    entry.notes = mapped.notes.clone();
+    entry.created_at = existing.created_at;
+    entry.locked = existing.locked;
+    entry.favorite = existing.favorite;
+
+    secrets::update_entry(&mapped.name, entry)?;
+    secrets::update_secret(&mapped.name, &mapped.secret)?;
 
     Ok(())
 }
+
+/// Merge an imported entry into the existing one, keeping its secret
+/// Existing metadata values win; missing keys and new tags are added,
+/// and notes are only filled in when the existing entry has none
+fn merge_entry(mapped: &MappedEntry) -> Result<()> {
+    let mut entry = secrets::get_entry(&mapped.name)?;
+
+    for (key, value) in &mapped.metadata {
+        entry
+            .metadata
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+
+    let mut tags = entry.tags.take().unwrap_or_default();
+    for tag in &mapped.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    entry.tags = (!tags.is_empty()).then_some(tags);
+
+    if entry.notes.as_deref().is_none_or(str::is_empty) {
+        entry.notes = mapped.notes.clone();
+    }
+
+    secrets::update_entry(&mapped.name, entry)
+}
+
+/// Add all new entries in a single transaction, showing progress while
+/// their secrets are encrypted
+fn import_new_entries(mapped: &[MappedEntry]) -> Result<()> {
+    let entries = mapped
+        .iter()
+        .map(|m| {
+            // Create unified Entry with metadata as env var mappings
+            let mut entry = Entry::new(m.name.clone(), m.metadata.clone());
+            if !m.tags.is_empty() {
+                entry.tags = Some(m.tags.clone());
+            }
+            entry.notes = m.notes.clone();
+            (entry, m.secret.clone())
+        })
+        .collect();
+
+    // Hidden automatically when stderr is not a terminal
+    let progress = ProgressBar::new(mapped.len() as u64);
+    progress.set_style(
+        ProgressStyle::with_template("{spinner} [{bar:40}] {pos}/{len} entries ({eta})")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+
+    let result = secrets::add_entries(entries, || progress.inc(1));
+    progress.finish_and_clear();
+
+    result
+}
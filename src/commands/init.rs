@@ -0,0 +1,148 @@
+// Init command implementation
+//
+// First-run setup: checks the OS keyring, offers to set a PIN, and creates
+// entries from presets with the user's real API keys.
+
+use crate::auth::pin;
+use crate::presets::{self, Preset};
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use dialoguer::{Confirm, Input, MultiSelect, Password};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Init = command {
+        do_init().await
+    } else {
+        unreachable!()
+    }
+}
+
+async fn do_init() -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        return Err(CcmError::InvalidArgument(
+            "ccm init is interactive; run it in a terminal (or use 'ccm add' in scripts)"
+                .to_string(),
+        ));
+    }
+
+    println!("{}", "CCM setup".bold().underline());
+    println!();
+
+    // 1. Keyring: the master key lives there, nothing works without it
+    println!("{} {}", "1.".bold(), "OS keyring".bold());
+    crate::secrets::master_key::check_os_secret_service_available()?;
+    output::success("OS secret service is available");
+    println!();
+
+    // 2. PIN
+    println!("{} {}", "2.".bold(), "PIN".bold());
+    if pin::has_pin()? {
+        println!("   A PIN is already set");
+        crate::auth::ensure_master_key_loaded().await?;
+    } else {
+        println!("   Without a PIN, anyone with access to your user account can read the vault.");
+        let set_pin = Confirm::new()
+            .with_prompt("Set a PIN now?")
+            .default(true)
+            .interact()?;
+        if set_pin {
+            super::auth::set_new_pin(None).await?;
+        } else {
+            crate::auth::ensure_master_key_loaded().await?;
+            println!("   Set one later with: {}", "ccm auth set".yellow());
+        }
+    }
+    println!();
+
+    // 3. Entries from presets
+    println!("{} {}", "3.".bold(), "Entries".bold());
+    let presets = presets::list_presets();
+    let labels: Vec<String> = presets
+        .iter()
+        .map(|p| format!("{} - {}", p.name, p.description))
+        .collect();
+    let chosen = MultiSelect::new()
+        .with_prompt("Which providers do you use? (space to select, enter to confirm)")
+        .items(&labels)
+        .interact()?;
+
+    let mut created = Vec::new();
+    for index in chosen {
+        if let Some(name) = add_from_preset(&presets[index])? {
+            created.push(name);
+        }
+    }
+    println!();
+
+    if created.is_empty() {
+        println!(
+            "No entries created. Add one any time with: {}",
+            "ccm add".yellow()
+        );
+    } else {
+        output::success(format!("Setup complete: {}", created.join(", ")));
+        println!("   Activate an entry with: ccm use {}", created[0]);
+    }
+
+    Ok(())
+}
+
+/// Ask for a name and the key(s) and create the entry; None when skipped
+fn add_from_preset(preset: &Preset) -> Result<Option<String>> {
+    println!();
+    println!("{} {}", "Preset:".bold(), preset.name.cyan().bold());
+
+    let policy = crate::db::get_database()?.get_name_policy()?;
+    let name: String = Input::new()
+        .with_prompt("Entry name")
+        .default(preset.name.clone())
+        .interact_text()?;
+    let name = policy.normalize(&name);
+    policy.validate(&name)?;
+    if secrets::get_entry(&name).is_ok() {
+        println!(
+            "{} Entry '{}' already exists, skipping",
+            "⚠️".yellow(),
+            name
+        );
+        return Ok(None);
+    }
+
+    let secret_field = preset.secret_field();
+    let mut metadata = HashMap::new();
+    let mut secret = None;
+    // Other required fields (e.g. the AWS access key ID) before the secret
+    let mut fields: Vec<_> = preset.env_mapping.iter().collect();
+    fields.sort_by_key(|(field, _)| (Some(field.as_str()) == secret_field, field.as_str()));
+    for (field, var) in fields {
+        if Some(field.as_str()) == secret_field {
+            let value = Password::new()
+                .with_prompt(format!("{} (hidden, empty to skip)", var))
+                .allow_empty_password(true)
+                .interact()?;
+            if value.trim().is_empty() {
+                println!("   Skipped {}", preset.name);
+                return Ok(None);
+            }
+            secret = Some(value.trim().to_string());
+            metadata.insert(var.clone(), "SECRET".to_string());
+        } else if preset.required_fields.contains(field) {
+            let value: String = Input::new().with_prompt(var.as_str()).interact_text()?;
+            metadata.insert(var.clone(), value.trim().to_string());
+        } else if let Some(default) = preset.default_fields.get(field) {
+            metadata.insert(var.clone(), default.clone());
+        }
+    }
+
+    let Some(secret) = secret else {
+        return Ok(None);
+    };
+    secrets::add_entry(&name, Entry::new(name.clone(), metadata), &secret)?;
+    output::success(format!("Added entry: {}", name.cyan().bold()));
+    Ok(Some(name))
+}
@@ -0,0 +1,88 @@
+// Launch command implementation
+//
+// `ccm launch <ENTRY> <TOOL>` starts an AI CLI with an entry's key, base URL
+// and model in its environment (translated for the tool, see
+// `crate::launch`). Only the launched process gets the variables; the shell
+// is left alone, and the tool's own variables set in it are not passed on.
+
+use crate::env;
+use crate::launch::{self, ToolProfile};
+use crate::secrets;
+use crate::secrets::master_key::get_cached_master_key;
+use crate::secrets::quick_access;
+use crate::types::Entry;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use std::collections::HashMap;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Launch { name, tool, args } = command {
+        let tool = launch::tool_profile(&tool)?;
+        let (entry, secret) = entry_with_secret(&name).await?;
+
+        let env_vars = env::get_env_mappings_with_secret(&entry, &secret);
+        let (env_vars, mut tool_args) = launch::tool_env(&tool, &entry.metadata, &env_vars);
+        tool_args.extend(args);
+
+        if !output::is_quiet() {
+            let mut names: Vec<&String> = env_vars.keys().collect();
+            names.sort();
+            let names: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+            eprintln!(
+                "{} Launching {} with '{}' ({})",
+                "🚀".cyan(),
+                tool.name.bold(),
+                name,
+                names.join(", ")
+            );
+        }
+        launch_tool(&tool, &tool_args, &env_vars)
+    } else {
+        unreachable!()
+    }
+}
+
+/// An entry and its secret: from quick access while the vault is locked,
+/// else from the vault (unlocking it if needed)
+pub(crate) async fn entry_with_secret(name: &str) -> Result<(Entry, String)> {
+    if matches!(get_cached_master_key(), Err(CcmError::PinRequired)) {
+        if let Some(found) = quick_access::get(name)? {
+            return Ok(found);
+        }
+        crate::auth::ensure_master_key_loaded().await?;
+    }
+    secrets::use_entry(name)
+}
+
+/// Replace ccm with the tool (Unix); elsewhere run it and pass its exit code on
+fn launch_tool(
+    tool: &ToolProfile,
+    args: &[String],
+    env_vars: &HashMap<String, String>,
+) -> Result<()> {
+    let mut command = launch::tool_command(tool, args, env_vars);
+
+    let not_found = |e: std::io::Error| {
+        CcmError::Unknown(format!(
+            "Failed to start {} ({}): {}. Is it installed and on PATH?",
+            tool.name, tool.program, e
+        ))
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // exec only returns on failure
+        Err(not_found(command.exec()))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = command.status().map_err(not_found)?;
+        if !status.success() {
+            std::process::exit(status.code().unwrap_or(1));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+// Lint command implementation
+
+use crate::presets::{self, LintFix};
+use crate::secrets;
+use crate::utils::{glob, output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Lint { name, fix } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        do_lint(name.as_deref(), fix)
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_lint(pattern: Option<&str>, fix: bool) -> Result<()> {
+    let mut entries: Vec<_> = secrets::list_entries()?
+        .into_iter()
+        .filter(|(n, _)| pattern.is_none_or(|p| glob::matches(p, n)))
+        .collect();
+
+    if entries.is_empty() {
+        return Err(match pattern {
+            Some(p) if !glob::is_pattern(p) => CcmError::EntryNotFound(p.to_string()),
+            _ => CcmError::InvalidArgument("No entries found matching the criteria.".to_string()),
+        });
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut issue_count = 0;
+    let mut fixed_count = 0;
+    let mut fixable_count = 0;
+
+    for (name, mut entry) in entries {
+        let issues = presets::lint_entry(&name, &entry.metadata);
+        if issues.is_empty() {
+            continue;
+        }
+
+        println!("{}", name.cyan().bold());
+        for issue in &issues {
+            println!("  {} {}", "⚠️".yellow(), issue.message);
+            match &issue.fix {
+                Some(LintFix::SetValue(value)) => {
+                    println!("     💡 fix: {} = {}", issue.var, value.green())
+                }
+                Some(LintFix::Rename(new_var)) => {
+                    println!("     💡 fix: rename {} → {}", issue.var, new_var.green())
+                }
+                None => {}
+            }
+        }
+        issue_count += issues.len();
+
+        let fixable = issues.iter().filter(|i| i.fix.is_some()).count();
+        if fix && fixable > 0 {
+            let applied = presets::apply_lint_fixes(&mut entry.metadata, &issues);
+            secrets::update_entry(&name, entry)?;
+            fixed_count += applied;
+            println!("  {} Applied {} fix(es)", "✅".green(), applied);
+        } else {
+            fixable_count += fixable;
+        }
+        println!();
+    }
+
+    if issue_count == 0 {
+        output::success("No issues found.");
+    } else if fix {
+        println!("Found {} issue(s), fixed {}.", issue_count, fixed_count);
+    } else {
+        println!("Found {} issue(s).", issue_count);
+        if fixable_count > 0 {
+            println!(
+                "Run {} to apply {} suggested fix(es).",
+                "ccm lint --fix".yellow(),
+                fixable_count
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -1,8 +1,12 @@
 // List command implementation
 
+use crate::db::{query, EntryFilter};
 use crate::secrets;
-use crate::utils::Result;
+use crate::types::Entry;
+use crate::utils::tree::Tree;
+use crate::utils::{glob, Result};
 use crate::Commands;
+use chrono::{DateTime, Utc};
 use colored::Colorize;
 use serde::Serialize;
 use std::collections::HashMap;
@@ -15,62 +19,144 @@ enum ListFormat {
     Json,
     Quieter,
     Verbose,
+    /// Groups as a tree, collapsed below the given depth
+    Tree(Option<usize>),
 }
 
 pub async fn execute(command: Commands) -> Result<()> {
     if let Commands::List {
         verbose,
-        json,
         json_alias,
         table: _,
         table_alias: _,
         quieter,
         quieter_alias,
+        tags,
+        untagged,
+        fav,
+        group,
+        tree,
+        depth,
+        filter,
+        where_expr,
+        show_domain,
+        sort,
     } = command
     {
         // Determine format
-        let format = if json || json_alias {
+        let format = if json_alias || crate::utils::output::is_json() {
             ListFormat::Json
         } else if quieter || quieter_alias {
             ListFormat::Quieter
         } else if verbose {
             ListFormat::Verbose
+        } else if tree {
+            ListFormat::Tree(depth.map(|d| d as usize))
         } else {
             // Default to table (even if --table/--tb not specified)
             ListFormat::Table
         };
 
-        do_list(format)
+        let (name_glob, name_contains) = match filter {
+            Some(f) if glob::is_pattern(&f) => (Some(f), None),
+            other => (None, other),
+        };
+        let filter = EntryFilter {
+            name_contains,
+            name_glob,
+            tags,
+            untagged,
+            favorite: fav,
+            group,
+            expr: where_expr.as_deref().map(query::parse).transpose()?,
+            ..EntryFilter::default()
+        };
+
+        do_list(format, &filter, show_domain, sort == "recent")
     } else {
         unreachable!()
     }
 }
 
-fn do_list(format: ListFormat) -> Result<()> {
-    let entries = secrets::list_entries()?;
+fn do_list(
+    format: ListFormat,
+    filter: &EntryFilter,
+    show_domain: bool,
+    recent: bool,
+) -> Result<()> {
+    let entries = secrets::list_entries_filtered(filter)?;
 
     if entries.is_empty() {
-        if format == ListFormat::Json {
-            println!("[]");
-        } else {
-            println!("No entries found.");
+        match format {
+            ListFormat::Json => println!("[]"),
+            // Names only: nothing, so scripts and completions get an empty list
+            ListFormat::Quieter => {}
+            _ => println!("No entries found. Run 'ccm init' to add your first ones."),
         }
         return Ok(());
     }
 
+    let mut sorted = sort_entries(&entries, recent);
+    if matches!(format, ListFormat::Table | ListFormat::Verbose) {
+        // Favorites first; the sort is stable, so each part keeps its order
+        sorted.sort_by_key(|(_, entry)| !entry.favorite);
+    }
     match format {
-        ListFormat::Json => list_json(&entries),
-        ListFormat::Quieter => list_quieter(&entries),
-        ListFormat::Verbose => list_verbose(&entries),
-        ListFormat::Table => list_table(&entries),
+        ListFormat::Json => list_json(&sorted, show_domain),
+        ListFormat::Quieter => list_quieter(&sorted),
+        ListFormat::Verbose => list_verbose(&sorted, show_domain),
+        ListFormat::Table => list_table(&sorted, show_domain),
+        ListFormat::Tree(depth) => list_tree(&entries, depth),
+    }
+}
+
+/// Entries by name, or most recently used first (never used last)
+fn sort_entries(entries: &HashMap<String, Entry>, recent: bool) -> Vec<(&String, &Entry)> {
+    let mut sorted: Vec<(&String, &Entry)> = entries.iter().collect();
+    if recent {
+        // Timestamps are fixed-width UTC, so they compare as text
+        sorted.sort_by(|a, b| {
+            b.1.last_used_at
+                .cmp(&a.1.last_used_at)
+                .then_with(|| a.0.cmp(b.0))
+        });
+    } else {
+        sorted.sort_by(|a, b| a.0.cmp(b.0));
+    }
+    sorted
+}
+
+/// How long ago an entry was last used ("3d ago", "never")
+fn last_used_label(entry: &Entry, now: DateTime<Utc>) -> String {
+    let Some(used) = entry
+        .last_used_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+    else {
+        return "never".to_string();
+    };
+
+    let age = now.signed_duration_since(used);
+    if age.num_minutes() < 1 {
+        "just now".to_string()
+    } else if age.num_hours() < 1 {
+        format!("{}m ago", age.num_minutes())
+    } else if age.num_days() < 1 {
+        format!("{}h ago", age.num_hours())
+    } else if age.num_days() < 60 {
+        format!("{}d ago", age.num_days())
+    } else {
+        used.format("%Y-%m-%d").to_string()
     }
 }
 
 /// JSON format output
-fn list_json(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
+fn list_json(entries: &[(&String, &Entry)], show_domain: bool) -> Result<()> {
     #[derive(Serialize)]
     struct JsonEntry {
         name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        domain: Option<String>,
         metadata: HashMap<String, String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         tags: Option<Vec<String>>,
@@ -80,24 +166,31 @@ fn list_json(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
         created_at: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         updated_at: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_used_at: Option<String>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        locked: bool,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        favorite: bool,
     }
 
     let mut result: Vec<JsonEntry> = Vec::new();
 
     for (name, entry) in entries {
         result.push(JsonEntry {
-            name: name.clone(),
+            name: (*name).clone(),
+            domain: entry.domain().filter(|_| show_domain),
             metadata: entry.metadata.clone(),
             tags: entry.tags.clone(),
             notes: entry.notes.clone(),
             created_at: entry.created_at.clone(),
             updated_at: entry.updated_at.clone(),
+            last_used_at: entry.last_used_at.clone(),
+            locked: entry.locked,
+            favorite: entry.favorite,
         });
     }
 
-    // Sort by name
-    result.sort_by(|a, b| a.name.cmp(&b.name));
-
     let json_output = serde_json::to_string_pretty(&result)
         .map_err(|e| crate::utils::CcmError::Unknown(format!("Failed to serialize JSON: {}", e)))?;
     println!("{}", json_output);
@@ -106,11 +199,8 @@ fn list_json(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
 }
 
 /// Quieter format - names only
-fn list_quieter(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
-    let mut names: Vec<&String> = entries.keys().collect();
-    names.sort();
-
-    for name in names {
+fn list_quieter(entries: &[(&String, &Entry)]) -> Result<()> {
+    for (name, _) in entries {
         println!("{}", name);
     }
 
@@ -118,16 +208,25 @@ fn list_quieter(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
 }
 
 /// Verbose format - detailed output with all metadata
-fn list_verbose(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
-    let mut sorted_entries: Vec<(&String, &crate::types::Entry)> = entries.iter().collect();
-    sorted_entries.sort_by(|a, b| a.0.cmp(b.0));
+fn list_verbose(entries: &[(&String, &Entry)], show_domain: bool) -> Result<()> {
+    let now = Utc::now();
 
     println!("{}", "Entries:".bold().underline());
     println!();
 
-    for (name, entry) in sorted_entries {
+    for &(name, entry) in entries {
         // Entry header
-        println!("  {}", name.bold());
+        let mut header = display_name(name, entry).bold().to_string();
+        if entry.locked {
+            header = format!("{} {}", header, "(locked)".yellow());
+        }
+        println!("  {}", header);
+
+        if show_domain {
+            if let Some(domain) = entry.domain() {
+                println!("  Domain: {}", domain);
+            }
+        }
 
         // Display metadata as environment variable mappings
         if !entry.metadata.is_empty() {
@@ -164,6 +263,7 @@ fn list_verbose(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
         if let Some(updated) = &entry.updated_at {
             println!("  Updated: {}", updated.dimmed());
         }
+        println!("  Last used: {}", last_used_label(entry, now).dimmed());
 
         println!();
     }
@@ -207,59 +307,109 @@ fn pad_string(s: &str, target_width: usize) -> String {
     format!("{}{}", s, " ".repeat(padding))
 }
 
+/// Tree format - '/'-separated names as nested groups
+fn list_tree(entries: &HashMap<String, Entry>, depth: Option<usize>) -> Result<()> {
+    let tree = Tree::build(entries.keys().map(String::as_str));
+    for line in tree.lines(depth) {
+        let favorite = line
+            .entry
+            .as_ref()
+            .and_then(|name| entries.get(name))
+            .is_some_and(|entry| entry.favorite);
+        let mut label = if favorite {
+            format!("★ {}", line.label)
+        } else {
+            line.label
+        };
+        if label.ends_with('/') {
+            label = label.blue().bold().to_string();
+        }
+        if line.collapsed > 0 {
+            let plural = if line.collapsed == 1 {
+                "entry"
+            } else {
+                "entries"
+            };
+            let count = format!("({} {})", line.collapsed, plural);
+            label = format!("{} {}", label, count.dimmed());
+        }
+        println!("{}{}", line.prefix.dimmed(), label);
+    }
+
+    Ok(())
+}
+
+/// An entry's name, marked with a star when it is a favorite
+fn display_name(name: &str, entry: &Entry) -> String {
+    if entry.favorite {
+        format!("★ {}", name)
+    } else {
+        name.to_string()
+    }
+}
+
 /// Table format - ASCII bordered table (default)
-fn list_table(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
-    let mut sorted_entries: Vec<(&String, &crate::types::Entry)> = entries.iter().collect();
-    sorted_entries.sort_by(|a, b| a.0.cmp(b.0));
+fn list_table(entries: &[(&String, &Entry)], show_domain: bool) -> Result<()> {
+    let now = Utc::now();
 
     // Calculate column widths using Unicode display width
     let mut max_name = 4; // "Name"
     let mut max_info = 4; // "Info"
+    let mut max_domain = 6; // "Domain"
+    let mut max_used = 9; // "Last used"
 
-    for (name, entry) in &sorted_entries {
-        max_name = max_name.max(UnicodeWidthStr::width(name.as_str()));
+    for (name, entry) in entries {
+        max_name = max_name.max(UnicodeWidthStr::width(display_name(name, entry).as_str()));
         let info = get_entry_info(entry);
         max_info = max_info.max(UnicodeWidthStr::width(info.as_str()));
+        if show_domain {
+            let domain = entry.domain().unwrap_or_default();
+            max_domain = max_domain.max(UnicodeWidthStr::width(domain.as_str()));
+        }
+        max_used = max_used.max(last_used_label(entry, now).len());
     }
 
     // Limit column widths
     max_name = max_name.min(30);
     max_info = max_info.min(60);
+    max_domain = max_domain.min(30);
 
-    // Print table
-    let border_line = format!(
-        "┌{}┬{}┐",
-        "─".repeat(max_name + 2),
-        "─".repeat(max_info + 2)
-    );
+    // Column widths in display order
+    let mut widths = vec![max_name];
+    if show_domain {
+        widths.push(max_domain);
+    }
+    widths.push(max_used);
+    widths.push(max_info);
 
-    let header_separator = format!(
-        "├{}┼{}┤",
-        "─".repeat(max_name + 2),
-        "─".repeat(max_info + 2)
-    );
+    let rule = |left: &str, mid: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{}{}{}", left, segments.join(mid), right)
+    };
+    let row = |cells: Vec<String>| format!("│ {} │", cells.join(" │ "));
 
-    let footer_line = format!(
-        "└{}┴{}┘",
-        "─".repeat(max_name + 2),
-        "─".repeat(max_info + 2)
-    );
+    // Print table
+    let border_line = rule("┌", "┬", "┐");
+    let header_separator = rule("├", "┼", "┤");
+    let footer_line = rule("└", "┴", "┘");
 
     println!("{}", border_line);
-    println!(
-        "│ {} │ {} │",
-        pad_string("Name", max_name).bold(),
-        pad_string("Environment Variables", max_info).bold()
+    let mut header = vec![pad_string("Name", max_name).bold().to_string()];
+    if show_domain {
+        header.push(pad_string("Domain", max_domain).bold().to_string());
+    }
+    header.push(pad_string("Last used", max_used).bold().to_string());
+    header.push(
+        pad_string("Environment Variables", max_info)
+            .bold()
+            .to_string(),
     );
+    println!("{}", row(header));
     println!("{}", header_separator);
 
-    for (name, entry) in sorted_entries {
+    for (name, entry) in entries {
         // Truncate name if needed
-        let display_name = if UnicodeWidthStr::width(name.as_str()) > max_name {
-            truncate_string(name, max_name)
-        } else {
-            name.clone()
-        };
+        let display_name = truncate_string(&display_name(name, entry), max_name);
 
         // Get info string (metadata summary)
         let info = get_entry_info(entry);
@@ -269,11 +419,19 @@ fn list_table(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
             info
         };
 
-        println!(
-            "│ {} │ {} │",
-            pad_string(&display_name, max_name),
-            pad_string(&display_info, max_info)
-        );
+        let mut cells = vec![pad_string(&display_name, max_name)];
+        if show_domain {
+            let domain = truncate_string(&entry.domain().unwrap_or_default(), max_domain);
+            cells.push(pad_string(&domain, max_domain));
+        }
+        let used = pad_string(&last_used_label(entry, now), max_used);
+        cells.push(if entry.last_used_at.is_some() {
+            used
+        } else {
+            used.dimmed().to_string()
+        });
+        cells.push(pad_string(&display_info, max_info));
+        println!("{}", row(cells));
     }
 
     println!("{}", footer_line);
@@ -282,7 +440,7 @@ fn list_table(entries: &HashMap<String, crate::types::Entry>) -> Result<()> {
 }
 
 /// Get summary info string for an entry
-fn get_entry_info(entry: &crate::types::Entry) -> String {
+fn get_entry_info(entry: &Entry) -> String {
     if entry.metadata.is_empty() {
         return String::new();
     }
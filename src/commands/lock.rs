@@ -0,0 +1,47 @@
+// Lock command implementation
+//
+// `ccm lock` protects entries: delete, update --secret, rollback and secret
+// edit refuse a locked entry unless given --force-unlock. `ccm unlock`
+// removes the protection.
+
+use crate::secrets;
+use crate::utils::{output, Result};
+use crate::Commands;
+use colored::Colorize;
+use serde_json::json;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    let (names, locked) = match command {
+        Commands::Lock { names } => (names, true),
+        Commands::Unlock { names } => (names, false),
+        _ => unreachable!(),
+    };
+
+    // Ensure master key is loaded (prompts for PIN if needed)
+    crate::auth::ensure_master_key_loaded().await?;
+
+    // Check every name first, so a typo changes nothing
+    for name in &names {
+        secrets::lookup_name(name, false)?;
+    }
+
+    let mut results = Vec::new();
+    for name in &names {
+        let changed = secrets::set_locked(name, locked)?;
+        if output::is_json() {
+            results.push(json!({ "name": name, "locked": locked, "changed": changed }));
+        } else if changed && locked {
+            output::success(format!("Locked: {}", name.cyan().bold()));
+        } else if changed {
+            output::success(format!("Unlocked: {}", name.cyan().bold()));
+        } else if !output::is_quiet() {
+            let state = if locked { "locked" } else { "not locked" };
+            println!("{} {} is already {}", "ℹ️".blue(), name.cyan(), state);
+        }
+    }
+
+    if output::is_json() {
+        output::print_json(&results)?;
+    }
+    Ok(())
+}
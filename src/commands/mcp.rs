@@ -0,0 +1,31 @@
+// MCP command implementation
+//
+// `ccm mcp` runs the Model Context Protocol server (see `crate::mcp`) on
+// stdin/stdout, for AI assistants such as Claude Code:
+//
+//   claude mcp add ccm -- ccm mcp
+//
+// The server cannot prompt for a PIN (stdin is the protocol). The vault is
+// unlocked with --pin-file or CCM_PIN when given, else on each call through a
+// running `ccm agent`; while locked, tool calls fail with a hint.
+
+use crate::utils::{output, Result};
+use crate::Commands;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Mcp = command {
+        if crate::auth::non_interactive_pin()?.is_some() {
+            crate::auth::ensure_master_key_loaded().await?;
+        }
+        if !output::is_quiet() {
+            eprintln!("ccm MCP server ready on stdio");
+        }
+        crate::rpc::serve(
+            std::io::stdin().lock(),
+            std::io::stdout().lock(),
+            crate::mcp::handle,
+        )
+    } else {
+        unreachable!()
+    }
+}
@@ -1,17 +1,55 @@
 // CLI command modules
 
 pub mod add;
+pub mod agent;
+pub mod allow;
+pub mod audit;
 pub mod auth;
+pub mod backup;
+pub mod claude;
+pub mod clone;
+pub mod completion;
 pub mod config;
+pub mod db;
 pub mod delete;
+pub mod docker;
+pub mod doctor;
+pub mod env_cmd;
+pub mod examples;
 pub mod export;
+pub mod fav;
 pub mod get;
 pub mod help;
+pub mod history;
 pub mod import;
+pub mod init;
+pub mod launch;
+pub mod lint;
 pub mod list;
+pub mod lock;
+pub mod mcp;
+pub mod model;
+pub mod models;
+pub mod pick;
+pub mod preset;
+pub mod quick;
+pub mod render;
+pub mod reset;
+pub mod restore;
+pub mod rollback;
+pub mod run;
 pub mod search;
+pub mod secret;
+pub mod serve;
+pub mod shell_init;
+pub mod ssh;
 pub mod stats;
+pub mod status;
+pub mod sync;
+pub mod test_cmd;
+pub mod transfer;
 pub mod update;
+pub mod usage;
 pub mod use_cmd;
+pub mod vault;
 pub mod version;
-pub mod preset;
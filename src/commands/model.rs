@@ -0,0 +1,100 @@
+// Model command implementation
+
+use crate::env;
+use crate::presets;
+use crate::secrets;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Model { name, model, var } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        do_model(&name, model.as_deref(), var.as_deref())
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_model(name: &str, model: Option<&str>, var: Option<&str>) -> Result<()> {
+    let mut entry = secrets::get_entry(name)?;
+
+    let model_var = match var {
+        Some(v) => v.to_string(),
+        None => find_model_var(name, &entry.metadata)?,
+    };
+
+    let model = match model {
+        Some(m) => m,
+        None => {
+            // No model given: show the current one
+            match entry.metadata.get(&model_var) {
+                Some(current) => println!("{} = {}", model_var.cyan(), current),
+                None => println!("{} is not set for '{}'", model_var.cyan(), name),
+            }
+            return Ok(());
+        }
+    };
+
+    let previous = entry.metadata.insert(model_var.clone(), model.to_string());
+    entry.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    secrets::update_entry(name, entry)?;
+
+    match previous {
+        Some(old) if old != model => output::success(format!(
+            "{}: {} → {}",
+            model_var.cyan(),
+            old.dimmed(),
+            model.bold()
+        )),
+        _ => output::success(format!("{} = {}", model_var.cyan(), model.bold())),
+    }
+
+    // Keep the shell in sync when switching the model of the active entry
+    let is_active = env::get_active_entry()?
+        .map(|active| active.name == name)
+        .unwrap_or(false);
+    if is_active {
+        crate::commands::use_cmd::do_use(name, true)?;
+        println!(
+            "{} Re-applied '{}'; reload your shell to pick up the new model",
+            "🔄".blue(),
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// Work out which variable holds the model for an entry
+/// Prefers the preset's model variable, then a single existing *_MODEL variable
+fn find_model_var(
+    name: &str,
+    metadata: &std::collections::HashMap<String, String>,
+) -> Result<String> {
+    if let Some(detected) = presets::detect_preset(name, metadata) {
+        if let Some(var) = detected.preset.env_mapping.get("model") {
+            return Ok(var.clone());
+        }
+    }
+
+    let mut model_vars: Vec<&String> = metadata.keys().filter(|k| k.ends_with("_MODEL")).collect();
+    model_vars.sort();
+
+    match model_vars.as_slice() {
+        [var] => Ok((*var).clone()),
+        [] => Err(CcmError::InvalidArgument(format!(
+            "Cannot tell which variable holds the model for '{}'. Use --var <VAR>.",
+            name
+        ))),
+        vars => Err(CcmError::InvalidArgument(format!(
+            "Several model variables found for '{}' ({}). Use --var <VAR>.",
+            name,
+            vars.iter()
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
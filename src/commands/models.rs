@@ -0,0 +1,93 @@
+// Models command implementation
+//
+// `ccm models NAME` asks the entry's provider (see `core::provider`) which
+// models its key can use, so one can be picked with `ccm model NAME MODEL`.
+
+use crate::core::provider::Provider;
+use crate::secrets;
+use crate::utils::{output, Result};
+use crate::Commands;
+use colored::Colorize;
+use serde_json::json;
+use std::time::Duration;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Models {
+        name,
+        filter,
+        timeout,
+    } = command
+    {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        let name = super::pick::lookup_name(&name, true)?;
+        do_models(&name, filter.as_deref(), Duration::from_secs(timeout))
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_models(name: &str, filter: Option<&str>, timeout: Duration) -> Result<()> {
+    let (entry, secret) = secrets::get_entry_with_secret(name)?;
+    let vars = crate::env::get_env_mappings_with_secret(&entry, &secret);
+    let provider = Provider::detect(name, &vars)?;
+    let current = provider
+        .model_var
+        .as_ref()
+        .and_then(|var| entry.metadata.get(var));
+
+    let mut models = provider.models(timeout)?;
+    if let Some(filter) = filter {
+        let filter = filter.to_lowercase();
+        models.retain(|model| model.id.to_lowercase().contains(&filter));
+    }
+
+    if output::is_json() {
+        let models: Vec<_> = models
+            .iter()
+            .map(|model| {
+                json!({
+                    "id": model.id,
+                    "name": model.name,
+                    "current": current == Some(&model.id),
+                })
+            })
+            .collect();
+        return output::print_json(&json!({
+            "name": name,
+            "provider": provider.preset,
+            "model_var": provider.model_var,
+            "models": models,
+        }));
+    }
+
+    if models.is_empty() {
+        println!("{} No models found", "ℹ️".blue());
+        return Ok(());
+    }
+    println!(
+        "{} models for {} ({}):",
+        models.len(),
+        name.cyan().bold(),
+        provider.preset
+    );
+    for model in &models {
+        let marker = if current == Some(&model.id) { "*" } else { " " };
+        match &model.name {
+            Some(display) if *display != model.id => {
+                println!("  {} {}  {}", marker, model.id.bold(), display.dimmed())
+            }
+            _ => println!("  {} {}", marker, model.id.bold()),
+        }
+    }
+    if let Some(var) = &provider.model_var {
+        println!();
+        println!(
+            "{} Set {} with: ccm model {} <MODEL>",
+            "ℹ️".blue(),
+            var.cyan(),
+            name
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+// Pick command implementation
+//
+// `ccm pick [QUERY]` opens the fuzzy picker over entry names, then shows,
+// uses or copies the chosen entry. `resolve_name` is how `ccm get` finds an
+// entry when given no name or a misspelt one; `lookup_name` is how `ccm use`
+// expands a unique prefix.
+
+use crate::secrets;
+use crate::utils::{fuzzy, output, picker, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use std::io::{self, IsTerminal};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Pick {
+        query,
+        use_entry,
+        copy,
+        name_only,
+    } = command
+    {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+
+        let Some(name) = pick_name(query.as_deref().unwrap_or(""))? else {
+            eprintln!("Pick cancelled.");
+            return Ok(());
+        };
+
+        if name_only {
+            println!("{}", name);
+            Ok(())
+        } else if use_entry {
+            super::use_cmd::do_use(&name, false)
+        } else if output::is_json() {
+            super::get::do_get_json(&name, None, copy)
+        } else {
+            super::get::do_get(&name, None, copy)
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+/// Entry names, sorted
+fn entry_names() -> Result<Vec<String>> {
+    let mut names: Vec<String> = secrets::list_entries()?.into_keys().collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Let the user pick an entry, the list starting filtered by `query`
+/// None when they cancel
+pub(crate) fn pick_name(query: &str) -> Result<Option<String>> {
+    picker::pick("Entry:", &entry_names()?, query)
+}
+
+/// `name`, or the only entry starting with it when `allow_prefix` is set
+/// (noted on stderr); see `secrets::lookup_name`
+pub(crate) fn lookup_name(name: &str, allow_prefix: bool) -> Result<String> {
+    let found = secrets::lookup_name(name, allow_prefix)?;
+    if found != name && !output::is_quiet() {
+        eprintln!(
+            "{} No entry '{}'; using {}",
+            "ℹ️".blue(),
+            name,
+            found.cyan().bold()
+        );
+    }
+    Ok(found)
+}
+
+/// The entry a command given `name` should act on
+///
+/// No name opens the picker. A unique prefix of an entry name stands for it.
+/// On a terminal, a name that is still not an entry is matched fuzzily: a
+/// single match is used (with a notice on stderr) and several open the
+/// picker with the name as the query. Elsewhere it fails with suggestions.
+/// None when the user cancels the picker.
+pub(crate) fn resolve_name(name: Option<String>) -> Result<Option<String>> {
+    let Some(name) = name else {
+        return pick_name("");
+    };
+    let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+    let not_found = match lookup_name(&name, true) {
+        Ok(found) => return Ok(Some(found)),
+        Err(e @ (CcmError::EntryNotFound(_) | CcmError::EntryNotFoundSuggestions { .. }))
+            if interactive =>
+        {
+            e
+        }
+        Err(e) => return Err(e),
+    };
+
+    let names = entry_names()?;
+    let matches = fuzzy::rank(&name, names.iter().map(String::as_str));
+    match matches.as_slice() {
+        [] => Err(not_found),
+        [only] => {
+            if !output::is_quiet() {
+                eprintln!(
+                    "{} No entry '{}'; using {}",
+                    "ℹ️".blue(),
+                    name,
+                    only.cyan().bold()
+                );
+            }
+            Ok(Some(only.to_string()))
+        }
+        _ => picker::pick("Entry:", &names, &name),
+    }
+}
@@ -1,15 +1,59 @@
 // Preset command implementation
 
 use crate::presets;
-use crate::utils::Result;
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::input::resolve_secret;
+use crate::utils::{output, CcmError, Result};
 use crate::Commands;
 use colored::Colorize;
+use std::path::Path;
 
 pub async fn execute(command: Commands) -> Result<()> {
     if let Commands::Preset { action } = command {
         match action {
             crate::PresetAction::List => list_presets(),
             crate::PresetAction::Show { name } => show_preset(&name),
+            crate::PresetAction::Import { file } => import_presets(&file),
+            crate::PresetAction::SaveFrom {
+                entry,
+                name,
+                description,
+                ask,
+                force,
+            } => {
+                // Ensure master key is loaded (prompts for PIN if needed)
+                crate::auth::ensure_master_key_loaded().await?;
+                save_from_entry(&entry, name, description, &ask, force)
+            }
+            crate::PresetAction::Detect { name, apply } => {
+                // Ensure master key is loaded (prompts for PIN if needed)
+                crate::auth::ensure_master_key_loaded().await?;
+                detect_preset(&name, apply)
+            }
+            crate::PresetAction::Relay {
+                base_url,
+                style,
+                auth,
+                model,
+                save,
+                secret,
+            } => {
+                let vars = presets::relay_env_mapping(
+                    &base_url,
+                    &style,
+                    auth.as_deref(),
+                    model.as_deref(),
+                )?;
+                match save {
+                    Some(name) => {
+                        // Ensure master key is loaded (prompts for PIN if needed)
+                        crate::auth::ensure_master_key_loaded().await?;
+                        save_relay_profile(&name, vars, secret)
+                    }
+                    None => show_relay_profile(&vars),
+                }
+            }
         }
     } else {
         unreachable!()
@@ -32,6 +76,89 @@ fn list_presets() -> Result<()> {
     Ok(())
 }
 
+fn import_presets(path: &Path) -> Result<()> {
+    let content = std::fs::read_to_string(path)?;
+    let target = presets::file::presets_path();
+    let names = presets::file::import(&target, &content)?;
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "imported": names,
+            "path": target.display().to_string(),
+        }));
+    }
+    if names.is_empty() {
+        println!("{} No presets in {}", "ℹ️".blue(), path.display());
+    } else {
+        output::success(format!(
+            "Imported {} preset(s) into {}: {}",
+            names.len(),
+            target.display(),
+            names.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+fn save_from_entry(
+    entry_name: &str,
+    name: Option<String>,
+    description: Option<String>,
+    ask: &[String],
+    force: bool,
+) -> Result<()> {
+    let entry_name = secrets::lookup_name(entry_name, false)?;
+    let entry = secrets::get_entry(&entry_name)?;
+    let name = name.unwrap_or_else(|| {
+        entry_name
+            .to_lowercase()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    });
+    if !force && presets::get_preset(&name).is_ok() {
+        return Err(CcmError::InvalidArgument(format!(
+            "Preset {} already exists; pass --force to replace it or --name to pick another",
+            name
+        )));
+    }
+
+    let description = description.unwrap_or_else(|| format!("Saved from {}", entry_name));
+    let def = presets::file::def_from_entry(&description, &entry.metadata, ask)?;
+    let preset = def.to_preset(&name)?;
+    let path = presets::file::presets_path();
+    presets::file::add(
+        &path,
+        presets::file::PresetFile {
+            presets: [(name.clone(), def)].into(),
+        },
+    )?;
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "preset": name,
+            "path": path.display().to_string(),
+            "env": preset.env_mapping,
+            "defaults": preset.default_fields,
+            "required": preset.required_fields,
+        }));
+    }
+    output::success(format!(
+        "Saved preset {} from {} in {}",
+        name.cyan().bold(),
+        entry_name,
+        path.display()
+    ));
+    println!("   Add the next one with: ccm add <NAME> --preset {}", name);
+    Ok(())
+}
+
 fn show_preset(name: &str) -> Result<()> {
     let preset = presets::get_preset(name)?;
 
@@ -63,9 +190,111 @@ fn show_preset(name: &str) -> Result<()> {
 
     println!("{}", "Example usage:".bold());
     println!(
-        "  ccm add api my-{} --secret YOUR_TOKEN --preset {}",
+        "  ccm add my-{} --preset {} --secret YOUR_TOKEN",
         preset.name, preset.name
     );
 
     Ok(())
 }
+
+fn detect_preset(name: &str, apply: bool) -> Result<()> {
+    let mut entry = secrets::get_entry(name)?;
+
+    let detected = match presets::detect_preset(name, &entry.metadata) {
+        Some(detected) => detected,
+        None => {
+            println!("No matching preset found for '{}'", name);
+            println!(
+                "Use {} to see available presets",
+                "ccm preset list".yellow()
+            );
+            return Ok(());
+        }
+    };
+
+    println!(
+        "{} {} ({})",
+        "Detected preset:".bold(),
+        detected.preset.name.cyan().bold(),
+        detected.preset.description
+    );
+    if detected.by_name {
+        println!("  Matched by entry name; no preset variables found");
+    } else {
+        println!("  Matched variables: {}", detected.matched.join(", "));
+    }
+    println!();
+
+    if detected.additions.is_empty() {
+        output::success("Entry already has all preset variables");
+        return Ok(());
+    }
+
+    println!("{}", "Missing variables:".bold());
+    for (var, value) in &detected.additions {
+        println!("  {} = {}", var.yellow(), value);
+    }
+    println!();
+
+    if !apply {
+        println!(
+            "Run {} to add them",
+            format!("ccm preset detect {} --apply", name).yellow()
+        );
+        return Ok(());
+    }
+
+    for (var, value) in detected.additions {
+        entry.metadata.insert(var, value);
+    }
+    entry.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    secrets::update_entry(name, entry)?;
+
+    output::success(format!(
+        "Applied preset {} to {}",
+        detected.preset.name.cyan(),
+        name.bold()
+    ));
+
+    Ok(())
+}
+
+fn show_relay_profile(vars: &[(String, String)]) -> Result<()> {
+    println!("{}", "Relay environment mapping:".bold());
+    for (var, value) in vars {
+        println!("  {} = {}", var.yellow(), value);
+    }
+    println!();
+
+    let env_args: Vec<String> = vars
+        .iter()
+        .map(|(var, value)| format!("--env {}={}", var, value))
+        .collect();
+    println!("{}", "Create an entry with:".bold());
+    println!("  ccm add <NAME> <SECRET> {}", env_args.join(" "));
+    println!(
+        "or rerun with {} to save it directly",
+        "--save <NAME>".yellow()
+    );
+
+    Ok(())
+}
+
+fn save_relay_profile(
+    name: &str,
+    vars: Vec<(String, String)>,
+    secret: Option<String>,
+) -> Result<()> {
+    let policy = crate::db::get_database()?.get_name_policy()?;
+    let normalized = policy.normalize(name);
+    policy.validate(&normalized)?;
+
+    let secret_value = resolve_secret(secret)?;
+    let entry = Entry::new(normalized.clone(), vars.into_iter().collect());
+    secrets::add_entry(&normalized, entry, &secret_value)?;
+
+    output::success(format!("Added relay entry: {}", normalized.cyan().bold()));
+    println!("   Activate it with: ccm use {}", normalized);
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+// Quick command implementation
+
+use crate::secrets::{self, quick_access};
+use crate::types::Entry;
+use crate::utils::{output, Result};
+use crate::{Commands, QuickAction};
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Quick { action } = command {
+        let store = quick_access::store();
+        match action {
+            QuickAction::Add { name } => {
+                // Copying the entry out of the vault needs the master key
+                crate::auth::ensure_master_key_loaded().await?;
+                let (entry, secret) = secrets::get_entry_with_secret(&name)?;
+                add(&store, entry, secret)
+            }
+            QuickAction::Remove { name } => remove(&store, &name).map(|_| ()),
+            QuickAction::List => list(&store).map(|_| ()),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+fn add(store: &quick_access::Store, entry: Entry, secret: String) -> Result<()> {
+    let name = entry.name.clone();
+    store.insert(entry, secret)?;
+
+    output::success(format!(
+        "{} can now be used without the PIN",
+        name.cyan().bold()
+    ));
+    println!(
+        "   {} Its secret is readable by anything with access to your keyring session.",
+        "⚠️".yellow()
+    );
+    println!("   Only use this for low-sensitivity entries (public URLs, non-secret config).");
+
+    Ok(())
+}
+
+/// Returns whether the entry was marked
+fn remove(store: &quick_access::Store, name: &str) -> Result<bool> {
+    let removed = store.remove(name)?;
+    if removed {
+        output::success(format!("{} requires the PIN again", name.cyan().bold()));
+    } else {
+        println!("{} {} is not a quick-access entry", "ℹ️".blue(), name);
+    }
+
+    Ok(removed)
+}
+
+/// Returns the names listed
+fn list(store: &quick_access::Store) -> Result<Vec<String>> {
+    let names = store.names()?;
+    if names.is_empty() {
+        println!("No quick-access entries. Add one with: ccm quick add <NAME>");
+        return Ok(names);
+    }
+
+    println!("{}", "Quick-access entries (no PIN)".bold().underline());
+    for name in &names {
+        println!("  {}", name);
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn key(_: bool) -> Result<Option<[u8; 32]>> {
+        Ok(Some([7; 32]))
+    }
+
+    #[test]
+    fn test_add_list_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = quick_access::Store::new(dir.path().join("quick-access.json"), key);
+        assert!(list(&store).unwrap().is_empty());
+
+        let entry = Entry::new("docs".to_string(), HashMap::new());
+        add(&store, entry, "public-token".to_string()).unwrap();
+        assert_eq!(list(&store).unwrap(), ["docs"]);
+        assert_eq!(store.get("docs").unwrap().unwrap().1, "public-token");
+
+        assert!(remove(&store, "docs").unwrap());
+        assert!(!remove(&store, "docs").unwrap());
+        assert!(list(&store).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+// Render command implementation
+//
+// `ccm render TEMPLATE --out FILE` fills the placeholders of a config file
+// template (see `utils::template`) with entry variables and secrets, so
+// that the secrets live in ccm rather than in the file.
+
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::template::{self, Placeholder};
+use crate::utils::{files, output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Render {
+        template,
+        out,
+        stdout,
+        entry,
+    } = command
+    {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        let entry = entry
+            .map(|name| super::pick::lookup_name(&name, true))
+            .transpose()?;
+        let out = if stdout { None } else { out };
+        do_render(&template, out.as_deref(), entry.as_deref())
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_render(template_path: &Path, out: Option<&Path>, default_entry: Option<&str>) -> Result<()> {
+    let text = std::fs::read_to_string(template_path).map_err(|e| {
+        CcmError::InvalidArgument(format!("Cannot read {}: {}", template_path.display(), e))
+    })?;
+    let placeholders = template::placeholders(&text)?;
+
+    // Load every entry first so that nothing is written if one is missing
+    let mut entries: HashMap<String, (Entry, String)> = HashMap::new();
+    for placeholder in &placeholders {
+        let name = entry_name(placeholder, default_entry)?;
+        if !entries.contains_key(name) {
+            entries.insert(name.to_string(), secrets::use_entry(name)?);
+        }
+    }
+
+    let rendered = template::render(&text, |placeholder| {
+        let name = entry_name(placeholder, default_entry)?;
+        let (entry, secret) = &entries[name];
+        match placeholder {
+            Placeholder::Secret(_) => Ok(secret.clone()),
+            Placeholder::Var { var, .. } => match entry.metadata.get(var) {
+                Some(value) if value == "SECRET" => Ok(secret.clone()),
+                Some(value) => Ok(value.clone()),
+                None => Err(CcmError::InvalidArgument(format!(
+                    "Entry '{}' has no variable {}",
+                    name, var
+                ))),
+            },
+        }
+    })?;
+
+    let Some(out) = out else {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(rendered.as_bytes())?;
+        return Ok(stdout.flush()?);
+    };
+    files::write_private_file(out, rendered.as_bytes())?;
+    if !output::is_quiet() {
+        output::success(format!(
+            "Rendered {} ({} placeholders from {} entries, mode 0600)",
+            out.display().to_string().cyan(),
+            placeholders.len(),
+            entries.len()
+        ));
+    }
+    Ok(())
+}
+
+/// The entry a placeholder reads
+fn entry_name<'a>(placeholder: &'a Placeholder, default_entry: Option<&'a str>) -> Result<&'a str> {
+    match placeholder {
+        Placeholder::Secret(name) => Ok(name),
+        Placeholder::Var {
+            entry: Some(name), ..
+        } => Ok(name),
+        Placeholder::Var { entry: None, var } => default_entry.ok_or_else(|| {
+            CcmError::InvalidArgument(format!(
+                "{{{{ {} }}}} names no entry: write {{{{ ENTRY.{} }}}} or pass --entry",
+                var, var
+            ))
+        }),
+    }
+}
@@ -0,0 +1,209 @@
+// Reset command implementation
+//
+// Starts over: deletes the database, the quick-access store and the instance
+// ID, removes the master key, quick-access key and PIN flag from the keyring,
+// stops the agent and deletes the session files. Works without the PIN, so a
+// forgotten PIN can be recovered from by losing the data. Snapshots are kept:
+// they stay readable with a master key escrow or paper key. So are the git
+// sync repository, presets.toml and the allowed .ccm.toml files, which are
+// not tied to the vault; the reset lists them.
+
+use crate::auth::{self, pin};
+use crate::db::location;
+use crate::secrets::master_key;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use dialoguer::Input;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Reset { confirm } = command {
+        do_reset(confirm.as_deref())
+    } else {
+        unreachable!()
+    }
+}
+
+/// Files of the vault at `db_path` deleted by the reset, when they exist,
+/// with the session state in `runtime_dir`
+fn files_to_delete(db_path: &Path, runtime_dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![
+        location::with_suffix(db_path, "-wal"),
+        location::with_suffix(db_path, "-shm"),
+        db_path.to_path_buf(),
+        location::quick_access_path(db_path),
+        location::instance_id_path(db_path),
+    ];
+    if db_path == location::default_db_path() {
+        // Where the default vault's instance ID used to be
+        files.push(location::data_dir().join("instance_id"));
+        files.push(crate::env::active_entry_changed_path());
+    }
+
+    // Session state of every shell (ccm-auth-shell-<pid>.json)
+    if let Ok(dir) = std::fs::read_dir(runtime_dir) {
+        let mut sessions: Vec<PathBuf> = dir
+            .flatten()
+            .map(|f| f.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("ccm-auth-shell-") && n.ends_with(".json"))
+            })
+            .collect();
+        sessions.sort();
+        files.extend(sessions);
+    }
+
+    files.retain(|path| path.exists());
+    files.dedup();
+    files
+}
+
+/// What the reset leaves in place, when it exists, and why
+fn kept_paths() -> Vec<(PathBuf, &'static str)> {
+    let mut kept = vec![
+        (
+            crate::db::snapshots::backups_dir(),
+            "snapshots; they need the old master key: an escrow file or paper key",
+        ),
+        (crate::sync::git::default_dir(), "git sync repository"),
+        (crate::presets::file::presets_path(), "user presets"),
+        (
+            crate::env::project::allowed_path(),
+            "allowed .ccm.toml files",
+        ),
+    ];
+    kept.retain(|(path, _)| path.exists());
+    kept
+}
+
+fn do_reset(confirm: Option<&str>) -> Result<()> {
+    let instance_id = master_key::get_instance_id_from_config()?;
+    let files = files_to_delete(&crate::db::db_path(), &auth::runtime_dir());
+
+    let Some(instance_id) = instance_id else {
+        if files.is_empty() {
+            println!("Nothing to reset: no vault found");
+            return Ok(());
+        }
+        return Err(CcmError::Initialization(
+            "The instance ID is missing, so the vault cannot be identified; \
+             delete the files below by hand"
+                .to_string(),
+        ));
+    };
+
+    println!("{}", "This permanently deletes:".red().bold());
+    for path in &files {
+        println!("  {}", path.display());
+    }
+    println!(
+        "  the master key and PIN of instance {} in the OS keyring",
+        instance_id
+    );
+    println!();
+    let kept = kept_paths();
+    if !kept.is_empty() {
+        println!("Kept (delete them by hand if you no longer need them):");
+        for (path, what) in &kept {
+            println!("  {} ({})", path.display(), what);
+        }
+        println!();
+    }
+
+    let typed = match confirm {
+        Some(typed) => typed.to_string(),
+        None if std::io::stdin().is_terminal() => Input::new()
+            .with_prompt(format!("Type the instance ID ({}) to confirm", instance_id))
+            .allow_empty(true)
+            .interact_text()?,
+        None => {
+            return Err(CcmError::InvalidArgument(
+                "Pass --confirm <INSTANCE_ID> to reset without a terminal".to_string(),
+            ))
+        }
+    };
+    if !reset_vault(&instance_id, &files, &typed)? {
+        println!("Instance ID does not match; nothing was deleted.");
+        return Ok(());
+    }
+
+    output::success("Vault reset");
+    println!("   Start again with: ccm init");
+    Ok(())
+}
+
+/// Delete the vault of `instance_id` and `files`, if `typed` is that
+/// instance ID; false (and nothing deleted) when it is not
+fn reset_vault(instance_id: &str, files: &[PathBuf], typed: &str) -> Result<bool> {
+    if typed.trim() != instance_id {
+        return Ok(false);
+    }
+
+    // The agent would keep handing out the old key
+    let _ = crate::agent::request("STOP");
+
+    master_key::delete_keyring_entries(instance_id)?;
+    if pin::has_pin()? {
+        pin::clear_pin_flag()?;
+    }
+    for path in files {
+        std::fs::remove_file(path)?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_to_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = dir.path().join("run");
+        std::fs::create_dir(&runtime).unwrap();
+        let db = dir.path().join("work.db");
+        for name in [
+            "work.db",
+            "work.db-wal",
+            "work.db.instance_id",
+            "work.db.quick-access.json",
+            "other.db",
+            "run/ccm-auth-shell-42.json",
+            "run/agent.sock.json",
+        ] {
+            std::fs::write(dir.path().join(name), name).unwrap();
+        }
+
+        let expected: Vec<PathBuf> = [
+            "work.db-wal",
+            "work.db",
+            "work.db.quick-access.json",
+            "work.db.instance_id",
+            "run/ccm-auth-shell-42.json",
+        ]
+        .iter()
+        .map(|name| dir.path().join(name))
+        .collect();
+        assert_eq!(files_to_delete(&db, &runtime), expected);
+
+        // Another vault's files are not touched
+        let none = files_to_delete(&dir.path().join("missing.db"), &dir.path().join("nothing"));
+        assert!(none.is_empty(), "{:?}", none);
+    }
+
+    #[test]
+    fn test_reset_needs_the_instance_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = vec![dir.path().join("work.db")];
+        std::fs::write(&files[0], "vault").unwrap();
+
+        for typed in ["", "abc12", "ABC123", "abc1234"] {
+            assert!(!reset_vault("abc123", &files, typed).unwrap());
+        }
+        assert!(files[0].exists());
+    }
+}
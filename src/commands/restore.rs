@@ -0,0 +1,372 @@
+// Restore command implementation
+
+use crate::commands::config;
+use crate::commands::export::{decrypt_backup, ExportData, ExportFile};
+use crate::commands::import::check_backup_signature;
+use crate::db;
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::{output, recipients, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use dialoguer::Password;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Restore {
+        file,
+        replace,
+        identity,
+        force,
+        force_unlock,
+        include,
+    } = command
+    {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        let options = RestoreOptions {
+            replace,
+            force,
+            force_unlock,
+        };
+        do_restore(Path::new(&file), identity.as_deref(), &options, &include)
+    } else {
+        unreachable!()
+    }
+}
+
+/// How a restore treats the entries already in the vault
+struct RestoreOptions {
+    /// Delete entries not in the backup
+    replace: bool,
+    /// Skip the confirmation
+    force: bool,
+    /// Overwrite or delete locked entries too
+    force_unlock: bool,
+}
+
+fn do_restore(
+    path: &Path,
+    identity: Option<&str>,
+    options: &RestoreOptions,
+    include: &[String],
+) -> Result<()> {
+    let RestoreOptions {
+        replace,
+        force,
+        force_unlock,
+    } = *options;
+    if !path.exists() {
+        return Err(CcmError::InvalidArgument(format!(
+            "File not found: {}",
+            path.display()
+        )));
+    }
+
+    let mut bytes = std::fs::read(path)?;
+    if recipients::is_age_file(&bytes) {
+        println!("🔒 age-encrypted backup detected");
+        let identity = identity.ok_or_else(|| {
+            CcmError::InvalidArgument(
+                "This backup is encrypted with age. Pass your identity file with --identity <FILE>"
+                    .to_string(),
+            )
+        })?;
+        bytes = recipients::age_decrypt(&bytes, Path::new(identity))?;
+    } else if recipients::is_gpg_file(&bytes) {
+        println!("🔒 OpenPGP-encrypted backup detected, decrypting with gpg");
+        bytes = recipients::gpg_decrypt(&bytes)?;
+    }
+
+    let content = String::from_utf8(bytes)
+        .map_err(|_| CcmError::InvalidArgument("Backup is not a text file".to_string()))?;
+    let mut backup = read_backup(&content)?;
+
+    let settings = if include.iter().any(|part| part == "config") {
+        let settings = backup.config.take().ok_or_else(|| {
+            CcmError::InvalidArgument(
+                "Backup does not contain settings (export with --include config)".to_string(),
+            )
+        })?;
+        Some(backup_settings(settings)?)
+    } else {
+        None
+    };
+    if !include.iter().any(|part| part == "entries") {
+        if let Some(settings) = settings {
+            restore_settings(&settings, force)?;
+        }
+        return Ok(());
+    }
+
+    let entries = backup_entries(backup)?;
+
+    if entries.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "Backup does not contain any entries".to_string(),
+        ));
+    }
+
+    let existing = secrets::list_entries()?;
+    let overwritten: Vec<&str> = entries
+        .iter()
+        .map(|(entry, _)| entry.name.as_str())
+        .filter(|name| existing.contains_key(*name))
+        .collect();
+    let removed: Vec<&str> = if replace {
+        existing
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !entries.iter().any(|(entry, _)| entry.name == *name))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Nothing is written if one of them is locked
+    let mut locked: Vec<&str> = overwritten
+        .iter()
+        .chain(&removed)
+        .copied()
+        .filter(|name| existing[*name].locked)
+        .collect();
+    locked.sort();
+    if let (Some(first), false) = (locked.first(), force_unlock) {
+        if locked.len() > 1 {
+            eprintln!(
+                "{} Locked entries the restore would replace: {}",
+                "⚠️".yellow(),
+                locked.join(", ")
+            );
+        }
+        return Err(CcmError::EntryLocked(first.to_string()));
+    }
+    let removed = removed.len();
+
+    println!("📊 Backup contains {} entries", entries.len());
+    if let Some(settings) = &settings {
+        println!("   {} settings will be applied", settings.len());
+    }
+    if !overwritten.is_empty() {
+        println!(
+            "   {} existing entries will be overwritten (previous secrets stay in their history)",
+            overwritten.len()
+        );
+    }
+    if removed > 0 {
+        println!(
+            "   {} {} entries not in the backup will be deleted, with their secret history",
+            "⚠️".yellow(),
+            removed
+        );
+    }
+
+    if !force && (removed > 0 || !overwritten.is_empty()) {
+        print!("Continue? (y/N): ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !input.trim().eq_ignore_ascii_case("y") && !input.trim().eq_ignore_ascii_case("yes") {
+            println!("Restore cancelled.");
+            return Ok(());
+        }
+    }
+
+    // Overwritten entries keep their lock
+    let mut entries = entries;
+    for (entry, _) in &mut entries {
+        if existing.get(&entry.name).is_some_and(|e| e.locked) {
+            entry.locked = true;
+        }
+    }
+    let count = entries.len();
+    secrets::restore_entries(entries, replace)?;
+
+    output::success(format!("Restored {} entries", count));
+    if removed > 0 {
+        println!("   Deleted {} entries not in the backup", removed);
+    }
+    if let Some(settings) = settings {
+        apply_settings(&settings)?;
+    }
+
+    Ok(())
+}
+
+/// Settings from a backup that can be applied here: ccm-managed keys are
+/// skipped, and every value must be valid before anything is saved
+fn backup_settings(settings: BTreeMap<String, String>) -> Result<Vec<(String, String)>> {
+    settings
+        .into_iter()
+        .filter(|(k, _)| config::is_editable(k))
+        .map(|(k, v)| {
+            config::validate_setting(&k, &v)?;
+            Ok((k, v))
+        })
+        .collect()
+}
+
+/// Apply only the settings of a backup, asking first when some change
+fn restore_settings(settings: &[(String, String)], force: bool) -> Result<()> {
+    let current = config::portable_settings(&db::get_database()?)?;
+    let changed = settings
+        .iter()
+        .filter(|(k, v)| current.get(k).is_some_and(|old| old != v))
+        .count();
+
+    println!("📊 Backup contains {} settings", settings.len());
+    if changed > 0 && !force {
+        print!(
+            "{} existing settings will be changed. Continue? (y/N): ",
+            changed
+        );
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !input.trim().eq_ignore_ascii_case("y") && !input.trim().eq_ignore_ascii_case("yes") {
+            println!("Restore cancelled.");
+            return Ok(());
+        }
+    }
+
+    apply_settings(settings)
+}
+
+fn apply_settings(settings: &[(String, String)]) -> Result<()> {
+    db::get_database()?.apply_settings(settings, &[])?;
+    output::success(format!("Restored {} settings", settings.len()));
+    Ok(())
+}
+
+/// Parse a backup written by `ccm export` (JSON, YAML or TOML; encrypted or
+/// plaintext), asking for the password of an encrypted one
+fn read_backup(content: &str) -> Result<ExportData> {
+    let value = parse_any(content)?;
+
+    if value.get("encrypted").and_then(|v| v.as_bool()) == Some(true) {
+        let file: ExportFile = serde_json::from_value(value)
+            .map_err(|e| CcmError::InvalidArgument(format!("Invalid encrypted backup: {}", e)))?;
+
+        println!("🔒 Encrypted backup detected");
+        check_backup_signature(
+            &file.format,
+            &file.algorithm,
+            &file.data,
+            file.signature.as_ref(),
+        )?;
+
+        let password = Password::new()
+            .with_prompt("Decryption password")
+            .interact()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        return decrypt_backup(&file, &password);
+    }
+
+    serde_json::from_value(value).map_err(|e| {
+        CcmError::InvalidArgument(format!(
+            "Not a ccm backup (use ccm import for other files): {}",
+            e
+        ))
+    })
+}
+
+/// Parse JSON, YAML or TOML into a generic value
+fn parse_any(content: &str) -> Result<serde_json::Value> {
+    if content.trim_start().starts_with('{') {
+        return Ok(serde_json::from_str(content)?);
+    }
+    if let Ok(value) = toml::from_str::<serde_json::Value>(content) {
+        return Ok(value);
+    }
+    serde_yaml::from_str(content)
+        .map_err(|e| CcmError::InvalidArgument(format!("Failed to parse backup: {}", e)))
+}
+
+/// Turn backup entries into vault entries, exactly as they were exported
+pub(crate) fn backup_entries(backup: ExportData) -> Result<Vec<(Entry, String)>> {
+    let mut entries: Vec<(Entry, String)> = backup
+        .entries
+        .into_iter()
+        .map(|(name, exported)| {
+            let secret = exported.secret.ok_or_else(|| {
+                CcmError::InvalidArgument(format!(
+                    "Entry '{}' has no secret in the backup; it cannot be restored",
+                    name
+                ))
+            })?;
+            let entry = Entry {
+                name,
+                metadata: exported.metadata,
+                tags: exported.tags,
+                notes: exported.notes,
+                created_at: exported.created_at,
+                updated_at: exported.updated_at,
+                last_used_at: None,
+                locked: exported.locked,
+                favorite: exported.favorite,
+            };
+            Ok((entry, secret))
+        })
+        .collect::<Result<_>>()?;
+    entries.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::export::{serialize, ExportEntry};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_read_yaml_and_toml_backups() {
+        let backup = ExportData {
+            version: "2.0.0".to_string(),
+            exported_at: "2026-01-01T00:00:00Z".to_string(),
+            entries: HashMap::from([(
+                "work/openai".to_string(),
+                ExportEntry {
+                    metadata: HashMap::from([("type".to_string(), "api".to_string())]),
+                    secret: Some("sk-1".to_string()),
+                    tags: Some(vec!["ai".to_string()]),
+                    notes: None,
+                    created_at: None,
+                    updated_at: None,
+                    locked: true,
+                    favorite: false,
+                },
+            )]),
+            config: Some(BTreeMap::from([(
+                "backup.auto".to_string(),
+                "daily".to_string(),
+            )])),
+        };
+
+        for format in ["json", "yaml", "toml"] {
+            let read = read_backup(&serialize(&backup, format).unwrap()).unwrap();
+            let entry = &read.entries["work/openai"];
+            assert_eq!(entry.secret.as_deref(), Some("sk-1"), "{}", format);
+            assert_eq!(entry.metadata["type"], "api");
+            assert_eq!(entry.tags, Some(vec!["ai".to_string()]));
+            assert!(entry.locked);
+            assert_eq!(read.config, backup.config);
+
+            // The wrapper of an encrypted backup
+            let file = ExportFile {
+                format: "ccm-backup-v2".to_string(),
+                encrypted: true,
+                algorithm: "AES-256-GCM".to_string(),
+                data: "c2VjcmV0".to_string(),
+                signature: None,
+            };
+            let value = parse_any(&serialize(&file, format).unwrap()).unwrap();
+            assert_eq!(value["encrypted"], true, "{}", format);
+            assert_eq!(value["data"], "c2VjcmV0");
+        }
+    }
+}
@@ -0,0 +1,34 @@
+// Rollback command implementation
+
+use crate::secrets;
+use crate::utils::{output, Result};
+use crate::Commands;
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Rollback {
+        name,
+        version,
+        force_unlock,
+    } = command
+    {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        secrets::ensure_unlocked(&name, force_unlock)?;
+        do_rollback(&name, version)
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_rollback(name: &str, version: i64) -> Result<()> {
+    secrets::rollback_secret(name, version)?;
+
+    output::success(format!("Restored version {} of {}", version, name.bold()));
+    println!(
+        "   The replaced value was kept in the history (see 'ccm history {}')",
+        name
+    );
+
+    Ok(())
+}
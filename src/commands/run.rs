@@ -0,0 +1,131 @@
+// Run command implementation (also `npm-run` and `cargo`)
+
+use crate::env;
+use crate::env::project::{self, PROJECT_FILE};
+use crate::secrets;
+use crate::secrets::master_key::get_cached_master_key;
+use crate::secrets::quick_access;
+use crate::utils::{CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use std::collections::HashMap;
+use std::process::Command;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    let (profiles, program, args) = match command {
+        Commands::Run { profiles, command } => {
+            let profiles = if profiles.is_empty() {
+                project_profiles(|config| config.default_profile())?
+            } else {
+                profiles
+            };
+            let mut command = command.into_iter();
+            let program = command.next().ok_or_else(|| {
+                CcmError::InvalidArgument("No command given (ccm run -- <COMMAND>...)".to_string())
+            })?;
+            (profiles, program, command.collect())
+        }
+        Commands::NpmRun { script, args } => {
+            let profiles = project_profiles(|config| config.for_script(&script))?;
+            let mut npm_args = vec!["run".to_string(), script];
+            if !args.is_empty() {
+                npm_args.push("--".to_string());
+                npm_args.extend(args);
+            }
+            (profiles, npm_program().to_string(), npm_args)
+        }
+        Commands::Cargo { args } => {
+            let subcommand = args.iter().find(|a| !a.starts_with('-')).cloned();
+            let profiles = project_profiles(|config| config.for_cargo(subcommand.as_deref()))?;
+            (profiles, "cargo".to_string(), args)
+        }
+        _ => unreachable!(),
+    };
+
+    if profiles.is_empty() {
+        return Err(CcmError::InvalidArgument(format!(
+            "No entries to inject: pass --profile or add a {} to the project",
+            PROJECT_FILE
+        )));
+    }
+
+    let env_vars = collect_env_vars(&profiles).await?;
+    run_with_env(&program, &args, &env_vars)
+}
+
+/// Entries picked from the project's `.ccm.toml` (none outside a project)
+fn project_profiles(
+    select: impl FnOnce(&project::ProjectConfig) -> Vec<String>,
+) -> Result<Vec<String>> {
+    Ok(project::load_project_config()?
+        .map(|(_, config)| select(&config))
+        .unwrap_or_default())
+}
+
+/// Environment variables of all `profiles`, later entries overriding earlier ones
+async fn collect_env_vars(profiles: &[String]) -> Result<HashMap<String, String>> {
+    let mut env_vars = HashMap::new();
+
+    if matches!(get_cached_master_key(), Err(CcmError::PinRequired)) {
+        // Quick-access entries work without unlocking the vault
+        let mut quick = Vec::new();
+        for name in profiles {
+            match quick_access::get(name)? {
+                Some(found) => quick.push(found),
+                None => break,
+            }
+        }
+        if quick.len() == profiles.len() {
+            for (entry, secret) in quick {
+                env_vars.extend(env::get_env_mappings_with_secret(&entry, &secret));
+            }
+            return Ok(env_vars);
+        }
+        crate::auth::ensure_master_key_loaded().await?;
+    }
+
+    for name in profiles {
+        let (entry, secret) = secrets::use_entry(name)?;
+        let vars = env::get_env_mappings_with_secret(&entry, &secret);
+        if vars.is_empty() {
+            eprintln!(
+                "{} No environment variable mappings found for entry '{}'",
+                "⚠️".yellow(),
+                name
+            );
+        }
+        env_vars.extend(vars);
+    }
+
+    Ok(env_vars)
+}
+
+/// npm is a batch script on Windows, which `Command` does not resolve by itself
+fn npm_program() -> &'static str {
+    if cfg!(windows) {
+        "npm.cmd"
+    } else {
+        "npm"
+    }
+}
+
+/// Run `program` with the extra variables and exit with its status
+pub(crate) fn run_with_env(
+    program: &str,
+    args: &[String],
+    env_vars: &HashMap<String, String>,
+) -> Result<()> {
+    let status = Command::new(program)
+        .env_remove(crate::auth::PIN_ENV)
+        .args(args)
+        .envs(env_vars)
+        .status()
+        .map_err(|e| CcmError::Unknown(format!("Failed to run {}: {}", program, e)))?;
+
+    if !status.success() {
+        // Pass the exit code through so scripts and CI see the real result
+        std::process::exit(status.code().unwrap_or(1));
+    }
+
+    Ok(())
+}
@@ -1,34 +1,135 @@
 // Search command implementation
 
+use crate::db::search::EntryMatch;
+use crate::db::EntryFilter;
 use crate::secrets;
-use crate::utils::Result;
+use crate::utils::csv_parser::extract_domain;
+use crate::utils::{output, CcmError, Result};
 use crate::Commands;
 use colored::Colorize;
+use serde_json::json;
 
 pub async fn execute(command: Commands) -> Result<()> {
-    if let Commands::Search { query } = command {
-        do_search(&query)
+    if let Commands::Search {
+        query,
+        domain,
+        regex,
+        tags,
+        fields,
+        name_only,
+    } = command
+    {
+        let domain = domain
+            .map(|d| {
+                extract_domain(&d)
+                    .map(|d| d.to_lowercase())
+                    .ok_or_else(|| CcmError::InvalidArgument(format!("Invalid domain: '{}'", d)))
+            })
+            .transpose()?;
+        let query = query.unwrap_or_default();
+        // A regex query is matched on the client, after the SQL filters
+        let matcher = EntryMatch::new(
+            (regex && !query.is_empty()).then_some(query.as_str()),
+            &fields,
+            regex,
+        )?;
+        let filter = EntryFilter {
+            domain,
+            tags,
+            ..EntryFilter::default()
+        };
+        do_search(&query, regex, filter, &matcher, name_only)
     } else {
         unreachable!()
     }
 }
 
-fn do_search(query: &str) -> Result<()> {
-    let results = secrets::search_entries(query)?;
+/// What was searched for, for the result header
+fn describe(query: &str, regex: bool, filter: &EntryFilter, matcher: &EntryMatch) -> String {
+    let mut parts = Vec::new();
+    if !query.is_empty() {
+        parts.push(if regex {
+            format!("/{}/", query)
+        } else {
+            format!("'{}'", query)
+        });
+    }
+    if let Some(d) = &filter.domain {
+        parts.push(format!("on domain {}", d));
+    }
+    if !filter.tags.is_empty() {
+        parts.push(format!("tagged {}", filter.tags.join(", ")));
+    }
+    if !matcher.fields.is_empty() {
+        let count = matcher.fields.len();
+        parts.push(format!(
+            "with {} field condition{}",
+            count,
+            if count == 1 { "" } else { "s" }
+        ));
+    }
+    parts.join(" ")
+}
+
+fn do_search(
+    query: &str,
+    regex: bool,
+    filter: EntryFilter,
+    matcher: &EntryMatch,
+    name_only: bool,
+) -> Result<()> {
+    let description = describe(query, regex, &filter, matcher);
+
+    // Best match first (by name for a regex)
+    let fts_query = if regex { "" } else { query };
+    let mut results = secrets::search_entries(fts_query, &filter)?;
+    if !matcher.is_empty() {
+        results.retain(|(_, entry)| matcher.matches(entry));
+    }
+
+    if name_only {
+        if output::is_json() {
+            let names: Vec<&String> = results.iter().map(|(name, _)| name).collect();
+            return output::print_json(&names);
+        }
+        for (name, _) in &results {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+
+    if output::is_json() {
+        let matches: Vec<_> = results
+            .iter()
+            .map(|(name, entry)| {
+                json!({
+                    "name": name,
+                    "domain": entry.domain(),
+                    "metadata": entry.metadata,
+                    "tags": entry.tags,
+                    "notes": entry.notes,
+                })
+            })
+            .collect();
+        return output::print_json(&matches);
+    }
 
     if results.is_empty() {
-        println!("No results found for '{}'", query);
+        println!("No results found for {}", description);
         return Ok(());
     }
 
     println!(
-        "Found {} entries matching '{}':",
+        "Found {} entries matching {}:",
         results.len(),
-        query.bold()
+        description.bold()
     );
 
     for (name, entry) in results {
-        println!("  {}", name.bold());
+        match entry.domain() {
+            Some(d) => println!("  {} {}", name.bold(), format!("({})", d).dimmed()),
+            None => println!("  {}", name.bold()),
+        }
 
         // Show metadata (env var mappings)
         if !entry.metadata.is_empty() {
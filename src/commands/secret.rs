@@ -0,0 +1,148 @@
+// Secret command implementation
+
+use crate::secrets;
+use crate::utils::files::{create_private_dir, shred_file};
+use crate::utils::{output, CcmError, Result};
+use crate::{Commands, SecretAction};
+use colored::Colorize;
+use rand::RngCore;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Secret { action } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+
+        match action {
+            SecretAction::Edit { name, force_unlock } => {
+                secrets::ensure_unlocked(&name, force_unlock)?;
+                edit_secret(&name)
+            }
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+/// Open the decrypted secret in an editor and store the result
+fn edit_secret(name: &str) -> Result<()> {
+    let (_, secret) = secrets::get_entry_with_secret(name)?;
+
+    let dir = scratch_dir();
+    if !dir.exists() {
+        create_private_dir(&dir)?;
+    }
+
+    let mut random = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut random);
+    let path = dir.join(format!("ccm-edit-{}.txt", hex::encode(random)));
+
+    create_scratch_file(&path, secret.as_bytes())?;
+
+    // Always shred the plaintext, whatever happens in the editor
+    let edited = run_editor(&path).and_then(|()| Ok(fs::read_to_string(&path)?));
+    if let Err(e) = shred_file(&path) {
+        eprintln!(
+            "{} Failed to remove {}: {}",
+            "⚠️".yellow(),
+            path.display(),
+            e
+        );
+    }
+    let mut edited = edited?;
+
+    // Editors append a final newline; keep the original's convention
+    if !secret.ends_with('\n') {
+        if let Some(stripped) = edited.strip_suffix('\n') {
+            edited = stripped.strip_suffix('\r').unwrap_or(stripped).to_string();
+        }
+    }
+
+    if edited == secret {
+        println!("{} No changes.", "ℹ️".blue());
+        return Ok(());
+    }
+
+    if edited.trim().is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "The edited secret is empty; keeping the old value.".to_string(),
+        ));
+    }
+
+    secrets::update_secret(name, &edited)?;
+    output::success(format!(
+        "Secret updated for: {} ({} lines)",
+        name.cyan().bold(),
+        edited.lines().count()
+    ));
+
+    Ok(())
+}
+
+/// Directory for the plaintext scratch file: the session runtime directory
+/// (tmpfs when it is under XDG_RUNTIME_DIR), or /dev/shm on Linux when only
+/// the disk-backed temp directory is left
+fn scratch_dir() -> PathBuf {
+    let dir = crate::auth::runtime_dir();
+
+    #[cfg(target_os = "linux")]
+    if dir == std::env::temp_dir() && Path::new("/dev/shm").is_dir() {
+        return PathBuf::from("/dev/shm");
+    }
+
+    dir
+}
+
+/// Create a new file readable only by the current user, failing if it exists
+fn create_scratch_file(path: &Path, data: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Run $VISUAL / $EDITOR (which may include arguments, e.g. "code --wait")
+pub fn run_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        });
+
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+
+    let status = Command::new(program)
+        .env_remove(crate::auth::PIN_ENV)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| CcmError::Unknown(format!("Failed to start editor '{}': {}", program, e)))?;
+
+    if !status.success() {
+        return Err(CcmError::Unknown(format!(
+            "Editor '{}' exited with {}; nothing was changed",
+            program, status
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,230 @@
+// Serve command implementation
+//
+// `ccm serve --stdio` answers JSON-RPC 2.0 requests on stdin/stdout for
+// editor plugins and automation (see `crate::rpc`). The vault is unlocked
+// once at startup (agent, --pin-file or CCM_PIN, else a prompt on a
+// terminal) and stays unlocked until stdin closes.
+//
+// Methods:
+//   list   {tags?, domain?}   -> [{name, domain, metadata, tags, notes}]
+//   search {query, domain?}   -> same as list
+//   get    {name, field?}     -> {name, metadata, tags, notes, secret}
+//                                or {name, field, value}
+//   use    {name}             -> {name, env}; records the active entry, the
+//                                client applies the variables itself
+//
+// `ccm serve --listen ADDR` instead shares the vault's storage over HTTP
+// with `ccm --remote` clients (see `crate::remote`).
+
+use crate::db::EntryFilter;
+use crate::env;
+use crate::remote::{self, server};
+use crate::rpc::{self, RpcError};
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::csv_parser::extract_domain;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Serve {
+        stdio,
+        listen,
+        tls_cert,
+        tls_key,
+        token_file,
+        max_connections,
+    } = command
+    {
+        if let Some(addr) = listen {
+            let tls = match (tls_cert, tls_key) {
+                (Some(cert), Some(key)) => Some((cert, key)),
+                _ => None,
+            };
+            return listen_http(&addr, tls, token_file.as_deref(), max_connections).await;
+        }
+        if tls_cert.is_some() || token_file.is_some() {
+            return Err(CcmError::InvalidArgument(
+                "--tls-cert, --tls-key and --token-file apply to --listen".to_string(),
+            ));
+        }
+        if !stdio {
+            return Err(CcmError::InvalidArgument(
+                "Choose a transport: ccm serve --stdio or --listen ADDR".to_string(),
+            ));
+        }
+        crate::auth::ensure_master_key_loaded().await?;
+        rpc::serve(std::io::stdin().lock(), std::io::stdout().lock(), call)
+    } else {
+        unreachable!()
+    }
+}
+
+/// Serve the local vault's storage to `ccm --remote` clients
+async fn listen_http(
+    addr: &str,
+    tls: Option<(PathBuf, PathBuf)>,
+    token_file: Option<&Path>,
+    max_connections: usize,
+) -> Result<()> {
+    if remote::remote_url().is_some() {
+        return Err(CcmError::InvalidArgument(
+            "ccm serve --listen serves the local vault; drop --remote / CCM_REMOTE".to_string(),
+        ));
+    }
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    if tls.is_none() && !local.ip().is_loopback() {
+        return Err(CcmError::InvalidArgument(format!(
+            "{} is reachable from other machines: serve HTTPS with --tls-cert and --tls-key",
+            local
+        )));
+    }
+    let tls = tls
+        .map(|(cert, key)| server::tls_config(&cert, &key))
+        .transpose()?;
+
+    let (token, generated) = match token_file {
+        Some(path) => (std::fs::read_to_string(path)?.trim().to_string(), false),
+        None => match std::env::var(remote::SERVER_TOKEN_ENV) {
+            Ok(token) if !token.trim().is_empty() => (token.trim().to_string(), false),
+            _ => (hex::encode(rand::random::<[u8; 32]>()), true),
+        },
+    };
+    if token.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "The server token is empty".to_string(),
+        ));
+    }
+
+    crate::auth::ensure_master_key_loaded().await?;
+    let backend = Arc::new(crate::db::get_database()?);
+
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    output::success(format!("Serving the vault on {}://{}", scheme, local));
+    if generated {
+        println!(
+            "  Clients: {}={} ccm --remote {}://{} ...",
+            remote::REMOTE_TOKEN_ENV,
+            token,
+            scheme,
+            local
+        );
+    }
+    server::Server::new(backend, &token, tls, max_connections).run(listener)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListParams {
+    #[serde(default)]
+    tags: Vec<String>,
+    domain: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchParams {
+    query: String,
+    domain: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GetParams {
+    name: String,
+    field: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UseParams {
+    name: String,
+}
+
+fn call(method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+    match method {
+        "list" => {
+            let params: ListParams = rpc::params(params)?;
+            let filter = EntryFilter {
+                tags: params.tags,
+                domain: domain_filter(params.domain)?,
+                ..EntryFilter::default()
+            };
+            let mut entries: Vec<_> = secrets::list_entries_filtered(&filter)?
+                .into_iter()
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Ok(entries
+                .iter()
+                .map(|(name, entry)| entry_json(name, entry))
+                .collect())
+        }
+        "search" => {
+            let params: SearchParams = rpc::params(params)?;
+            let filter = EntryFilter {
+                domain: domain_filter(params.domain)?,
+                ..EntryFilter::default()
+            };
+            let results = secrets::search_entries(&params.query, &filter)?;
+            Ok(results
+                .iter()
+                .map(|(name, entry)| entry_json(name, entry))
+                .collect())
+        }
+        "get" => {
+            let params: GetParams = rpc::params(params)?;
+            let (entry, secret) = secrets::use_entry(&params.name)?;
+            Ok(match params.field {
+                Some(field) => {
+                    let value = super::get::field_value(&entry, secret, Some(&field))?;
+                    json!({ "name": params.name, "field": field, "value": value })
+                }
+                None => json!({
+                    "name": params.name,
+                    "metadata": entry.metadata,
+                    "tags": entry.tags,
+                    "notes": entry.notes,
+                    "secret": secret,
+                }),
+            })
+        }
+        "use" => {
+            let params: UseParams = rpc::params(params)?;
+            let (entry, secret) = secrets::use_entry(&params.name)?;
+            let env_vars = env::get_env_mappings_with_secret(&entry, &secret);
+            if !env_vars.is_empty() {
+                env::record_active_entry(&params.name, &env_vars)?;
+            }
+            Ok(json!({ "name": params.name, "env": env_vars }))
+        }
+        _ => Err(RpcError::method_not_found(method)),
+    }
+}
+
+/// Normalize a domain param like `ccm search --domain`
+fn domain_filter(domain: Option<String>) -> std::result::Result<Option<String>, RpcError> {
+    domain
+        .map(|d| {
+            extract_domain(&d)
+                .map(|d| d.to_lowercase())
+                .ok_or_else(|| RpcError::invalid_params(format!("invalid domain '{}'", d)))
+        })
+        .transpose()
+}
+
+/// An entry without its secret
+fn entry_json(name: &str, entry: &Entry) -> Value {
+    json!({
+        "name": name,
+        "domain": entry.domain(),
+        "metadata": entry.metadata,
+        "tags": entry.tags,
+        "notes": entry.notes,
+    })
+}
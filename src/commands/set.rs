@@ -47,11 +47,7 @@ fn do_set(action: &str, entry_type: Option<&str>) -> Result<()> {
     let db = db::get_database()?;
     db.save_setting("default_type", &normalized_type)?;
 
-    println!(
-        "{} Default type set to: {}",
-        "✅".green(),
-        normalized_type.bold()
-    );
+    output::success(format!("Default type set to: {}", normalized_type.bold()));
     println!(
         "   When adding entries without specifying type, \"{}\" will be used.",
         normalized_type
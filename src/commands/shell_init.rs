@@ -0,0 +1,17 @@
+// Shell-init command implementation
+//
+// Prints the `ccm` shell function from `env::shell`; it needs no vault, so
+// rc files can eval it before anything is unlocked.
+
+use crate::env::shell;
+use crate::utils::Result;
+use crate::Commands;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::ShellInit { shell, no_prompt } = command {
+        print!("{}", shell::init_script(shell, !no_prompt)?);
+        Ok(())
+    } else {
+        unreachable!()
+    }
+}
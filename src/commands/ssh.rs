@@ -0,0 +1,115 @@
+// SSH command implementation
+//
+// `ccm ssh add` stores a private key file as an entry's secret; `write` puts
+// it back in a file only the user can read, and `add-agent` hands it to
+// ssh-agent without writing it anywhere.
+
+use crate::types::Entry;
+use crate::utils::{output, ssh, CcmError, Result};
+use crate::{Commands, SshAction};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Ssh { action } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+
+        match action {
+            SshAction::Add {
+                name,
+                key_file,
+                tags,
+                notes,
+            } => add(&name, &key_file, tags, notes),
+            SshAction::Write { name, path } => write(&name, path),
+            SshAction::AddAgent { name, lifetime } => add_agent(&name, lifetime),
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+fn add(name: &str, key_file: &Path, tags: Option<String>, notes: Option<String>) -> Result<()> {
+    // Normalize and validate name against the configured policy
+    let policy = crate::db::get_database()?.get_name_policy()?;
+    let normalized = policy.normalize(name);
+    policy.validate(&normalized)?;
+    if normalized != name {
+        println!("{} Name normalized: {} → {}", "ℹ️".blue(), name, normalized);
+    }
+    let name = normalized.as_str();
+
+    let key = std::fs::read_to_string(key_file).map_err(|e| {
+        CcmError::InvalidArgument(format!("Cannot read {}: {}", key_file.display(), e))
+    })?;
+    ssh::check_private_key(&key)?;
+
+    let mut metadata = HashMap::new();
+    metadata.insert(ssh::PRIVATE_KEY_VAR.to_string(), "SECRET".to_string());
+    let public_key = ssh::public_key_for(key_file);
+    if let Some(public_key) = &public_key {
+        metadata.insert(ssh::PUBLIC_KEY_VAR.to_string(), public_key.clone());
+    }
+
+    let mut entry = Entry::new(name.to_string(), metadata);
+    let mut tags_vec = vec![ssh::SSH_TAG.to_string()];
+    for tag in tags.iter().flat_map(|t| t.split(',')) {
+        let tag = tag.trim();
+        if !tag.is_empty() && !tags_vec.iter().any(|t| t == tag) {
+            tags_vec.push(tag.to_string());
+        }
+    }
+    entry.tags = Some(tags_vec);
+    entry.notes = notes;
+
+    crate::secrets::add_entry(name, entry, &ssh::normalize_key(&key))?;
+
+    output::success(format!("Added SSH key: {}", name.cyan().bold()));
+    if !output::is_quiet() {
+        match public_key {
+            Some(public_key) => println!("   Public key: {}", public_key.dimmed()),
+            None => println!(
+                "   {}",
+                format!("(no {}.pub found next to it)", key_file.display()).dimmed()
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn write(name: &str, path: Option<PathBuf>) -> Result<()> {
+    let (_, key) = crate::secrets::use_entry(name)?;
+    ssh::check_private_key(&key)?;
+
+    let path = match path {
+        Some(path) => path,
+        None => ssh::key_dir()?.join(ssh::key_file_name(name)),
+    };
+    ssh::write_key(&path, &key)?;
+
+    // Only the path on stdout, so that ssh -i "$(ccm ssh write NAME)" works
+    if output::is_json() {
+        output::print_json(&serde_json::json!({ "path": path }))?;
+    } else {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+fn add_agent(name: &str, lifetime: Option<u32>) -> Result<()> {
+    let (_, key) = crate::secrets::use_entry(name)?;
+    ssh::check_private_key(&key)?;
+    ssh::add_to_agent(&key, lifetime)?;
+
+    let expiry = lifetime
+        .map(|seconds| format!(" for {}s", seconds))
+        .unwrap_or_default();
+    output::success(format!(
+        "Added {} to ssh-agent{}",
+        name.cyan().bold(),
+        expiry
+    ));
+    Ok(())
+}
@@ -1,7 +1,7 @@
 // Stats command implementation
 
 use crate::db;
-use crate::utils::Result;
+use crate::utils::{output, Result};
 use crate::Commands;
 use colored::Colorize;
 use std::fs;
@@ -16,21 +16,38 @@ pub async fn execute(command: Commands) -> Result<()> {
 
 fn do_stats(verbose: bool) -> Result<()> {
     let db = db::get_database()?;
-    let entries = db.get_all_entries()?;
+
+    // Stream entries instead of loading them all into memory
+    let mut total = 0;
+    let mut with_secret = 0;
+    db.for_each_entry(&db::EntryFilter::default(), |entry| {
+        total += 1;
+        if entry.has_secret_placeholder() {
+            with_secret += 1;
+        }
+        Ok(())
+    })?;
+
+    let db_path = crate::db::db_path();
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "entries": total,
+            "entriesWithSecret": with_secret,
+            "databaseBytes": fs::metadata(&db_path).ok().map(|m| m.len()),
+            "databasePath": db_path,
+            "pinEnabled": crate::auth::pin::has_pin().unwrap_or(false),
+            "masterKeyPresent": crate::secrets::master_key::has_master_key().unwrap_or(false),
+        }));
+    }
 
     println!("{}", "Statistics".bold().underline());
     println!();
-    println!("  Total entries: {}", entries.len());
+    println!("  Total entries: {}", total);
 
     // Count entries with SECRET placeholder
-    let with_secret = entries
-        .values()
-        .filter(|e| e.has_secret_placeholder())
-        .count();
     println!("  Entries with secrets: {}", with_secret);
 
     // Get database file size
-    let db_path = crate::db::db_path();
     if let Ok(metadata) = fs::metadata(&db_path) {
         let size_bytes = metadata.len();
         let size_str = format_file_size(size_bytes);
@@ -0,0 +1,222 @@
+// Status command implementation
+
+use crate::auth;
+use crate::env;
+use crate::secrets;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use chrono::Local;
+use colored::Colorize;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Status = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        do_status()
+    } else {
+        unreachable!()
+    }
+}
+
+/// Show what `ccm use` applied in this shell and in other live shells
+fn print_activations() -> Result<()> {
+    let activations = env::get_activations()?;
+    if activations.is_empty() {
+        return Ok(());
+    }
+
+    let shell_pid = auth::get_shell_pid().unwrap_or_else(std::process::id);
+    let (here, elsewhere): (Vec<_>, Vec<_>) = activations
+        .into_iter()
+        .partition(|a| a.shell_pid == shell_pid);
+
+    match here.first() {
+        Some(a) => println!(
+            "  Entry {} active in this shell since {}{}",
+            a.name.cyan().bold(),
+            format_time(&a.activated_at),
+            format_project(a)
+        ),
+        None => println!("  No entry applied in this shell"),
+    }
+
+    let others: Vec<_> = elsewhere
+        .into_iter()
+        .filter(|a| auth::is_process_alive(a.shell_pid))
+        .collect();
+    if !others.is_empty() {
+        println!("  Other shells:");
+        for a in others {
+            println!(
+                "    {} in shell {} since {}{}",
+                a.name.cyan(),
+                a.shell_pid,
+                format_time(&a.activated_at),
+                format_project(&a)
+            );
+        }
+    }
+    println!();
+
+    Ok(())
+}
+
+/// Local time of an RFC 3339 timestamp: "10:32" today, else with the date
+fn format_time(timestamp: &str) -> String {
+    match chrono::DateTime::parse_from_rfc3339(timestamp) {
+        Ok(time) => {
+            let local = time.with_timezone(&Local);
+            if local.date_naive() == Local::now().date_naive() {
+                local.format("%H:%M").to_string()
+            } else {
+                local.format("%Y-%m-%d %H:%M").to_string()
+            }
+        }
+        Err(_) => timestamp.to_string(),
+    }
+}
+
+fn format_project(activation: &env::Activation) -> String {
+    match &activation.project {
+        Some(project) => format!(" ({})", project).dimmed().to_string(),
+        None => String::new(),
+    }
+}
+
+/// How an exported variable compares with the active entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VarState {
+    Unset,
+    Unmapped,
+    Matches,
+    Differs,
+    Added,
+}
+
+impl VarState {
+    fn code(self) -> &'static str {
+        match self {
+            VarState::Unset => "unset",
+            VarState::Unmapped => "unmapped",
+            VarState::Matches => "matches",
+            VarState::Differs => "differs",
+            VarState::Added => "added",
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            VarState::Unset => "not set in current shell".red().to_string(),
+            VarState::Unmapped => "no longer mapped by entry".yellow().to_string(),
+            VarState::Matches => "matches".green().to_string(),
+            VarState::Differs => "differs from entry".yellow().to_string(),
+            VarState::Added => "added since last use".yellow().to_string(),
+        }
+    }
+}
+
+/// Compare the exported variables with what the entry maps to now
+fn variable_states(
+    active: &env::ActiveEntry,
+    expected: Option<&std::collections::HashMap<String, String>>,
+) -> Vec<(String, VarState)> {
+    let mut states: Vec<(String, VarState)> = active
+        .env_vars
+        .iter()
+        .map(|key| {
+            let current = std::env::var(key).ok();
+            let wanted = expected.and_then(|vars| vars.get(key));
+            let state = match (current.as_ref(), wanted) {
+                (None, _) => VarState::Unset,
+                (Some(_), None) => VarState::Unmapped,
+                (Some(value), Some(wanted)) if value == wanted => VarState::Matches,
+                (Some(_), Some(_)) => VarState::Differs,
+            };
+            (key.clone(), state)
+        })
+        .collect();
+
+    // Variables added to the entry after it was applied
+    if let Some(vars) = expected {
+        let mut added: Vec<&String> = vars
+            .keys()
+            .filter(|k| !active.env_vars.contains(k))
+            .collect();
+        added.sort();
+        states.extend(added.into_iter().map(|k| (k.clone(), VarState::Added)));
+    }
+    states
+}
+
+fn do_status() -> Result<()> {
+    let active = match env::get_active_entry()? {
+        Some(active) => active,
+        None if output::is_json() => {
+            return output::print_json(&serde_json::json!({ "active": null }));
+        }
+        None => {
+            println!("No active entry. Run 'ccm use <NAME>' to activate one.");
+            return Ok(());
+        }
+    };
+
+    // Resolve the values the entry currently maps to
+    let expected = match secrets::get_entry_with_secret(&active.name) {
+        Ok((entry, secret)) => Some(env::get_env_mappings_with_secret(&entry, &secret)),
+        Err(CcmError::EntryNotFound(_)) => None,
+        Err(e) => return Err(e),
+    };
+    let states = variable_states(&active, expected.as_ref());
+    let mismatched = states
+        .iter()
+        .filter(|(_, state)| *state != VarState::Matches)
+        .count();
+
+    if output::is_json() {
+        let variables: Vec<_> = states
+            .iter()
+            .map(|(name, state)| serde_json::json!({ "name": name, "state": state.code() }))
+            .collect();
+        return output::print_json(&serde_json::json!({
+            "active": active.name,
+            "activatedAt": active.activated_at,
+            "entryExists": expected.is_some(),
+            "variables": variables,
+            "matches": mismatched == 0,
+        }));
+    }
+
+    println!("{}", "Status".bold().underline());
+    println!();
+    println!("  Active entry: {}", active.name.cyan().bold());
+    println!("  Activated at: {}", active.activated_at.dimmed());
+    println!();
+
+    print_activations()?;
+
+    if expected.is_none() {
+        println!("{} Entry '{}' no longer exists", "⚠️".yellow(), active.name);
+        println!();
+    }
+
+    println!("Exported Variables:");
+    for (key, state) in &states {
+        println!("  {} ({})", key.cyan(), state.label());
+    }
+
+    println!();
+    if mismatched == 0 {
+        output::success("Current shell matches the active entry");
+    } else {
+        println!(
+            "{} Current shell does not match the active entry",
+            "⚠️".yellow()
+        );
+        println!(
+            "   Run 'ccm use {}' and reload your shell to refresh it",
+            active.name
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,532 @@
+// Sync command implementation
+//
+// `ccm sync <provider> pull|push` copies entries between the vault and an
+// external secret store (see `crate::sync`). `ccm sync git` keeps a git
+// repository of encrypted entries instead (see `crate::sync::git`), and
+// `ccm sync webdav|s3` versions of an encrypted backup (`crate::sync::blob`).
+
+use super::export::{self, ExportData, ExportFile};
+use crate::sync::aws::{AwsCredentials, AwsService, AwsStore};
+use crate::sync::blob::{self, BlobStore, PullMode, S3Bucket, WebDav};
+use crate::sync::git::{self, GitRepo, GitReport};
+use crate::sync::{self, azure::AzureKeyVault, gcp::GcpSecretManager, vault::VaultKv};
+use crate::sync::{SyncBackend, SyncReport};
+use crate::utils::{output, recipients, CcmError, Result};
+use crate::{BlobSyncArgs, Commands, SyncArgs, SyncProvider};
+use colored::Colorize;
+use dialoguer::Password;
+use std::path::Path;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Sync { provider } = command {
+        match provider {
+            SyncProvider::Vault {
+                mode,
+                addr,
+                path,
+                options,
+            } => {
+                let backend = VaultKv::new(addr.as_deref(), &path)?;
+                run(&backend, &mode, &options, &format!("Vault ({})", path)).await
+            }
+            SyncProvider::Aws {
+                mode,
+                prefix,
+                service,
+                credentials,
+                region,
+                options,
+            } => {
+                let vars = aws_vars(credentials.as_deref()).await?;
+                let (service, store) = match service.as_str() {
+                    "ssm" => (AwsService::Ssm, "SSM Parameter Store"),
+                    _ => (AwsService::SecretsManager, "Secrets Manager"),
+                };
+                let backend = AwsStore::new(
+                    AwsCredentials::from_vars(&vars)?,
+                    region.as_deref(),
+                    service,
+                    &prefix,
+                )?;
+                run(
+                    &backend,
+                    &mode,
+                    &options,
+                    &format!("{} ({})", store, prefix),
+                )
+                .await
+            }
+            SyncProvider::Azure {
+                mode,
+                vault,
+                prefix,
+                options,
+            } => {
+                let backend = AzureKeyVault::new(&vault, &prefix)?;
+                run(
+                    &backend,
+                    &mode,
+                    &options,
+                    &format!("Azure Key Vault ({})", vault),
+                )
+                .await
+            }
+            SyncProvider::Gcp {
+                mode,
+                project,
+                prefix,
+                options,
+            } => {
+                let backend = GcpSecretManager::new(project.as_deref(), &prefix)?;
+                run(&backend, &mode, &options, "Secret Manager").await
+            }
+            SyncProvider::Git {
+                mode,
+                origin,
+                dry_run,
+                force_unlock,
+            } => run_git(&mode, origin.as_deref(), dry_run, force_unlock).await,
+            SyncProvider::Webdav {
+                mode,
+                url,
+                user,
+                credentials,
+                options,
+            } => {
+                let password = match credentials {
+                    Some(name) => Some(super::launch::entry_with_secret(&name).await?.1),
+                    None => std::env::var(blob::WEBDAV_PASSWORD_ENV).ok(),
+                };
+                let store = WebDav::new(&url, user.as_deref(), password.as_deref())?;
+                run_blob(&store, &mode, &options, &url).await
+            }
+            SyncProvider::S3 {
+                mode,
+                bucket,
+                prefix,
+                endpoint,
+                region,
+                credentials,
+                options,
+            } => {
+                let vars = aws_vars(credentials.as_deref()).await?;
+                let store = S3Bucket::new(
+                    AwsCredentials::from_vars(&vars)?,
+                    region.as_deref(),
+                    endpoint.as_deref(),
+                    &bucket,
+                    &prefix,
+                )?;
+                let location = format!("s3://{}/{}", bucket, prefix.trim_start_matches('/'));
+                run_blob(&store, &mode, &options, &location).await
+            }
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+/// The variables of an aws preset entry, else the environment
+async fn aws_vars(credentials: Option<&str>) -> Result<std::collections::HashMap<String, String>> {
+    Ok(match credentials {
+        Some(name) => {
+            let (entry, secret) = super::launch::entry_with_secret(name).await?;
+            crate::env::get_env_mappings_with_secret(&entry, &secret)
+        }
+        None => std::env::vars().collect(),
+    })
+}
+
+async fn run(backend: &dyn SyncBackend, mode: &str, options: &SyncArgs, store: &str) -> Result<()> {
+    if mode == "pull" && !options.entries.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "--entry only applies to push".to_string(),
+        ));
+    }
+    if mode != "pull" && options.force_unlock {
+        return Err(CcmError::InvalidArgument(
+            "--force-unlock applies to pull".to_string(),
+        ));
+    }
+
+    // Ensure master key is loaded (prompts for PIN if needed)
+    crate::auth::ensure_master_key_loaded().await?;
+
+    let report = if mode == "pull" {
+        sync::pull(
+            backend,
+            options.overwrite,
+            options.force_unlock,
+            options.dry_run,
+        )?
+    } else {
+        sync::push(
+            backend,
+            &options.entries,
+            options.overwrite,
+            options.dry_run,
+        )?
+    };
+
+    if output::is_json() {
+        output::print_json(&report)?;
+    } else {
+        print_report(
+            &report,
+            mode,
+            store,
+            options.dry_run,
+            "differs; --overwrite to replace",
+        );
+    }
+
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(CcmError::Remote(format!(
+            "{} entr{} could not be synced",
+            report.failed.len(),
+            if report.failed.len() == 1 { "y" } else { "ies" }
+        )))
+    }
+}
+
+async fn run_git(
+    mode: &str,
+    origin: Option<&str>,
+    dry_run: bool,
+    force_unlock: bool,
+) -> Result<()> {
+    let dir = git::default_dir();
+    if force_unlock && mode != "pull" {
+        return Err(CcmError::InvalidArgument(
+            "--force-unlock applies to pull".to_string(),
+        ));
+    }
+    if mode == "init" {
+        if dry_run {
+            return Err(CcmError::InvalidArgument(
+                "--dry-run applies to push and pull".to_string(),
+            ));
+        }
+        GitRepo::init(&dir, origin)?;
+        if output::is_json() {
+            output::print_json(&serde_json::json!({ "path": dir, "origin": origin }))?;
+        } else {
+            output::success(format!("Sync repository ready in {}", dir.display()));
+            if !output::is_quiet() {
+                println!("  Next: ccm sync git pull (origin has entries) or ccm sync git push");
+            }
+        }
+        return Ok(());
+    }
+    if origin.is_some() {
+        return Err(CcmError::InvalidArgument(
+            "--origin applies to init".to_string(),
+        ));
+    }
+    let repo = GitRepo::open(&dir)?;
+
+    // Ensure master key is loaded (prompts for PIN if needed)
+    crate::auth::ensure_master_key_loaded().await?;
+
+    let report = if mode == "pull" {
+        repo.pull(dry_run, force_unlock)?
+    } else {
+        repo.push(&git::vault_snapshot()?, dry_run)?
+    };
+
+    if output::is_json() {
+        output::print_json(&report)?;
+    } else {
+        print_git_report(&report, mode, dry_run);
+    }
+    Ok(())
+}
+
+async fn run_blob(
+    store: &dyn BlobStore,
+    mode: &str,
+    options: &BlobSyncArgs,
+    location: &str,
+) -> Result<()> {
+    let pull_only = options.identity.is_some()
+        || options.version.is_some()
+        || options.overwrite
+        || options.merge
+        || options.force_unlock;
+    if mode != "pull" && pull_only {
+        return Err(CcmError::InvalidArgument(
+            "--identity, --version, --overwrite, --merge and --force-unlock apply to pull"
+                .to_string(),
+        ));
+    }
+    if mode != "push" && !options.age_recipients.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "--age-recipient applies to push".to_string(),
+        ));
+    }
+
+    if mode == "versions" {
+        let mut versions = blob::versions(store)?;
+        versions.reverse();
+        if output::is_json() {
+            output::print_json(&versions)?;
+        } else if versions.is_empty() {
+            println!("No versions in {}", location);
+        } else {
+            println!("{} (newest first):", location);
+            for version in &versions {
+                println!("  {}", version);
+            }
+        }
+        return Ok(());
+    }
+
+    // Ensure master key is loaded (prompts for PIN if needed)
+    crate::auth::ensure_master_key_loaded().await?;
+
+    if mode == "push" {
+        push_blob(store, options, location)
+    } else {
+        pull_blob(store, options, location)
+    }
+}
+
+fn push_blob(store: &dyn BlobStore, options: &BlobSyncArgs, location: &str) -> Result<()> {
+    let backup = export::backup_data(&[])?;
+    let age = !options.age_recipients.is_empty();
+    let version = blob::version_name(chrono::Utc::now(), age);
+    let mut versions = blob::versions(store)?;
+    versions.push(version.clone());
+    let stale = blob::stale_versions(&versions, options.keep);
+
+    if !options.dry_run {
+        let data = if age {
+            let json = serde_json::to_string_pretty(&backup)?;
+            recipients::age_encrypt(json.as_bytes(), &options.age_recipients)?
+        } else {
+            let password = sync_password(true)?;
+            serde_json::to_vec_pretty(&export::encrypt_backup(&backup, &password)?)?
+        };
+        store.put(&version, &data)?;
+        for old in stale {
+            store.delete(old)?;
+        }
+    }
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "version": version,
+            "entries": backup.entries.len(),
+            "deleted": stale,
+        }));
+    }
+    for old in stale {
+        let verb = if options.dry_run {
+            "Would delete:"
+        } else {
+            "Deleted:"
+        };
+        println!("  {} {}", verb.red(), old);
+    }
+    let summary = format!(
+        "Uploaded {} to {}: {} entries",
+        version,
+        location,
+        backup.entries.len()
+    );
+    if options.dry_run {
+        println!("{} {}", "Dry run:".cyan(), summary);
+    } else {
+        output::success(summary);
+    }
+    Ok(())
+}
+
+fn pull_blob(store: &dyn BlobStore, options: &BlobSyncArgs, location: &str) -> Result<()> {
+    let version = match &options.version {
+        Some(version) => version.clone(),
+        None => blob::versions(store)?.pop().ok_or_else(|| {
+            CcmError::InvalidArgument(format!(
+                "No versions in {}; run push on another machine first",
+                location
+            ))
+        })?,
+    };
+    let data = store.get(&version)?.ok_or_else(|| {
+        CcmError::InvalidArgument(format!("No version {} in {}", version, location))
+    })?;
+    let backup = open_blob(&data, options.identity.as_deref())?;
+
+    let mode = if options.overwrite {
+        PullMode::Overwrite
+    } else if options.merge {
+        PullMode::Merge
+    } else {
+        PullMode::Skip
+    };
+    let report = blob::apply(
+        super::restore::backup_entries(backup)?,
+        mode,
+        options.force_unlock,
+        options.dry_run,
+    )?;
+
+    if output::is_json() {
+        output::print_json(&report)?;
+    } else {
+        let skip_hint = if options.merge {
+            "the local copy is newer"
+        } else {
+            "differs; --overwrite to replace, --merge to keep the newer"
+        };
+        print_report(
+            &report,
+            "pull",
+            &format!("{} ({})", location, version),
+            options.dry_run,
+            skip_hint,
+        );
+    }
+    Ok(())
+}
+
+/// Decrypt a version: age-encrypted, else a password-encrypted backup
+fn open_blob(data: &[u8], identity: Option<&str>) -> Result<ExportData> {
+    if recipients::is_age_file(data) {
+        let identity = identity.ok_or_else(|| {
+            CcmError::InvalidArgument(
+                "This version is encrypted with age. Pass your identity file with --identity <FILE>"
+                    .to_string(),
+            )
+        })?;
+        let json = recipients::age_decrypt(data, Path::new(identity))?;
+        return serde_json::from_slice(&json)
+            .map_err(|e| CcmError::Decryption(format!("Failed to parse decrypted data: {}", e)));
+    }
+
+    let file: ExportFile = serde_json::from_slice(data)
+        .map_err(|e| CcmError::InvalidArgument(format!("Not a ccm backup: {}", e)))?;
+    super::import::check_backup_signature(
+        &file.format,
+        &file.algorithm,
+        &file.data,
+        file.signature.as_ref(),
+    )?;
+    export::decrypt_backup(&file, &sync_password(false)?)
+}
+
+/// The password of the versions: CCM_SYNC_PASSWORD, else asked (twice when
+/// encrypting)
+fn sync_password(confirm: bool) -> Result<String> {
+    if let Ok(password) = std::env::var(blob::SYNC_PASSWORD_ENV) {
+        if !password.is_empty() {
+            return Ok(password);
+        }
+    }
+    let prompt = Password::new().with_prompt("Sync password");
+    let password = if confirm {
+        prompt
+            .with_confirmation("Confirm password", "Passwords do not match.")
+            .interact()
+    } else {
+        prompt.interact()
+    }
+    .map_err(|e| CcmError::Unknown(e.to_string()))?;
+
+    if confirm && password.len() < 6 {
+        return Err(CcmError::InvalidArgument(
+            "Password must be at least 6 characters.".to_string(),
+        ));
+    }
+    Ok(password)
+}
+
+fn print_git_report(report: &GitReport, mode: &str, dry_run: bool) {
+    let verb = |done: &str, planned: &str| format!("{}:", if dry_run { planned } else { done });
+    for name in &report.created {
+        println!("  {} {}", verb("Created", "Would create").green(), name);
+    }
+    for name in &report.updated {
+        println!("  {} {}", verb("Updated", "Would update").yellow(), name);
+    }
+    for name in &report.deleted {
+        println!("  {} {}", verb("Deleted", "Would delete").red(), name);
+    }
+    for (name, fields) in &report.conflicts {
+        println!(
+            "  {} {}: {} (kept the local value)",
+            "Conflict:".red().bold(),
+            name,
+            fields
+        );
+    }
+
+    let changes = format!(
+        "{} created, {} updated, {} deleted",
+        report.created.len(),
+        report.updated.len(),
+        report.deleted.len()
+    );
+    let summary = if mode == "pull" {
+        format!(
+            "Merged origin into the vault: {}, {} conflict{}",
+            changes,
+            report.conflicts.len(),
+            if report.conflicts.len() == 1 { "" } else { "s" }
+        )
+    } else if report.pushed {
+        format!("Pushed the vault to origin: {}", changes)
+    } else {
+        format!("Committed the vault (no origin to push to): {}", changes)
+    };
+    if dry_run {
+        println!("{} {}", "Dry run:".cyan(), summary);
+    } else {
+        output::success(summary);
+    }
+}
+
+pub(crate) fn print_report(
+    report: &SyncReport,
+    mode: &str,
+    store: &str,
+    dry_run: bool,
+    skip_hint: &str,
+) {
+    let (created, updated) = if dry_run {
+        ("Would create", "Would update")
+    } else {
+        ("Created", "Updated")
+    };
+    for name in &report.created {
+        println!("  {} {}", format!("{}:", created).green(), name);
+    }
+    for name in &report.updated {
+        println!("  {} {}", format!("{}:", updated).yellow(), name);
+    }
+    for name in &report.skipped {
+        println!("  {} {} ({})", "Skipped:".dimmed(), name, skip_hint);
+    }
+    for (name, reason) in &report.failed {
+        println!("  {} {}: {}", "Failed:".red(), name, reason);
+    }
+
+    let direction = if mode == "pull" { "from" } else { "to" };
+    let summary = format!(
+        "{} {} {}: {} created, {} updated, {} unchanged, {} skipped",
+        if mode == "pull" { "Pulled" } else { "Pushed" },
+        direction,
+        store,
+        report.created.len(),
+        report.updated.len(),
+        report.unchanged.len(),
+        report.skipped.len()
+    );
+    if dry_run {
+        println!("{} {}", "Dry run:".cyan(), summary);
+    } else {
+        output::success(summary);
+    }
+}
@@ -0,0 +1,75 @@
+// Test command implementation
+//
+// `ccm test NAME` sends the entry's key to its provider (see
+// `core::provider`) and reports whether it works, how long the provider took
+// and what it says about the key.
+
+use crate::core::provider::Provider;
+use crate::secrets;
+use crate::utils::{output, CcmError, Result};
+use crate::Commands;
+use colored::Colorize;
+use serde_json::json;
+use std::time::Duration;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Test { name, timeout } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        let name = super::pick::lookup_name(&name, true)?;
+        do_test(&name, Duration::from_secs(timeout))
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_test(name: &str, timeout: Duration) -> Result<()> {
+    let (entry, secret) = secrets::get_entry_with_secret(name)?;
+    let vars = crate::env::get_env_mappings_with_secret(&entry, &secret);
+    let provider = Provider::detect(name, &vars)?;
+
+    if !output::is_json() && !output::is_quiet() {
+        println!(
+            "Testing {} ({}) at {}",
+            name.cyan().bold(),
+            provider.preset,
+            provider.base_url
+        );
+    }
+    let report = provider.test(timeout)?;
+    let ms = report.elapsed.as_millis();
+
+    if !report.works() {
+        return Err(CcmError::Provider(format!(
+            "{} key rejected: {} from {} after {} ms: {}",
+            name,
+            report.status,
+            report.url,
+            ms,
+            report.error.unwrap_or_default()
+        )));
+    }
+
+    if output::is_json() {
+        let details: serde_json::Map<String, serde_json::Value> = report
+            .details
+            .iter()
+            .map(|(k, v)| (k.clone(), json!(v)))
+            .collect();
+        return output::print_json(&json!({
+            "name": name,
+            "provider": provider.preset,
+            "url": report.url,
+            "status": report.status,
+            "latency_ms": ms,
+            "works": true,
+            "details": details,
+        }));
+    }
+
+    output::success(format!("Key works ({}, {} ms)", report.status, ms));
+    for (key, value) in &report.details {
+        println!("   {}: {}", key, value);
+    }
+    Ok(())
+}
@@ -0,0 +1,193 @@
+// Transfer command implementation
+//
+// `ccm transfer send` shows a code and hands the entries to the first
+// receiver that connects; `ccm transfer receive` finds the sender and adds
+// the entries to the vault (see `crate::transfer`).
+
+use super::export::{self, ExportData};
+use crate::secrets;
+use crate::sync::blob::{self, PullMode};
+use crate::transfer;
+use crate::utils::{glob, output, CcmError, Result};
+use crate::{Commands, TransferAction};
+use colored::Colorize;
+use dialoguer::Input;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long the sender waits for the receiver
+const WAIT: Duration = Duration::from_secs(10 * 60);
+/// How long the receiver looks for the sender
+const DISCOVER_TIMEOUT: Duration = Duration::from_secs(60);
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Transfer { action } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+
+        match action {
+            TransferAction::Send { names, all, port } => send(if all { &[] } else { &names }, port),
+            TransferAction::Receive {
+                code,
+                connect,
+                overwrite,
+                merge,
+                force_unlock,
+            } => {
+                let mode = if overwrite {
+                    PullMode::Overwrite
+                } else if merge {
+                    PullMode::Merge
+                } else {
+                    PullMode::Skip
+                };
+                receive(code, connect.as_deref(), mode, force_unlock)
+            }
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+/// Entry names matching the patterns (all entries when there are none)
+fn resolve_names(patterns: &[String]) -> Result<Vec<String>> {
+    let mut all: Vec<String> = secrets::list_entries()?.into_keys().collect();
+    all.sort();
+    if patterns.is_empty() {
+        return Ok(all);
+    }
+
+    let mut names = Vec::new();
+    for pattern in patterns {
+        let matching: Vec<&String> = all.iter().filter(|n| glob::matches(pattern, n)).collect();
+        if matching.is_empty() {
+            return Err(CcmError::EntryNotFound(pattern.clone()));
+        }
+        for name in matching {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn send(patterns: &[String], port: u16) -> Result<()> {
+    let names = resolve_names(patterns)?;
+    if names.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "The vault has no entries to send".to_string(),
+        ));
+    }
+    let payload = serde_json::to_vec(&export::backup_data(&names)?)?;
+
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))?;
+    let port = listener.local_addr()?.port();
+    let code = transfer::generate_code();
+    let stop = Arc::new(AtomicBool::new(false));
+    transfer::announce(transfer::nameplate(&code)?, port, Arc::clone(&stop))?;
+
+    println!("📤 Ready to send {} entries", names.len());
+    println!("   Code: {}", code.bold());
+    println!("   On the other machine: ccm transfer receive {}", code);
+    if let Some(ip) = transfer::local_ip() {
+        println!(
+            "   {}",
+            format!(
+                "(if it cannot find this one, add --connect {}:{})",
+                ip, port
+            )
+            .dimmed()
+        );
+    }
+
+    let accepted = accept(&listener, WAIT);
+    stop.store(true, Ordering::Relaxed);
+    let (mut stream, peer) = accepted?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    println!("🔐 {} connected, checking the code...", peer.ip());
+    transfer::send_over(&mut stream, &code, &payload)?;
+    output::success(format!("Sent {} entries to {}", names.len(), peer.ip()));
+    Ok(())
+}
+
+/// The first connection, within `timeout`
+fn accept(listener: &TcpListener, timeout: Duration) -> Result<(TcpStream, SocketAddr)> {
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                stream.set_nonblocking(false)?;
+                return Ok((stream, peer));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                if Instant::now() >= deadline {
+                    return Err(CcmError::Remote(
+                        "No receiver connected; the code has expired".to_string(),
+                    ));
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn receive(
+    code: Option<String>,
+    connect: Option<&str>,
+    mode: PullMode,
+    force_unlock: bool,
+) -> Result<()> {
+    let code = match code {
+        Some(code) => code,
+        None => Input::<String>::new()
+            .with_prompt("Transfer code")
+            .interact_text()?,
+    };
+    let code = code.trim();
+    let nameplate = transfer::nameplate(code)?;
+
+    let addr = match connect {
+        Some(address) => address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| CcmError::InvalidArgument(format!("Cannot resolve {}", address)))?,
+        None => {
+            println!("🔎 Looking for the sender on the local network...");
+            transfer::discover(nameplate, DISCOVER_TIMEOUT)?
+        }
+    };
+    let mut stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)
+        .map_err(|e| CcmError::Remote(format!("Cannot connect to {}: {}", addr, e)))?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let payload = transfer::receive_over(&mut stream, code)?;
+    let backup: ExportData = serde_json::from_slice(&payload)?;
+    let report = blob::apply(
+        super::restore::backup_entries(backup)?,
+        mode,
+        force_unlock,
+        false,
+    )?;
+
+    if output::is_json() {
+        output::print_json(&report)?;
+    } else {
+        let skip_hint = if mode == PullMode::Merge {
+            "the local copy is newer"
+        } else {
+            "differs; --overwrite to replace, --merge to keep the newer"
+        };
+        super::sync::print_report(&report, "pull", &addr.ip().to_string(), false, skip_hint);
+    }
+    Ok(())
+}
@@ -1,7 +1,9 @@
 // Update command implementation
 
+use crate::commands::add::{generate_secret, show_generated_secret};
 use crate::secrets;
-use crate::utils::Result;
+use crate::utils::input::{read_secret_file, resolve_secret};
+use crate::utils::{output, Result};
 use crate::Commands;
 use colored::Colorize;
 
@@ -9,14 +11,43 @@ pub async fn execute(command: Commands) -> Result<()> {
     if let Commands::Update {
         name,
         secret,
+        secret_file,
+        generate,
         env,
         tags,
         notes,
+        force_unlock,
     } = command
     {
         // Ensure master key is loaded (prompts for PIN if needed)
         crate::auth::ensure_master_key_loaded().await?;
-        do_update(&name, secret.as_deref(), &env, tags.as_deref(), notes.as_deref())
+        // Fail on a misspelt name (with suggestions) before asking for anything
+        secrets::lookup_name(&name, false)?;
+        if secret.is_some() || secret_file.is_some() || generate.generate {
+            secrets::ensure_unlocked(&name, force_unlock)?;
+        }
+
+        // "-" reads the new secret from stdin
+        let generated = generate_secret(&generate)?;
+        let secret = match (&generated, secret_file) {
+            (Some(value), _) => Some(value.clone()),
+            (None, Some(path)) => Some(read_secret_file(&path)?),
+            (None, None) => secret.map(|s| resolve_secret(Some(s))).transpose()?,
+        };
+
+        do_update(
+            &name,
+            secret.as_deref(),
+            &env,
+            tags.as_deref(),
+            notes.as_deref(),
+        )?;
+
+        if let Some(value) = generated {
+            show_generated_secret(&value, generate.copy);
+        }
+
+        Ok(())
     } else {
         unreachable!()
     }
@@ -98,11 +129,7 @@ fn do_update(
     if updated {
         entry.updated_at = Some(chrono::Utc::now().to_rfc3339());
         secrets::update_entry(name, entry)?;
-        println!(
-            "{} Updated entry: {}",
-            "✅".green(),
-            name.bold()
-        );
+        output::success(format!("Updated entry: {}", name.bold()));
         for change in &changes {
             println!("  {}", change);
         }
@@ -113,6 +140,7 @@ fn do_update(
         println!();
         println!("Available options:");
         println!("  -s, --secret <VALUE>       Update secret value");
+        println!("  -g, --generate             Generate a random secret");
         println!("  -e, --env VAR=VALUE        Update environment variable mapping");
         println!("      --tags <TAGS>          Update tags (comma-separated)");
         println!("  -n, --notes <NOTES>        Update notes");
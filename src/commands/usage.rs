@@ -0,0 +1,52 @@
+// Usage command implementation
+//
+// `ccm usage NAME` asks the entry's provider how much of the key's credits
+// or quota is left (see `net::usage`).
+
+use crate::core::provider::Provider;
+use crate::net::usage;
+use crate::secrets;
+use crate::utils::{output, Result};
+use crate::Commands;
+use colored::Colorize;
+use serde_json::json;
+use std::time::Duration;
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Usage { name, timeout } = command {
+        // Ensure master key is loaded (prompts for PIN if needed)
+        crate::auth::ensure_master_key_loaded().await?;
+        let name = super::pick::lookup_name(&name, true)?;
+        do_usage(&name, Duration::from_secs(timeout))
+    } else {
+        unreachable!()
+    }
+}
+
+fn do_usage(name: &str, timeout: Duration) -> Result<()> {
+    let (entry, secret) = secrets::get_entry_with_secret(name)?;
+    let vars = crate::env::get_env_mappings_with_secret(&entry, &secret);
+    let provider = Provider::detect(name, &vars)?;
+    let usage = usage::usage(&provider, timeout)?;
+
+    if output::is_json() {
+        let items: serde_json::Map<String, serde_json::Value> = usage
+            .items
+            .iter()
+            .map(|(k, v)| (k.clone(), json!(v)))
+            .collect();
+        return output::print_json(&json!({
+            "name": name,
+            "provider": provider.preset,
+            "source": usage.source,
+            "usage": items,
+        }));
+    }
+
+    println!("{} ({}):", name.cyan().bold(), usage.source);
+    let width = usage.items.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+    for (label, value) in &usage.items {
+        println!("  {:width$}  {}", label, value.bold(), width = width);
+    }
+    Ok(())
+}
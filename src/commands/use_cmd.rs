@@ -1,31 +1,92 @@
 // Use command implementation
 
+use crate::core::health::{self, Health};
 use crate::env;
 use crate::secrets;
-use crate::utils::Result;
+use crate::secrets::master_key::get_cached_master_key;
+use crate::secrets::quick_access;
+use crate::types::Entry;
+use crate::utils::{output, parse_bool, CcmError, Result};
 use crate::Commands;
+use clap_complete::Shell;
 use colored::Colorize;
+use std::collections::HashMap;
 
+#[cfg(unix)]
+use crate::utils::files::write_file_atomic;
 #[cfg(unix)]
 use std::path::PathBuf;
 
 pub async fn execute(command: Commands) -> Result<()> {
-    if let Commands::Use { name, quiet } = command {
-        do_use(&name, quiet)
+    if let Commands::Use {
+        name,
+        quiet,
+        check,
+        no_check,
+        shell,
+    } = command
+    {
+        let check = match (check, no_check) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+        // `ccm -q use` (global flag) and `ccm use -q` both work
+        let quiet = quiet || output::is_quiet();
+
+        if matches!(get_cached_master_key(), Err(CcmError::PinRequired)) {
+            // Quick-access entries apply without unlocking the vault (or
+            // reading its settings)
+            if let Some((entry, secret)) = quick_access::get(&name)? {
+                let check = check.unwrap_or(false);
+                return apply_entry(&name, &entry, &secret, quiet, false, check, shell);
+            }
+            crate::auth::ensure_master_key_loaded().await?;
+        }
+
+        let check = match check {
+            Some(check) => check,
+            None => health_check_enabled()?,
+        };
+        let name = super::pick::lookup_name(&name, true)?;
+        let (entry, secret) = secrets::use_entry(&name)?;
+        apply_entry(&name, &entry, &secret, quiet, true, check, shell)
     } else {
         unreachable!()
     }
 }
 
-fn do_use(name: &str, quiet: bool) -> Result<()> {
-    let (entry, secret) = secrets::get_entry_with_secret(name)?;
+pub(crate) fn do_use(name: &str, quiet: bool) -> Result<()> {
+    let (entry, secret) = secrets::use_entry(name)?;
+    apply_entry(name, &entry, &secret, quiet, true, false, None)
+}
+
+/// Whether use.health-check is on
+fn health_check_enabled() -> Result<bool> {
+    Ok(crate::db::get_database()?
+        .get_setting::<String>(health::HEALTH_CHECK_KEY)?
+        .and_then(|v| parse_bool(&v))
+        .unwrap_or(false))
+}
 
+/// Export an entry's variables; `record` saves it as the active entry, which
+/// needs the (unlocked) database, and `check` pings its base URLs afterwards.
+/// With `shell` the variables are printed as statements for that shell.
+fn apply_entry(
+    name: &str,
+    entry: &Entry,
+    secret: &str,
+    quiet: bool,
+    record: bool,
+    check: bool,
+    shell: Option<Shell>,
+) -> Result<()> {
     // Get environment variable mappings with secret substitution
-    let env_vars = env::get_env_mappings_with_secret(&entry, &secret);
+    let env_vars = env::get_env_mappings_with_secret(entry, secret);
 
     if env_vars.is_empty() {
         if !quiet {
-            println!(
+            eprintln!(
                 "⚠️  No environment variable mappings found for entry '{}'",
                 name
             );
@@ -33,6 +94,10 @@ fn do_use(name: &str, quiet: bool) -> Result<()> {
         return Ok(());
     }
 
+    if let Some(shell) = shell {
+        return apply_in_shell(name, &env_vars, shell, quiet, record, check);
+    }
+
     // Set environment variables based on platform
     #[cfg(windows)]
     set_env_windows(&env_vars, quiet)?;
@@ -40,25 +105,138 @@ fn do_use(name: &str, quiet: bool) -> Result<()> {
     #[cfg(unix)]
     set_env_unix(&env_vars, quiet)?;
 
+    // Remember which entry is active for `ccm status`
+    if record {
+        env::record_active_entry(name, &env_vars)?;
+    }
+
     if !quiet {
         println!("✅ Set {} environment variables for '{}':", env_vars.len(), name);
-        for (key, _) in &env_vars {
+        if !record {
+            println!("   (quick access, no PIN)");
+        }
+        for key in env_vars.keys() {
             println!("  {}", key);
         }
         println!();
         println!("You can now use '{}' in your applications", name.bold());
     }
 
+    if check {
+        check_endpoints(&env_vars, quiet);
+    }
+
+    Ok(())
+}
+
+/// Print the statements the `ccm shell-init` function evals; stdout holds
+/// only those, so everything else goes to stderr
+fn apply_in_shell(
+    name: &str,
+    env_vars: &HashMap<String, String>,
+    shell: Shell,
+    quiet: bool,
+    record: bool,
+    check: bool,
+) -> Result<()> {
+    print!("{}", env::shell::export_script(shell, name, env_vars)?);
+
+    if record {
+        env::record_active_entry(name, env_vars)?;
+    }
+    if !quiet {
+        let note = if record {
+            ""
+        } else {
+            " (quick access, no PIN)"
+        };
+        eprintln!(
+            "✅ Set {} environment variables for '{}'{}",
+            env_vars.len(),
+            name,
+            note
+        );
+    }
+    if check {
+        // Reachable endpoints would only be noise on stderr here
+        check_endpoints(env_vars, true);
+    }
     Ok(())
 }
 
+/// Warn about base URLs that do not respond; failures never fail `use`
+fn check_endpoints(env_vars: &HashMap<String, String>, quiet: bool) {
+    let urls = health::endpoint_urls(env_vars);
+    if urls.is_empty() {
+        if !quiet {
+            println!("No base URL to check.");
+        }
+        return;
+    }
+
+    for (var, url) in urls {
+        match health::check_url(&url, health::DEFAULT_TIMEOUT) {
+            Health::Reachable { status, elapsed } => {
+                if !quiet {
+                    output::success(format!(
+                        "{} is reachable (HTTP {}, {} ms)",
+                        url,
+                        status,
+                        elapsed.as_millis()
+                    ));
+                }
+            }
+            Health::Unreachable(reason) => {
+                eprintln!(
+                    "{} {} ({}) is unreachable: {}",
+                    "⚠️".yellow(),
+                    url,
+                    var,
+                    reason
+                );
+            }
+        }
+    }
+}
+
+/// Re-apply the active entry if it changed since it was applied (--reapply)
+pub async fn reapply_active_entry() -> Result<()> {
+    let name = match env::changed_active_entry() {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    // Ensure master key is loaded (prompts for PIN if needed)
+    crate::auth::ensure_master_key_loaded().await?;
+
+    println!();
+    println!(
+        "{} Re-applying changed active entry '{}'",
+        "🔄".blue(),
+        name
+    );
+    match do_use(&name, false) {
+        Err(CcmError::EntryNotFound(_)) => {
+            // The active entry was deleted; nothing left to apply
+            std::fs::remove_file(env::active_entry_changed_path())?;
+            println!("{} Entry '{}' no longer exists", "⚠️".yellow(), name);
+            Ok(())
+        }
+        other => other,
+    }
+}
+
 /// Set environment variables on Windows
 #[cfg(windows)]
 fn set_env_windows(env_vars: &std::collections::HashMap<String, String>, quiet: bool) -> Result<()> {
     use std::process::Command;
 
     for (key, value) in env_vars {
-        let output = Command::new("setx").arg(key).arg(value).output();
+        let output = Command::new("setx")
+            .env_remove(crate::auth::PIN_ENV)
+            .arg(key)
+            .arg(value)
+            .output();
 
         match output {
             Ok(output) if output.status.success() => {
@@ -82,20 +260,12 @@ fn set_env_windows(env_vars: &std::collections::HashMap<String, String>, quiet:
 /// Set environment variables on Unix/macOS
 #[cfg(unix)]
 fn set_env_unix(env_vars: &std::collections::HashMap<String, String>, quiet: bool) -> Result<()> {
-    use std::fs::OpenOptions;
-    use std::io::Write;
-
     // Determine shell config file
     let shell_config = detect_shell_config()?;
 
     // Read existing content to avoid duplicates
     let existing_content = std::fs::read_to_string(&shell_config).unwrap_or_default();
-
-    // Open file for appending
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&shell_config)?;
+    let mut content = existing_content.clone();
 
     for (key, value) in env_vars {
         let export_line = format!("export {}=\"{}\"\n", key, value);
@@ -109,13 +279,19 @@ fn set_env_unix(env_vars: &std::collections::HashMap<String, String>, quiet: boo
             continue;
         }
 
-        writeln!(file, "{}", export_line)?;
+        content.push_str(&export_line);
+        content.push('\n');
 
         if !quiet {
             println!("  {} = {}", key, value);
         }
     }
 
+    // Rewrite the whole file atomically so a crash cannot leave it half-written
+    if content != existing_content {
+        write_file_atomic(&shell_config, content.as_bytes())?;
+    }
+
     if !quiet {
         println!(
             "💡 Run `source {}` or restart your shell to use the new variables",
@@ -0,0 +1,273 @@
+// Vault command implementation
+
+use crate::auth::pin;
+use crate::commands::auth::{prompt_new_escrow_passphrase, unwrap_master_key, wrap_master_key};
+use crate::db;
+use crate::secrets::master_key;
+use crate::utils::files::write_private_file;
+use crate::utils::{output, CcmError, Result};
+use crate::{Commands, VaultAction};
+use base64::{engine::general_purpose, Engine as _};
+use colored::Colorize;
+use dialoguer::{Input, Password, Select};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Format tag of vault transfer bundles
+const BUNDLE_FORMAT: &str = "ccm-vault-v1";
+
+/// Vault transfer bundle: the encrypted database plus its wrapped master key
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultBundle {
+    format: String,
+    #[serde(rename = "instanceId")]
+    instance_id: String,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    /// Master key encrypted with the transfer passphrase
+    #[serde(rename = "masterKey")]
+    master_key: String,
+    /// Gzipped SQLCipher database file, base64 encoded
+    database: String,
+}
+
+pub async fn execute(command: Commands) -> Result<()> {
+    if let Commands::Vault { action } = command {
+        match action {
+            VaultAction::Transfer { out, input } => match (out, input) {
+                (Some(path), None) => send(Path::new(&path)).await,
+                (None, Some(path)) => receive(Path::new(&path)).await,
+                _ => transfer_wizard().await,
+            },
+        }
+    } else {
+        unreachable!()
+    }
+}
+
+/// Ask which side of the transfer this machine is on
+async fn transfer_wizard() -> Result<()> {
+    let choices = [
+        "Send: bundle this vault into a file",
+        "Receive: restore a vault from a bundle file",
+    ];
+    let selection = Select::new()
+        .with_prompt("Vault transfer")
+        .items(&choices)
+        .default(0)
+        .interact()?;
+
+    if selection == 0 {
+        let default_name = format!("ccm-vault-{}.json", chrono::Local::now().format("%Y%m%d"));
+        let path: String = Input::new()
+            .with_prompt("Bundle file")
+            .default(default_name)
+            .interact_text()?;
+        send(Path::new(&path)).await
+    } else {
+        let path: String = Input::new().with_prompt("Bundle file").interact_text()?;
+        receive(Path::new(&path)).await
+    }
+}
+
+/// Bundle the database and the passphrase-wrapped master key
+async fn send(path: &Path) -> Result<()> {
+    crate::auth::ensure_master_key_loaded().await?;
+    let master_key = master_key::get_cached_master_key()?;
+    let instance_id = master_key::get_instance_id()?;
+
+    // Make the database file self-contained before reading it
+    let database = db::get_database()?;
+    let entry_count = database.count_entries(&db::EntryFilter::default())?;
+    database.checkpoint()?;
+    drop(database);
+
+    let db_bytes = fs::read(db::db_path())?;
+
+    println!(
+        "{} The bundle contains the master key. Anyone with the file and its",
+        "⚠️".yellow()
+    );
+    println!("   passphrase can read every secret. Delete it after the transfer.");
+    println!();
+
+    let passphrase = prompt_new_escrow_passphrase()?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&db_bytes)?;
+    let compressed = encoder.finish()?;
+
+    let bundle = VaultBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        instance_id,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        master_key: wrap_master_key(&master_key, &passphrase)?,
+        database: general_purpose::STANDARD.encode(compressed),
+    };
+
+    let json = serde_json::to_string_pretty(&bundle)?;
+    write_private_file(path, json.as_bytes())?;
+
+    output::success(format!(
+        "Bundled {} entries into: {}",
+        entry_count,
+        path.display()
+    ));
+    println!(
+        "   On the target machine run: ccm vault transfer --in {}",
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Restore the database, keyring entry and instance ID from a bundle
+async fn receive(path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)?;
+    let bundle: VaultBundle = serde_json::from_str(&content)
+        .map_err(|e| CcmError::InvalidArgument(format!("Invalid vault bundle: {}", e)))?;
+
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(CcmError::InvalidArgument(format!(
+            "Unsupported vault bundle format: {}",
+            bundle.format
+        )));
+    }
+
+    // The restored key is wrapped with ZERO_KEY, which would not match an existing PIN
+    if pin::has_pin()? {
+        return Err(CcmError::InvalidArgument(
+            "A PIN is set on this machine. Remove it first with 'ccm auth remove'.".to_string(),
+        ));
+    }
+
+    let passphrase = Password::new()
+        .with_prompt("Transfer passphrase")
+        .interact()?;
+    let key = unwrap_master_key(&bundle.master_key, &passphrase)?;
+
+    let compressed = general_purpose::STANDARD
+        .decode(&bundle.database)
+        .map_err(|e| CcmError::InvalidArgument(format!("Invalid vault bundle: {}", e)))?;
+    let mut db_bytes = Vec::new();
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut db_bytes)?;
+
+    // The instance ID names the keyring entry of the current key; a backup of
+    // the database is only usable with both
+    let previous_instance = master_key::get_instance_id_from_config()?;
+    let previous_key = match &previous_instance {
+        Some(_) => master_key::load_master_key()?,
+        None => None,
+    };
+    if let (Some(id), Some(old_key)) = (&previous_instance, previous_key) {
+        if *id == bundle.instance_id && old_key != key {
+            return Err(CcmError::InvalidArgument(format!(
+                "This machine already has vault instance {} with another master key, which \
+                 the transfer would overwrite. Remove that vault first with 'ccm reset'.",
+                id
+            )));
+        }
+    }
+
+    let db_path = db::db_path();
+    if db_path.exists() || master_key::has_master_key()? {
+        println!(
+            "{} This machine already has a vault. It will be replaced;",
+            "⚠️".yellow()
+        );
+        println!("   the current database file is kept as a backup.");
+        print!("Replace the existing vault? (y/N): ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !input.trim().eq_ignore_ascii_case("y") && !input.trim().eq_ignore_ascii_case("yes") {
+            println!("Transfer cancelled.");
+            return Ok(());
+        }
+    }
+
+    if let Some(dir) = db_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if let Some(backup) = backup_existing_database(&db_path)? {
+        match (&previous_instance, previous_key) {
+            (Some(id), Some(_)) => {
+                let id_file = with_suffix(&backup, ".instance_id");
+                write_private_file(&id_file, id.as_bytes())?;
+                println!(
+                    "{} Previous database saved to: {}",
+                    "ℹ️".blue(),
+                    backup.display()
+                );
+                println!(
+                    "   Its key stays in the keyring under instance {}. To go back, move it to",
+                    id
+                );
+                println!(
+                    "   {} and copy {} to {}",
+                    db_path.display(),
+                    id_file.display(),
+                    master_key::instance_id_path().display()
+                );
+            }
+            _ => println!(
+                "{} Previous database moved to: {} (its master key was not found, so it \
+                 cannot be opened)",
+                "⚠️".yellow(),
+                backup.display()
+            ),
+        }
+    }
+
+    write_private_file(&db_path, &db_bytes)?;
+    master_key::store_master_key(&key, &bundle.instance_id)?;
+
+    // Opening the database proves the key and the file belong together
+    let entry_count = db::get_database()?.count_entries(&db::EntryFilter::default())?;
+
+    output::success(format!(
+        "Vault restored: {} entries, instance {}",
+        entry_count, bundle.instance_id
+    ));
+    println!(
+        "{} The key is protected by ZERO_KEY. Run 'ccm auth set' to add a PIN.",
+        "🔐".blue()
+    );
+    println!("   Delete the bundle file once you have checked your entries.");
+
+    Ok(())
+}
+
+/// Move the existing database (and its WAL files) aside
+fn backup_existing_database(db_path: &Path) -> Result<Option<PathBuf>> {
+    if !db_path.exists() {
+        return Ok(None);
+    }
+
+    let stamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+    let backup = db_path.with_extension(format!("db.bak-{}", stamp));
+    fs::rename(db_path, &backup)?;
+
+    // Stale WAL/SHM files would be applied to the restored database
+    for suffix in ["-wal", "-shm"] {
+        let side = with_suffix(db_path, suffix);
+        if side.exists() {
+            fs::rename(&side, with_suffix(&backup, suffix))?;
+        }
+    }
+
+    Ok(Some(backup))
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
@@ -1,8 +1,29 @@
 // Version command implementation
 
-use crate::utils::Result;
+use crate::utils::{output, Result};
 use crate::Commands;
+use clap::CommandFactory;
 use colored::Colorize;
+use serde::Serialize;
+
+/// Version from Cargo.toml
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Build metadata (see build.rs)
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    #[serde(rename = "gitCommit")]
+    pub git_commit: &'static str,
+    /// RFC 3339 build time (UTC)
+    #[serde(rename = "buildDate")]
+    pub build_date: Option<String>,
+    pub target: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub profile: &'static str,
+    pub features: Vec<String>,
+}
 
 pub async fn execute(command: Commands) -> Result<()> {
     if let Commands::Version = command {
@@ -12,17 +33,72 @@ pub async fn execute(command: Commands) -> Result<()> {
     }
 }
 
+/// Metadata of the running binary
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: VERSION,
+        git_commit: env!("CCM_GIT_COMMIT"),
+        build_date: env!("CCM_BUILD_TIMESTAMP")
+            .parse::<i64>()
+            .ok()
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .map(|time| time.to_rfc3339()),
+        target: env!("CCM_TARGET"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        features: enabled_features(),
+    }
+}
+
+/// Optional capabilities compiled into this binary
+/// There are no cargo features: everything is always built in (gpg is used
+/// from PATH at runtime), and the sync providers are read from the CLI so
+/// the list follows `ccm sync`
+fn enabled_features() -> Vec<String> {
+    let mut features: Vec<String> = [
+        "sqlcipher",
+        "age-recipients",
+        "gpg-recipients",
+        "remote",
+        "transfer",
+    ]
+    .map(String::from)
+    .to_vec();
+    if let Some(sync) = crate::Cli::command().find_subcommand("sync") {
+        features.extend(
+            sync.get_subcommands()
+                .map(|provider| format!("sync-{}", provider.get_name())),
+        );
+    }
+    features
+}
+
 fn do_version() -> Result<()> {
-    println!("CCM - Custom Configuration Manager {}", "0.9.1".bold());
+    let info = build_info();
+
+    if output::is_json() {
+        return output::print_json(&info);
+    }
+
+    println!("CCM - Custom Configuration Manager {}", info.version.bold());
     println!();
     println!("A secure profile and API key manager with AES-256-GCM encryption");
     println!();
-    println!("Platform: {}", std::env::consts::OS);
-    println!("Architecture: {}", std::env::consts::ARCH);
+    println!("Platform: {}", info.os);
+    println!("Architecture: {}", info.arch);
+    println!("Target: {}", info.target);
     println!(
-        "Build: {}",
-        option_env!("RUST_TOOLCHAIN").unwrap_or("stable")
+        "Build: {} ({}, {})",
+        info.git_commit,
+        info.profile,
+        info.build_date.as_deref().unwrap_or("unknown date")
     );
+    println!("Features: {}", info.features.join(", "));
 
     Ok(())
 }
@@ -0,0 +1,129 @@
+// Endpoint health check for `ccm use`
+//
+// Sends a HEAD request to the base URLs an entry exports, so a dead relay is
+// noticed when the entry is applied rather than on the next API call. Any
+// HTTP response counts as reachable (APIs answer 401/404 to a bare HEAD);
+// only DNS, connection and timeout failures are reported.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Setting: check endpoints on every `ccm use` (true/false, default false)
+pub const HEALTH_CHECK_KEY: &str = "use.health-check";
+
+/// How long to wait for an endpoint
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Result of checking one endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    /// The server answered with this status code
+    Reachable { status: u16, elapsed: Duration },
+    /// No HTTP response: the reason
+    Unreachable(String),
+}
+
+/// Variables holding an endpoint (`*_BASE_URL`, `*_ENDPOINT`, `url`) with an
+/// http(s) value, as (variable, url) sorted by variable
+pub fn endpoint_urls(vars: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut urls: Vec<(String, String)> = vars
+        .iter()
+        .filter(|(key, _)| {
+            let key = key.to_uppercase();
+            key.ends_with("BASE_URL") || key.ends_with("_ENDPOINT") || key == "URL"
+        })
+        .filter(|(_, value)| value.starts_with("http://") || value.starts_with("https://"))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    urls.sort();
+
+    // Check a URL shared by several variables once
+    let mut seen = std::collections::HashSet::new();
+    urls.retain(|(_, url)| seen.insert(url.clone()));
+    urls
+}
+
+/// HEAD `url`, giving up after `timeout`
+pub fn check_url(url: &str, timeout: Duration) -> Health {
+    let agent = crate::net::agent(url, timeout);
+
+    let started = Instant::now();
+    match agent.head(url).call() {
+        Ok(response) => Health::Reachable {
+            status: response.status(),
+            elapsed: started.elapsed(),
+        },
+        Err(ureq::Error::Status(status, _)) => Health::Reachable {
+            status,
+            elapsed: started.elapsed(),
+        },
+        Err(ureq::Error::Transport(e)) => Health::Unreachable(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_endpoint_urls() {
+        let vars: HashMap<String, String> = [
+            ("OPENAI_BASE_URL", "https://relay.example.com/v1"),
+            ("ANTHROPIC_BASE_URL", "https://relay.example.com/v1"),
+            ("OPENAI_API_KEY", "sk-test"),
+            ("AZURE_ENDPOINT", "https://x.openai.azure.com"),
+            ("CACHE_URL", "redis://localhost"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        assert_eq!(
+            endpoint_urls(&vars),
+            vec![
+                (
+                    "ANTHROPIC_BASE_URL".to_string(),
+                    "https://relay.example.com/v1".to_string()
+                ),
+                (
+                    "AZURE_ENDPOINT".to_string(),
+                    "https://x.openai.azure.com".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_url() {
+        // Any HTTP answer, even an error status, is reachable
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+        assert!(matches!(
+            check_url(&url, DEFAULT_TIMEOUT),
+            Health::Reachable { status: 404, .. }
+        ));
+        server.join().unwrap();
+
+        // Nothing listening
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = format!("http://127.0.0.1:{}/", port);
+        assert!(matches!(
+            check_url(&url, DEFAULT_TIMEOUT),
+            Health::Unreachable(_)
+        ));
+    }
+}
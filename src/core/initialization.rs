@@ -1,29 +1,50 @@
 // Unified initialization layer
 // Centralized system state check at startup
 
-use crate::secrets::master_key::{check_os_secret_service_available, has_master_key};
+use crate::secrets::master_key::{
+    check_os_secret_service_available, has_master_key, is_master_key_cached,
+};
 use crate::types::{InitContext, InitPath};
 use crate::utils::debug_print_category;
 use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::OnceLock;
 
-/// Global initialization context
+/// Keyring state, checked on first use (see `context`)
 static INIT_CONTEXT: OnceLock<InitContext> = OnceLock::new();
 
-/// Unified initialization entry point
-/// Called once at startup, returns cached context on subsequent calls
-pub async fn initialize() -> Result<InitContext> {
-    // Get or create context (synchronous)
-    let context = INIT_CONTEXT.get_or_init(init_internal_sync);
+/// Set once the one-time work after opening the vault has started
+static VAULT_INITIALIZED: AtomicBool = AtomicBool::new(false);
 
-    Ok(context.clone())
+/// Unified initialization entry point, called once at startup
+/// Only the file system is touched here. The keyring and the database are
+/// left alone until a command needs the master key, so commands that do not
+/// (help, version, examples, doctor) work without an OS secret service
+pub async fn initialize() -> Result<()> {
+    debug_print_category("init", "Starting initialization...");
+
+    // Move ~/.ccm to the XDG directories (Linux, once)
+    match crate::db::location::migrate_legacy_dir() {
+        Ok(Some(dir)) => eprintln!(
+            "Moved {} to {} (XDG base directories)",
+            crate::db::location::legacy_dir().display(),
+            dir.display()
+        ),
+        Ok(None) => {}
+        Err(e) => debug_print_category("init", &format!("XDG migration error: {}", e)),
+    }
+
+    debug_print_category("init", "Initialization complete");
+    Ok(())
 }
 
-/// Internal synchronous initialization
-fn init_internal_sync() -> InitContext {
-    debug_print_category("init", "Starting initialization...");
+/// Keyring and master key state; the first call queries the keyring
+pub fn context() -> InitContext {
+    INIT_CONTEXT.get_or_init(check_keyring_state).clone()
+}
 
-    // Initialize with default values
+/// Query the OS secret service and look for the master key
+fn check_keyring_state() -> InitContext {
     let mut context = InitContext {
         has_os_secret_service: false,
         has_pin: false,
@@ -62,28 +83,62 @@ fn init_internal_sync() -> InitContext {
         }
         Err(e) => {
             debug_print_category("init", &format!("Master key check error: {}", e));
-            context.initialized = true;
             context.error = Some(e.to_string());
-            return context;
         }
     }
 
-    // 3. Check for legacy JSON migration
+    context.has_pin = crate::auth::pin::has_pin().unwrap_or(false);
+    context.initialized = true;
+    context
+}
+
+/// One-time work after the vault is first opened in this process (called by
+/// `db::get_database`): legacy JSON migration. It opens the database itself,
+/// so later calls return immediately
+pub fn on_vault_opened() {
+    if VAULT_INITIALIZED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    // Check for legacy JSON migration
     if crate::db::migration::needs_migration() {
         debug_print_category("init", "Legacy migration needed, running...");
         // Run migration silently - errors are non-fatal
         let _ = crate::db::migration::run_migration();
     }
+}
 
-    // 4. Create default profiles on first run
-    if crate::db::migration::should_create_defaults() {
-        debug_print_category("init", "Creating default profiles...");
-        let _ = crate::db::migration::create_default_profiles();
+/// Automatic local backup, run after a successful command
+/// Only when the command already unlocked the vault (never prompts), and only
+/// if `backup.auto` is set and entries changed; failures are reported but do
+/// not fail the command
+pub fn run_auto_backup() {
+    if !is_master_key_cached() || !crate::db::db_path().exists() {
+        return;
     }
 
-    debug_print_category("init", "Initialization complete");
-    context.initialized = true;
-    context
+    let result = crate::db::get_database().and_then(|db| crate::db::snapshots::auto_snapshot(&db));
+    match result {
+        Ok(Some(snapshot)) => debug_print_category(
+            "backup",
+            &format!("Snapshot written to {}", snapshot.path.display()),
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!("⚠️  Automatic backup failed: {}", e),
+    }
+}
+
+/// Show the secret hygiene report when it is enabled and due (after a
+/// command that unlocked the vault; never prompts)
+pub fn run_report() {
+    if !is_master_key_cached() || !crate::db::db_path().exists() {
+        return;
+    }
+
+    let result = crate::db::get_database().and_then(|db| crate::core::report::show_if_due(&db));
+    if let Err(e) = result {
+        debug_print_category("report", &format!("Report failed: {}", e));
+    }
 }
 
 /// Check if PIN is set silently (no prompts)
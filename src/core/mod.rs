@@ -1,3 +1,7 @@
 // Core initialization module
 
+pub mod diagnostics;
+pub mod health;
 pub mod initialization;
+pub mod provider;
+pub mod report;
@@ -0,0 +1,618 @@
+// Provider APIs reached with an entry's key (ccm test, ccm models)
+//
+// `Provider::detect` works out from an entry's variables (through its
+// preset) which API the key belongs to and where to send it; `test` makes
+// the cheapest authenticated request that API has: the model list for
+// Anthropic, OpenAI-compatible, Gemini, Azure OpenAI and Ollama, /user for
+// GitHub and STS GetCallerIdentity for AWS. `models` reads that model list.
+
+use crate::net::{self, Answer};
+use crate::presets;
+use crate::sync::aws::{host, sign_request, AwsCredentials, SignedRequest, AWS_ENDPOINT_URL_ENV};
+use crate::utils::crypto::sha256_hash;
+use crate::utils::{CcmError, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a provider
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// `anthropic-version` header of Anthropic requests
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// `api-version` of Azure OpenAI requests when the entry sets none
+const AZURE_API_VERSION: &str = "2024-10-21";
+const STS_BODY: &str = "Action=GetCallerIdentity&Version=2011-06-15";
+
+/// The kind of API behind a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    Anthropic,
+    /// OpenAI and the many APIs copying it (DeepSeek, Groq, relays...)
+    OpenAi,
+    Gemini,
+    GitHub,
+    Azure,
+    Ollama,
+    Aws,
+}
+
+/// A key and the API it is for
+#[derive(Debug, Clone)]
+pub struct Provider {
+    pub api: Api,
+    /// Name of the preset it was recognised by
+    pub preset: String,
+    /// Base URL (for AWS, the STS endpoint)
+    pub base_url: String,
+    key: String,
+    /// Variable of the preset's `model` field (ANTHROPIC_MODEL...)
+    pub model_var: Option<String>,
+    /// Send the key as `Authorization: Bearer` rather than the API's own header
+    bearer: bool,
+    /// The entry's variables, for API-specific settings (AWS credentials,
+    /// Azure API version)
+    vars: HashMap<String, String>,
+}
+
+/// Outcome of `Provider::test`
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// URL requested
+    pub url: String,
+    pub status: u16,
+    pub elapsed: Duration,
+    /// What the API says about the key: account, scopes, expiry...
+    pub details: Vec<(String, String)>,
+    /// The API's error message when the key was refused
+    pub error: Option<String>,
+}
+
+impl Report {
+    /// Whether the key was accepted
+    pub fn works(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// A model offered by a provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    /// What to set the model variable to
+    pub id: String,
+    /// Display name, when the API gives one
+    pub name: Option<String>,
+}
+
+impl Provider {
+    /// The provider of an entry, from its variables with the secret filled in
+    pub fn detect(entry_name: &str, vars: &HashMap<String, String>) -> Result<Self> {
+        let unknown = || {
+            CcmError::InvalidArgument(format!(
+                "Cannot tell which provider {} is for; its variables match no preset \
+                 (see ccm preset list)",
+                entry_name
+            ))
+        };
+        let detected = presets::detect_preset(entry_name, vars).ok_or_else(unknown)?;
+        let preset = detected.preset;
+        let var = |field: &str| {
+            preset
+                .env_mapping
+                .get(field)
+                .and_then(|name| vars.get(name))
+                .filter(|value| !value.is_empty())
+                .cloned()
+        };
+        let token_var = preset
+            .secret_field()
+            .and_then(|f| preset.env_mapping.get(f));
+
+        let api = match token_var.map(String::as_str).unwrap_or_default() {
+            "AWS_SECRET_ACCESS_KEY" => Api::Aws,
+            "AWS_BEARER_TOKEN_BEDROCK" => {
+                return Err(CcmError::InvalidArgument(
+                    "Testing Bedrock gateway keys is not supported".to_string(),
+                ))
+            }
+            v if v.starts_with("ANTHROPIC_") => Api::Anthropic,
+            v if v.starts_with("GEMINI_") => Api::Gemini,
+            v if v.starts_with("GITHUB_") => Api::GitHub,
+            v if v.starts_with("AZURE_OPENAI_") => Api::Azure,
+            v if v.starts_with("OLLAMA_") => Api::Ollama,
+            _ => Api::OpenAi,
+        };
+
+        // Anthropic relays take the key as a bearer token
+        let (key, bearer) = match api {
+            Api::Anthropic => match vars.get("ANTHROPIC_API_KEY") {
+                Some(key) => (Some(key.clone()), false),
+                None => (vars.get("ANTHROPIC_AUTH_TOKEN").cloned(), true),
+            },
+            Api::Aws => (Some(String::new()), false),
+            _ => (preset.secret_field().and_then(var), true),
+        };
+        let key = key
+            .filter(|k| api == Api::Aws || !k.is_empty())
+            .ok_or_else(|| {
+                CcmError::InvalidArgument(format!(
+                    "{} has no {} key to test",
+                    entry_name, preset.name
+                ))
+            })?;
+
+        let base_url = match api {
+            Api::Aws => {
+                let region = vars
+                    .get("AWS_REGION")
+                    .or_else(|| vars.get("AWS_DEFAULT_REGION"))
+                    .map(String::as_str)
+                    .unwrap_or("us-east-1");
+                std::env::var(AWS_ENDPOINT_URL_ENV)
+                    .ok()
+                    .filter(|url| !url.is_empty())
+                    .unwrap_or_else(|| format!("https://sts.{}.amazonaws.com", region))
+            }
+            _ => var("url")
+                .or_else(|| {
+                    vars.get("OPENAI_API_BASE")
+                        .filter(|_| api == Api::OpenAi)
+                        .cloned()
+                })
+                .or_else(|| preset.default_fields.get("url").cloned())
+                .ok_or_else(|| {
+                    CcmError::InvalidArgument(format!("{} has no base URL", entry_name))
+                })?,
+        };
+
+        Ok(Provider {
+            api,
+            preset: preset.name.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model_var: preset.env_mapping.get("model").cloned(),
+            key,
+            bearer,
+            vars: vars.clone(),
+        })
+    }
+
+    /// Make a minimal authenticated request and report whether the key works
+    pub fn test(&self, timeout: Duration) -> Result<Report> {
+        let (url, answer) = match self.api {
+            Api::Aws => self.call_sts(timeout)?,
+            Api::GitHub => self.get("/user", timeout)?,
+            _ => self.get_models(timeout)?,
+        };
+
+        let mut details = Vec::new();
+        let mut error = None;
+        if answer.ok() {
+            details = self.details(&answer);
+        } else {
+            error = Some(error_message(&answer.body));
+        }
+        Ok(Report {
+            url,
+            status: answer.status,
+            elapsed: answer.elapsed,
+            details,
+            error,
+        })
+    }
+
+    /// The model the entry sets, if any
+    pub fn model(&self) -> Option<&str> {
+        let var = self.model_var.as_ref()?;
+        self.vars
+            .get(var)
+            .map(String::as_str)
+            .filter(|m| !m.is_empty())
+    }
+
+    /// The models the key can use, sorted by id
+    pub fn models(&self, timeout: Duration) -> Result<Vec<Model>> {
+        if matches!(self.api, Api::GitHub | Api::Aws) {
+            return Err(CcmError::InvalidArgument(format!(
+                "The {} preset has no model list",
+                self.preset
+            )));
+        }
+        let (url, answer) = self.get_models(timeout)?;
+        if !answer.ok() {
+            return Err(CcmError::Provider(format!(
+                "{} from {}: {}",
+                answer.status,
+                url,
+                error_message(&answer.body)
+            )));
+        }
+        let body: Value = serde_json::from_str(&answer.body).map_err(|e| {
+            CcmError::Provider(format!("Unexpected model list from {}: {}", url, e))
+        })?;
+        let mut models = parse_models(&body);
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+
+    /// Request the model list
+    fn get_models(&self, timeout: Duration) -> Result<(String, Answer)> {
+        match self.api {
+            Api::Anthropic => self.get("/v1/models", timeout),
+            Api::Gemini => self.get("/v1beta/models", timeout),
+            Api::Azure => {
+                let version = self
+                    .vars
+                    .get("OPENAI_API_VERSION")
+                    .map(String::as_str)
+                    .unwrap_or(AZURE_API_VERSION);
+                self.get(&format!("/openai/models?api-version={}", version), timeout)
+            }
+            Api::Ollama => self.get("/api/tags", timeout),
+            _ => self.get("/models", timeout),
+        }
+    }
+
+    /// The base URL with `path`; Anthropic and Gemini base URLs may or may
+    /// not already end with the version
+    fn url(&self, path: &str) -> String {
+        let base = match self.api {
+            Api::Anthropic => self.base_url.strip_suffix("/v1"),
+            Api::Gemini => self.base_url.strip_suffix("/v1beta"),
+            _ => None,
+        }
+        .unwrap_or(&self.base_url);
+        format!("{}{}", base, path)
+    }
+
+    /// GET `path` with the key
+    fn get(&self, path: &str, timeout: Duration) -> Result<(String, Answer)> {
+        let (url, request) = self.request("GET", path, timeout);
+        let started = Instant::now();
+        let answer = net::send(&url, started, request.call())?;
+        Ok((url, answer))
+    }
+
+    /// A `method` request for `path` carrying the key, and its URL
+    pub(crate) fn request(
+        &self,
+        method: &str,
+        path: &str,
+        timeout: Duration,
+    ) -> (String, ureq::Request) {
+        let url = self.url(path);
+        let mut request = net::agent(&url, timeout).request(method, &url);
+        request = match (self.api, self.bearer) {
+            (Api::Anthropic, false) => request.set("x-api-key", &self.key),
+            (Api::Gemini, _) => request.set("x-goog-api-key", &self.key),
+            (Api::Azure, _) => request.set("api-key", &self.key),
+            (Api::Ollama, _) if self.key.is_empty() => request,
+            _ => request.set("Authorization", &format!("Bearer {}", self.key)),
+        };
+        match self.api {
+            Api::Anthropic => request = request.set("anthropic-version", ANTHROPIC_VERSION),
+            Api::GitHub => request = request.set("Accept", "application/vnd.github+json"),
+            _ => {}
+        }
+        (url, request)
+    }
+
+    /// STS GetCallerIdentity, signed with the entry's AWS credentials
+    fn call_sts(&self, timeout: Duration) -> Result<(String, Answer)> {
+        let credentials = AwsCredentials::from_vars(&self.vars)?;
+        let region = credentials.region.as_deref().unwrap_or("us-east-1");
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let request = SignedRequest {
+            method: "POST",
+            path: "/",
+            query: "",
+            host: &host(&self.base_url),
+            payload_hash: &hex::encode(sha256_hash(STS_BODY.as_bytes())),
+        };
+        let headers = vec![(
+            "content-type".to_string(),
+            "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+        )];
+        let headers = sign_request(&credentials, region, "sts", &request, headers, &amz_date);
+
+        let url = format!("{}/", self.base_url);
+        let mut request = net::agent(&url, timeout).post(&url);
+        for (name, value) in &headers {
+            request = request.set(name, value);
+        }
+        let started = Instant::now();
+        let answer = net::send(&url, started, request.send_string(STS_BODY))?;
+        Ok((url, answer))
+    }
+
+    /// Account, scopes, expiry and model count from a successful answer
+    fn details(&self, answer: &Answer) -> Vec<(String, String)> {
+        let mut details = Vec::new();
+        let mut add = |name: &str, value: Option<String>| {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                details.push((name.to_string(), value));
+            }
+        };
+
+        if self.api == Api::Aws {
+            add("account", xml_tag(&answer.body, "Account"));
+            add("arn", xml_tag(&answer.body, "Arn"));
+            return details;
+        }
+
+        let body: Value = serde_json::from_str(&answer.body).unwrap_or_default();
+        if self.api == Api::GitHub {
+            add("login", body["login"].as_str().map(str::to_string));
+            // Classic tokens list their scopes; fine-grained ones have none
+            add("scopes", answer.headers.get("x-oauth-scopes").cloned());
+            add(
+                "expires",
+                answer
+                    .headers
+                    .get("github-authentication-token-expiration")
+                    .cloned(),
+            );
+        } else {
+            let list = ["data", "models"]
+                .iter()
+                .find_map(|field| body[*field].as_array());
+            add("models", list.map(|models| models.len().to_string()));
+        }
+        details
+    }
+}
+
+/// The error message of an API error body (JSON or AWS XML), or its start
+pub(crate) fn error_message(body: &str) -> String {
+    if let Ok(json) = serde_json::from_str::<Value>(body) {
+        let message = [&json["error"]["message"], &json["error"], &json["message"]]
+            .into_iter()
+            .find_map(|value| value.as_str());
+        if let Some(message) = message {
+            return message.to_string();
+        }
+    }
+    if let Some(message) = xml_tag(body, "Message") {
+        return message;
+    }
+    body.chars()
+        .take(200)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Models of a model list: `data[].id` (Anthropic, OpenAI, Azure) or
+/// `models[].name` (Gemini, without its "models/" prefix, and Ollama)
+fn parse_models(body: &Value) -> Vec<Model> {
+    let text = |value: &Value| value.as_str().map(str::to_string);
+    if let Some(data) = body["data"].as_array() {
+        return data
+            .iter()
+            .filter_map(|model| {
+                Some(Model {
+                    id: text(&model["id"])?,
+                    name: text(&model["display_name"]),
+                })
+            })
+            .collect();
+    }
+    body["models"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            let id = text(&model["name"])?;
+            Some(Model {
+                id: id.strip_prefix("models/").map(str::to_string).unwrap_or(id),
+                name: text(&model["displayName"]),
+            })
+        })
+        .collect()
+}
+
+/// Text of the first `<tag>` element
+fn xml_tag(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(xml[start..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        let claude = Provider::detect("work", &vars(&[("ANTHROPIC_API_KEY", "sk-ant")])).unwrap();
+        assert_eq!(claude.api, Api::Anthropic);
+        assert!(!claude.bearer);
+        assert_eq!(
+            claude.url("/v1/models"),
+            "https://api.anthropic.com/v1/models"
+        );
+
+        let relay = Provider::detect(
+            "relay",
+            &vars(&[
+                ("ANTHROPIC_AUTH_TOKEN", "sk-relay"),
+                ("ANTHROPIC_BASE_URL", "https://relay.example.com/v1/"),
+            ]),
+        )
+        .unwrap();
+        assert!(relay.bearer);
+        assert_eq!(
+            relay.url("/v1/models"),
+            "https://relay.example.com/v1/models"
+        );
+
+        let openai = Provider::detect(
+            "x",
+            &vars(&[
+                ("OPENAI_API_KEY", "sk"),
+                ("OPENAI_API_BASE", "https://relay.example.com/v1"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(openai.api, Api::OpenAi);
+        assert_eq!(openai.base_url, "https://relay.example.com/v1");
+
+        let groq = Provider::detect("x", &vars(&[("GROQ_API_KEY", "gsk")])).unwrap();
+        assert_eq!((groq.api, groq.preset.as_str()), (Api::OpenAi, "groq"));
+        assert_eq!(groq.base_url, "https://api.groq.com/openai/v1");
+
+        let aws = Provider::detect(
+            "x",
+            &vars(&[
+                ("AWS_ACCESS_KEY_ID", "AKIA"),
+                ("AWS_SECRET_ACCESS_KEY", "s"),
+                ("AWS_REGION", "eu-west-1"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(aws.api, Api::Aws);
+        assert_eq!(aws.base_url, "https://sts.eu-west-1.amazonaws.com");
+
+        assert!(Provider::detect("misc", &vars(&[("TOKEN", "x")])).is_err());
+        assert!(Provider::detect("x", &vars(&[("OPENAI_BASE_URL", "https://a.b")])).is_err());
+    }
+
+    #[test]
+    fn test_error_message() {
+        assert_eq!(
+            error_message(r#"{"error":{"message":"Incorrect API key","type":"x"}}"#),
+            "Incorrect API key"
+        );
+        assert_eq!(
+            error_message(r#"{"message":"Bad credentials"}"#),
+            "Bad credentials"
+        );
+        assert_eq!(
+            error_message("<ErrorResponse><Error><Code>InvalidClientTokenId</Code><Message>The security token is invalid.</Message></Error></ErrorResponse>"),
+            "The security token is invalid."
+        );
+        assert_eq!(error_message("Forbidden"), "Forbidden");
+    }
+
+    /// Answer one request with `status` and a JSON `body`; returns the URL
+    /// and the request
+    fn serve_once(status: &str, body: &str) -> (String, std::thread::JoinHandle<String>) {
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn test_test() {
+        let (url, server) = serve_once(
+            "200 OK",
+            r#"{"object":"list","data":[{"id":"gpt-a"},{"id":"gpt-b"}]}"#,
+        );
+        let provider = Provider::detect(
+            "x",
+            &vars(&[("OPENAI_API_KEY", "sk-test"), ("OPENAI_BASE_URL", &url)]),
+        )
+        .unwrap();
+        let report = provider.test(DEFAULT_TIMEOUT).unwrap();
+        assert!(report.works());
+        assert_eq!(report.url, format!("{}/models", url));
+        assert_eq!(report.details, [("models".to_string(), "2".to_string())]);
+        let request = server.join().unwrap().to_lowercase();
+        assert!(request.starts_with("get /v1/models "));
+        assert!(request.contains("authorization: bearer sk-test"));
+
+        let (url, server) = serve_once(
+            "401 Unauthorized",
+            r#"{"error":{"message":"invalid x-key"}}"#,
+        );
+        let provider = Provider::detect(
+            "x",
+            &vars(&[("ANTHROPIC_API_KEY", "bad"), ("ANTHROPIC_BASE_URL", &url)]),
+        )
+        .unwrap();
+        let report = provider.test(DEFAULT_TIMEOUT).unwrap();
+        assert!(!report.works());
+        assert_eq!(report.status, 401);
+        assert_eq!(report.error.as_deref(), Some("invalid x-key"));
+        let request = server.join().unwrap().to_lowercase();
+        assert!(request.contains("x-api-key: bad"));
+        assert!(request.contains("anthropic-version: 2023-06-01"));
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let anthropic: Value = serde_json::from_str(
+            r#"{"data":[{"id":"claude-x","display_name":"Claude X","type":"model"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse_models(&anthropic),
+            [Model {
+                id: "claude-x".to_string(),
+                name: Some("Claude X".to_string()),
+            }]
+        );
+
+        let gemini: Value = serde_json::from_str(
+            r#"{"models":[{"name":"models/gemini-pro","displayName":"Gemini Pro"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(parse_models(&gemini)[0].id, "gemini-pro");
+
+        let ollama: Value = serde_json::from_str(r#"{"models":[{"name":"llama3:8b"}]}"#).unwrap();
+        assert_eq!(parse_models(&ollama)[0].id, "llama3:8b");
+        assert!(parse_models(&Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_models() {
+        let (url, server) = serve_once(
+            "200 OK",
+            r#"{"object":"list","data":[{"id":"gpt-b"},{"id":"gpt-a"}]}"#,
+        );
+        let provider = Provider::detect(
+            "x",
+            &vars(&[("OPENAI_API_KEY", "sk-test"), ("OPENAI_BASE_URL", &url)]),
+        )
+        .unwrap();
+        let ids: Vec<String> = provider
+            .models(DEFAULT_TIMEOUT)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(ids, ["gpt-a", "gpt-b"]);
+        server.join().unwrap();
+
+        let (url, server) = serve_once("403 Forbidden", r#"{"error":"no access"}"#);
+        let provider = Provider::detect(
+            "x",
+            &vars(&[("OPENAI_API_KEY", "sk-test"), ("OPENAI_BASE_URL", &url)]),
+        )
+        .unwrap();
+        let err = provider.models(DEFAULT_TIMEOUT).unwrap_err().to_string();
+        assert!(err.contains("403") && err.contains("no access"), "{}", err);
+        server.join().unwrap();
+    }
+}
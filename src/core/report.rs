@@ -0,0 +1,180 @@
+// Secret hygiene report shown on the first command of the day (or week)
+//
+// Opt-in with `ccm config report.frequency daily`. Prints one line to stderr
+// after the command, so it never mixes with output meant for `eval` or pipes.
+
+use crate::db::Database;
+use crate::utils::{CcmError, Result};
+use chrono::{Duration, Local, NaiveDate, Utc};
+use colored::Colorize;
+
+/// Setting: how often to show the report (off, daily, weekly)
+pub const REPORT_FREQUENCY_KEY: &str = "report.frequency";
+/// Setting: age in days after which a secret is due for rotation
+pub const REPORT_MAX_AGE_KEY: &str = "report.max-age";
+/// Internal setting: local date the report was last shown
+const LAST_REPORT_KEY: &str = "__report_last__";
+
+/// Secret age reported when `report.max-age` is not set
+pub const DEFAULT_MAX_AGE_DAYS: i64 = 90;
+/// Entries whose secret was not read for this long count as unused
+const UNUSED_DAYS: i64 = 30;
+
+/// How often the report is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl Frequency {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" | "false" | "no" => Some(Frequency::Off),
+            "daily" | "on" | "true" | "yes" => Some(Frequency::Daily),
+            "weekly" => Some(Frequency::Weekly),
+            _ => None,
+        }
+    }
+
+    /// Whether a report is due today, given the day of the last one
+    pub fn is_due(self, last: Option<NaiveDate>, today: NaiveDate) -> bool {
+        let days = match self {
+            Frequency::Off => return false,
+            Frequency::Daily => 1,
+            Frequency::Weekly => 7,
+        };
+        last.is_none_or(|last| (today - last).num_days() >= days)
+    }
+}
+
+/// Parse `report.max-age`: a positive number of days
+pub fn parse_max_age(value: &str) -> Result<i64> {
+    match value.trim().trim_end_matches('d').parse::<i64>() {
+        Ok(days) if days > 0 => Ok(days),
+        _ => Err(CcmError::InvalidArgument(format!(
+            "Invalid value for {}: '{}' (expected a number of days, e.g. 90)",
+            REPORT_MAX_AGE_KEY, value
+        ))),
+    }
+}
+
+/// What the report counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    pub max_age_days: i64,
+    /// Secrets not changed for `max_age_days`
+    pub old_secrets: usize,
+    /// Entries whose secret was not read for `UNUSED_DAYS`
+    pub unused_entries: usize,
+}
+
+impl Report {
+    pub fn summary(&self) -> String {
+        if self.old_secrets == 0 && self.unused_entries == 0 {
+            return "all secrets are fresh and in use".to_string();
+        }
+        let mut parts = Vec::new();
+        if self.old_secrets > 0 {
+            parts.push(format!(
+                "{} secret(s) older than {} days",
+                self.old_secrets, self.max_age_days
+            ));
+        }
+        if self.unused_entries > 0 {
+            parts.push(format!(
+                "{} entr{} unused for {}+ days",
+                self.unused_entries,
+                if self.unused_entries == 1 { "y" } else { "ies" },
+                UNUSED_DAYS
+            ));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Count old secrets and unused entries
+pub fn build_report(db: &Database, max_age_days: i64) -> Result<Report> {
+    let now = Utc::now();
+    let changed_before = (now - Duration::days(max_age_days)).to_rfc3339();
+    let used_since = crate::audit::format_timestamp(now - Duration::days(UNUSED_DAYS));
+    let (old_secrets, unused_entries) = db.hygiene_counts(&changed_before, &used_since)?;
+
+    Ok(Report {
+        max_age_days,
+        old_secrets,
+        unused_entries,
+    })
+}
+
+/// Show the report if it is enabled and due, and remember that it was shown
+pub fn show_if_due(db: &Database) -> Result<()> {
+    let frequency = db
+        .get_setting::<String>(REPORT_FREQUENCY_KEY)?
+        .and_then(|v| Frequency::parse(&v))
+        .unwrap_or(Frequency::Off);
+    let today = Local::now().date_naive();
+    let last = db
+        .get_setting::<String>(LAST_REPORT_KEY)?
+        .and_then(|v| v.parse::<NaiveDate>().ok());
+    if !frequency.is_due(last, today) {
+        return Ok(());
+    }
+
+    let max_age_days = db
+        .get_setting::<String>(REPORT_MAX_AGE_KEY)?
+        .and_then(|v| parse_max_age(&v).ok())
+        .unwrap_or(DEFAULT_MAX_AGE_DAYS);
+    let report = build_report(db, max_age_days)?;
+    db.save_setting(LAST_REPORT_KEY, &today.to_string())?;
+
+    let title = match frequency {
+        Frequency::Weekly => "Weekly report",
+        _ => "Daily report",
+    };
+    eprintln!("📋 {}: {}", title.bold(), report.summary());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency() {
+        assert_eq!(Frequency::parse("Weekly"), Some(Frequency::Weekly));
+        assert_eq!(Frequency::parse("off"), Some(Frequency::Off));
+        assert_eq!(Frequency::parse("hourly"), None);
+
+        let today = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        let yesterday = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+        assert!(Frequency::Daily.is_due(None, today));
+        assert!(Frequency::Daily.is_due(Some(yesterday), today));
+        assert!(!Frequency::Daily.is_due(Some(today), today));
+        assert!(!Frequency::Weekly.is_due(Some(yesterday), today));
+        assert!(!Frequency::Off.is_due(None, today));
+
+        assert_eq!(parse_max_age("30d").unwrap(), 30);
+        assert!(parse_max_age("0").is_err());
+    }
+
+    #[test]
+    fn test_build_report() {
+        let db = Database::open_in_memory().unwrap();
+        assert_eq!(
+            build_report(&db, 90).unwrap().summary(),
+            "all secrets are fresh and in use"
+        );
+
+        let report = Report {
+            max_age_days: 90,
+            old_secrets: 1,
+            unused_entries: 1,
+        };
+        assert_eq!(
+            report.summary(),
+            "1 secret(s) older than 90 days, 1 entry unused for 30+ days"
+        );
+    }
+}
@@ -0,0 +1,613 @@
+// Storage backends
+//
+// `StorageBackend` is what the secrets layer needs from storage: entries,
+// encrypted secrets (hex strings, encrypted before they get here), settings
+// (JSON text) and the audit log. `Database` (SQLCipher) is the default;
+// `storage()` hands out a `RemoteBackend` instead with --remote (see
+// `crate::remote`), and an in-memory backend exists for tests. Snapshots
+// and integrity checks are SQLite features and stay on the local
+// `Database` (`get_database`).
+
+use super::{get_database, Database, EntryFilter, SecretVersion};
+use crate::types::Entry;
+use crate::utils::{CcmError, Result};
+use std::collections::HashMap;
+
+/// Entries, encrypted secrets, settings and audit events
+pub trait StorageBackend {
+    fn get_entry(&self, name: &str) -> Result<Option<Entry>>;
+
+    /// Insert or replace an entry, stamping it as updated now
+    fn save_entry(&self, name: &str, entry: &Entry) -> Result<()>;
+
+    /// Whether the entry existed
+    fn delete_entry(&self, name: &str) -> Result<bool>;
+
+    /// Record that an entry's secret was used at `at`, keeping its update time
+    fn mark_used(&self, name: &str, at: &str) -> Result<()>;
+
+    /// Set or clear an entry's `ccm lock` flag, keeping its update time
+    fn set_locked(&self, name: &str, locked: bool) -> Result<()>;
+
+    /// Set or clear an entry's `ccm fav` flag, keeping its update time
+    fn set_favorite(&self, name: &str, favorite: bool) -> Result<()>;
+
+    /// Visit the entries matching `filter`, ordered by name
+    fn for_each_entry(
+        &self,
+        filter: &EntryFilter,
+        f: &mut dyn FnMut(Entry) -> Result<()>,
+    ) -> Result<()>;
+
+    /// Entries matching a search query and a filter, best match first
+    /// SQLite uses its full-text index (see `super::search`); this default
+    /// keeps the entries containing the query in their name, tags, notes or
+    /// variables
+    fn search_entries(&self, query: &str, filter: &EntryFilter) -> Result<Vec<Entry>> {
+        let query = query.to_lowercase();
+        let contains = |text: &str| text.to_lowercase().contains(&query);
+        let mut found = Vec::new();
+        self.for_each_entry(filter, &mut |entry| {
+            let hit = contains(&entry.name)
+                || entry.notes.as_deref().is_some_and(contains)
+                || entry.tags.iter().flatten().any(|tag| contains(tag))
+                || entry
+                    .metadata
+                    .iter()
+                    .any(|(k, v)| contains(k) || contains(v));
+            if hit {
+                found.push(entry);
+            }
+            Ok(())
+        })?;
+        Ok(found)
+    }
+
+    /// The encrypted secret of an entry
+    fn get_secret(&self, name: &str) -> Result<Option<String>>;
+
+    /// Insert or replace an encrypted secret
+    fn save_secret(&self, name: &str, encrypted_value: &str) -> Result<()>;
+
+    /// Whether the secret existed
+    fn delete_secret(&self, name: &str) -> Result<bool>;
+
+    /// A setting as JSON text
+    fn get_setting_json(&self, key: &str) -> Result<Option<String>>;
+
+    fn save_setting_json(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Whether the setting existed
+    fn delete_setting(&self, key: &str) -> Result<bool>;
+
+    fn append_audit_event(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_name: Option<&str>,
+        user: Option<&str>,
+    ) -> Result<()>;
+
+    /// All entries by name
+    fn get_all_entries(&self) -> Result<HashMap<String, Entry>> {
+        let mut entries = HashMap::new();
+        self.for_each_entry(&EntryFilter::default(), &mut |entry| {
+            entries.insert(entry.name.clone(), entry);
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Save new entries with their encrypted secrets; fails before writing
+    /// anything if one of the names exists (SQLite also writes all of them in
+    /// one transaction)
+    fn insert_entries_batch(&self, items: &[(Entry, String)]) -> Result<()> {
+        for (entry, _) in items {
+            if self.get_entry(&entry.name)?.is_some() {
+                return Err(CcmError::InvalidArgument(format!(
+                    "Entry '{}' already exists",
+                    entry.name
+                )));
+            }
+        }
+        for (entry, encrypted_value) in items {
+            self.save_entry(&entry.name, entry)?;
+            self.save_secret(&entry.name, encrypted_value)?;
+        }
+        Ok(())
+    }
+
+    /// Delete an entry and its secret; whether either existed
+    fn delete_entry_and_secret(&self, name: &str) -> Result<bool> {
+        let secret = self.delete_secret(name)?;
+        let entry = self.delete_entry(name)?;
+        Ok(entry || secret)
+    }
+
+    /// The same audit event for several entries
+    fn append_audit_events(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_names: &[&str],
+        user: Option<&str>,
+    ) -> Result<()> {
+        for name in entry_names {
+            self.append_audit_event(timestamp, action, Some(name), user)?;
+        }
+        Ok(())
+    }
+
+    /// All values of a secret, oldest first (the last is the current one);
+    /// without a history that is only the current value
+    fn get_secret_versions(&self, name: &str) -> Result<Vec<SecretVersion>> {
+        let Some(encrypted_value) = self.get_secret(name)? else {
+            return Ok(Vec::new());
+        };
+        let created_at = self
+            .get_entry(name)?
+            .and_then(|entry| entry.updated_at)
+            .unwrap_or_default();
+        Ok(vec![SecretVersion {
+            version: 1,
+            encrypted_value,
+            created_at,
+            replaced_at: None,
+        }])
+    }
+
+    /// Make an earlier value of a secret the current one
+    fn restore_secret_version(&self, name: &str, version: i64) -> Result<()> {
+        let value = self
+            .get_secret_versions(name)?
+            .into_iter()
+            .find(|v| v.version == version)
+            .ok_or_else(|| {
+                CcmError::InvalidArgument(format!(
+                    "Version {} of '{}' not found in history",
+                    version, name
+                ))
+            })?;
+        self.save_secret(name, &value.encrypted_value)
+    }
+
+    /// Restore entries with their encrypted secrets from a backup; with
+    /// `replace` the entries not in it are deleted first
+    fn restore_entries(&self, items: &[(Entry, String)], replace: bool) -> Result<()> {
+        if replace {
+            for name in self.get_all_entries()?.into_keys() {
+                if !items.iter().any(|(entry, _)| entry.name == name) {
+                    self.delete_entry_and_secret(&name)?;
+                }
+            }
+        }
+        for (entry, encrypted_value) in items {
+            self.save_entry(&entry.name, entry)?;
+            self.save_secret(&entry.name, encrypted_value)?;
+        }
+        Ok(())
+    }
+
+    /// Save entries with their encrypted secrets and delete others (in one
+    /// transaction where the backend has them)
+    fn apply_entries(&self, saved: &[(Entry, String)], deleted: &[String]) -> Result<()> {
+        for (entry, encrypted_value) in saved {
+            self.save_entry(&entry.name, entry)?;
+            self.save_secret(&entry.name, encrypted_value)?;
+        }
+        for name in deleted {
+            self.delete_entry_and_secret(name)?;
+        }
+        Ok(())
+    }
+}
+
+impl dyn StorageBackend + '_ {
+    /// A setting, deserialized
+    pub fn get_setting<T>(&self, key: &str) -> Result<Option<T>>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        self.get_setting_json(key)?
+            .map(|value| serde_json::from_str(&value).map_err(CcmError::Serialization))
+            .transpose()
+    }
+
+    /// Save a setting, serialized
+    pub fn save_setting<T>(&self, key: &str, value: &T) -> Result<()>
+    where
+        T: serde::Serialize,
+    {
+        self.save_setting_json(key, &serde_json::to_string(value)?)
+    }
+
+    /// The entry name policy from the names.* config keys
+    pub fn get_name_policy(&self) -> Result<crate::utils::NamePolicy> {
+        super::read_name_policy(|key| self.get_setting::<String>(key))
+    }
+}
+
+/// The storage of the vault: the server given by --remote / CCM_REMOTE,
+/// else the SQLite database
+/// The first successful open in a process runs the deferred initialization
+pub fn storage() -> Result<Box<dyn StorageBackend>> {
+    if let Some(url) = crate::remote::remote_url() {
+        return Ok(Box::new(crate::remote::client::RemoteBackend::new(&url)?));
+    }
+    Ok(Box::new(get_database()?))
+}
+
+impl StorageBackend for Database {
+    fn get_entry(&self, name: &str) -> Result<Option<Entry>> {
+        Database::get_entry(self, name)
+    }
+
+    fn save_entry(&self, name: &str, entry: &Entry) -> Result<()> {
+        Database::save_entry(self, name, entry)
+    }
+
+    fn delete_entry(&self, name: &str) -> Result<bool> {
+        Database::delete_entry(self, name)
+    }
+
+    fn mark_used(&self, name: &str, at: &str) -> Result<()> {
+        Database::mark_used(self, name, at)
+    }
+
+    fn set_locked(&self, name: &str, locked: bool) -> Result<()> {
+        Database::set_locked(self, name, locked)
+    }
+
+    fn set_favorite(&self, name: &str, favorite: bool) -> Result<()> {
+        Database::set_favorite(self, name, favorite)
+    }
+
+    fn for_each_entry(
+        &self,
+        filter: &EntryFilter,
+        f: &mut dyn FnMut(Entry) -> Result<()>,
+    ) -> Result<()> {
+        Database::for_each_entry(self, filter, f)
+    }
+
+    fn search_entries(&self, query: &str, filter: &EntryFilter) -> Result<Vec<Entry>> {
+        Database::search_entries(self, query, filter)
+    }
+
+    fn get_secret(&self, name: &str) -> Result<Option<String>> {
+        Database::get_secret(self, name)
+    }
+
+    fn save_secret(&self, name: &str, encrypted_value: &str) -> Result<()> {
+        Database::save_secret(self, name, encrypted_value)
+    }
+
+    fn delete_secret(&self, name: &str) -> Result<bool> {
+        Database::delete_secret(self, name)
+    }
+
+    fn get_setting_json(&self, key: &str) -> Result<Option<String>> {
+        Database::get_setting_json(self, key)
+    }
+
+    fn save_setting_json(&self, key: &str, value: &str) -> Result<()> {
+        Database::save_setting_json(self, key, value)
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<bool> {
+        Database::delete_setting(self, key)
+    }
+
+    fn append_audit_event(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_name: Option<&str>,
+        user: Option<&str>,
+    ) -> Result<()> {
+        Database::append_audit_event(self, timestamp, action, entry_name, user)
+    }
+
+    fn get_all_entries(&self) -> Result<HashMap<String, Entry>> {
+        Database::get_all_entries(self)
+    }
+
+    fn insert_entries_batch(&self, items: &[(Entry, String)]) -> Result<()> {
+        Database::insert_entries_batch(self, items)
+    }
+
+    fn delete_entry_and_secret(&self, name: &str) -> Result<bool> {
+        Database::delete_entry_and_secret(self, name)
+    }
+
+    fn append_audit_events(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_names: &[&str],
+        user: Option<&str>,
+    ) -> Result<()> {
+        Database::append_audit_events(self, timestamp, action, entry_names, user)
+    }
+
+    fn get_secret_versions(&self, name: &str) -> Result<Vec<SecretVersion>> {
+        Database::get_secret_versions(self, name)
+    }
+
+    fn restore_secret_version(&self, name: &str, version: i64) -> Result<()> {
+        Database::restore_secret_version(self, name, version)
+    }
+
+    fn restore_entries(&self, items: &[(Entry, String)], replace: bool) -> Result<()> {
+        Database::restore_entries(self, items, replace)
+    }
+
+    fn apply_entries(&self, saved: &[(Entry, String)], deleted: &[String]) -> Result<()> {
+        Database::apply_entries(self, saved, deleted)
+    }
+}
+
+/// Everything in memory (tests)
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryBackend {
+    entries: std::sync::Mutex<std::collections::BTreeMap<String, Entry>>,
+    secrets: std::sync::Mutex<HashMap<String, String>>,
+    settings: std::sync::Mutex<HashMap<String, String>>,
+    /// (action, entry name)
+    pub audit: std::sync::Mutex<Vec<(String, Option<String>)>>,
+}
+
+#[cfg(test)]
+impl StorageBackend for MemoryBackend {
+    fn get_entry(&self, name: &str) -> Result<Option<Entry>> {
+        Ok(self.entries.lock().unwrap().get(name).cloned())
+    }
+
+    fn save_entry(&self, name: &str, entry: &Entry) -> Result<()> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut entry = entry.clone();
+        entry.created_at.get_or_insert_with(|| now.clone());
+        entry.updated_at = Some(now);
+        self.entries.lock().unwrap().insert(name.to_string(), entry);
+        Ok(())
+    }
+
+    fn delete_entry(&self, name: &str) -> Result<bool> {
+        Ok(self.entries.lock().unwrap().remove(name).is_some())
+    }
+
+    fn mark_used(&self, name: &str, at: &str) -> Result<()> {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(name) {
+            entry.last_used_at = Some(at.to_string());
+        }
+        Ok(())
+    }
+
+    fn set_locked(&self, name: &str, locked: bool) -> Result<()> {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(name) {
+            entry.locked = locked;
+        }
+        Ok(())
+    }
+
+    fn set_favorite(&self, name: &str, favorite: bool) -> Result<()> {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(name) {
+            entry.favorite = favorite;
+        }
+        Ok(())
+    }
+
+    fn for_each_entry(
+        &self,
+        filter: &EntryFilter,
+        f: &mut dyn FnMut(Entry) -> Result<()>,
+    ) -> Result<()> {
+        if filter.expr.is_some() {
+            return Err(CcmError::InvalidArgument(
+                "--where needs the SQLite storage".to_string(),
+            ));
+        }
+        let entries: Vec<Entry> = self.entries.lock().unwrap().values().cloned().collect();
+        for entry in entries.into_iter().filter(|e| matches(filter, e)) {
+            f(entry)?;
+        }
+        Ok(())
+    }
+
+    fn get_secret(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.secrets.lock().unwrap().get(name).cloned())
+    }
+
+    fn save_secret(&self, name: &str, encrypted_value: &str) -> Result<()> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), encrypted_value.to_string());
+        Ok(())
+    }
+
+    fn delete_secret(&self, name: &str) -> Result<bool> {
+        Ok(self.secrets.lock().unwrap().remove(name).is_some())
+    }
+
+    fn get_setting_json(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.settings.lock().unwrap().get(key).cloned())
+    }
+
+    fn save_setting_json(&self, key: &str, value: &str) -> Result<()> {
+        self.settings
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<bool> {
+        Ok(self.settings.lock().unwrap().remove(key).is_some())
+    }
+
+    fn append_audit_event(
+        &self,
+        _timestamp: &str,
+        action: &str,
+        entry_name: Option<&str>,
+        _user: Option<&str>,
+    ) -> Result<()> {
+        self.audit
+            .lock()
+            .unwrap()
+            .push((action.to_string(), entry_name.map(str::to_string)));
+        Ok(())
+    }
+}
+
+/// Whether an entry passes a filter without a `--where` expression
+#[cfg(test)]
+fn matches(filter: &EntryFilter, entry: &Entry) -> bool {
+    let tags = entry.tags.as_deref().unwrap_or_default();
+    let has_tag = |tag: &String| tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+    let on_domain = |domain: &String| {
+        entry
+            .domain()
+            .is_some_and(|d| d == *domain || d.ends_with(&format!(".{}", domain)))
+    };
+
+    filter
+        .name_contains
+        .as_ref()
+        .is_none_or(|part| entry.name.to_lowercase().contains(&part.to_lowercase()))
+        && filter
+            .name_glob
+            .as_ref()
+            .is_none_or(|pattern| crate::utils::glob::matches(pattern, &entry.name))
+        && filter.tags.iter().all(has_tag)
+        && (!filter.untagged || tags.is_empty())
+        && (!filter.favorite || entry.favorite)
+        && filter.group.as_ref().is_none_or(|group| {
+            crate::utils::glob::matches(&crate::utils::tree::group_glob(group), &entry.name)
+        })
+        && filter.domain.as_ref().is_none_or(on_domain)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn entry(name: &str, tags: &[&str]) -> Entry {
+        let metadata = HashMap::from([("API_KEY".to_string(), "SECRET".to_string())]);
+        let mut entry = Entry::new(name.to_string(), metadata);
+        if !tags.is_empty() {
+            entry.tags = Some(tags.iter().map(|t| t.to_string()).collect());
+        }
+        entry
+    }
+
+    /// The same behaviour from every backend
+    pub(crate) fn check_backend(backend: &dyn StorageBackend) {
+        backend.save_entry("b", &entry("b", &["work"])).unwrap();
+        backend.save_secret("b", "00ff").unwrap();
+        assert_eq!(backend.get_entry("b").unwrap().unwrap().name, "b");
+        assert_eq!(backend.get_secret("b").unwrap().as_deref(), Some("00ff"));
+        assert!(backend.get_entry("missing").unwrap().is_none());
+
+        let updated_at = backend.get_entry("b").unwrap().unwrap().updated_at;
+        backend.mark_used("b", "2024-01-02T03:04:05.000Z").unwrap();
+        backend
+            .mark_used("missing", "2024-01-02T03:04:05.000Z")
+            .unwrap();
+        let used = backend.get_entry("b").unwrap().unwrap();
+        assert_eq!(
+            used.last_used_at.as_deref(),
+            Some("2024-01-02T03:04:05.000Z")
+        );
+        assert_eq!(used.updated_at, updated_at);
+
+        backend.set_locked("b", true).unwrap();
+        let locked = backend.get_entry("b").unwrap().unwrap();
+        assert!(locked.locked);
+        assert_eq!(locked.updated_at, updated_at);
+        // Saving the entry back keeps the flag
+        backend.save_entry("b", &locked).unwrap();
+        assert!(backend.get_entry("b").unwrap().unwrap().locked);
+        backend.set_locked("b", false).unwrap();
+        assert!(!backend.get_entry("b").unwrap().unwrap().locked);
+
+        let saved_at = backend.get_entry("b").unwrap().unwrap().updated_at;
+        backend.set_favorite("b", true).unwrap();
+        let favorite = backend.get_entry("b").unwrap().unwrap();
+        assert!(favorite.favorite);
+        assert_eq!(favorite.updated_at, saved_at);
+        let favorites = EntryFilter {
+            favorite: true,
+            ..EntryFilter::default()
+        };
+        let mut names = Vec::new();
+        backend
+            .for_each_entry(&favorites, &mut |e| {
+                names.push(e.name);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(names, ["b"]);
+        backend.set_favorite("b", false).unwrap();
+
+        let items = vec![
+            (entry("a", &[]), "aa".to_string()),
+            (entry("c", &["Work"]), "cc".to_string()),
+        ];
+        backend.insert_entries_batch(&items).unwrap();
+        // One existing name rejects the whole batch
+        let clash = vec![
+            (entry("d", &[]), "dd".to_string()),
+            (entry("a", &[]), "aa".to_string()),
+        ];
+        assert!(backend.insert_entries_batch(&clash).is_err());
+        assert!(backend.get_entry("d").unwrap().is_none());
+
+        let mut names = Vec::new();
+        let filter = EntryFilter {
+            tags: vec!["work".to_string()],
+            ..Default::default()
+        };
+        backend
+            .for_each_entry(&filter, &mut |e| {
+                names.push(e.name);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(names, ["b", "c"]);
+        assert_eq!(backend.get_all_entries().unwrap().len(), 3);
+        let found = backend
+            .search_entries("work", &EntryFilter::default())
+            .unwrap();
+        assert_eq!(found.len(), 2);
+
+        assert!(backend.delete_entry_and_secret("b").unwrap());
+        assert!(!backend.delete_entry_and_secret("b").unwrap());
+        assert!(backend.get_secret("b").unwrap().is_none());
+
+        // A setting remote clients may use
+        let key = "active_entry";
+        backend.save_setting(key, &42).unwrap();
+        assert_eq!(backend.get_setting::<i32>(key).unwrap(), Some(42));
+        assert!(backend.delete_setting(key).unwrap());
+        assert_eq!(backend.get_setting::<i32>(key).unwrap(), None);
+
+        backend
+            .append_audit_events("2024-01-01T00:00:00Z", "entry.create", &["a", "c"], None)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_backend() {
+        check_backend(&Database::open_in_memory().unwrap());
+    }
+
+    #[test]
+    fn test_memory_backend() {
+        let backend = MemoryBackend::default();
+        check_backend(&backend);
+        assert_eq!(backend.audit.lock().unwrap().len(), 2);
+    }
+}
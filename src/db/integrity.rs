@@ -0,0 +1,517 @@
+// Database integrity checks and repair (`ccm db check` / `ccm db repair`)
+//
+// Besides SQLite's own page-level check, every entry must have a secret and
+// every secret an entry; history rows must belong to an existing secret.
+// Repair removes the half that has lost its partner in one transaction.
+//
+// Each entry row also carries an HMAC of its name, metadata, tags, notes and
+// `locked` flag, and each secret and secret history row an HMAC of its name
+// and ciphertext, keyed from the master key. Rows are checked on every read
+// and all at once by `ccm db verify`, so a row edited with another SQLite
+// client, or a ciphertext or MAC copied from another entry, is refused
+// instead of silently used.
+//
+// Not covered: last use, favorite and timestamps (bookkeeping, not used for
+// decisions), the version numbers of history rows (so versions of one secret
+// can be reordered) and whole rows deleted together with their partners. The
+// MAC key comes from the master key, so anyone holding it can forge MACs;
+// the check is against edits by someone who can open the file (SQLCipher key
+// or a stolen key file) without going through ccm, not against the owner.
+
+use crate::db::{get_database, Database};
+use crate::utils::crypto::{hmac_sha256, hmac_sha256_verify};
+use crate::utils::timings::{self, Phase};
+use crate::utils::{CcmError, Result};
+use rusqlite::{params, Connection};
+
+/// Problems found by `Database::check_integrity`
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct IntegrityReport {
+    /// Messages from `PRAGMA integrity_check` (empty when it reports ok)
+    #[serde(rename = "sqliteErrors")]
+    pub sqlite_errors: Vec<String>,
+    /// Entries whose secret is missing (the value is lost)
+    #[serde(rename = "entriesWithoutSecret")]
+    pub entries_without_secret: Vec<String>,
+    /// Secrets left behind by a deleted entry
+    #[serde(rename = "secretsWithoutEntry")]
+    pub secrets_without_entry: Vec<String>,
+    /// Names with history rows but no current secret
+    #[serde(rename = "orphanedHistory")]
+    pub orphaned_history: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.sqlite_errors.is_empty() && !self.has_orphans()
+    }
+
+    /// Whether there are entry/secret mismatches that `repair` can fix
+    pub fn has_orphans(&self) -> bool {
+        !self.entries_without_secret.is_empty()
+            || !self.secrets_without_entry.is_empty()
+            || !self.orphaned_history.is_empty()
+    }
+}
+
+/// What `Database::repair` removed
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepairSummary {
+    pub entries_removed: usize,
+    pub secrets_removed: usize,
+    pub history_removed: usize,
+}
+
+const ENTRIES_WITHOUT_SECRET: &str =
+    "SELECT name FROM entries WHERE name NOT IN (SELECT name FROM secrets) ORDER BY name";
+const SECRETS_WITHOUT_ENTRY: &str =
+    "SELECT name FROM secrets WHERE name NOT IN (SELECT name FROM entries) ORDER BY name";
+const ORPHANED_HISTORY: &str = "SELECT DISTINCT name FROM secret_history
+     WHERE name NOT IN (SELECT name FROM secrets) ORDER BY name";
+
+fn names(conn: &Connection, sql: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let names = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<String>, _>>()?;
+    Ok(names)
+}
+
+/// Key of the row MACs, derived from the master key
+pub(crate) fn row_mac_key(master_key: &[u8; 32]) -> [u8; 32] {
+    hmac_sha256(master_key, b"ccm entry row mac v1")
+}
+
+/// The MACed fields of a row, each length-prefixed (absent fields differ
+/// from empty ones); the first names the table, so an entry MAC never
+/// verifies a secret row
+fn mac_input(fields: &[Option<&str>]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for &field in fields {
+        match field {
+            Some(value) => {
+                input.push(1);
+                input.extend_from_slice(&(value.len() as u64).to_be_bytes());
+                input.extend_from_slice(value.as_bytes());
+            }
+            None => input.push(0),
+        }
+    }
+    input
+}
+
+fn entry_fields<'a>(
+    name: &'a str,
+    metadata: &'a str,
+    tags: Option<&'a str>,
+    notes: Option<&'a str>,
+    locked: bool,
+) -> [Option<&'a str>; 6] {
+    let locked = if locked { "1" } else { "0" };
+    [
+        Some("entries"),
+        Some(name),
+        Some(metadata),
+        tags,
+        notes,
+        Some(locked),
+    ]
+}
+
+fn mac_valid(key: &[u8; 32], fields: &[Option<&str>], mac: Option<&str>) -> bool {
+    mac.and_then(|mac| hex::decode(mac).ok())
+        .is_some_and(|tag| hmac_sha256_verify(key, &mac_input(fields), &tag))
+}
+
+/// MAC of an entry row as stored (hex)
+pub(crate) fn row_mac(
+    key: &[u8; 32],
+    name: &str,
+    metadata: &str,
+    tags: Option<&str>,
+    notes: Option<&str>,
+    locked: bool,
+) -> String {
+    let fields = entry_fields(name, metadata, tags, notes, locked);
+    hex::encode(hmac_sha256(key, &mac_input(&fields)))
+}
+
+/// Whether a row's stored MAC matches its contents (a missing MAC does not)
+pub(crate) fn row_mac_valid(
+    key: &[u8; 32],
+    name: &str,
+    metadata: &str,
+    tags: Option<&str>,
+    notes: Option<&str>,
+    locked: bool,
+    mac: Option<&str>,
+) -> bool {
+    mac_valid(key, &entry_fields(name, metadata, tags, notes, locked), mac)
+}
+
+/// MAC of a secret (or secret history) row as stored (hex)
+pub(crate) fn secret_mac(key: &[u8; 32], name: &str, encrypted_value: &str) -> String {
+    let fields = [Some("secrets"), Some(name), Some(encrypted_value)];
+    hex::encode(hmac_sha256(key, &mac_input(&fields)))
+}
+
+/// Whether a secret row's stored MAC matches its name and ciphertext
+pub(crate) fn secret_mac_valid(
+    key: &[u8; 32],
+    name: &str,
+    encrypted_value: &str,
+    mac: Option<&str>,
+) -> bool {
+    let fields = [Some("secrets"), Some(name), Some(encrypted_value)];
+    mac_valid(key, &fields, mac)
+}
+
+/// Raw MACed columns of an entry row, and its stored MAC
+type MacRow = (
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    bool,
+    Option<String>,
+);
+
+fn mac_rows(conn: &Connection) -> Result<Vec<MacRow>> {
+    let mut stmt =
+        conn.prepare("SELECT name, metadata, tags, notes, locked, mac FROM entries ORDER BY name")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<MacRow>, _>>()?;
+    Ok(rows)
+}
+
+/// Name, ciphertext and stored MAC of every secret and history row
+fn secret_mac_rows(conn: &Connection) -> Result<Vec<(String, String, Option<String>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, encrypted_value, mac FROM secrets
+         UNION ALL SELECT name, encrypted_value, mac FROM secret_history",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+impl Database {
+    /// Names whose entry, secret or secret history rows have a MAC that is
+    /// missing or does not match
+    pub fn verify_rows(&self) -> Result<Vec<String>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let entries = mac_rows(&conn)?
+            .into_iter()
+            .filter(|(name, metadata, tags, notes, locked, mac)| {
+                !row_mac_valid(
+                    &self.mac_key,
+                    name,
+                    metadata,
+                    tags.as_deref(),
+                    notes.as_deref(),
+                    *locked,
+                    mac.as_deref(),
+                )
+            })
+            .map(|(name, ..)| name);
+        let secrets = secret_mac_rows(&conn)?
+            .into_iter()
+            .filter(|(name, value, mac)| {
+                !secret_mac_valid(&self.mac_key, name, value, mac.as_deref())
+            })
+            .map(|(name, ..)| name);
+
+        let mut names: Vec<String> = entries.chain(secrets).collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Accept the rows of these names as they are now: sign their entry,
+    /// secret and history rows again
+    pub fn reseal_rows(&self, names: &[String]) -> Result<usize> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        let sealed = self.seal_rows(&tx, Some(names))? + self.seal_secrets(&tx, Some(names))?;
+        tx.commit()?;
+        Ok(sealed)
+    }
+
+    /// Store the MAC of the named entry rows (all rows for `None`)
+    pub(super) fn seal_rows(&self, conn: &Connection, names: Option<&[String]>) -> Result<usize> {
+        let mut sealed = 0;
+        for (name, metadata, tags, notes, locked, _) in mac_rows(conn)? {
+            if names.is_some_and(|names| !names.contains(&name)) {
+                continue;
+            }
+            let mac = row_mac(
+                &self.mac_key,
+                &name,
+                &metadata,
+                tags.as_deref(),
+                notes.as_deref(),
+                locked,
+            );
+            sealed += conn.execute(
+                "UPDATE entries SET mac = ?1 WHERE name = ?2",
+                params![mac, name],
+            )?;
+        }
+        Ok(sealed)
+    }
+
+    /// Store the MAC of the named secret and history rows (all for `None`)
+    pub(super) fn seal_secrets(
+        &self,
+        conn: &Connection,
+        names: Option<&[String]>,
+    ) -> Result<usize> {
+        let mut sealed = 0;
+        for table in ["secrets", "secret_history"] {
+            let rows = conn
+                .prepare(&format!(
+                    "SELECT rowid, name, encrypted_value FROM {}",
+                    table
+                ))?
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<std::result::Result<Vec<(i64, String, String)>, _>>()?;
+            for (rowid, name, value) in rows {
+                if names.is_some_and(|names| !names.contains(&name)) {
+                    continue;
+                }
+                let mac = secret_mac(&self.mac_key, &name, &value);
+                sealed += conn.execute(
+                    &format!("UPDATE {} SET mac = ?1 WHERE rowid = ?2", table),
+                    params![mac, rowid],
+                )?;
+            }
+        }
+        Ok(sealed)
+    }
+
+    /// Run SQLite's integrity check and look for entries and secrets that
+    /// lost their counterpart
+    pub fn check_integrity(&self) -> Result<IntegrityReport> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let mut sqlite_errors = names(&conn, "PRAGMA integrity_check")?;
+        if sqlite_errors == ["ok"] {
+            sqlite_errors.clear();
+        }
+
+        let mut orphaned_history = names(&conn, ORPHANED_HISTORY)?;
+        // An entry without a secret is reported once, not again for its history
+        let entries_without_secret = names(&conn, ENTRIES_WITHOUT_SECRET)?;
+        orphaned_history.retain(|name| !entries_without_secret.contains(name));
+
+        Ok(IntegrityReport {
+            sqlite_errors,
+            entries_without_secret,
+            secrets_without_entry: names(&conn, SECRETS_WITHOUT_ENTRY)?,
+            orphaned_history,
+        })
+    }
+
+    /// Delete entries without a secret, secrets without an entry and
+    /// history without a secret, in one transaction
+    pub fn repair(&self) -> Result<RepairSummary> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        let entries_removed = tx.execute(
+            "DELETE FROM entries WHERE name NOT IN (SELECT name FROM secrets)",
+            [],
+        )?;
+        let secrets_removed = tx.execute(
+            "DELETE FROM secrets WHERE name NOT IN (SELECT name FROM entries)",
+            [],
+        )?;
+        let history_removed = tx.execute(
+            "DELETE FROM secret_history WHERE name NOT IN (SELECT name FROM secrets)",
+            [],
+        )?;
+        tx.commit()?;
+
+        Ok(RepairSummary {
+            entries_removed,
+            secrets_removed,
+            history_removed,
+        })
+    }
+}
+
+/// Check the integrity of the vault database
+pub fn check_integrity() -> Result<IntegrityReport> {
+    get_database()?.check_integrity()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Entry;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_check_and_repair() {
+        let db = Database::open_in_memory().unwrap();
+        for name in ["good", "no-secret", "no-entry", "gone"] {
+            db.save_entry(name, &Entry::new(name.to_string(), HashMap::new()))
+                .unwrap();
+            db.save_secret(name, "v1").unwrap();
+        }
+        db.save_secret("good", "v2").unwrap();
+        db.save_secret("gone", "v2").unwrap();
+
+        assert!(db.check_integrity().unwrap().is_ok());
+
+        db.delete_secret("no-secret").unwrap();
+        db.delete_entry("no-entry").unwrap();
+        {
+            // History left behind by rows deleted outside delete_secret
+            let conn = db.conn.lock().unwrap();
+            conn.execute("DELETE FROM entries WHERE name = 'gone'", [])
+                .unwrap();
+            conn.execute("DELETE FROM secrets WHERE name = 'gone'", [])
+                .unwrap();
+        }
+
+        let report = db.check_integrity().unwrap();
+        assert!(report.sqlite_errors.is_empty());
+        assert_eq!(report.entries_without_secret, vec!["no-secret"]);
+        assert_eq!(report.secrets_without_entry, vec!["no-entry"]);
+        assert_eq!(report.orphaned_history, vec!["gone"]);
+        assert!(!report.is_ok());
+
+        let summary = db.repair().unwrap();
+        assert_eq!(summary.entries_removed, 1);
+        assert_eq!(summary.secrets_removed, 1);
+        assert_eq!(summary.history_removed, 1);
+        assert!(db.check_integrity().unwrap().is_ok());
+        assert!(db.get_entry("good").unwrap().is_some());
+        assert_eq!(db.get_secret_versions("good").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_row_mac() {
+        let db = Database::open_in_memory().unwrap();
+        for name in ["a", "b", "c"] {
+            let mut entry = Entry::new(name.to_string(), HashMap::new());
+            entry.notes = Some(format!("notes of {}", name));
+            db.save_entry(name, &entry).unwrap();
+        }
+        assert!(db.verify_rows().unwrap().is_empty());
+
+        {
+            // Edits made with another SQLite client
+            let conn = db.conn.lock().unwrap();
+            conn.execute("UPDATE entries SET notes = 'changed' WHERE name = 'a'", [])
+                .unwrap();
+            conn.execute(
+                "UPDATE entries SET mac = (SELECT mac FROM entries WHERE name = 'a') WHERE name = 'b'",
+                [],
+            )
+            .unwrap();
+            conn.execute("UPDATE entries SET mac = NULL WHERE name = 'c'", [])
+                .unwrap();
+        }
+        assert_eq!(db.verify_rows().unwrap(), vec!["a", "b", "c"]);
+        let err = db.get_entry("a").unwrap_err();
+        assert!(matches!(err, CcmError::Integrity(_)), "{}", err);
+        assert!(db.get_all_entries().is_err());
+
+        assert_eq!(db.reseal_rows(&["a".to_string()]).unwrap(), 1);
+        assert_eq!(db.verify_rows().unwrap(), vec!["b", "c"]);
+        assert_eq!(
+            db.get_entry("a").unwrap().unwrap().notes.as_deref(),
+            Some("changed")
+        );
+
+        // Absent and empty notes are different rows
+        assert_ne!(
+            row_mac(&db.mac_key, "x", "{}", None, None, false),
+            row_mac(&db.mac_key, "x", "{}", None, Some(""), false)
+        );
+    }
+
+    const SWAP_SECRETS: &str = "UPDATE secrets SET name = 'tmp' WHERE name = 'a';
+         UPDATE secrets SET name = 'a' WHERE name = 'b';
+         UPDATE secrets SET name = 'b' WHERE name = 'tmp';";
+
+    #[test]
+    fn test_lock_and_secret_mac() {
+        let db = Database::open_in_memory().unwrap();
+        for name in ["a", "b"] {
+            db.save_entry(name, &Entry::new(name.to_string(), HashMap::new()))
+                .unwrap();
+            db.save_secret(name, &format!("{}1", name)).unwrap();
+            db.save_secret(name, &format!("{}2", name)).unwrap();
+        }
+        db.set_locked("a", true).unwrap();
+        assert!(db.verify_rows().unwrap().is_empty());
+        assert!(db.get_entry("a").unwrap().unwrap().locked);
+
+        {
+            // Unlock `a` and swap the secrets of `a` and `b`, MACs included
+            let conn = db.conn.lock().unwrap();
+            conn.execute("UPDATE entries SET locked = 0 WHERE name = 'a'", [])
+                .unwrap();
+            conn.execute_batch(SWAP_SECRETS).unwrap();
+        }
+        assert_eq!(db.verify_rows().unwrap(), vec!["a", "b"]);
+        assert!(matches!(
+            db.get_entry("a").unwrap_err(),
+            CcmError::Integrity(_)
+        ));
+        let err = db.get_secret("a").unwrap_err();
+        assert!(matches!(err, CcmError::Integrity(_)), "{}", err);
+        assert!(db.get_secret_versions("b").is_err());
+
+        {
+            // A history row copied to another name is refused on rollback
+            let conn = db.conn.lock().unwrap();
+            conn.execute_batch(SWAP_SECRETS).unwrap();
+            conn.execute(
+                "UPDATE secret_history SET encrypted_value = 'b1', mac =
+                   (SELECT mac FROM secret_history WHERE name = 'b')
+                 WHERE name = 'a'",
+                [],
+            )
+            .unwrap();
+        }
+        assert!(db.restore_secret_version("a", 1).is_err());
+
+        assert_eq!(db.reseal_rows(&["a".to_string()]).unwrap(), 3);
+        assert!(db.verify_rows().unwrap().is_empty());
+        assert!(!db.get_entry("a").unwrap().unwrap().locked);
+        assert_eq!(db.get_secret("a").unwrap().as_deref(), Some("a2"));
+    }
+}
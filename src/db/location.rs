@@ -0,0 +1,226 @@
+// Where ccm keeps its files
+//
+// `CCM_HOME` puts everything in one directory. Otherwise Linux follows the
+// XDG Base Directory spec: the database, snapshots and quick-access store go
+// to `$XDG_DATA_HOME/ccm`, the instance ID and user presets to
+// `$XDG_CONFIG_HOME/ccm`.
+// Other platforms, and Linux installs whose `~/.ccm` could not be moved,
+// keep using `~/.ccm`. A vault opened with `--db` keeps its instance ID and
+// quick-access store next to its database instead.
+//
+// Settings stay in the database, so the config directory only holds those two
+// files. Runtime state (the per-shell auth state of `auth::auth_state_path`,
+// the agent socket) is not covered here: it lives in `auth::runtime_dir`,
+// `$XDG_RUNTIME_DIR/ccm` or else the temp directory.
+
+use crate::db::CCM_HOME_ENV;
+use crate::utils::files::create_private_dir;
+use crate::utils::{debug_print_category, Result};
+use std::path::{Path, PathBuf};
+
+/// Files that belong in the config directory rather than the data directory
+const CONFIG_FILES: &[&str] = &["instance_id", "presets.toml"];
+
+/// `CCM_HOME`, if set
+fn ccm_home() -> Option<PathBuf> {
+    std::env::var_os(CCM_HOME_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `~/.ccm`
+pub fn legacy_dir() -> PathBuf {
+    dirs::home_dir().unwrap().join(".ccm")
+}
+
+/// `$XDG_DATA_HOME/ccm` (Linux only)
+fn xdg_data_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "linux") {
+        dirs::data_dir().map(|dir| dir.join("ccm"))
+    } else {
+        None
+    }
+}
+
+/// `$XDG_CONFIG_HOME/ccm` (Linux only)
+fn xdg_config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "linux") {
+        dirs::config_dir().map(|dir| dir.join("ccm"))
+    } else {
+        None
+    }
+}
+
+/// Whether the XDG directories are in use: on Linux, unless a `~/.ccm`
+/// that was never migrated is still around
+fn uses_xdg() -> bool {
+    match xdg_data_dir() {
+        Some(data) => data.exists() || !legacy_dir().exists(),
+        None => false,
+    }
+}
+
+/// Directory for the database and other data
+pub fn data_dir() -> PathBuf {
+    if let Some(home) = ccm_home() {
+        return home;
+    }
+    match xdg_data_dir() {
+        Some(dir) if uses_xdg() => dir,
+        _ => legacy_dir(),
+    }
+}
+
+/// Directory for configuration (the instance ID, presets.toml)
+pub fn config_dir() -> PathBuf {
+    if let Some(home) = ccm_home() {
+        return home;
+    }
+    match xdg_config_dir() {
+        Some(dir) if uses_xdg() => dir,
+        _ => legacy_dir(),
+    }
+}
+
+/// Database file of the default vault
+pub fn default_db_path() -> PathBuf {
+    data_dir().join("ccm.db")
+}
+
+/// Instance ID file of the vault whose database is `db`: in the config
+/// directory for the default vault, else next to the database, so a vault
+/// opened with `--db` does not share the default vault's keyring entry
+pub fn instance_id_path(db: &Path) -> PathBuf {
+    if db == default_db_path() {
+        config_dir().join("instance_id")
+    } else {
+        with_suffix(db, ".instance_id")
+    }
+}
+
+/// Quick-access store of the vault whose database is `db` (see
+/// `instance_id_path`)
+pub fn quick_access_path(db: &Path) -> PathBuf {
+    if db == default_db_path() {
+        data_dir().join("quick-access.json")
+    } else {
+        with_suffix(db, ".quick-access.json")
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Move `~/.ccm` to the XDG directories, once. Returns the new data
+/// directory if something was moved; when the move is not possible (e.g. a
+/// different file system) `~/.ccm` stays in use
+pub fn migrate_legacy_dir() -> Result<Option<PathBuf>> {
+    let (Some(data), Some(config)) = (xdg_data_dir(), xdg_config_dir()) else {
+        return Ok(None);
+    };
+    if ccm_home().is_some() {
+        return Ok(None);
+    }
+    move_dir(&legacy_dir(), &data, &config)
+}
+
+/// Move `legacy` to `data` and its config files on to `config`; nothing
+/// when `data` already exists or `legacy` does not
+fn move_dir(legacy: &Path, data: &Path, config: &Path) -> Result<Option<PathBuf>> {
+    if data.exists() || !legacy.is_dir() {
+        return Ok(None);
+    }
+
+    if let Some(parent) = data.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if let Err(e) = std::fs::rename(legacy, data) {
+        debug_print_category("init", &format!("Keeping {}: {}", legacy.display(), e));
+        return Ok(None);
+    }
+
+    // A config file left behind is still found next to the database
+    create_private_dir(config)?;
+    for name in CONFIG_FILES {
+        let from = data.join(name);
+        if from.exists() {
+            let _ = std::fs::rename(&from, config.join(name));
+        }
+    }
+
+    Ok(Some(data.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_files() {
+        let default = default_db_path();
+        assert_eq!(instance_id_path(&default), config_dir().join("instance_id"));
+        assert_eq!(
+            quick_access_path(&default),
+            data_dir().join("quick-access.json")
+        );
+
+        let custom = Path::new("/mnt/secure/work.db");
+        assert_eq!(
+            instance_id_path(custom),
+            Path::new("/mnt/secure/work.db.instance_id")
+        );
+        assert_eq!(
+            quick_access_path(custom),
+            Path::new("/mnt/secure/work.db.quick-access.json")
+        );
+    }
+
+    #[test]
+    fn test_move_dir() {
+        let home = tempfile::tempdir().unwrap();
+        let legacy = home.path().join(".ccm");
+        let data = home.path().join(".local/share/ccm");
+        let config = home.path().join(".config/ccm");
+        std::fs::create_dir_all(legacy.join("backups")).unwrap();
+        for name in ["ccm.db", "instance_id", "presets.toml", "backups/one.db"] {
+            std::fs::write(legacy.join(name), name).unwrap();
+        }
+
+        assert_eq!(
+            move_dir(&legacy, &data, &config).unwrap(),
+            Some(data.clone())
+        );
+        assert!(!legacy.exists());
+        assert_eq!(
+            std::fs::read_to_string(data.join("ccm.db")).unwrap(),
+            "ccm.db"
+        );
+        assert!(data.join("backups/one.db").exists());
+        assert_eq!(
+            std::fs::read_to_string(config.join("instance_id")).unwrap(),
+            "instance_id"
+        );
+        assert!(config.join("presets.toml").exists());
+        assert!(!data.join("instance_id").exists());
+
+        // Once moved, or with nothing to move, nothing happens
+        assert_eq!(move_dir(&legacy, &data, &config).unwrap(), None);
+        std::fs::create_dir(&legacy).unwrap();
+        std::fs::write(legacy.join("ccm.db"), "new").unwrap();
+        assert_eq!(move_dir(&legacy, &data, &config).unwrap(), None);
+        assert_eq!(
+            std::fs::read_to_string(data.join("ccm.db")).unwrap(),
+            "ccm.db"
+        );
+        let elsewhere = home.path().join("other");
+        assert_eq!(
+            move_dir(&home.path().join("missing"), &elsewhere, &config).unwrap(),
+            None
+        );
+        assert!(!elsewhere.exists());
+    }
+}
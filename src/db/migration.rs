@@ -4,7 +4,7 @@
 use crate::db;
 use crate::secrets;
 use crate::types::Entry;
-use crate::utils::{CcmError, Result};
+use crate::utils::{output, CcmError, Result};
 use colored::Colorize;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -29,10 +29,12 @@ pub fn needs_migration() -> bool {
 fn find_legacy_files() -> Vec<PathBuf> {
     let mut files = Vec::new();
 
-    // Check home directory for .ccm files
-    if let Some(home) = dirs::home_dir() {
-        let ccm_dir = home.join(".ccm");
-
+    // Check the data directory (and ~/.ccm, if it was not moved) for old files
+    let mut ccm_dirs = vec![db::db_dir()];
+    if !ccm_dirs.contains(&db::location::legacy_dir()) {
+        ccm_dirs.push(db::location::legacy_dir());
+    }
+    for ccm_dir in ccm_dirs {
         // cstore.json - secret store from older versions
         let cstore = ccm_dir.join("cstore.json");
         if cstore.exists() {
@@ -134,12 +136,10 @@ pub fn run_migration() -> Result<MigrationResult> {
 
     println!();
     if result.entries_migrated > 0 {
-        println!(
-            "{} Migration complete: {} entries from {} files",
-            "✅".green(),
-            result.entries_migrated,
-            result.files_processed
-        );
+        output::success(format!(
+            "Migration complete: {} entries from {} files",
+            result.entries_migrated, result.files_processed
+        ));
     }
 
     if !result.errors.is_empty() {
@@ -292,90 +292,3 @@ pub struct MigrationResult {
     pub entries_migrated: usize,
     pub errors: Vec<String>,
 }
-
-/// Check if default profiles should be created (first run with empty database)
-pub fn should_create_defaults() -> bool {
-    // Check if we've already created defaults
-    if let Ok(db) = db::get_database() {
-        if let Ok(Some(_)) = db.get_setting::<String>("defaults_created") {
-            return false;
-        }
-    }
-
-    // Check if database is empty
-    match secrets::list_entries() {
-        Ok(entries) => entries.is_empty(),
-        Err(_) => false,
-    }
-}
-
-/// Create default API profiles for first-time users
-pub fn create_default_profiles() -> Result<usize> {
-    if !should_create_defaults() {
-        return Ok(0);
-    }
-
-    println!(
-        "\n{} First run detected - creating default profiles...",
-        "ℹ️".blue()
-    );
-
-    let mut count = 0;
-
-    // Default profile - Claude via proxy
-    let default_profile = create_default_api_entry(
-        "default",
-        "https://api.anthropic.com",
-        "claude-sonnet-4-20250514",
-        "claude",
-    );
-    if let Ok(entry) = default_profile {
-        // Use a placeholder key - user will update it
-        if secrets::add_entry("default", entry, "sk-ant-REDACTED").is_ok() {
-            println!("  {} Created 'default' profile (Claude API)", "✅".green());
-            count += 1;
-        }
-    }
-
-    // Backup profile - direct Anthropic
-    let backup_profile = create_default_api_entry(
-        "backup",
-        "https://api.anthropic.com",
-        "claude-sonnet-4-20250514",
-        "claude",
-    );
-    if let Ok(entry) = backup_profile {
-        if secrets::add_entry("backup", entry, "sk-ant-REDACTED").is_ok() {
-            println!(
-                "  {} Created 'backup' profile (Anthropic direct)",
-                "✅".green()
-            );
-            count += 1;
-        }
-    }
-
-    // Mark defaults as created
-    if let Ok(db) = db::get_database() {
-        let timestamp = chrono::Utc::now().to_rfc3339();
-        let _ = db.save_setting("defaults_created", &timestamp);
-    }
-
-    if count > 0 {
-        println!();
-        println!("{} Default profiles created.", "✅".green());
-        println!("   Update API keys with: ccm update <name> --key <your-api-key>");
-    }
-
-    Ok(count)
-}
-
-/// Helper to create a default API entry
-fn create_default_api_entry(_name: &str, base_url: &str, model: &str, tool: &str) -> Result<Entry> {
-    let mut metadata = HashMap::new();
-    metadata.insert("SECRET".to_string(), "SECRET".to_string());
-    metadata.insert("BASE_URL".to_string(), base_url.to_string());
-    metadata.insert("MODEL".to_string(), model.to_string());
-    metadata.insert("TOOL".to_string(), tool.to_string());
-
-    Ok(Entry::new(_name.to_string(), metadata))
-}
@@ -1,36 +1,75 @@
 // Database layer with SQLCipher encryption
 // All platforms use SQLCipher for database-level encryption
 
+pub mod backend;
+pub mod integrity;
+pub mod location;
 pub mod migration;
+pub mod query;
+pub mod search;
+pub mod snapshots;
 
 use crate::types::Entry;
-use crate::utils::{CcmError, Result};
+use crate::utils::timings::{self, Phase};
+use crate::utils::{output, tree, CcmError, NamePolicy, Result};
 use colored::Colorize;
-use rusqlite::{params, Connection};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+pub use backend::{storage, StorageBackend};
+
+/// Environment variable: directory holding the database and ccm's other
+/// files (default `~/.ccm`, or the XDG directories on Linux)
+pub const CCM_HOME_ENV: &str = "CCM_HOME";
+
+/// Columns read into an `Entry` (see `read_entry_row`)
+const ENTRY_COLUMNS: &str =
+    "name, metadata, tags, notes, created_at, updated_at, last_used_at, mac, locked, favorite";
+
+/// Database file chosen with the global `--db` flag
+static DB_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `path` as the database file for this process (`--db`)
+/// Relative paths are resolved against the current directory
+pub fn set_db_path(path: &Path) -> Result<()> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    DB_PATH_OVERRIDE
+        .set(path)
+        .map_err(|_| CcmError::Initialization("Database path already set".to_string()))
+}
 
-/// Database directory and file paths
+/// Database directory and file paths (see `location`)
 pub fn db_dir() -> PathBuf {
-    dirs::home_dir().unwrap().join(".ccm")
+    location::data_dir()
 }
 
+/// The vault's database file: `--db`, else the default one. The vault's
+/// other files are derived from it (see `location::instance_id_path`)
 pub fn db_path() -> PathBuf {
-    db_dir().join("ccm.db")
+    match DB_PATH_OVERRIDE.get() {
+        Some(path) => path.clone(),
+        None => location::default_db_path(),
+    }
 }
 
 /// Database wrapper with SQLCipher encryption
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
     path: PathBuf,
+    /// Key of the entry row MACs (see `integrity::row_mac`)
+    mac_key: [u8; 32],
 }
 
 impl Database {
-    /// Create a new database instance
-    pub fn new() -> Result<Self> {
-        let path = db_path();
-
+    /// Open (or create) the database at `path` with the master key
+    pub fn new(path: PathBuf) -> Result<Self> {
         // Ensure directory exists
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -39,17 +78,38 @@ impl Database {
         Self::new_sqlcipher(path)
     }
 
+    /// Database file this connection was opened from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Create database with SQLCipher encryption
     fn new_sqlcipher(path: PathBuf) -> Result<Self> {
         use crate::secrets::master_key::get_cached_master_key;
 
         // Get master key for database encryption
         // Note: has_pin() now uses OS keychain, so no circular dependency
-        let master_key = get_cached_master_key()
-            .map_err(|e| CcmError::Unknown(format!("Failed to get master key: {}. Please run 'ccm auth set' first.", e)))?;
+        // Entry metadata is encrypted too, so even listing names needs the key
+        let master_key = get_cached_master_key().map_err(|e| match e {
+            CcmError::OsSecretServiceRequired => e,
+            e => CcmError::Unknown(format!(
+                "Failed to get master key: {}. Please run 'ccm auth set' first.",
+                e
+            )),
+        })?;
+
+        Self::open_with_key(path, &master_key)
+    }
 
+    /// Open (or create) the database at `path` keyed with `master_key`
+    /// Every page, including entry names, metadata, tags and notes, is
+    /// encrypted by SQLCipher; a wrong key fails to open the file
+    pub fn open_with_key(path: PathBuf, master_key: &[u8; 32]) -> Result<Self> {
         // Derive database encryption key from master key (64 hex chars)
-        let db_key = hex::encode(&master_key);
+        let db_key = hex::encode(master_key);
+
+        // Opening covers the SQLCipher key schedule and schema setup
+        let _span = timings::span(Phase::Database);
 
         // Open connection
         let conn = Connection::open(&path)?;
@@ -68,6 +128,7 @@ impl Database {
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
             path,
+            mac_key: integrity::row_mac_key(master_key),
         };
 
         db.init_schema_sqlcipher(is_new_db)?;
@@ -129,7 +190,7 @@ impl Database {
 
             if has_type_column {
                 // Run migration to remove type column
-                self.migrate_remove_type_column(&conn)?;
+                self.migrate_remove_type_column(conn)?;
             }
         }
 
@@ -146,6 +207,43 @@ impl Database {
             [],
         )?;
 
+        // Add the last-used column (stamped by get, use, exec...)
+        if !has_column(conn, "entries", "last_used_at") {
+            self.migrate_add_last_used_column(conn)?;
+        }
+
+        // Add the `ccm lock` flag (before anything reading ENTRY_COLUMNS or
+        // signing rows)
+        if !has_column(conn, "entries", "locked") {
+            conn.execute(
+                "ALTER TABLE entries ADD COLUMN locked INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+        }
+
+        // Add the row MAC column and sign the existing rows
+        if !has_column(conn, "entries", "mac") {
+            self.migrate_add_mac_column(conn)?;
+        }
+
+        // Add the `ccm fav` flag
+        if !has_column(conn, "entries", "favorite") {
+            conn.execute(
+                "ALTER TABLE entries ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+        }
+
+        // Add the URL domain column (indexed for `ccm search --domain`)
+        if !has_column(conn, "entries", "domain") {
+            self.migrate_add_domain_column(conn)?;
+        }
+
+        // Create the full-text index of the entries (kept in sync by triggers)
+        if !search::has_index(conn) {
+            search::create_index(conn)?;
+        }
+
         // Create secrets table
         conn.execute(
             "CREATE TABLE IF NOT EXISTS secrets (
@@ -157,6 +255,25 @@ impl Database {
             [],
         )?;
 
+        // Create secret history table (previous values of each secret)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS secret_history (
+                name TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                encrypted_value TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                replaced_at TEXT NOT NULL,
+                PRIMARY KEY (name, version)
+            )",
+            [],
+        )?;
+
+        // Add the secret MACs; entry MACs now cover `locked`, so sign every
+        // row again
+        if !has_column(conn, "secrets", "mac") {
+            self.migrate_add_secret_mac_columns(conn)?;
+        }
+
         // Create settings table
         conn.execute(
             "CREATE TABLE IF NOT EXISTS settings (
@@ -167,11 +284,31 @@ impl Database {
             [],
         )?;
 
+        // Create audit log table (append-only record of sensitive operations)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                action TEXT NOT NULL,
+                entry_name TEXT,
+                user TEXT
+            )",
+            [],
+        )?;
+
         // Create indexes
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_entries_updated ON entries(updated_at)",
             [],
         )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_entries_domain ON entries(domain)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp)",
+            [],
+        )?;
 
         Ok(())
     }
@@ -225,66 +362,181 @@ impl Database {
         let timestamp = chrono::Utc::now().to_rfc3339();
         let _ = self.save_setting("schema_migration_unified", &timestamp);
 
-        println!("{} Database migration complete", "✅".green());
+        output::success("Database migration complete");
+
+        Ok(())
+    }
+
+    /// Migrate database: add the last_used_at column and fill it from the
+    /// latest secret read in the audit log
+    fn migrate_add_last_used_column(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute("ALTER TABLE entries ADD COLUMN last_used_at TEXT", [])?;
+        if has_table(&tx, "audit_log") {
+            tx.execute(
+                "UPDATE entries SET last_used_at =
+                   (SELECT MAX(timestamp) FROM audit_log
+                    WHERE entry_name = entries.name AND action = ?1)",
+                params![crate::audit::SECRET_READ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Migrate database: add the mac column and sign every row as it is now
+    fn migrate_add_mac_column(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute("ALTER TABLE entries ADD COLUMN mac TEXT", [])?;
+        self.seal_rows(&tx, None)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Migrate database: add the mac column of secrets and their history, and
+    /// sign every entry, secret and history row as it is now
+    fn migrate_add_secret_mac_columns(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute("ALTER TABLE secrets ADD COLUMN mac TEXT", [])?;
+        tx.execute("ALTER TABLE secret_history ADD COLUMN mac TEXT", [])?;
+        self.seal_rows(&tx, None)?;
+        self.seal_secrets(&tx, None)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Migrate database: add the domain column and fill it from each entry's url
+    fn migrate_add_domain_column(&self, conn: &Connection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute("ALTER TABLE entries ADD COLUMN domain TEXT", [])?;
+
+        let entries = {
+            let mut stmt = tx.prepare(&format!("SELECT {} FROM entries", ENTRY_COLUMNS))?;
+            let rows = stmt.query_map([], read_entry_row)?;
+            let mut entries = Vec::new();
+            for row in rows {
+                entries.push(self.entry_from_row(row?)?);
+            }
+            entries
+        };
+
+        for entry in entries.iter().filter(|e| e.domain().is_some()) {
+            tx.execute(
+                "UPDATE entries SET domain = ?1 WHERE name = ?2",
+                params![entry.domain(), entry.name],
+            )?;
+        }
 
+        tx.commit()?;
         Ok(())
     }
 
     /// Get all entries
     pub fn get_all_entries(&self) -> Result<HashMap<String, Entry>> {
+        let mut entries = HashMap::new();
+
+        self.for_each_entry(&EntryFilter::default(), |entry| {
+            entries.insert(entry.name.clone(), entry);
+            Ok(())
+        })?;
+
+        Ok(entries)
+    }
+
+    /// Get a page of entries ordered by name
+    pub fn get_entries_page(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: &EntryFilter,
+    ) -> Result<EntryPage> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
-        let mut stmt = conn.prepare("SELECT * FROM entries")?;
+        let (where_sql, mut values) = filter.where_clause();
 
-        let entry_iter = stmt.query_map([], |row| {
-            let name: String = row.get(0)?;
-            let metadata: String = row.get(1)?;
-            let tags: Option<String> = row.get(2)?;
-            let notes: Option<String> = row.get(3)?;
-            let created_at: String = row.get(4)?;
-            let updated_at: String = row.get(5)?;
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM entries{}", where_sql),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
 
-            Ok((name, metadata, tags, notes, created_at, updated_at))
-        })?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM entries{} ORDER BY name LIMIT ? OFFSET ?",
+            ENTRY_COLUMNS, where_sql
+        ))?;
 
-        let mut entries = HashMap::new();
+        values.push(Value::Integer(limit as i64));
+        values.push(Value::Integer(offset as i64));
+        let rows = stmt.query_map(params_from_iter(values.iter()), read_entry_row)?;
 
-        for entry_data in entry_iter {
-            let (name, metadata, tags, notes, created_at, updated_at) = entry_data?;
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(self.entry_from_row(row?)?);
+        }
 
-            // Parse metadata as JSON object
-            let metadata_value: serde_json::Value =
-                serde_json::from_str(&metadata).map_err(CcmError::Serialization)?;
+        Ok(EntryPage {
+            entries,
+            offset,
+            total: total as usize,
+        })
+    }
 
-            let mut metadata_map = HashMap::new();
-            if let serde_json::Value::Object(map) = metadata_value {
-                for (k, v) in map {
-                    if let Some(s) = v.as_str() {
-                        metadata_map.insert(k, s.to_string());
-                    } else {
-                        metadata_map.insert(k, v.to_string());
-                    }
-                }
-            }
+    /// Visit entries ordered by name
+    /// The rows are read before `f` runs, so it may use the database itself
+    pub fn for_each_entry<F>(&self, filter: &EntryFilter, mut f: F) -> Result<()>
+    where
+        F: FnMut(Entry) -> Result<()>,
+    {
+        let rows = {
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|e| CcmError::Unknown(e.to_string()))?;
+            let _span = timings::span(Phase::Database);
+
+            let (where_sql, values) = filter.where_clause();
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM entries{} ORDER BY name",
+                ENTRY_COLUMNS, where_sql
+            ))?;
+            let rows = stmt.query_map(params_from_iter(values.iter()), read_entry_row)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
 
-            let mut entry = Entry::new(name.clone(), metadata_map);
-            entry.created_at = Some(created_at);
-            entry.updated_at = Some(updated_at);
-            entry.notes = notes;
+        for row in rows {
+            f(self.entry_from_row(row)?)?;
+        }
 
-            if let Some(tags_str) = tags {
-                let tags_vec: Vec<String> =
-                    serde_json::from_str(&tags_str).map_err(CcmError::Serialization)?;
-                entry.tags = Some(tags_vec);
-            }
+        Ok(())
+    }
 
-            entries.insert(name, entry);
-        }
+    /// Count entries matching a filter
+    pub fn count_entries(&self, filter: &EntryFilter) -> Result<usize> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
-        Ok(entries)
+        let (where_sql, values) = filter.where_clause();
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM entries{}", where_sql),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        Ok(count as usize)
     }
 
     /// Get a single entry
@@ -293,49 +545,17 @@ impl Database {
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
-        let mut stmt = conn.prepare("SELECT * FROM entries WHERE name = ?1")?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM entries WHERE name = ?1",
+            ENTRY_COLUMNS
+        ))?;
 
-        let mut entry_iter = stmt.query_map(params![name], |row| {
-            let metadata: String = row.get(1)?;
-            let tags: Option<String> = row.get(2)?;
-            let notes: Option<String> = row.get(3)?;
-            let created_at: String = row.get(4)?;
-            let updated_at: String = row.get(5)?;
-
-            Ok((metadata, tags, notes, created_at, updated_at))
-        })?;
+        let mut entry_iter = stmt.query_map(params![name], read_entry_row)?;
 
         if let Some(entry_data) = entry_iter.next() {
-            let (metadata, tags, notes, created_at, updated_at) = entry_data?;
-
-            // Parse metadata as JSON object
-            let metadata_value: serde_json::Value =
-                serde_json::from_str(&metadata).map_err(CcmError::Serialization)?;
-
-            let mut metadata_map = HashMap::new();
-            if let serde_json::Value::Object(map) = metadata_value {
-                for (k, v) in map {
-                    if let Some(s) = v.as_str() {
-                        metadata_map.insert(k, s.to_string());
-                    } else {
-                        metadata_map.insert(k, v.to_string());
-                    }
-                }
-            }
-
-            let mut entry = Entry::new(name.to_string(), metadata_map);
-            entry.created_at = Some(created_at);
-            entry.updated_at = Some(updated_at);
-            entry.notes = notes;
-
-            if let Some(tags_str) = tags {
-                let tags_vec: Vec<String> =
-                    serde_json::from_str(&tags_str).map_err(CcmError::Serialization)?;
-                entry.tags = Some(tags_vec);
-            }
-
-            Ok(Some(entry))
+            Ok(Some(self.entry_from_row(entry_data?)?))
         } else {
             Ok(None)
         }
@@ -347,97 +567,425 @@ impl Database {
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
+        self.write_entry(&conn, name, entry)
+    }
+
+    /// Insert or replace an entry row, stamping it as updated now
+    fn write_entry(&self, conn: &Connection, name: &str, entry: &Entry) -> Result<()> {
+        self.write_entry_row(conn, name, entry, false)
+    }
+
+    /// Insert or replace an entry row; `keep_updated_at` keeps the entry's own
+    /// update time (restoring a backup) instead of stamping it with now
+    fn write_entry_row(
+        &self,
+        conn: &Connection,
+        name: &str,
+        entry: &Entry,
+        keep_updated_at: bool,
+    ) -> Result<()> {
         // Convert metadata HashMap to JSON object
         let metadata_json = serde_json::to_string(&entry.metadata)?;
         let tags = entry.tags.as_ref().map(serde_json::to_string).transpose()?;
         let notes = entry.notes.as_deref();
         let now = chrono::Utc::now().to_rfc3339();
         let created_at = entry.created_at.as_deref().unwrap_or(&now);
-        let updated_at = &now;
+        let updated_at = match &entry.updated_at {
+            Some(updated_at) if keep_updated_at => updated_at,
+            _ => &now,
+        };
+
+        let mac = integrity::row_mac(
+            &self.mac_key,
+            name,
+            &metadata_json,
+            tags.as_deref(),
+            notes,
+            entry.locked,
+        );
 
         conn.execute(
-            "INSERT OR REPLACE INTO entries (name, metadata, tags, notes, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT OR REPLACE INTO entries
+               (name, metadata, tags, notes, created_at, updated_at, domain, last_used_at, mac, locked, favorite)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             params![
                 name,
                 metadata_json,
                 tags,
                 notes,
                 created_at,
-                updated_at
+                updated_at,
+                entry.domain(),
+                entry.last_used_at,
+                mac,
+                entry.locked,
+                entry.favorite
             ],
         )?;
 
         Ok(())
     }
 
+    /// Record that an entry's secret was used at `at` (its update time stays)
+    pub fn mark_used(&self, name: &str, at: &str) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        conn.execute(
+            "UPDATE entries SET last_used_at = ?1 WHERE name = ?2",
+            params![at, name],
+        )?;
+        Ok(())
+    }
+
+    /// Set or clear an entry's `ccm lock` flag (its update time stays)
+    pub fn set_locked(&self, name: &str, locked: bool) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE entries SET locked = ?1 WHERE name = ?2",
+            params![locked, name],
+        )?;
+        self.seal_rows(&tx, Some(&[name.to_string()]))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Set or clear an entry's `ccm fav` flag (its update time stays)
+    pub fn set_favorite(&self, name: &str, favorite: bool) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        conn.execute(
+            "UPDATE entries SET favorite = ?1 WHERE name = ?2",
+            params![favorite, name],
+        )?;
+        Ok(())
+    }
+
+    /// Save many new entries with their encrypted secrets in one transaction
+    /// Fails (writing nothing) if any of the names already exists
+    pub fn insert_entries_batch(&self, items: &[(Entry, String)]) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        for (entry, encrypted_value) in items {
+            let exists = tx
+                .prepare_cached("SELECT 1 FROM entries WHERE name = ?1")?
+                .exists(params![entry.name])?;
+            if exists {
+                return Err(CcmError::InvalidArgument(format!(
+                    "Entry '{}' already exists",
+                    entry.name
+                )));
+            }
+
+            self.write_entry(&tx, &entry.name, entry)?;
+            self.write_secret(&tx, &entry.name, encrypted_value)?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Restore entries with their encrypted secrets from a backup in one
+    /// transaction, keeping their timestamps. Entries with the same name are
+    /// overwritten (their previous secret goes to the history); with `replace`
+    /// the entries not in the backup are deleted with their secret and history
+    pub fn restore_entries(&self, items: &[(Entry, String)], replace: bool) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        if replace {
+            let restored: HashSet<&str> = items.iter().map(|(e, _)| e.name.as_str()).collect();
+            let names = tx
+                .prepare("SELECT name FROM entries UNION SELECT name FROM secrets")?
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            for name in names
+                .iter()
+                .filter(|name| !restored.contains(name.as_str()))
+            {
+                tx.execute("DELETE FROM entries WHERE name = ?1", params![name])?;
+                tx.execute("DELETE FROM secrets WHERE name = ?1", params![name])?;
+                tx.execute("DELETE FROM secret_history WHERE name = ?1", params![name])?;
+            }
+        }
+        for (entry, encrypted_value) in items {
+            self.write_entry_row(&tx, &entry.name, entry, true)?;
+            self.write_secret(&tx, &entry.name, encrypted_value)?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Save entries with their encrypted secrets and delete others in one
+    /// transaction (a merged sync pull): all of it is written or none
+    pub fn apply_entries(&self, saved: &[(Entry, String)], deleted: &[String]) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        for (entry, encrypted_value) in saved {
+            self.write_entry(&tx, &entry.name, entry)?;
+            self.write_secret(&tx, &entry.name, encrypted_value)?;
+        }
+        for name in deleted {
+            tx.execute("DELETE FROM entries WHERE name = ?1", params![name])?;
+            tx.execute("DELETE FROM secrets WHERE name = ?1", params![name])?;
+            tx.execute("DELETE FROM secret_history WHERE name = ?1", params![name])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
     /// Delete an entry
     pub fn delete_entry(&self, name: &str) -> Result<bool> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
         let rows_affected = conn.execute("DELETE FROM entries WHERE name = ?1", params![name])?;
 
         Ok(rows_affected > 0)
     }
 
-    /// Get encrypted secret value
-    pub fn get_secret(&self, name: &str) -> Result<Option<String>> {
+    /// Delete an entry together with its secret and history in one
+    /// transaction, so neither is left behind; returns whether anything existed
+    pub fn delete_entry_and_secret(&self, name: &str) -> Result<bool> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
-        let mut stmt = conn.prepare("SELECT encrypted_value FROM secrets WHERE name = ?1")?;
+        let tx = conn.unchecked_transaction()?;
+        let entries = tx.execute("DELETE FROM entries WHERE name = ?1", params![name])?;
+        let secrets = tx.execute("DELETE FROM secrets WHERE name = ?1", params![name])?;
+        tx.execute("DELETE FROM secret_history WHERE name = ?1", params![name])?;
+        tx.commit()?;
 
-        let mut iter = stmt.query_map(params![name], |row| row.get(0))?;
+        Ok(entries + secrets > 0)
+    }
 
-        if let Some(encrypted_value) = iter.next() {
-            Ok(Some(encrypted_value?))
-        } else {
-            Ok(None)
+    /// Get encrypted secret value
+    pub fn get_secret(&self, name: &str) -> Result<Option<String>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let row = conn
+            .query_row(
+                "SELECT encrypted_value, mac FROM secrets WHERE name = ?1",
+                params![name],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()?;
+
+        row.map(|(encrypted_value, mac)| self.checked_secret(name, encrypted_value, mac))
+            .transpose()
+    }
+
+    /// A secret or history ciphertext, refused if its MAC does not match
+    /// (changed or copied from another entry outside ccm)
+    fn checked_secret(
+        &self,
+        name: &str,
+        encrypted_value: String,
+        mac: Option<String>,
+    ) -> Result<String> {
+        if !integrity::secret_mac_valid(&self.mac_key, name, &encrypted_value, mac.as_deref()) {
+            return Err(CcmError::Integrity(format!(
+                "Secret of '{}' was modified outside ccm; run 'ccm db verify'",
+                name
+            )));
         }
+        Ok(encrypted_value)
     }
 
     /// Save encrypted secret value
+    /// A previous value is moved to the secret history first
     pub fn save_secret(&self, name: &str, encrypted_value: &str) -> Result<()> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        self.write_secret(&tx, name, encrypted_value)?;
+        tx.commit()?;
+
+        Ok(())
+    }
 
+    /// Archive the current value (if any, with its MAC) and store the new one
+    fn write_secret(&self, conn: &Connection, name: &str, encrypted_value: &str) -> Result<()> {
         let now = chrono::Utc::now().to_rfc3339();
+        let mac = integrity::secret_mac(&self.mac_key, name, encrypted_value);
 
         conn.execute(
-            "INSERT OR REPLACE INTO secrets (name, encrypted_value, created_at, updated_at)
-             VALUES (?1, ?2, COALESCE((SELECT created_at FROM secrets WHERE name = ?1), ?3), ?4)",
-            params![name, encrypted_value, now, now],
+            "INSERT INTO secret_history
+               (name, version, encrypted_value, created_at, replaced_at, mac)
+             SELECT name,
+                    (SELECT COALESCE(MAX(version), 0) + 1 FROM secret_history WHERE name = ?1),
+                    encrypted_value, updated_at, ?2, mac
+             FROM secrets WHERE name = ?1",
+            params![name, now],
+        )?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO secrets (name, encrypted_value, created_at, updated_at, mac)
+             VALUES (?1, ?2, COALESCE((SELECT created_at FROM secrets WHERE name = ?1), ?3), ?4, ?5)",
+            params![name, encrypted_value, now, now, mac],
         )?;
 
         Ok(())
     }
 
-    /// Delete a secret
+    /// Delete a secret and its history
     pub fn delete_secret(&self, name: &str) -> Result<bool> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
-        let rows_affected = conn.execute("DELETE FROM secrets WHERE name = ?1", params![name])?;
+        let tx = conn.unchecked_transaction()?;
+        let rows_affected = tx.execute("DELETE FROM secrets WHERE name = ?1", params![name])?;
+        tx.execute("DELETE FROM secret_history WHERE name = ?1", params![name])?;
+        tx.commit()?;
 
         Ok(rows_affected > 0)
     }
 
+    /// Get all versions of a secret, oldest first
+    /// The last element is the current value
+    pub fn get_secret_versions(&self, name: &str) -> Result<Vec<SecretVersion>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let mut stmt = conn.prepare(
+            "SELECT version, encrypted_value, created_at, replaced_at, mac FROM secret_history
+             WHERE name = ?1 ORDER BY version",
+        )?;
+        let rows = stmt
+            .query_map(params![name], |row| {
+                Ok((
+                    SecretVersion {
+                        version: row.get(0)?,
+                        encrypted_value: row.get(1)?,
+                        created_at: row.get(2)?,
+                        replaced_at: row.get(3)?,
+                    },
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut versions = Vec::with_capacity(rows.len() + 1);
+        for (mut version, mac) in rows {
+            version.encrypted_value = self.checked_secret(name, version.encrypted_value, mac)?;
+            versions.push(version);
+        }
+
+        let current = conn
+            .query_row(
+                "SELECT encrypted_value, updated_at, mac FROM secrets WHERE name = ?1",
+                params![name],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        if let Some((encrypted_value, created_at, mac)) = current {
+            let version = versions.last().map(|v| v.version + 1).unwrap_or(1);
+            versions.push(SecretVersion {
+                version,
+                encrypted_value: self.checked_secret(name, encrypted_value, mac)?,
+                created_at,
+                replaced_at: None,
+            });
+        }
+
+        Ok(versions)
+    }
+
+    /// Make an earlier version the current secret
+    /// The value being replaced is archived, so a rollback can itself be undone
+    pub fn restore_secret_version(&self, name: &str, version: i64) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let (encrypted_value, mac) = conn
+            .query_row(
+                "SELECT encrypted_value, mac FROM secret_history WHERE name = ?1 AND version = ?2",
+                params![name, version],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| {
+                CcmError::InvalidArgument(format!(
+                    "Version {} of '{}' not found in history",
+                    version, name
+                ))
+            })?;
+        let encrypted_value = self.checked_secret(name, encrypted_value, mac)?;
+
+        let tx = conn.unchecked_transaction()?;
+        self.write_secret(&tx, name, &encrypted_value)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
     /// Get all secret names
     pub fn get_all_secret_names(&self) -> Result<Vec<String>> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
         let mut stmt = conn.prepare("SELECT name FROM secrets")?;
 
@@ -456,20 +1004,30 @@ impl Database {
     where
         T: for<'de> serde::Deserialize<'de>,
     {
+        match self.get_setting_json(key)? {
+            Some(value_str) => {
+                let value = serde_json::from_str(&value_str).map_err(CcmError::Serialization)?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get a setting value as stored (JSON text)
+    pub fn get_setting_json(&self, key: &str) -> Result<Option<String>> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
         let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
 
         let mut iter = stmt.query_map(params![key], |row| row.get::<_, String>(0))?;
 
-        if let Some(value_str) = iter.next() {
-            let value = serde_json::from_str(&value_str?).map_err(CcmError::Serialization)?;
-            Ok(Some(value))
-        } else {
-            Ok(None)
+        match iter.next() {
+            Some(value_str) => Ok(Some(value_str?)),
+            None => Ok(None),
         }
     }
 
@@ -478,12 +1036,17 @@ impl Database {
     where
         T: serde::Serialize,
     {
+        self.save_setting_json(key, &serde_json::to_string(value)?)
+    }
+
+    /// Save a setting value given as JSON text
+    pub fn save_setting_json(&self, key: &str, value_str: &str) -> Result<()> {
         let conn = self
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
-        let value_str = serde_json::to_string(value)?;
         let now = chrono::Utc::now().to_rfc3339();
 
         conn.execute(
@@ -500,6 +1063,7 @@ impl Database {
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
         let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
         let iter = stmt.query_map([], |row| {
@@ -523,14 +1087,1134 @@ impl Database {
             .conn
             .lock()
             .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
 
         let rows_affected = conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
 
         Ok(rows_affected > 0)
     }
-}
 
-/// Get database instance (singleton-like)
-pub fn get_database() -> Result<Database> {
-    Database::new()
+    /// Set and delete several settings in one transaction
+    pub fn apply_settings(&self, set: &[(String, String)], delete: &[String]) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.unchecked_transaction()?;
+        for (key, value) in set {
+            tx.execute(
+                "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                params![key, serde_json::to_string(value)?, now],
+            )?;
+        }
+        for key in delete {
+            tx.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Load the entry name policy from the names.* config keys
+    pub fn get_name_policy(&self) -> Result<NamePolicy> {
+        read_name_policy(|key| self.get_setting::<String>(key))
+    }
+
+    /// Append an event to the audit log
+    pub fn append_audit_event(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_name: Option<&str>,
+        user: Option<&str>,
+    ) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        conn.execute(
+            "INSERT INTO audit_log (timestamp, action, entry_name, user) VALUES (?1, ?2, ?3, ?4)",
+            params![timestamp, action, entry_name, user],
+        )?;
+
+        Ok(())
+    }
+
+    /// Append the same event for several entries in one transaction
+    pub fn append_audit_events(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_names: &[&str],
+        user: Option<&str>,
+    ) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let tx = conn.unchecked_transaction()?;
+        for name in entry_names {
+            tx.prepare_cached(
+                "INSERT INTO audit_log (timestamp, action, entry_name, user) VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![timestamp, action, name, user])?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Get audit events, oldest first, optionally only those at or after `since`
+    /// (an RFC 3339 timestamp in the same format the events are stored with)
+    pub fn get_audit_events(&self, since: Option<&str>) -> Result<Vec<AuditEvent>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, action, entry_name, user FROM audit_log
+             WHERE ?1 IS NULL OR timestamp >= ?1
+             ORDER BY id",
+        )?;
+
+        let rows = stmt.query_map(params![since], |row| {
+            Ok(AuditEvent {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                action: row.get(2)?,
+                entry: row.get(3)?,
+                user: row.get(4)?,
+            })
+        })?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            events.push(row?);
+        }
+
+        Ok(events)
+    }
+
+    /// Flush the WAL into the main database file
+    /// After this the database file alone is a complete copy of the data
+    pub fn checkpoint(&self) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Schema details for diagnostics (no entry data)
+    pub fn schema_info(&self) -> Result<SchemaInfo> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let user_version = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let cipher_version = conn
+            .query_row("PRAGMA cipher_version", [], |row| row.get(0))
+            .optional()?;
+        let mut stmt =
+            conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+        let tables = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(SchemaInfo {
+            user_version,
+            cipher_version,
+            tables,
+        })
+    }
+
+    /// Cheap summary of entries and secrets that changes whenever one is
+    /// added, updated or deleted (reads and settings do not affect it)
+    pub fn entries_fingerprint(&self) -> Result<String> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let fingerprint = conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM entries) || ':' ||
+                    (SELECT COALESCE(MAX(updated_at), '') FROM entries) || ':' ||
+                    (SELECT COUNT(*) FROM secrets) || ':' ||
+                    (SELECT COALESCE(MAX(updated_at), '') FROM secrets) || ':' ||
+                    (SELECT COUNT(*) FROM secret_history)",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(fingerprint)
+    }
+
+    /// Count secrets last changed before `changed_before`, and entries
+    /// created before `used_since` whose secret has not been read since
+    /// (both RFC 3339 timestamps)
+    pub fn hygiene_counts(&self, changed_before: &str, used_since: &str) -> Result<(usize, usize)> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let counts = conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM secrets WHERE updated_at < ?1),
+                    (SELECT COUNT(*) FROM entries e WHERE e.created_at < ?2
+                       AND NOT EXISTS (SELECT 1 FROM audit_log a
+                                       WHERE a.entry_name = e.name
+                                         AND a.action = ?3 AND a.timestamp >= ?2))",
+            params![changed_before, used_since, crate::audit::SECRET_READ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as usize,
+                    row.get::<_, i64>(1)? as usize,
+                ))
+            },
+        )?;
+        Ok(counts)
+    }
+}
+
+/// Database schema details (see `Database::schema_info`)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SchemaInfo {
+    #[serde(rename = "userVersion")]
+    pub user_version: i64,
+    /// SQLCipher version (None for plain SQLite)
+    #[serde(rename = "cipherVersion")]
+    pub cipher_version: Option<String>,
+    pub tables: Vec<String>,
+}
+
+/// One stored value of a secret
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SecretVersion {
+    /// Version number, starting at 1 for the first value
+    pub version: i64,
+    pub encrypted_value: String,
+    /// When this value was stored
+    pub created_at: String,
+    /// When this value was replaced (None for the current value)
+    pub replaced_at: Option<String>,
+}
+
+/// One row of the audit log
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    /// When the event happened (RFC 3339, UTC)
+    pub timestamp: String,
+    /// Dotted action name, e.g. `secret.read`
+    pub action: String,
+    /// Entry the action applied to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+    /// OS user that ran the command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// Filter applied when paging or streaming entries
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct EntryFilter {
+    /// Case-insensitive substring the entry name must contain
+    pub name_contains: Option<String>,
+    /// Glob the entry name must match (`*` and `?`, see `utils::glob`)
+    pub name_glob: Option<String>,
+    /// Tags the entry must all have (case-insensitive)
+    pub tags: Vec<String>,
+    /// Only entries without tags
+    pub untagged: bool,
+    /// Only entries pinned with `ccm fav`
+    pub favorite: bool,
+    /// Only entries in this group or its subgroups (`work` for work/github)
+    pub group: Option<String>,
+    /// Only entries whose url is on this domain or one of its subdomains
+    /// (lowercase, as returned by `Entry::domain`)
+    pub domain: Option<String>,
+    /// `--where` expression the entry must match
+    pub expr: Option<query::Expr>,
+}
+
+impl EntryFilter {
+    /// Build the WHERE clause (empty when the filter matches everything)
+    /// and its positional parameters
+    fn where_clause(&self) -> (String, Vec<Value>) {
+        let mut clauses: Vec<String> = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(pattern) = self.like_pattern() {
+            clauses.push("name LIKE ? ESCAPE '\\'".to_string());
+            values.push(Value::Text(pattern));
+        }
+
+        if let Some(pattern) = &self.name_glob {
+            clauses.push("name GLOB ?".to_string());
+            values.push(Value::Text(escape_glob(pattern)));
+        }
+
+        for tag in &self.tags {
+            clauses.push(
+                "EXISTS (SELECT 1 FROM json_each(entries.tags) WHERE lower(json_each.value) = lower(?))"
+                    .to_string(),
+            );
+            values.push(Value::Text(tag.clone()));
+        }
+
+        if self.untagged {
+            clauses.push("(tags IS NULL OR json_array_length(tags) = 0)".to_string());
+        }
+
+        if self.favorite {
+            clauses.push("favorite = 1".to_string());
+        }
+
+        if let Some(group) = &self.group {
+            clauses.push("name GLOB ?".to_string());
+            values.push(Value::Text(escape_glob(&tree::group_glob(group))));
+        }
+
+        if let Some(domain) = &self.domain {
+            clauses.push("(domain = ? OR domain LIKE ? ESCAPE '\\')".to_string());
+            values.push(Value::Text(domain.clone()));
+            values.push(Value::Text(format!("%.{}", escape_like(domain))));
+        }
+
+        if let Some(expr) = &self.expr {
+            clauses.push(expr.to_sql(&mut values));
+        }
+
+        if clauses.is_empty() {
+            (String::new(), values)
+        } else {
+            (format!(" WHERE {}", clauses.join(" AND ")), values)
+        }
+    }
+
+    /// Build the SQL LIKE pattern for this filter (None matches everything)
+    fn like_pattern(&self) -> Option<String> {
+        self.name_contains
+            .as_ref()
+            .map(|s| format!("%{}%", escape_like(s)))
+    }
+}
+
+/// The name policy from the names.* settings read by `get`
+/// A setting that cannot be read fails instead of falling back to the default
+pub(crate) fn read_name_policy<F>(get: F) -> Result<NamePolicy>
+where
+    F: Fn(&str) -> Result<Option<String>>,
+{
+    let mut values = HashMap::new();
+    for key in crate::utils::NAME_POLICY_KEYS {
+        if let Some(value) = get(key)? {
+            values.insert(*key, value);
+        }
+    }
+    NamePolicy::from_settings(|key| values.get(key).cloned())
+        .map_err(|e| CcmError::InvalidArgument(e.to_string()))
+}
+
+/// Escape LIKE wildcards (for use with `ESCAPE '\'`)
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// A `utils::glob` pattern as an SQLite GLOB one, where `[` opens a
+/// character class
+fn escape_glob(pattern: &str) -> String {
+    pattern.replace('[', "[[]")
+}
+
+/// Whether a table exists
+fn has_table(conn: &Connection, table: &str) -> bool {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |_| Ok(()),
+    )
+    .optional()
+    .ok()
+    .flatten()
+    .is_some()
+}
+
+/// Whether a table has a column
+fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+    conn.prepare(&format!("PRAGMA table_info({})", table))
+        .and_then(|mut stmt| {
+            let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+            names.collect::<rusqlite::Result<Vec<String>>>()
+        })
+        .map(|names| names.iter().any(|n| n == column))
+        .unwrap_or(false)
+}
+
+/// A page of entries returned by `Database::get_entries_page`
+#[derive(Debug, Clone)]
+pub struct EntryPage {
+    pub entries: Vec<Entry>,
+    pub offset: usize,
+    /// Total number of entries matching the filter
+    pub total: usize,
+}
+
+impl EntryPage {
+    /// Whether more entries exist after this page
+    pub fn has_more(&self) -> bool {
+        self.offset + self.entries.len() < self.total
+    }
+}
+
+/// Raw entries row (`ENTRY_COLUMNS`)
+struct EntryRow {
+    name: String,
+    metadata: String,
+    tags: Option<String>,
+    notes: Option<String>,
+    created_at: String,
+    updated_at: String,
+    last_used_at: Option<String>,
+    mac: Option<String>,
+    locked: bool,
+    favorite: bool,
+}
+
+fn read_entry_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<EntryRow> {
+    Ok(EntryRow {
+        name: row.get(0)?,
+        metadata: row.get(1)?,
+        tags: row.get(2)?,
+        notes: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+        last_used_at: row.get(6)?,
+        mac: row.get(7)?,
+        locked: row.get(8)?,
+        favorite: row.get(9)?,
+    })
+}
+
+impl Database {
+    /// Convert a raw entries row into an Entry, refusing a row whose MAC does
+    /// not match (changed outside ccm)
+    fn entry_from_row(&self, row: EntryRow) -> Result<Entry> {
+        let valid = integrity::row_mac_valid(
+            &self.mac_key,
+            &row.name,
+            &row.metadata,
+            row.tags.as_deref(),
+            row.notes.as_deref(),
+            row.locked,
+            row.mac.as_deref(),
+        );
+        if !valid {
+            return Err(CcmError::Integrity(format!(
+                "Entry '{}' was modified outside ccm; run 'ccm db verify'",
+                row.name
+            )));
+        }
+        entry_from_row(row)
+    }
+}
+
+/// Convert a raw entries row into an Entry
+fn entry_from_row(row: EntryRow) -> Result<Entry> {
+    let EntryRow {
+        name,
+        metadata,
+        tags,
+        notes,
+        created_at,
+        updated_at,
+        last_used_at,
+        mac: _,
+        locked,
+        favorite,
+    } = row;
+
+    // Parse metadata as JSON object
+    let metadata_value: serde_json::Value =
+        serde_json::from_str(&metadata).map_err(CcmError::Serialization)?;
+
+    let mut metadata_map = HashMap::new();
+    if let serde_json::Value::Object(map) = metadata_value {
+        for (k, v) in map {
+            if let Some(s) = v.as_str() {
+                metadata_map.insert(k, s.to_string());
+            } else {
+                metadata_map.insert(k, v.to_string());
+            }
+        }
+    }
+
+    let mut entry = Entry::new(name, metadata_map);
+    entry.created_at = Some(created_at);
+    entry.updated_at = Some(updated_at);
+    entry.last_used_at = last_used_at;
+    entry.locked = locked;
+    entry.favorite = favorite;
+    entry.notes = notes;
+
+    if let Some(tags_str) = tags {
+        let tags_vec: Vec<String> =
+            serde_json::from_str(&tags_str).map_err(CcmError::Serialization)?;
+        entry.tags = Some(tags_vec);
+    }
+
+    Ok(entry)
+}
+
+/// Get database instance (singleton-like)
+/// The first successful open in a process runs the deferred initialization
+pub fn get_database() -> Result<Database> {
+    let db = Database::new(db_path())?;
+    crate::core::initialization::on_vault_opened();
+    Ok(db)
+}
+
+#[cfg(test)]
+impl Database {
+    /// Open an unencrypted in-memory database (tests only)
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        let db = Self {
+            conn: Arc::new(Mutex::new(Connection::open_in_memory()?)),
+            path: PathBuf::from(":memory:"),
+            mac_key: integrity::row_mac_key(&[0u8; 32]),
+        };
+
+        {
+            let conn = db
+                .conn
+                .lock()
+                .map_err(|e| CcmError::Unknown(e.to_string()))?;
+            db.init_tables(&conn)?;
+        }
+
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(db: &Database, names: &[&str]) {
+        for name in names {
+            let mut metadata = HashMap::new();
+            metadata.insert("API_KEY".to_string(), "SECRET".to_string());
+            db.save_entry(name, &Entry::new(name.to_string(), metadata))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_database_encrypted_at_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ccm.db");
+        let key = [42u8; 32];
+
+        {
+            let db = Database::open_with_key(path.clone(), &key).unwrap();
+            let mut entry = Entry::new("plaintext-probe".to_string(), HashMap::new());
+            entry.tags = Some(vec!["probe-tag".to_string()]);
+            entry.notes = Some("probe-notes".to_string());
+            db.save_entry("plaintext-probe", &entry).unwrap();
+            db.checkpoint().unwrap();
+        }
+
+        let raw = std::fs::read(&path).unwrap();
+        let contains = |needle: &[u8]| raw.windows(needle.len()).any(|w| w == needle);
+        assert!(!raw.starts_with(b"SQLite format 3"));
+        assert!(!contains(b"plaintext-probe"));
+        assert!(!contains(b"probe-tag"));
+        assert!(!contains(b"probe-notes"));
+
+        assert!(Database::open_with_key(path.clone(), &[7u8; 32]).is_err());
+        let db = Database::open_with_key(path, &key).unwrap();
+        assert!(db.get_entry("plaintext-probe").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_for_each_entry_can_use_the_database() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_entry("a", &Entry::new("a".to_string(), HashMap::new()))
+            .unwrap();
+
+        let mut names = Vec::new();
+        db.for_each_entry(&EntryFilter::default(), |entry| {
+            names.push(db.get_entry(&entry.name)?.unwrap().name);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(names, ["a"]);
+    }
+
+    #[test]
+    fn test_entries_page() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db, &["delta", "alpha", "charlie", "bravo", "echo"]);
+
+        let page = db.get_entries_page(0, 2, &EntryFilter::default()).unwrap();
+        let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha", "bravo"]);
+        assert_eq!(page.total, 5);
+        assert!(page.has_more());
+
+        let last = db.get_entries_page(4, 2, &EntryFilter::default()).unwrap();
+        assert_eq!(last.entries.len(), 1);
+        assert!(!last.has_more());
+    }
+
+    #[test]
+    fn test_entry_filter_escapes_wildcards() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db, &["work_prod", "workxprod", "personal"]);
+
+        let filter = EntryFilter {
+            name_contains: Some("work_".to_string()),
+            ..EntryFilter::default()
+        };
+        let page = db.get_entries_page(0, 10, &filter).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].name, "work_prod");
+
+        let mut streamed = Vec::new();
+        db.for_each_entry(&EntryFilter::default(), |entry| {
+            streamed.push(entry.name);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(streamed, vec!["personal", "work_prod", "workxprod"]);
+        assert_eq!(db.count_entries(&EntryFilter::default()).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_entry_filter_name_glob() {
+        let db = Database::open_in_memory().unwrap();
+        seed(
+            &db,
+            &[
+                "openai-prod",
+                "openai-dev",
+                "my-openai",
+                "test[1]",
+                "Openai-x",
+            ],
+        );
+
+        let names = |pattern: &str| -> Vec<String> {
+            let filter = EntryFilter {
+                name_glob: Some(pattern.to_string()),
+                ..EntryFilter::default()
+            };
+            let mut names = Vec::new();
+            db.for_each_entry(&filter, |entry| {
+                names.push(entry.name);
+                Ok(())
+            })
+            .unwrap();
+            names
+        };
+        assert_eq!(names("openai-*"), ["openai-dev", "openai-prod"]);
+        assert_eq!(
+            names("*openai*"),
+            ["my-openai", "openai-dev", "openai-prod"]
+        );
+        assert_eq!(names("openai-???"), ["openai-dev"]);
+        assert_eq!(names("test[1]"), ["test[1]"]);
+        assert!(names("openai").is_empty());
+    }
+
+    #[test]
+    fn test_entry_filter_group() {
+        let db = Database::open_in_memory().unwrap();
+        seed(
+            &db,
+            &["work", "work/github", "work/anthropic/prod", "workshop/x"],
+        );
+
+        let names = |group: &str| -> Vec<String> {
+            let filter = EntryFilter {
+                group: Some(group.to_string()),
+                ..EntryFilter::default()
+            };
+            let mut names = Vec::new();
+            db.for_each_entry(&filter, |entry| {
+                names.push(entry.name);
+                Ok(())
+            })
+            .unwrap();
+            names
+        };
+        assert_eq!(names("work"), ["work/anthropic/prod", "work/github"]);
+        assert_eq!(names("work/anthropic/"), ["work/anthropic/prod"]);
+        assert!(names("Work").is_empty());
+    }
+
+    #[test]
+    fn test_entry_filter_tags() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db, &["bare"]);
+        for (name, tags) in [
+            ("a", vec!["work", "prod"]),
+            ("b", vec!["Work"]),
+            ("c", vec![]),
+        ] {
+            let mut entry = Entry::new(name.to_string(), HashMap::new());
+            entry.tags = Some(tags.into_iter().map(String::from).collect());
+            db.save_entry(name, &entry).unwrap();
+        }
+
+        let names = |filter: EntryFilter| -> Vec<String> {
+            let mut names = Vec::new();
+            db.for_each_entry(&filter, |entry| {
+                names.push(entry.name);
+                Ok(())
+            })
+            .unwrap();
+            names
+        };
+
+        let work = EntryFilter {
+            tags: vec!["work".to_string()],
+            ..EntryFilter::default()
+        };
+        assert_eq!(names(work), vec!["a", "b"]);
+
+        let work_prod = EntryFilter {
+            tags: vec!["work".to_string(), "prod".to_string()],
+            ..EntryFilter::default()
+        };
+        assert_eq!(db.count_entries(&work_prod).unwrap(), 1);
+
+        let untagged = EntryFilter {
+            untagged: true,
+            ..EntryFilter::default()
+        };
+        assert_eq!(names(untagged), vec!["bare", "c"]);
+    }
+
+    #[test]
+    fn test_entry_filter_domain() {
+        let db = Database::open_in_memory().unwrap();
+        for (name, url) in [
+            ("gh", "https://www.GitHub.com/login"),
+            ("gist", "https://gist.github.com/"),
+            ("fake", "https://notgithub.com"),
+        ] {
+            let mut metadata = HashMap::new();
+            metadata.insert("url".to_string(), url.to_string());
+            db.save_entry(name, &Entry::new(name.to_string(), metadata))
+                .unwrap();
+        }
+
+        let filter = EntryFilter {
+            domain: Some("github.com".to_string()),
+            ..EntryFilter::default()
+        };
+        let page = db.get_entries_page(0, 10, &filter).unwrap();
+        let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["gh", "gist"]);
+    }
+
+    #[test]
+    fn test_migrate_add_domain_column() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE entries (
+                name TEXT PRIMARY KEY,
+                metadata TEXT NOT NULL,
+                tags TEXT,
+                notes TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO entries VALUES ('site', '{\"url\":\"https://www.example.com/x\"}', NULL, NULL, 'now', 'now')",
+            [],
+        )
+        .unwrap();
+
+        let db = Database {
+            conn: Arc::new(Mutex::new(conn)),
+            path: PathBuf::from(":memory:"),
+            mac_key: integrity::row_mac_key(&[0u8; 32]),
+        };
+        db.init_tables(&db.conn.lock().unwrap()).unwrap();
+
+        let filter = EntryFilter {
+            domain: Some("example.com".to_string()),
+            ..EntryFilter::default()
+        };
+        assert_eq!(db.count_entries(&filter).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_migrate_add_secret_mac_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE entries (
+                name TEXT PRIMARY KEY,
+                metadata TEXT NOT NULL,
+                tags TEXT,
+                notes TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE secrets (
+                name TEXT PRIMARY KEY,
+                encrypted_value TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE secret_history (
+                name TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                encrypted_value TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                replaced_at TEXT NOT NULL,
+                PRIMARY KEY (name, version)
+            );
+            INSERT INTO entries VALUES ('api', '{}', NULL, NULL, 'now', 'now');
+            INSERT INTO secrets VALUES ('api', 'v2', 'now', 'now');
+            INSERT INTO secret_history VALUES ('api', 1, 'v1', 'then', 'now');",
+        )
+        .unwrap();
+
+        let db = Database {
+            conn: Arc::new(Mutex::new(conn)),
+            path: PathBuf::from(":memory:"),
+            mac_key: integrity::row_mac_key(&[0u8; 32]),
+        };
+        db.init_tables(&db.conn.lock().unwrap()).unwrap();
+
+        assert!(db.verify_rows().unwrap().is_empty());
+        assert_eq!(db.get_secret("api").unwrap().as_deref(), Some("v2"));
+        db.restore_secret_version("api", 1).unwrap();
+        assert_eq!(db.get_secret("api").unwrap().as_deref(), Some("v1"));
+        assert_eq!(db.get_secret_versions("api").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_migrate_add_last_used_column() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE entries (
+                name TEXT PRIMARY KEY,
+                metadata TEXT NOT NULL,
+                tags TEXT,
+                notes TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                action TEXT NOT NULL,
+                entry_name TEXT,
+                user TEXT
+            );
+            INSERT INTO entries VALUES ('used', '{}', NULL, NULL, 'now', 'now');
+            INSERT INTO entries VALUES ('idle', '{}', NULL, NULL, 'now', 'now');
+            INSERT INTO audit_log (timestamp, action, entry_name)
+              VALUES ('2024-01-01T00:00:00.000Z', 'secret.read', 'used'),
+                     ('2024-03-01T00:00:00.000Z', 'secret.read', 'used'),
+                     ('2024-05-01T00:00:00.000Z', 'entry.update', 'used'),
+                     ('2024-05-01T00:00:00.000Z', 'entry.update', 'idle');",
+        )
+        .unwrap();
+
+        let db = Database {
+            conn: Arc::new(Mutex::new(conn)),
+            path: PathBuf::from(":memory:"),
+            mac_key: integrity::row_mac_key(&[0u8; 32]),
+        };
+        db.init_tables(&db.conn.lock().unwrap()).unwrap();
+
+        let used = db.get_entry("used").unwrap().unwrap();
+        assert_eq!(
+            used.last_used_at.as_deref(),
+            Some("2024-03-01T00:00:00.000Z")
+        );
+        assert_eq!(db.get_entry("idle").unwrap().unwrap().last_used_at, None);
+
+        // Saving an entry keeps its last use
+        db.save_entry("used", &used).unwrap();
+        let saved = db.get_entry("used").unwrap().unwrap();
+        assert_eq!(saved.last_used_at, used.last_used_at);
+    }
+
+    #[test]
+    fn test_apply_settings() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_setting("keep", &"1").unwrap();
+        db.save_setting("drop", &"2").unwrap();
+
+        db.apply_settings(
+            &[
+                ("keep".to_string(), "3".to_string()),
+                ("new".to_string(), "4".to_string()),
+            ],
+            &["drop".to_string()],
+        )
+        .unwrap();
+
+        assert_eq!(
+            db.get_setting::<String>("keep").unwrap().as_deref(),
+            Some("3")
+        );
+        assert_eq!(
+            db.get_setting::<String>("new").unwrap().as_deref(),
+            Some("4")
+        );
+        assert_eq!(db.get_setting::<String>("drop").unwrap(), None);
+    }
+
+    #[test]
+    fn test_get_name_policy() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_setting("names.normalize", &"lowercase").unwrap();
+        let policy = db.get_name_policy().unwrap();
+        assert_eq!(policy.normalize(" My-API "), "my-api");
+
+        // An unreadable setting is an error, not the default policy
+        let setting = "names.allow-slash";
+        db.save_setting_json(setting, "not json").unwrap();
+        assert!(db.get_name_policy().is_err());
+    }
+
+    #[test]
+    fn test_insert_entries_batch() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db, &["taken"]);
+
+        let item = |name: &str| {
+            let mut metadata = HashMap::new();
+            metadata.insert("API_KEY".to_string(), "SECRET".to_string());
+            (
+                Entry::new(name.to_string(), metadata),
+                format!("enc-{}", name),
+            )
+        };
+
+        db.insert_entries_batch(&[item("a"), item("b")]).unwrap();
+        assert_eq!(db.get_secret("b").unwrap().as_deref(), Some("enc-b"));
+
+        // A conflicting name rolls back the whole batch
+        assert!(db
+            .insert_entries_batch(&[item("c"), item("taken")])
+            .is_err());
+        assert!(db.get_entry("c").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_schema_info() {
+        let db = Database::open_in_memory().unwrap();
+        let info = db.schema_info().unwrap();
+        assert!(info.tables.iter().any(|t| t == "entries"));
+        assert!(info.tables.iter().any(|t| t == "secret_history"));
+    }
+
+    #[test]
+    fn test_entries_fingerprint() {
+        let db = Database::open_in_memory().unwrap();
+        let empty = db.entries_fingerprint().unwrap();
+
+        seed(&db, &["a"]);
+        let seeded = db.entries_fingerprint().unwrap();
+        assert_ne!(empty, seeded);
+
+        db.save_setting("theme", &"dark").unwrap();
+        assert_eq!(db.entries_fingerprint().unwrap(), seeded);
+
+        db.delete_entry("a").unwrap();
+        assert_eq!(db.entries_fingerprint().unwrap(), empty);
+    }
+
+    #[test]
+    fn test_restore_entries() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db, &["kept", "restored"]);
+        db.save_secret("kept", "enc-kept").unwrap();
+        db.save_secret("restored", "enc-old").unwrap();
+
+        let mut entry = Entry::new("restored".to_string(), HashMap::new());
+        entry.created_at = Some("2025-01-01T00:00:00+00:00".to_string());
+        entry.updated_at = Some("2025-06-01T00:00:00+00:00".to_string());
+        let items = vec![(entry, "enc-restored".to_string())];
+
+        db.restore_entries(&items, false).unwrap();
+        let restored = db.get_entry("restored").unwrap().unwrap();
+        assert_eq!(
+            restored.created_at.as_deref(),
+            Some("2025-01-01T00:00:00+00:00")
+        );
+        assert_eq!(
+            restored.updated_at.as_deref(),
+            Some("2025-06-01T00:00:00+00:00")
+        );
+        assert_eq!(
+            db.get_secret("restored").unwrap().as_deref(),
+            Some("enc-restored")
+        );
+        assert!(db.get_entry("kept").unwrap().is_some());
+        assert_eq!(db.get_secret_versions("restored").unwrap().len(), 2);
+
+        // --replace deletes what is not in the backup; restored entries keep
+        // their history
+        db.restore_entries(&items, true).unwrap();
+        assert!(db.get_entry("kept").unwrap().is_none());
+        assert!(db.get_secret("kept").unwrap().is_none());
+        assert!(db.get_secret_versions("kept").unwrap().is_empty());
+        assert_eq!(db.get_secret_versions("restored").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_apply_entries() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db, &["kept", "gone"]);
+        db.save_secret("kept", "k1").unwrap();
+        db.save_secret("gone", "g1").unwrap();
+
+        let new = Entry::new("new".to_string(), HashMap::new());
+        let kept = db.get_entry("kept").unwrap().unwrap();
+        db.apply_entries(
+            &[(new, "n1".to_string()), (kept, "k2".to_string())],
+            &["gone".to_string()],
+        )
+        .unwrap();
+        assert_eq!(db.get_secret("new").unwrap().as_deref(), Some("n1"));
+        assert_eq!(db.get_secret_versions("kept").unwrap().len(), 2);
+        assert!(db.get_entry("gone").unwrap().is_none());
+        assert!(db.get_secret_versions("gone").unwrap().is_empty());
+
+        // A failure part way writes nothing
+        db.conn
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "CREATE TRIGGER refuse BEFORE INSERT ON secrets WHEN NEW.name = 'bad'
+                 BEGIN SELECT RAISE(ABORT, 'refused'); END;",
+            )
+            .unwrap();
+        let items = [
+            (
+                Entry::new("ok".to_string(), HashMap::new()),
+                "o".to_string(),
+            ),
+            (
+                Entry::new("bad".to_string(), HashMap::new()),
+                "b".to_string(),
+            ),
+        ];
+        assert!(db.apply_entries(&items, &["kept".to_string()]).is_err());
+        assert!(db.get_entry("ok").unwrap().is_none());
+        assert!(db.get_entry("kept").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_hygiene_counts() {
+        let db = Database::open_in_memory().unwrap();
+        seed(&db, &["old", "fresh"]);
+        db.save_secret("old", "enc").unwrap();
+        db.save_secret("fresh", "enc").unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE secrets SET updated_at = '2025-01-01T00:00:00+00:00' WHERE name = 'old'",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "UPDATE entries SET created_at = '2025-01-01T00:00:00+00:00'",
+                [],
+            )
+            .unwrap();
+        }
+        db.append_audit_event(
+            "2026-02-01T00:00:00.000Z",
+            "secret.read",
+            Some("fresh"),
+            None,
+        )
+        .unwrap();
+        db.append_audit_event("2025-06-01T00:00:00.000Z", "secret.read", Some("old"), None)
+            .unwrap();
+
+        let counts = db
+            .hygiene_counts("2025-06-01T00:00:00+00:00", "2026-01-01T00:00:00.000Z")
+            .unwrap();
+        assert_eq!(counts, (1, 1));
+    }
+
+    #[test]
+    fn test_audit_events_since() {
+        let db = Database::open_in_memory().unwrap();
+        db.append_audit_event("2026-01-01T00:00:00.000Z", "entry.create", Some("a"), None)
+            .unwrap();
+        db.append_audit_event(
+            "2026-02-01T00:00:00.000Z",
+            "secret.read",
+            Some("a"),
+            Some("bob"),
+        )
+        .unwrap();
+
+        assert_eq!(db.get_audit_events(None).unwrap().len(), 2);
+
+        let recent = db
+            .get_audit_events(Some("2026-01-15T00:00:00.000Z"))
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].action, "secret.read");
+        assert_eq!(recent[0].user.as_deref(), Some("bob"));
+    }
+
+    #[test]
+    fn test_secret_history_and_restore() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_secret("api", "v1").unwrap();
+        db.save_secret("api", "v2").unwrap();
+        db.save_secret("api", "v3").unwrap();
+
+        let versions = db.get_secret_versions("api").unwrap();
+        let values: Vec<&str> = versions
+            .iter()
+            .map(|v| v.encrypted_value.as_str())
+            .collect();
+        assert_eq!(values, vec!["v1", "v2", "v3"]);
+        assert_eq!(versions[2].version, 3);
+        assert!(versions[2].replaced_at.is_none());
+        assert!(versions[0].replaced_at.is_some());
+
+        db.restore_secret_version("api", 1).unwrap();
+        assert_eq!(db.get_secret("api").unwrap().as_deref(), Some("v1"));
+        assert_eq!(db.get_secret_versions("api").unwrap().len(), 4);
+        assert!(db.restore_secret_version("api", 9).is_err());
+
+        db.delete_secret("api").unwrap();
+        assert!(db.get_secret_versions("api").unwrap().is_empty());
+    }
 }
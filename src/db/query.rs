@@ -0,0 +1,479 @@
+// `ccm list --where` expressions
+//
+// A small filter language compiled to SQL predicates on the entries table:
+//
+//   tag=prod && updated>2024-01-01 && env~ANTHROPIC
+//
+// A condition is `field op value`. Lowercase fields are built in (name, tag,
+// env, notes, domain, created, updated); any other field is an entry
+// variable, e.g. `OPENAI_BASE_URL~relay`. Operators are `=`, `!=`, `~`
+// (contains, case-insensitive), `!~` and, for dates, `>`, `>=`, `<`, `<=`.
+// Combine conditions with `&&` and `||` (`&&` binds tighter), group them
+// with parentheses, and quote values containing spaces or operators.
+
+use crate::utils::{CcmError, Result};
+use chrono::Utc;
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+
+/// A parsed `--where` expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cond(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub field: Field,
+    pub op: Op,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Field {
+    Name,
+    /// One of the entry's tags
+    Tag,
+    /// Name of one of the entry's variables
+    Env,
+    Notes,
+    Domain,
+    Created,
+    Updated,
+    /// Value of the named variable
+    Var(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Op {
+    Eq,
+    Ne,
+    Contains,
+    NotContains,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Contains => "~",
+            Op::NotContains => "!~",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        matches!(self, Op::Gt | Op::Ge | Op::Lt | Op::Le)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Op(Op),
+    Word(String),
+}
+
+fn invalid(expr: &str, reason: impl std::fmt::Display) -> CcmError {
+    CcmError::InvalidArgument(format!("Invalid --where expression '{}': {}", expr, reason))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('!', Some('~')) => (Token::Op(Op::NotContains), 2),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('=', _) => (Token::Op(Op::Eq), 1),
+            ('~', _) => (Token::Op(Op::Contains), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('"' | '\'', _) => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&q| q == c)
+                    .ok_or_else(|| invalid(input, "unterminated quote"))?;
+                let word: String = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Word(word), end + 2)
+            }
+            ('&' | '|' | '!', _) => {
+                return Err(invalid(input, format!("unexpected '{}'", c)));
+            }
+            _ => {
+                let len = chars[i..]
+                    .iter()
+                    .position(|&c| c.is_whitespace() || "()&|!=~<>\"'".contains(c))
+                    .unwrap_or(chars.len() - i);
+                (Token::Word(chars[i..i + len].iter().collect()), len)
+            }
+        };
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.primary()?));
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(invalid(self.input, "missing ')'")),
+                }
+            }
+            Some(Token::Word(field)) => {
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    _ => {
+                        return Err(invalid(
+                            self.input,
+                            format!("expected an operator after '{}'", field),
+                        ))
+                    }
+                };
+                let value = match self.next() {
+                    Some(Token::Word(value)) => value,
+                    _ => {
+                        return Err(invalid(
+                            self.input,
+                            format!("expected a value after '{}{}'", field, op.symbol()),
+                        ))
+                    }
+                };
+                Ok(Expr::Cond(condition(self.input, &field, op, value)?))
+            }
+            _ => Err(invalid(self.input, "expected a condition such as tag=prod")),
+        }
+    }
+}
+
+fn condition(input: &str, field: &str, op: Op, value: String) -> Result<Condition> {
+    let field = match field {
+        "name" => Field::Name,
+        "tag" | "tags" => Field::Tag,
+        "env" | "var" => Field::Env,
+        "notes" => Field::Notes,
+        "domain" => Field::Domain,
+        "created" => Field::Created,
+        "updated" => Field::Updated,
+        _ => Field::Var(field.to_string()),
+    };
+
+    let is_date = matches!(field, Field::Created | Field::Updated);
+    if is_date && matches!(op, Op::Contains | Op::NotContains) {
+        return Err(invalid(input, "dates support =, !=, >, >=, < and <="));
+    }
+    if !is_date && op.is_ordering() {
+        return Err(invalid(
+            input,
+            format!("'{}' only applies to created and updated", op.symbol()),
+        ));
+    }
+    if is_date {
+        crate::audit::parse_since(&value, Utc::now()).map_err(|_| {
+            invalid(
+                input,
+                format!("'{}' is not a date (e.g. 2024-01-31 or 30d)", value),
+            )
+        })?;
+    }
+
+    Ok(Condition { field, op, value })
+}
+
+/// Parse a `--where` expression
+pub fn parse(input: &str) -> Result<Expr> {
+    let mut parser = Parser {
+        input,
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+    let expr = parser.or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(invalid(input, "unexpected text after the expression"));
+    }
+    Ok(expr)
+}
+
+fn like_contains(value: &str) -> Value {
+    Value::Text(format!("%{}%", super::escape_like(value)))
+}
+
+impl Expr {
+    /// SQL predicate on the entries table, appending its parameters
+    pub fn to_sql(&self, values: &mut Vec<Value>) -> String {
+        match self {
+            Expr::And(a, b) => format!("({} AND {})", a.to_sql(values), b.to_sql(values)),
+            Expr::Or(a, b) => format!("({} OR {})", a.to_sql(values), b.to_sql(values)),
+            Expr::Cond(cond) => cond.to_sql(values),
+        }
+    }
+}
+
+impl Condition {
+    fn to_sql(&self, values: &mut Vec<Value>) -> String {
+        let text = Value::Text(self.value.clone());
+        let positive = match (&self.field, self.op) {
+            (Field::Created | Field::Updated, op) => {
+                let column = if self.field == Field::Created {
+                    "created_at"
+                } else {
+                    "updated_at"
+                };
+                // Validated when parsing
+                let time = crate::audit::parse_since(&self.value, Utc::now())
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default();
+                return match op {
+                    Op::Eq | Op::Ne => {
+                        values.push(Value::Text(time[..10].to_string()));
+                        let cmp = if op == Op::Eq { "=" } else { "!=" };
+                        format!("substr({}, 1, 10) {} ?", column, cmp)
+                    }
+                    _ => {
+                        values.push(Value::Text(time));
+                        format!("{} {} ?", column, op.symbol())
+                    }
+                };
+            }
+            (Field::Name, Op::Eq | Op::Ne) => {
+                values.push(text);
+                "name = ?".to_string()
+            }
+            (Field::Notes, Op::Eq | Op::Ne) => {
+                values.push(text);
+                "COALESCE(notes, '') = ?".to_string()
+            }
+            (Field::Domain, Op::Eq | Op::Ne) => {
+                values.push(Value::Text(self.value.to_lowercase()));
+                "COALESCE(domain, '') = ?".to_string()
+            }
+            (Field::Name, _) => {
+                values.push(like_contains(&self.value));
+                "name LIKE ? ESCAPE '\\'".to_string()
+            }
+            (Field::Notes, _) => {
+                values.push(like_contains(&self.value));
+                "COALESCE(notes, '') LIKE ? ESCAPE '\\'".to_string()
+            }
+            (Field::Domain, _) => {
+                values.push(like_contains(&self.value));
+                "COALESCE(domain, '') LIKE ? ESCAPE '\\'".to_string()
+            }
+            (Field::Tag, Op::Eq | Op::Ne) => {
+                values.push(text);
+                "EXISTS (SELECT 1 FROM json_each(entries.tags) WHERE lower(json_each.value) = lower(?))"
+                    .to_string()
+            }
+            (Field::Tag, _) => {
+                values.push(like_contains(&self.value));
+                "EXISTS (SELECT 1 FROM json_each(entries.tags) WHERE json_each.value LIKE ? ESCAPE '\\')"
+                    .to_string()
+            }
+            (Field::Env, Op::Eq | Op::Ne) => {
+                values.push(text);
+                "EXISTS (SELECT 1 FROM json_each(entries.metadata) WHERE json_each.key = ?)"
+                    .to_string()
+            }
+            (Field::Env, _) => {
+                values.push(like_contains(&self.value));
+                "EXISTS (SELECT 1 FROM json_each(entries.metadata) WHERE json_each.key LIKE ? ESCAPE '\\')"
+                    .to_string()
+            }
+            (Field::Var(var), op) => {
+                values.push(Value::Text(var.clone()));
+                let cmp = if matches!(op, Op::Eq | Op::Ne) {
+                    values.push(text);
+                    "json_each.value = ?"
+                } else {
+                    values.push(like_contains(&self.value));
+                    "json_each.value LIKE ? ESCAPE '\\'"
+                };
+                format!(
+                    "EXISTS (SELECT 1 FROM json_each(entries.metadata) WHERE json_each.key = ? AND {})",
+                    cmp
+                )
+            }
+        };
+
+        if matches!(self.op, Op::Ne | Op::NotContains) {
+            format!("NOT ({})", positive)
+        } else {
+            positive
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Database, EntryFilter};
+    use crate::types::Entry;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_precedence_and_errors() {
+        let expr = parse("tag=prod && env~ANTHROPIC || name='my key'").unwrap();
+        match expr {
+            Expr::Or(left, right) => {
+                assert!(matches!(*left, Expr::And(_, _)));
+                assert_eq!(
+                    *right,
+                    Expr::Cond(Condition {
+                        field: Field::Name,
+                        op: Op::Eq,
+                        value: "my key".to_string()
+                    })
+                );
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert!(parse("updated>=2024-01-01").is_ok());
+        assert!(parse("(tag=a || tag=b) && OPENAI_BASE_URL!~relay").is_ok());
+        assert!(parse("tag").is_err());
+        assert!(parse("tag=").is_err());
+        assert!(parse("name>abc").is_err());
+        assert!(parse("updated~2024").is_err());
+        assert!(parse("updated>yesterday").is_err());
+        assert!(parse("(tag=a").is_err());
+        assert!(parse("tag=a & tag=b").is_err());
+        assert!(parse("tag=a tag=b").is_err());
+    }
+
+    #[test]
+    fn test_where_filters_entries() {
+        let db = Database::open_in_memory().unwrap();
+        let make = |name: &str, tags: &[&str], vars: &[(&str, &str)], updated: &str| {
+            let metadata: HashMap<String, String> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let mut entry = Entry::new(name.to_string(), metadata);
+            entry.tags = Some(tags.iter().map(|t| t.to_string()).collect());
+            entry.created_at = Some("2023-06-01T00:00:00+00:00".to_string());
+            entry.updated_at = Some(updated.to_string());
+            (entry, "encrypted".to_string())
+        };
+        db.restore_entries(
+            &[
+                make(
+                    "claude-prod",
+                    &["prod"],
+                    &[("ANTHROPIC_API_KEY", "SECRET")],
+                    "2024-03-01T10:00:00+00:00",
+                ),
+                make(
+                    "claude-old",
+                    &["prod"],
+                    &[("ANTHROPIC_API_KEY", "SECRET")],
+                    "2023-12-01T10:00:00+00:00",
+                ),
+                make(
+                    "openai-relay",
+                    &["dev"],
+                    &[
+                        ("OPENAI_API_KEY", "SECRET"),
+                        ("OPENAI_BASE_URL", "https://relay.example.com"),
+                    ],
+                    "2024-05-01T10:00:00+00:00",
+                ),
+            ],
+            false,
+        )
+        .unwrap();
+
+        let names = |expr: &str| -> Vec<String> {
+            let filter = EntryFilter {
+                expr: Some(parse(expr).unwrap()),
+                ..EntryFilter::default()
+            };
+            let mut names = Vec::new();
+            db.for_each_entry(&filter, |entry| {
+                names.push(entry.name);
+                Ok(())
+            })
+            .unwrap();
+            names
+        };
+
+        assert_eq!(
+            names("tag=prod && updated>2024-01-01 && env~ANTHROPIC"),
+            vec!["claude-prod"]
+        );
+        assert_eq!(names("tag!=prod"), vec!["openai-relay"]);
+        assert_eq!(names("OPENAI_BASE_URL~RELAY"), vec!["openai-relay"]);
+        assert_eq!(names("updated=2023-12-01"), vec!["claude-old"]);
+        assert_eq!(
+            names("name~claude && (updated<2024-01-01 || env=OPENAI_API_KEY)"),
+            vec!["claude-old"]
+        );
+        assert_eq!(names("env!~KEY").len(), 0);
+    }
+}
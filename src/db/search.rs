@@ -0,0 +1,416 @@
+// Full-text search over entries (`ccm search`)
+//
+// `entries_fts` is an FTS5 table with one row per entry: its name, tags,
+// notes, variable names and variable values. Triggers on `entries` keep it
+// in sync on every write, including `INSERT OR REPLACE` (which deletes the
+// old row without firing delete triggers, hence rows are keyed by name).
+//
+// Queries are plain text: every word matches as a prefix (`anth` finds
+// anthropic), "quoted words" match as a phrase, and all of them must match.
+// Results are ranked with BM25, a hit in the name counting most.
+//
+// `EntryMatch` holds the filters SQL cannot express (`--regex`, `--field`);
+// they are applied to the results on the client.
+
+use crate::db::{has_table, read_entry_row, Database, EntryFilter, ENTRY_COLUMNS};
+use crate::types::Entry;
+use crate::utils::timings::{self, Phase};
+use crate::utils::{CcmError, Result};
+use regex::{Regex, RegexBuilder};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+
+/// BM25 weights of the columns: name, tags, notes, keys, values
+const WEIGHTS: &str = "10.0, 5.0, 1.0, 2.0, 1.0";
+
+/// The indexed text of an entries row (`prefix` is `new` or `old`)
+fn indexed_values(prefix: &str) -> String {
+    format!(
+        "{p}.name,
+         (SELECT group_concat(value, ' ') FROM json_each({p}.tags)),
+         {p}.notes,
+         (SELECT group_concat(key, ' ') FROM json_each({p}.metadata)),
+         (SELECT group_concat(value, ' ') FROM json_each({p}.metadata))",
+        p = prefix
+    )
+}
+
+/// Create the index, its triggers and fill it from the entries
+pub(super) fn create_index(conn: &Connection) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(&format!(
+        "CREATE VIRTUAL TABLE entries_fts USING fts5(
+             name, tags, notes, keys, vals,
+             tokenize = 'unicode61 remove_diacritics 2',
+             prefix = '2 3'
+         );
+
+         CREATE TRIGGER entries_fts_insert AFTER INSERT ON entries BEGIN
+             DELETE FROM entries_fts WHERE name = new.name;
+             INSERT INTO entries_fts (name, tags, notes, keys, vals) VALUES ({new});
+         END;
+
+         CREATE TRIGGER entries_fts_update AFTER UPDATE OF name, metadata, tags, notes ON entries BEGIN
+             DELETE FROM entries_fts WHERE name = old.name;
+             INSERT INTO entries_fts (name, tags, notes, keys, vals) VALUES ({new});
+         END;
+
+         CREATE TRIGGER entries_fts_delete AFTER DELETE ON entries BEGIN
+             DELETE FROM entries_fts WHERE name = old.name;
+         END;
+
+         INSERT INTO entries_fts (name, tags, notes, keys, vals)
+             SELECT {entries} FROM entries;",
+        new = indexed_values("new"),
+        entries = indexed_values("entries"),
+    ))?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Whether the index exists (it is created with the schema)
+pub(super) fn has_index(conn: &Connection) -> bool {
+    has_table(conn, "entries_fts")
+}
+
+/// The FTS5 query for what the user typed (None when there is nothing to
+/// search for)
+pub fn fts_query(input: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(quoted) = rest.strip_prefix('"') {
+            // A phrase, up to the closing quote (or the end)
+            let (phrase, after) = quoted.split_once('"').unwrap_or((quoted, ""));
+            if !phrase.trim().is_empty() {
+                terms.push(format!("\"{}\"", phrase.trim()));
+            }
+            rest = after;
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(rest.len());
+            let word = rest[..end].trim_end_matches('*');
+            if word.chars().any(char::is_alphanumeric) {
+                terms.push(format!("\"{}\"*", word));
+            }
+            rest = &rest[end..];
+        }
+    }
+    (!terms.is_empty()).then(|| terms.join(" AND "))
+}
+
+/// A case-insensitive regex, or a plain substring when `regex` is false
+fn pattern(text: &str, regex: bool) -> Result<Regex> {
+    let source = if regex {
+        text.to_string()
+    } else {
+        regex::escape(text)
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| CcmError::InvalidArgument(format!("Invalid regex '{}': {}", text, e)))
+}
+
+/// Whether variable `var` is the one `key` names: the same name, or one
+/// ending in `_KEY` (BASE_URL names OPENAI_BASE_URL), ignoring case
+fn key_matches(key: &str, var: &str) -> bool {
+    let (key, var) = (key.to_uppercase(), var.to_uppercase());
+    var == key || var.ends_with(&format!("_{}", key))
+}
+
+/// A `--field KEY[=VALUE]` condition
+#[derive(Debug)]
+pub struct FieldCondition {
+    key: String,
+    value: Option<Regex>,
+}
+
+impl FieldCondition {
+    /// Parse `KEY` or `KEY=VALUE`; VALUE is a substring, or a regex when
+    /// `regex` is set
+    pub fn parse(spec: &str, regex: bool) -> Result<Self> {
+        let (key, value) = match spec.split_once('=') {
+            Some((key, value)) => (key, Some(pattern(value, regex)?)),
+            None => (spec, None),
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(CcmError::InvalidArgument(format!(
+                "Invalid --field '{}': expected KEY or KEY=VALUE",
+                spec
+            )));
+        }
+        Ok(Self {
+            key: key.to_string(),
+            value,
+        })
+    }
+
+    /// Whether the entry has the variable, with a matching value if one was
+    /// given (secrets are never compared)
+    pub fn matches(&self, entry: &Entry) -> bool {
+        entry.metadata.iter().any(|(var, value)| {
+            key_matches(&self.key, var)
+                && match &self.value {
+                    None => true,
+                    Some(re) => value != "SECRET" && re.is_match(value),
+                }
+        })
+    }
+}
+
+/// Filters `ccm search` applies to entries after fetching them
+#[derive(Debug, Default)]
+pub struct EntryMatch {
+    /// Must match the name, a tag, the notes, a variable name or a value
+    pub regex: Option<Regex>,
+    /// Must all hold
+    pub fields: Vec<FieldCondition>,
+}
+
+impl EntryMatch {
+    /// `query` as a regex (when given) and the `--field` conditions
+    pub fn new(query: Option<&str>, fields: &[String], regex: bool) -> Result<Self> {
+        Ok(Self {
+            regex: query.map(|q| pattern(q, true)).transpose()?,
+            fields: fields
+                .iter()
+                .map(|spec| FieldCondition::parse(spec, regex))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regex.is_none() && self.fields.is_empty()
+    }
+
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let text_matches = self.regex.as_ref().is_none_or(|re| {
+            re.is_match(&entry.name)
+                || entry.tags.iter().flatten().any(|t| re.is_match(t))
+                || entry.notes.as_deref().is_some_and(|n| re.is_match(n))
+                || entry
+                    .metadata
+                    .iter()
+                    .any(|(k, v)| re.is_match(k) || (v != "SECRET" && re.is_match(v)))
+        });
+        text_matches && self.fields.iter().all(|f| f.matches(entry))
+    }
+}
+
+impl Database {
+    /// Entries matching a search query and a filter, best match first
+    /// An empty query returns every entry matching the filter, by name
+    pub fn search_entries(&self, query: &str, filter: &EntryFilter) -> Result<Vec<Entry>> {
+        let Some(fts_query) = fts_query(query) else {
+            let mut entries = Vec::new();
+            self.for_each_entry(filter, |entry| {
+                entries.push(entry);
+                Ok(())
+            })?;
+            return Ok(entries);
+        };
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+        let _span = timings::span(Phase::Database);
+
+        let (where_sql, filter_values) = filter.where_clause();
+        let mut values = vec![Value::Text(fts_query)];
+        values.extend(filter_values);
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM entries
+             JOIN (SELECT name AS hit, bm25(entries_fts, {}) AS score
+                   FROM entries_fts WHERE entries_fts MATCH ?) ON hit = entries.name{}
+             ORDER BY score, entries.name",
+            ENTRY_COLUMNS, WEIGHTS, where_sql
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values.iter()), read_entry_row)?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(self.entry_from_row(row?)?);
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn entry(name: &str, vars: &[(&str, &str)], tags: &[&str], notes: Option<&str>) -> Entry {
+        let metadata: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut entry = Entry::new(name.to_string(), metadata);
+        if !tags.is_empty() {
+            entry.tags = Some(tags.iter().map(|t| t.to_string()).collect());
+        }
+        entry.notes = notes.map(str::to_string);
+        entry
+    }
+
+    fn names(db: &Database, query: &str) -> Vec<String> {
+        db.search_entries(query, &EntryFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("anth").as_deref(), Some("\"anth\"*"));
+        assert_eq!(
+            fts_query("  claude  \"base url\" prod*").as_deref(),
+            Some("\"claude\"* AND \"base url\" AND \"prod\"*")
+        );
+        assert_eq!(
+            fts_query("\"unterminated phrase").as_deref(),
+            Some("\"unterminated phrase\"")
+        );
+        assert_eq!(fts_query("sk-ant").as_deref(), Some("\"sk-ant\"*"));
+        assert_eq!(fts_query(""), None);
+        assert_eq!(fts_query("  \"\" * -- "), None);
+    }
+
+    #[test]
+    fn test_search_entries() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_entry(
+            "claude",
+            &entry(
+                "claude",
+                &[
+                    ("ANTHROPIC_API_KEY", "SECRET"),
+                    ("ANTHROPIC_BASE_URL", "https://api.anthropic.com"),
+                ],
+                &["work", "ai"],
+                Some("Main account for the team"),
+            ),
+        )
+        .unwrap();
+        db.save_entry(
+            "openai",
+            &entry(
+                "openai",
+                &[("OPENAI_API_KEY", "SECRET")],
+                &["ai"],
+                Some("claude fallback"),
+            ),
+        )
+        .unwrap();
+        db.save_entry(
+            "db-prod",
+            &entry("db-prod", &[("DB_PASSWORD", "SECRET")], &["prod"], None),
+        )
+        .unwrap();
+
+        // Prefixes over names, tags, notes and variables; a name hit ranks first
+        assert_eq!(names(&db, "clau"), ["claude", "openai"]);
+        assert_eq!(names(&db, "anthropic"), ["claude"]);
+        assert_eq!(names(&db, "OPENAI_API"), ["openai"]);
+        let mut tagged = names(&db, "ai");
+        tagged.sort();
+        assert_eq!(tagged, ["claude", "openai"]);
+        assert_eq!(names(&db, "prod"), ["db-prod"]);
+        // Every word must match; phrases match in order
+        assert_eq!(names(&db, "ai fallback"), ["openai"]);
+        assert_eq!(names(&db, "\"main account\""), ["claude"]);
+        assert!(names(&db, "\"account main\"").is_empty());
+
+        // The index follows updates, renames through replace, and deletes
+        let mut updated = db.get_entry("db-prod").unwrap().unwrap();
+        updated.notes = Some("postgres primary".to_string());
+        db.save_entry("db-prod", &updated).unwrap();
+        assert_eq!(names(&db, "postgres"), ["db-prod"]);
+        assert_eq!(names(&db, "prod"), ["db-prod"]);
+        db.delete_entry("db-prod").unwrap();
+        assert!(names(&db, "postgres").is_empty());
+
+        // Combined with a filter; an empty query lists the filtered entries
+        let filter = EntryFilter {
+            tags: vec!["work".to_string()],
+            ..EntryFilter::default()
+        };
+        let found = db.search_entries("ai", &filter).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(db.search_entries("", &filter).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_entry_match() {
+        let relay = entry(
+            "relay",
+            &[
+                ("OPENAI_API_KEY", "SECRET"),
+                ("OPENAI_BASE_URL", "https://openrouter.ai/api/v1"),
+            ],
+            &["Work"],
+            Some("team relay"),
+        );
+        let matches = |query: Option<&str>, fields: &[&str], regex: bool| {
+            let fields: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+            EntryMatch::new(query, &fields, regex)
+                .unwrap()
+                .matches(&relay)
+        };
+
+        // Regex over name, tags, notes, variable names and plain values
+        assert!(matches(Some("^rel"), &[], true));
+        assert!(matches(Some("^work$"), &[], true));
+        assert!(matches(Some("team\\s+relay"), &[], true));
+        assert!(matches(Some("openrouter\\.ai"), &[], true));
+        assert!(matches(Some("_API_KEY$"), &[], true));
+        assert!(!matches(Some("^secret$"), &[], true));
+
+        // Fields: whole or trailing variable names, values as substrings or
+        // regexes; secrets are never compared
+        assert!(matches(None, &["BASE_URL"], false));
+        assert!(matches(None, &["openai_base_url=OpenRouter"], false));
+        assert!(matches(None, &["BASE_URL=.*openrouter.*"], true));
+        assert!(!matches(None, &["BASE_URL=.*openrouter.*"], false));
+        assert!(matches(None, &["url"], false));
+        assert!(!matches(None, &["ASE_URL"], false));
+        assert!(!matches(None, &["API_KEY=SECRET"], false));
+        assert!(!matches(None, &["BASE_URL", "MODEL"], false));
+
+        assert!(EntryMatch::new(Some("(unclosed"), &[], true).is_err());
+        assert!(FieldCondition::parse("=value", false).is_err());
+    }
+
+    #[test]
+    fn test_index_built_for_existing_entries() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_entry(
+            "legacy",
+            &entry("legacy", &[("TOKEN", "SECRET")], &[], None),
+        )
+        .unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute_batch(
+                "DROP TRIGGER entries_fts_insert;
+                 DROP TRIGGER entries_fts_update;
+                 DROP TRIGGER entries_fts_delete;
+                 DROP TABLE entries_fts;",
+            )
+            .unwrap();
+            assert!(!has_index(&conn));
+            db.init_tables(&conn).unwrap();
+        }
+        assert_eq!(names(&db, "legacy"), ["legacy"]);
+    }
+}
@@ -0,0 +1,226 @@
+// Local database snapshots (`~/.ccm/backups/`, next to the database)
+//
+// A snapshot is a plain copy of the SQLCipher database file, so it stays
+// encrypted with the master key. Snapshots are named by their UTC time and
+// rotated so only the newest `backup.keep` remain.
+
+use crate::db::{db_path, Database};
+use crate::utils::files::{create_private_dir, write_private_file};
+use crate::utils::{CcmError, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Setting: when to snapshot automatically (off, change, daily)
+pub const AUTO_BACKUP_KEY: &str = "backup.auto";
+/// Setting: how many snapshots to keep
+pub const BACKUP_KEEP_KEY: &str = "backup.keep";
+/// Internal setting: entries fingerprint at the last snapshot
+const FINGERPRINT_KEY: &str = "__backup_fingerprint__";
+
+/// Snapshots kept when `backup.keep` is not set
+pub const DEFAULT_KEEP: usize = 10;
+
+const PREFIX: &str = "ccm-";
+const SUFFIX: &str = ".db";
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// When automatic snapshots are taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoBackup {
+    Off,
+    /// After any command that changed entries or secrets
+    OnChange,
+    /// After a change, at most once per day
+    Daily,
+}
+
+impl AutoBackup {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" | "false" | "no" => Some(AutoBackup::Off),
+            "change" | "on" | "true" | "yes" => Some(AutoBackup::OnChange),
+            "daily" => Some(AutoBackup::Daily),
+            _ => None,
+        }
+    }
+}
+
+/// One snapshot file
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+/// Directory holding the snapshots (`backups/` next to the database)
+pub fn backups_dir() -> PathBuf {
+    let db_file = db_path();
+    db_file
+        .parent()
+        .map(|dir| dir.join("backups"))
+        .unwrap_or_else(|| PathBuf::from("backups"))
+}
+
+/// Parse the creation time out of a snapshot file name
+fn snapshot_time(file_name: &str) -> Option<DateTime<Utc>> {
+    let stamp = file_name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, TIME_FORMAT)
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+/// Snapshots in `dir`, newest first
+pub fn list_snapshots(dir: &Path) -> Result<Vec<Snapshot>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for item in std::fs::read_dir(dir)? {
+        let item = item?;
+        let name = item.file_name().to_string_lossy().into_owned();
+        if let Some(created_at) = snapshot_time(&name) {
+            snapshots.push(Snapshot {
+                path: item.path(),
+                created_at,
+                size: item.metadata()?.len(),
+            });
+        }
+    }
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+
+    Ok(snapshots)
+}
+
+/// Copy the database file into `dir` as a new snapshot
+pub fn write_snapshot(dir: &Path, db_file: &Path, now: DateTime<Utc>) -> Result<Snapshot> {
+    create_private_dir(dir)?;
+
+    let data = std::fs::read(db_file)?;
+    let path = dir.join(format!("{}{}{}", PREFIX, now.format(TIME_FORMAT), SUFFIX));
+    write_private_file(&path, &data)?;
+
+    Ok(Snapshot {
+        path,
+        created_at: now,
+        size: data.len() as u64,
+    })
+}
+
+/// Delete all but the newest `keep` snapshots; returns how many were deleted
+pub fn rotate(dir: &Path, keep: usize) -> Result<usize> {
+    let snapshots = list_snapshots(dir)?;
+    let mut removed = 0;
+    for snapshot in snapshots.iter().skip(keep.max(1)) {
+        std::fs::remove_file(&snapshot.path)?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Configured number of snapshots to keep
+pub fn keep_count(db: &Database) -> Result<usize> {
+    match db.get_setting::<String>(BACKUP_KEEP_KEY)? {
+        Some(value) => parse_keep(&value),
+        None => Ok(DEFAULT_KEEP),
+    }
+}
+
+/// Validate a `backup.keep` value
+pub fn parse_keep(value: &str) -> Result<usize> {
+    match value.trim().parse::<usize>() {
+        Ok(keep) if keep > 0 => Ok(keep),
+        _ => Err(CcmError::InvalidArgument(format!(
+            "Invalid value for {}: '{}' (expected a number of at least 1)",
+            BACKUP_KEEP_KEY, value
+        ))),
+    }
+}
+
+/// Snapshot the open database now and rotate old snapshots
+pub fn snapshot_now(db: &Database) -> Result<Snapshot> {
+    // Fold the WAL into the main file so the copy is complete
+    db.checkpoint()?;
+
+    let dir = backups_dir();
+    let snapshot = write_snapshot(&dir, db.path(), Utc::now())?;
+    db.save_setting(FINGERPRINT_KEY, &db.entries_fingerprint()?)?;
+    rotate(&dir, keep_count(db)?)?;
+
+    Ok(snapshot)
+}
+
+/// Take an automatic snapshot if `backup.auto` asks for one: entries changed
+/// since the last snapshot and, for `daily`, the last one is a day old.
+/// Returns the snapshot taken, if any
+pub fn auto_snapshot(db: &Database) -> Result<Option<Snapshot>> {
+    let mode = db
+        .get_setting::<String>(AUTO_BACKUP_KEY)?
+        .and_then(|v| AutoBackup::parse(&v))
+        .unwrap_or(AutoBackup::Off);
+    if mode == AutoBackup::Off {
+        return Ok(None);
+    }
+
+    let latest = list_snapshots(&backups_dir())?.into_iter().next();
+    if mode == AutoBackup::Daily {
+        if let Some(latest) = &latest {
+            if Utc::now() - latest.created_at < chrono::Duration::days(1) {
+                return Ok(None);
+            }
+        }
+    }
+
+    let unchanged = db.get_setting::<String>(FINGERPRINT_KEY)?.as_deref()
+        == Some(db.entries_fingerprint()?.as_str());
+    if unchanged && latest.is_some() {
+        return Ok(None);
+    }
+
+    snapshot_now(db).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_auto_backup_parse() {
+        assert_eq!(AutoBackup::parse("daily"), Some(AutoBackup::Daily));
+        assert_eq!(AutoBackup::parse("Change"), Some(AutoBackup::OnChange));
+        assert_eq!(AutoBackup::parse("off"), Some(AutoBackup::Off));
+        assert_eq!(AutoBackup::parse("hourly"), None);
+        assert!(parse_keep("0").is_err());
+        assert_eq!(parse_keep("5").unwrap(), 5);
+    }
+
+    #[test]
+    fn test_snapshots_list_and_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_file = dir.path().join("ccm.db");
+        std::fs::write(&db_file, b"encrypted pages").unwrap();
+        let backups = dir.path().join("backups");
+
+        for day in 1..=4 {
+            let time = Utc.with_ymd_and_hms(2026, 3, day, 12, 0, 0).unwrap();
+            write_snapshot(&backups, &db_file, time).unwrap();
+        }
+        std::fs::write(backups.join("notes.txt"), b"ignored").unwrap();
+
+        let snapshots = list_snapshots(&backups).unwrap();
+        assert_eq!(snapshots.len(), 4);
+        assert_eq!(snapshots[0].created_at.format("%d").to_string(), "04");
+        assert_eq!(snapshots[0].size, 15);
+
+        assert_eq!(rotate(&backups, 2).unwrap(), 2);
+        let kept: Vec<String> = list_snapshots(&backups)
+            .unwrap()
+            .iter()
+            .map(|s| s.created_at.format("%d").to_string())
+            .collect();
+        assert_eq!(kept, vec!["04", "03"]);
+        assert!(backups.join("notes.txt").exists());
+    }
+}
@@ -1,12 +1,17 @@
 // Environment variable management (platform-specific)
 
 use crate::types::Entry;
+#[cfg(unix)]
+use crate::utils::files::write_file_atomic;
 use crate::utils::Result;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-
-#[cfg(unix)]
 use std::path::PathBuf;
 
+pub mod project;
+pub mod shell;
+
 /// Set environment variables for an entry
 pub fn set_env_for_entry(name: &str, entry: &Entry, quiet: bool) -> Result<()> {
     // Get all environment variable mappings from metadata
@@ -30,7 +35,7 @@ pub fn set_env_for_entry(name: &str, entry: &Entry, quiet: bool) -> Result<()> {
 
     if !quiet {
         println!("✅ Set {} environment variables for '{}':", env_vars.len(), name);
-        for (key, _) in &env_vars {
+        for key in env_vars.keys() {
             println!("  {}", key);
         }
     }
@@ -90,13 +95,164 @@ pub fn get_env_mappings_with_secret(entry: &Entry, secret: &str) -> HashMap<Stri
     env_vars
 }
 
+/// Settings key holding the entry last applied with `ccm use`
+pub(crate) const ACTIVE_ENTRY_KEY: &str = "active_entry";
+
+/// Record of the entry most recently applied with `ccm use`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveEntry {
+    /// Entry name
+    pub name: String,
+    /// Environment variable names that were exported
+    pub env_vars: Vec<String>,
+    /// When the entry was applied
+    pub activated_at: String,
+}
+
+/// Settings key holding recent `ccm use` activations per shell
+pub(crate) const ACTIVATIONS_KEY: &str = "entry_activations";
+
+/// Number of activations kept for `ccm status`
+const MAX_ACTIVATIONS: usize = 20;
+
+/// One application of an entry with `ccm use`: which shell, where and when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activation {
+    /// Entry name
+    pub name: String,
+    /// Shell the entry was applied in (see `auth::get_shell_pid`)
+    pub shell_pid: u32,
+    /// Project the command ran in: the git root, else the working directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// When the entry was applied
+    pub activated_at: String,
+}
+
+/// Recent activations, newest first
+pub fn get_activations() -> Result<Vec<Activation>> {
+    let db = crate::db::storage()?;
+    Ok(db
+        .get_setting::<Vec<Activation>>(ACTIVATIONS_KEY)?
+        .unwrap_or_default())
+}
+
+/// Record that `name` was applied in the current shell
+fn record_activation(
+    db: &dyn crate::db::StorageBackend,
+    name: &str,
+    activated_at: &str,
+) -> Result<()> {
+    let shell_pid = crate::auth::get_shell_pid().unwrap_or_else(std::process::id);
+    let project = current_project().map(|p| p.display().to_string());
+
+    let mut activations = db
+        .get_setting::<Vec<Activation>>(ACTIVATIONS_KEY)?
+        .unwrap_or_default();
+    activations.retain(|a| !(a.shell_pid == shell_pid && a.name == name));
+    activations.insert(
+        0,
+        Activation {
+            name: name.to_string(),
+            shell_pid,
+            project,
+            activated_at: activated_at.to_string(),
+        },
+    );
+    activations.truncate(MAX_ACTIVATIONS);
+
+    db.save_setting(ACTIVATIONS_KEY, &activations)
+}
+
+/// The git repository containing the working directory, else the directory itself
+fn current_project() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    let root = cwd
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(PathBuf::from);
+    Some(root.unwrap_or(cwd))
+}
+
+/// Persist the active entry in the settings table
+pub fn record_active_entry(name: &str, env_vars: &HashMap<String, String>) -> Result<()> {
+    let mut keys: Vec<String> = env_vars.keys().cloned().collect();
+    keys.sort();
+
+    let active = ActiveEntry {
+        name: name.to_string(),
+        env_vars: keys,
+        activated_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let db = crate::db::storage()?;
+    db.save_setting(ACTIVE_ENTRY_KEY, &active)?;
+    record_activation(&*db, name, &active.activated_at)?;
+
+    // The shell now has the current values
+    let marker = active_entry_changed_path();
+    if marker.exists() {
+        std::fs::remove_file(marker)?;
+    }
+
+    Ok(())
+}
+
+/// Get the active entry, if any
+pub fn get_active_entry() -> Result<Option<ActiveEntry>> {
+    let db = crate::db::storage()?;
+    db.get_setting::<ActiveEntry>(ACTIVE_ENTRY_KEY)
+}
+
+/// Marker written when the active entry is modified after it was applied
+/// Lives outside the encrypted database so every command can check it cheaply,
+/// without needing the master key
+pub fn active_entry_changed_path() -> PathBuf {
+    crate::db::db_dir().join("active_entry_changed")
+}
+
+/// Flag the active entry as stale if `name` is the active entry
+pub fn note_entry_changed(name: &str) -> Result<()> {
+    if let Some(active) = get_active_entry()? {
+        if active.name == name {
+            std::fs::write(active_entry_changed_path(), name)?;
+        }
+    }
+    Ok(())
+}
+
+/// Name of the active entry if it changed since it was applied
+pub fn changed_active_entry() -> Option<String> {
+    std::fs::read_to_string(active_entry_changed_path())
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Print a notice when the active entry changed since it was applied
+pub fn print_changed_active_entry_notice() {
+    if let Some(name) = changed_active_entry() {
+        eprintln!(
+            "{} Active entry '{}' changed after it was applied; your shell has stale values.",
+            "⚠️".yellow(),
+            name.bold()
+        );
+        eprintln!("   Run 'ccm use {}' or add --reapply to refresh it.", name);
+        eprintln!();
+    }
+}
+
 /// Set environment variables on Windows
 #[cfg(windows)]
 fn set_env_windows(env_vars: &HashMap<String, String>, quiet: bool) -> Result<()> {
     use std::process::Command;
 
     for (key, value) in env_vars {
-        let output = Command::new("setx").arg(key).arg(value).output();
+        let output = Command::new("setx")
+            .env_remove(crate::auth::PIN_ENV)
+            .arg(key)
+            .arg(value)
+            .output();
 
         match output {
             Ok(output) if output.status.success() => {
@@ -120,20 +276,12 @@ fn set_env_windows(env_vars: &HashMap<String, String>, quiet: bool) -> Result<()
 /// Set environment variables on Unix/macOS
 #[cfg(unix)]
 fn set_env_unix(env_vars: &HashMap<String, String>, quiet: bool) -> Result<()> {
-    use std::fs::OpenOptions;
-    use std::io::Write;
-
     // Determine shell config file
     let shell_config = detect_shell_config()?;
 
     // Read existing content to avoid duplicates
     let existing_content = std::fs::read_to_string(&shell_config).unwrap_or_default();
-
-    // Open file for appending
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&shell_config)?;
+    let mut content = existing_content.clone();
 
     for (key, value) in env_vars {
         let export_line = format!("export {}=\"{}\"\n", key, value);
@@ -147,13 +295,19 @@ fn set_env_unix(env_vars: &HashMap<String, String>, quiet: bool) -> Result<()> {
             continue;
         }
 
-        writeln!(file, "{}", export_line)?;
+        content.push_str(&export_line);
+        content.push('\n');
 
         if !quiet {
             println!("  {} = {}", key, value);
         }
     }
 
+    // Rewrite the whole file atomically so a crash cannot leave it half-written
+    if content != existing_content {
+        write_file_atomic(&shell_config, content.as_bytes())?;
+    }
+
     if !quiet {
         println!(
             "💡 Run `source {}` or restart your shell to use the new variables",
@@ -238,6 +392,7 @@ fn unset_env_windows(keys: &[String], quiet: bool) -> Result<()> {
 
     for key in keys {
         let output = Command::new("reg")
+            .env_remove(crate::auth::PIN_ENV)
             .args(["delete", "HKCU\\Environment", "/v", key, "/f"])
             .output();
 
@@ -289,7 +444,7 @@ fn unset_env_unix(keys: &[String], quiet: bool) -> Result<()> {
     }
 
     if removed > 0 {
-        std::fs::write(&shell_config, content)?;
+        write_file_atomic(&shell_config, content.as_bytes())?;
 
         if !quiet {
             println!(
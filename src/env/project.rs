@@ -0,0 +1,259 @@
+// Per-project entry mapping (`.ccm.toml`)
+//
+// A project can commit a `.ccm.toml` naming the entries its scripts need, so
+// `ccm run`, `ccm npm-run` and `ccm cargo` inject them without every package
+// script wrapping itself in `ccm use`:
+//
+//     profile = "openai-dev"          # default for any command
+//
+//     [scripts]                       # ccm npm-run <script>
+//     e2e = ["openai-dev", "stripe-test"]
+//
+//     [cargo]                         # ccm cargo <subcommand>
+//     test = "openai-test"
+//
+// A cloned repository could name any entry, so like direnv's `.envrc` a
+// `.ccm.toml` is only used once `ccm allow` recorded its path and content
+// hash; any change to it needs another `ccm allow`.
+
+use crate::utils::files::write_file_atomic;
+use crate::utils::{sha256_hash, CcmError, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// File name looked up from the current directory upwards
+pub const PROJECT_FILE: &str = ".ccm.toml";
+
+/// Allowed project files in the config directory: path → SHA-256 of the content
+pub const ALLOWED_FILE: &str = "allowed-projects.json";
+
+/// One entry name or several
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Profiles {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Profiles {
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Profiles::One(name) => vec![name.clone()],
+            Profiles::Many(names) => names.clone(),
+        }
+    }
+}
+
+/// Parsed `.ccm.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Entries used when nothing more specific matches
+    pub profile: Option<Profiles>,
+    /// Entries per `npm run` script
+    #[serde(default)]
+    pub scripts: HashMap<String, Profiles>,
+    /// Entries per cargo subcommand
+    #[serde(default)]
+    pub cargo: HashMap<String, Profiles>,
+}
+
+impl ProjectConfig {
+    /// Entries for an npm script (falls back to the default profile)
+    pub fn for_script(&self, script: &str) -> Vec<String> {
+        self.scripts
+            .get(script)
+            .or(self.profile.as_ref())
+            .map(Profiles::names)
+            .unwrap_or_default()
+    }
+
+    /// Entries for a cargo subcommand (falls back to the default profile)
+    pub fn for_cargo(&self, subcommand: Option<&str>) -> Vec<String> {
+        subcommand
+            .and_then(|sub| self.cargo.get(sub))
+            .or(self.profile.as_ref())
+            .map(Profiles::names)
+            .unwrap_or_default()
+    }
+
+    /// The default entries
+    pub fn default_profile(&self) -> Vec<String> {
+        self.profile
+            .as_ref()
+            .map(Profiles::names)
+            .unwrap_or_default()
+    }
+}
+
+/// Find the nearest `.ccm.toml` in `start` or one of its parents
+pub fn find_project_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Parse a `.ccm.toml`
+pub fn parse_project_config(content: &str) -> Result<ProjectConfig> {
+    toml::from_str(content)
+        .map_err(|e| CcmError::InvalidArgument(format!("Invalid {}: {}", PROJECT_FILE, e)))
+}
+
+/// Load the project config for the current directory, with its path
+/// (`None` outside a project); fails unless it was allowed as it is
+pub fn load_project_config() -> Result<Option<(PathBuf, ProjectConfig)>> {
+    load_allowed_config(&std::env::current_dir()?, &allowed_path())
+}
+
+fn load_allowed_config(start: &Path, allowed: &Path) -> Result<Option<(PathBuf, ProjectConfig)>> {
+    let Some(path) = find_project_file(start) else {
+        return Ok(None);
+    };
+    let content = std::fs::read_to_string(&path)?;
+    let path = path.canonicalize()?;
+    match load_allowed(allowed)?.get(&path.display().to_string()) {
+        Some(hash) if *hash == content_hash(&content) => {}
+        Some(_) => {
+            return Err(CcmError::InvalidArgument(format!(
+                "{} changed since it was allowed. Review it, then run 'ccm allow' again",
+                path.display()
+            )))
+        }
+        None => {
+            return Err(CcmError::InvalidArgument(format!(
+                "{} is not allowed yet. Review the entries it names, then run 'ccm allow'",
+                path.display()
+            )))
+        }
+    }
+    Ok(Some((path, parse_project_config(&content)?)))
+}
+
+/// Path of the list of allowed project files
+pub fn allowed_path() -> PathBuf {
+    crate::db::location::config_dir().join(ALLOWED_FILE)
+}
+
+fn load_allowed(allowed: &Path) -> Result<BTreeMap<String, String>> {
+    if !allowed.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(allowed)?)?)
+}
+
+fn content_hash(content: &str) -> String {
+    hex::encode(sha256_hash(content.as_bytes()))
+}
+
+/// Allow `file` with its current content (`ccm allow`); returns its
+/// canonical path
+pub fn allow(allowed: &Path, file: &Path) -> Result<PathBuf> {
+    let content = std::fs::read_to_string(file)?;
+    parse_project_config(&content)?;
+    let path = file.canonicalize()?;
+
+    let mut list = load_allowed(allowed)?;
+    list.insert(path.display().to_string(), content_hash(&content));
+    save_allowed(allowed, &list)?;
+    Ok(path)
+}
+
+/// Forget `file` (`ccm allow --revoke`); returns whether it was allowed
+pub fn revoke(allowed: &Path, file: &Path) -> Result<bool> {
+    let path = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    let mut list = load_allowed(allowed)?;
+    let removed = list.remove(&path.display().to_string()).is_some();
+    if removed {
+        save_allowed(allowed, &list)?;
+    }
+    Ok(removed)
+}
+
+fn save_allowed(allowed: &Path, list: &BTreeMap<String, String>) -> Result<()> {
+    if let Some(dir) = allowed.parent() {
+        crate::utils::files::create_private_dir(dir)?;
+    }
+    write_file_atomic(allowed, serde_json::to_string_pretty(list)?.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_config() {
+        let config = parse_project_config(
+            r#"
+profile = "openai-dev"
+
+[scripts]
+e2e = ["openai-dev", "stripe-test"]
+
+[cargo]
+test = "openai-test"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default_profile(), vec!["openai-dev"]);
+        assert_eq!(config.for_script("e2e"), vec!["openai-dev", "stripe-test"]);
+        assert_eq!(config.for_script("build"), vec!["openai-dev"]);
+        assert_eq!(config.for_cargo(Some("test")), vec!["openai-test"]);
+        assert_eq!(config.for_cargo(Some("build")), vec!["openai-dev"]);
+        assert_eq!(config.for_cargo(None), vec!["openai-dev"]);
+
+        assert!(parse_project_config("").unwrap().for_script("x").is_empty());
+        assert!(parse_project_config("profiles = \"typo\"").is_err());
+    }
+
+    #[test]
+    fn test_find_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let nested = root.join("crates").join("app");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join(PROJECT_FILE), "profile = \"x\"\n").unwrap();
+
+        assert_eq!(find_project_file(&nested), Some(root.join(PROJECT_FILE)));
+    }
+
+    #[test]
+    fn test_allow() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("repo");
+        let nested = root.join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        let file = root.join(PROJECT_FILE);
+        std::fs::write(&file, "profile = \"openai-dev\"\n").unwrap();
+        let allowed = dir.path().join("config").join(ALLOWED_FILE);
+
+        let err = load_allowed_config(&nested, &allowed).unwrap_err();
+        assert!(err.to_string().contains("not allowed yet"), "{}", err);
+
+        allow(&allowed, &file).unwrap();
+        let (path, config) = load_allowed_config(&nested, &allowed).unwrap().unwrap();
+        assert_eq!(path, file.canonicalize().unwrap());
+        assert_eq!(config.default_profile(), ["openai-dev"]);
+
+        // Any change needs another allow
+        std::fs::write(&file, "profile = \"prod-admin\"\n").unwrap();
+        let err = load_allowed_config(&nested, &allowed).unwrap_err();
+        assert!(
+            err.to_string().contains("changed since it was allowed"),
+            "{}",
+            err
+        );
+        allow(&allowed, &file).unwrap();
+        assert!(load_allowed_config(&nested, &allowed).is_ok());
+
+        assert!(revoke(&allowed, &file).unwrap());
+        assert!(!revoke(&allowed, &file).unwrap());
+        assert!(load_allowed_config(&nested, &allowed).is_err());
+        assert!(load_allowed_config(dir.path(), &allowed).unwrap().is_none());
+
+        std::fs::write(&file, "profiles = 1").unwrap();
+        assert!(allow(&allowed, &file).is_err());
+    }
+}
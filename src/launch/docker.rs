@@ -0,0 +1,76 @@
+// Docker arguments for `ccm docker`
+//
+// `docker run -e NAME` (no `=value`) copies NAME from the docker client's own
+// environment into the container. ccm sets the entry's variables on the
+// docker process only and adds one `--env NAME` per variable, so the values
+// appear neither in the process list nor in a file on disk.
+
+use crate::utils::{CcmError, Result};
+
+/// Docker subcommands that accept --env
+const ENV_SUBCOMMANDS: &[&str] = &["run", "create", "exec"];
+
+/// Global docker options followed by a value (`--context prod`)
+const VALUE_OPTIONS: &[&str] = &[
+    "--context",
+    "-c",
+    "--host",
+    "-H",
+    "--config",
+    "--log-level",
+    "-l",
+];
+
+/// `args` with `--env NAME` flags inserted right after the run/create/exec
+/// subcommand (`docker container run` works too)
+pub fn with_env_flags(args: &[String], names: &[String]) -> Result<Vec<String>> {
+    // Skip global options (and the `container` group) up to the subcommand
+    let mut i = 0;
+    let position = loop {
+        match args.get(i).map(String::as_str) {
+            Some(arg) if ENV_SUBCOMMANDS.contains(&arg) => break Some(i),
+            Some(arg) if VALUE_OPTIONS.contains(&arg) => i += 2,
+            Some(arg) if arg.starts_with('-') || arg == "container" => i += 1,
+            _ => break None,
+        }
+    };
+    let position = position.ok_or_else(|| {
+        CcmError::InvalidArgument(
+            "ccm docker needs a run, create or exec command (ccm docker <ENTRY> -- run IMAGE)"
+                .to_string(),
+        )
+    })?;
+
+    let mut result = args[..=position].to_vec();
+    for name in names {
+        result.push("--env".to_string());
+        result.push(name.clone());
+    }
+    result.extend_from_slice(&args[position + 1..]);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_with_env_flags() {
+        let names = args("OPENAI_API_KEY OPENAI_BASE_URL");
+        assert_eq!(
+            with_env_flags(&args("run --rm -it python:3 python"), &names).unwrap(),
+            args("run --env OPENAI_API_KEY --env OPENAI_BASE_URL --rm -it python:3 python")
+        );
+        assert_eq!(
+            with_env_flags(&args("--context prod container exec app sh"), &names[..1]).unwrap(),
+            args("--context prod container exec --env OPENAI_API_KEY app sh")
+        );
+        // "run" as an argument of the container, not the subcommand
+        assert!(with_env_flags(&args("build -t run ."), &names).is_err());
+        assert!(with_env_flags(&args("ps"), &names).is_err());
+    }
+}
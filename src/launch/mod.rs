@@ -0,0 +1,307 @@
+// Tool profiles for `ccm launch`
+//
+// Each AI CLI reads its key, base URL and model from its own variables. An
+// entry made for one provider (say an OpenAI-compatible relay) is translated
+// for the tool being launched: the entry's variables are all passed on, and
+// the variables the tool expects are added from the entry's secret, base URL
+// and model when the entry does not set them already. The base URL is only
+// carried over when the key is, so a native entry keeps the tool's default.
+// The tool's own variables inherited from the shell (say a relay applied with
+// `ccm use`) are removed first, so they never mix with the entry's.
+
+pub mod claude;
+pub mod docker;
+
+use crate::utils::{CcmError, Result};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// API flavour a tool speaks, which decides the base URL form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    /// Base URL without /v1 (the client appends it)
+    Anthropic,
+    /// Base URL ending in /v1
+    OpenAi,
+    /// Base URL used as given
+    Gemini,
+}
+
+/// What a tool expects in its environment
+#[derive(Debug, Clone)]
+pub struct ToolProfile {
+    pub name: &'static str,
+    /// Executable to run
+    pub program: &'static str,
+    pub api: Api,
+    /// Variables the tool reads the key from; the first is set when missing
+    pub key_vars: &'static [&'static str],
+    pub base_url_var: Option<&'static str>,
+    pub model_var: Option<&'static str>,
+    /// Command-line option for the model, for tools without a model variable
+    pub model_arg: Option<&'static str>,
+}
+
+/// The supported tools
+pub fn tool_profiles() -> Vec<ToolProfile> {
+    vec![
+        ToolProfile {
+            name: "claude",
+            program: "claude",
+            api: Api::Anthropic,
+            key_vars: &["ANTHROPIC_API_KEY", "ANTHROPIC_AUTH_TOKEN"],
+            base_url_var: Some("ANTHROPIC_BASE_URL"),
+            model_var: Some("ANTHROPIC_MODEL"),
+            model_arg: None,
+        },
+        ToolProfile {
+            name: "codex",
+            program: "codex",
+            api: Api::OpenAi,
+            key_vars: &["OPENAI_API_KEY"],
+            base_url_var: Some("OPENAI_BASE_URL"),
+            model_var: None,
+            model_arg: Some("--model"),
+        },
+        ToolProfile {
+            name: "gemini",
+            program: "gemini",
+            api: Api::Gemini,
+            key_vars: &["GEMINI_API_KEY", "GOOGLE_API_KEY"],
+            base_url_var: None,
+            model_var: Some("GEMINI_MODEL"),
+            model_arg: None,
+        },
+        ToolProfile {
+            name: "aider",
+            program: "aider",
+            api: Api::OpenAi,
+            key_vars: &["OPENAI_API_KEY", "ANTHROPIC_API_KEY", "GEMINI_API_KEY"],
+            base_url_var: Some("OPENAI_API_BASE"),
+            model_var: Some("AIDER_MODEL"),
+            model_arg: None,
+        },
+    ]
+}
+
+/// Look up a tool by name
+pub fn tool_profile(name: &str) -> Result<ToolProfile> {
+    let profiles = tool_profiles();
+    let names: Vec<&str> = profiles.iter().map(|p| p.name).collect();
+    profiles
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name))
+        .cloned()
+        .ok_or_else(|| {
+            CcmError::InvalidArgument(format!(
+                "Unknown tool: {}. Available: {} (or use 'ccm run -p <ENTRY> -- <COMMAND>')",
+                name,
+                names.join(", ")
+            ))
+        })
+}
+
+/// The environment and extra arguments to launch `tool` with an entry
+///
+/// `metadata` is the entry's mapping (SECRET marks the secret), `env_vars`
+/// the same with the secret substituted.
+pub fn tool_env(
+    tool: &ToolProfile,
+    metadata: &HashMap<String, String>,
+    env_vars: &HashMap<String, String>,
+) -> (HashMap<String, String>, Vec<String>) {
+    let mut env = env_vars.clone();
+    let mut args = Vec::new();
+
+    let find = |suffixes: &[&str]| {
+        let mut vars: Vec<&String> = env_vars
+            .keys()
+            .filter(|var| suffixes.iter().any(|s| var.ends_with(s)))
+            .collect();
+        vars.sort();
+        vars.first().map(|var| env_vars[*var].clone())
+    };
+    let base_url = find(&["_BASE_URL", "_API_BASE"]);
+
+    // A native entry already has the tool's base URL (or means the default)
+    let translated = !tool.key_vars.iter().any(|var| env.contains_key(*var));
+    if translated {
+        let secret_var = metadata
+            .iter()
+            .filter(|(_, value)| value.as_str() == "SECRET")
+            .map(|(var, _)| var)
+            .min();
+        if let Some(secret) = secret_var.and_then(|var| env_vars.get(var)) {
+            env.insert(
+                key_var(tool, base_url.as_deref()).to_string(),
+                secret.clone(),
+            );
+        }
+    }
+
+    if let (true, Some(var), Some(url)) = (translated, tool.base_url_var, &base_url) {
+        env.entry(var.to_string())
+            .or_insert_with(|| base_url_for(tool.api, url));
+    }
+
+    if let Some(model) = find(&["_MODEL"]) {
+        if let Some(var) = tool.model_var {
+            env.entry(var.to_string()).or_insert(model);
+        } else if let Some(arg) = tool.model_arg {
+            args.push(arg.to_string());
+            args.push(model);
+        }
+    }
+
+    (env, args)
+}
+
+/// Variables the tool reads its key, base URL and model from (for claude,
+/// every variable `ccm claude` manages)
+pub fn tool_vars(tool: &ToolProfile) -> Vec<&'static str> {
+    let mut vars: Vec<&'static str> = tool.key_vars.to_vec();
+    vars.extend(tool.base_url_var);
+    vars.extend(tool.model_var);
+    if tool.name == "claude" {
+        vars.extend(claude::MANAGED_VARS);
+    }
+    vars.sort();
+    vars.dedup();
+    vars
+}
+
+/// The command starting `tool` with only the entry's values for its variables
+pub fn tool_command(
+    tool: &ToolProfile,
+    args: &[String],
+    env_vars: &HashMap<String, String>,
+) -> Command {
+    let mut command = Command::new(tool.program);
+    command.env_remove(crate::auth::PIN_ENV);
+    for var in tool_vars(tool) {
+        command.env_remove(var);
+    }
+    command.args(args).envs(env_vars);
+    command
+}
+
+/// Variable for a translated key: Anthropic's own API takes x-api-key
+/// (ANTHROPIC_API_KEY), relays mostly a bearer token (ANTHROPIC_AUTH_TOKEN)
+fn key_var(tool: &ToolProfile, base_url: Option<&str>) -> &'static str {
+    let official = base_url.is_none_or(|url| url.contains("api.anthropic.com"));
+    if tool.api == Api::Anthropic && !official {
+        "ANTHROPIC_AUTH_TOKEN"
+    } else {
+        tool.key_vars[0]
+    }
+}
+
+fn base_url_for(api: Api, url: &str) -> String {
+    let base = url.trim_end_matches('/');
+    match api {
+        Api::Anthropic => base.strip_suffix("/v1").unwrap_or(base).to_string(),
+        Api::OpenAi if !base.ends_with("/v1") => format!("{}/v1", base),
+        Api::OpenAi | Api::Gemini => base.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maps(
+        vars: &[(&str, &str)],
+        secret: &str,
+    ) -> (HashMap<String, String>, HashMap<String, String>) {
+        let metadata: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let env = metadata
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.clone(),
+                    if v == "SECRET" {
+                        secret.to_string()
+                    } else {
+                        v.clone()
+                    },
+                )
+            })
+            .collect();
+        (metadata, env)
+    }
+
+    #[test]
+    fn test_tool_profile() {
+        assert_eq!(tool_profile("Claude").unwrap().program, "claude");
+        assert!(tool_profile("emacs").is_err());
+    }
+
+    #[test]
+    fn test_tool_env_translates_relay_for_claude() {
+        let (metadata, env_vars) = maps(
+            &[
+                ("OPENAI_API_KEY", "SECRET"),
+                ("OPENAI_BASE_URL", "https://relay.example.com/v1"),
+                ("OPENAI_MODEL", "glm-4.6"),
+            ],
+            "sk-1",
+        );
+        let (env, args) = tool_env(&tool_profile("claude").unwrap(), &metadata, &env_vars);
+        assert_eq!(env["ANTHROPIC_AUTH_TOKEN"], "sk-1");
+        assert_eq!(env["ANTHROPIC_BASE_URL"], "https://relay.example.com");
+        assert_eq!(env["ANTHROPIC_MODEL"], "glm-4.6");
+        assert_eq!(env["OPENAI_API_KEY"], "sk-1");
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_tool_env_keeps_native_variables() {
+        let (metadata, env_vars) = maps(&[("ANTHROPIC_API_KEY", "SECRET")], "sk-ant");
+        let (env, _) = tool_env(&tool_profile("claude").unwrap(), &metadata, &env_vars);
+        assert_eq!(env, env_vars);
+
+        // aider reads ANTHROPIC_API_KEY itself; no OpenAI base URL is made up
+        let (metadata, env_vars) = maps(
+            &[
+                ("ANTHROPIC_API_KEY", "SECRET"),
+                ("ANTHROPIC_BASE_URL", "https://a.example.com"),
+            ],
+            "sk-ant",
+        );
+        let (env, _) = tool_env(&tool_profile("aider").unwrap(), &metadata, &env_vars);
+        assert_eq!(env, env_vars);
+
+        let (metadata, env_vars) = maps(
+            &[
+                ("ANTHROPIC_API_KEY", "SECRET"),
+                ("ANTHROPIC_MODEL", "claude-sonnet-4-5"),
+            ],
+            "sk-ant",
+        );
+        let (env, args) = tool_env(&tool_profile("codex").unwrap(), &metadata, &env_vars);
+        assert_eq!(env["OPENAI_API_KEY"], "sk-ant");
+        assert_eq!(args, ["--model", "claude-sonnet-4-5"]);
+    }
+
+    #[test]
+    fn test_tool_command_clears_inherited_variables() {
+        let tool = tool_profile("claude").unwrap();
+        let env_vars = HashMap::from([("ANTHROPIC_API_KEY".to_string(), "sk-ant".to_string())]);
+        let command = tool_command(&tool, &[], &env_vars);
+        let envs: HashMap<_, _> = command
+            .get_envs()
+            .map(|(var, value)| (var.to_str().unwrap(), value.and_then(|v| v.to_str())))
+            .collect();
+
+        // A relay's URL and token from the shell do not reach the tool
+        assert_eq!(envs["ANTHROPIC_BASE_URL"], None);
+        assert_eq!(envs["ANTHROPIC_AUTH_TOKEN"], None);
+        assert_eq!(envs["ANTHROPIC_SMALL_FAST_MODEL"], None);
+        assert_eq!(envs["ANTHROPIC_API_KEY"], Some("sk-ant"));
+        assert_eq!(envs[crate::auth::PIN_ENV], None);
+        assert!(tool_vars(&tool_profile("codex").unwrap()).contains(&"OPENAI_BASE_URL"));
+    }
+}
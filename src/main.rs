@@ -4,40 +4,100 @@
 // Allow dead code for unused helper functions and types that are part of the API
 #![allow(dead_code)]
 
+mod agent;
+mod audit;
 mod auth;
 mod commands;
 mod core;
 mod db;
 mod env;
+mod examples;
+mod launch;
+mod mcp;
+mod net;
 mod presets;
+mod remote;
+mod rpc;
 mod secrets;
+mod sync;
+mod transfer;
 mod types;
 mod utils;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use colored::Colorize;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 /// CCM - Custom Configuration Manager
 /// Secure profile and API key manager with AES-256-GCM encryption
 #[derive(Parser, Debug)]
 #[command(name = "ccm")]
 #[command(author = "CCM Contributors")]
-#[command(version = "0.9.1")]
+#[command(version)]
 #[command(about = "Manage AI API configurations, passwords, SSH keys, and secrets with military-grade encryption", long_about = None)]
 #[command(disable_version_flag = true)]
+#[command(disable_help_subcommand = true)]
 struct Cli {
     /// Print version
     #[arg(short = 'v', long = "version", action = clap::ArgAction::Version)]
     version: (),
 
+    /// Print a timing breakdown (keyring, key derivation, database, crypto) at the end
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Re-apply the active entry after the command if it has changed
+    #[arg(long, global = true)]
+    reapply: bool,
+
+    /// Database file to use instead of ccm.db in $CCM_HOME (or ~/.ccm, or $XDG_DATA_HOME/ccm on Linux)
+    #[arg(long, global = true, value_name = "PATH")]
+    db: Option<PathBuf>,
+
+    /// Read the PIN from this file instead of prompting (for CI and cron;
+    /// the CCM_PIN environment variable works too)
+    #[arg(long, global = true, value_name = "PATH")]
+    pin_file: Option<PathBuf>,
+
+    /// Keep entries on a `ccm serve --listen` server instead of the local
+    /// database (also: CCM_REMOTE; token in CCM_REMOTE_TOKEN)
+    #[arg(long, global = true, value_name = "URL")]
+    remote: Option<String>,
+
+    /// Print results and errors as JSON (also: CCM_OUTPUT=json)
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Only print results and errors, no confirmations or notices
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Disable colors (also: NO_COLOR=1)
+    #[arg(long, global = true)]
+    no_color: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    /// Set up ccm: check the keyring, set a PIN and add your first entries
+    Init,
+
+    /// Delete the vault, its keys in the OS keyring and all session state
+    ///
+    /// Asks for the instance ID (shown by the command) as confirmation. Works
+    /// without the PIN; snapshots, the git sync repository, presets.toml and
+    /// allowed .ccm.toml files are kept (and listed).
+    Reset {
+        /// Instance ID, to confirm without a prompt (scripts)
+        #[arg(long, value_name = "INSTANCE_ID")]
+        confirm: Option<String>,
+    },
+
     /// Add a new entry
+    ///
     /// Entries store environment variable mappings with SECRET as placeholder for encrypted value
     Add {
         /// Name for the entry
@@ -45,13 +105,26 @@ enum Commands {
         name: String,
 
         /// Secret value (API key, password, etc.)
-        #[arg(value_name = "SECRET")]
+        /// Omit to enter it at a hidden prompt, or use "-" to read it from stdin
+        #[arg(value_name = "SECRET", conflicts_with = "generate")]
         secret: Option<String>,
 
         /// Secret value (alternative to positional argument)
-        #[arg(short = 's', long, value_name = "SECRET")]
+        #[arg(short = 's', long, value_name = "SECRET", conflicts_with = "generate")]
         secret_flag: Option<String>,
 
+        /// Read the secret from a file, kept byte for byte (certificates,
+        /// private keys, service-account JSON, kubeconfigs)
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["secret", "secret_flag", "generate"]
+        )]
+        secret_file: Option<PathBuf>,
+
+        #[command(flatten)]
+        generate: GenerateArgs,
+
         /// Environment variable mapping (can be used multiple times: --env VAR=VALUE)
         /// Use VALUE="SECRET" to indicate the encrypted secret value
         #[arg(short = 'e', long, value_name = "VAR=VALUE")]
@@ -64,13 +137,19 @@ enum Commands {
         /// Notes for the entry
         #[arg(short = 'n', long, value_name = "NOTES")]
         notes: Option<String>,
+
+        /// Fill the variables from a preset (see ccm preset list): the secret,
+        /// default URL, and other required fields (asked for unless given with --env)
+        #[arg(short = 'p', long, value_name = "PRESET")]
+        preset: Option<String>,
     },
 
     /// Get an entry (decrypt and display secret)
     Get {
-        /// Entry name
+        /// Entry name; omitted, pick one interactively. On a terminal a
+        /// misspelt name is matched fuzzily (`anthpric` finds anthropic-work)
         #[arg(value_name = "NAME")]
-        name: String,
+        name: Option<String>,
 
         /// Specific field to retrieve
         #[arg(short, long, value_name = "FIELD")]
@@ -79,6 +158,20 @@ enum Commands {
         /// Copy secret to clipboard
         #[arg(short, long)]
         copy: bool,
+
+        /// Write only the raw value (secret unless --field is given) to stdout,
+        /// without a trailing newline, for pipes and $(...)
+        #[arg(long, visible_alias = "stdout-raw", conflicts_with = "copy")]
+        raw: bool,
+
+        /// Allow --raw when stdout is a terminal
+        #[arg(long, requires = "raw")]
+        force_tty: bool,
+
+        /// Write the value (secret unless --field is given) to a file readable
+        /// only by you (0600), exactly as stored
+        #[arg(short, long, value_name = "PATH", conflicts_with_all = ["copy", "raw"])]
+        out: Option<PathBuf>,
     },
 
     /// List all entries
@@ -88,15 +181,6 @@ enum Commands {
         #[arg(short, long)]
         verbose: bool,
 
-        /// Output as JSON
-        #[arg(
-            long,
-            conflicts_with = "verbose",
-            conflicts_with = "table",
-            conflicts_with = "quieter"
-        )]
-        json: bool,
-
         /// Alias for --json
         #[arg(
             long = "jq",
@@ -144,6 +228,57 @@ enum Commands {
             hide = true
         )]
         quieter_alias: bool,
+
+        /// Only entries with this tag (repeat to require several)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Only entries without tags
+        #[arg(long, conflicts_with = "tags")]
+        untagged: bool,
+
+        /// Only favorites (see ccm fav)
+        #[arg(long)]
+        fav: bool,
+
+        /// Only entries in a group and its subgroups: work for work/github,
+        /// work/anthropic/prod...
+        #[arg(long, value_name = "GROUP")]
+        group: Option<String>,
+
+        /// Show '/'-separated names as a tree of groups
+        #[arg(
+            long,
+            conflicts_with = "verbose",
+            conflicts_with = "json",
+            conflicts_with = "quieter"
+        )]
+        tree: bool,
+
+        /// With --tree, collapse groups below this many levels into their entry count
+        #[arg(long, value_name = "N", requires = "tree", value_parser = clap::value_parser!(u32).range(1..))]
+        depth: Option<u32>,
+
+        /// Only entries whose name matches a glob (* and ?), e.g. 'openai-*';
+        /// without wildcards, names containing the text
+        #[arg(long, value_name = "PATTERN")]
+        filter: Option<String>,
+
+        /// Only entries matching an expression, e.g. 'tag=prod && updated>2024-01-01 && env~ANTHROPIC'
+        ///
+        /// Fields: name, tag, env (variable names), notes, domain, created,
+        /// updated, or a variable such as OPENAI_BASE_URL. Operators: = != ~
+        /// (contains) !~ and > >= < <= for dates; combine with && and ||.
+        #[arg(long = "where", value_name = "EXPR")]
+        where_expr: Option<String>,
+
+        /// Add a Domain column (from each entry's url)
+        #[arg(long)]
+        show_domain: bool,
+
+        /// Order by name, or most recently used first (recent)
+        #[arg(long, value_name = "ORDER", default_value = "name", value_parser = ["name", "recent"])]
+        sort: String,
     },
 
     /// Update an entry
@@ -152,10 +287,22 @@ enum Commands {
         #[arg(value_name = "NAME")]
         name: String,
 
-        /// Update secret value
-        #[arg(short = 's', long = "secret", value_name = "VALUE")]
+        /// Update secret value ("-" reads from stdin)
+        #[arg(
+            short = 's',
+            long = "secret",
+            value_name = "VALUE",
+            conflicts_with = "generate"
+        )]
         secret: Option<String>,
 
+        /// Read the new secret from a file, kept byte for byte
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["secret", "generate"])]
+        secret_file: Option<PathBuf>,
+
+        #[command(flatten)]
+        generate: GenerateArgs,
+
         /// Update environment variable mappings (can be used multiple times: --env VAR=VALUE)
         /// Use VALUE="SECRET" to indicate the encrypted secret value
         #[arg(short = 'e', long, value_name = "VAR=VALUE")]
@@ -168,18 +315,96 @@ enum Commands {
         /// Update notes
         #[arg(short = 'n', long = "notes", value_name = "NOTES")]
         notes: Option<String>,
+
+        /// Change the secret of a locked entry (see ccm lock)
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// Switch the model an entry exports (e.g. ANTHROPIC_MODEL)
+    Model {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Model ID (omit to show the current model)
+        #[arg(value_name = "MODEL")]
+        model: Option<String>,
+
+        /// Variable to set instead of the detected model variable
+        #[arg(long, value_name = "VAR")]
+        var: Option<String>,
+    },
+
+    /// Work with an entry's secret value
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+
+    /// Entries usable with `ccm use` without the PIN (low-sensitivity only)
+    Quick {
+        #[command(subcommand)]
+        action: QuickAction,
+    },
+
+    /// Copy an entry (mappings, tags and notes) under a new name
+    #[command(visible_aliases = ["copy", "cp"])]
+    Clone {
+        /// Entry to copy
+        #[arg(value_name = "SOURCE")]
+        source: String,
+
+        /// Name of the new entry
+        #[arg(value_name = "DEST")]
+        destination: String,
+
+        /// Use a different secret for the copy ("-" reads from stdin)
+        #[arg(short = 's', long = "secret", value_name = "VALUE")]
+        secret: Option<String>,
     },
 
     /// Delete one or more entries
     #[command(visible_aliases = ["del", "rm"])]
     Delete {
-        /// Entry names to delete (can specify multiple)
+        /// Entry names to delete (can specify multiple); quoted globs such as
+        /// 'test-*' delete every matching entry after listing them
         #[arg(value_name = "NAME")]
         names: Vec<String>,
 
         /// Skip confirmation (use with caution)
         #[arg(long)]
         force: bool,
+
+        /// Delete locked entries too (see ccm lock)
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// Protect entries from deletion and secret changes
+    ///
+    /// delete, update --secret, rollback and secret edit refuse a locked
+    /// entry unless given --force-unlock.
+    Lock {
+        /// Entry names
+        #[arg(value_name = "NAME", required = true)]
+        names: Vec<String>,
+    },
+
+    /// Remove the protection added by ccm lock
+    Unlock {
+        /// Entry names
+        #[arg(value_name = "NAME", required = true)]
+        names: Vec<String>,
+    },
+
+    /// Pin an entry as a favorite, or unpin it if it already is one
+    ///
+    /// Favorites are listed first (marked ★); list --fav shows only them.
+    Fav {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
     },
 
     /// Set environment variables for an entry
@@ -191,6 +416,156 @@ enum Commands {
         /// Quiet mode
         #[arg(short, long)]
         quiet: bool,
+
+        /// Check that the entry's base URLs respond (HEAD, 3s timeout)
+        /// `ccm config use.health-check true` does this on every use
+        #[arg(long, conflicts_with = "no_check")]
+        check: bool,
+
+        /// Skip the endpoint check even if use.health-check is on
+        #[arg(long)]
+        no_check: bool,
+
+        /// Print statements setting the variables in SHELL instead of
+        /// editing its rc file (what the `ccm shell-init` function evals)
+        #[arg(long, value_name = "SHELL")]
+        shell: Option<clap_complete::Shell>,
+    },
+
+    /// Run a command with entries' variables in its environment
+    ///
+    /// Without --profile the entries come from the nearest .ccm.toml
+    /// (its `profile` key). Nothing is written to shell config files.
+    Run {
+        /// Entry to inject (repeat for several; later ones win on clashes)
+        #[arg(short, long = "profile", value_name = "NAME")]
+        profiles: Vec<String>,
+
+        /// Command and its arguments (after --)
+        #[arg(
+            value_name = "COMMAND",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+
+    /// Run an npm script with the entries .ccm.toml maps to it
+    #[command(name = "npm-run")]
+    NpmRun {
+        /// Script name from package.json
+        #[arg(value_name = "SCRIPT")]
+        script: String,
+
+        /// Arguments passed on to the script
+        #[arg(
+            value_name = "ARGS",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        args: Vec<String>,
+    },
+
+    /// Run cargo with the entries .ccm.toml maps to the subcommand
+    Cargo {
+        /// Arguments passed to cargo (e.g. test --workspace)
+        #[arg(
+            value_name = "ARGS",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        args: Vec<String>,
+    },
+
+    /// Trust a .ccm.toml as it is now, so run, npm-run and cargo use it
+    ///
+    /// The path and a hash of the content are recorded; after any change the
+    /// file is refused until it is allowed again.
+    Allow {
+        /// The .ccm.toml or its directory (default: the nearest one)
+        #[arg(value_name = "PATH")]
+        path: Option<String>,
+
+        /// Stop trusting it
+        #[arg(long)]
+        revoke: bool,
+    },
+
+    /// Start an AI CLI (claude, codex, gemini, aider) with an entry's keys
+    ///
+    /// The entry's key, base URL and model are set in the variables the tool
+    /// reads, for that process only. Example: ccm launch deepseek claude
+    Launch {
+        /// Entry to use
+        #[arg(value_name = "ENTRY")]
+        name: String,
+
+        /// Tool to start: claude, codex, gemini or aider
+        #[arg(value_name = "TOOL")]
+        tool: String,
+
+        /// Arguments passed on to the tool
+        #[arg(
+            value_name = "ARGS",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        args: Vec<String>,
+    },
+
+    /// Run docker with an entry's variables passed into the container
+    ///
+    /// Adds `--env NAME` for each variable after run/create/exec; the values
+    /// travel in docker's environment, never on its command line or on disk.
+    /// Example: ccm docker openai -- run --rm -it python:3 python
+    Docker {
+        /// Entry to inject
+        #[arg(value_name = "ENTRY")]
+        name: String,
+
+        /// Docker arguments (after --)
+        #[arg(
+            value_name = "ARGS",
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            required = true
+        )]
+        args: Vec<String>,
+    },
+
+    /// Pull entries from, or push them to, an external secret store
+    Sync {
+        #[command(subcommand)]
+        provider: SyncProvider,
+    },
+
+    /// Send entries to another machine on the local network
+    ///
+    /// The sender shows a one-time code; the receiver types it, and the two
+    /// machines agree on a key from it (SPAKE2) before the entries are sent,
+    /// encrypted. A wrong code ends the transfer.
+    Transfer {
+        #[command(subcommand)]
+        action: TransferAction,
+    },
+
+    /// Hand an entry's variables to tools that read an env file
+    Env {
+        #[command(subcommand)]
+        action: EnvAction,
+    },
+
+    /// SSH private keys: store them encrypted, write them out when a tool
+    /// needs a file, or load them straight into ssh-agent
+    Ssh {
+        #[command(subcommand)]
+        action: SshAction,
+    },
+
+    /// Switch Claude Code to an entry (settings.json)
+    Claude {
+        #[command(subcommand)]
+        action: ClaudeAction,
     },
 
     /// Authentication management (login, logout, change PIN)
@@ -202,13 +577,156 @@ enum Commands {
         /// New PIN (for 'change' action)
         #[arg(short, long, value_name = "PIN")]
         pin: Option<String>,
+
+        /// Key escrow file (for 'export-master-key' / 'import-master-key')
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<String>,
+
+        /// Paper key format for 'export-key': mnemonic (24 words) or base58
+        #[arg(long, value_name = "FORMAT", default_value = "mnemonic", value_parser = ["mnemonic", "base58"])]
+        format: String,
+    },
+
+    /// Check entries against their preset
+    ///
+    /// Reports a missing model variable, http:// or trailing-slash URLs and
+    /// deprecated variable names. --fix leaves http:// to private network
+    /// hosts as it is
+
+    Lint {
+        /// Entry name or glob pattern (all entries when omitted)
+        #[arg(value_name = "NAME")]
+        name: Option<String>,
+
+        /// Apply the suggested fixes
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Check that an entry's key works with a minimal authenticated request
+    ///
+    /// The provider comes from the entry's variables: Anthropic, OpenAI and
+    /// compatible APIs, Gemini, Azure OpenAI and Ollama list their models,
+    /// GitHub fetches /user and AWS calls STS GetCallerIdentity. Reports the
+    /// latency and what the API tells about the key (account, scopes, expiry).
+    Test {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Seconds to wait for the provider
+        #[arg(long, value_name = "SECONDS", default_value_t = 15)]
+        timeout: u64,
+    },
+
+    /// Show the credits or quota left on an entry's key
+    ///
+    /// Supported: OpenRouter (key usage and limit), DeepSeek (balance), OpenAI
+    /// (this month's costs; needs an admin key) and Anthropic (rate-limit
+    /// headers of a free token count request).
+    Usage {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Seconds to wait for the provider
+        #[arg(long, value_name = "SECONDS", default_value_t = 15)]
+        timeout: u64,
+    },
+
+    /// List the models an entry's key can use
+    ///
+    /// Asks the provider's model list endpoint (Anthropic, OpenAI and
+    /// compatible APIs, Gemini, Azure OpenAI, Ollama) with the entry's key
+    /// and base URL. The model the entry sets is marked with '*'.
+    Models {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Only show models whose id contains this text
+        #[arg(long, short = 'f', value_name = "TEXT")]
+        filter: Option<String>,
+
+        /// Seconds to wait for the provider
+        #[arg(long, value_name = "SECONDS", default_value_t = 15)]
+        timeout: u64,
+    },
+
+    /// Fill a template with entry variables and secrets
+    ///
+    /// Placeholders: {{ ENTRY.VAR }} (a variable of an entry, SECRET
+    /// resolved), {{ secret("ENTRY") }} and, with --entry, {{ VAR }}. A
+    /// backslash before {{ keeps it literal. The file is written with 0600
+    /// permissions.
+    Render {
+        /// Template file
+        #[arg(value_name = "TEMPLATE")]
+        template: PathBuf,
+
+        /// File to write
+        #[arg(short, long, value_name = "FILE", required_unless_present = "stdout")]
+        out: Option<PathBuf>,
+
+        /// Print the result instead of writing a file
+        #[arg(long, conflicts_with = "out")]
+        stdout: bool,
+
+        /// Entry of {{ VAR }} placeholders
+        #[arg(short, long, value_name = "NAME")]
+        entry: Option<String>,
     },
 
     /// Search entries
     Search {
-        /// Search query
+        /// Words to look for in names, tags, notes and variables, best match
+        /// first: each word matches as a prefix, "quoted words" as a phrase
+        #[arg(
+            value_name = "QUERY",
+            required_unless_present_any = ["domain", "tags", "fields"]
+        )]
+        query: Option<String>,
+
+        /// Only entries whose url is on this domain (subdomains included)
+        #[arg(long, value_name = "DOMAIN")]
+        domain: Option<String>,
+
+        /// Treat QUERY and --field values as regular expressions
+        /// (case-insensitive; results by name)
+        #[arg(long)]
+        regex: bool,
+
+        /// Only entries with this tag (repeat to require several)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Only entries with variable KEY (BASE_URL also finds OPENAI_BASE_URL)
+        /// whose value contains VALUE when given (repeat to require several)
+        #[arg(long = "field", value_name = "KEY[=VALUE]")]
+        fields: Vec<String>,
+
+        /// Print only the matching names, one per line
+        #[arg(long)]
+        name_only: bool,
+    },
+
+    /// Pick an entry with a fuzzy finder, then show, use or copy it
+    Pick {
+        /// Text to start filtering with
         #[arg(value_name = "QUERY")]
-        query: String,
+        query: Option<String>,
+
+        /// Use the picked entry (like ccm use)
+        #[arg(short = 'u', long = "use", conflicts_with_all = ["copy", "name_only"])]
+        use_entry: bool,
+
+        /// Copy the picked entry's secret to the clipboard
+        #[arg(short, long, conflicts_with = "name_only")]
+        copy: bool,
+
+        /// Print only the picked name, for $(ccm pick --name)
+        #[arg(long = "name")]
+        name_only: bool,
     },
 
     /// Import entries from file
@@ -217,14 +735,91 @@ enum Commands {
         #[arg(value_name = "FILE")]
         file: String,
 
-        /// Import format (json, csv)
-        #[arg(short, long, value_name = "FORMAT")]
+        /// Import format (json, csv, env); detected from the file when omitted.
+        /// YAML and TOML backups are read by 'ccm restore'
+        #[arg(short, long, value_name = "FORMAT", value_parser = ["json", "csv", "env"])]
         format: Option<String>,
+
+        /// .env import: variable holding the secret (prompts if omitted)
+        #[arg(long, value_name = "KEY")]
+        secret_var: Option<String>,
+
+        /// .env import: name of the created entry (defaults to the file or directory name)
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+
+        /// Tag imported entries with their source (e.g. chrome, edge); logins already
+        /// in the vault gain the tag too
+        #[arg(long, value_name = "TAG")]
+        source_tag: Option<String>,
+
+        /// What to do when an entry name already exists: rename (name-1, ...),
+        /// skip, overwrite (replaces the secret, asks first) or merge (keeps the
+        /// secret, adds missing metadata and tags)
+        #[arg(
+            long,
+            value_name = "STRATEGY",
+            default_value = "rename",
+            value_parser = ["rename", "skip", "overwrite", "merge"]
+        )]
+        on_conflict: String,
+
+        /// age identity file (from age-keygen) to decrypt an age-encrypted backup
+        #[arg(short, long, value_name = "FILE")]
+        identity: Option<String>,
+
+        /// Overwrite without asking for confirmation
+        #[arg(long)]
+        force: bool,
+
+        /// Let --on-conflict overwrite replace locked entries (they stay locked)
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// Restore a backup written by `ccm export`
+    ///
+    /// Unlike import, every entry comes back exactly as exported: name,
+    /// metadata, tags, notes and created/updated timestamps. Entries with the
+    /// same name are overwritten.
+    Restore {
+        /// Backup file (encrypted or plaintext; JSON, YAML or TOML)
+        #[arg(value_name = "FILE")]
+        file: String,
+
+        /// Delete the entries not in the backup (with their history), so the
+        /// vault matches it
+        #[arg(long)]
+        replace: bool,
+
+        /// age identity file (from age-keygen) for an age-encrypted backup
+        #[arg(short, long, value_name = "FILE")]
+        identity: Option<String>,
+
+        /// Skip the confirmation
+        #[arg(long)]
+        force: bool,
+
+        /// Overwrite or delete locked entries too (overwritten ones stay locked)
+        #[arg(long)]
+        force_unlock: bool,
+
+        /// What to restore from the backup, comma-separated: entries, config
+        /// (backups never hold presets; copy presets.toml or use
+        /// `ccm preset import`)
+        #[arg(
+            long,
+            value_name = "PARTS",
+            value_delimiter = ',',
+            default_value = "entries",
+            value_parser = ["entries", "config"]
+        )]
+        include: Vec<String>,
     },
 
     /// Export entries to file
     Export {
-        /// Entry name to export
+        /// Entry name or glob pattern to export (e.g. "openai-*")
         #[arg(value_name = "NAME")]
         name: Option<String>,
 
@@ -235,6 +830,81 @@ enum Commands {
         /// Export as plaintext (NOT encrypted - use with caution)
         #[arg(short, long)]
         decrypt: bool,
+
+        /// Only entries with this tag (repeat to require several)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Only entries without tags
+        #[arg(long, conflicts_with = "tags")]
+        untagged: bool,
+
+        /// Only entries in a group and its subgroups (work for work/github)
+        #[arg(long, value_name = "GROUP")]
+        group: Option<String>,
+
+        /// Output format: json, yaml or toml (backup file), csv (plaintext only)
+        /// or env (one .env file per entry); YAML and TOML backups are read by
+        /// 'ccm restore' only, not 'ccm import'
+        #[arg(long, value_name = "FORMAT", default_value = "json", value_parser = ["json", "yaml", "toml", "env", "csv"])]
+        format: String,
+
+        /// Leave the SECRET placeholder instead of the decrypted secret (env format only)
+        #[arg(long)]
+        no_secret: bool,
+
+        /// Encrypt to an age public key (age1...) instead of a password
+        /// (repeat for several recipients)
+        #[arg(
+            long = "age-recipient",
+            value_name = "PUBKEY",
+            conflicts_with = "decrypt"
+        )]
+        age_recipients: Vec<String>,
+
+        /// Encrypt to a GnuPG key (ID, fingerprint or email) with the local gpg
+        /// (repeat for several recipients)
+        #[arg(
+            long = "gpg-recipient",
+            value_name = "KEYID",
+            conflicts_with_all = ["decrypt", "age_recipients"]
+        )]
+        gpg_recipients: Vec<String>,
+
+        /// Also write these parts to the backup, comma-separated: config
+        /// (settings editable with `ccm config`); custom presets are not
+        /// exported, they live in presets.toml in the config directory
+        #[arg(long, value_name = "PARTS", value_delimiter = ',', value_parser = ["config"])]
+        include: Vec<String>,
+    },
+
+    /// Show the active entry and whether the current shell matches it
+    Status,
+
+    /// Show previous values of an entry's secret
+    History {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Show the secret values instead of fingerprints
+        #[arg(short, long)]
+        show: bool,
+    },
+
+    /// Restore a previous value of an entry's secret
+    Rollback {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Version to restore (see 'ccm history')
+        #[arg(long, value_name = "N")]
+        version: i64,
+
+        /// Roll back a locked entry (see ccm lock)
+        #[arg(long)]
+        force_unlock: bool,
     },
 
     /// Show statistics
@@ -246,7 +916,7 @@ enum Commands {
 
     /// Configuration management
     Config {
-        /// Configuration key
+        /// Configuration key ("show" lists all, "edit" opens them in $EDITOR)
         #[arg(value_name = "KEY")]
         key: Option<String>,
 
@@ -263,7 +933,31 @@ enum Commands {
         command: Option<String>,
     },
 
+    /// Show copy-pasteable workflows (relays, browser import, CI, backups)
+    Examples {
+        /// Topic to show (lists the topics when omitted)
+        #[arg(value_name = "TOPIC")]
+        topic: Option<String>,
+    },
+
+    /// Check the keyring, master key and database for problems
+    ///
+    /// --bundle also writes the diagnostics to a JSON file to attach to bug
+    /// reports. It never contains secrets: entry names are hashed, credential
+    /// settings redacted and the home directory shortened to ~.
+    Doctor {
+        /// Write a redacted diagnostics bundle
+        #[arg(long)]
+        bundle: bool,
+
+        /// Bundle path (default: ccm-doctor-<timestamp>.json in the current directory)
+        #[arg(short, long, value_name = "FILE", requires = "bundle")]
+        output: Option<String>,
+    },
+
     /// Show version information
+    ///
+    /// With --json, prints the build metadata as JSON (for bug reports and scripts)
     #[command(visible_aliases = ["ver", "v"])]
     Version,
 
@@ -272,12 +966,149 @@ enum Commands {
         #[command(subcommand)]
         action: PresetAction,
     },
-}
 
-#[derive(Subcommand, Debug)]
-enum PresetAction {
-    /// List all available presets
-    List,
+    /// Move the vault between machines
+    Vault {
+        #[command(subcommand)]
+        action: VaultAction,
+    },
+
+    /// Local snapshots of the encrypted database (backups/ next to it)
+    ///
+    /// Set `ccm config backup.auto change` (or `daily`) to take them
+    /// automatically after commands that change entries, and `backup.keep`
+    /// for how many to keep (default 10).
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Check the database for corruption and orphaned rows
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+
+    /// Background agent that keeps the vault unlocked for later commands
+    Agent {
+        #[command(subcommand)]
+        action: AgentAction,
+    },
+
+    /// Print a shell completion script (completes entry names too)
+    ///
+    /// bash: source <(ccm completion bash)
+    /// zsh:  ccm completion zsh > "${fpath[1]}/_ccm"
+    /// fish: ccm completion fish > ~/.config/fish/completions/ccm.fish
+    /// PowerShell: ccm completion powershell | Out-String | Invoke-Expression
+    #[command(visible_alias = "completions")]
+    Completion {
+        /// Shell to generate the script for
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print a `ccm` shell function that makes `ccm use` set variables in
+    /// the current shell, with the active entry in the prompt
+    ///
+    /// bash: eval "$(ccm shell-init bash)"   (in ~/.bashrc)
+    /// zsh:  eval "$(ccm shell-init zsh)"    (in ~/.zshrc)
+    /// fish: ccm shell-init fish | source    (in config.fish)
+    /// PowerShell: ccm shell-init powershell | Out-String | Invoke-Expression
+    #[command(name = "shell-init")]
+    ShellInit {
+        /// Shell to generate the function for
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+
+        /// Leave the prompt alone
+        #[arg(long)]
+        no_prompt: bool,
+    },
+
+    /// Answer JSON-RPC requests (list, search, get, use) for editors and tools
+    ///
+    /// --stdio: one JSON-RPC 2.0 message per line on stdin, replies on stdout.
+    /// --listen: share this vault's storage over HTTP with `ccm --remote`
+    /// clients, which encrypt secrets themselves. The vault is unlocked once
+    /// at startup: run an agent, or pass --pin-file or CCM_PIN.
+    Serve {
+        /// Use stdin/stdout as the transport
+        #[arg(long, conflicts_with = "listen")]
+        stdio: bool,
+
+        /// Serve the vault over HTTP on this address (e.g. 127.0.0.1:7700)
+        #[arg(long, value_name = "ADDR")]
+        listen: Option<String>,
+
+        /// PEM certificate chain, to serve HTTPS (with --tls-key)
+        #[arg(long, value_name = "FILE", requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+
+        /// PEM private key of --tls-cert
+        #[arg(long, value_name = "FILE", requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+
+        /// File holding the token clients must send (default: CCM_SERVER_TOKEN,
+        /// else a new token printed at startup)
+        #[arg(long, value_name = "FILE")]
+        token_file: Option<PathBuf>,
+
+        /// Connections --listen serves at once; others wait their turn
+        #[arg(long, value_name = "N", default_value_t = remote::server::DEFAULT_MAX_CONNECTIONS)]
+        max_connections: usize,
+    },
+
+    /// Model Context Protocol server on stdio, for AI assistants
+    ///
+    /// Tools: list_entries, search, get_env_for_entry. Secret values are
+    /// redacted unless `ccm config mcp.reveal-secrets true`.
+    /// Claude Code: claude mcp add ccm -- ccm mcp
+    Mcp,
+
+    /// Audit log of sensitive operations
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+}
+
+/// Options for generating a random secret (shared by add and update)
+#[derive(Args, Debug, Clone)]
+struct GenerateArgs {
+    /// Generate a random secret instead of supplying one
+    #[arg(short = 'g', long)]
+    generate: bool,
+
+    /// Length of the generated password
+    #[arg(long, value_name = "N", default_value_t = 32, requires = "generate")]
+    length: usize,
+
+    /// Include symbols in the generated password
+    #[arg(long, requires = "generate")]
+    symbols: bool,
+
+    /// Exclude uppercase letters from the generated password
+    #[arg(long, requires = "generate")]
+    no_uppercase: bool,
+
+    /// Exclude digits from the generated password
+    #[arg(long, requires = "generate")]
+    no_digits: bool,
+
+    /// Generate a diceware-style passphrase with N words instead
+    #[arg(long, value_name = "N", requires = "generate", conflicts_with_all = ["symbols", "no_uppercase", "no_digits"])]
+    words: Option<usize>,
+
+    /// Copy the generated secret to the clipboard instead of printing it
+    #[arg(long, requires = "generate")]
+    copy: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum PresetAction {
+    /// List all available presets
+    List,
 
     /// Show details of a specific preset
     Show {
@@ -285,6 +1116,620 @@ enum PresetAction {
         #[arg(value_name = "NAME")]
         name: String,
     },
+
+    /// Suggest the preset matching an entry's variables
+    Detect {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Add the missing preset variables to the entry
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// Add the presets of a TOML file to your own (presets.toml in the
+    /// config directory), replacing any with the same names
+    ///
+    /// Each preset is a [presets.NAME] table with env (field → variable),
+    /// optional defaults (field → value), required fields (the last one is
+    /// the secret; default ["token"]) and a description.
+    Import {
+        /// TOML file to import
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+
+    /// Save an entry's variables as a preset in presets.toml
+    ///
+    /// The secret is left out: its variable becomes the preset's required
+    /// field, and the other values become defaults. Add the next entry with
+    /// `ccm add NAME --preset PRESET`.
+    #[command(name = "save-from")]
+    SaveFrom {
+        /// Entry to copy the variables from
+        #[arg(value_name = "ENTRY")]
+        entry: String,
+
+        /// Preset name (default: the entry name)
+        #[arg(long, value_name = "PRESET")]
+        name: Option<String>,
+
+        /// Description shown by ccm preset list
+        #[arg(short, long, value_name = "TEXT")]
+        description: Option<String>,
+
+        /// Ask for this variable when adding an entry instead of keeping the
+        /// entry's value as the default (repeatable), e.g. an account ID.
+        /// Variables named like credentials (*_KEY, *_KEY_ID, *TOKEN*,
+        /// *SECRET*, *PASSWORD*) are always asked for
+        #[arg(long, value_name = "VAR")]
+        ask: Vec<String>,
+
+        /// Replace an existing preset of the same name
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Generate env variables for an OpenAI/Anthropic-compatible relay
+    Relay {
+        /// Relay base URL
+        #[arg(long, value_name = "URL")]
+        base_url: String,
+
+        /// API style the relay speaks
+        #[arg(long, value_name = "STYLE", default_value = "openai", value_parser = ["openai", "anthropic"])]
+        style: String,
+
+        /// How the key is sent (anthropic style: bearer or x-api-key)
+        #[arg(long, value_name = "MODE", value_parser = ["bearer", "x-api-key"])]
+        auth: Option<String>,
+
+        /// Default model
+        #[arg(long, value_name = "MODEL")]
+        model: Option<String>,
+
+        /// Save the profile as a new entry with this name
+        #[arg(long, value_name = "NAME")]
+        save: Option<String>,
+
+        /// Secret for the saved entry ("-" reads from stdin, prompts if omitted)
+        #[arg(short = 's', long = "secret", value_name = "VALUE", requires = "save")]
+        secret: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum VaultAction {
+    /// Bundle the database and master key, or restore such a bundle
+    /// (interactive when neither --out nor --in is given)
+    Transfer {
+        /// Write a transfer bundle to FILE (source machine)
+        #[arg(long, value_name = "FILE", conflicts_with = "input")]
+        out: Option<String>,
+
+        /// Restore the vault from bundle FILE (target machine)
+        #[arg(long = "in", value_name = "FILE")]
+        input: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SecretAction {
+    /// Edit the secret in $VISUAL/$EDITOR (for multi-line secrets such as PEM
+    /// keys or JSON service accounts)
+    Edit {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Edit the secret of a locked entry (see ccm lock)
+        #[arg(long)]
+        force_unlock: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum QuickAction {
+    /// Make an entry usable without the PIN
+    Add {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// Require the PIN for an entry again
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+
+    /// List quick-access entries
+    #[command(visible_alias = "ls")]
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupAction {
+    /// Snapshot the database now (and rotate old snapshots)
+    Now,
+
+    /// List snapshots, newest first
+    #[command(visible_alias = "ls")]
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum AgentAction {
+    /// Unlock the vault (asking for the PIN) and start the agent
+    Start {
+        /// Stay in the foreground instead of detaching
+        #[arg(long)]
+        foreground: bool,
+    },
+
+    /// Stop the running agent (the key is zeroized)
+    Stop,
+
+    /// Show whether an agent is running
+    Status,
+
+    /// Run the agent, reading the key from stdin (used by `start`)
+    #[command(hide = true)]
+    Serve,
+}
+
+#[derive(Subcommand, Debug)]
+enum ClaudeAction {
+    /// Point Claude Code's settings.json at an entry
+    ///
+    /// Writes the entry's base URL and model to the env block and sets
+    /// apiKeyHelper to `ccm claude helper <ENTRY>`, so the key stays in the vault
+    Use {
+        /// Entry to use
+        #[arg(value_name = "ENTRY")]
+        name: String,
+
+        /// Write the key itself into the env block instead of using apiKeyHelper
+        #[arg(long)]
+        inline: bool,
+
+        /// Settings file to update (default: ~/.claude/settings.json,
+        /// or $CLAUDE_CONFIG_DIR/settings.json)
+        #[arg(long, value_name = "FILE")]
+        settings: Option<PathBuf>,
+    },
+
+    /// Print an entry's key, for Claude Code's apiKeyHelper
+    Helper {
+        /// Entry name
+        #[arg(value_name = "ENTRY")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SyncProvider {
+    /// HashiCorp Vault KV version 2
+    ///
+    /// Each entry is one secret under PATH (mount and prefix, e.g. secret/ccm).
+    /// The token comes from VAULT_TOKEN or ~/.vault-token, the namespace from
+    /// VAULT_NAMESPACE.
+    Vault {
+        /// pull: Vault to ccm; push: ccm to Vault
+        #[arg(value_name = "MODE", value_parser = ["pull", "push"])]
+        mode: String,
+
+        /// Vault address (default: $VAULT_ADDR)
+        #[arg(long, value_name = "URL")]
+        addr: Option<String>,
+
+        /// KV v2 mount and prefix
+        #[arg(long, value_name = "PATH", default_value = "secret/ccm")]
+        path: String,
+
+        #[command(flatten)]
+        options: SyncArgs,
+    },
+
+    /// AWS Systems Manager Parameter Store or Secrets Manager
+    ///
+    /// Each entry is one SecureString parameter (or secret) under PREFIX.
+    /// Credentials and region come from an entry made with the aws preset,
+    /// else from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_SESSION_TOKEN
+    /// and AWS_REGION.
+    Aws {
+        /// pull: AWS to ccm; push: ccm to AWS
+        #[arg(value_name = "MODE", value_parser = ["pull", "push"])]
+        mode: String,
+
+        /// Parameter path or secret name prefix
+        #[arg(long, value_name = "PREFIX", default_value = "/ccm/")]
+        prefix: String,
+
+        /// ssm (Parameter Store) or secretsmanager
+        #[arg(long, value_name = "SERVICE", default_value = "ssm", value_parser = ["ssm", "secretsmanager"])]
+        service: String,
+
+        /// Entry holding the AWS credentials (aws preset)
+        #[arg(long, value_name = "ENTRY")]
+        credentials: Option<String>,
+
+        /// Region (default: the credentials' region, AWS_REGION)
+        #[arg(long, value_name = "REGION")]
+        region: Option<String>,
+
+        #[command(flatten)]
+        options: SyncArgs,
+    },
+
+    /// Azure Key Vault
+    ///
+    /// Each entry is one secret named PREFIX + entry name. The token comes
+    /// from AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, else
+    /// from `az login`.
+    Azure {
+        /// pull: Key Vault to ccm; push: ccm to Key Vault
+        #[arg(value_name = "MODE", value_parser = ["pull", "push"])]
+        mode: String,
+
+        /// Key vault name or URL
+        #[arg(long, value_name = "VAULT")]
+        vault: String,
+
+        /// Secret name prefix (letters, digits, dashes)
+        #[arg(long, value_name = "PREFIX", default_value = "ccm-")]
+        prefix: String,
+
+        #[command(flatten)]
+        options: SyncArgs,
+    },
+
+    /// Google Cloud Secret Manager
+    ///
+    /// Each entry is one secret named PREFIX + entry name. The token comes
+    /// from GOOGLE_OAUTH_ACCESS_TOKEN, else from `gcloud auth login`.
+    Gcp {
+        /// pull: Secret Manager to ccm; push: ccm to Secret Manager
+        #[arg(value_name = "MODE", value_parser = ["pull", "push"])]
+        mode: String,
+
+        /// Project ID (default: $GOOGLE_CLOUD_PROJECT)
+        #[arg(long, value_name = "PROJECT")]
+        project: Option<String>,
+
+        /// Secret ID prefix (letters, digits, dashes, underscores)
+        #[arg(long, value_name = "PREFIX", default_value = "ccm-")]
+        prefix: String,
+
+        #[command(flatten)]
+        options: SyncArgs,
+    },
+
+    /// A git repository of encrypted entries, shared through any git remote
+    ///
+    /// The repository lives in sync/ in the data directory, one file per
+    /// entry with its secret encrypted by the master key (machines must
+    /// share it: ccm auth export-key / import-key). pull merges three ways;
+    /// entries changed on both sides are merged field by field and conflicts
+    /// keep the local value.
+    Git {
+        /// init: create the repository; push: commit and push the vault;
+        /// pull: fetch and merge into the vault
+        #[arg(value_name = "MODE", value_parser = ["init", "push", "pull"])]
+        mode: String,
+
+        /// Remote to sync through (init; sets `origin`)
+        #[arg(long, value_name = "URL")]
+        origin: Option<String>,
+
+        /// Show what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Let pull delete locked entries or change their secret (they stay
+        /// locked)
+        #[arg(long)]
+        force_unlock: bool,
+    },
+
+    /// A WebDAV folder (Nextcloud, a NAS, ...) holding encrypted backups
+    ///
+    /// push uploads the vault as a new encrypted version (like ccm export);
+    /// pull applies the newest one. The password comes from CCM_SYNC_PASSWORD
+    /// or a prompt; the WebDAV password from the --credentials entry or
+    /// CCM_WEBDAV_PASSWORD.
+    Webdav {
+        /// push: upload a version; pull: apply one to the vault;
+        /// versions: list them
+        #[arg(value_name = "MODE", value_parser = ["push", "pull", "versions"])]
+        mode: String,
+
+        /// Folder URL
+        #[arg(long, value_name = "URL")]
+        url: String,
+
+        /// WebDAV user name
+        #[arg(long, value_name = "USER")]
+        user: Option<String>,
+
+        /// Entry whose secret is the WebDAV password
+        #[arg(long, value_name = "ENTRY", requires = "user")]
+        credentials: Option<String>,
+
+        #[command(flatten)]
+        options: BlobSyncArgs,
+    },
+
+    /// An S3-compatible bucket (AWS, MinIO, R2, ...) holding encrypted backups
+    ///
+    /// Like webdav, with versions stored under PREFIX. Credentials come from
+    /// an entry made with the aws preset, else from AWS_ACCESS_KEY_ID,
+    /// AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN.
+    S3 {
+        /// push: upload a version; pull: apply one to the vault;
+        /// versions: list them
+        #[arg(value_name = "MODE", value_parser = ["push", "pull", "versions"])]
+        mode: String,
+
+        /// Bucket name
+        #[arg(long, value_name = "BUCKET")]
+        bucket: String,
+
+        /// Key prefix of the versions
+        #[arg(long, value_name = "PREFIX", default_value = "ccm/")]
+        prefix: String,
+
+        /// Endpoint of an S3-compatible service (default: $AWS_ENDPOINT_URL,
+        /// else AWS)
+        #[arg(long, value_name = "URL")]
+        endpoint: Option<String>,
+
+        /// Region (default: the credentials' region, AWS_REGION)
+        #[arg(long, value_name = "REGION")]
+        region: Option<String>,
+
+        /// Entry holding the AWS credentials (aws preset)
+        #[arg(long, value_name = "ENTRY")]
+        credentials: Option<String>,
+
+        #[command(flatten)]
+        options: BlobSyncArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum EnvAction {
+    /// Write an entry's variables to a private .env file and print its path
+    ///
+    /// The file (0600) goes to a private directory in $XDG_RUNTIME_DIR
+    /// (/run/user/UID, kept in memory) or the temp directory. With --ttl,
+    /// ccm deletes it once the time is up:
+    /// docker run --env-file "$(ccm env file openai --ttl 10m)" ...
+    File {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Write here instead
+        #[arg(short, long, value_name = "PATH")]
+        path: Option<PathBuf>,
+
+        /// Delete the file after this long (e.g. 300, 30s, 15m, 2h)
+        #[arg(short, long, value_name = "DURATION")]
+        ttl: Option<String>,
+    },
+
+    /// Delete an env file when its TTL is up (run by `file --ttl`)
+    #[command(hide = true)]
+    Expire {
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Seconds to wait
+        #[arg(long)]
+        after: u64,
+
+        /// Modification time of the file (ms since the epoch); a file
+        /// written again since is left alone
+        #[arg(long)]
+        written: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SshAction {
+    /// Store a private key file as a new entry (tagged ssh)
+    Add {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Private key file (its .pub file, if any, is kept as metadata)
+        #[arg(long, value_name = "FILE")]
+        key_file: PathBuf,
+
+        /// More tags, comma-separated
+        #[arg(long, value_name = "TAGS")]
+        tags: Option<String>,
+
+        /// Notes for the entry
+        #[arg(short = 'n', long, value_name = "NOTES")]
+        notes: Option<String>,
+    },
+
+    /// Write a key to a file only you can read and print its path
+    ///
+    /// The default path is in a private directory under $XDG_RUNTIME_DIR
+    /// (else the temp directory), e.g. ssh -i "$(ccm ssh write github)"
+    Write {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Where to write the key
+        #[arg(short, long, value_name = "FILE")]
+        path: Option<PathBuf>,
+    },
+
+    /// Load a key into ssh-agent through `ssh-add -`, without writing it to disk
+    AddAgent {
+        /// Entry name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Remove the key from the agent after this many seconds
+        #[arg(short = 't', long, value_name = "SECONDS")]
+        lifetime: Option<u32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TransferAction {
+    /// Show a code and wait for the receiver
+    Send {
+        /// Entry names or glob patterns
+        #[arg(
+            value_name = "NAME",
+            required_unless_present = "all",
+            conflicts_with = "all"
+        )]
+        names: Vec<String>,
+
+        /// Send every entry
+        #[arg(long)]
+        all: bool,
+
+        /// TCP port to listen on (default: any free port)
+        #[arg(long, value_name = "PORT", default_value_t = 0)]
+        port: u16,
+    },
+
+    /// Receive entries from a sender
+    Receive {
+        /// The code the sender shows (asked for when omitted)
+        #[arg(value_name = "CODE")]
+        code: Option<String>,
+
+        /// Sender address, when broadcasts do not get through (shown by the sender)
+        #[arg(long, value_name = "ADDRESS:PORT")]
+        connect: Option<String>,
+
+        /// Replace entries that differ
+        #[arg(long, conflicts_with = "merge")]
+        overwrite: bool,
+
+        /// For entries that differ, keep whichever side was updated last
+        #[arg(long)]
+        merge: bool,
+
+        /// Replace locked entries too (they stay locked)
+        #[arg(long)]
+        force_unlock: bool,
+    },
+}
+
+/// Options of the encrypted backup providers (webdav, s3)
+#[derive(Args, Debug, Clone)]
+struct BlobSyncArgs {
+    /// push: encrypt to this age public key instead of a password (repeatable)
+    #[arg(long = "age-recipient", value_name = "PUBKEY")]
+    age_recipients: Vec<String>,
+
+    /// pull: age identity file for versions encrypted to age recipients
+    #[arg(short, long, value_name = "FILE")]
+    identity: Option<String>,
+
+    /// pull: apply this version instead of the newest
+    #[arg(long, value_name = "NAME")]
+    version: Option<String>,
+
+    /// pull: replace entries that differ
+    #[arg(long, conflicts_with = "merge")]
+    overwrite: bool,
+
+    /// pull: for entries that differ, keep whichever side was updated last
+    #[arg(long)]
+    merge: bool,
+
+    /// pull: replace locked entries too (they stay locked)
+    #[arg(long)]
+    force_unlock: bool,
+
+    /// push: versions to keep (0 keeps all)
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    keep: usize,
+
+    /// Show what would change without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Options shared by all sync providers
+#[derive(Args, Debug, Clone)]
+struct SyncArgs {
+    /// Push only this entry (repeatable; default: all entries)
+    #[arg(long = "entry", value_name = "NAME")]
+    entries: Vec<String>,
+
+    /// Replace entries (pull) or remote secrets (push) that differ
+    #[arg(long)]
+    overwrite: bool,
+
+    /// pull: replace locked entries too (they stay locked)
+    #[arg(long)]
+    force_unlock: bool,
+
+    /// Show what would change without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum DbAction {
+    /// Run SQLite's integrity check and look for entries without a secret
+    /// (and secrets without an entry)
+    Check,
+
+    /// Remove orphaned entries, secrets and history in one transaction
+    /// (a snapshot is taken first)
+    Repair {
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Check every entry row against its MAC to find rows changed outside ccm
+    Verify {
+        /// Accept the changed rows as they are now and sign them again
+        /// (a snapshot is taken first)
+        #[arg(long)]
+        accept: bool,
+
+        /// Skip the confirmation prompt of --accept
+        #[arg(short, long, requires = "accept")]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditAction {
+    /// Export audit events for ingestion into a SIEM (Splunk, ELK, ...)
+    Export {
+        /// Output format: jsonl (one JSON object per line) or cef (ArcSight CEF)
+        #[arg(long, value_name = "FORMAT", default_value = "jsonl", value_parser = ["jsonl", "cef"])]
+        format: String,
+
+        /// Only events since a relative age (30d, 12h, 2w), a date or an RFC 3339 timestamp
+        #[arg(long, value_name = "WHEN")]
+        since: Option<String>,
+
+        /// Write to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
 }
 
 #[tokio::main]
@@ -294,37 +1739,149 @@ async fn main() -> Result<()> {
         env_logger::init();
     }
 
-    let cli = Cli::parse();
+    // Usage errors exit with EX_USAGE instead of clap's 2 (which means "not found" here)
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        if e.use_stderr() {
+            let _ = e.print();
+            std::process::exit(utils::EXIT_USAGE);
+        }
+        e.exit()
+    });
+
+    utils::output::init(utils::output::OutputOptions {
+        json: cli.json,
+        quiet: cli.quiet,
+        no_color: cli.no_color,
+    });
+
+    let started = std::time::Instant::now();
+    if cli.timings {
+        utils::timings::enable();
+    }
+
+    if let Some(path) = &cli.pin_file {
+        auth::set_pin_file(path);
+    }
+
+    if let Some(url) = &cli.remote {
+        remote::set_remote(url);
+    }
+
+    if let Some(path) = &cli.db {
+        if let Err(e) = db::set_db_path(path) {
+            utils::output::print_error(&e);
+            std::process::exit(e.exit_code());
+        }
+    }
 
     // Initialize system
     if let Err(e) = core::initialization::initialize().await {
-        eprintln!("{} {}", "Error:".red(), e);
-        std::process::exit(1);
+        let e = e.downcast::<utils::CcmError>().unwrap_or_else(Into::into);
+        utils::output::print_error(&e);
+        std::process::exit(e.exit_code());
+    }
+
+    // Warn about stale exported variables (use/status deal with them themselves)
+    let refreshes_active = matches!(cli.command, Commands::Use { .. } | Commands::Status);
+    if !refreshes_active && !cli.reapply && !cli.quiet {
+        env::print_changed_active_entry_notice();
     }
+    let reapply = cli.reapply;
+    // stdout belongs to the protocol
+    let serves = matches!(cli.command, Commands::Serve { .. } | Commands::Mcp);
 
     // Execute command
     let result = match cli.command {
+        Commands::Init => commands::init::execute(cli.command).await,
+        Commands::Reset { .. } => commands::reset::execute(cli.command).await,
         Commands::Add { .. } => commands::add::execute(cli.command).await,
         Commands::Get { .. } => commands::get::execute(cli.command).await,
         Commands::List { .. } => commands::list::execute(cli.command).await,
         Commands::Update { .. } => commands::update::execute(cli.command).await,
+        Commands::Model { .. } => commands::model::execute(cli.command).await,
+        Commands::Clone { .. } => commands::clone::execute(cli.command).await,
+        Commands::Secret { .. } => commands::secret::execute(cli.command).await,
+        Commands::Quick { .. } => commands::quick::execute(cli.command).await,
         Commands::Delete { .. } => commands::delete::execute(cli.command).await,
+        Commands::Lock { .. } | Commands::Unlock { .. } => {
+            commands::lock::execute(cli.command).await
+        }
+        Commands::Fav { .. } => commands::fav::execute(cli.command).await,
         Commands::Use { .. } => commands::use_cmd::execute(cli.command).await,
+        Commands::Run { .. } | Commands::NpmRun { .. } | Commands::Cargo { .. } => {
+            commands::run::execute(cli.command).await
+        }
+        Commands::Allow { .. } => commands::allow::execute(cli.command).await,
+        Commands::Launch { .. } => commands::launch::execute(cli.command).await,
+        Commands::Sync { .. } => commands::sync::execute(cli.command).await,
+        Commands::Transfer { .. } => commands::transfer::execute(cli.command).await,
+        Commands::Env { .. } => commands::env_cmd::execute(cli.command).await,
+        Commands::Ssh { .. } => commands::ssh::execute(cli.command).await,
+        Commands::Claude { .. } => commands::claude::execute(cli.command).await,
+        Commands::Docker { .. } => commands::docker::execute(cli.command).await,
         Commands::Auth { .. } => commands::auth::execute(cli.command).await,
         Commands::Search { .. } => commands::search::execute(cli.command).await,
+        Commands::Pick { .. } => commands::pick::execute(cli.command).await,
+        Commands::Lint { .. } => commands::lint::execute(cli.command).await,
+        Commands::Test { .. } => commands::test_cmd::execute(cli.command).await,
+        Commands::Models { .. } => commands::models::execute(cli.command).await,
+        Commands::Usage { .. } => commands::usage::execute(cli.command).await,
+        Commands::Render { .. } => commands::render::execute(cli.command).await,
         Commands::Import { .. } => commands::import::execute(cli.command).await,
         Commands::Export { .. } => commands::export::execute(cli.command).await,
+        Commands::Restore { .. } => commands::restore::execute(cli.command).await,
+        Commands::Status => commands::status::execute(cli.command).await,
+        Commands::History { .. } => commands::history::execute(cli.command).await,
+        Commands::Rollback { .. } => commands::rollback::execute(cli.command).await,
         Commands::Stats { .. } => commands::stats::execute(cli.command).await,
         Commands::Config { .. } => commands::config::execute(cli.command).await,
         Commands::Help { .. } => commands::help::execute(cli.command).await,
+        Commands::Examples { .. } => commands::examples::execute(cli.command).await,
         Commands::Version => commands::version::execute(cli.command).await,
+        Commands::Doctor { .. } => commands::doctor::execute(cli.command).await,
         Commands::Preset { .. } => commands::preset::execute(cli.command).await,
+        Commands::Vault { .. } => commands::vault::execute(cli.command).await,
+        Commands::Audit { .. } => commands::audit::execute(cli.command).await,
+        Commands::Backup { .. } => commands::backup::execute(cli.command).await,
+        Commands::Db { .. } => commands::db::execute(cli.command).await,
+        Commands::Agent { .. } => commands::agent::execute(cli.command).await,
+        Commands::Completion { .. } => commands::completion::execute(cli.command).await,
+        Commands::ShellInit { .. } => commands::shell_init::execute(cli.command).await,
+        Commands::Serve { .. } => commands::serve::execute(cli.command).await,
+        Commands::Mcp => commands::mcp::execute(cli.command).await,
+    };
+
+    if result.is_ok() {
+        core::initialization::run_auto_backup();
+        if !utils::output::is_quiet() && !serves {
+            core::initialization::run_report();
+        }
+    }
+
+    let result = match result {
+        Ok(()) if reapply => commands::use_cmd::reapply_active_entry().await,
+        other => other,
     };
 
+    if utils::timings::is_enabled() {
+        utils::timings::print_report(started.elapsed());
+    }
+
     if let Err(e) = result {
-        eprintln!("{} {}", "Error:".red(), e);
-        std::process::exit(1);
+        utils::output::print_error(&e);
+        std::process::exit(e.exit_code());
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+}
@@ -0,0 +1,268 @@
+// Model Context Protocol server (ccm mcp)
+//
+// Lets AI assistants discover entries and read the environment an entry sets,
+// over JSON-RPC on stdio (see `crate::rpc`). Secret values are replaced by
+// `REDACTED` unless `mcp.reveal-secrets` is true; entry names, tags, notes and
+// literal values (base URLs, model names) are always visible.
+//
+// Tools:
+//   list_entries       {tag?}    entries without secrets
+//   search             {query}   same, matching name, metadata, tags or notes
+//   get_env_for_entry  {name}    the variables `ccm use <name>` would set
+
+use crate::db::EntryFilter;
+use crate::rpc::{self, RpcError};
+use crate::types::Entry;
+use crate::utils::{parse_bool, CcmError, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Setting: true to let `get_env_for_entry` return secret values
+pub const REVEAL_SECRETS_KEY: &str = "mcp.reveal-secrets";
+
+/// Shown instead of a secret value
+pub const REDACTED: &str = "REDACTED";
+
+/// Tool error while the vault is locked (no prompt is possible on stdio)
+const LOCKED_HINT: &str = "The ccm vault is locked. Ask the user to run `ccm agent start` \
+                           in a terminal, then try again.";
+
+/// Protocol revisions this server speaks, newest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Answer one MCP request or notification
+pub fn handle(method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+    match method {
+        "initialize" => Ok(initialize(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => {
+            let call: ToolCall = rpc::params(params)?;
+            let result = call_tool(&call.name, call.arguments)?;
+            Ok(match result {
+                Ok(value) => tool_result(&format!("{:#}", value), false),
+                Err(CcmError::PinRequired) => tool_result(LOCKED_HINT, true),
+                Err(e) => tool_result(&e.to_string(), true),
+            })
+        }
+        // Notifications (initialized, cancelled, ...) need no answer
+        m if m.starts_with("notifications/") => Ok(Value::Null),
+        _ => Err(RpcError::method_not_found(method)),
+    }
+}
+
+/// Agree on a protocol revision: the client's if supported, else our newest
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "ccm", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Entries are named credential profiles (API keys with their base URLs \
+                         and models). Use list_entries or search to find one and \
+                         get_env_for_entry for the environment variables it sets.",
+    })
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "list_entries",
+            "description": "List the entries in the ccm vault (names, tags, notes and \
+                            environment variable names; no secrets)",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "tag": { "type": "string", "description": "Only entries with this tag" }
+                },
+            },
+        },
+        {
+            "name": "search",
+            "description": "Search entries by name, environment variable, tag or notes",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "query": { "type": "string" } },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "get_env_for_entry",
+            "description": "The environment variables an entry sets (as `ccm use` would). \
+                            Secret values are REDACTED unless the user allowed them with \
+                            `ccm config mcp.reveal-secrets true`",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "name": { "type": "string", "description": "Entry name" } },
+                "required": ["name"],
+            },
+        },
+    ])
+}
+
+#[derive(Deserialize)]
+struct ToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct ListArgs {
+    tag: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    query: String,
+}
+
+#[derive(Deserialize)]
+struct EnvArgs {
+    name: String,
+}
+
+/// Run a tool; the inner error is reported to the model as a failed call,
+/// the outer one (unknown tool, bad arguments) as a protocol error
+fn call_tool(name: &str, arguments: Value) -> std::result::Result<Result<Value>, RpcError> {
+    Ok(match name {
+        "list_entries" => {
+            let args: ListArgs = rpc::params(arguments)?;
+            let filter = EntryFilter {
+                tags: args.tag.into_iter().collect(),
+                ..EntryFilter::default()
+            };
+            crate::secrets::list_entries_filtered(&filter).map(|entries| {
+                let mut entries: Vec<_> = entries.into_iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                entries
+                    .iter()
+                    .map(|(name, entry)| entry_json(name, entry))
+                    .collect()
+            })
+        }
+        "search" => {
+            let args: SearchArgs = rpc::params(arguments)?;
+            crate::secrets::search_entries(&args.query, &EntryFilter::default()).map(|results| {
+                results
+                    .iter()
+                    .map(|(name, entry)| entry_json(name, entry))
+                    .collect()
+            })
+        }
+        "get_env_for_entry" => {
+            let args: EnvArgs = rpc::params(arguments)?;
+            env_for_entry(&args.name)
+        }
+        _ => return Err(RpcError::invalid_params(format!("unknown tool '{}'", name))),
+    })
+}
+
+fn env_for_entry(name: &str) -> Result<Value> {
+    let reveal = crate::db::get_database()?
+        .get_setting::<String>(REVEAL_SECRETS_KEY)?
+        .and_then(|v| parse_bool(&v))
+        .unwrap_or(false);
+
+    let (entry, secret) = if reveal {
+        let (entry, secret) = crate::secrets::use_entry(name)?;
+        (entry, Some(secret))
+    } else {
+        (crate::secrets::get_entry(name)?, None)
+    };
+    Ok(json!({
+        "name": name,
+        "env": env_map(&entry, secret.as_deref()),
+        "secretsRedacted": !reveal,
+    }))
+}
+
+/// An entry's variables, with the secret (None: `REDACTED`) substituted
+fn env_map(entry: &Entry, secret: Option<&str>) -> BTreeMap<String, String> {
+    crate::env::get_env_mappings_with_secret(entry, secret.unwrap_or(REDACTED))
+        .into_iter()
+        .collect()
+}
+
+/// An entry without its secret
+fn entry_json(name: &str, entry: &Entry) -> Value {
+    let mut variables: Vec<&String> = entry.metadata.keys().collect();
+    variables.sort();
+    json!({
+        "name": name,
+        "domain": entry.domain(),
+        "variables": variables,
+        "tags": entry.tags,
+        "notes": entry.notes,
+    })
+}
+
+fn tool_result(text: &str, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_initialize() {
+        let reply = handle("initialize", json!({ "protocolVersion": "2024-11-05" })).unwrap();
+        assert_eq!(reply["protocolVersion"], "2024-11-05");
+        assert_eq!(reply["serverInfo"]["name"], "ccm");
+
+        let reply = handle("initialize", json!({ "protocolVersion": "1999-01-01" })).unwrap();
+        assert_eq!(reply["protocolVersion"], PROTOCOL_VERSIONS[0]);
+    }
+
+    #[test]
+    fn test_tools() {
+        let reply = handle("tools/list", Value::Null).unwrap();
+        let names: Vec<_> = reply["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["list_entries", "search", "get_env_for_entry"]);
+
+        let unknown = handle("tools/call", json!({ "name": "delete_everything" })).unwrap_err();
+        assert_eq!(unknown.code, rpc::INVALID_PARAMS);
+        let missing = handle("tools/call", json!({ "name": "search", "arguments": {} }));
+        assert_eq!(missing.unwrap_err().code, rpc::INVALID_PARAMS);
+
+        assert_eq!(
+            handle("notifications/initialized", Value::Null).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            handle("resources/list", Value::Null).unwrap_err().code,
+            rpc::METHOD_NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_env_map_redacts_secret() {
+        let metadata = HashMap::from([
+            ("OPENAI_API_KEY".to_string(), "SECRET".to_string()),
+            (
+                "OPENAI_BASE_URL".to_string(),
+                "https://api.openai.com/v1".to_string(),
+            ),
+        ]);
+        let entry = Entry::new("openai".to_string(), metadata);
+
+        let redacted = env_map(&entry, None);
+        assert_eq!(redacted["OPENAI_API_KEY"], REDACTED);
+        assert_eq!(redacted["OPENAI_BASE_URL"], "https://api.openai.com/v1");
+        assert_eq!(env_map(&entry, Some("sk-1"))["OPENAI_API_KEY"], "sk-1");
+    }
+}
@@ -0,0 +1,67 @@
+// HTTP requests to provider APIs (ccm test, ccm models, ccm usage)
+//
+// `agent` builds the client (through the proxy, see `proxy`) and `send`
+// turns its result into an `Answer`, keeping error statuses so that callers
+// can report the API's message.
+
+pub mod proxy;
+pub mod usage;
+
+use crate::utils::{CcmError, Result};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// An HTTP answer
+pub struct Answer {
+    pub status: u16,
+    /// Header names are lowercase
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub elapsed: Duration,
+}
+
+impl Answer {
+    /// Whether the status is 2xx
+    pub fn ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// A client for requests to `url`
+pub fn agent(url: &str, timeout: Duration) -> ureq::Agent {
+    proxy::builder(url).timeout(timeout).build()
+}
+
+/// Read the answer to a request sent at `started`, keeping error statuses
+/// as answers
+pub fn send(
+    url: &str,
+    started: Instant,
+    sent: std::result::Result<ureq::Response, ureq::Error>,
+) -> Result<Answer> {
+    let response = match sent {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(ureq::Error::Transport(e)) => {
+            return Err(CcmError::Provider(format!("Cannot reach {}: {}", url, e)))
+        }
+    };
+    let elapsed = started.elapsed();
+
+    let status = response.status();
+    let headers = response
+        .headers_names()
+        .into_iter()
+        .filter_map(|name| {
+            let value = response.header(&name)?.to_string();
+            Some((name.to_lowercase(), value))
+        })
+        .collect();
+    let body = response.into_string()?;
+    Ok(Answer {
+        status,
+        headers,
+        body,
+        elapsed,
+    })
+}
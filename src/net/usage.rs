@@ -0,0 +1,398 @@
+// Credits and quota of an API key (ccm usage)
+//
+// Each provider reports them differently, so every endpoint is a `UsageApi`
+// adapter: OpenRouter's key info, DeepSeek's balance, OpenAI's costs API
+// (which needs an admin key) and the rate-limit headers Anthropic sends
+// with a free token count request.
+
+use super::Answer;
+use crate::core::provider::{error_message, Api, Provider};
+use crate::utils::{CcmError, Result};
+use chrono::{Datelike, TimeZone, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// What a provider says about a key's spending and limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct Usage {
+    /// Where the figures come from
+    pub source: &'static str,
+    /// Label and value, in display order
+    pub items: Vec<(String, String)>,
+}
+
+/// An endpoint telling about credits or quota
+trait UsageApi {
+    /// Whether the endpoint applies to `provider`
+    fn handles(&self, provider: &Provider) -> bool;
+    fn fetch(&self, provider: &Provider, timeout: Duration) -> Result<Usage>;
+}
+
+const APIS: [&dyn UsageApi; 4] = [&OpenRouter, &DeepSeek, &OpenAiCosts, &AnthropicLimits];
+
+/// Credits or quota of the provider's key
+pub fn usage(provider: &Provider, timeout: Duration) -> Result<Usage> {
+    let api = APIS
+        .into_iter()
+        .find(|api| api.handles(provider))
+        .ok_or_else(|| {
+            CcmError::InvalidArgument(format!(
+                "No usage endpoint is known for {} (supported: openai, openrouter, deepseek, \
+                 anthropic)",
+                provider.preset
+            ))
+        })?;
+    api.fetch(provider, timeout)
+}
+
+/// Send `body` (or nothing) to `path`, failing on an error status
+fn call(
+    provider: &Provider,
+    method: &str,
+    path: &str,
+    body: Option<Value>,
+    timeout: Duration,
+) -> Result<Answer> {
+    let (url, request) = provider.request(method, path, timeout);
+    let started = Instant::now();
+    let sent = match body {
+        Some(body) => request
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string()),
+        None => request.call(),
+    };
+    let answer = super::send(&url, started, sent)?;
+    if !answer.ok() {
+        return Err(CcmError::Provider(format!(
+            "{} from {}: {}",
+            answer.status,
+            url,
+            error_message(&answer.body)
+        )));
+    }
+    Ok(answer)
+}
+
+fn json_body(answer: &Answer) -> Result<Value> {
+    serde_json::from_str(&answer.body)
+        .map_err(|e| CcmError::Provider(format!("Unexpected usage answer: {}", e)))
+}
+
+/// Dollar amount, or "none" for a missing limit
+fn dollars(value: &Value) -> String {
+    value
+        .as_f64()
+        .map(|v| format!("${:.2}", v))
+        .unwrap_or_else(|| "none".to_string())
+}
+
+fn is_preset(provider: &Provider, preset: &str, host: &str) -> bool {
+    provider.preset == preset || provider.base_url.contains(host)
+}
+
+/// GET /key: spending and limit of an OpenRouter key
+struct OpenRouter;
+
+impl UsageApi for OpenRouter {
+    fn handles(&self, provider: &Provider) -> bool {
+        is_preset(provider, "openrouter", "openrouter.ai")
+    }
+
+    fn fetch(&self, provider: &Provider, timeout: Duration) -> Result<Usage> {
+        let answer = call(provider, "GET", "/key", None, timeout)?;
+        Ok(openrouter_usage(&json_body(&answer)?))
+    }
+}
+
+fn openrouter_usage(body: &Value) -> Usage {
+    let key = &body["data"];
+    let mut items = vec![
+        ("used".to_string(), dollars(&key["usage"])),
+        ("limit".to_string(), dollars(&key["limit"])),
+    ];
+    if !key["limit_remaining"].is_null() {
+        items.push(("remaining".to_string(), dollars(&key["limit_remaining"])));
+    }
+    if key["is_free_tier"].as_bool() == Some(true) {
+        items.push(("tier".to_string(), "free".to_string()));
+    }
+    Usage {
+        source: "OpenRouter key",
+        items,
+    }
+}
+
+/// GET /user/balance: DeepSeek account balance per currency
+struct DeepSeek;
+
+impl UsageApi for DeepSeek {
+    fn handles(&self, provider: &Provider) -> bool {
+        is_preset(provider, "deepseek", "api.deepseek.com")
+    }
+
+    fn fetch(&self, provider: &Provider, timeout: Duration) -> Result<Usage> {
+        let answer = call(provider, "GET", "/user/balance", None, timeout)?;
+        Ok(deepseek_usage(&json_body(&answer)?))
+    }
+}
+
+fn deepseek_usage(body: &Value) -> Usage {
+    let mut items = Vec::new();
+    if let Some(available) = body["is_available"].as_bool() {
+        let available = if available { "yes" } else { "no" };
+        items.push(("available".to_string(), available.to_string()));
+    }
+    for info in body["balance_infos"].as_array().into_iter().flatten() {
+        let currency = info["currency"].as_str().unwrap_or_default();
+        for (field, label) in [
+            ("total_balance", "balance"),
+            ("granted_balance", "granted"),
+            ("topped_up_balance", "topped up"),
+        ] {
+            if let Some(amount) = info[field].as_str() {
+                items.push((label.to_string(), format!("{} {}", amount, currency)));
+            }
+        }
+    }
+    Usage {
+        source: "DeepSeek balance",
+        items,
+    }
+}
+
+/// GET /organization/costs: this month's OpenAI spending (admin keys only)
+struct OpenAiCosts;
+
+impl UsageApi for OpenAiCosts {
+    fn handles(&self, provider: &Provider) -> bool {
+        // Only OpenAI itself, not the relays using its preset
+        provider.api == Api::OpenAi && provider.base_url.contains("api.openai.com")
+    }
+
+    fn fetch(&self, provider: &Provider, timeout: Duration) -> Result<Usage> {
+        let now = Utc::now();
+        let start = Utc
+            .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .single()
+            .unwrap_or(now);
+        let path = format!(
+            "/organization/costs?start_time={}&bucket_width=1d&limit=31",
+            start.timestamp()
+        );
+        let answer = call(provider, "GET", &path, None, timeout).map_err(|e| match e {
+            CcmError::Provider(message) if message.starts_with("40") => {
+                CcmError::Provider(format!("{} (the costs API needs an admin key)", message))
+            }
+            other => other,
+        })?;
+        Ok(openai_usage(
+            &json_body(&answer)?,
+            &start.format("%Y-%m-%d").to_string(),
+        ))
+    }
+}
+
+fn openai_usage(body: &Value, since: &str) -> Usage {
+    let mut total = 0.0;
+    let mut currency = "usd";
+    let results = body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|bucket| bucket["results"].as_array().into_iter().flatten());
+    for result in results {
+        total += result["amount"]["value"].as_f64().unwrap_or_default();
+        currency = result["amount"]["currency"].as_str().unwrap_or(currency);
+    }
+    Usage {
+        source: "OpenAI costs",
+        items: vec![
+            ("since".to_string(), since.to_string()),
+            (
+                "spent".to_string(),
+                format!("{:.2} {}", total, currency.to_uppercase()),
+            ),
+        ],
+    }
+}
+
+/// POST /v1/messages/count_tokens: Anthropic's rate-limit headers
+struct AnthropicLimits;
+
+/// Limits Anthropic reports, as in `anthropic-ratelimit-<kind>-remaining`
+const ANTHROPIC_LIMITS: [&str; 4] = ["requests", "tokens", "input-tokens", "output-tokens"];
+
+impl UsageApi for AnthropicLimits {
+    fn handles(&self, provider: &Provider) -> bool {
+        provider.api == Api::Anthropic
+    }
+
+    fn fetch(&self, provider: &Provider, timeout: Duration) -> Result<Usage> {
+        // Counting tokens is free but needs a model the key can use
+        let model = match provider.model() {
+            Some(model) => model.to_string(),
+            None => provider
+                .models(timeout)?
+                .into_iter()
+                .next()
+                .map(|model| model.id)
+                .ok_or_else(|| CcmError::Provider("The key can use no model".to_string()))?,
+        };
+        let body = json!({
+            "model": model,
+            "messages": [{ "role": "user", "content": "ping" }],
+        });
+        let answer = call(
+            provider,
+            "POST",
+            "/v1/messages/count_tokens",
+            Some(body),
+            timeout,
+        )?;
+        let usage = anthropic_usage(&answer.headers);
+        if usage.items.is_empty() {
+            return Err(CcmError::Provider(
+                "The API sent no rate-limit headers (relays often drop them)".to_string(),
+            ));
+        }
+        Ok(usage)
+    }
+}
+
+fn anthropic_usage(headers: &HashMap<String, String>) -> Usage {
+    let header =
+        |kind: &str, what: &str| headers.get(&format!("anthropic-ratelimit-{}-{}", kind, what));
+    let items = ANTHROPIC_LIMITS
+        .iter()
+        .filter_map(|kind| {
+            let remaining = header(kind, "remaining")?;
+            let mut value = match header(kind, "limit") {
+                Some(limit) => format!("{} of {} left", remaining, limit),
+                None => format!("{} left", remaining),
+            };
+            if let Some(reset) = header(kind, "reset") {
+                value.push_str(&format!(", resets {}", reset));
+            }
+            Some((kind.replace('-', " "), value))
+        })
+        .collect();
+    Usage {
+        source: "Anthropic rate limits",
+        items,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(pairs: &[(&str, &str)]) -> Provider {
+        let vars = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Provider::detect("x", &vars).unwrap()
+    }
+
+    fn handler(provider: &Provider) -> Option<&'static str> {
+        let index = APIS.iter().position(|api| api.handles(provider))?;
+        Some(["openrouter", "deepseek", "openai", "anthropic"][index])
+    }
+
+    #[test]
+    fn test_handles() {
+        assert_eq!(
+            handler(&provider(&[("OPENROUTER_API_KEY", "k")])),
+            Some("openrouter")
+        );
+        assert_eq!(
+            handler(&provider(&[("DEEPSEEK_API_KEY", "k")])),
+            Some("deepseek")
+        );
+        assert_eq!(
+            handler(&provider(&[("OPENAI_API_KEY", "k")])),
+            Some("openai")
+        );
+        assert_eq!(
+            handler(&provider(&[("ANTHROPIC_API_KEY", "k")])),
+            Some("anthropic")
+        );
+        // An OpenAI-compatible relay is not OpenAI
+        let relay = provider(&[
+            ("OPENAI_API_KEY", "k"),
+            ("OPENAI_BASE_URL", "https://relay.example.com/v1"),
+        ]);
+        assert_eq!(handler(&relay), None);
+        assert!(usage(&relay, Duration::from_secs(1)).is_err());
+        assert_eq!(handler(&provider(&[("GROQ_API_KEY", "k")])), None);
+    }
+
+    fn items(usage: &Usage) -> Vec<(&str, &str)> {
+        usage
+            .items
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        let openrouter: Value = serde_json::from_str(
+            r#"{"data":{"label":"k","usage":1.5,"limit":10,"limit_remaining":8.5,"is_free_tier":false}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            items(&openrouter_usage(&openrouter)),
+            [
+                ("used", "$1.50"),
+                ("limit", "$10.00"),
+                ("remaining", "$8.50")
+            ]
+        );
+
+        let deepseek: Value = serde_json::from_str(
+            r#"{"is_available":true,"balance_infos":[{"currency":"CNY","total_balance":"110.00","granted_balance":"10.00","topped_up_balance":"100.00"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            items(&deepseek_usage(&deepseek)),
+            [
+                ("available", "yes"),
+                ("balance", "110.00 CNY"),
+                ("granted", "10.00 CNY"),
+                ("topped up", "100.00 CNY"),
+            ]
+        );
+
+        let openai: Value = serde_json::from_str(
+            r#"{"object":"page","data":[
+                {"results":[{"amount":{"value":1.25,"currency":"usd"}}]},
+                {"results":[{"amount":{"value":0.5,"currency":"usd"}}]},
+                {"results":[]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            items(&openai_usage(&openai, "2026-10-01")),
+            [("since", "2026-10-01"), ("spent", "1.75 USD")]
+        );
+
+        let headers: HashMap<String, String> = [
+            ("anthropic-ratelimit-requests-limit", "50"),
+            ("anthropic-ratelimit-requests-remaining", "49"),
+            ("anthropic-ratelimit-requests-reset", "2026-10-17T12:00:00Z"),
+            ("anthropic-ratelimit-input-tokens-remaining", "39000"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(
+            items(&anthropic_usage(&headers)),
+            [
+                ("requests", "49 of 50 left, resets 2026-10-17T12:00:00Z"),
+                ("input tokens", "39000 left"),
+            ]
+        );
+        assert!(anthropic_usage(&HashMap::new()).items.is_empty());
+    }
+}
@@ -0,0 +1,372 @@
+// User presets from presets.toml (next to the instance ID)
+//
+// Teams distribute their provider list as a TOML file and load it with
+// `ccm preset import`; its presets are added to the built-in ones, and one
+// with a built-in name replaces it:
+//
+//     [presets.corp-relay]
+//     description = "Company Anthropic relay"
+//     required = ["token"]              # the last one is the secret
+//     env = { token = "ANTHROPIC_AUTH_TOKEN", url = "ANTHROPIC_BASE_URL" }
+//     defaults = { url = "https://llm.corp.example.com" }
+
+use super::Preset;
+use crate::utils::{files, CcmError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// File name in the config directory
+pub const PRESETS_FILE: &str = "presets.toml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetFile {
+    #[serde(default)]
+    pub presets: BTreeMap<String, PresetDef>,
+}
+
+/// A preset as written in the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetDef {
+    #[serde(default)]
+    pub description: String,
+    /// Field → environment variable
+    pub env: BTreeMap<String, String>,
+    /// Field → default value
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, String>,
+    /// Fields an entry must have; the last one is stored as the secret
+    #[serde(default = "default_required")]
+    pub required: Vec<String>,
+}
+
+fn default_required() -> Vec<String> {
+    vec!["token".to_string()]
+}
+
+/// Path of the user presets file
+pub fn presets_path() -> PathBuf {
+    crate::db::location::config_dir().join(PRESETS_FILE)
+}
+
+/// Parse and check a presets file
+pub fn parse(content: &str) -> Result<PresetFile> {
+    let file: PresetFile = toml::from_str(content)
+        .map_err(|e| CcmError::InvalidArgument(format!("Invalid presets file: {}", e)))?;
+    for (name, def) in &file.presets {
+        def.to_preset(name)?;
+    }
+    Ok(file)
+}
+
+/// Presets of the user file; none when it does not exist
+pub fn load(path: &Path) -> Result<Vec<Preset>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = parse(&std::fs::read_to_string(path)?).map_err(|e| match e {
+        CcmError::InvalidArgument(message) => {
+            CcmError::InvalidArgument(format!("{}: {}", path.display(), message))
+        }
+        other => other,
+    })?;
+    file.presets
+        .iter()
+        .map(|(name, def)| def.to_preset(name))
+        .collect()
+}
+
+/// Add the presets of `content` to the user file (replacing those with the
+/// same names); returns their names
+pub fn import(path: &Path, content: &str) -> Result<Vec<String>> {
+    add(path, parse(content)?)
+}
+
+/// Add `presets` to the user file, replacing those with the same names;
+/// returns their names
+pub fn add(path: &Path, imported: PresetFile) -> Result<Vec<String>> {
+    for (name, def) in &imported.presets {
+        def.to_preset(name)?;
+    }
+    let mut file = if path.exists() {
+        parse(&std::fs::read_to_string(path)?)?
+    } else {
+        PresetFile::default()
+    };
+
+    let names: Vec<String> = imported.presets.keys().cloned().collect();
+    file.presets.extend(imported.presets);
+    save(path, &file)?;
+    Ok(names)
+}
+
+/// Write the user file
+pub fn save(path: &Path, file: &PresetFile) -> Result<()> {
+    let text = toml::to_string_pretty(file)
+        .map_err(|e| CcmError::Unknown(format!("Failed to write presets: {}", e)))?;
+    if let Some(dir) = path.parent() {
+        files::create_private_dir(dir)?;
+    }
+    files::write_file_atomic(path, text.as_bytes())
+}
+
+/// A preset reproducing an entry's variables (`ccm preset save-from`)
+///
+/// The SECRET variable becomes the required `token` field (or is named after
+/// the variable when there are several), URL and model variables the `url`
+/// and `model` fields, and the others fields named after them. Values other
+/// than SECRET become defaults, except for the variables in `ask` and those
+/// named like credentials (see `looks_like_credential`), which become
+/// required fields asked for when adding an entry: presets.toml is meant to
+/// be shared.
+pub fn def_from_entry(
+    description: &str,
+    metadata: &HashMap<String, String>,
+    ask: &[String],
+) -> Result<PresetDef> {
+    if let Some(var) = ask.iter().find(|var| !metadata.contains_key(*var)) {
+        return Err(CcmError::InvalidArgument(format!(
+            "The entry has no variable {}",
+            var
+        )));
+    }
+
+    let mut vars: Vec<(&String, &String)> = metadata.iter().collect();
+    vars.sort();
+    let secrets = vars.iter().filter(|(_, value)| *value == "SECRET").count();
+    if secrets == 0 {
+        return Err(CcmError::InvalidArgument(
+            "The entry has no SECRET variable".to_string(),
+        ));
+    }
+
+    let mut def = PresetDef {
+        description: description.to_string(),
+        env: BTreeMap::new(),
+        defaults: BTreeMap::new(),
+        required: Vec::new(),
+    };
+    let mut secret_field = None;
+    for (var, value) in vars {
+        let conventional = if value == "SECRET" {
+            (secrets == 1).then_some("token")
+        } else if var.ends_with("_MODEL") {
+            Some("model")
+        } else if value.starts_with("http://") || value.starts_with("https://") {
+            Some("url")
+        } else {
+            None
+        };
+        let field = match conventional {
+            Some(field) if !def.env.contains_key(field) => field.to_string(),
+            _ => var.to_lowercase(),
+        };
+        def.env.insert(field.clone(), var.clone());
+
+        if value == "SECRET" {
+            // The first one is the secret, any other is a copy of it
+            if secret_field.is_none() {
+                secret_field = Some(field);
+            } else {
+                def.defaults.insert(field, value.clone());
+            }
+        } else if ask.contains(var) || looks_like_credential(var) {
+            def.required.push(field);
+        } else {
+            def.defaults.insert(field, value.clone());
+        }
+    }
+    def.required.extend(secret_field);
+    Ok(def)
+}
+
+/// Whether a variable name suggests a credential (`*_KEY`, `*_KEY_ID`,
+/// `*TOKEN*`, `*SECRET*`, `*PASSWORD*`; not token counts like `MAX_TOKENS`)
+fn looks_like_credential(var: &str) -> bool {
+    let var = var.to_ascii_uppercase();
+    var.ends_with("KEY")
+        || var.ends_with("_KEY_ID")
+        || (var.contains("TOKEN") && !var.contains("TOKENS"))
+        || var.contains("SECRET")
+        || var.contains("PASSWORD")
+}
+
+/// Whether `name` can be an environment variable name
+/// (`[A-Za-z_][A-Za-z0-9_]*`, safe to write unquoted into shell code)
+pub(crate) fn is_var_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl PresetDef {
+    /// The preset, after checking that it is usable
+    pub fn to_preset(&self, name: &str) -> Result<Preset> {
+        let invalid = |message: String| {
+            Err(CcmError::InvalidArgument(format!(
+                "Preset '{}': {}",
+                name, message
+            )))
+        };
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return invalid("names use lowercase letters, digits, '-' and '_'".to_string());
+        }
+        if self.env.is_empty() {
+            return invalid("env maps no fields".to_string());
+        }
+        if let Some((field, var)) = self.env.iter().find(|(_, var)| !is_var_name(var)) {
+            return invalid(format!("{} is not a variable name (field {})", var, field));
+        }
+        if self.required.is_empty() {
+            return invalid("required must list at least the secret field".to_string());
+        }
+        let unmapped = self
+            .required
+            .iter()
+            .chain(self.defaults.keys())
+            .find(|field| !self.env.contains_key(*field));
+        if let Some(field) = unmapped {
+            return invalid(format!("field {} is not in env", field));
+        }
+        if let Some(url) = self.defaults.get("url") {
+            if let Err(e) = crate::utils::validate_url(url) {
+                return invalid(e.to_string());
+            }
+        }
+
+        Ok(Preset {
+            name: name.to_string(),
+            description: self.description.clone(),
+            default_fields: to_hash_map(&self.defaults),
+            env_mapping: to_hash_map(&self.env),
+            required_fields: self.required.clone(),
+        })
+    }
+}
+
+fn to_hash_map(map: &BTreeMap<String, String>) -> HashMap<String, String> {
+    map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORP: &str = r#"
+[presets.corp-relay]
+description = "Company relay"
+env = { token = "ANTHROPIC_AUTH_TOKEN", url = "ANTHROPIC_BASE_URL" }
+defaults = { url = "https://llm.corp.example.com" }
+"#;
+
+    #[test]
+    fn test_parse() {
+        let file = parse(CORP).unwrap();
+        let preset = file.presets["corp-relay"].to_preset("corp-relay").unwrap();
+        assert_eq!(preset.secret_field(), Some("token"));
+        assert_eq!(preset.env_mapping["url"], "ANTHROPIC_BASE_URL");
+        assert_eq!(preset.default_fields["url"], "https://llm.corp.example.com");
+
+        for bad in [
+            "[presets.x]\nenv = {}",
+            "[presets.x]\nenv = { token = \"MY KEY\" }",
+            "[presets.x]\nenv = { token = \"KEY\" }\nrequired = []",
+            "[presets.x]\nenv = { url = \"URL\" }",
+            "[presets.x]\nenv = { token = \"KEY\" }\ndefaults = { url = \"nope\" }",
+            "[presets.Bad]\nenv = { token = \"KEY\" }",
+            "[presets.x]\nenv = { token = \"KEY\" }\ncolor = \"red\"",
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_def_from_entry() {
+        let metadata: HashMap<String, String> = [
+            ("AWS_ACCESS_KEY_ID", "AKIAEXAMPLE"),
+            ("AWS_SECRET_ACCESS_KEY", "SECRET"),
+            ("AWS_SESSION_TOKEN", "FwoGZXIvYXdzE"),
+            ("AWS_REGION", "eu-west-1"),
+            ("API_URL", "https://api.example.com"),
+            ("OTHER_URL", "https://other.example.com"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        // Credential-like names are asked for even without `ask`
+        let def = def_from_entry("Mine", &metadata, &[]).unwrap();
+        assert_eq!(def.env["token"], "AWS_SECRET_ACCESS_KEY");
+        assert_eq!(def.env["url"], "API_URL");
+        assert_eq!(def.env["other_url"], "OTHER_URL");
+        assert_eq!(
+            def.required,
+            ["aws_access_key_id", "aws_session_token", "token"]
+        );
+        assert!(!def.defaults.contains_key("aws_access_key_id"));
+        assert!(!def.defaults.contains_key("aws_session_token"));
+        assert_eq!(def.defaults["aws_region"], "eu-west-1");
+        assert!(!def.defaults.values().any(|v| v == "SECRET"));
+
+        // Filling it in gives back the entry's variables
+        let preset = def.to_preset("mine").unwrap();
+        let mut filled = HashMap::new();
+        preset
+            .fill_metadata(&mut filled, |var| Ok(metadata[var].clone()))
+            .unwrap();
+        assert_eq!(filled, metadata);
+
+        let def = def_from_entry("", &metadata, &["AWS_REGION".to_string()]).unwrap();
+        assert!(def.required.contains(&"aws_region".to_string()));
+        assert!(!def.defaults.contains_key("aws_region"));
+
+        assert!(def_from_entry("", &metadata, &["NOPE".to_string()]).is_err());
+        let no_secret = HashMap::from([("A".to_string(), "b".to_string())]);
+        assert!(def_from_entry("", &no_secret, &[]).is_err());
+    }
+
+    #[test]
+    fn test_looks_like_credential() {
+        for var in [
+            "OPENAI_API_KEY",
+            "AWS_ACCESS_KEY_ID",
+            "GITHUB_TOKEN",
+            "HF_TOKEN_PATH",
+            "CLIENT_SECRET",
+            "DB_PASSWORD",
+        ] {
+            assert!(looks_like_credential(var), "{}", var);
+        }
+        for var in [
+            "AWS_REGION",
+            "OPENAI_BASE_URL",
+            "KEYRING_BACKEND",
+            "MAX_TOKENS",
+        ] {
+            assert!(!looks_like_credential(var), "{}", var);
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PRESETS_FILE);
+        assert!(load(&path).unwrap().is_empty());
+
+        assert_eq!(import(&path, CORP).unwrap(), ["corp-relay"]);
+        let other = "[presets.claude]\nenv = { token = \"CLAUDE_KEY\" }";
+        assert_eq!(import(&path, other).unwrap(), ["claude"]);
+
+        let names: Vec<String> = load(&path).unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["claude", "corp-relay"]);
+        assert!(import(&path, "[presets.x]\nenv = {}").is_err());
+        assert_eq!(load(&path).unwrap().len(), 2);
+    }
+}
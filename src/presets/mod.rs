@@ -1,7 +1,12 @@
 // Preset configurations for common API providers
 
+pub mod file;
+
 use crate::utils::{CcmError, Result};
+use colored::Colorize;
 use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone)]
 pub struct Preset {
@@ -12,32 +17,490 @@ pub struct Preset {
     pub required_fields: Vec<String>,
 }
 
+impl Preset {
+    /// The field stored as the entry's secret: its last required field
+    /// (the API key; for AWS the secret access key)
+    pub fn secret_field(&self) -> Option<&str> {
+        self.required_fields.last().map(String::as_str)
+    }
+
+    /// Complete an entry's variables from the preset: SECRET for the secret
+    /// field, the default fields, and the other required fields, whose
+    /// values come from `ask(var)`. Variables already set are kept.
+    /// Fails when a required value is empty or a URL is invalid.
+    pub fn fill_metadata<F>(&self, metadata: &mut HashMap<String, String>, mut ask: F) -> Result<()>
+    where
+        F: FnMut(&str) -> Result<String>,
+    {
+        let secret_field = self.secret_field();
+        let mut fields: Vec<(&String, &String)> = self.env_mapping.iter().collect();
+        fields.sort();
+
+        for (field, var) in fields {
+            if !metadata.contains_key(var) {
+                let value = if Some(field.as_str()) == secret_field {
+                    Some("SECRET".to_string())
+                } else if self.required_fields.contains(field) {
+                    Some(ask(var)?.trim().to_string())
+                } else {
+                    self.default_fields.get(field).cloned()
+                };
+                if let Some(value) = value {
+                    metadata.insert(var.clone(), value);
+                }
+            }
+
+            let Some(value) = metadata.get(var) else {
+                continue;
+            };
+            if self.required_fields.contains(field) && value.trim().is_empty() {
+                return Err(CcmError::InvalidArgument(format!(
+                    "{} is required by the {} preset",
+                    var, self.name
+                )));
+            }
+            if field == "url" {
+                crate::utils::validate_url(value)
+                    .map_err(|e| CcmError::InvalidArgument(format!("{}: {}", var, e)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Get preset by name
 pub fn get_preset(name: &str) -> Result<Preset> {
-    match name.to_lowercase().as_str() {
-        "claude" => Ok(claude_preset()),
-        "openai" => Ok(openai_preset()),
-        "gemini" => Ok(gemini_preset()),
-        "github" => Ok(github_preset()),
-        "aws" => Ok(aws_preset()),
-        _ => Err(CcmError::InvalidArgument(format!(
-            "Unknown preset: {}. Available: claude, openai, gemini, github, aws",
-            name
-        ))),
-    }
+    let lower = name.to_lowercase();
+    let presets = list_presets();
+    let names: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+    let available = names.join(", ");
+    presets
+        .iter()
+        .find(|p| p.name == lower)
+        .cloned()
+        .ok_or_else(|| {
+            CcmError::InvalidArgument(format!(
+                "Unknown preset: {}. Available: {}",
+                name, available
+            ))
+        })
 }
 
-/// List all available presets
+/// List all available presets: the built-in ones, then those of the user
+/// presets file (which replace built-ins of the same name)
 pub fn list_presets() -> Vec<Preset> {
+    merge(builtin_presets(), user_presets().to_vec())
+}
+
+/// Presets of the user file, read once; an invalid file is reported and
+/// ignored so that commands using presets keep working
+fn user_presets() -> &'static [Preset] {
+    static USER_PRESETS: OnceLock<Vec<Preset>> = OnceLock::new();
+    USER_PRESETS.get_or_init(|| {
+        file::load(&file::presets_path()).unwrap_or_else(|e| {
+            eprintln!("{} Ignoring user presets: {}", "⚠️".yellow(), e);
+            Vec::new()
+        })
+    })
+}
+
+/// `builtin` with `user` presets added, or replacing those of the same name
+fn merge(mut builtin: Vec<Preset>, user: Vec<Preset>) -> Vec<Preset> {
+    for preset in user {
+        match builtin.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => builtin.push(preset),
+        }
+    }
+    builtin
+}
+
+fn builtin_presets() -> Vec<Preset> {
     vec![
         claude_preset(),
         openai_preset(),
         gemini_preset(),
         github_preset(),
         aws_preset(),
+        api_preset(
+            "deepseek",
+            "DeepSeek API (OpenAI-compatible)",
+            ["DEEPSEEK_API_KEY", "DEEPSEEK_BASE_URL", "DEEPSEEK_MODEL"],
+            "https://api.deepseek.com",
+        ),
+        api_preset(
+            "openrouter",
+            "OpenRouter (OpenAI-compatible, many providers)",
+            [
+                "OPENROUTER_API_KEY",
+                "OPENROUTER_BASE_URL",
+                "OPENROUTER_MODEL",
+            ],
+            "https://openrouter.ai/api/v1",
+        ),
+        api_preset(
+            "groq",
+            "Groq API (OpenAI-compatible)",
+            ["GROQ_API_KEY", "GROQ_BASE_URL", "GROQ_MODEL"],
+            "https://api.groq.com/openai/v1",
+        ),
+        api_preset(
+            "mistral",
+            "Mistral AI API",
+            ["MISTRAL_API_KEY", "MISTRAL_BASE_URL", "MISTRAL_MODEL"],
+            "https://api.mistral.ai/v1",
+        ),
+        azure_preset(),
+        api_preset(
+            "ollama",
+            "Ollama (local server, or ollama.com with an API key)",
+            ["OLLAMA_API_KEY", "OLLAMA_HOST", "OLLAMA_MODEL"],
+            "http://localhost:11434",
+        ),
+        api_preset(
+            "together",
+            "Together AI API (OpenAI-compatible)",
+            ["TOGETHER_API_KEY", "TOGETHER_BASE_URL", "TOGETHER_MODEL"],
+            "https://api.together.xyz/v1",
+        ),
+        bedrock_preset(),
     ]
 }
 
+/// A preset that fits an existing entry
+#[derive(Debug, Clone)]
+pub struct PresetMatch {
+    pub preset: Preset,
+    /// Entry variables that belong to the preset
+    pub matched: Vec<String>,
+    /// Preset variables the entry lacks, with the value to fill in (VAR, value)
+    /// Defaults come from the preset; a single required field maps to SECRET
+    pub additions: Vec<(String, String)>,
+    /// Matched only because the entry name mentions the preset
+    pub by_name: bool,
+}
+
+/// Find the preset that best fits an entry's env var names
+/// Falls back to the entry name (e.g. "openai-prod") when no variable matches
+pub fn detect_preset(entry_name: &str, metadata: &HashMap<String, String>) -> Option<PresetMatch> {
+    let lower_name = entry_name.to_lowercase();
+
+    let mut best: Option<PresetMatch> = None;
+    for preset in list_presets() {
+        let mut matched: Vec<String> = preset
+            .env_mapping
+            .values()
+            .filter(|var| metadata.contains_key(*var))
+            .cloned()
+            .collect();
+        matched.sort();
+
+        let by_name = matched.is_empty() && lower_name.contains(&preset.name);
+        if matched.is_empty() && !by_name {
+            continue;
+        }
+
+        let better = match &best {
+            None => true,
+            Some(current) => matched.len() > current.matched.len() || (current.by_name && !by_name),
+        };
+        if better {
+            let secret_field = match preset.required_fields.as_slice() {
+                [field] => Some(field.as_str()),
+                _ => None,
+            };
+
+            let mut additions: Vec<(String, String)> = preset
+                .env_mapping
+                .iter()
+                .filter(|(_, var)| !metadata.contains_key(*var))
+                .filter_map(|(field, var)| {
+                    if Some(field.as_str()) == secret_field {
+                        return Some((var.clone(), "SECRET".to_string()));
+                    }
+                    preset
+                        .default_fields
+                        .get(field)
+                        .map(|value| (var.clone(), value.clone()))
+                })
+                .collect();
+            additions.sort();
+
+            best = Some(PresetMatch {
+                preset,
+                matched,
+                additions,
+                by_name,
+            });
+        }
+    }
+
+    best
+}
+
+/// Variable names superseded by the ones current SDKs and tools read (old, current)
+const DEPRECATED_VARS: &[(&str, &str)] = &[
+    ("OPENAI_API_BASE", "OPENAI_BASE_URL"),
+    ("CLAUDE_API_KEY", "ANTHROPIC_API_KEY"),
+    ("ANTHROPIC_API_URL", "ANTHROPIC_BASE_URL"),
+    ("AWS_DEFAULT_REGION", "AWS_REGION"),
+];
+
+/// A problem found by `lint_entry`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    /// Variable the issue is about
+    pub var: String,
+    pub message: String,
+    /// Automatic fix, when there is a safe one
+    pub fix: Option<LintFix>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintFix {
+    /// Replace the variable's value
+    SetValue(String),
+    /// Rename the variable, keeping its value
+    Rename(String),
+}
+
+/// Check an entry against its preset's expectations and common mistakes:
+/// missing model variable, plain http:// URLs, trailing slashes on URLs and
+/// deprecated variable names. http:// to a private network host is only
+/// reported, not switched to https://
+pub fn lint_entry(entry_name: &str, metadata: &HashMap<String, String>) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+
+    let mut vars: Vec<(&String, &String)> = metadata.iter().collect();
+    vars.sort();
+
+    for (var, value) in &vars {
+        let trailing_slash = value.starts_with("http") && value.ends_with('/');
+        let trimmed = value.trim_end_matches('/');
+        let host = value.strip_prefix("http://").map(url_host);
+        match host.map(host_kind) {
+            Some(HostKind::Public) => issues.push(LintIssue {
+                var: var.to_string(),
+                message: if trailing_slash {
+                    format!(
+                        "{} uses plain http:// and has a trailing slash ({})",
+                        var, value
+                    )
+                } else {
+                    format!("{} uses plain http:// ({})", var, value)
+                },
+                fix: Some(LintFix::SetValue(
+                    trimmed.replacen("http://", "https://", 1),
+                )),
+            }),
+            // A LAN service often has no TLS at all, so only point it out
+            Some(HostKind::Private) => issues.push(LintIssue {
+                var: var.to_string(),
+                message: format!(
+                    "{} uses plain http:// to a private network host ({}); \
+                     use https:// if the service supports it",
+                    var, value
+                ),
+                fix: None,
+            }),
+            Some(HostKind::Loopback) | None => {}
+        }
+        if trailing_slash && host.map(host_kind) != Some(HostKind::Public) {
+            issues.push(LintIssue {
+                var: var.to_string(),
+                message: format!("{} has a trailing slash ({})", var, value),
+                fix: Some(LintFix::SetValue(trimmed.to_string())),
+            });
+        }
+    }
+
+    for (old, current) in DEPRECATED_VARS {
+        let Some(old_value) = metadata.get(*old) else {
+            continue;
+        };
+        match metadata.get(*current) {
+            // Kept next to the current name for older tools (e.g. relay profiles)
+            Some(value) if value == old_value => {}
+            Some(_) => issues.push(LintIssue {
+                var: old.to_string(),
+                message: format!(
+                    "{} is deprecated and differs from {}; remove one of them",
+                    old, current
+                ),
+                fix: None,
+            }),
+            None => issues.push(LintIssue {
+                var: old.to_string(),
+                message: format!("{} is deprecated; use {}", old, current),
+                fix: Some(LintFix::Rename(current.to_string())),
+            }),
+        }
+    }
+
+    if let Some(detected) = detect_preset(entry_name, metadata) {
+        if let Some(model_var) = detected.preset.env_mapping.get("model") {
+            if !metadata.contains_key(model_var) {
+                issues.push(LintIssue {
+                    var: model_var.clone(),
+                    message: format!(
+                        "{} is not set; the tool's default model will be used \
+                         (set one with: ccm model {} <MODEL>)",
+                        model_var, entry_name
+                    ),
+                    fix: None,
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+/// Where a plain http:// URL points to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HostKind {
+    /// This machine: http is fine
+    Loopback,
+    /// A private, link-local or LAN host, which may not offer https
+    Private,
+    Public,
+}
+
+/// The host of a URL without its scheme (IPv6 addresses keep their brackets)
+fn url_host(rest: &str) -> &str {
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority.rsplit('@').next().unwrap_or(authority);
+    if authority.starts_with('[') {
+        return authority.split_inclusive(']').next().unwrap_or(authority);
+    }
+    authority.split(':').next().unwrap_or(authority)
+}
+
+fn host_kind(host: &str) -> HostKind {
+    let host = host.to_ascii_lowercase();
+    let ip = host.trim_start_matches('[').trim_end_matches(']');
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) if ip.is_loopback() || ip.is_unspecified() => HostKind::Loopback,
+        Ok(IpAddr::V4(ip)) if ip.is_private() || ip.is_link_local() => HostKind::Private,
+        Ok(IpAddr::V6(ip)) if ip.is_loopback() || ip.is_unspecified() => HostKind::Loopback,
+        Ok(IpAddr::V6(ip)) if ip.is_unique_local() || ip.is_unicast_link_local() => {
+            HostKind::Private
+        }
+        Ok(_) => HostKind::Public,
+        Err(_) if host == "localhost" || host.ends_with(".localhost") => HostKind::Loopback,
+        // Single-label names (docker compose services), mDNS and internal zones
+        Err(_)
+            if !host.contains('.')
+                || [".local", ".lan", ".internal", ".home.arpa"]
+                    .iter()
+                    .any(|suffix| host.ends_with(suffix)) =>
+        {
+            HostKind::Private
+        }
+        Err(_) => HostKind::Public,
+    }
+}
+
+/// Apply the automatic fixes of `issues` to an entry's variables
+/// Returns the number of fixes applied
+pub fn apply_lint_fixes(metadata: &mut HashMap<String, String>, issues: &[LintIssue]) -> usize {
+    let mut applied = 0;
+
+    // Values first, so a renamed variable keeps its fixed value
+    for issue in issues {
+        if let Some(LintFix::SetValue(value)) = &issue.fix {
+            if let Some(current) = metadata.get_mut(&issue.var) {
+                *current = value.clone();
+                applied += 1;
+            }
+        }
+    }
+    for issue in issues {
+        if let Some(LintFix::Rename(new_var)) = &issue.fix {
+            if let Some(value) = metadata.remove(&issue.var) {
+                metadata.insert(new_var.clone(), value);
+                applied += 1;
+            }
+        }
+    }
+
+    applied
+}
+
+/// Relay API styles supported by relay_env_mapping()
+pub const RELAY_STYLES: &[&str] = &["openai", "anthropic"];
+
+/// How an Anthropic-style relay expects the key to be sent
+pub const RELAY_AUTH_MODES: &[&str] = &["bearer", "x-api-key"];
+
+/// Build env mappings for a third-party OpenAI/Anthropic-compatible relay
+///
+/// - openai: OPENAI_API_KEY (sent as Authorization: Bearer) and a base URL ending in /v1
+/// - anthropic: the base URL without /v1, and the key as ANTHROPIC_AUTH_TOKEN
+///   (Authorization: Bearer, what most relays expect) or ANTHROPIC_API_KEY (x-api-key)
+pub fn relay_env_mapping(
+    base_url: &str,
+    style: &str,
+    auth: Option<&str>,
+    model: Option<&str>,
+) -> Result<Vec<(String, String)>> {
+    crate::utils::validate_url(base_url).map_err(|e| CcmError::InvalidArgument(e.to_string()))?;
+    let base = base_url.trim_end_matches('/');
+
+    let mut vars = Vec::new();
+    match style.to_lowercase().as_str() {
+        "openai" => {
+            if matches!(auth, Some(mode) if mode != "bearer") {
+                return Err(CcmError::InvalidArgument(
+                    "OpenAI-style relays always use bearer authentication".to_string(),
+                ));
+            }
+            let url = if base.ends_with("/v1") {
+                base.to_string()
+            } else {
+                format!("{}/v1", base)
+            };
+            vars.push(("OPENAI_API_KEY".to_string(), "SECRET".to_string()));
+            vars.push(("OPENAI_BASE_URL".to_string(), url.clone()));
+            // Older SDKs and tools still read OPENAI_API_BASE
+            vars.push(("OPENAI_API_BASE".to_string(), url));
+            if let Some(model) = model {
+                vars.push(("OPENAI_MODEL".to_string(), model.to_string()));
+            }
+        }
+        "anthropic" => {
+            let key_var = match auth.unwrap_or("bearer") {
+                "bearer" => "ANTHROPIC_AUTH_TOKEN",
+                "x-api-key" => "ANTHROPIC_API_KEY",
+                other => {
+                    return Err(CcmError::InvalidArgument(format!(
+                        "Unknown auth mode: {}. Available: {}",
+                        other,
+                        RELAY_AUTH_MODES.join(", ")
+                    )))
+                }
+            };
+            // Anthropic clients append /v1 themselves
+            let url = base.strip_suffix("/v1").unwrap_or(base);
+            vars.push((key_var.to_string(), "SECRET".to_string()));
+            vars.push(("ANTHROPIC_BASE_URL".to_string(), url.to_string()));
+            if let Some(model) = model {
+                vars.push(("ANTHROPIC_MODEL".to_string(), model.to_string()));
+            }
+        }
+        _ => {
+            return Err(CcmError::InvalidArgument(format!(
+                "Unknown relay style: {}. Available: {}",
+                style,
+                RELAY_STYLES.join(", ")
+            )))
+        }
+    }
+
+    Ok(vars)
+}
+
 fn claude_preset() -> Preset {
     let mut default_fields = HashMap::new();
     default_fields.insert("url".to_string(), "https://api.anthropic.com".to_string());
@@ -131,6 +594,73 @@ fn aws_preset() -> Preset {
     }
 }
 
+/// A provider with an API key, a base URL and a model, given as their
+/// variables `[token, url, model]`
+fn api_preset(name: &str, description: &str, vars: [&str; 3], url: &str) -> Preset {
+    let [token_var, url_var, model_var] = vars;
+
+    let mut default_fields = HashMap::new();
+    default_fields.insert("url".to_string(), url.to_string());
+
+    let mut env_mapping = HashMap::new();
+    env_mapping.insert("token".to_string(), token_var.to_string());
+    env_mapping.insert("url".to_string(), url_var.to_string());
+    env_mapping.insert("model".to_string(), model_var.to_string());
+
+    Preset {
+        name: name.to_string(),
+        description: description.to_string(),
+        default_fields,
+        env_mapping,
+        required_fields: vec!["token".to_string()],
+    }
+}
+
+fn azure_preset() -> Preset {
+    let mut default_fields = HashMap::new();
+    default_fields.insert("api_version".to_string(), "2024-10-21".to_string());
+
+    // The variables the official OpenAI SDKs read for Azure
+    let mut env_mapping = HashMap::new();
+    env_mapping.insert("token".to_string(), "AZURE_OPENAI_API_KEY".to_string());
+    env_mapping.insert("url".to_string(), "AZURE_OPENAI_ENDPOINT".to_string());
+    env_mapping.insert("api_version".to_string(), "OPENAI_API_VERSION".to_string());
+
+    Preset {
+        name: "azure".to_string(),
+        description: "Azure OpenAI (endpoint https://<resource>.openai.azure.com)".to_string(),
+        default_fields,
+        env_mapping,
+        required_fields: vec!["url".to_string(), "token".to_string()],
+    }
+}
+
+/// Claude Code against Bedrock through a gateway or proxy that takes a
+/// Bedrock API key
+fn bedrock_preset() -> Preset {
+    let mut default_fields = HashMap::new();
+    default_fields.insert("use_bedrock".to_string(), "1".to_string());
+    default_fields.insert("region".to_string(), "us-east-1".to_string());
+
+    let mut env_mapping = HashMap::new();
+    env_mapping.insert("token".to_string(), "AWS_BEARER_TOKEN_BEDROCK".to_string());
+    env_mapping.insert("url".to_string(), "ANTHROPIC_BEDROCK_BASE_URL".to_string());
+    env_mapping.insert(
+        "use_bedrock".to_string(),
+        "CLAUDE_CODE_USE_BEDROCK".to_string(),
+    );
+    env_mapping.insert("region".to_string(), "AWS_REGION".to_string());
+    env_mapping.insert("model".to_string(), "ANTHROPIC_MODEL".to_string());
+
+    Preset {
+        name: "bedrock".to_string(),
+        description: "Claude on Amazon Bedrock via a gateway or proxy".to_string(),
+        default_fields,
+        env_mapping,
+        required_fields: vec!["url".to_string(), "token".to_string()],
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,9 +672,204 @@ mod tests {
         assert!(preset.env_mapping.contains_key("token"));
     }
 
+    #[test]
+    fn test_detect_preset() {
+        let mut metadata = HashMap::new();
+        metadata.insert("OPENAI_API_KEY".to_string(), "SECRET".to_string());
+        let detected = detect_preset("work", &metadata).unwrap();
+        assert_eq!(detected.preset.name, "openai");
+        assert_eq!(detected.matched, vec!["OPENAI_API_KEY"]);
+        assert_eq!(
+            detected.additions,
+            vec![(
+                "OPENAI_BASE_URL".to_string(),
+                "https://api.openai.com/v1".to_string()
+            )]
+        );
+
+        let mut bare = HashMap::new();
+        bare.insert("GITHUB_PROD".to_string(), "SECRET".to_string());
+        let by_name = detect_preset("github-prod", &bare).unwrap();
+        assert_eq!(by_name.preset.name, "github");
+        assert!(by_name.by_name);
+        assert!(by_name
+            .additions
+            .contains(&("GITHUB_TOKEN".to_string(), "SECRET".to_string())));
+
+        assert!(detect_preset("misc", &bare).is_none());
+    }
+
+    #[test]
+    fn test_relay_env_mapping() {
+        let openai = relay_env_mapping("https://relay.example.com/", "openai", None, None).unwrap();
+        assert!(openai.contains(&(
+            "OPENAI_BASE_URL".to_string(),
+            "https://relay.example.com/v1".to_string()
+        )));
+
+        let anthropic =
+            relay_env_mapping("https://relay.example.com/v1", "anthropic", None, Some("m"))
+                .unwrap();
+        assert_eq!(
+            anthropic,
+            vec![
+                ("ANTHROPIC_AUTH_TOKEN".to_string(), "SECRET".to_string()),
+                (
+                    "ANTHROPIC_BASE_URL".to_string(),
+                    "https://relay.example.com".to_string()
+                ),
+                ("ANTHROPIC_MODEL".to_string(), "m".to_string()),
+            ]
+        );
+
+        let x_api_key = relay_env_mapping(
+            "https://relay.example.com",
+            "anthropic",
+            Some("x-api-key"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(x_api_key[0].0, "ANTHROPIC_API_KEY");
+
+        assert!(relay_env_mapping("not a url", "openai", None, None).is_err());
+        assert!(relay_env_mapping("https://relay.example.com", "gemini", None, None).is_err());
+    }
+
+    #[test]
+    fn test_lint_entry() {
+        let mut metadata = HashMap::new();
+        metadata.insert("OPENAI_API_KEY".to_string(), "SECRET".to_string());
+        metadata.insert(
+            "OPENAI_API_BASE".to_string(),
+            "http://relay.example.com/v1/".to_string(),
+        );
+        metadata.insert("LOCAL_URL".to_string(), "http://localhost:8080".to_string());
+        metadata.insert(
+            "OLLAMA_HOST".to_string(),
+            "http://192.168.1.5:11434/".to_string(),
+        );
+        metadata.insert(
+            "PROXY_URL".to_string(),
+            "http://host.docker.internal:8080".to_string(),
+        );
+
+        let issues = lint_entry("work", &metadata);
+        let messages: Vec<&str> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(issues.len(), 6, "{:?}", messages);
+        assert!(messages[0].contains("private network host"));
+        assert!(messages[1].starts_with("OLLAMA_HOST has a trailing slash"));
+        assert!(messages[2].contains("plain http:// and has a trailing slash"));
+        assert!(messages[3].starts_with("PROXY_URL uses plain http:// to a private"));
+        assert!(messages[4].contains("deprecated; use OPENAI_BASE_URL"));
+        assert!(messages[5].starts_with("OPENAI_MODEL is not set"));
+
+        // One fix per value: the slash of the LAN URL, scheme and slash of the relay
+        assert_eq!(apply_lint_fixes(&mut metadata, &issues), 3);
+        assert_eq!(
+            metadata.get("OPENAI_BASE_URL").map(String::as_str),
+            Some("https://relay.example.com/v1")
+        );
+        assert!(!metadata.contains_key("OPENAI_API_BASE"));
+        assert_eq!(metadata["LOCAL_URL"], "http://localhost:8080");
+        assert_eq!(metadata["OLLAMA_HOST"], "http://192.168.1.5:11434");
+        assert_eq!(metadata["PROXY_URL"], "http://host.docker.internal:8080");
+    }
+
+    #[test]
+    fn test_host_kind() {
+        for (url, kind) in [
+            ("localhost:8080", HostKind::Loopback),
+            ("[::1]:8080/v1", HostKind::Loopback),
+            ("127.0.0.1", HostKind::Loopback),
+            ("10.0.0.2/v1", HostKind::Private),
+            ("172.20.1.1", HostKind::Private),
+            ("169.254.169.254/latest", HostKind::Private),
+            ("[fe80::1]:80", HostKind::Private),
+            ("nas.local:5000", HostKind::Private),
+            ("ollama:11434", HostKind::Private),
+            ("user:pw@relay.example.com", HostKind::Public),
+            ("8.8.8.8", HostKind::Public),
+        ] {
+            assert_eq!(host_kind(url_host(url)), kind, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_lint_entry_keeps_compat_alias() {
+        let vars =
+            relay_env_mapping("https://relay.example.com", "openai", None, Some("m")).unwrap();
+        let metadata: HashMap<String, String> = vars.into_iter().collect();
+        assert!(lint_entry("relay", &metadata).is_empty());
+    }
+
+    #[test]
+    fn test_fill_metadata() {
+        let mut metadata = HashMap::new();
+        get_preset("claude")
+            .unwrap()
+            .fill_metadata(&mut metadata, |_| unreachable!())
+            .unwrap();
+        assert_eq!(metadata["ANTHROPIC_API_KEY"], "SECRET");
+        assert_eq!(metadata["ANTHROPIC_BASE_URL"], "https://api.anthropic.com");
+        assert!(!metadata.contains_key("ANTHROPIC_MODEL"));
+
+        // Given variables win; other required fields are asked for
+        let aws = get_preset("aws").unwrap();
+        assert_eq!(aws.secret_field(), Some("secret_key"));
+        let mut metadata = HashMap::from([("AWS_REGION".to_string(), "eu-west-1".to_string())]);
+        let mut asked = Vec::new();
+        aws.fill_metadata(&mut metadata, |var| {
+            asked.push(var.to_string());
+            Ok(" AKIAEXAMPLE ".to_string())
+        })
+        .unwrap();
+        assert_eq!(asked, ["AWS_ACCESS_KEY_ID"]);
+        assert_eq!(metadata["AWS_ACCESS_KEY_ID"], "AKIAEXAMPLE");
+        assert_eq!(metadata["AWS_SECRET_ACCESS_KEY"], "SECRET");
+        assert_eq!(metadata["AWS_REGION"], "eu-west-1");
+
+        assert!(aws
+            .fill_metadata(&mut HashMap::new(), |_| Ok(String::new()))
+            .is_err());
+        let mut bad_url = HashMap::from([("OPENAI_BASE_URL".to_string(), "not a url".to_string())]);
+        assert!(get_preset("openai")
+            .unwrap()
+            .fill_metadata(&mut bad_url, |_| unreachable!())
+            .is_err());
+    }
+
     #[test]
     fn test_list_presets() {
-        let presets = list_presets();
-        assert_eq!(presets.len(), 5);
+        let presets = builtin_presets();
+        assert_eq!(presets.len(), 13);
+        for preset in &presets {
+            assert_eq!(get_preset(&preset.name).unwrap().name, preset.name);
+            let secret = preset.secret_field().unwrap();
+            assert!(preset.env_mapping.contains_key(secret), "{}", preset.name);
+            for field in preset.default_fields.keys() {
+                assert!(preset.env_mapping.contains_key(field), "{}", preset.name);
+            }
+        }
+        assert!(get_preset("Groq").is_ok());
+
+        let mut custom = get_preset("openai").unwrap();
+        custom.description = "Company OpenAI".to_string();
+        let mut extra = custom.clone();
+        extra.name = "corp".to_string();
+        let merged = merge(builtin_presets(), vec![custom, extra]);
+        assert_eq!(merged.len(), 14);
+        assert_eq!(merged[1].description, "Company OpenAI");
+        assert_eq!(merged[13].name, "corp");
+        assert!(get_preset("nope").is_err());
+
+        let mut metadata = HashMap::new();
+        get_preset("azure")
+            .unwrap()
+            .fill_metadata(&mut metadata, |_| {
+                Ok("https://example.openai.azure.com".to_string())
+            })
+            .unwrap();
+        assert_eq!(metadata["AZURE_OPENAI_API_KEY"], "SECRET");
+        assert_eq!(metadata["OPENAI_API_VERSION"], "2024-10-21");
     }
 }
@@ -0,0 +1,242 @@
+// Client side of `ccm --remote`: a `StorageBackend` calling the server
+
+use super::{REMOTE_CA_ENV, REMOTE_TOKEN_ENV, RPC_PATH};
+use crate::db::{EntryFilter, SecretVersion, StorageBackend};
+use crate::types::Entry;
+use crate::utils::{CcmError, Result};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The storage of a `ccm serve --listen` server
+pub struct RemoteBackend {
+    url: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl RemoteBackend {
+    /// `url` is the server's base URL; the token comes from CCM_REMOTE_TOKEN
+    pub fn new(url: &str) -> Result<Self> {
+        let token = std::env::var(REMOTE_TOKEN_ENV)
+            .ok()
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| {
+                CcmError::InvalidArgument(format!(
+                    "Set {} to the token of the ccm server at {}",
+                    REMOTE_TOKEN_ENV, url
+                ))
+            })?;
+        Self::with_token(url, &token)
+    }
+
+    pub fn with_token(url: &str, token: &str) -> Result<Self> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(CcmError::InvalidArgument(format!(
+                "Remote URL '{}' must start with https:// (or http://)",
+                url
+            )));
+        }
+        let mut agent = crate::net::proxy::builder(url).timeout(TIMEOUT);
+        if let Some(ca) = std::env::var_os(REMOTE_CA_ENV).filter(|p| !p.is_empty()) {
+            agent = agent.tls_config(client_tls(std::path::Path::new(&ca))?);
+        }
+
+        Ok(RemoteBackend {
+            url: format!("{}{}", url.trim_end_matches('/'), RPC_PATH),
+            token: token.to_string(),
+            agent: agent.build(),
+        })
+    }
+
+    /// Call a method and decode its result
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("Content-Type", "application/json")
+            .send_string(&request.to_string());
+        let body = match response {
+            Ok(response) => response.into_string()?,
+            Err(ureq::Error::Status(401, _)) => {
+                return Err(CcmError::Remote(format!(
+                    "{} rejected the token ({})",
+                    self.url, REMOTE_TOKEN_ENV
+                )))
+            }
+            Err(ureq::Error::Status(status, _)) => {
+                return Err(CcmError::Remote(format!(
+                    "{} answered HTTP {}",
+                    self.url, status
+                )))
+            }
+            Err(e) => return Err(CcmError::Remote(e.to_string())),
+        };
+
+        let mut reply: Value = serde_json::from_str(&body)?;
+        if let Some(error) = reply.get("error") {
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            return Err(CcmError::Remote(format!("ccm server: {}", message)));
+        }
+        Ok(serde_json::from_value(reply["result"].take())?)
+    }
+}
+
+impl StorageBackend for RemoteBackend {
+    fn get_entry(&self, name: &str) -> Result<Option<Entry>> {
+        self.call("entry.get", json!({ "name": name }))
+    }
+
+    fn save_entry(&self, name: &str, entry: &Entry) -> Result<()> {
+        self.call("entry.save", json!({ "name": name, "entry": entry }))
+    }
+
+    fn delete_entry(&self, name: &str) -> Result<bool> {
+        self.call("entry.delete", json!({ "name": name }))
+    }
+
+    fn mark_used(&self, name: &str, at: &str) -> Result<()> {
+        self.call("entry.mark_used", json!({ "name": name, "at": at }))
+    }
+
+    fn set_locked(&self, name: &str, locked: bool) -> Result<()> {
+        self.call(
+            "entry.set_locked",
+            json!({ "name": name, "locked": locked }),
+        )
+    }
+
+    fn set_favorite(&self, name: &str, favorite: bool) -> Result<()> {
+        self.call(
+            "entry.set_favorite",
+            json!({ "name": name, "favorite": favorite }),
+        )
+    }
+
+    fn for_each_entry(
+        &self,
+        filter: &EntryFilter,
+        f: &mut dyn FnMut(Entry) -> Result<()>,
+    ) -> Result<()> {
+        let entries: Vec<Entry> = self.call("entry.list", json!({ "filter": filter }))?;
+        entries.into_iter().try_for_each(f)
+    }
+
+    fn search_entries(&self, query: &str, filter: &EntryFilter) -> Result<Vec<Entry>> {
+        self.call("entry.search", json!({ "query": query, "filter": filter }))
+    }
+
+    fn get_secret(&self, name: &str) -> Result<Option<String>> {
+        self.call("secret.get", json!({ "name": name }))
+    }
+
+    fn save_secret(&self, name: &str, encrypted_value: &str) -> Result<()> {
+        self.call(
+            "secret.save",
+            json!({ "name": name, "value": encrypted_value }),
+        )
+    }
+
+    fn delete_secret(&self, name: &str) -> Result<bool> {
+        self.call("secret.delete", json!({ "name": name }))
+    }
+
+    fn get_setting_json(&self, key: &str) -> Result<Option<String>> {
+        self.call("setting.get", json!({ "key": key }))
+    }
+
+    fn save_setting_json(&self, key: &str, value: &str) -> Result<()> {
+        self.call("setting.save", json!({ "key": key, "value": value }))
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<bool> {
+        self.call("setting.delete", json!({ "key": key }))
+    }
+
+    fn append_audit_event(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_name: Option<&str>,
+        user: Option<&str>,
+    ) -> Result<()> {
+        let entries: Vec<&str> = entry_name.into_iter().collect();
+        self.call(
+            "audit.append",
+            json!({ "timestamp": timestamp, "action": action, "entries": entries, "user": user }),
+        )
+    }
+
+    // One round trip (and one server transaction) instead of one per item
+
+    fn insert_entries_batch(&self, items: &[(Entry, String)]) -> Result<()> {
+        self.call("entry.insert_batch", json!({ "items": items }))
+    }
+
+    fn delete_entry_and_secret(&self, name: &str) -> Result<bool> {
+        self.call("entry.delete_with_secret", json!({ "name": name }))
+    }
+
+    fn append_audit_events(
+        &self,
+        timestamp: &str,
+        action: &str,
+        entry_names: &[&str],
+        user: Option<&str>,
+    ) -> Result<()> {
+        if entry_names.is_empty() {
+            return Ok(());
+        }
+        self.call(
+            "audit.append",
+            json!({ "timestamp": timestamp, "action": action, "entries": entry_names, "user": user }),
+        )
+    }
+
+    fn get_secret_versions(&self, name: &str) -> Result<Vec<SecretVersion>> {
+        self.call("secret.versions", json!({ "name": name }))
+    }
+
+    fn restore_secret_version(&self, name: &str, version: i64) -> Result<()> {
+        self.call(
+            "secret.restore_version",
+            json!({ "name": name, "version": version }),
+        )
+    }
+
+    fn restore_entries(&self, items: &[(Entry, String)], replace: bool) -> Result<()> {
+        self.call(
+            "entry.restore",
+            json!({ "items": items, "replace": replace }),
+        )
+    }
+
+    fn apply_entries(&self, saved: &[(Entry, String)], deleted: &[String]) -> Result<()> {
+        self.call("entry.apply", json!({ "saved": saved, "deleted": deleted }))
+    }
+}
+
+/// TLS settings trusting only the certificates in a PEM file
+fn client_tls(ca: &std::path::Path) -> Result<Arc<rustls::ClientConfig>> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        CcmError::InvalidArgument(format!("{} ({}): {}", ca.display(), REMOTE_CA_ENV, e))
+    };
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(ca).map_err(|e| invalid(&e))? {
+        roots
+            .add(cert.map_err(|e| invalid(&e))?)
+            .map_err(|e| invalid(&e))?;
+    }
+    Ok(Arc::new(
+        rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    ))
+}
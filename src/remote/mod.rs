@@ -0,0 +1,174 @@
+// Remote vault: `ccm serve --listen` and `ccm --remote`
+//
+// The server exposes its vault's storage (`crate::db::StorageBackend`) as
+// JSON-RPC 2.0 over HTTP: one request (or batch) per POST to `/v1/rpc`,
+// authenticated with a bearer token, over TLS when given a certificate.
+// A client started with `--remote URL` (or CCM_REMOTE) stores and reads
+// entries there instead of in its local database.
+//
+// Secrets are encrypted and decrypted by the clients with their master key;
+// the server only ever sees ciphertext. Machines that share a vault share
+// the master key (`ccm auth export-key` / `import-key`), the server vault
+// keeps its own.
+//
+// Methods (params -> result):
+//   entry.get {name} -> entry | null        entry.save {name, entry}
+//   entry.delete {name} -> bool             entry.list {filter} -> [entry]
+//   entry.insert_batch {items: [[entry, secret]]}
+//   entry.delete_with_secret {name} -> bool
+//   entry.restore {items: [[entry, secret]], replace}
+//   entry.apply {saved: [[entry, secret]], deleted: [name]}
+//   entry.mark_used {name, at}            entry.set_locked {name, locked}
+//   entry.set_favorite {name, favorite}
+//   entry.search {query, filter} -> [entry], best match first
+//   secret.get {name} -> hex | null         secret.save {name, value}
+//   secret.delete {name} -> bool
+//   secret.versions {name} -> [{version, encrypted_value, created_at,
+//   replaced_at}], oldest first       secret.restore_version {name, version}
+//   setting.get {key} -> json text | null   setting.save {key, value}
+//   setting.delete {key} -> bool
+//   (settings: only active_entry and entry_activations; the server's PIN
+//   state and configuration stay out of reach)
+//   audit.append {timestamp, action, entries, user} (no entries: one event
+//   without an entry)
+
+pub mod client;
+pub mod server;
+
+use std::sync::OnceLock;
+
+/// Environment variable: server URL (like --remote)
+pub const REMOTE_ENV: &str = "CCM_REMOTE";
+/// Environment variable: token sent to the server
+pub const REMOTE_TOKEN_ENV: &str = "CCM_REMOTE_TOKEN";
+/// Environment variable: PEM file of the CA to trust for the server instead
+/// of the public roots (a self-signed certificate works if it is not marked
+/// as a CA)
+pub const REMOTE_CA_ENV: &str = "CCM_REMOTE_CA";
+/// Environment variable: token clients must send (like --token-file)
+pub const SERVER_TOKEN_ENV: &str = "CCM_SERVER_TOKEN";
+
+/// Path of the JSON-RPC endpoint
+pub const RPC_PATH: &str = "/v1/rpc";
+/// Path answering `{"status":"ok"}` without authentication
+pub const HEALTH_PATH: &str = "/v1/health";
+
+static REMOTE_URL: OnceLock<String> = OnceLock::new();
+
+/// Use the server at `url` for this process (--remote)
+pub fn set_remote(url: &str) {
+    let _ = REMOTE_URL.set(url.trim_end_matches('/').to_string());
+}
+
+/// The server in use: --remote, else CCM_REMOTE
+pub fn remote_url() -> Option<String> {
+    REMOTE_URL.get().cloned().or_else(|| {
+        std::env::var(REMOTE_ENV)
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::client::RemoteBackend;
+    use super::server::Server;
+    use super::*;
+    use crate::db::backend::MemoryBackend;
+    use crate::db::StorageBackend;
+    use std::sync::Arc;
+
+    /// Serve a vault on a local port, return its URL
+    fn start(backend: Arc<dyn StorageBackend + Send + Sync>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = Server::new(backend, "s3cret", None, 2);
+        std::thread::spawn(move || server.run(listener));
+        url
+    }
+
+    #[test]
+    fn test_remote_backend() {
+        let backend = Arc::new(MemoryBackend::default());
+        let url = start(backend.clone());
+
+        let remote = RemoteBackend::with_token(&url, "s3cret").unwrap();
+        crate::db::backend::tests::check_backend(&remote);
+        remote
+            .append_audit_event("2024-01-01T00:00:00Z", "vault.unlock", None, None)
+            .unwrap();
+        assert_eq!(backend.audit.lock().unwrap().len(), 3);
+        assert_eq!(
+            backend.audit.lock().unwrap()[2],
+            ("vault.unlock".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_remote_history() {
+        let url = start(Arc::new(crate::db::Database::open_in_memory().unwrap()));
+        let remote = RemoteBackend::with_token(&url, "s3cret").unwrap();
+
+        let entry = crate::types::Entry::new("a".to_string(), Default::default());
+        remote.save_entry("a", &entry).unwrap();
+        remote.save_secret("a", "01").unwrap();
+        remote.save_secret("a", "02").unwrap();
+        let versions = remote.get_secret_versions("a").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].encrypted_value, "01");
+
+        remote.restore_secret_version("a", 1).unwrap();
+        assert_eq!(remote.get_secret("a").unwrap().as_deref(), Some("01"));
+        assert!(remote.restore_secret_version("a", 9).is_err());
+
+        let mut b = entry.clone();
+        b.name = "b".to_string();
+        remote
+            .restore_entries(&[(b, "0b".to_string())], true)
+            .unwrap();
+        assert!(remote.get_entry("a").unwrap().is_none());
+        assert_eq!(remote.get_secret("b").unwrap().as_deref(), Some("0b"));
+    }
+
+    #[test]
+    fn test_server_refusals() {
+        let url = start(Arc::new(MemoryBackend::default()));
+
+        let wrong = RemoteBackend::with_token(&url, "guess").unwrap();
+        let error = wrong.get_entry("a").unwrap_err().to_string();
+        assert!(error.contains("rejected the token"), "{}", error);
+
+        let health = ureq::get(&format!("{}{}", url, HEALTH_PATH))
+            .call()
+            .unwrap();
+        assert_eq!(health.into_string().unwrap(), r#"{"status":"ok"}"#);
+        match ureq::get(&format!("{}/v1/nope", url)).call() {
+            Err(ureq::Error::Status(status, _)) => assert_eq!(status, 404),
+            other => panic!("expected 404, got {:?}", other.map(|r| r.status())),
+        }
+
+        let client = RemoteBackend::with_token(&url, "s3cret").unwrap();
+        for key in ["pinHash", "pinSalt", "__pin_failures__"] {
+            assert!(client.get_setting_json(key).is_err(), "{}", key);
+            assert!(client.save_setting_json(key, "0").is_err(), "{}", key);
+            assert!(client.delete_setting(key).is_err(), "{}", key);
+        }
+
+        // More clients than workers still all get answers
+        let threads: Vec<_> = (0..6)
+            .map(|_| {
+                let url = url.clone();
+                std::thread::spawn(move || {
+                    let client = RemoteBackend::with_token(&url, "s3cret").unwrap();
+                    client.get_entry("a").unwrap()
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert!(thread.join().unwrap().is_none());
+        }
+
+        assert!(RemoteBackend::with_token("host:7700", "s3cret").is_err());
+    }
+}
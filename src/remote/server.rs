@@ -0,0 +1,496 @@
+// HTTP side of `ccm serve --listen`
+//
+// A small HTTP/1.1 server: a fixed pool of threads, one request per
+// connection (`Connection: close`), optional TLS with rustls, bearer token
+// checked on every RPC request. Clients may only touch the settings they
+// keep in the vault (`CLIENT_SETTINGS`), never the PIN state.
+
+use super::{HEALTH_PATH, RPC_PATH};
+use crate::db::{EntryFilter, StorageBackend};
+use crate::rpc::{self, RpcError};
+use crate::types::Entry;
+use crate::utils::{CcmError, Result};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Largest request body accepted
+const MAX_BODY: usize = 16 * 1024 * 1024;
+const MAX_HEADERS: usize = 64;
+const MAX_LINE: u64 = 8 * 1024;
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// Connections served at once by default (--max-connections)
+pub const DEFAULT_MAX_CONNECTIONS: usize = 16;
+
+/// Settings clients read and write through `StorageBackend`: the active
+/// entry of `ccm use` and the activations `ccm status` lists
+const CLIENT_SETTINGS: &[&str] = &[crate::env::ACTIVE_ENTRY_KEY, crate::env::ACTIVATIONS_KEY];
+
+/// The storage served and the token clients must present
+pub struct Server {
+    backend: Arc<dyn StorageBackend + Send + Sync>,
+    token_hash: [u8; 32],
+    tls: Option<Arc<rustls::ServerConfig>>,
+    max_connections: usize,
+}
+
+impl Server {
+    pub fn new(
+        backend: Arc<dyn StorageBackend + Send + Sync>,
+        token: &str,
+        tls: Option<Arc<rustls::ServerConfig>>,
+        max_connections: usize,
+    ) -> Self {
+        Server {
+            backend,
+            token_hash: Sha256::digest(token.as_bytes()).into(),
+            tls,
+            max_connections: max_connections.max(1),
+        }
+    }
+
+    /// Answer connections until the listener fails, `max_connections` at a
+    /// time; further connections wait in the listen backlog
+    pub fn run(self, listener: TcpListener) -> Result<()> {
+        let server = Arc::new(self);
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<TcpStream>(0);
+        let receiver = Arc::new(std::sync::Mutex::new(receiver));
+        for _ in 0..server.max_connections {
+            let server = Arc::clone(&server);
+            let receiver = Arc::clone(&receiver);
+            std::thread::spawn(move || loop {
+                let stream = match receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return,
+                };
+                let Ok(stream) = stream else { return };
+                if let Err(e) = server.connection(stream) {
+                    crate::debug_log!("Remote connection failed: {}", e);
+                }
+            });
+        }
+        for stream in listener.incoming() {
+            // Blocks until a worker is free
+            if sender.send(stream?).is_err() {
+                return Err(CcmError::Remote(
+                    "The connection workers stopped".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn connection(&self, stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        match &self.tls {
+            Some(config) => {
+                let conn = rustls::ServerConnection::new(Arc::clone(config))
+                    .map_err(|e| CcmError::Remote(e.to_string()))?;
+                let mut tls = rustls::StreamOwned::new(conn, stream);
+                self.exchange(&mut tls)?;
+                tls.conn.send_close_notify();
+                tls.flush()?;
+                Ok(())
+            }
+            None => self.exchange(&mut &stream),
+        }
+    }
+
+    /// Read one request and write its response
+    fn exchange<S: Read + Write>(&self, stream: &mut S) -> Result<()> {
+        let (status, body) = match read_request(&mut *stream)? {
+            Ok(request) => self.respond(&request),
+            Err(status) => (status, json!({ "error": reason(status) })),
+        };
+        let body = body.to_string();
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            status,
+            reason(status),
+            body.len()
+        );
+        if status == 401 {
+            head.push_str("WWW-Authenticate: Bearer\r\n");
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body.as_bytes())?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn respond(&self, request: &Request) -> (u16, Value) {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", HEALTH_PATH) => (200, json!({ "status": "ok" })),
+            ("POST", RPC_PATH) if !self.authorized(request) => {
+                (401, json!({ "error": reason(401) }))
+            }
+            ("POST", RPC_PATH) => {
+                let body = String::from_utf8_lossy(&request.body);
+                let backend = &*self.backend;
+                let reply =
+                    rpc::handle_line(&body, &mut |method, params| call(backend, method, params));
+                (200, reply.unwrap_or(Value::Null))
+            }
+            (_, HEALTH_PATH | RPC_PATH) => (405, json!({ "error": reason(405) })),
+            _ => (404, json!({ "error": reason(404) })),
+        }
+    }
+
+    /// Whether the request carries the token (hashes are compared so the
+    /// time taken says nothing about the token)
+    fn authorized(&self, request: &Request) -> bool {
+        request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| {
+                <[u8; 32]>::from(Sha256::digest(token.trim().as_bytes())) == self.token_hash
+            })
+    }
+}
+
+/// TLS settings from a PEM certificate chain and private key
+pub fn tls_config(cert: &Path, key: &Path) -> Result<Arc<rustls::ServerConfig>> {
+    let invalid = |file: &Path, e: &dyn std::fmt::Display| {
+        CcmError::InvalidArgument(format!("{}: {}", file.display(), e))
+    };
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|e| invalid(cert, &e))?;
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|e| invalid(key, &e))?;
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid(cert, &e))?;
+    Ok(Arc::new(config))
+}
+
+struct Request {
+    method: String,
+    path: String,
+    /// Header names lowercased
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parse a request; `Err(status)` for requests to refuse
+fn read_request<S: Read>(stream: S) -> Result<std::result::Result<Request, u16>> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(Err(400));
+    };
+    let method = method.to_string();
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if read_line(&mut reader, &mut line)? == 0 {
+            return Ok(Err(400));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Ok(Err(431));
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Ok(Err(400));
+        };
+        headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    let length = match request.header("content-length").map(str::parse::<usize>) {
+        None => 0,
+        Some(Ok(length)) if length <= MAX_BODY => length,
+        Some(Ok(_)) => return Ok(Err(413)),
+        Some(Err(_)) => return Ok(Err(400)),
+    };
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(Ok(request))
+}
+
+/// A line of the request head, cut at `MAX_LINE` bytes
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> Result<usize> {
+    Ok(reader.by_ref().take(MAX_LINE).read_line(line)?)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NameParams {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchParams {
+    query: String,
+    #[serde(default)]
+    filter: EntryFilter,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MarkUsedParams {
+    name: String,
+    at: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SetLockedParams {
+    name: String,
+    locked: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SetFavoriteParams {
+    name: String,
+    favorite: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SaveEntryParams {
+    name: String,
+    entry: Entry,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListParams {
+    #[serde(default)]
+    filter: EntryFilter,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InsertParams {
+    items: Vec<(Entry, String)>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RestoreParams {
+    items: Vec<(Entry, String)>,
+    replace: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApplyParams {
+    saved: Vec<(Entry, String)>,
+    deleted: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VersionParams {
+    name: String,
+    version: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SaveSecretParams {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyParams {
+    key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SaveSettingParams {
+    key: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuditParams {
+    timestamp: String,
+    action: String,
+    /// One event without an entry when empty
+    #[serde(default)]
+    entries: Vec<String>,
+    user: Option<String>,
+}
+
+/// Refuse settings outside `CLIENT_SETTINGS` (PIN hash, lockout counter,
+/// server configuration)
+fn client_setting(key: &str) -> Result<()> {
+    if CLIENT_SETTINGS.contains(&key) {
+        Ok(())
+    } else {
+        Err(CcmError::InvalidArgument(format!(
+            "Setting '{}' is not available to remote clients",
+            key
+        )))
+    }
+}
+
+fn call(
+    backend: &dyn StorageBackend,
+    method: &str,
+    params: Value,
+) -> std::result::Result<Value, RpcError> {
+    match method {
+        "entry.get" => {
+            let params: NameParams = rpc::params(params)?;
+            Ok(json!(backend.get_entry(&params.name)?))
+        }
+        "entry.save" => {
+            let params: SaveEntryParams = rpc::params(params)?;
+            backend.save_entry(&params.name, &params.entry)?;
+            Ok(Value::Null)
+        }
+        "entry.delete" => {
+            let params: NameParams = rpc::params(params)?;
+            Ok(json!(backend.delete_entry(&params.name)?))
+        }
+        "entry.mark_used" => {
+            let params: MarkUsedParams = rpc::params(params)?;
+            backend.mark_used(&params.name, &params.at)?;
+            Ok(Value::Null)
+        }
+        "entry.set_locked" => {
+            let params: SetLockedParams = rpc::params(params)?;
+            backend.set_locked(&params.name, params.locked)?;
+            Ok(Value::Null)
+        }
+        "entry.set_favorite" => {
+            let params: SetFavoriteParams = rpc::params(params)?;
+            backend.set_favorite(&params.name, params.favorite)?;
+            Ok(Value::Null)
+        }
+        "entry.list" => {
+            let params: ListParams = rpc::params(params)?;
+            let mut entries = Vec::new();
+            backend.for_each_entry(&params.filter, &mut |entry| {
+                entries.push(entry);
+                Ok(())
+            })?;
+            Ok(json!(entries))
+        }
+        "entry.search" => {
+            let params: SearchParams = rpc::params(params)?;
+            Ok(json!(backend.search_entries(&params.query, &params.filter)?))
+        }
+        "entry.insert_batch" => {
+            let params: InsertParams = rpc::params(params)?;
+            backend.insert_entries_batch(&params.items)?;
+            Ok(Value::Null)
+        }
+        "entry.restore" => {
+            let params: RestoreParams = rpc::params(params)?;
+            backend.restore_entries(&params.items, params.replace)?;
+            Ok(Value::Null)
+        }
+        "entry.apply" => {
+            let params: ApplyParams = rpc::params(params)?;
+            backend.apply_entries(&params.saved, &params.deleted)?;
+            Ok(Value::Null)
+        }
+        "entry.delete_with_secret" => {
+            let params: NameParams = rpc::params(params)?;
+            Ok(json!(backend.delete_entry_and_secret(&params.name)?))
+        }
+        "secret.get" => {
+            let params: NameParams = rpc::params(params)?;
+            Ok(json!(backend.get_secret(&params.name)?))
+        }
+        "secret.save" => {
+            let params: SaveSecretParams = rpc::params(params)?;
+            backend.save_secret(&params.name, &params.value)?;
+            Ok(Value::Null)
+        }
+        "secret.delete" => {
+            let params: NameParams = rpc::params(params)?;
+            Ok(json!(backend.delete_secret(&params.name)?))
+        }
+        "secret.versions" => {
+            let params: NameParams = rpc::params(params)?;
+            Ok(json!(backend.get_secret_versions(&params.name)?))
+        }
+        "secret.restore_version" => {
+            let params: VersionParams = rpc::params(params)?;
+            backend.restore_secret_version(&params.name, params.version)?;
+            Ok(Value::Null)
+        }
+        "setting.get" => {
+            let params: KeyParams = rpc::params(params)?;
+            client_setting(&params.key)?;
+            Ok(json!(backend.get_setting_json(&params.key)?))
+        }
+        "setting.save" => {
+            let params: SaveSettingParams = rpc::params(params)?;
+            client_setting(&params.key)?;
+            backend.save_setting_json(&params.key, &params.value)?;
+            Ok(Value::Null)
+        }
+        "setting.delete" => {
+            let params: KeyParams = rpc::params(params)?;
+            client_setting(&params.key)?;
+            Ok(json!(backend.delete_setting(&params.key)?))
+        }
+        "audit.append" => {
+            let params: AuditParams = rpc::params(params)?;
+            let user = params.user.as_deref();
+            if params.entries.is_empty() {
+                backend.append_audit_event(&params.timestamp, &params.action, None, user)?;
+            } else {
+                let names: Vec<&str> = params.entries.iter().map(String::as_str).collect();
+                backend.append_audit_events(&params.timestamp, &params.action, &names, user)?;
+            }
+            Ok(Value::Null)
+        }
+        _ => Err(RpcError::method_not_found(method)),
+    }
+}
@@ -0,0 +1,219 @@
+// JSON-RPC 2.0 over a line-based stream (ccm serve --stdio)
+//
+// Each line read is one request, or a batch (array of requests); each reply
+// is written as one line. Notifications (requests without an id) get no
+// reply. Methods are answered by a handler; errors from ccm are returned with
+// code `APP_ERROR` and the stable `CcmError::code` in `data.code`.
+
+use crate::utils::{CcmError, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// Error raised by ccm itself (entry not found, decryption, ...)
+pub const APP_ERROR: i64 = -32000;
+
+/// The error member of a response
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        RpcError::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))
+    }
+
+    pub fn invalid_params(message: impl std::fmt::Display) -> Self {
+        RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", message))
+    }
+}
+
+impl From<CcmError> for RpcError {
+    fn from(error: CcmError) -> Self {
+        RpcError {
+            code: APP_ERROR,
+            message: error.to_string(),
+            data: Some(json!({ "code": error.code() })),
+        }
+    }
+}
+
+/// Decode the params of a method (missing params count as `{}`)
+pub fn params<T: serde::de::DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(RpcError::invalid_params)
+}
+
+/// Answer requests from `reader` on `writer` until end of input
+pub fn serve<R, W, H>(reader: R, mut writer: W, mut handler: H) -> Result<()>
+where
+    R: BufRead,
+    W: Write,
+    H: FnMut(&str, Value) -> std::result::Result<Value, RpcError>,
+{
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(reply) = handle_line(&line, &mut handler) {
+            writeln!(writer, "{}", reply)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// The reply to one line, None when it only held notifications
+pub fn handle_line<H>(line: &str, handler: &mut H) -> Option<Value>
+where
+    H: FnMut(&str, Value) -> std::result::Result<Value, RpcError>,
+{
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ))
+        }
+    };
+
+    match message {
+        Value::Array(requests) if requests.is_empty() => Some(error_response(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "Empty batch"),
+        )),
+        Value::Array(requests) => {
+            let replies: Vec<Value> = requests
+                .into_iter()
+                .filter_map(|request| handle_request(request, handler))
+                .collect();
+            (!replies.is_empty()).then_some(Value::Array(replies))
+        }
+        request => handle_request(request, handler),
+    }
+}
+
+/// The reply to one request, None for a notification
+fn handle_request<H>(request: Value, handler: &mut H) -> Option<Value>
+where
+    H: FnMut(&str, Value) -> std::result::Result<Value, RpcError>,
+{
+    let Value::Object(mut request) = request else {
+        return Some(error_response(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "A request must be an object"),
+        ));
+    };
+
+    let id = request.remove("id");
+    let method = match (request.remove("jsonrpc"), request.remove("method")) {
+        (Some(Value::String(version)), Some(Value::String(method))) if version == "2.0" => method,
+        _ => {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Expected jsonrpc \"2.0\" and a method"),
+            ))
+        }
+    };
+    let params = request.remove("params").unwrap_or(Value::Null);
+
+    let result = handler(&method, params);
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+        match method {
+            "echo" => Ok(params),
+            "fail" => Err(CcmError::EntryNotFound("openai".to_string()).into()),
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    fn reply(line: &str) -> Option<Value> {
+        handle_line(line, &mut echo)
+    }
+
+    #[test]
+    fn test_handle_line() {
+        assert_eq!(
+            reply(r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":{"a":1}}"#),
+            Some(json!({"jsonrpc":"2.0","id":1,"result":{"a":1}}))
+        );
+        assert_eq!(
+            reply(r#"{"jsonrpc":"2.0","id":"x","method":"fail"}"#),
+            Some(json!({"jsonrpc":"2.0","id":"x","error":{
+                "code":APP_ERROR,"message":"Entry not found: openai","data":{"code":"entry_not_found"}
+            }}))
+        );
+        assert_eq!(
+            reply(r#"{"jsonrpc":"2.0","id":2,"method":"nope"}"#).unwrap()["error"]["code"],
+            METHOD_NOT_FOUND
+        );
+        // Notifications get no reply
+        assert_eq!(reply(r#"{"jsonrpc":"2.0","method":"echo"}"#), None);
+    }
+
+    #[test]
+    fn test_handle_line_errors() {
+        let error_code = |line: &str| reply(line).unwrap()["error"]["code"].clone();
+        assert_eq!(error_code("{not json"), PARSE_ERROR);
+        assert_eq!(error_code("[]"), INVALID_REQUEST);
+        assert_eq!(error_code("42"), INVALID_REQUEST);
+        assert_eq!(error_code(r#"{"id":1,"method":"echo"}"#), INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_batch() {
+        let replies = reply(
+            r#"[{"jsonrpc":"2.0","id":1,"method":"echo","params":[1]},
+                {"jsonrpc":"2.0","method":"echo"},
+                {"jsonrpc":"2.0","id":2,"method":"nope"}]"#,
+        )
+        .unwrap();
+        let replies = replies.as_array().unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0]["result"], json!([1]));
+        assert_eq!(replies[1]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_serve() {
+        let input = b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"echo\",\"params\":\"hi\"}\n\n";
+        let mut out = Vec::new();
+        serve(&input[..], &mut out, echo).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"id\":1,\"jsonrpc\":\"2.0\",\"result\":\"hi\"}\n"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+// In-memory master key cache
+//
+// Holds the unlocked master key behind a read-write lock so concurrent
+// readers never block each other. The key is zeroized when it is replaced,
+// cleared, or has been cached longer than the configured TTL. Anything that
+// keeps a key alive between commands (such as a long-running agent) should
+// use a `KeyCache` rather than its own copy.
+
+use crate::utils::{CcmError, Result};
+use parking_lot::RwLock;
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+/// Environment variable: how long an unlocked key stays in memory
+/// (`300`, `30s`, `15m`, `2h`; `0` or `off` keeps it until exit)
+pub const KEY_TTL_ENV: &str = "CCM_KEY_TTL";
+
+/// A cached key and when it was unlocked
+/// The key is boxed so its address is stable, and locked into RAM (on Unix)
+/// so it is never written to swap
+struct CachedKey {
+    key: Box<[u8; 32]>,
+    loaded_at: Instant,
+}
+
+impl CachedKey {
+    fn new(key: [u8; 32]) -> Self {
+        let key = Box::new(key);
+        #[cfg(unix)]
+        // SAFETY: the pointer and length describe the boxed array, which
+        // lives until drop (where it is unlocked)
+        unsafe {
+            libc::mlock(key.as_ptr() as *const libc::c_void, key.len());
+        }
+        Self {
+            key,
+            loaded_at: Instant::now(),
+        }
+    }
+}
+
+impl Drop for CachedKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        #[cfg(unix)]
+        // SAFETY: same region that was locked in new()
+        unsafe {
+            libc::munlock(self.key.as_ptr() as *const libc::c_void, self.key.len());
+        }
+    }
+}
+
+struct State {
+    key: Option<CachedKey>,
+    instance_id: String,
+    ttl: Option<Duration>,
+}
+
+impl State {
+    fn is_expired(&self, cached: &CachedKey) -> bool {
+        self.ttl
+            .is_some_and(|ttl| cached.loaded_at.elapsed() >= ttl)
+    }
+}
+
+/// Master key cache with an optional time-to-live
+pub struct KeyCache {
+    state: RwLock<State>,
+}
+
+impl KeyCache {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            state: RwLock::new(State {
+                key: None,
+                instance_id: String::new(),
+                ttl,
+            }),
+        }
+    }
+
+    /// The cached key, unless it is missing or expired
+    pub fn get(&self) -> Option<[u8; 32]> {
+        {
+            let state = self.state.read();
+            match &state.key {
+                None => return None,
+                Some(cached) if !state.is_expired(cached) => return Some(*cached.key),
+                Some(_) => {}
+            }
+        }
+
+        // Expired: drop (and zeroize) it under the write lock
+        let mut state = self.state.write();
+        if state
+            .key
+            .as_ref()
+            .is_some_and(|cached| state.is_expired(cached))
+        {
+            state.key = None;
+        }
+        state.key.as_ref().map(|cached| *cached.key)
+    }
+
+    pub fn is_cached(&self) -> bool {
+        self.get().is_some()
+    }
+
+    /// Cache a key for an instance; the TTL starts now
+    pub fn set(&self, key: [u8; 32], instance_id: &str) {
+        let mut state = self.state.write();
+        state.key = Some(CachedKey::new(key));
+        state.instance_id = instance_id.to_string();
+    }
+
+    /// Replace the cached key, keeping the instance ID
+    pub fn set_key(&self, key: [u8; 32]) {
+        let mut state = self.state.write();
+        state.key = Some(CachedKey::new(key));
+    }
+
+    /// Instance ID of the cached key, if one was loaded
+    pub fn instance_id(&self) -> Option<String> {
+        let state = self.state.read();
+        (!state.instance_id.is_empty()).then(|| state.instance_id.clone())
+    }
+
+    /// Zeroize and forget the key and instance ID
+    pub fn clear(&self) {
+        let mut state = self.state.write();
+        state.key = None;
+        state.instance_id.clear();
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.state.read().ttl
+    }
+
+    /// Change the TTL; applies to the key already cached as well
+    pub fn set_ttl(&self, ttl: Option<Duration>) {
+        self.state.write().ttl = ttl;
+    }
+}
+
+/// Parse a TTL value: seconds, or a number with an `s`, `m` or `h` suffix.
+/// `0` and `off` mean no expiry
+pub fn parse_ttl(value: &str) -> Result<Option<Duration>> {
+    let value = value.trim().to_lowercase();
+    let invalid = || {
+        CcmError::InvalidArgument(format!(
+            "Invalid TTL '{}'. Use e.g. 300, 30s, 15m, 2h or off",
+            value
+        ))
+    };
+    if value.is_empty() || value == "off" || value == "0" {
+        return Ok(None);
+    }
+
+    let (amount, unit) = match value.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&value[..i], c),
+        _ => (value.as_str(), 's'),
+    };
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        _ => return Err(invalid()),
+    };
+
+    match amount
+        .parse::<u64>()
+        .ok()
+        .and_then(|a| a.checked_mul(multiplier))
+    {
+        Some(0) => Ok(None),
+        Some(secs) => Ok(Some(Duration::from_secs(secs))),
+        None => Err(invalid()),
+    }
+}
+
+/// TTL from `CCM_KEY_TTL`; an invalid value is reported and ignored
+pub fn ttl_from_env() -> Option<Duration> {
+    let value = std::env::var(KEY_TTL_ENV).ok()?;
+    match parse_ttl(&value) {
+        Ok(ttl) => ttl,
+        Err(e) => {
+            eprintln!("Warning: {}: {}", KEY_TTL_ENV, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_cache_set_get_clear() {
+        let cache = KeyCache::new(None);
+        assert!(cache.get().is_none());
+        assert!(cache.instance_id().is_none());
+
+        cache.set([7u8; 32], "abc123");
+        assert_eq!(cache.get(), Some([7u8; 32]));
+        assert_eq!(cache.instance_id().as_deref(), Some("abc123"));
+
+        cache.set_key([9u8; 32]);
+        assert_eq!(cache.get(), Some([9u8; 32]));
+        assert_eq!(cache.instance_id().as_deref(), Some("abc123"));
+
+        cache.clear();
+        assert!(!cache.is_cached());
+        assert!(cache.instance_id().is_none());
+    }
+
+    #[test]
+    fn test_key_cache_ttl_expiry() {
+        let cache = KeyCache::new(Some(Duration::from_millis(20)));
+        cache.set([1u8; 32], "abc123");
+        assert!(cache.is_cached());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get().is_none());
+        // The instance ID outlives the key; only the key is secret
+        assert_eq!(cache.instance_id().as_deref(), Some("abc123"));
+
+        cache.set([2u8; 32], "abc123");
+        cache.set_ttl(None);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(), Some([2u8; 32]));
+    }
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(parse_ttl("300").unwrap(), Some(Duration::from_secs(300)));
+        assert_eq!(parse_ttl("30s").unwrap(), Some(Duration::from_secs(30)));
+        assert_eq!(parse_ttl("15m").unwrap(), Some(Duration::from_secs(900)));
+        assert_eq!(parse_ttl("2H").unwrap(), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_ttl("off").unwrap(), None);
+        assert_eq!(parse_ttl("0").unwrap(), None);
+        assert!(parse_ttl("5d").is_err());
+        assert!(parse_ttl("soon").is_err());
+    }
+}
@@ -1,7 +1,9 @@
 // Master key management for CCM
 // Compatible with TypeScript version's keyring format
 
+use crate::secrets::key_cache::{self, KeyCache};
 use crate::utils::crypto::*;
+use crate::utils::timings::{self, Phase};
 use crate::utils::{CcmError, Result};
 use anyhow::Context;
 use flate2::read::GzDecoder;
@@ -11,8 +13,6 @@ use keyring::Entry as KeyringEntry;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
-use std::sync::{Arc, Mutex};
-use zeroize::Zeroize;
 
 // Import base64 Engine trait
 use base64::Engine;
@@ -24,32 +24,19 @@ struct EncryptedData {
     ciphertext: String, // Base64 encoded ciphertext
     #[serde(rename = "authTag")]
     auth_tag: String, // Base64 encoded auth tag (16 bytes)
+    /// PBKDF2 iterations of the PIN-derived wrapping key (absent: the default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    iterations: Option<u32>,
 }
 
-/// Master key cache (in-memory, cleared on drop)
-struct MasterKeyCache {
-    key: Option<[u8; 32]>,
-    instance_id: String,
-}
-
-unsafe impl Send for MasterKeyCache {}
-unsafe impl Sync for MasterKeyCache {}
-
-impl Drop for MasterKeyCache {
-    fn drop(&mut self) {
-        if let Some(mut key) = self.key.take() {
-            key.zeroize();
-        }
-    }
+// Global master key cache (TTL from CCM_KEY_TTL)
+lazy_static! {
+    static ref MASTER_KEY_CACHE: KeyCache = KeyCache::new(key_cache::ttl_from_env());
 }
 
-// Global master key cache
-lazy_static! {
-    static ref MASTER_KEY_CACHE: Arc<Mutex<MasterKeyCache>> =
-        Arc::new(Mutex::new(MasterKeyCache {
-            key: None,
-            instance_id: String::new(),
-        }));
+/// The process-wide master key cache
+pub fn master_key_cache() -> &'static KeyCache {
+    &MASTER_KEY_CACHE
 }
 
 /// Get keyring service name for given instance ID
@@ -60,13 +47,16 @@ fn get_keyring_service(instance_id: &str) -> String {
 /// Keyring entry name (matches TypeScript)
 const KEYRING_NAME: &str = "master-key";
 
+/// Keyring entry holding the quick-access data key (see `secrets::quick_access`)
+const QUICK_ACCESS_KEYRING_NAME: &str = "quick-access-key";
+
 /// Check if OS secret service is available
 pub fn check_os_secret_service_available() -> Result<()> {
     // Test with a generic service name
     let entry = KeyringEntry::new("ccm-test", "test")?;
 
     // Try to get the password to check if service is available
-    match entry.get_password() {
+    match timings::measure(Phase::Keyring, || entry.get_password()) {
         Ok(_) => Ok(()),
         Err(keyring::Error::NoEntry) => Ok(()), // Service available, just no entry
         Err(keyring::Error::PlatformFailure(_)) => Err(CcmError::OsSecretServiceRequired),
@@ -86,13 +76,45 @@ pub fn check_os_secret_service_available() -> Result<()> {
     }
 }
 
-/// Direct database access to avoid circular dependency with get_database()
-pub fn get_instance_id_from_config() ->
-Result<Option<String>> {
+/// Plain-text file holding the instance ID of the vault in use (see
+/// `location::instance_id_path`)
+/// The ID only names the keyring service, so it is not secret, and it must be
+/// readable before the (SQLCipher-encrypted) database can be opened
+pub fn instance_id_path() -> std::path::PathBuf {
+    crate::db::location::instance_id_path(&crate::db::db_path())
+}
+
+/// Persist the instance ID
+pub fn save_instance_id(instance_id: &str) -> Result<()> {
+    let path = instance_id_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, instance_id)?;
+    Ok(())
+}
+
+/// Read the instance ID without opening the database through get_database()
+/// (avoids a circular dependency, since the database key comes from the keyring)
+pub fn get_instance_id_from_config() -> Result<Option<String>> {
     use rusqlite::Connection;
 
+    // The default vault's was next to the database before the config directory
     let db_path = crate::db::db_path();
+    let mut paths = vec![instance_id_path()];
+    if db_path == crate::db::location::default_db_path() {
+        paths.push(crate::db::db_dir().join("instance_id"));
+    }
+    for path in paths {
+        if let Ok(content) = std::fs::read_to_string(path) {
+            let instance_id = content.trim();
+            if !instance_id.is_empty() {
+                return Ok(Some(instance_id.to_string()));
+            }
+        }
+    }
 
+    // Legacy location: settings table of an unencrypted database
     // Only proceed if database file exists
     if !db_path.exists() {
         return Ok(None);
@@ -100,19 +122,17 @@ Result<Option<String>> {
 
     let conn = Connection::open(&db_path)?;
 
-    // Try to read instance_id directly from settings table 
     let result = conn
         .query_row(
-            "SELECT value FROM settings WHERE key =
-'secretInstanceId' LIMIT 1",
+            "SELECT value FROM settings WHERE key = 'secretInstanceId' LIMIT 1",
             [],
-            |row| row.get::<_, String>(0))
+            |row| row.get::<_, String>(0),
+        )
         .ok();
 
-    // Parse JSON if needed (settings are stored as JSON)   
+    // Parse JSON if needed (settings are stored as JSON)
     if let Some(raw_value) = result {
-        if let Ok(instance_id) =
-serde_json::from_str::<String>(&raw_value) {
+        if let Ok(instance_id) = serde_json::from_str::<String>(&raw_value) {
             return Ok(Some(instance_id));
         }
     }
@@ -131,10 +151,10 @@ pub fn has_master_key() -> Result<bool> {
     let service = get_keyring_service(&instance_id);
     let entry = KeyringEntry::new(&service, KEYRING_NAME)?;
 
-    match entry.get_password() {
+    match timings::measure(Phase::Keyring, || entry.get_password()) {
         Ok(password) => Ok(!password.is_empty()),
         Err(keyring::Error::NoEntry) => Ok(false),
-        Err(e) => Err(CcmError::Keyring(e)),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -166,6 +186,8 @@ fn encrypt_aes256_gcm_ts(
         Aes256Gcm,
     };
 
+    let _span = timings::span(Phase::Crypto);
+
     // Optionally compress the data first
     let data_to_encrypt = if compress {
         compress_data(plaintext)?
@@ -190,6 +212,7 @@ fn encrypt_aes256_gcm_ts(
         iv: base64::engine::general_purpose::STANDARD.encode(nonce),
         ciphertext: base64::engine::general_purpose::STANDARD.encode(ciphertext),
         auth_tag: base64::engine::general_purpose::STANDARD.encode(auth_tag),
+        iterations: None,
     })
 }
 
@@ -204,6 +227,8 @@ fn decrypt_aes256_gcm_ts(
         Aes256Gcm, Nonce,
     };
 
+    let _span = timings::span(Phase::Crypto);
+
     let iv = base64::engine::general_purpose::STANDARD
         .decode(&encrypted.iv)
         .context("Failed to decode IV")?;
@@ -257,7 +282,7 @@ fn decrypt_aes256_gcm_ts(
 /// Returns None if key doesn't exist (first-time setup)
 /// Use load_master_key_with_pin() when PIN is set
 pub fn load_master_key() -> Result<Option<[u8; 32]>> {
-    load_master_key_internal(&ZERO_KEY)
+    load_master_key_internal(|_| ZERO_KEY)
 }
 
 /// Load master key from OS keyring using PIN-derived key
@@ -266,14 +291,17 @@ pub fn load_master_key_with_pin(pin: &str) -> Result<Option<[u8; 32]>> {
     // Get the stored salt
     let salt = crate::auth::pin::get_pin_salt()?.ok_or_else(|| CcmError::PinRequired)?;
 
-    // Derive key from PIN
-    let protection_key = crate::auth::pin::derive_key_from_pin(pin, &salt);
-
-    load_master_key_internal(&protection_key)
+    // Derive key from PIN, with the iterations the key was wrapped with
+    load_master_key_internal(|iterations| {
+        crate::auth::pin::derive_key_from_pin(pin, &salt, iterations.unwrap_or(PBKDF2_ITERATIONS))
+    })
 }
 
-/// Internal function to load master key with a given protection key
-fn load_master_key_internal(protection_key: &[u8; 32]) -> Result<Option<[u8; 32]>> {
+/// Internal function to load master key; `protection_key` derives the
+/// wrapping key from the PBKDF2 iterations stored with the wrapped key
+fn load_master_key_internal(
+    protection_key: impl FnOnce(Option<u32>) -> [u8; 32],
+) -> Result<Option<[u8; 32]>> {
     // Get instance ID from config
     let instance_id = match get_instance_id_from_config()? {
         Some(id) => id,
@@ -286,10 +314,10 @@ fn load_master_key_internal(protection_key: &[u8; 32]) -> Result<Option<[u8; 32]
     let service = get_keyring_service(&instance_id);
     let entry = KeyringEntry::new(&service, KEYRING_NAME)?;
 
-    let password = match entry.get_password() {
+    let password = match timings::measure(Phase::Keyring, || entry.get_password()) {
         Ok(pwd) => pwd,
         Err(keyring::Error::NoEntry) => return Ok(None),
-        Err(e) => return Err(CcmError::Keyring(e)),
+        Err(e) => return Err(e.into()),
     };
 
     // Parse JSON format (TypeScript stores as JSON)
@@ -297,8 +325,9 @@ fn load_master_key_internal(protection_key: &[u8; 32]) -> Result<Option<[u8; 32]
         .context("Failed to parse master key from keyring (invalid format)")?;
 
     // Decrypt master key using the provided protection key
-    let decrypted_key = decrypt_aes256_gcm_ts(protection_key, &encrypted, true)
-        .context("Failed to decrypt master key from keyring")?;
+    let decrypted_key =
+        decrypt_aes256_gcm_ts(&protection_key(encrypted.iterations), &encrypted, true)
+            .context("Failed to decrypt master key from keyring")?;
 
     if decrypted_key.len() != 32 {
         return Err(CcmError::Encryption(format!(
@@ -311,13 +340,7 @@ fn load_master_key_internal(protection_key: &[u8; 32]) -> Result<Option<[u8; 32]
     key.copy_from_slice(&decrypted_key);
 
     // Cache the key
-    {
-        let mut cache = MASTER_KEY_CACHE
-            .lock()
-            .map_err(|e| CcmError::Unknown(e.to_string()))?;
-        cache.key = Some(key);
-        cache.instance_id = instance_id;
-    }
+    MASTER_KEY_CACHE.set(key, &instance_id);
 
     Ok(Some(key))
 }
@@ -330,30 +353,22 @@ pub fn generate_and_save_master_key() -> Result<[u8; 32]> {
     // Generate or get instance ID
     let instance_id = match get_instance_id_from_config()? {
         Some(id) => id,
-        None => {
-            let new_id = generate_instance_id();
-             // Save instance ID directly to database (avoiding get_database circular dependency)
-            use rusqlite::Connection;
-            let db_path = crate::db::db_path();
-            if let Ok(conn) = Connection::open(&db_path) {
-                let _ = conn.execute(
-                    "INSERT OR REPLACE INTO settings (key, value,       
-            updated_at) VALUES (?1, ?2, ?3)",
-                    rusqlite::params![
-                        "secretInstanceId",
-          
-            serde_json::to_string(&new_id).unwrap_or_default(),
-                        chrono::Utc::now().to_rfc3339()
-                    ]
-                );
-            }
-            new_id
-        }
+        None => generate_instance_id(),
     };
 
+    store_master_key(&master_key, &instance_id)?;
+
+    Ok(master_key)
+}
+
+/// Store a master key in the keyring under the given instance ID
+/// The key is protected with ZERO_KEY; setting a PIN re-encrypts it later
+pub fn store_master_key(master_key: &[u8; 32], instance_id: &str) -> Result<()> {
+    save_instance_id(instance_id)?;
+
     // Encrypt master key with ZERO_KEY (for initial storage)
     // User will set PIN later to re-encrypt with PIN-derived key
-    let encrypted = encrypt_aes256_gcm_ts(&ZERO_KEY, &master_key, true)
+    let encrypted = encrypt_aes256_gcm_ts(&ZERO_KEY, master_key, true)
         .context("Failed to encrypt master key")?;
 
     // Serialize to JSON
@@ -361,34 +376,32 @@ pub fn generate_and_save_master_key() -> Result<[u8; 32]> {
         serde_json::to_string(&encrypted).context("Failed to serialize encrypted master key")?;
 
     // Save to keyring
-    let service = get_keyring_service(&instance_id);
+    let service = get_keyring_service(instance_id);
     let entry = KeyringEntry::new(&service, KEYRING_NAME)?;
-    entry.set_password(&serialized).map_err(CcmError::Keyring)?;
+    timings::measure(Phase::Keyring, || entry.set_password(&serialized)).map_err(CcmError::from)?;
 
     // Cache the key
-    {
-        let mut cache = MASTER_KEY_CACHE
-            .lock()
-            .map_err(|e| CcmError::Unknown(e.to_string()))?;
-        cache.key = Some(master_key);
-        cache.instance_id = instance_id;
-    }
+    MASTER_KEY_CACHE.set(*master_key, instance_id);
 
-    Ok(master_key)
+    Ok(())
+}
+
+/// Whether the master key is already in memory (this process used the vault)
+pub fn is_master_key_cached() -> bool {
+    MASTER_KEY_CACHE.is_cached()
 }
 
 /// Get cached master key (auto-loads from keyring if not cached and no PIN required)
 /// If PIN is set, returns error - use get_cached_master_key_with_pin() instead
 pub fn get_cached_master_key() -> Result<[u8; 32]> {
     // First check if already cached
-    {
-        let cache = MASTER_KEY_CACHE
-            .lock()
-            .map_err(|e| CcmError::Unknown(e.to_string()))?;
+    if let Some(key) = MASTER_KEY_CACHE.get() {
+        return Ok(key);
+    }
 
-        if let Some(key) = cache.key {
-            return Ok(key);
-        }
+    // A running `ccm agent` already unlocked this vault
+    if let Some(key) = load_master_key_from_agent() {
+        return Ok(key);
     }
 
     // Not cached - check if PIN is required
@@ -408,17 +421,21 @@ pub fn get_cached_master_key() -> Result<[u8; 32]> {
     generate_and_save_master_key()
 }
 
+/// The master key held by `ccm agent`, if an agent for this instance runs
+fn load_master_key_from_agent() -> Option<[u8; 32]> {
+    let (key, instance_id) = crate::agent::fetch_key()?;
+    if get_instance_id_from_config().ok()?.as_deref() != Some(instance_id.as_str()) {
+        return None;
+    }
+    MASTER_KEY_CACHE.set(key, &instance_id);
+    Some(key)
+}
+
 /// Get cached master key, loading with PIN if necessary
 pub fn get_cached_master_key_with_pin(pin: &str) -> Result<[u8; 32]> {
     // First check if already cached
-    {
-        let cache = MASTER_KEY_CACHE
-            .lock()
-            .map_err(|e| CcmError::Unknown(e.to_string()))?;
-
-        if let Some(key) = cache.key {
-            return Ok(key);
-        }
+    if let Some(key) = MASTER_KEY_CACHE.get() {
+        return Ok(key);
     }
 
     // Not cached - load with PIN
@@ -467,30 +484,31 @@ pub async fn load_master_key_for_session(pin: Option<&str>) -> Result<()> {
 
 /// Get instance ID
 pub fn get_instance_id() -> Result<String> {
-    let cache = MASTER_KEY_CACHE
-        .lock()
-        .map_err(|e| CcmError::Unknown(e.to_string()))?;
-
-    if !cache.instance_id.is_empty() {
-        Ok(cache.instance_id.clone())
-    } else {
+    match MASTER_KEY_CACHE.instance_id() {
+        Some(instance_id) => Ok(instance_id),
         // Try to get from config
-        get_instance_id_from_config()?.ok_or_else(|| CcmError::MasterKeyNotAvailable)
+        None => get_instance_id_from_config()?.ok_or_else(|| CcmError::MasterKeyNotAvailable),
     }
 }
 
 /// Clear master key from memory (logout)
 pub fn clear_master_key() -> Result<()> {
-    let mut cache = MASTER_KEY_CACHE
-        .lock()
-        .map_err(|e| CcmError::Unknown(e.to_string()))?;
+    MASTER_KEY_CACHE.clear();
+    Ok(())
+}
 
-    if let Some(mut key) = cache.key.take() {
-        key.zeroize();
+/// Delete the master key and the quick-access key of an instance from the
+/// keyring (`ccm reset`); missing entries are not an error
+pub fn delete_keyring_entries(instance_id: &str) -> Result<()> {
+    let service = get_keyring_service(instance_id);
+    for name in [KEYRING_NAME, QUICK_ACCESS_KEYRING_NAME] {
+        let entry = KeyringEntry::new(&service, name)?;
+        match timings::measure(Phase::Keyring, || entry.delete_password()) {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(e.into()),
+        }
     }
-
-    cache.instance_id.clear();
-
+    MASTER_KEY_CACHE.clear();
     Ok(())
 }
 
@@ -511,10 +529,18 @@ pub fn reencrypt_master_key(
     };
 
     // Determine the new protection key
-    let protection_key = match (new_pin, new_salt) {
-        (Some(pin), Some(salt)) => crate::auth::pin::derive_key_from_pin(pin, salt),
-        (None, _) => ZERO_KEY,
-        (Some(_), None) => {
+    let iterations = match new_pin {
+        Some(_) => Some(crate::auth::kdf::configured_iterations(
+            &crate::db::get_database()?,
+        )?),
+        None => None,
+    };
+    let protection_key = match (new_pin, new_salt, iterations) {
+        (Some(pin), Some(salt), Some(iterations)) => {
+            crate::auth::pin::derive_key_from_pin(pin, salt, iterations)
+        }
+        (None, _, _) => ZERO_KEY,
+        (Some(_), _, _) => {
             return Err(CcmError::InvalidArgument(
                 "Salt is required when setting PIN".to_string(),
             ));
@@ -522,8 +548,9 @@ pub fn reencrypt_master_key(
     };
 
     // Encrypt master key with new protection key
-    let encrypted = encrypt_aes256_gcm_ts(&protection_key, &master_key, true)
+    let mut encrypted = encrypt_aes256_gcm_ts(&protection_key, &master_key, true)
         .context("Failed to re-encrypt master key")?;
+    encrypted.iterations = iterations.filter(|&n| n != PBKDF2_ITERATIONS);
 
     let serialized =
         serde_json::to_string(&encrypted).context("Failed to serialize encrypted master key")?;
@@ -531,19 +558,55 @@ pub fn reencrypt_master_key(
     let instance_id = get_instance_id()?;
     let service = get_keyring_service(&instance_id);
     let entry = KeyringEntry::new(&service, KEYRING_NAME)?;
-    entry.set_password(&serialized).map_err(CcmError::Keyring)?;
+    timings::measure(Phase::Keyring, || entry.set_password(&serialized)).map_err(CcmError::from)?;
 
     // Update the cache with the master key
-    {
-        let mut cache = MASTER_KEY_CACHE
-            .lock()
-            .map_err(|e| CcmError::Unknown(e.to_string()))?;
-        cache.key = Some(master_key);
-    }
+    MASTER_KEY_CACHE.set_key(master_key);
 
     Ok(())
 }
 
+/// Load the quick-access data key, creating it if `create` is set
+/// It is wrapped with ZERO_KEY, never the PIN, so it is available without one
+pub fn load_quick_access_key(create: bool) -> Result<Option<[u8; 32]>> {
+    let instance_id = match get_instance_id_from_config()? {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+
+    let service = get_keyring_service(&instance_id);
+    let entry = KeyringEntry::new(&service, QUICK_ACCESS_KEYRING_NAME)?;
+
+    match timings::measure(Phase::Keyring, || entry.get_password()) {
+        Ok(password) => {
+            let encrypted: EncryptedData = serde_json::from_str(&password)
+                .context("Failed to parse quick-access key from keyring (invalid format)")?;
+            let decrypted = decrypt_aes256_gcm_ts(&ZERO_KEY, &encrypted, false)
+                .context("Failed to decrypt quick-access key from keyring")?;
+
+            let key: [u8; 32] = decrypted.as_slice().try_into().map_err(|_| {
+                CcmError::Encryption(format!(
+                    "Invalid quick-access key length: expected 32, got {}",
+                    decrypted.len()
+                ))
+            })?;
+            Ok(Some(key))
+        }
+        Err(keyring::Error::NoEntry) if create => {
+            let key = generate_master_key();
+            let encrypted = encrypt_aes256_gcm_ts(&ZERO_KEY, &key, false)
+                .context("Failed to encrypt quick-access key")?;
+            let serialized = serde_json::to_string(&encrypted)
+                .context("Failed to serialize quick-access key")?;
+            timings::measure(Phase::Keyring, || entry.set_password(&serialized))
+                .map_err(CcmError::from)?;
+            Ok(Some(key))
+        }
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
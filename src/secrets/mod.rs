@@ -1,16 +1,19 @@
 // Secret management (CRUD operations)
 
+pub mod key_cache;
 pub mod master_key;
+pub mod quick_access;
 
-use crate::db::get_database;
+use crate::audit;
+use crate::db::{storage, EntryFilter, StorageBackend};
 use crate::secrets::master_key::get_cached_master_key;
 use crate::types::Entry;
-use crate::utils::{decrypt_aes256_gcm, encrypt_aes256_gcm, Result};
+use crate::utils::{decrypt_aes256_gcm, encrypt_aes256_gcm, fuzzy, CcmError, Result};
 use std::collections::HashMap;
 
 /// Add a new entry with secret
 pub fn add_entry(name: &str, entry: Entry, secret_value: &str) -> Result<()> {
-    let db = get_database()?;
+    let db = storage()?;
 
     // Check if entry already exists
     if db.get_entry(name)?.is_some() {
@@ -28,14 +31,97 @@ pub fn add_entry(name: &str, entry: Entry, secret_value: &str) -> Result<()> {
     // Save entry and secret
     db.save_entry(name, &entry)?;
     db.save_secret(name, &encrypted_hex)?;
+    audit::record(&*db, audit::ENTRY_CREATE, Some(name));
+
+    Ok(())
+}
+
+/// Add many new entries at once (bulk import)
+/// Secrets are encrypted in parallel and everything is written in a single
+/// transaction, so either all entries are added or none. `on_encrypted` is
+/// called once per entry as its secret is encrypted (for progress reporting)
+pub fn add_entries(entries: Vec<(Entry, String)>, on_encrypted: impl Fn() + Sync) -> Result<()> {
+    use rayon::prelude::*;
+
+    let db = storage()?;
+    let master_key = get_cached_master_key()?;
+
+    let encrypted: Vec<(Entry, String)> = entries
+        .into_par_iter()
+        .map(|(entry, secret_value)| {
+            let encrypted_secret = encrypt_aes256_gcm(&master_key, secret_value.as_bytes())?;
+            on_encrypted();
+            Ok((entry, hex::encode(&encrypted_secret)))
+        })
+        .collect::<Result<_>>()?;
+
+    db.insert_entries_batch(&encrypted)?;
+
+    let names: Vec<&str> = encrypted.iter().map(|(e, _)| e.name.as_str()).collect();
+    audit::record_many(&*db, audit::ENTRY_CREATE, &names);
+
+    Ok(())
+}
+
+/// Restore entries from a backup, keeping their timestamps (see
+/// `Database::restore_entries`); with `replace` all other entries are deleted
+pub fn restore_entries(entries: Vec<(Entry, String)>, replace: bool) -> Result<()> {
+    use rayon::prelude::*;
+
+    let db = storage()?;
+    let master_key = get_cached_master_key()?;
+    let previous: Vec<String> = if replace {
+        db.get_all_entries()?.into_keys().collect()
+    } else {
+        Vec::new()
+    };
+
+    let encrypted: Vec<(Entry, String)> = entries
+        .into_par_iter()
+        .map(|(entry, secret_value)| {
+            let encrypted_secret = encrypt_aes256_gcm(&master_key, secret_value.as_bytes())?;
+            Ok((entry, hex::encode(&encrypted_secret)))
+        })
+        .collect::<Result<_>>()?;
+
+    db.restore_entries(&encrypted, replace)?;
+
+    let names: Vec<&str> = encrypted.iter().map(|(e, _)| e.name.as_str()).collect();
+    let deleted: Vec<&str> = previous
+        .iter()
+        .map(String::as_str)
+        .filter(|name| !names.contains(name))
+        .collect();
+    audit::record_many(&*db, audit::ENTRY_DELETE, &deleted);
+    audit::record_many(&*db, audit::ENTRY_RESTORE, &names);
+    for name in names.iter().chain(&deleted) {
+        notify_changed(&*db, name);
+    }
 
     Ok(())
 }
 
 /// Get an entry with its decrypted secret
 pub fn get_entry_with_secret(name: &str) -> Result<(Entry, String)> {
-    let db = get_database()?;
+    read_entry_with_secret(&*storage()?, name)
+}
+
+/// Get an entry with its decrypted secret to use it (get, use, exec...),
+/// recording when it was last used
+pub fn use_entry(name: &str) -> Result<(Entry, String)> {
+    let db = storage()?;
+    let (mut entry, secret) = read_entry_with_secret(&*db, name)?;
+
+    let now = audit::format_timestamp(chrono::Utc::now());
+    match db.mark_used(name, &now) {
+        Ok(()) => entry.last_used_at = Some(now),
+        Err(e) => crate::debug_log!("Failed to record the use of '{}': {}", name, e),
+    }
 
+    Ok((entry, secret))
+}
+
+fn read_entry_with_secret(db: &dyn StorageBackend, name: &str) -> Result<(Entry, String)> {
     let entry = db
         .get_entry(name)?
         .ok_or_else(|| crate::utils::CcmError::EntryNotFound(name.to_string()))?;
@@ -44,29 +130,214 @@ pub fn get_entry_with_secret(name: &str) -> Result<(Entry, String)> {
         .get_secret(name)?
         .ok_or_else(|| crate::utils::CcmError::SecretNotFound(name.to_string()))?;
 
-    let encrypted_bytes = hex::decode(&encrypted_hex)
+    let master_key = get_cached_master_key()?;
+    let secret_value = decrypt_secret(&master_key, &encrypted_hex)?;
+    audit::record(db, audit::SECRET_READ, Some(name));
+
+    Ok((entry, secret_value))
+}
+
+/// Whether an entry's secret equals `value`
+/// The secret never leaves the vault, so this is not recorded as a read
+pub fn secret_matches(name: &str, value: &str) -> Result<bool> {
+    let Some(encrypted_hex) = storage()?.get_secret(name)? else {
+        return Ok(false);
+    };
+    let master_key = get_cached_master_key()?;
+    Ok(decrypt_secret(&master_key, &encrypted_hex)? == value)
+}
+
+/// Decrypt a hex-encoded secret value
+fn decrypt_secret(master_key: &[u8; 32], encrypted_hex: &str) -> Result<String> {
+    let encrypted_bytes = hex::decode(encrypted_hex)
         .map_err(|_| crate::utils::CcmError::Decryption("Invalid hex encoding".to_string()))?;
 
+    let decrypted_bytes = decrypt_aes256_gcm(master_key, &encrypted_bytes)?;
+
+    String::from_utf8(decrypted_bytes)
+        .map_err(|_| crate::utils::CcmError::Decryption("Invalid UTF-8".to_string()))
+}
+
+/// Decrypt a secret as the vault stores it (hex), e.g. one synced from
+/// another machine that shares the master key
+pub fn decrypt_stored_secret(encrypted_hex: &str) -> Result<String> {
     let master_key = get_cached_master_key()?;
-    let decrypted_bytes = decrypt_aes256_gcm(&master_key, &encrypted_bytes)?;
+    decrypt_secret(&master_key, encrypted_hex)
+}
 
-    let secret_value = String::from_utf8(decrypted_bytes)
-        .map_err(|_| crate::utils::CcmError::Decryption("Invalid UTF-8".to_string()))?;
+/// All entries that have a secret, with the secret as stored (encrypted)
+pub fn list_entries_encrypted() -> Result<Vec<(Entry, String)>> {
+    let db = storage()?;
 
-    Ok((entry, secret_value))
+    let mut entries = Vec::new();
+    for (name, entry) in db.get_all_entries()? {
+        if let Some(encrypted_hex) = db.get_secret(&name)? {
+            entries.push((entry, encrypted_hex));
+        }
+    }
+    Ok(entries)
+}
+
+/// Save entries whose secrets are already encrypted with the master key and
+/// delete others, all in one transaction (a merged sync pull)
+pub fn apply_encrypted_entries(saved: &[(Entry, String)], deleted: &[String]) -> Result<()> {
+    let db = storage()?;
+
+    let existing = db.get_all_entries()?;
+    db.apply_entries(saved, deleted)?;
+
+    let (updated, created): (Vec<&str>, Vec<&str>) = saved
+        .iter()
+        .map(|(entry, _)| entry.name.as_str())
+        .partition(|name| existing.contains_key(*name));
+    let deleted: Vec<&str> = deleted.iter().map(String::as_str).collect();
+    for (action, names) in [
+        (audit::ENTRY_CREATE, &created),
+        (audit::ENTRY_UPDATE, &updated),
+        (audit::ENTRY_DELETE, &deleted),
+    ] {
+        if !names.is_empty() {
+            audit::record_many(&*db, action, names);
+        }
+    }
+    for name in created.iter().chain(&updated).chain(&deleted) {
+        notify_changed(&*db, name);
+    }
+
+    Ok(())
+}
+
+/// A version of a secret with its decrypted value
+pub struct SecretHistoryItem {
+    pub version: i64,
+    pub value: String,
+    pub created_at: String,
+    pub replaced_at: Option<String>,
+}
+
+/// Get all versions of an entry's secret, oldest first (last is current)
+pub fn get_secret_history(name: &str) -> Result<Vec<SecretHistoryItem>> {
+    let db = storage()?;
+
+    if db.get_entry(name)?.is_none() {
+        return Err(crate::utils::CcmError::EntryNotFound(name.to_string()));
+    }
+
+    let master_key = get_cached_master_key()?;
+    db.get_secret_versions(name)?
+        .into_iter()
+        .map(|v| {
+            Ok(SecretHistoryItem {
+                version: v.version,
+                value: decrypt_secret(&master_key, &v.encrypted_value)?,
+                created_at: v.created_at,
+                replaced_at: v.replaced_at,
+            })
+        })
+        .collect()
+}
+
+/// Restore an earlier version of an entry's secret
+pub fn rollback_secret(name: &str, version: i64) -> Result<()> {
+    let db = storage()?;
+
+    if db.get_entry(name)?.is_none() {
+        return Err(crate::utils::CcmError::EntryNotFound(name.to_string()));
+    }
+
+    db.restore_secret_version(name, version)?;
+    audit::record(&*db, audit::SECRET_ROLLBACK, Some(name));
+    notify_changed(&*db, name);
+
+    Ok(())
 }
 
 /// Get only the entry (without secret)
 pub fn get_entry(name: &str) -> Result<Entry> {
-    let db = get_database()?;
+    let db = storage()?;
 
     db.get_entry(name)?
         .ok_or_else(|| crate::utils::CcmError::EntryNotFound(name.to_string()))
 }
 
+/// The entry `name` refers to: itself when it exists, else with
+/// `allow_prefix` the only entry whose name starts with it
+/// A name referring to nothing fails with the closest names as suggestions
+pub fn lookup_name(name: &str, allow_prefix: bool) -> Result<String> {
+    let db = storage()?;
+    if db.get_entry(name)?.is_some() {
+        return Ok(name.to_string());
+    }
+    // Names are stored normalized, so `My-API` finds `my-api` under lowercase
+    let normalized = db.get_name_policy()?.normalize(name);
+    if normalized != name && db.get_entry(&normalized)?.is_some() {
+        return Ok(normalized);
+    }
+
+    let mut names: Vec<String> = db.get_all_entries()?.into_keys().collect();
+    names.sort();
+    if allow_prefix {
+        if let Some(found) = fuzzy::unique_prefix(name, names.iter().map(String::as_str)) {
+            return Ok(found.to_string());
+        }
+    }
+    let suggestions: Vec<String> = fuzzy::suggestions(name, names.iter().map(String::as_str), 3)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if suggestions.is_empty() {
+        Err(CcmError::EntryNotFound(name.to_string()))
+    } else {
+        Err(CcmError::EntryNotFoundSuggestions {
+            name: name.to_string(),
+            suggestions,
+        })
+    }
+}
+
+/// Set or clear an entry's `ccm lock` flag
+/// Returns false when the entry already was in that state
+pub fn set_locked(name: &str, locked: bool) -> Result<bool> {
+    let db = storage()?;
+    let entry = db
+        .get_entry(name)?
+        .ok_or_else(|| CcmError::EntryNotFound(name.to_string()))?;
+    if entry.locked == locked {
+        return Ok(false);
+    }
+
+    db.set_locked(name, locked)?;
+    let action = if locked {
+        audit::ENTRY_LOCK
+    } else {
+        audit::ENTRY_UNLOCK
+    };
+    audit::record(&*db, action, Some(name));
+    Ok(true)
+}
+
+/// Toggle an entry's `ccm fav` flag; returns the new state
+pub fn toggle_favorite(name: &str) -> Result<bool> {
+    let db = storage()?;
+    let entry = db
+        .get_entry(name)?
+        .ok_or_else(|| CcmError::EntryNotFound(name.to_string()))?;
+    db.set_favorite(name, !entry.favorite)?;
+    Ok(!entry.favorite)
+}
+
+/// Refuse to delete an entry or change its secret while it is locked,
+/// unless `force_unlock` is given
+pub fn ensure_unlocked(name: &str, force_unlock: bool) -> Result<()> {
+    if !force_unlock && storage()?.get_entry(name)?.is_some_and(|e| e.locked) {
+        return Err(CcmError::EntryLocked(name.to_string()));
+    }
+    Ok(())
+}
+
 /// Update an entry
 pub fn update_entry(name: &str, entry: Entry) -> Result<()> {
-    let db = get_database()?;
+    let db = storage()?;
 
     // Check if entry exists
     if db.get_entry(name)?.is_none() {
@@ -74,13 +345,15 @@ pub fn update_entry(name: &str, entry: Entry) -> Result<()> {
     }
 
     db.save_entry(name, &entry)?;
+    audit::record(&*db, audit::ENTRY_UPDATE, Some(name));
+    notify_changed(&*db, name);
 
     Ok(())
 }
 
 /// Update the secret value for an entry
 pub fn update_secret(name: &str, secret_value: &str) -> Result<()> {
-    let db = get_database()?;
+    let db = storage()?;
 
     // Check if entry exists
     if db.get_entry(name)?.is_none() {
@@ -93,100 +366,78 @@ pub fn update_secret(name: &str, secret_value: &str) -> Result<()> {
     let encrypted_hex = hex::encode(&encrypted_secret);
 
     db.save_secret(name, &encrypted_hex)?;
+    audit::record(&*db, audit::SECRET_UPDATE, Some(name));
+    notify_changed(&*db, name);
 
     Ok(())
 }
 
+/// Flag the active entry as stale when it is modified, and refresh its
+/// quick-access copy
+/// Best effort: a failure here must not fail the write that already happened
+fn notify_changed(db: &dyn StorageBackend, name: &str) {
+    if let Err(e) = crate::env::note_entry_changed(name) {
+        crate::debug_log!("Failed to record change of '{}': {}", name, e);
+    }
+    if let Err(e) = quick_access::sync(db, name) {
+        crate::debug_log!("Failed to update quick-access copy of '{}': {}", name, e);
+    }
+}
+
 /// Delete an entry and its secret
 pub fn delete_entry(name: &str) -> Result<bool> {
-    let db = get_database()?;
+    let db = storage()?;
 
-    let entry_deleted = db.delete_entry(name)?;
-    let secret_deleted = db.delete_secret(name)?;
+    let deleted = db.delete_entry_and_secret(name)?;
+    if deleted {
+        audit::record(&*db, audit::ENTRY_DELETE, Some(name));
+    }
+    notify_changed(&*db, name);
 
-    Ok(entry_deleted || secret_deleted)
+    Ok(deleted)
 }
 
 /// List all entries (without secrets)
 pub fn list_entries() -> Result<HashMap<String, Entry>> {
-    let db = get_database()?;
+    let db = storage()?;
     db.get_all_entries()
 }
 
-/// Search entries by name or metadata
-pub fn search_entries(query: &str) -> Result<Vec<(String, Entry)>> {
-    let all_entries = list_entries()?;
-    let query_lower = query.to_lowercase();
+/// List entries matching a filter (without secrets)
+pub fn list_entries_filtered(filter: &EntryFilter) -> Result<HashMap<String, Entry>> {
+    let db = storage()?;
 
-    let mut results = Vec::new();
+    let mut entries = HashMap::new();
+    db.for_each_entry(filter, &mut |entry| {
+        entries.insert(entry.name.clone(), entry);
+        Ok(())
+    })?;
 
-    for (name, entry) in all_entries {
-        // Search in name
-        if name.to_lowercase().contains(&query_lower) {
-            results.push((name, entry));
-            continue;
-        }
-
-        // Search in notes
-        if let Some(notes) = &entry.notes {
-            if notes.to_lowercase().contains(&query_lower) {
-                results.push((name, entry));
-                continue;
-            }
-        }
-
-        // Search in tags
-        if let Some(tags) = &entry.tags {
-            let mut found_in_tags = false;
-            for tag in tags {
-                if tag.to_lowercase().contains(&query_lower) {
-                    found_in_tags = true;
-                    break;
-                }
-            }
-            if found_in_tags {
-                results.push((name, entry));
-                continue;
-            }
-        }
-
-        // Search in metadata fields
-        let metadata = &entry.metadata;
-        let mut found_in_metadata = false;
-
-        for (key, value) in metadata {
-            // Check key
-            if key.to_lowercase().contains(&query_lower) {
-                found_in_metadata = true;
-                break;
-            }
-            // Check value
-            if value.to_lowercase().contains(&query_lower) {
-                found_in_metadata = true;
-                break;
-            }
-        }
-
-        if found_in_metadata {
-            results.push((name, entry));
-        }
-    }
+    Ok(entries)
+}
 
-    Ok(results)
+/// Search entries by name, tags, notes and variables, best match first
+pub fn search_entries(query: &str, filter: &EntryFilter) -> Result<Vec<(String, Entry)>> {
+    let results = storage()?.search_entries(query, filter)?;
+    Ok(results
+        .into_iter()
+        .map(|entry| (entry.name.clone(), entry))
+        .collect())
 }
 
 /// Get statistics about entries
 pub fn get_stats() -> Result<Stats> {
-    let all_entries = list_entries()?;
+    let db = storage()?;
 
     let mut stats = Stats::default();
 
-    for (_name, entry) in all_entries {
+    db.for_each_entry(&EntryFilter::default(), &mut |entry| {
         stats.total_count += 1;
         if entry.has_secret_placeholder() {
             stats.with_secret_count += 1;
         }
-    }
+        Ok(())
+    })?;
 
     Ok(stats)
 }
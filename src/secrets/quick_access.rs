@@ -0,0 +1,281 @@
+// Quick-access tier: entries usable without the PIN
+//
+// The vault database is encrypted with the master key, which the PIN protects,
+// so entries marked for quick access are copied to a separate file encrypted
+// with their own data key. That key lives in the keyring wrapped with ZERO_KEY,
+// meaning anyone with access to the user's keyring session can read these
+// entries: only low-sensitivity ones belong here.
+
+use crate::db::StorageBackend;
+use crate::secrets::master_key::load_quick_access_key;
+use crate::types::Entry;
+use crate::utils::files::write_private_file;
+use crate::utils::{decrypt_aes256_gcm, encrypt_aes256_gcm, CcmError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Current store file version
+const STORE_VERSION: u32 = 1;
+
+/// On-disk store: the entries, encrypted as one JSON document
+#[derive(Debug, Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    /// Hex-encoded AES-256-GCM ciphertext of the entries map
+    data: String,
+}
+
+/// A quick-access copy of an entry and its secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QuickEntry {
+    entry: Entry,
+    secret: String,
+}
+
+/// Path of the quick-access store of the vault in use (see
+/// `location::quick_access_path`)
+pub fn store_path() -> PathBuf {
+    crate::db::location::quick_access_path(&crate::db::db_path())
+}
+
+/// A quick-access store file and where its key comes from
+pub struct Store {
+    path: PathBuf,
+    /// The data key; `true` creates it when missing
+    key: fn(bool) -> Result<Option<[u8; 32]>>,
+}
+
+/// The store of this vault, keyed from the keyring
+pub fn store() -> Store {
+    Store::new(store_path(), load_quick_access_key)
+}
+
+impl Store {
+    pub fn new(path: PathBuf, key: fn(bool) -> Result<Option<[u8; 32]>>) -> Self {
+        Store { path, key }
+    }
+
+    /// Load all quick-access entries (empty if none were ever marked)
+    fn load(&self) -> Result<BTreeMap<String, QuickEntry>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let file: StoreFile = serde_json::from_str(&std::fs::read_to_string(&self.path)?)?;
+        if file.version != STORE_VERSION {
+            return Err(CcmError::Decryption(format!(
+                "Unsupported quick-access store version {}",
+                file.version
+            )));
+        }
+
+        let key = (self.key)(false)?.ok_or_else(|| {
+            CcmError::Decryption("Quick-access key missing from the keyring".to_string())
+        })?;
+        let encrypted = hex::decode(&file.data)
+            .map_err(|_| CcmError::Decryption("Invalid hex encoding".to_string()))?;
+        let plaintext = decrypt_aes256_gcm(&key, &encrypted)?;
+
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Encrypt and write the entries, removing the file when none are left
+    fn save(&self, entries: &BTreeMap<String, QuickEntry>) -> Result<()> {
+        if entries.is_empty() {
+            if self.path.exists() {
+                std::fs::remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+
+        let key = (self.key)(true)?.ok_or(CcmError::MasterKeyNotAvailable)?;
+        let encrypted = encrypt_aes256_gcm(&key, &serde_json::to_vec(entries)?)?;
+        let file = StoreFile {
+            version: STORE_VERSION,
+            data: hex::encode(encrypted),
+        };
+
+        write_private_file(&self.path, serde_json::to_string_pretty(&file)?.as_bytes())
+    }
+
+    /// Names of the quick-access entries, sorted
+    pub fn names(&self) -> Result<Vec<String>> {
+        Ok(self.load()?.into_keys().collect())
+    }
+
+    /// Get a quick-access entry with its secret, without the master key
+    pub fn get(&self, name: &str) -> Result<Option<(Entry, String)>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        Ok(self.load()?.remove(name).map(|q| (q.entry, q.secret)))
+    }
+
+    /// Add (or replace) the copy of an entry
+    pub fn insert(&self, entry: Entry, secret: String) -> Result<()> {
+        let mut entries = self.load()?;
+        entries.insert(entry.name.clone(), QuickEntry { entry, secret });
+        self.save(&entries)
+    }
+
+    /// Remove an entry from quick access; returns whether it was marked
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        let mut entries = self.load()?;
+        let removed = entries.remove(name).is_some();
+        if removed {
+            self.save(&entries)?;
+        }
+        Ok(removed)
+    }
+
+    /// Bring the copy of `name` in line with the vault after a change
+    /// (refreshed, or dropped when the entry was deleted); `master_key` is
+    /// only asked for when the entry is marked
+    fn refresh(
+        &self,
+        db: &dyn StorageBackend,
+        name: &str,
+        master_key: impl FnOnce() -> Result<[u8; 32]>,
+    ) -> Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+
+        let mut entries = self.load()?;
+        if !entries.contains_key(name) {
+            return Ok(());
+        }
+
+        match (db.get_entry(name)?, db.get_secret(name)?) {
+            (Some(entry), Some(encrypted_hex)) => {
+                let secret = super::decrypt_secret(&master_key()?, &encrypted_hex)?;
+                entries.insert(name.to_string(), QuickEntry { entry, secret });
+            }
+            _ => {
+                entries.remove(name);
+            }
+        }
+
+        self.save(&entries)
+    }
+}
+
+/// Get a quick-access entry with its secret, without the master key
+pub fn get(name: &str) -> Result<Option<(Entry, String)>> {
+    store().get(name)
+}
+
+/// Bring the quick-access copy of `name` in line with the vault after a change
+/// (refreshed, or dropped when the entry was deleted)
+pub(super) fn sync(db: &dyn StorageBackend, name: &str) -> Result<()> {
+    store().refresh(db, name, super::get_cached_master_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    const MASTER_KEY: [u8; 32] = [1; 32];
+
+    fn key(_: bool) -> Result<Option<[u8; 32]>> {
+        Ok(Some([7; 32]))
+    }
+
+    fn other_key(_: bool) -> Result<Option<[u8; 32]>> {
+        Ok(Some([8; 32]))
+    }
+
+    fn entry(name: &str, url: &str) -> Entry {
+        let metadata = [
+            ("API_KEY".to_string(), "SECRET".to_string()),
+            ("API_URL".to_string(), url.to_string()),
+        ];
+        Entry::new(name.to_string(), metadata.into_iter().collect())
+    }
+
+    fn save_encrypted(db: &Database, entry: &Entry, secret: &str) {
+        db.save_entry(&entry.name, entry).unwrap();
+        let encrypted = encrypt_aes256_gcm(&MASTER_KEY, secret.as_bytes()).unwrap();
+        db.save_secret(&entry.name, &hex::encode(encrypted))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_add_get_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path().join("quick-access.json"), key);
+        assert!(store.names().unwrap().is_empty());
+        assert!(store.get("a").unwrap().is_none());
+
+        store
+            .insert(entry("a", "https://a.example.com"), "sk-a".to_string())
+            .unwrap();
+        store
+            .insert(entry("b", "https://b.example.com"), "sk-b".to_string())
+            .unwrap();
+        assert_eq!(store.names().unwrap(), ["a", "b"]);
+        let (a, secret) = store.get("a").unwrap().unwrap();
+        assert_eq!(a.metadata["API_URL"], "https://a.example.com");
+        assert_eq!(secret, "sk-a");
+
+        // Only ciphertext is written
+        let text = std::fs::read_to_string(dir.path().join("quick-access.json")).unwrap();
+        assert!(!text.contains("sk-a") && !text.contains("a.example.com"));
+
+        assert!(store.remove("a").unwrap());
+        assert!(!store.remove("a").unwrap());
+        assert_eq!(store.names().unwrap(), ["b"]);
+        // The file goes with the last entry
+        assert!(store.remove("b").unwrap());
+        assert!(!dir.path().join("quick-access.json").exists());
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quick-access.json");
+        Store::new(path.clone(), key)
+            .insert(entry("a", "https://a.example.com"), "sk-a".to_string())
+            .unwrap();
+
+        let store = Store::new(path, other_key);
+        let err = store.get("a").unwrap_err().to_string();
+        assert!(err.contains("Decryption failed"), "{}", err);
+        assert!(store.names().is_err());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path().join("quick-access.json"), key);
+        let db = Database::open_in_memory().unwrap();
+        let master_key = || Ok(MASTER_KEY);
+
+        // Entries that are not marked are left alone
+        save_encrypted(&db, &entry("a", "https://a.example.com"), "sk-a");
+        store.refresh(&db, "a", master_key).unwrap();
+        assert!(store.names().unwrap().is_empty());
+
+        store
+            .insert(entry("a", "https://a.example.com"), "sk-a".to_string())
+            .unwrap();
+        store
+            .insert(entry("b", "https://b.example.com"), "sk-b".to_string())
+            .unwrap();
+
+        // An update is copied
+        save_encrypted(&db, &entry("a", "https://new.example.com"), "sk-new");
+        store.refresh(&db, "a", master_key).unwrap();
+        let (a, secret) = store.get("a").unwrap().unwrap();
+        assert_eq!(a.metadata["API_URL"], "https://new.example.com");
+        assert_eq!(secret, "sk-new");
+
+        // A deleted entry is dropped, without asking for the master key
+        store
+            .refresh(&db, "b", || Err(CcmError::MasterKeyNotAvailable))
+            .unwrap();
+        assert_eq!(store.names().unwrap(), ["a"]);
+    }
+}
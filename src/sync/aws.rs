@@ -0,0 +1,566 @@
+// AWS backend (ccm sync aws): SSM Parameter Store or Secrets Manager
+//
+// Each entry is one SecureString parameter (or one secret) named
+// `<prefix><entry>`, whose value is the JSON object of the entry's
+// variables; the ccm metadata is kept as JSON in the description. Requests
+// go to the services' JSON APIs, signed with Signature Version 4 using the
+// variables of the aws preset, from an entry or from the environment.
+// AWS_ENDPOINT_URL points the requests elsewhere (LocalStack, a VPC endpoint).
+
+use super::{data_from_string, string_map, RemoteSecret, SyncBackend};
+use crate::presets;
+use crate::utils::crypto::{hmac_sha256, sha256_hash};
+use crate::utils::{CcmError, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Environment variable: endpoint overriding https://<service>.<region>.amazonaws.com
+pub const AWS_ENDPOINT_URL_ENV: &str = "AWS_ENDPOINT_URL";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Error types that mean "no such parameter/secret"
+const NOT_FOUND: &[&str] = &["ParameterNotFound", "ResourceNotFoundException"];
+
+/// The secret store
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AwsService {
+    /// Systems Manager Parameter Store
+    Ssm,
+    SecretsManager,
+}
+
+impl AwsService {
+    /// Signing name and endpoint prefix
+    fn name(self) -> &'static str {
+        match self {
+            AwsService::Ssm => "ssm",
+            AwsService::SecretsManager => "secretsmanager",
+        }
+    }
+
+    /// X-Amz-Target of an action
+    fn target(self, action: &str) -> String {
+        match self {
+            AwsService::Ssm => format!("AmazonSSM.{}", action),
+            AwsService::SecretsManager => format!("secretsmanager.{}", action),
+        }
+    }
+}
+
+/// Credentials and region
+#[derive(Debug, Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub region: Option<String>,
+}
+
+impl AwsCredentials {
+    /// From the variables the aws preset maps (an entry's variables, or the
+    /// process environment)
+    pub fn from_vars(vars: &HashMap<String, String>) -> Result<Self> {
+        let preset = presets::get_preset("aws")?;
+        let var = |field: &str| {
+            preset
+                .env_mapping
+                .get(field)
+                .and_then(|name| vars.get(name))
+                .filter(|value| !value.is_empty())
+                .cloned()
+        };
+
+        let access_key_id = var("access_key");
+        let secret_access_key = var("secret_key");
+        let (Some(access_key_id), Some(secret_access_key)) = (access_key_id, secret_access_key)
+        else {
+            return Err(CcmError::InvalidArgument(
+                "No AWS credentials: pass --credentials <ENTRY> (aws preset) \
+                 or set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY"
+                    .to_string(),
+            ));
+        };
+        Ok(AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token: var("session_token"),
+            region: var("region").or_else(|| vars.get("AWS_DEFAULT_REGION").cloned()),
+        })
+    }
+}
+
+/// A parameter path or secret name prefix in one region
+pub struct AwsStore {
+    credentials: AwsCredentials,
+    region: String,
+    service: AwsService,
+    prefix: String,
+    endpoint: String,
+    agent: ureq::Agent,
+}
+
+impl AwsStore {
+    pub fn new(
+        credentials: AwsCredentials,
+        region: Option<&str>,
+        service: AwsService,
+        prefix: &str,
+    ) -> Result<Self> {
+        let region = region
+            .map(str::to_string)
+            .or_else(|| credentials.region.clone())
+            .ok_or_else(|| {
+                CcmError::InvalidArgument(
+                    "No AWS region: pass --region or set AWS_REGION".to_string(),
+                )
+            })?;
+        let endpoint = std::env::var(AWS_ENDPOINT_URL_ENV)
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| format!("https://{}.{}.amazonaws.com", service.name(), region));
+
+        Ok(AwsStore {
+            credentials,
+            region,
+            service,
+            prefix: normalize_prefix(service, prefix),
+            agent: crate::net::agent(&endpoint, TIMEOUT),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Call an action; None when the parameter or secret does not exist
+    fn call(&self, action: &str, body: Value) -> Result<Option<Value>> {
+        let body = body.to_string();
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let headers = sign(
+            &self.credentials,
+            &self.region,
+            self.service,
+            &host(&self.endpoint),
+            &self.service.target(action),
+            &body,
+            &amz_date,
+        );
+
+        let mut request = self.agent.post(&format!("{}/", self.endpoint));
+        for (name, value) in &headers {
+            request = request.set(name, value);
+        }
+        match request.send_string(&body) {
+            Ok(response) => Ok(Some(serde_json::from_str(&response.into_string()?)?)),
+            Err(ureq::Error::Status(status, response)) => {
+                let body: Value = response
+                    .into_string()
+                    .ok()
+                    .and_then(|body| serde_json::from_str(&body).ok())
+                    .unwrap_or_default();
+                // "__type": "com.amazonaws...#ParameterNotFound" or just the name
+                let kind = body["__type"]
+                    .as_str()
+                    .and_then(|t| t.rsplit('#').next())
+                    .unwrap_or_default();
+                if NOT_FOUND.contains(&kind) {
+                    return Ok(None);
+                }
+                let message = body["message"]
+                    .as_str()
+                    .or_else(|| body["Message"].as_str())
+                    .unwrap_or_default();
+                Err(CcmError::Remote(format!(
+                    "AWS {} returned {} {}: {}",
+                    action, status, kind, message
+                )))
+            }
+            Err(ureq::Error::Transport(e)) => {
+                Err(CcmError::Remote(format!("Cannot reach AWS: {}", e)))
+            }
+        }
+    }
+
+    /// Like `call`, for actions that do not name one parameter or secret
+    fn call_existing(&self, action: &str, body: Value) -> Result<Value> {
+        self.call(action, body)?.ok_or_else(|| {
+            CcmError::Remote(format!(
+                "AWS {} found nothing under {}",
+                action, self.prefix
+            ))
+        })
+    }
+
+    fn full_name(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// Page through a list action, collecting `names(page)`
+    fn list_pages(
+        &self,
+        action: &str,
+        body: Value,
+        names: impl Fn(&Value) -> Vec<String>,
+    ) -> Result<Vec<String>> {
+        let mut all = Vec::new();
+        let mut body = body;
+        loop {
+            let page = self.call_existing(action, body.clone())?;
+            all.extend(names(&page));
+            match page["NextToken"].as_str() {
+                Some(token) => body["NextToken"] = json!(token),
+                None => return Ok(all),
+            }
+        }
+    }
+}
+
+impl SyncBackend for AwsStore {
+    fn list(&self) -> Result<Vec<String>> {
+        let (action, body, list, field) = match self.service {
+            AwsService::Ssm => (
+                "GetParametersByPath",
+                json!({
+                    "Path": path_of(&self.prefix),
+                    "Recursive": true,
+                    "WithDecryption": false,
+                }),
+                "Parameters",
+                "Name",
+            ),
+            AwsService::SecretsManager => {
+                let mut body = json!({ "MaxResults": 100 });
+                if !self.prefix.is_empty() {
+                    body["Filters"] = json!([{ "Key": "name", "Values": [self.prefix] }]);
+                }
+                ("ListSecrets", body, "SecretList", "Name")
+            }
+        };
+
+        let mut names = self.list_pages(action, body, |page| {
+            page[list]
+                .as_array()
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| item[field].as_str())
+                        .filter_map(|name| name.strip_prefix(&self.prefix))
+                        .filter(|name| !name.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        })?;
+        names.sort();
+        Ok(names)
+    }
+
+    fn read(&self, name: &str) -> Result<Option<RemoteSecret>> {
+        let full_name = self.full_name(name);
+        let (value, description) = match self.service {
+            AwsService::Ssm => {
+                let Some(found) = self.call(
+                    "GetParameter",
+                    json!({ "Name": full_name, "WithDecryption": true }),
+                )?
+                else {
+                    return Ok(None);
+                };
+                let described = self.call_existing(
+                    "DescribeParameters",
+                    json!({
+                        "ParameterFilters": [
+                            { "Key": "Name", "Option": "Equals", "Values": [full_name] }
+                        ]
+                    }),
+                )?;
+                (
+                    found["Parameter"]["Value"].clone(),
+                    described["Parameters"][0]["Description"].clone(),
+                )
+            }
+            AwsService::SecretsManager => {
+                let Some(found) = self.call("GetSecretValue", json!({ "SecretId": full_name }))?
+                else {
+                    return Ok(None);
+                };
+                let described =
+                    self.call_existing("DescribeSecret", json!({ "SecretId": full_name }))?;
+                (
+                    found["SecretString"].clone(),
+                    described["Description"].clone(),
+                )
+            }
+        };
+
+        let value = value
+            .as_str()
+            .ok_or_else(|| CcmError::Remote(format!("{} has no string value", full_name)))?;
+        Ok(Some(RemoteSecret {
+            name: name.to_string(),
+            data: data_from_string(value),
+            meta: meta_from_description(description.as_str()),
+        }))
+    }
+
+    fn write(&self, secret: &RemoteSecret) -> Result<()> {
+        let full_name = self.full_name(&secret.name);
+        let value = serde_json::to_string(&secret.data)?;
+        let description = serde_json::to_string(&secret.meta)?;
+        match self.service {
+            AwsService::Ssm => {
+                self.call_existing(
+                    "PutParameter",
+                    json!({
+                        "Name": full_name,
+                        "Value": value,
+                        "Type": "SecureString",
+                        "Description": description,
+                        "Overwrite": true,
+                    }),
+                )?;
+            }
+            AwsService::SecretsManager => {
+                let updated = self.call(
+                    "UpdateSecret",
+                    json!({
+                        "SecretId": full_name,
+                        "SecretString": value,
+                        "Description": description,
+                    }),
+                )?;
+                if updated.is_none() {
+                    self.call_existing(
+                        "CreateSecret",
+                        json!({
+                            "Name": full_name,
+                            "SecretString": value,
+                            "Description": description,
+                        }),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parameter paths start with a slash; both kinds of prefix end with one
+fn normalize_prefix(service: AwsService, prefix: &str) -> String {
+    let trimmed = prefix.trim_matches('/');
+    match (service, trimmed.is_empty()) {
+        (AwsService::Ssm, true) => "/".to_string(),
+        (AwsService::Ssm, false) => format!("/{}/", trimmed),
+        (AwsService::SecretsManager, true) => String::new(),
+        (AwsService::SecretsManager, false) if prefix.starts_with('/') => {
+            format!("/{}/", trimmed)
+        }
+        (AwsService::SecretsManager, false) => format!("{}/", trimmed),
+    }
+}
+
+/// The path GetParametersByPath takes (no trailing slash, except the root)
+fn path_of(prefix: &str) -> &str {
+    match prefix.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+/// ccm metadata from a description (other descriptions are ignored)
+fn meta_from_description(description: Option<&str>) -> BTreeMap<String, String> {
+    description
+        .and_then(|d| serde_json::from_str::<Value>(d).ok())
+        .map(|value| string_map(&value))
+        .unwrap_or_default()
+}
+
+/// host[:port] of an endpoint, as the Host header carries it
+pub(crate) fn host(endpoint: &str) -> String {
+    let (scheme, rest) = endpoint.split_once("://").unwrap_or(("https", endpoint));
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let default_port = if scheme == "http" { ":80" } else { ":443" };
+    authority
+        .strip_suffix(default_port)
+        .unwrap_or(authority)
+        .to_string()
+}
+
+/// Signature Version 4 headers for a POST of `body` to `/`
+fn sign(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: AwsService,
+    host: &str,
+    target: &str,
+    body: &str,
+    amz_date: &str,
+) -> Vec<(String, String)> {
+    let headers = vec![
+        (
+            "content-type".to_string(),
+            "application/x-amz-json-1.1".to_string(),
+        ),
+        ("x-amz-target".to_string(), target.to_string()),
+    ];
+    let request = SignedRequest {
+        method: "POST",
+        path: "/",
+        query: "",
+        host,
+        payload_hash: &hex::encode(sha256_hash(body.as_bytes())),
+    };
+    sign_request(
+        credentials,
+        region,
+        service.name(),
+        &request,
+        headers,
+        amz_date,
+    )
+}
+
+/// What Signature Version 4 covers besides the headers
+pub(crate) struct SignedRequest<'a> {
+    pub method: &'a str,
+    /// URI-encoded path
+    pub path: &'a str,
+    /// Canonical query string (encoded, sorted by name)
+    pub query: &'a str,
+    pub host: &'a str,
+    /// Hex SHA-256 of the body
+    pub payload_hash: &'a str,
+}
+
+/// Signature Version 4: `headers` (lowercase names) plus host, date, session
+/// token and authorization, less host (ureq sets Host itself)
+pub(crate) fn sign_request(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    request: &SignedRequest,
+    mut headers: Vec<(String, String)>,
+    amz_date: &str,
+) -> Vec<(String, String)> {
+    let date = &amz_date[..8];
+    headers.push(("host".to_string(), request.host.to_string()));
+    headers.push(("x-amz-date".to_string(), amz_date.to_string()));
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    headers.sort();
+
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        request.path,
+        request.query,
+        canonical_headers,
+        signed_headers,
+        request.payload_hash
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(sha256_hash(canonical_request.as_bytes()))
+    );
+    let mut key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    headers.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    headers.retain(|(name, _)| name != "host");
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_vars() {
+        let vars = HashMap::from([
+            ("AWS_ACCESS_KEY_ID".to_string(), "AKIA1".to_string()),
+            ("AWS_SECRET_ACCESS_KEY".to_string(), "s3cr3t".to_string()),
+            ("AWS_DEFAULT_REGION".to_string(), "eu-west-1".to_string()),
+        ]);
+        let credentials = AwsCredentials::from_vars(&vars).unwrap();
+        assert_eq!(credentials.access_key_id, "AKIA1");
+        assert_eq!(credentials.session_token, None);
+        assert_eq!(credentials.region.as_deref(), Some("eu-west-1"));
+
+        assert!(AwsCredentials::from_vars(&HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_prefixes() {
+        assert_eq!(normalize_prefix(AwsService::Ssm, "myapp"), "/myapp/");
+        assert_eq!(normalize_prefix(AwsService::Ssm, "/"), "/");
+        assert_eq!(
+            normalize_prefix(AwsService::SecretsManager, "myapp/"),
+            "myapp/"
+        );
+        assert_eq!(normalize_prefix(AwsService::SecretsManager, ""), "");
+        assert_eq!(path_of("/myapp/"), "/myapp");
+        assert_eq!(path_of("/"), "/");
+        assert_eq!(
+            host("https://ssm.us-east-1.amazonaws.com"),
+            "ssm.us-east-1.amazonaws.com"
+        );
+        assert_eq!(host("http://localhost:4566/"), "localhost:4566");
+        assert_eq!(host("https://vpce.example.com:443"), "vpce.example.com");
+    }
+
+    #[test]
+    fn test_sign() {
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+            region: None,
+        };
+        let headers = sign(
+            &credentials,
+            "us-east-1",
+            AwsService::Ssm,
+            "ssm.us-east-1.amazonaws.com",
+            "AmazonSSM.GetParameter",
+            r#"{"Name":"/ccm/openai"}"#,
+            "20150830T123600Z",
+        );
+        let authorization = &headers
+            .iter()
+            .find(|(n, _)| n == "authorization")
+            .unwrap()
+            .1;
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/ssm/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date;x-amz-target, \
+             Signature=b6666b10c9ff4dfc9c46bb760d0386941aeeabf936b477145ce469b99620855c"
+        );
+        assert!(headers.iter().all(|(n, _)| n != "host"));
+    }
+}
@@ -0,0 +1,294 @@
+// Azure Key Vault backend (ccm sync azure)
+//
+// Each entry is one secret named `<prefix><entry>` (other characters than
+// letters, digits and dashes become dashes), whose value is the JSON object
+// of the entry's variables; the ccm metadata and the entry name are kept in
+// the secret's tags. The token comes from a service principal
+// (AZURE_TENANT_ID, AZURE_CLIENT_ID, AZURE_CLIENT_SECRET), else from
+// `az account get-access-token`.
+
+use super::{
+    data_from_string, json_reply, secret_id, send_json, string_map, take_entry_name, RemoteSecret,
+    SyncBackend, NAME_META,
+};
+use crate::utils::{CcmError, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Environment variable: service principal tenant
+pub const AZURE_TENANT_ID_ENV: &str = "AZURE_TENANT_ID";
+/// Environment variable: service principal application ID
+pub const AZURE_CLIENT_ID_ENV: &str = "AZURE_CLIENT_ID";
+/// Environment variable: service principal secret
+pub const AZURE_CLIENT_SECRET_ENV: &str = "AZURE_CLIENT_SECRET";
+
+const API_VERSION: &str = "7.4";
+const RESOURCE: &str = "https://vault.azure.net";
+const STORE: &str = "Azure Key Vault";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Secrets with a name prefix in one key vault
+pub struct AzureKeyVault {
+    url: String,
+    prefix: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl AzureKeyVault {
+    /// `vault` is the vault name or its URL
+    pub fn new(vault: &str, prefix: &str) -> Result<Self> {
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(CcmError::InvalidArgument(
+                "Key Vault secret names allow letters, digits and dashes only".to_string(),
+            ));
+        }
+        let url = if vault.contains("://") {
+            vault.trim_end_matches('/').to_string()
+        } else {
+            format!("https://{}.vault.azure.net", vault)
+        };
+        let agent = crate::net::agent(&url, TIMEOUT);
+
+        Ok(AzureKeyVault {
+            url,
+            prefix: prefix.to_string(),
+            token: access_token(&agent)?,
+            agent,
+        })
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        self.agent
+            .request(method, url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+    }
+
+    fn secret_url(&self, id: &str) -> String {
+        format!("{}/secrets/{}?api-version={}", self.url, id, API_VERSION)
+    }
+}
+
+impl SyncBackend for AzureKeyVault {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut url = format!("{}/secrets?api-version={}", self.url, API_VERSION);
+        loop {
+            let page = send_json(self.request("GET", &url), None, STORE)?.unwrap_or_default();
+            for item in page["value"].as_array().into_iter().flatten() {
+                let id = item["id"].as_str().unwrap_or_default();
+                let id = id.rsplit('/').next().unwrap_or(id);
+                let Some(rest) = id.strip_prefix(&self.prefix).filter(|r| !r.is_empty()) else {
+                    continue;
+                };
+                // Disabled secrets cannot be read
+                if item["attributes"]["enabled"] == json!(false) {
+                    continue;
+                }
+                names.push(take_entry_name(&mut string_map(&item["tags"]), rest));
+            }
+            match page["nextLink"].as_str() {
+                Some(next) => url = next.to_string(),
+                None => break,
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn read(&self, name: &str) -> Result<Option<RemoteSecret>> {
+        let id = secret_id(&self.prefix, name);
+        let Some(secret) = send_json(self.request("GET", &self.secret_url(&id)), None, STORE)?
+        else {
+            return Ok(None);
+        };
+
+        let mut meta = string_map(&secret["tags"]);
+        let holds = take_entry_name(&mut meta, &id[self.prefix.len()..]);
+        if holds != name {
+            return Err(CcmError::Remote(format!(
+                "Key Vault secret '{}' holds entry '{}', not '{}'",
+                id, holds, name
+            )));
+        }
+        let value = secret["value"].as_str().unwrap_or_default();
+        Ok(Some(RemoteSecret {
+            name: name.to_string(),
+            data: data_from_string(value),
+            meta,
+        }))
+    }
+
+    fn write(&self, secret: &RemoteSecret) -> Result<()> {
+        let id = secret_id(&self.prefix, &secret.name);
+        let mut tags = secret.meta.clone();
+        tags.insert(NAME_META.to_string(), secret.name.clone());
+        let body = json!({
+            "value": serde_json::to_string(&secret.data)?,
+            "contentType": "application/json",
+            "tags": tags,
+        });
+        send_json(
+            self.request("PUT", &self.secret_url(&id)),
+            Some(&body),
+            STORE,
+        )?;
+        Ok(())
+    }
+
+    fn remote_name(&self, name: &str) -> String {
+        secret_id(&self.prefix, name)
+    }
+}
+
+/// A token for Key Vault: client credentials of a service principal, else
+/// the Azure CLI's login
+fn access_token(agent: &ureq::Agent) -> Result<String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let (Some(tenant), Some(client_id), Some(client_secret)) = (
+        var(AZURE_TENANT_ID_ENV),
+        var(AZURE_CLIENT_ID_ENV),
+        var(AZURE_CLIENT_SECRET_ENV),
+    ) else {
+        return super::token_from_cli(
+            "az",
+            &[
+                "account",
+                "get-access-token",
+                "--resource",
+                RESOURCE,
+                "--query",
+                "accessToken",
+                "--output",
+                "tsv",
+            ],
+            "install the Azure CLI and run 'az login', or set AZURE_TENANT_ID, \
+             AZURE_CLIENT_ID and AZURE_CLIENT_SECRET",
+        );
+    };
+
+    let scope = format!("{}/.default", RESOURCE);
+    let response = agent
+        .post(&format!(
+            "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+            tenant
+        ))
+        .send_form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+            ("scope", &scope),
+        ]);
+    let reply: Value = json_reply(response, "Microsoft Entra ID")?.unwrap_or_default();
+    reply["access_token"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| CcmError::Remote("Microsoft Entra ID returned no access token".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::tests::serve;
+    use std::collections::BTreeMap;
+
+    fn key_vault(url: &str) -> AzureKeyVault {
+        AzureKeyVault {
+            url: url.to_string(),
+            prefix: "ccm-".to_string(),
+            token: "t0ken".to_string(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    #[test]
+    fn test_list() {
+        let (url, server) = serve(&[
+            (
+                "200 OK",
+                r#"{"value": [
+                    {"id": "{url}/secrets/ccm-work-openai", "tags": {"ccm-name": "work/openai"}},
+                    {"id": "{url}/secrets/other"},
+                    {"id": "{url}/secrets/ccm-off", "attributes": {"enabled": false}}
+                ], "nextLink": "{url}/secrets?api-version=7.4&$skiptoken=2"}"#,
+            ),
+            (
+                "200 OK",
+                r#"{"value": [{"id": "{url}/secrets/ccm-legacy"}]}"#,
+            ),
+        ]);
+        assert_eq!(key_vault(&url).list().unwrap(), ["legacy", "work/openai"]);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /secrets?api-version=7.4 "));
+        assert!(requests[0].contains("Authorization: Bearer t0ken"));
+        assert!(requests[1].starts_with("GET /secrets?api-version=7.4&$skiptoken=2 "));
+    }
+
+    #[test]
+    fn test_read() {
+        let (url, server) = serve(&[
+            (
+                "200 OK",
+                r#"{"value": "{\"OPENAI_API_KEY\":\"sk-1\"}",
+                    "tags": {"ccm-name": "work/openai", "ccm-tags": "ai"}}"#,
+            ),
+            (
+                "200 OK",
+                r#"{"value": "x", "tags": {"ccm-name": "work.openai"}}"#,
+            ),
+            (
+                "404 Not Found",
+                r#"{"error": {"message": "SecretNotFound"}}"#,
+            ),
+            (
+                "403 Forbidden",
+                r#"{"error": {"message": "Access denied"}}"#,
+            ),
+        ]);
+        let vault = key_vault(&url);
+
+        let secret = vault.read("work/openai").unwrap().unwrap();
+        assert_eq!(secret.name, "work/openai");
+        assert_eq!(secret.data["OPENAI_API_KEY"], "sk-1");
+        assert_eq!(
+            secret.meta,
+            BTreeMap::from([("ccm-tags".to_string(), "ai".to_string())])
+        );
+
+        // The secret of another entry with the same secret name
+        let err = vault.read("work/openai").unwrap_err().to_string();
+        assert!(err.contains("holds entry 'work.openai'"), "{}", err);
+        assert_eq!(vault.read("gone").unwrap(), None);
+        let err = vault.read("denied").unwrap_err().to_string();
+        assert!(err.contains("403: Access denied"), "{}", err);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /secrets/ccm-work-openai?api-version=7.4 "));
+    }
+
+    #[test]
+    fn test_write() {
+        let (url, server) = serve(&[("200 OK", "{}")]);
+        let secret = RemoteSecret {
+            name: "work/openai".to_string(),
+            data: BTreeMap::from([("OPENAI_API_KEY".to_string(), "sk-1".to_string())]),
+            meta: BTreeMap::from([("ccm-tags".to_string(), "ai".to_string())]),
+        };
+        key_vault(&url).write(&secret).unwrap();
+
+        let request = server.join().unwrap().remove(0);
+        assert!(request.starts_with("PUT /secrets/ccm-work-openai?api-version=7.4 "));
+        let body: Value = serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["value"], r#"{"OPENAI_API_KEY":"sk-1"}"#);
+        assert_eq!(body["tags"]["ccm-name"], "work/openai");
+        assert_eq!(body["tags"]["ccm-tags"], "ai");
+        assert_eq!(
+            key_vault(&url).remote_name("work.openai"),
+            "ccm-work-openai"
+        );
+    }
+}
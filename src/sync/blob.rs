@@ -0,0 +1,628 @@
+// Encrypted backup sync (ccm sync webdav|s3): the whole vault as one file
+//
+// push uploads what `ccm export` writes, encrypted with a password (and
+// signed) or to age recipients, as a new version named
+// ccm-vault-<UTC time>.json (.json.age), then deletes all but the newest
+// versions. pull applies the newest version (or a chosen one) to the vault.
+// The store only ever holds ciphertext, so a WebDAV share (Nextcloud, a NAS)
+// or any S3-compatible bucket can carry it. Deleting an entry is not synced.
+
+use super::aws::{host, sign_request, AwsCredentials, SignedRequest, AWS_ENDPOINT_URL_ENV};
+use super::{same_entry, SyncReport};
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::crypto::sha256_hash;
+use crate::utils::{CcmError, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use std::io::Read;
+use std::time::Duration;
+
+/// File names of versions start with this
+pub const VERSION_PREFIX: &str = "ccm-vault-";
+/// Environment variable: password of the versions (instead of a prompt)
+pub const SYNC_PASSWORD_ENV: &str = "CCM_SYNC_PASSWORD";
+/// Environment variable: WebDAV password (instead of --credentials)
+pub const WEBDAV_PASSWORD_ENV: &str = "CCM_WEBDAV_PASSWORD";
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A directory (or key prefix) holding files
+pub trait BlobStore {
+    /// Names of the files in it
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// A file's content, None when it does not exist
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Create or replace a file
+    fn put(&self, name: &str, data: &[u8]) -> Result<()>;
+
+    /// Delete a file (no error when it does not exist)
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// The name of a version made at `at`
+pub fn version_name(at: DateTime<Utc>, age: bool) -> String {
+    format!(
+        "{}{}.json{}",
+        VERSION_PREFIX,
+        at.format("%Y%m%dT%H%M%S%.3fZ"),
+        if age { ".age" } else { "" }
+    )
+}
+
+fn is_version(name: &str) -> bool {
+    name.starts_with(VERSION_PREFIX) && (name.ends_with(".json") || name.ends_with(".json.age"))
+}
+
+/// Versions in the store, oldest first
+pub fn versions(store: &dyn BlobStore) -> Result<Vec<String>> {
+    let mut names: Vec<String> = store
+        .list()?
+        .into_iter()
+        .filter(|name| is_version(name))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// The versions to delete to keep the newest `keep` (none when `keep` is 0)
+pub fn stale_versions(versions: &[String], keep: usize) -> &[String] {
+    if keep == 0 {
+        return &[];
+    }
+    &versions[..versions.len().saturating_sub(keep)]
+}
+
+/// What pull does with entries that differ from the version pulled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullMode {
+    /// Leave them
+    Skip,
+    /// Replace them
+    Overwrite,
+    /// Keep whichever side was updated last
+    Merge,
+}
+
+/// Whether `remote` was updated after `local` (entries without a timestamp
+/// count as oldest)
+fn remote_is_newer(local: &Entry, remote: &Entry) -> bool {
+    let time = |entry: &Entry| {
+        entry
+            .updated_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+    };
+    match (time(local), time(remote)) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(local), Some(remote)) => remote > local,
+    }
+}
+
+/// Apply the entries of a version to the vault; locked entries are only
+/// replaced with `force_unlock` (and stay locked). With `dry_run` nothing is
+/// written
+pub fn apply(
+    entries: Vec<(Entry, String)>,
+    mode: PullMode,
+    force_unlock: bool,
+    dry_run: bool,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    let mut new_entries = Vec::new();
+
+    for (entry, secret) in entries {
+        let name = entry.name.clone();
+        match secrets::get_entry_with_secret(&name) {
+            Ok((existing, existing_secret)) => {
+                let replace = match mode {
+                    _ if same_entry(&existing, &entry) && existing_secret == secret => {
+                        report.unchanged.push(name);
+                        continue;
+                    }
+                    PullMode::Skip => false,
+                    PullMode::Overwrite => true,
+                    PullMode::Merge => remote_is_newer(&existing, &entry),
+                };
+                if !replace {
+                    report.skipped.push(name);
+                    continue;
+                }
+                if existing.locked && !force_unlock {
+                    let reason = CcmError::EntryLocked(name.clone()).to_string();
+                    report.failed.push((name, reason));
+                    continue;
+                }
+                if !dry_run {
+                    let mut entry = entry;
+                    entry.created_at = existing.created_at;
+                    entry.locked = existing.locked;
+                    secrets::update_entry(&name, entry)?;
+                    secrets::update_secret(&name, &secret)?;
+                }
+                report.updated.push(name);
+            }
+            Err(CcmError::EntryNotFound(_)) => {
+                report.created.push(name);
+                new_entries.push((entry, secret));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if !dry_run && !new_entries.is_empty() {
+        secrets::add_entries(new_entries, || {})?;
+    }
+    Ok(report)
+}
+
+/// The error for a failed request to `store`
+fn request_error(store: &str, what: &str, error: ureq::Error) -> CcmError {
+    match error {
+        ureq::Error::Status(401 | 403, _) => {
+            CcmError::Remote(format!("{} rejected the credentials ({})", store, what))
+        }
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            let message = xml_texts(&body, "Message").into_iter().next();
+            CcmError::Remote(format!(
+                "{} {} returned {}{}",
+                store,
+                what,
+                status,
+                message.map(|m| format!(": {}", m)).unwrap_or_default()
+            ))
+        }
+        ureq::Error::Transport(e) => CcmError::Remote(format!("Cannot reach {}: {}", store, e)),
+    }
+}
+
+fn read_body(response: ureq::Response) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    response.into_reader().read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// A WebDAV collection (Nextcloud, ownCloud, a NAS, Apache mod_dav, ...)
+pub struct WebDav {
+    /// Collection URL, ending with a slash
+    url: String,
+    authorization: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl WebDav {
+    /// `url` is the collection; basic authentication when `user` is given
+    pub fn new(url: &str, user: Option<&str>, password: Option<&str>) -> Result<Self> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(CcmError::InvalidArgument(format!(
+                "WebDAV URL '{}' must start with https:// (or http://)",
+                url
+            )));
+        }
+        let authorization = user.map(|user| {
+            let credentials = format!("{}:{}", user, password.unwrap_or_default());
+            format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(credentials)
+            )
+        });
+        Ok(WebDav {
+            url: format!("{}/", url.trim_end_matches('/')),
+            authorization,
+            agent: crate::net::agent(url, TIMEOUT),
+        })
+    }
+
+    fn request(&self, method: &str, name: &str) -> ureq::Request {
+        let request = self
+            .agent
+            .request(method, &format!("{}{}", self.url, uri_encode(name, false)));
+        match &self.authorization {
+            Some(value) => request.set("Authorization", value),
+            None => request,
+        }
+    }
+}
+
+impl BlobStore for WebDav {
+    fn list(&self) -> Result<Vec<String>> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/></d:prop></d:propfind>"#;
+        let response = self
+            .request("PROPFIND", "")
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml")
+            .send_string(body);
+        let xml = match response {
+            Ok(response) => response.into_string()?,
+            Err(ureq::Error::Status(404, _)) => return Ok(Vec::new()),
+            Err(e) => return Err(request_error("WebDAV", "PROPFIND", e)),
+        };
+        // The collection itself and sub-collections end with a slash
+        Ok(xml_texts(&xml, "href")
+            .iter()
+            .filter(|href| !href.ends_with('/'))
+            .filter_map(|href| href.rsplit('/').next())
+            .map(percent_decode)
+            .collect())
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match self.request("GET", name).call() {
+            Ok(response) => Ok(Some(read_body(response)?)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(request_error("WebDAV", &format!("GET {}", name), e)),
+        }
+    }
+
+    fn put(&self, name: &str, data: &[u8]) -> Result<()> {
+        let what = format!("PUT {}", name);
+        match self.request("PUT", name).send_bytes(data) {
+            Ok(_) => Ok(()),
+            // The collection does not exist yet
+            Err(ureq::Error::Status(404 | 409, _)) => {
+                match self.request("MKCOL", "").call() {
+                    Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                    Err(e) => return Err(request_error("WebDAV", "MKCOL", e)),
+                }
+                self.request("PUT", name)
+                    .send_bytes(data)
+                    .map_err(|e| request_error("WebDAV", &what, e))?;
+                Ok(())
+            }
+            Err(e) => Err(request_error("WebDAV", &what, e)),
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        match self.request("DELETE", name).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(e) => Err(request_error("WebDAV", &format!("DELETE {}", name), e)),
+        }
+    }
+}
+
+/// A key prefix in an S3 bucket (AWS, MinIO, Cloudflare R2, Backblaze B2,
+/// ...), addressed path-style
+pub struct S3Bucket {
+    credentials: AwsCredentials,
+    region: String,
+    endpoint: String,
+    bucket: String,
+    /// Empty or ending with a slash
+    prefix: String,
+    agent: ureq::Agent,
+}
+
+impl S3Bucket {
+    /// The endpoint defaults to AWS_ENDPOINT_URL, else AWS S3 in the region;
+    /// other endpoints default to region us-east-1
+    pub fn new(
+        credentials: AwsCredentials,
+        region: Option<&str>,
+        endpoint: Option<&str>,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<Self> {
+        let endpoint = endpoint
+            .map(str::to_string)
+            .or_else(|| std::env::var(AWS_ENDPOINT_URL_ENV).ok())
+            .filter(|url| !url.is_empty());
+        let region = region
+            .map(str::to_string)
+            .or_else(|| credentials.region.clone())
+            .or_else(|| endpoint.as_ref().map(|_| "us-east-1".to_string()))
+            .ok_or_else(|| {
+                CcmError::InvalidArgument(
+                    "No AWS region: pass --region or set AWS_REGION".to_string(),
+                )
+            })?;
+        let endpoint = endpoint.unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+
+        let prefix = prefix.trim_matches('/');
+        Ok(S3Bucket {
+            credentials,
+            region,
+            agent: crate::net::agent(&endpoint, TIMEOUT),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket: bucket.to_string(),
+            prefix: if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", prefix)
+            },
+        })
+    }
+
+    /// Send a signed request for an object (or the bucket, with no name);
+    /// None on 404
+    fn send(
+        &self,
+        method: &str,
+        name: Option<&str>,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<Option<ureq::Response>> {
+        let mut path = format!("/{}", uri_encode(&self.bucket, false));
+        if let Some(name) = name {
+            path.push('/');
+            path.push_str(&uri_encode(&format!("{}{}", self.prefix, name), true));
+        }
+        let query = canonical_query(query);
+        let payload_hash = hex::encode(sha256_hash(body));
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let request = SignedRequest {
+            method,
+            path: &path,
+            query: &query,
+            host: &host(&self.endpoint),
+            payload_hash: &payload_hash,
+        };
+        let headers = sign_request(
+            &self.credentials,
+            &self.region,
+            "s3",
+            &request,
+            vec![("x-amz-content-sha256".to_string(), payload_hash.clone())],
+            &amz_date,
+        );
+
+        let mut url = format!("{}{}", self.endpoint, path);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query);
+        }
+        let mut request = self.agent.request(method, &url);
+        for (name, value) in &headers {
+            request = request.set(name, value);
+        }
+        let response = if body.is_empty() {
+            request.call()
+        } else {
+            request.send_bytes(body)
+        };
+        match response {
+            Ok(response) => Ok(Some(response)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => {
+                let what = format!("{} {}", method, name.unwrap_or(&self.bucket));
+                Err(request_error("S3", &what, e))
+            }
+        }
+    }
+}
+
+impl BlobStore for S3Bucket {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type", "2"),
+                ("prefix", self.prefix.as_str()),
+                ("delimiter", "/"),
+            ];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token));
+            }
+            let xml = self
+                .send("GET", None, &query, &[])?
+                .ok_or_else(|| CcmError::Remote(format!("No S3 bucket {}", self.bucket)))?
+                .into_string()?;
+            names.extend(
+                xml_texts(&xml, "Key")
+                    .iter()
+                    .filter_map(|key| key.strip_prefix(&self.prefix))
+                    .map(str::to_string),
+            );
+            token = xml_texts(&xml, "NextContinuationToken").into_iter().next();
+            if xml_texts(&xml, "IsTruncated").first().map(String::as_str) != Some("true")
+                || token.is_none()
+            {
+                return Ok(names);
+            }
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.send("GET", Some(name), &[], &[])?
+            .map(read_body)
+            .transpose()
+    }
+
+    fn put(&self, name: &str, data: &[u8]) -> Result<()> {
+        self.send("PUT", Some(name), &[], data)?
+            .ok_or_else(|| CcmError::Remote(format!("No S3 bucket {}", self.bucket)))?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.send("DELETE", Some(name), &[], &[])?;
+        Ok(())
+    }
+}
+
+/// Percent-encode all but unreserved characters (and slashes when `path`)
+fn uri_encode(value: &str, path: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric()
+            || matches!(byte, b'-' | b'_' | b'.' | b'~')
+            || (path && byte == b'/')
+        {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// A query string as Signature Version 4 wants it: encoded, sorted by name
+fn canonical_query(params: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", uri_encode(name, false), uri_encode(value, false)))
+        .collect();
+    pairs.sort();
+    pairs.join("&")
+}
+
+/// The text of every element named `name`, whatever its namespace prefix
+fn xml_texts(xml: &str, name: &str) -> Vec<String> {
+    let mut texts = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('/') || tag.ends_with('/') {
+            continue;
+        }
+        let tag_name = tag.split_whitespace().next().unwrap_or_default();
+        if tag_name.rsplit(':').next() == Some(name) {
+            let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+            texts.push(
+                text.replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&quot;", "\"")
+                    .replace("&apos;", "'")
+                    .replace("&amp;", "&"),
+            );
+        }
+    }
+    texts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct MemoryStore(RefCell<BTreeMap<String, Vec<u8>>>);
+
+    impl BlobStore for MemoryStore {
+        fn list(&self) -> Result<Vec<String>> {
+            Ok(self.0.borrow().keys().cloned().collect())
+        }
+
+        fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.borrow().get(name).cloned())
+        }
+
+        fn put(&self, name: &str, data: &[u8]) -> Result<()> {
+            self.0.borrow_mut().insert(name.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn delete(&self, name: &str) -> Result<()> {
+            self.0.borrow_mut().remove(name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_versions() {
+        let store = MemoryStore::default();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let first = version_name(at("2024-01-02T03:04:05Z"), false);
+        assert_eq!(first, "ccm-vault-20240102T030405.000Z.json");
+        let second = version_name(at("2024-11-02T00:00:00Z"), true);
+        let third = version_name(at("2025-01-01T00:00:00Z"), false);
+        for name in [&third, &first, &second, &"notes.txt".to_string()] {
+            store.put(name, b"x").unwrap();
+        }
+
+        let all = versions(&store).unwrap();
+        assert_eq!(all, vec![first.clone(), second.clone(), third.clone()]);
+        assert_eq!(stale_versions(&all, 2), &[first]);
+        assert!(stale_versions(&all, 3).is_empty());
+        assert!(stale_versions(&all, 0).is_empty());
+    }
+
+    #[test]
+    fn test_remote_is_newer() {
+        let entry = |updated: Option<&str>| {
+            let mut entry = Entry::new("a".to_string(), Default::default());
+            entry.updated_at = updated.map(str::to_string);
+            entry
+        };
+        let old = entry(Some("2024-01-01T00:00:00Z"));
+        let new = entry(Some("2024-01-01T01:00:00+00:30"));
+        assert!(remote_is_newer(&old, &new));
+        assert!(!remote_is_newer(&new, &old));
+        assert!(remote_is_newer(&entry(None), &old));
+        assert!(!remote_is_newer(&old, &entry(None)));
+    }
+
+    #[test]
+    fn test_xml_texts() {
+        let propfind = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>/dav/ccm/</d:href><d:propstat/></d:response>
+  <d:response><d:href>/dav/ccm/ccm-vault-1.json</d:href></d:response>
+  <D:response><D:href>/dav/ccm/a%20b&amp;c.json</D:href></D:response>
+</d:multistatus>"#;
+        assert_eq!(
+            xml_texts(propfind, "href"),
+            vec![
+                "/dav/ccm/",
+                "/dav/ccm/ccm-vault-1.json",
+                "/dav/ccm/a%20b&c.json"
+            ]
+        );
+        assert_eq!(percent_decode("a%20b&c.json"), "a b&c.json");
+        assert_eq!(percent_decode("100%"), "100%");
+
+        let listing = r#"<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <IsTruncated>false</IsTruncated>
+  <Contents><Key>ccm/ccm-vault-1.json</Key><Size>10</Size></Contents>
+</ListBucketResult>"#;
+        assert_eq!(xml_texts(listing, "Key"), vec!["ccm/ccm-vault-1.json"]);
+        assert_eq!(xml_texts(listing, "IsTruncated"), vec!["false"]);
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        assert_eq!(
+            uri_encode("ccm/ccm vault~1.json", true),
+            "ccm/ccm%20vault~1.json"
+        );
+        assert_eq!(uri_encode("a/b", false), "a%2Fb");
+        assert_eq!(
+            canonical_query(&[("prefix", "ccm/"), ("list-type", "2"), ("delimiter", "/")]),
+            "delimiter=%2F&list-type=2&prefix=ccm%2F"
+        );
+    }
+}
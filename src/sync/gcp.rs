@@ -0,0 +1,309 @@
+// Google Cloud Secret Manager backend (ccm sync gcp)
+//
+// Each entry is one secret named `<prefix><entry>` (other characters than
+// letters, digits and dashes become dashes), whose latest version holds the
+// JSON object of the entry's variables; the ccm metadata and the entry name
+// are kept in the secret's annotations. Pushing a change adds a version.
+// The token comes from GOOGLE_OAUTH_ACCESS_TOKEN, else from
+// `gcloud auth print-access-token`.
+
+use super::{
+    data_from_string, secret_id, send_json, string_map, take_entry_name, RemoteSecret, SyncBackend,
+    NAME_META,
+};
+use crate::utils::{CcmError, Result};
+use base64::Engine;
+use serde_json::json;
+use std::time::Duration;
+
+/// Environment variable: access token
+pub const GOOGLE_OAUTH_ACCESS_TOKEN_ENV: &str = "GOOGLE_OAUTH_ACCESS_TOKEN";
+/// Environment variables naming the project (default for --project)
+pub const PROJECT_ENVS: &[&str] = &["GOOGLE_CLOUD_PROJECT", "CLOUDSDK_CORE_PROJECT"];
+
+const API: &str = "https://secretmanager.googleapis.com/v1";
+const STORE: &str = "Secret Manager";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Secrets with a name prefix in one project
+pub struct GcpSecretManager {
+    api: String,
+    project: String,
+    prefix: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl GcpSecretManager {
+    pub fn new(project: Option<&str>, prefix: &str) -> Result<Self> {
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(CcmError::InvalidArgument(
+                "Secret Manager secret IDs allow letters, digits, dashes and underscores only"
+                    .to_string(),
+            ));
+        }
+        let project = project
+            .map(str::to_string)
+            .or_else(|| {
+                PROJECT_ENVS
+                    .iter()
+                    .find_map(|var| std::env::var(var).ok().filter(|p| !p.is_empty()))
+            })
+            .ok_or_else(|| {
+                CcmError::InvalidArgument(
+                    "No Google Cloud project: pass --project or set GOOGLE_CLOUD_PROJECT"
+                        .to_string(),
+                )
+            })?;
+
+        Ok(GcpSecretManager {
+            api: API.to_string(),
+            project,
+            prefix: prefix.to_string(),
+            token: access_token()?,
+            agent: crate::net::agent(API, TIMEOUT),
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        self.agent
+            .request(
+                method,
+                &format!("{}/projects/{}/{}", self.api, self.project, path),
+            )
+            .set("Authorization", &format!("Bearer {}", self.token))
+    }
+}
+
+impl SyncBackend for GcpSecretManager {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut page_token = String::new();
+        loop {
+            let request = self
+                .request("GET", "secrets")
+                .query("pageSize", "250")
+                .query("pageToken", &page_token);
+            let page = send_json(request, None, STORE)?.unwrap_or_default();
+            for secret in page["secrets"].as_array().into_iter().flatten() {
+                // projects/<number>/secrets/<id>
+                let id = secret["name"].as_str().unwrap_or_default();
+                let id = id.rsplit('/').next().unwrap_or(id);
+                if let Some(rest) = id.strip_prefix(&self.prefix).filter(|r| !r.is_empty()) {
+                    names.push(take_entry_name(
+                        &mut string_map(&secret["annotations"]),
+                        rest,
+                    ));
+                }
+            }
+            match page["nextPageToken"].as_str() {
+                Some(next) if !next.is_empty() => page_token = next.to_string(),
+                _ => break,
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn read(&self, name: &str) -> Result<Option<RemoteSecret>> {
+        let id = secret_id(&self.prefix, name);
+        let Some(secret) = send_json(self.request("GET", &format!("secrets/{}", id)), None, STORE)?
+        else {
+            return Ok(None);
+        };
+
+        let mut meta = string_map(&secret["annotations"]);
+        let holds = take_entry_name(&mut meta, &id[self.prefix.len()..]);
+        if holds != name {
+            return Err(CcmError::Remote(format!(
+                "Secret '{}' holds entry '{}', not '{}'",
+                id, holds, name
+            )));
+        }
+
+        // A secret without versions has nothing to pull
+        let Some(version) = send_json(
+            self.request("GET", &format!("secrets/{}/versions/latest:access", id)),
+            None,
+            STORE,
+        )?
+        else {
+            return Ok(None);
+        };
+        let payload = version["payload"]["data"].as_str().unwrap_or_default();
+        let payload = base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|e| CcmError::Remote(format!("Secret '{}' payload: {}", id, e)))?;
+        Ok(Some(RemoteSecret {
+            name: name.to_string(),
+            data: data_from_string(&String::from_utf8_lossy(&payload)),
+            meta,
+        }))
+    }
+
+    fn write(&self, secret: &RemoteSecret) -> Result<()> {
+        let id = secret_id(&self.prefix, &secret.name);
+        let mut annotations = secret.meta.clone();
+        annotations.insert(NAME_META.to_string(), secret.name.clone());
+        let annotations = json!({ "annotations": annotations });
+
+        let updated = send_json(
+            self.request("PATCH", &format!("secrets/{}", id))
+                .query("updateMask", "annotations"),
+            Some(&annotations),
+            STORE,
+        )?;
+        if updated.is_none() {
+            let mut body = annotations;
+            body["replication"] = json!({ "automatic": {} });
+            send_json(
+                self.request("POST", "secrets").query("secretId", &id),
+                Some(&body),
+                STORE,
+            )?;
+        }
+
+        let data =
+            base64::engine::general_purpose::STANDARD.encode(serde_json::to_string(&secret.data)?);
+        send_json(
+            self.request("POST", &format!("secrets/{}:addVersion", id)),
+            Some(&json!({ "payload": { "data": data } })),
+            STORE,
+        )?;
+        Ok(())
+    }
+
+    fn remote_name(&self, name: &str) -> String {
+        secret_id(&self.prefix, name)
+    }
+}
+
+/// GOOGLE_OAUTH_ACCESS_TOKEN, else the gcloud CLI's login
+fn access_token() -> Result<String> {
+    if let Some(token) = std::env::var(GOOGLE_OAUTH_ACCESS_TOKEN_ENV)
+        .ok()
+        .filter(|t| !t.is_empty())
+    {
+        return Ok(token);
+    }
+    super::token_from_cli(
+        "gcloud",
+        &["auth", "print-access-token"],
+        "install the Google Cloud CLI and run 'gcloud auth login', or set GOOGLE_OAUTH_ACCESS_TOKEN",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::tests::serve;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+
+    fn secret_manager(url: &str) -> GcpSecretManager {
+        GcpSecretManager {
+            api: url.to_string(),
+            project: "proj".to_string(),
+            prefix: "ccm-".to_string(),
+            token: "t0ken".to_string(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    fn body(request: &str) -> Value {
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_list() {
+        let (url, server) = serve(&[
+            (
+                "200 OK",
+                r#"{"secrets": [
+                    {"name": "projects/1/secrets/ccm-work-openai",
+                     "annotations": {"ccm-name": "work/openai"}},
+                    {"name": "projects/1/secrets/other"}
+                ], "nextPageToken": "p2"}"#,
+            ),
+            (
+                "200 OK",
+                r#"{"secrets": [{"name": "projects/1/secrets/ccm-legacy"}]}"#,
+            ),
+        ]);
+        assert_eq!(
+            secret_manager(&url).list().unwrap(),
+            ["legacy", "work/openai"]
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /projects/proj/secrets?pageSize=250&pageToken= "));
+        assert!(requests[0].contains("Authorization: Bearer t0ken"));
+        assert!(requests[1].starts_with("GET /projects/proj/secrets?pageSize=250&pageToken=p2 "));
+    }
+
+    #[test]
+    fn test_read() {
+        // {"OPENAI_API_KEY":"sk-1"}
+        let payload = r#"{"payload": {"data": "eyJPUEVOQUlfQVBJX0tFWSI6InNrLTEifQ=="}}"#;
+        let (url, server) = serve(&[
+            (
+                "200 OK",
+                r#"{"annotations": {"ccm-name": "work/openai", "ccm-tags": "ai"}}"#,
+            ),
+            ("200 OK", payload),
+            ("200 OK", r#"{"annotations": {"ccm-name": "work.openai"}}"#),
+            ("200 OK", "{}"),
+            ("404 Not Found", r#"{"error": {"message": "no versions"}}"#),
+            ("404 Not Found", r#"{"error": {"message": "not found"}}"#),
+        ]);
+        let manager = secret_manager(&url);
+
+        let secret = manager.read("work/openai").unwrap().unwrap();
+        assert_eq!(secret.data["OPENAI_API_KEY"], "sk-1");
+        assert_eq!(
+            secret.meta,
+            BTreeMap::from([("ccm-tags".to_string(), "ai".to_string())])
+        );
+        let err = manager.read("work/openai").unwrap_err().to_string();
+        assert!(err.contains("holds entry 'work.openai'"), "{}", err);
+        // A secret without versions, and no secret
+        assert_eq!(manager.read("empty").unwrap(), None);
+        assert_eq!(manager.read("gone").unwrap(), None);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /projects/proj/secrets/ccm-work-openai "));
+        assert!(requests[1]
+            .starts_with("GET /projects/proj/secrets/ccm-work-openai/versions/latest:access "));
+    }
+
+    #[test]
+    fn test_write() {
+        let (url, server) = serve(&[
+            ("404 Not Found", r#"{"error": {"message": "not found"}}"#),
+            ("200 OK", "{}"),
+            ("200 OK", "{}"),
+        ]);
+        let secret = RemoteSecret {
+            name: "work/openai".to_string(),
+            data: BTreeMap::from([("OPENAI_API_KEY".to_string(), "sk-1".to_string())]),
+            meta: BTreeMap::new(),
+        };
+        secret_manager(&url).write(&secret).unwrap();
+
+        // A missing secret is created before its version is added
+        let requests = server.join().unwrap();
+        assert!(requests[0]
+            .starts_with("PATCH /projects/proj/secrets/ccm-work-openai?updateMask=annotations "));
+        assert!(requests[1].starts_with("POST /projects/proj/secrets?secretId=ccm-work-openai "));
+        assert_eq!(body(&requests[1])["annotations"]["ccm-name"], "work/openai");
+        assert!(body(&requests[1])["replication"]["automatic"].is_object());
+        assert!(requests[2].starts_with("POST /projects/proj/secrets/ccm-work-openai:addVersion "));
+        assert_eq!(
+            body(&requests[2])["payload"]["data"],
+            "eyJPUEVOQUlfQVBJX0tFWSI6InNrLTEifQ=="
+        );
+    }
+}
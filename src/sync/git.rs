@@ -0,0 +1,949 @@
+// Git-backed sync (ccm sync git init|push|pull)
+//
+// The repository (`sync/` in the data directory) holds one JSON file per
+// entry under `entries/`: its variables, tags, notes and the secret as the
+// vault stores it, encrypted with the master key. Machines syncing the same
+// repository share that key (`ccm auth export-key` / `import-key`).
+//
+// push commits the vault's state and pushes it to `origin`. pull fetches
+// `origin` and merges three ways against the last common commit: an entry
+// changed on one side takes that side's version; changed on both sides, it
+// is merged variable by variable (tags as a set). A variable, the notes or
+// the secret changed differently on both sides, or an entry deleted on one
+// side and changed on the other, is a conflict: the local value is kept and
+// reported. The merged state is written to the vault in one transaction and
+// committed on top of both histories. Updated entries keep what only this
+// vault knows (lock, favorite, creation and last use); a locked entry that
+// would be deleted or get another secret stops the pull unless
+// --force-unlock is given.
+
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::{CcmError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Directory of the repository, in the data directory
+pub const SYNC_DIR: &str = "sync";
+const ENTRIES_DIR: &str = "entries";
+const BRANCH: &str = "main";
+
+/// The repository used by default
+pub fn default_dir() -> PathBuf {
+    crate::db::location::data_dir().join(SYNC_DIR)
+}
+
+/// One entry file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryFile {
+    pub name: String,
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Encrypted with the master key (hex)
+    pub secret: String,
+}
+
+impl EntryFile {
+    pub fn new(entry: &Entry, encrypted_secret: &str) -> Self {
+        EntryFile {
+            name: entry.name.clone(),
+            metadata: entry.metadata.clone().into_iter().collect(),
+            tags: entry.tags.clone().unwrap_or_default(),
+            notes: entry.notes.clone().filter(|n| !n.is_empty()),
+            secret: encrypted_secret.to_string(),
+        }
+    }
+
+    pub fn to_entry(&self) -> Entry {
+        let mut entry = Entry::new(
+            self.name.clone(),
+            self.metadata.clone().into_iter().collect(),
+        );
+        entry.tags = (!self.tags.is_empty()).then(|| self.tags.clone());
+        entry.notes = self.notes.clone();
+        entry
+    }
+}
+
+/// Entries by name
+pub type Snapshot = BTreeMap<String, EntryFile>;
+
+/// The vault's entries
+pub fn vault_snapshot() -> Result<Snapshot> {
+    Ok(snapshot_of(&secrets::list_entries_encrypted()?))
+}
+
+fn snapshot_of(entries: &[(Entry, String)]) -> Snapshot {
+    entries
+        .iter()
+        .map(|(entry, secret)| (entry.name.clone(), EntryFile::new(entry, secret)))
+        .collect()
+}
+
+/// File name of an entry: letters, digits, `-`, `_` and `.` as they are,
+/// other bytes (and a leading dot) as %XX
+fn file_name(name: &str) -> String {
+    let mut file = String::new();
+    for (i, byte) in name.bytes().enumerate() {
+        let plain =
+            byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_') || (byte == b'.' && i > 0);
+        if plain {
+            file.push(byte as char);
+        } else {
+            file.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    file.push_str(".json");
+    file
+}
+
+/// What a pull or push changed, by entry name
+#[derive(Debug, Default, Serialize)]
+pub struct GitReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Entry and what conflicted (the local value was kept)
+    pub conflicts: Vec<(String, String)>,
+    /// Commit made, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    pub pushed: bool,
+}
+
+impl GitReport {
+    /// The changes that turn `from` into `to`
+    fn diff(from: &Snapshot, to: &Snapshot, same_secret: &dyn Fn(&str, &str) -> bool) -> Self {
+        let mut report = GitReport::default();
+        for (name, file) in to {
+            match from.get(name) {
+                None => report.created.push(name.clone()),
+                Some(old) if !same_file(old, file, same_secret) => {
+                    report.updated.push(name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        report.deleted = from
+            .keys()
+            .filter(|name| !to.contains_key(*name))
+            .cloned()
+            .collect();
+        report
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// Whether two secrets hold the same value: the same ciphertext, or two
+/// that decrypt to the same value
+fn same_secret(a: &str, b: &str) -> bool {
+    a == b
+        || matches!(
+            (secrets::decrypt_stored_secret(a), secrets::decrypt_stored_secret(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+fn same_file(a: &EntryFile, b: &EntryFile, same_secret: &dyn Fn(&str, &str) -> bool) -> bool {
+    a.metadata == b.metadata
+        && a.tags == b.tags
+        && a.notes == b.notes
+        && same_secret(&a.secret, &b.secret)
+}
+
+fn same_option(
+    a: Option<&EntryFile>,
+    b: Option<&EntryFile>,
+    same_secret: &dyn Fn(&str, &str) -> bool,
+) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => same_file(a, b, same_secret),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Three-way choice of a value: the side that changed it, else ours and
+/// whether that is a conflict
+fn pick<T: Clone>(
+    base: Option<&T>,
+    ours: &T,
+    theirs: &T,
+    eq: impl Fn(&T, &T) -> bool,
+) -> (T, bool) {
+    if eq(ours, theirs) || base.is_some_and(|b| eq(b, theirs)) {
+        (ours.clone(), false)
+    } else if base.is_some_and(|b| eq(b, ours)) {
+        (theirs.clone(), false)
+    } else {
+        (ours.clone(), true)
+    }
+}
+
+/// Merge an entry changed on both sides; also the fields in conflict
+fn merge_fields(
+    base: Option<&EntryFile>,
+    ours: &EntryFile,
+    theirs: &EntryFile,
+    same_secret: &dyn Fn(&str, &str) -> bool,
+) -> (EntryFile, Vec<String>) {
+    let mut conflicts = Vec::new();
+    let mut merged = ours.clone();
+
+    let empty = BTreeMap::new();
+    let base_vars = base.map_or(&empty, |b| &b.metadata);
+    let vars: BTreeSet<&String> = base_vars
+        .keys()
+        .chain(ours.metadata.keys())
+        .chain(theirs.metadata.keys())
+        .collect();
+    merged.metadata.clear();
+    for var in vars {
+        let (value, conflict) = pick(
+            base.map(|_| base_vars.get(var).cloned()).as_ref(),
+            &ours.metadata.get(var).cloned(),
+            &theirs.metadata.get(var).cloned(),
+            |a, b| a == b,
+        );
+        if conflict {
+            conflicts.push(var.clone());
+        }
+        if let Some(value) = value {
+            merged.metadata.insert(var.clone(), value);
+        }
+    }
+
+    // Tags as a set: keep what either side added, drop what either removed
+    let base_tags = base.map(|b| b.tags.as_slice()).unwrap_or_default();
+    merged.tags = ours
+        .tags
+        .iter()
+        .chain(theirs.tags.iter().filter(|t| !ours.tags.contains(t)))
+        .filter(|t| !base_tags.contains(t) || (ours.tags.contains(t) && theirs.tags.contains(t)))
+        .cloned()
+        .collect();
+
+    let (notes, conflict) = pick(
+        base.map(|b| &b.notes),
+        &ours.notes,
+        &theirs.notes,
+        |a, b| a == b,
+    );
+    merged.notes = notes;
+    if conflict {
+        conflicts.push("notes".to_string());
+    }
+
+    let (secret, conflict) = pick(
+        base.map(|b| &b.secret),
+        &ours.secret,
+        &theirs.secret,
+        |a, b| same_secret(a, b),
+    );
+    merged.secret = secret;
+    if conflict {
+        conflicts.push("secret".to_string());
+    }
+
+    (merged, conflicts)
+}
+
+/// Three-way merge of snapshots; also the conflicts (entry, description)
+pub fn merge(
+    base: &Snapshot,
+    ours: &Snapshot,
+    theirs: &Snapshot,
+    same_secret: &dyn Fn(&str, &str) -> bool,
+) -> (Snapshot, Vec<(String, String)>) {
+    let names: BTreeSet<&String> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .collect();
+    let mut merged = Snapshot::new();
+    let mut conflicts = Vec::new();
+
+    for name in names {
+        let (b, o, t) = (base.get(name), ours.get(name), theirs.get(name));
+        let file = if same_option(o, t, same_secret) || same_option(b, t, same_secret) {
+            o.cloned()
+        } else if same_option(b, o, same_secret) {
+            t.cloned()
+        } else {
+            match (o, t) {
+                (Some(o), Some(t)) => {
+                    let (file, fields) = merge_fields(b, o, t, same_secret);
+                    if !fields.is_empty() {
+                        conflicts.push((name.clone(), fields.join(", ")));
+                    }
+                    Some(file)
+                }
+                (None, t) => {
+                    conflicts.push((name.clone(), "deleted here, changed remotely".to_string()));
+                    t.cloned()
+                }
+                (o, None) => {
+                    conflicts.push((name.clone(), "changed here, deleted remotely".to_string()));
+                    o.cloned()
+                }
+            }
+        };
+        if let Some(file) = file {
+            merged.insert(name.clone(), file);
+        }
+    }
+
+    (merged, conflicts)
+}
+
+/// The sync repository
+pub struct GitRepo {
+    dir: PathBuf,
+}
+
+impl GitRepo {
+    /// An existing repository
+    pub fn open(dir: &Path) -> Result<Self> {
+        if !dir.join(".git").exists() {
+            return Err(CcmError::InvalidArgument(format!(
+                "No sync repository in {}: run 'ccm sync git init' first",
+                dir.display()
+            )));
+        }
+        Ok(GitRepo {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Create the repository if needed, and point `origin` at `origin`
+    pub fn init(dir: &Path, origin: Option<&str>) -> Result<Self> {
+        let repo = GitRepo {
+            dir: dir.to_path_buf(),
+        };
+        if !dir.join(".git").exists() {
+            crate::utils::files::create_private_dir(dir)?;
+            repo.run(&["init", "-q"])?;
+            repo.run(&["symbolic-ref", "HEAD", &format!("refs/heads/{}", BRANCH)])?;
+        }
+        if let Some(url) = origin {
+            let verb = if repo.has_origin()? { "set-url" } else { "add" };
+            repo.run(&["remote", verb, "origin", url])?;
+        }
+        Ok(repo)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn git(&self, args: &[&str]) -> Result<std::process::Output> {
+        Command::new("git")
+            .env_remove(crate::auth::PIN_ENV)
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| {
+                CcmError::Process(format!(
+                    "Failed to run git: {}. Is git installed and on PATH?",
+                    e
+                ))
+            })
+    }
+
+    /// Run git and return its output, failing on a non-zero exit
+    fn run(&self, args: &[&str]) -> Result<String> {
+        let output = self.git(args)?;
+        if !output.status.success() {
+            return Err(CcmError::Process(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Run git; None on a non-zero exit
+    fn try_run(&self, args: &[&str]) -> Result<Option<String>> {
+        let output = self.git(args)?;
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
+    fn has_origin(&self) -> Result<bool> {
+        Ok(self.run(&["remote"])?.lines().any(|r| r == "origin"))
+    }
+
+    /// The commit a revision names, None if it does not exist
+    fn commit_of(&self, rev: &str) -> Result<Option<String>> {
+        self.try_run(&[
+            "rev-parse",
+            "--verify",
+            "-q",
+            &format!("{}^{{commit}}", rev),
+        ])
+    }
+
+    fn is_ancestor(&self, ancestor: &str, of: &str) -> Result<bool> {
+        Ok(self
+            .try_run(&["merge-base", "--is-ancestor", ancestor, of])?
+            .is_some())
+    }
+
+    /// `-c` options naming ccm as the committer when git has no identity
+    fn identity(&self) -> Result<Vec<&'static str>> {
+        Ok(match self.try_run(&["config", "user.email"])? {
+            Some(email) if !email.is_empty() => Vec::new(),
+            _ => vec!["-c", "user.name=ccm", "-c", "user.email=ccm@localhost"],
+        })
+    }
+
+    /// The entries in a commit
+    pub fn read_snapshot(&self, rev: &str) -> Result<Snapshot> {
+        let listing = self.run(&["ls-tree", "-r", "-z", rev, "--", ENTRIES_DIR])?;
+        let blobs: Vec<&str> = listing
+            .split('\0')
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(info, _)| info.split(' ').nth(2))
+            .collect();
+        if blobs.is_empty() {
+            return Ok(Snapshot::new());
+        }
+
+        let mut child = Command::new("git")
+            .env_remove(crate::auth::PIN_ENV)
+            .arg("-C")
+            .arg(&self.dir)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let request = blobs.join("\n") + "\n";
+        let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+        let output = child.wait_with_output()?;
+        writer
+            .join()
+            .map_err(|_| CcmError::Unknown("git input thread panicked".to_string()))??;
+
+        // "<oid> blob <size>\n<content>\n" per object
+        let mut snapshot = Snapshot::new();
+        let mut rest = output.stdout.as_slice();
+        while let Some(newline) = rest.iter().position(|&b| b == b'\n') {
+            let header = String::from_utf8_lossy(&rest[..newline]).to_string();
+            let size: usize = header
+                .rsplit(' ')
+                .next()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| {
+                    CcmError::Process(format!("Unexpected git cat-file output: {}", header))
+                })?;
+            let content = rest
+                .get(newline + 1..newline + 1 + size)
+                .ok_or_else(|| CcmError::Process("Truncated git cat-file output".to_string()))?;
+            let file: EntryFile = serde_json::from_slice(content)?;
+            snapshot.insert(file.name.clone(), file);
+            rest = rest.get(newline + 2 + size..).unwrap_or_default();
+        }
+        Ok(snapshot)
+    }
+
+    /// The entries of the last commit (none before the first one)
+    pub fn head_snapshot(&self) -> Result<Snapshot> {
+        match self.commit_of("HEAD")? {
+            Some(head) => self.read_snapshot(&head),
+            None => Ok(Snapshot::new()),
+        }
+    }
+
+    /// Make `entries/` hold exactly `snapshot` and stage it
+    fn write_entries(&self, snapshot: &Snapshot) -> Result<()> {
+        let dir = self.dir.join(ENTRIES_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let files: BTreeSet<String> = snapshot.keys().map(|name| file_name(name)).collect();
+        for existing in std::fs::read_dir(&dir)? {
+            let existing = existing?;
+            if !files.contains(&existing.file_name().to_string_lossy().to_string()) {
+                std::fs::remove_file(existing.path())?;
+            }
+        }
+        for (name, file) in snapshot {
+            let json = serde_json::to_string_pretty(file)? + "\n";
+            crate::utils::files::write_file_atomic(&dir.join(file_name(name)), json.as_bytes())?;
+        }
+        self.run(&["add", "-A", "--", ENTRIES_DIR])?;
+        Ok(())
+    }
+
+    fn branch(&self) -> Result<String> {
+        Ok(self
+            .try_run(&["symbolic-ref", "--short", "HEAD"])?
+            .unwrap_or_else(|| BRANCH.to_string()))
+    }
+
+    /// Commit the vault and push it to origin
+    pub fn push(&self, vault: &Snapshot, dry_run: bool) -> Result<GitReport> {
+        let mut report = GitReport::diff(&self.head_snapshot()?, vault, &same_secret);
+        if dry_run {
+            return Ok(report);
+        }
+
+        if !report.is_empty() {
+            self.write_entries(vault)?;
+            let message = format!("Update {} from {}", plural(&report), hostname());
+            let mut args = self.identity()?;
+            args.extend(["commit", "-q", "-m", &message]);
+            self.run(&args)?;
+            report.commit = self.commit_of("HEAD")?;
+        }
+
+        if self.has_origin()? && self.commit_of("HEAD")?.is_some() {
+            let branch = self.branch()?;
+            let refspec = format!("HEAD:refs/heads/{}", branch);
+            let output = self.git(&["push", "-q", "origin", &refspec])?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if stderr.contains("[rejected]") || stderr.contains("non-fast-forward") {
+                    return Err(CcmError::Remote(
+                        "origin has changes this machine has not merged: run 'ccm sync git pull' first"
+                            .to_string(),
+                    ));
+                }
+                return Err(CcmError::Process(format!(
+                    "git push failed: {}",
+                    stderr.trim()
+                )));
+            }
+            report.pushed = true;
+        }
+        Ok(report)
+    }
+
+    /// Fetch origin; None when it has nothing this repository lacks
+    fn fetch(&self) -> Result<Option<Incoming>> {
+        if !self.has_origin()? {
+            return Err(CcmError::InvalidArgument(
+                "The sync repository has no origin: run 'ccm sync git init --origin URL'"
+                    .to_string(),
+            ));
+        }
+        self.run(&["fetch", "-q", "origin"])?;
+        let branch = self.branch()?;
+        let head = self.commit_of("HEAD")?;
+        let Some(theirs) = self.commit_of(&format!("refs/remotes/origin/{}", branch))? else {
+            return Ok(None);
+        };
+        let base = match &head {
+            Some(head) if self.is_ancestor(&theirs, head)? => return Ok(None),
+            Some(head) => match self.try_run(&["merge-base", head, &theirs])? {
+                Some(base) => self.read_snapshot(&base)?,
+                None => Snapshot::new(),
+            },
+            None => Snapshot::new(),
+        };
+
+        Ok(Some(Incoming {
+            snapshot: self.read_snapshot(&theirs)?,
+            base,
+            head,
+            theirs,
+            branch,
+        }))
+    }
+
+    /// Commit the merged state on top of both histories (a fast-forward
+    /// when it is origin's state); the commit made, if any
+    fn commit_merge(&self, incoming: &Incoming, merged: &Snapshot) -> Result<Option<String>> {
+        self.write_entries(merged)?;
+        let tree = self.run(&["write-tree"])?;
+        let theirs = incoming.theirs.as_str();
+        let parents: Vec<&str> = match &incoming.head {
+            Some(head) if !self.is_ancestor(head, theirs)? => vec![head.as_str(), theirs],
+            _ => vec![theirs],
+        };
+
+        let theirs_tree = self.run(&["rev-parse", &format!("{}^{{tree}}", theirs)])?;
+        let commit = if parents.len() == 1 && theirs_tree == tree {
+            None
+        } else {
+            let message = format!("Merge origin/{} on {}", incoming.branch, hostname());
+            let mut args = self.identity()?;
+            args.extend(["commit-tree", tree.as_str(), "-m", message.as_str()]);
+            for parent in &parents {
+                args.extend(["-p", parent]);
+            }
+            Some(self.run(&args)?)
+        };
+        self.run(&["update-ref", "HEAD", commit.as_deref().unwrap_or(theirs)])?;
+        Ok(commit)
+    }
+
+    /// Fetch origin and merge it into the vault (and the repository); locked
+    /// entries are only deleted or given another secret with `force_unlock`
+    pub fn pull(&self, dry_run: bool, force_unlock: bool) -> Result<GitReport> {
+        let Some(incoming) = self.fetch()? else {
+            return Ok(GitReport::default());
+        };
+        let local = secrets::list_entries_encrypted()?;
+        let ours = snapshot_of(&local);
+        let (merged, conflicts) = merge(&incoming.base, &ours, &incoming.snapshot, &same_secret);
+        let mut report = GitReport::diff(&ours, &merged, &same_secret);
+        report.conflicts = conflicts;
+
+        let existing: HashMap<&str, &Entry> = local
+            .iter()
+            .map(|(entry, _)| (entry.name.as_str(), entry))
+            .collect();
+        let saved = planned_writes(
+            &report,
+            &existing,
+            &ours,
+            &merged,
+            force_unlock,
+            &same_secret,
+        )?;
+        if dry_run {
+            return Ok(report);
+        }
+
+        // Refuse secrets this vault cannot decrypt before changing anything
+        for (entry, secret) in &saved {
+            if secrets::decrypt_stored_secret(secret).is_err() {
+                return Err(CcmError::Decryption(format!(
+                    "The secret of '{}' from origin was encrypted with another master key",
+                    entry.name
+                )));
+            }
+        }
+        secrets::apply_encrypted_entries(&saved, &report.deleted)?;
+
+        report.commit = self.commit_merge(&incoming, &merged)?;
+        Ok(report)
+    }
+}
+
+/// The entries a pull saves: created and updated ones from the merge, the
+/// updated ones keeping this vault's lock, favorite, creation and last use.
+/// Fails if a locked entry would be deleted or get another secret, unless
+/// `force_unlock`
+fn planned_writes(
+    report: &GitReport,
+    existing: &HashMap<&str, &Entry>,
+    ours: &Snapshot,
+    merged: &Snapshot,
+    force_unlock: bool,
+    same_secret: &dyn Fn(&str, &str) -> bool,
+) -> Result<Vec<(Entry, String)>> {
+    let mut locked: Vec<&str> = report
+        .deleted
+        .iter()
+        .chain(
+            report
+                .updated
+                .iter()
+                .filter(|name| !same_secret(&ours[*name].secret, &merged[*name].secret)),
+        )
+        .map(String::as_str)
+        .filter(|name| existing.get(name).is_some_and(|entry| entry.locked))
+        .collect();
+    locked.sort();
+    if !force_unlock && !locked.is_empty() {
+        // `ccm unlock` takes several names
+        return Err(CcmError::EntryLocked(locked.join(" ")));
+    }
+
+    Ok(report
+        .created
+        .iter()
+        .chain(&report.updated)
+        .map(|name| {
+            let file = &merged[name];
+            let mut entry = file.to_entry();
+            if let Some(old) = existing.get(name.as_str()) {
+                entry.created_at = old.created_at.clone();
+                entry.last_used_at = old.last_used_at.clone();
+                entry.locked = old.locked;
+                entry.favorite = old.favorite;
+            }
+            (entry, file.secret.clone())
+        })
+        .collect())
+}
+
+/// What a fetch brought
+struct Incoming {
+    /// Entries on origin
+    snapshot: Snapshot,
+    /// Entries at the last common commit
+    base: Snapshot,
+    head: Option<String>,
+    theirs: String,
+    branch: String,
+}
+
+/// "3 entries" for a commit message
+fn plural(report: &GitReport) -> String {
+    let count = report.created.len() + report.updated.len() + report.deleted.len();
+    format!("{} entr{}", count, if count == 1 { "y" } else { "ies" })
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .filter(|h| !h.is_empty())
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty())
+        })
+        .unwrap_or_else(|| "this machine".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, vars: &[(&str, &str)], tags: &[&str], secret: &str) -> EntryFile {
+        EntryFile {
+            name: name.to_string(),
+            metadata: vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            notes: None,
+            secret: secret.to_string(),
+        }
+    }
+
+    fn snapshot(files: &[EntryFile]) -> Snapshot {
+        files.iter().map(|f| (f.name.clone(), f.clone())).collect()
+    }
+
+    fn same(a: &str, b: &str) -> bool {
+        a == b
+    }
+
+    fn local(name: &str, locked: bool) -> Entry {
+        let mut entry = Entry::new(name.to_string(), Default::default());
+        entry.created_at = Some("2024-01-01T00:00:00Z".to_string());
+        entry.last_used_at = Some("2025-01-01T00:00:00Z".to_string());
+        entry.locked = locked;
+        entry.favorite = true;
+        entry
+    }
+
+    #[test]
+    fn test_planned_writes_keep_local_fields() {
+        let ours = snapshot(&[
+            file("a", &[("K", "v1")], &[], "s"),
+            file("gone", &[], &[], "g"),
+        ]);
+        let merged = snapshot(&[
+            file("a", &[("K", "v2")], &[], "s"),
+            file("new", &[], &[], "n"),
+        ]);
+        let report = GitReport::diff(&ours, &merged, &same);
+        let entries = [local("a", false), local("gone", false)];
+        let existing = entries.iter().map(|e| (e.name.as_str(), e)).collect();
+
+        let saved = planned_writes(&report, &existing, &ours, &merged, false, &same).unwrap();
+        let names: Vec<&str> = saved.iter().map(|(e, _)| e.name.as_str()).collect();
+        assert_eq!(names, ["new", "a"]);
+        let (a, secret) = &saved[1];
+        assert_eq!(a.metadata["K"], "v2");
+        assert_eq!(secret, "s");
+        assert_eq!(a.created_at.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(a.last_used_at.as_deref(), Some("2025-01-01T00:00:00Z"));
+        assert!(a.favorite);
+        assert!(!saved[0].0.favorite);
+        assert_eq!(report.deleted, ["gone"]);
+    }
+
+    #[test]
+    fn test_planned_writes_refuse_locked_entries() {
+        let ours = snapshot(&[
+            file("a", &[("K", "v")], &[], "s1"),
+            file("b", &[("K", "v")], &[], "s2"),
+            file("c", &[], &[], "s3"),
+        ]);
+        let rekeyed = snapshot(&[
+            file("a", &[("K", "v")], &[], "other"),
+            file("b", &[("K", "w")], &[], "s2"),
+            file("c", &[], &[], "s3"),
+        ]);
+        let entries = [local("a", true), local("b", true), local("c", true)];
+        let existing = entries.iter().map(|e| (e.name.as_str(), e)).collect();
+
+        // Another secret or a deletion needs --force-unlock
+        let report = GitReport::diff(&ours, &rekeyed, &same);
+        let err = planned_writes(&report, &existing, &ours, &rekeyed, false, &same).unwrap_err();
+        assert!(matches!(err, CcmError::EntryLocked(names) if names == "a"));
+        let mut without_c = rekeyed.clone();
+        without_c.remove("c");
+        let report = GitReport::diff(&ours, &without_c, &same);
+        let err = planned_writes(&report, &existing, &ours, &without_c, false, &same).unwrap_err();
+        assert!(matches!(err, CcmError::EntryLocked(names) if names == "a c"));
+
+        // With it, or for metadata only, the entries are written and stay locked
+        let saved = planned_writes(&report, &existing, &ours, &without_c, true, &same).unwrap();
+        assert!(saved.iter().all(|(entry, _)| entry.locked));
+        let metadata_only = snapshot(&[
+            file("a", &[("K", "v")], &[], "s1"),
+            file("b", &[("K", "w")], &[], "s2"),
+            file("c", &[], &[], "s3"),
+        ]);
+        let report = GitReport::diff(&ours, &metadata_only, &same);
+        let saved =
+            planned_writes(&report, &existing, &ours, &metadata_only, false, &same).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].0.locked);
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("openai"), "openai.json");
+        assert_eq!(file_name("work/db.prod"), "work%2Fdb.prod.json");
+        assert_eq!(file_name(".env"), "%2Eenv.json");
+        assert_ne!(file_name("a/b"), file_name("a-b"));
+    }
+
+    #[test]
+    fn test_merge_one_side() {
+        let a = file("a", &[("K", "SECRET")], &[], "s1");
+        let base = snapshot(&[a.clone(), file("b", &[], &[], "b")]);
+        // Ours changed a, theirs deleted b and added c
+        let ours = snapshot(&[
+            file("a", &[("K", "SECRET")], &[], "s2"),
+            file("b", &[], &[], "b"),
+        ]);
+        let theirs = snapshot(&[a, file("c", &[], &[], "c")]);
+
+        let (merged, conflicts) = merge(&base, &ours, &theirs, &same);
+        assert!(conflicts.is_empty());
+        assert_eq!(merged.keys().collect::<Vec<_>>(), ["a", "c"]);
+        assert_eq!(merged["a"].secret, "s2");
+    }
+
+    #[test]
+    fn test_merge_fields() {
+        let base = snapshot(&[file(
+            "a",
+            &[("URL", "u1"), ("MODEL", "m1")],
+            &["x", "y"],
+            "s",
+        )]);
+        let ours = snapshot(&[file("a", &[("URL", "u2"), ("MODEL", "m1")], &["x"], "s2")]);
+        let theirs = snapshot(&[file(
+            "a",
+            &[("URL", "u1"), ("MODEL", "m2"), ("NEW", "n")],
+            &["x", "y", "z"],
+            "s3",
+        )]);
+
+        let (merged, conflicts) = merge(&base, &ours, &theirs, &same);
+        let a = &merged["a"];
+        assert_eq!(a.metadata["URL"], "u2");
+        assert_eq!(a.metadata["MODEL"], "m2");
+        assert_eq!(a.metadata["NEW"], "n");
+        assert_eq!(a.tags, ["x", "z"]);
+        // Both changed the secret: ours is kept and flagged
+        assert_eq!(a.secret, "s2");
+        assert_eq!(conflicts, [("a".to_string(), "secret".to_string())]);
+    }
+
+    #[test]
+    fn test_merge_delete_conflict() {
+        let base = snapshot(&[file("a", &[], &[], "s")]);
+        let changed = snapshot(&[file("a", &[], &[], "s2")]);
+
+        let (merged, conflicts) = merge(&base, &Snapshot::new(), &changed, &same);
+        assert_eq!(merged["a"].secret, "s2");
+        assert_eq!(conflicts[0].1, "deleted here, changed remotely");
+
+        let (merged, conflicts) = merge(&base, &changed, &Snapshot::new(), &same);
+        assert_eq!(merged["a"].secret, "s2");
+        assert_eq!(conflicts[0].1, "changed here, deleted remotely");
+    }
+
+    #[test]
+    fn test_repo_snapshot_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let repo = GitRepo::init(dir, None).unwrap();
+
+        let files = snapshot(&[
+            file("work/db", &[("PASSWORD", "SECRET")], &["prod"], "00ff"),
+            file("openai", &[], &[], "aa"),
+        ]);
+        repo.write_entries(&files).unwrap();
+        let mut args = repo.identity().unwrap();
+        args.extend(["commit", "-q", "-m", "test"]);
+        repo.run(&args).unwrap();
+        assert_eq!(repo.head_snapshot().unwrap(), files);
+
+        // Entries no longer in the snapshot are removed
+        repo.write_entries(&snapshot(&[file("openai", &[], &[], "aa")]))
+            .unwrap();
+        assert!(!dir.join(ENTRIES_DIR).join(file_name("work/db")).exists());
+    }
+
+    #[test]
+    fn test_sync_through_origin() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let origin = root.join("origin.git");
+        let status = Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(&origin)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let origin = origin.to_string_lossy().to_string();
+        let a = GitRepo::init(&root.join("a"), Some(&origin)).unwrap();
+        let b = GitRepo::init(&root.join("b"), Some(&origin)).unwrap();
+
+        let first = snapshot(&[file("x", &[], &[], "x1"), file("y", &[], &[], "y1")]);
+        assert!(a.push(&first, false).unwrap().pushed);
+
+        // A new machine takes origin's state as is
+        let incoming = b.fetch().unwrap().unwrap();
+        assert_eq!(incoming.snapshot, first);
+        assert_eq!(b.commit_merge(&incoming, &incoming.snapshot).unwrap(), None);
+        assert_eq!(b.head_snapshot().unwrap(), first);
+        assert!(b.fetch().unwrap().is_none());
+
+        // a changes x, b adds z: the merge keeps both
+        let second = snapshot(&[file("x", &[], &[], "x2"), file("y", &[], &[], "y1")]);
+        assert_eq!(a.push(&second, false).unwrap().updated, ["x"]);
+        let mut ours = first.clone();
+        ours.insert("z".to_string(), file("z", &[], &[], "z1"));
+        let incoming = b.fetch().unwrap().unwrap();
+        let (merged, conflicts) = merge(&incoming.base, &ours, &incoming.snapshot, &same);
+        assert!(conflicts.is_empty());
+        assert_eq!(merged.keys().collect::<Vec<_>>(), ["x", "y", "z"]);
+        assert!(b.commit_merge(&incoming, &merged).unwrap().is_some());
+        assert!(b.push(&merged, false).unwrap().is_empty());
+
+        // a is now behind origin: its push is refused until it pulls
+        let error = a.push(&snapshot(&[]), false).unwrap_err().to_string();
+        assert!(error.contains("ccm sync git pull"), "{}", error);
+        let incoming = a.fetch().unwrap().unwrap();
+        assert_eq!(incoming.snapshot, merged);
+    }
+}
@@ -0,0 +1,620 @@
+// Sync with external secret stores (ccm sync <provider> pull|push)
+//
+// An entry is stored as one remote secret named like the entry:
+//   data      the variables `ccm use` exports, the secret under its variable
+//             (or under `ccm_secret` when no variable maps it)
+//   metadata  ccm-secret-vars (data keys holding the secret), ccm-tags,
+//             ccm-notes
+// Secrets created outside ccm have no metadata; their secret is guessed from
+// the key names (a single key, else the one named like *_KEY, *TOKEN, ...).
+// Pulled secrets are encrypted with the local master key like any entry.
+
+pub mod aws;
+pub mod azure;
+pub mod blob;
+pub mod gcp;
+pub mod git;
+pub mod vault;
+
+use crate::secrets;
+use crate::types::Entry;
+use crate::utils::{CcmError, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Metadata: comma-separated data keys that hold the secret
+pub const SECRET_VARS_META: &str = "ccm-secret-vars";
+/// Metadata: comma-separated tags
+pub const TAGS_META: &str = "ccm-tags";
+/// Metadata: notes
+pub const NOTES_META: &str = "ccm-notes";
+/// Data key for a secret that no variable maps (passwords, notes)
+pub const UNMAPPED_SECRET_KEY: &str = "ccm_secret";
+/// Metadata: the entry name, where secret names cannot hold it as is
+pub const NAME_META: &str = "ccm-name";
+
+/// A secret as a remote store holds it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSecret {
+    /// Entry name (relative to the store's prefix)
+    pub name: String,
+    pub data: BTreeMap<String, String>,
+    /// Store-side metadata (Vault custom metadata, tags, labels)
+    pub meta: BTreeMap<String, String>,
+}
+
+/// A remote secret store
+pub trait SyncBackend {
+    /// Names of all secrets under the prefix
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// A secret, None when it does not exist
+    fn read(&self, name: &str) -> Result<Option<RemoteSecret>>;
+
+    /// Create or replace a secret
+    fn write(&self, secret: &RemoteSecret) -> Result<()>;
+
+    /// The name the store keeps an entry under, where it must differ
+    fn remote_name(&self, name: &str) -> String {
+        name.to_string()
+    }
+}
+
+/// The remote form of an entry
+pub fn to_remote(entry: &Entry, secret: &str) -> RemoteSecret {
+    let mut data = BTreeMap::new();
+    let mut secret_vars = Vec::new();
+    for (var, value) in &entry.metadata {
+        if value == "SECRET" {
+            secret_vars.push(var.clone());
+            data.insert(var.clone(), secret.to_string());
+        } else {
+            data.insert(var.clone(), value.clone());
+        }
+    }
+    if secret_vars.is_empty() {
+        secret_vars.push(UNMAPPED_SECRET_KEY.to_string());
+        data.insert(UNMAPPED_SECRET_KEY.to_string(), secret.to_string());
+    }
+    secret_vars.sort();
+
+    let mut meta = BTreeMap::new();
+    meta.insert(SECRET_VARS_META.to_string(), secret_vars.join(","));
+    if let Some(tags) = entry.tags.as_ref().filter(|t| !t.is_empty()) {
+        meta.insert(TAGS_META.to_string(), tags.join(","));
+    }
+    if let Some(notes) = entry.notes.as_ref().filter(|n| !n.is_empty()) {
+        meta.insert(NOTES_META.to_string(), notes.clone());
+    }
+
+    RemoteSecret {
+        name: entry.name.clone(),
+        data,
+        meta,
+    }
+}
+
+/// The entry and secret for a remote secret
+pub fn from_remote(remote: &RemoteSecret) -> Result<(Entry, String)> {
+    let secret_vars: Vec<String> = match remote.meta.get(SECRET_VARS_META) {
+        Some(vars) => vars.split(',').map(str::to_string).collect(),
+        None => guess_secret_key(&remote.data).into_iter().collect(),
+    };
+    let secret = secret_vars
+        .iter()
+        .find_map(|var| remote.data.get(var))
+        .ok_or_else(|| {
+            CcmError::InvalidArgument(format!(
+                "Cannot tell which key of '{}' is the secret",
+                remote.name
+            ))
+        })?
+        .clone();
+
+    let metadata: HashMap<String, String> = remote
+        .data
+        .iter()
+        .filter(|(key, _)| key.as_str() != UNMAPPED_SECRET_KEY)
+        .map(|(key, value)| {
+            let value = if secret_vars.contains(key) {
+                "SECRET".to_string()
+            } else {
+                value.clone()
+            };
+            (key.clone(), value)
+        })
+        .collect();
+
+    let mut entry = Entry::new(remote.name.clone(), metadata);
+    entry.tags = remote
+        .meta
+        .get(TAGS_META)
+        .map(|tags| tags.split(',').map(str::to_string).collect());
+    entry.notes = remote.meta.get(NOTES_META).cloned();
+    Ok((entry, secret))
+}
+
+/// The key holding the secret of a secret made outside ccm
+fn guess_secret_key(data: &BTreeMap<String, String>) -> Option<String> {
+    if data.len() == 1 {
+        return data.keys().next().cloned();
+    }
+    const HINTS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD"];
+    data.keys()
+        .find(|key| {
+            let upper = key.to_uppercase();
+            HINTS.iter().any(|hint| upper.ends_with(hint)) && !upper.ends_with("KEY_ID")
+        })
+        .cloned()
+}
+
+/// A JSON object as strings (non-string values in their JSON form)
+pub(crate) fn string_map(value: &Value) -> BTreeMap<String, String> {
+    value
+        .as_object()
+        .map(|object| {
+            object
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    (key.clone(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Data for stores that hold one string per secret: the JSON object ccm
+/// writes, or a plain value under UNMAPPED_SECRET_KEY
+pub(crate) fn data_from_string(value: &str) -> BTreeMap<String, String> {
+    match serde_json::from_str::<Value>(value) {
+        Ok(object @ Value::Object(_)) => string_map(&object),
+        _ => BTreeMap::from([(UNMAPPED_SECRET_KEY.to_string(), value.to_string())]),
+    }
+}
+
+/// Send a request with an optional JSON body and parse the JSON reply
+/// (Null when empty); None on 404. `store` names the service in errors
+pub(crate) fn send_json(
+    request: ureq::Request,
+    body: Option<&Value>,
+    store: &str,
+) -> Result<Option<Value>> {
+    let response = match body {
+        Some(body) => request
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string()),
+        None => request.call(),
+    };
+    json_reply(response, store)
+}
+
+/// The JSON reply of a request (Null when empty); None on 404
+pub(crate) fn json_reply(
+    response: std::result::Result<ureq::Response, ureq::Error>,
+    store: &str,
+) -> Result<Option<Value>> {
+    match response {
+        Ok(response) => {
+            let text = response.into_string()?;
+            if text.trim().is_empty() {
+                return Ok(Some(Value::Null));
+            }
+            Ok(Some(serde_json::from_str(&text)?))
+        }
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(ureq::Error::Status(status, response)) => {
+            let body: Value = response
+                .into_string()
+                .ok()
+                .and_then(|body| serde_json::from_str(&body).ok())
+                .unwrap_or_default();
+            Err(CcmError::Remote(format!(
+                "{} returned {}: {}",
+                store,
+                status,
+                error_message(&body)
+            )))
+        }
+        Err(ureq::Error::Transport(e)) => {
+            Err(CcmError::Remote(format!("Cannot reach {}: {}", store, e)))
+        }
+    }
+}
+
+/// The message of an error reply: Vault's `errors`, the `error.message` of
+/// Azure and Google APIs, or an OAuth `error_description`
+fn error_message(body: &Value) -> String {
+    if let Some(errors) = body["errors"].as_array() {
+        return errors
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("; ");
+    }
+    body["error"]["message"]
+        .as_str()
+        .or_else(|| body["error_description"].as_str())
+        .or_else(|| body["message"].as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The secret name for an entry where names are limited to letters, digits
+/// and dashes (Azure Key Vault, Google Secret Manager): other characters
+/// become dashes, and the entry name is kept in NAME_META
+pub(crate) fn secret_id(prefix: &str, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{}{}", prefix, name)
+}
+
+/// The entry a secret named `prefix` + `rest` holds: NAME_META (which is
+/// taken out of `meta`), else `rest` itself
+pub(crate) fn take_entry_name(meta: &mut BTreeMap<String, String>, rest: &str) -> String {
+    meta.remove(NAME_META).unwrap_or_else(|| rest.to_string())
+}
+
+/// Why entries cannot be pushed because the store would keep them under the
+/// same name (`work/openai` and `work.openai` in Key Vault), by entry name
+fn name_collisions(backend: &dyn SyncBackend, names: &[String]) -> HashMap<String, String> {
+    let mut by_remote: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for name in names {
+        by_remote
+            .entry(backend.remote_name(name))
+            .or_default()
+            .push(name);
+    }
+    by_remote
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .flat_map(|(remote, names)| {
+            let reason = format!("{} would all be stored as '{}'", names.join(", "), remote);
+            names
+                .into_iter()
+                .map(move |name| (name.to_string(), reason.clone()))
+        })
+        .collect()
+}
+
+/// An access token printed by a cloud CLI (az, gcloud)
+pub(crate) fn token_from_cli(program: &str, args: &[&str], hint: &str) -> Result<String> {
+    let output = std::process::Command::new(program)
+        .env_remove(crate::auth::PIN_ENV)
+        .args(args)
+        .output()
+        .map_err(|_| {
+            CcmError::InvalidArgument(format!("No credentials: {} not found; {}", program, hint))
+        })?;
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || token.is_empty() {
+        return Err(CcmError::Remote(format!(
+            "{} did not print an access token: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(token)
+}
+
+/// Whether two entries have the same variables, tags and notes
+pub(crate) fn same_entry(a: &Entry, b: &Entry) -> bool {
+    let tags = |e: &Entry| e.tags.clone().unwrap_or_default();
+    let notes = |e: &Entry| e.notes.clone().unwrap_or_default();
+    a.metadata == b.metadata && tags(a) == tags(b) && notes(a) == notes(b)
+}
+
+/// What a pull or push did, by entry name
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Existing on the other side, not overwritten
+    pub skipped: Vec<String>,
+    /// Name and reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Copy remote secrets into the vault; existing entries are only replaced
+/// with `overwrite`, locked ones also need `force_unlock` (and stay
+/// locked). With `dry_run` nothing is written
+pub fn pull(
+    backend: &dyn SyncBackend,
+    overwrite: bool,
+    force_unlock: bool,
+    dry_run: bool,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    let mut new_entries = Vec::new();
+
+    for name in backend.list()? {
+        let Some(remote) = backend.read(&name)? else {
+            continue;
+        };
+        let (entry, secret) = match from_remote(&remote) {
+            Ok(found) => found,
+            Err(e) => {
+                report.failed.push((name, e.to_string()));
+                continue;
+            }
+        };
+
+        match secrets::get_entry_with_secret(&name) {
+            Ok((existing, existing_secret)) => {
+                if same_entry(&existing, &entry) && existing_secret == secret {
+                    report.unchanged.push(name);
+                } else if !overwrite {
+                    report.skipped.push(name);
+                } else if existing.locked && !force_unlock {
+                    let reason = CcmError::EntryLocked(name.clone()).to_string();
+                    report.failed.push((name, reason));
+                } else {
+                    if !dry_run {
+                        let mut entry = entry;
+                        entry.created_at = existing.created_at;
+                        entry.locked = existing.locked;
+                        secrets::update_entry(&name, entry)?;
+                        secrets::update_secret(&name, &secret)?;
+                    }
+                    report.updated.push(name);
+                }
+            }
+            Err(CcmError::EntryNotFound(_)) => {
+                report.created.push(name);
+                new_entries.push((entry, secret));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if !dry_run && !new_entries.is_empty() {
+        secrets::add_entries(new_entries, || {})?;
+    }
+    Ok(report)
+}
+
+/// Copy entries (all when `names` is empty) to the remote store; existing
+/// remote secrets are only replaced with `overwrite`. Entries the store would
+/// keep under one name fail, and so does overwriting a secret that holds
+/// another entry (its NAME_META differs, which `read` reports)
+pub fn push(
+    backend: &dyn SyncBackend,
+    names: &[String],
+    overwrite: bool,
+    dry_run: bool,
+) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+
+    let names = if names.is_empty() {
+        let mut all: Vec<String> = secrets::list_entries()?.into_keys().collect();
+        all.sort();
+        all
+    } else {
+        names.to_vec()
+    };
+    let mut collisions = name_collisions(backend, &names);
+
+    for name in names {
+        if let Some(reason) = collisions.remove(&name) {
+            report.failed.push((name, reason));
+            continue;
+        }
+        let (entry, secret) = secrets::get_entry_with_secret(&name)?;
+        let remote = to_remote(&entry, &secret);
+
+        let existing = match backend.read(&name) {
+            Ok(existing) => existing,
+            Err(e) => {
+                report.failed.push((name, e.to_string()));
+                continue;
+            }
+        };
+        match existing {
+            Some(existing) if existing == remote => report.unchanged.push(name),
+            Some(_) if !overwrite => report.skipped.push(name),
+            existing => {
+                if !dry_run {
+                    if let Err(e) = backend.write(&remote) {
+                        report.failed.push((name, e.to_string()));
+                        continue;
+                    }
+                }
+                match existing {
+                    Some(_) => report.updated.push(name),
+                    None => report.created.push(name),
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(vars: &[(&str, &str)]) -> Entry {
+        let metadata = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Entry::new("openai".to_string(), metadata)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut original = entry(&[
+            ("OPENAI_API_KEY", "SECRET"),
+            ("OPENAI_BASE_URL", "https://api.openai.com/v1"),
+        ]);
+        original.tags = Some(vec!["ai".to_string(), "work".to_string()]);
+        original.notes = Some("team key".to_string());
+
+        let remote = to_remote(&original, "sk-1");
+        assert_eq!(remote.data["OPENAI_API_KEY"], "sk-1");
+        assert_eq!(remote.meta[SECRET_VARS_META], "OPENAI_API_KEY");
+
+        let (pulled, secret) = from_remote(&remote).unwrap();
+        assert_eq!(secret, "sk-1");
+        assert_eq!(pulled.metadata, original.metadata);
+        assert_eq!(pulled.tags, original.tags);
+        assert_eq!(pulled.notes, original.notes);
+
+        // A secret no variable maps is kept under its own key
+        let password = to_remote(&entry(&[]), "hunter2");
+        assert_eq!(password.data[UNMAPPED_SECRET_KEY], "hunter2");
+        let (pulled, secret) = from_remote(&password).unwrap();
+        assert!(pulled.metadata.is_empty());
+        assert_eq!(secret, "hunter2");
+    }
+
+    #[test]
+    fn test_from_remote_guesses_secret() {
+        let remote = |data: &[(&str, &str)]| RemoteSecret {
+            name: "aws".to_string(),
+            data: data
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            meta: BTreeMap::new(),
+        };
+
+        let (entry, secret) = from_remote(&remote(&[
+            ("AWS_ACCESS_KEY_ID", "AKIA1"),
+            ("AWS_SECRET_ACCESS_KEY", "s3cr3t"),
+            ("AWS_REGION", "eu-west-1"),
+        ]))
+        .unwrap();
+        assert_eq!(secret, "s3cr3t");
+        assert_eq!(entry.metadata["AWS_SECRET_ACCESS_KEY"], "SECRET");
+        assert_eq!(entry.metadata["AWS_ACCESS_KEY_ID"], "AKIA1");
+
+        let (_, secret) = from_remote(&remote(&[("value", "x")])).unwrap();
+        assert_eq!(secret, "x");
+        assert!(from_remote(&remote(&[("a", "1"), ("b", "2")])).is_err());
+    }
+
+    #[test]
+    fn test_string_data() {
+        let map = string_map(&serde_json::json!({ "a": "x", "port": 8080 }));
+        assert_eq!(map["a"], "x");
+        assert_eq!(map["port"], "8080");
+        assert!(string_map(&Value::Null).is_empty());
+
+        assert_eq!(data_from_string(r#"{"API_KEY":"k"}"#)["API_KEY"], "k");
+        assert_eq!(data_from_string("hunter2")[UNMAPPED_SECRET_KEY], "hunter2");
+        assert_eq!(data_from_string("42")[UNMAPPED_SECRET_KEY], "42");
+    }
+
+    #[test]
+    fn test_secret_id() {
+        assert_eq!(secret_id("ccm-", "work/openai"), "ccm-work-openai");
+        assert_eq!(secret_id("", "My Bank"), "My-Bank");
+
+        let mut meta = BTreeMap::from([(NAME_META.to_string(), "work/openai".to_string())]);
+        assert_eq!(take_entry_name(&mut meta, "work-openai"), "work/openai");
+        assert!(meta.is_empty());
+        assert_eq!(take_entry_name(&mut meta, "legacy-db"), "legacy-db");
+    }
+
+    struct Dashes;
+
+    impl SyncBackend for Dashes {
+        fn list(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+        fn read(&self, _: &str) -> Result<Option<RemoteSecret>> {
+            Ok(None)
+        }
+        fn write(&self, _: &RemoteSecret) -> Result<()> {
+            Ok(())
+        }
+        fn remote_name(&self, name: &str) -> String {
+            secret_id("ccm-", name)
+        }
+    }
+
+    #[test]
+    fn test_name_collisions() {
+        let names: Vec<String> = ["work/openai", "work.openai", "work-claude"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        let collisions = name_collisions(&Dashes, &names);
+        assert_eq!(collisions.len(), 2);
+        assert_eq!(
+            collisions["work.openai"],
+            "work/openai, work.openai would all be stored as 'ccm-work-openai'"
+        );
+        assert!(!collisions.contains_key("work-claude"));
+    }
+
+    /// A server answering one request per response (status line, JSON body
+    /// where `{url}` is its URL) in order; its URL, and the requests it got
+    pub(crate) fn serve(
+        responses: &[(&str, &str)],
+    ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses: Vec<String> = responses
+            .iter()
+            .map(|(status, body)| {
+                let body = body.replace("{url}", &url);
+                format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+            })
+            .collect();
+        let server = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let request = read_request(&mut stream);
+                    stream.write_all(response.as_bytes()).unwrap();
+                    request
+                })
+                .collect()
+        });
+        (url, server)
+    }
+
+    fn read_request(stream: &mut std::net::TcpStream) -> String {
+        use std::io::Read;
+
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            let complete = text.find("\r\n\r\n").is_some_and(|end| {
+                let length = text[..end]
+                    .lines()
+                    .find_map(|line| {
+                        let line = line.to_lowercase();
+                        line.strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                request.len() >= end + 4 + length
+            });
+            if complete || n == 0 {
+                return text;
+            }
+        }
+    }
+}
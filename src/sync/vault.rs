@@ -0,0 +1,233 @@
+// HashiCorp Vault KV version 2 backend (ccm sync vault)
+//
+// `--path secret/ccm` is the KV mount (`secret`) followed by a prefix
+// (`ccm`); entry `openai` becomes `secret/ccm/openai`, and entry names with
+// slashes become nested paths. The ccm metadata is kept in the secret's
+// custom_metadata. Authentication uses the Vault CLI's conventions:
+// VAULT_ADDR, VAULT_TOKEN (else ~/.vault-token) and VAULT_NAMESPACE.
+
+use super::{send_json, string_map, RemoteSecret, SyncBackend};
+use crate::utils::{CcmError, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Environment variable: Vault address (default for --addr)
+pub const VAULT_ADDR_ENV: &str = "VAULT_ADDR";
+/// Environment variable: Vault token
+pub const VAULT_TOKEN_ENV: &str = "VAULT_TOKEN";
+/// Environment variable: Vault Enterprise namespace
+pub const VAULT_NAMESPACE_ENV: &str = "VAULT_NAMESPACE";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A KV v2 mount and prefix on a Vault server
+pub struct VaultKv {
+    addr: String,
+    token: String,
+    namespace: Option<String>,
+    mount: String,
+    prefix: String,
+    agent: ureq::Agent,
+}
+
+impl VaultKv {
+    /// Connect to `addr` (else VAULT_ADDR) at `path` (mount/prefix)
+    pub fn new(addr: Option<&str>, path: &str) -> Result<Self> {
+        let addr = match addr {
+            Some(addr) => addr.to_string(),
+            None => std::env::var(VAULT_ADDR_ENV).map_err(|_| {
+                CcmError::InvalidArgument(format!(
+                    "No Vault address: pass --addr or set {}",
+                    VAULT_ADDR_ENV
+                ))
+            })?,
+        };
+        let (mount, prefix) = split_path(path)?;
+
+        Ok(VaultKv {
+            agent: crate::net::agent(&addr, TIMEOUT),
+            addr: addr.trim_end_matches('/').to_string(),
+            token: token()?,
+            namespace: std::env::var(VAULT_NAMESPACE_ENV)
+                .ok()
+                .filter(|n| !n.is_empty()),
+            mount,
+            prefix,
+        })
+    }
+
+    /// URL of `name` under the `data` or `metadata` API of the mount
+    fn url(&self, api: &str, name: &str) -> String {
+        let path = [self.prefix.as_str(), name]
+            .iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| encode_path(p.trim_matches('/')))
+            .collect::<Vec<_>>()
+            .join("/");
+        format!(
+            "{}/v1/{}/{}/{}",
+            self.addr,
+            encode_path(&self.mount),
+            api,
+            path
+        )
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self
+            .agent
+            .request(method, url)
+            .set("X-Vault-Token", &self.token);
+        match &self.namespace {
+            Some(namespace) => request.set("X-Vault-Namespace", namespace),
+            None => request,
+        }
+    }
+
+    /// GET a JSON document; None on 404
+    fn get(&self, url: &str) -> Result<Option<Value>> {
+        send_json(self.request("GET", url), None, "Vault")
+    }
+
+    fn post(&self, url: &str, body: &Value) -> Result<()> {
+        send_json(self.request("POST", url), Some(body), "Vault")?;
+        Ok(())
+    }
+
+    /// Secret names below `dir` (relative to the prefix), recursively
+    fn list_dir(&self, dir: &str, names: &mut Vec<String>) -> Result<()> {
+        let url = format!("{}?list=true", self.url("metadata", dir));
+        let Some(document) = self.get(&url)? else {
+            return Ok(());
+        };
+        let keys = document["data"]["keys"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        for key in keys.iter().filter_map(Value::as_str) {
+            let name = format!("{}{}", dir, key);
+            if key.ends_with('/') {
+                self.list_dir(&name, names)?;
+            } else {
+                names.push(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SyncBackend for VaultKv {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        self.list_dir("", &mut names)?;
+        names.sort();
+        Ok(names)
+    }
+
+    fn read(&self, name: &str) -> Result<Option<RemoteSecret>> {
+        let Some(document) = self.get(&self.url("data", name))? else {
+            return Ok(None);
+        };
+        // A deleted (but not destroyed) version has no data
+        if document["data"]["data"].is_null() {
+            return Ok(None);
+        }
+        Ok(Some(RemoteSecret {
+            name: name.to_string(),
+            data: string_map(&document["data"]["data"]),
+            meta: string_map(&document["data"]["metadata"]["custom_metadata"]),
+        }))
+    }
+
+    fn write(&self, secret: &RemoteSecret) -> Result<()> {
+        self.post(
+            &self.url("data", &secret.name),
+            &json!({ "data": secret.data }),
+        )?;
+        self.post(
+            &self.url("metadata", &secret.name),
+            &json!({ "custom_metadata": secret.meta }),
+        )
+    }
+}
+
+/// The token: VAULT_TOKEN, else the one `vault login` saved
+fn token() -> Result<String> {
+    if let Some(token) = std::env::var(VAULT_TOKEN_ENV)
+        .ok()
+        .filter(|t| !t.is_empty())
+    {
+        return Ok(token);
+    }
+    dirs::home_dir()
+        .and_then(|home| std::fs::read_to_string(home.join(".vault-token")).ok())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| {
+            CcmError::InvalidArgument(format!(
+                "No Vault token: set {} or run 'vault login'",
+                VAULT_TOKEN_ENV
+            ))
+        })
+}
+
+/// Split `secret/team/ccm` into the mount (`secret`) and prefix (`team/ccm`)
+fn split_path(path: &str) -> Result<(String, String)> {
+    let path = path.trim_matches('/');
+    let (mount, prefix) = path.split_once('/').unwrap_or((path, ""));
+    if mount.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "The Vault path needs a KV mount, e.g. secret/ccm".to_string(),
+        ));
+    }
+    Ok((mount.to_string(), prefix.to_string()))
+}
+
+/// Percent-encode a path, keeping the slashes
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            split_path("secret/team/ccm/").unwrap(),
+            ("secret".to_string(), "team/ccm".to_string())
+        );
+        assert_eq!(split_path("kv").unwrap(), ("kv".to_string(), String::new()));
+        assert!(split_path("/").is_err());
+    }
+
+    #[test]
+    fn test_url() {
+        let kv = VaultKv {
+            addr: "https://vault.example.com:8200".to_string(),
+            token: "t".to_string(),
+            namespace: None,
+            mount: "secret".to_string(),
+            prefix: "ccm".to_string(),
+            agent: ureq::Agent::new(),
+        };
+        assert_eq!(
+            kv.url("data", "work/My Bank"),
+            "https://vault.example.com:8200/v1/secret/data/ccm/work/My%20Bank"
+        );
+        assert_eq!(
+            kv.url("metadata", ""),
+            "https://vault.example.com:8200/v1/secret/metadata/ccm"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+// Machine-to-machine transfer (ccm transfer send|receive)
+//
+// The sender listens on a TCP port and shows a one-time code such as
+// 42-1234-5678; it announces the port on the local network with UDP
+// broadcasts tagged with the code's first group (the nameplate). The
+// receiver, given the code, finds the sender (or connects where --connect
+// says), then both run SPAKE2 with the code as password (see `pake`). Each
+// side proves it has the key before anything else is sent; the entries
+// follow encrypted with AES-256-GCM, and the receiver acknowledges them.
+// The sender accepts a single connection, so a wrong code ends the transfer.
+//
+// Wire format: frames of a 4-byte big-endian length and the bytes.
+//   sender -> receiver   share (33 bytes)
+//   receiver -> sender   share
+//   sender -> receiver   confirmation (HMAC of "sender")
+//   receiver -> sender   confirmation (HMAC of "receiver")
+//   sender -> receiver   encrypted payload
+//   receiver -> sender   encrypted "ok"
+
+pub mod pake;
+
+use crate::utils::crypto::{
+    decrypt_aes256_gcm, encrypt_aes256_gcm, hmac_sha256, hmac_sha256_verify,
+};
+use crate::utils::{CcmError, Result};
+use pake::{Exchange, Side};
+use rand::Rng;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// UDP port the sender's announcements go to
+pub const DISCOVERY_PORT: u16 = 7712;
+
+const BEACON_PREFIX: &str = "ccm-transfer/1";
+const MAX_FRAME: usize = 64 * 1024 * 1024;
+const ANNOUNCE_EVERY: Duration = Duration::from_secs(1);
+
+/// A fresh code: a two-digit nameplate and eight secret digits
+pub fn generate_code() -> String {
+    let mut rng = rand::rngs::OsRng;
+    format!(
+        "{}-{:04}-{:04}",
+        rng.gen_range(10..100),
+        rng.gen_range(0..10_000),
+        rng.gen_range(0..10_000)
+    )
+}
+
+/// The nameplate of a code, checking its shape
+pub fn nameplate(code: &str) -> Result<&str> {
+    let groups: Vec<&str> = code.split('-').collect();
+    let valid = groups.len() >= 2
+        && groups
+            .iter()
+            .all(|g| !g.is_empty() && g.bytes().all(|b| b.is_ascii_digit()));
+    if !valid {
+        return Err(CcmError::InvalidArgument(format!(
+            "'{}' is not a transfer code (like 42-1234-5678)",
+            code
+        )));
+    }
+    Ok(groups[0])
+}
+
+fn beacon(nameplate: &str, port: u16) -> String {
+    format!("{} {} {}", BEACON_PREFIX, nameplate, port)
+}
+
+/// Nameplate and port of an announcement
+fn parse_beacon(text: &str) -> Option<(&str, u16)> {
+    let mut parts = text.split_whitespace();
+    if parts.next() != Some(BEACON_PREFIX) {
+        return None;
+    }
+    let nameplate = parts.next()?;
+    let port = parts.next()?.parse().ok()?;
+    parts.next().is_none().then_some((nameplate, port))
+}
+
+/// Announce `port` on the local network until `stop` is set
+pub fn announce(nameplate: &str, port: u16, stop: Arc<AtomicBool>) -> Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    let message = beacon(nameplate, port);
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            let _ = socket.send_to(message.as_bytes(), (Ipv4Addr::BROADCAST, DISCOVERY_PORT));
+            std::thread::sleep(ANNOUNCE_EVERY);
+        }
+    });
+    Ok(())
+}
+
+/// Wait for the announcement of `nameplate`; the sender's address
+pub fn discover(nameplate: &str, timeout: Duration) -> Result<SocketAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT)).map_err(|e| {
+        CcmError::Remote(format!(
+            "Cannot listen for the sender on UDP port {}: {} (pass --connect ADDRESS:PORT)",
+            DISCOVERY_PORT, e
+        ))
+    })?;
+    let deadline = Instant::now() + timeout;
+    let mut buffer = [0u8; 256];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(CcmError::Remote(
+                "No sender with this code found on the local network \
+                 (pass --connect ADDRESS:PORT, as shown by the sender)"
+                    .to_string(),
+            ));
+        }
+        socket.set_read_timeout(Some(left))?;
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let text = String::from_utf8_lossy(&buffer[..len]);
+        if let Some((found, port)) = parse_beacon(&text) {
+            if found == nameplate {
+                return Ok(SocketAddr::new(from.ip(), port));
+            }
+        }
+    }
+}
+
+/// This machine's address on the network the default route leads to
+/// (nothing is sent)
+pub fn local_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+fn write_frame<W: Write>(stream: &mut W, data: &[u8]) -> Result<()> {
+    stream.write_all(&(data.len() as u32).to_be_bytes())?;
+    stream.write_all(data)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// A frame; `hangup` is the error when the other side closed the connection
+fn read_frame<R: Read>(stream: &mut R, hangup: &str) -> Result<Vec<u8>> {
+    let closed = |e: std::io::Error| match e.kind() {
+        ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset => {
+            CcmError::Remote(hangup.to_string())
+        }
+        _ => e.into(),
+    };
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length).map_err(closed)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME {
+        return Err(CcmError::Remote(format!(
+            "The other side sent {} bytes at once",
+            length
+        )));
+    }
+    let mut data = vec![0; length];
+    stream.read_exact(&mut data).map_err(closed)?;
+    Ok(data)
+}
+
+/// Keys derived from the SPAKE2 key
+struct SessionKeys {
+    /// Key of the confirmations (HMACs of "sender" and "receiver")
+    confirm: [u8; 32],
+    data: [u8; 32],
+}
+
+impl SessionKeys {
+    fn confirmation(&self, side: Side) -> [u8; 32] {
+        hmac_sha256(&self.confirm, side_label(side))
+    }
+
+    /// Whether `tag` is the confirmation of `side` (in constant time)
+    fn confirms(&self, side: Side, tag: &[u8]) -> bool {
+        hmac_sha256_verify(&self.confirm, side_label(side), tag)
+    }
+}
+
+fn side_label(side: Side) -> &'static [u8] {
+    match side {
+        Side::Sender => b"sender",
+        Side::Receiver => b"receiver",
+    }
+}
+
+/// Run SPAKE2 on `stream`
+fn handshake<S: Read + Write>(stream: &mut S, side: Side, code: &str) -> Result<SessionKeys> {
+    let hangup = "The other side closed the connection";
+    let (pake, share) = Exchange::start(side, code.as_bytes());
+    let theirs = match side {
+        Side::Sender => {
+            write_frame(stream, &share)?;
+            read_frame(stream, hangup)?
+        }
+        Side::Receiver => {
+            let theirs = read_frame(stream, hangup)?;
+            write_frame(stream, &share)?;
+            theirs
+        }
+    };
+    let key = pake.finish(&theirs)?;
+    Ok(SessionKeys {
+        confirm: hmac_sha256(&key, b"confirm"),
+        data: hmac_sha256(&key, b"data"),
+    })
+}
+
+/// Send `payload` to the receiver at the other end of `stream`
+pub fn send_over<S: Read + Write>(stream: &mut S, code: &str, payload: &[u8]) -> Result<()> {
+    let keys = handshake(stream, Side::Sender, code)?;
+    write_frame(stream, &keys.confirmation(Side::Sender))?;
+    let confirm = read_frame(stream, "The receiver entered a wrong code")?;
+    if !keys.confirms(Side::Receiver, &confirm) {
+        return Err(CcmError::Remote(
+            "The receiver entered a wrong code".to_string(),
+        ));
+    }
+
+    write_frame(stream, &encrypt_aes256_gcm(&keys.data, payload)?)?;
+    let ack = read_frame(
+        stream,
+        "The receiver closed the connection before confirming",
+    )?;
+    match decrypt_aes256_gcm(&keys.data, &ack) {
+        Ok(ack) if ack == b"ok" => Ok(()),
+        _ => Err(CcmError::Remote(
+            "The receiver did not confirm the transfer".to_string(),
+        )),
+    }
+}
+
+/// Receive the payload from the sender at the other end of `stream`
+pub fn receive_over<S: Read + Write>(stream: &mut S, code: &str) -> Result<Vec<u8>> {
+    let keys = handshake(stream, Side::Receiver, code)?;
+    let confirm = read_frame(stream, "The sender closed the connection")?;
+    if !keys.confirms(Side::Sender, &confirm) {
+        return Err(CcmError::InvalidArgument(
+            "Wrong code: the sender has a different one (it now has to start over)".to_string(),
+        ));
+    }
+    write_frame(stream, &keys.confirmation(Side::Receiver))?;
+
+    let sealed = read_frame(stream, "The sender closed the connection")?;
+    let payload = decrypt_aes256_gcm(&keys.data, &sealed)
+        .map_err(|_| CcmError::Decryption("The transfer was tampered with".to_string()))?;
+    write_frame(stream, &encrypt_aes256_gcm(&keys.data, b"ok")?)?;
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+
+    /// Run a sender with `send_code` and a receiver with `receive_code`
+    fn transfer(send_code: &str, receive_code: &str) -> (Result<()>, Result<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let send_code = send_code.to_string();
+        let sender = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            send_over(&mut stream, &send_code, b"{\"entries\":{}}")
+        });
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let received = receive_over(&mut stream, receive_code);
+        drop(stream);
+        (sender.join().unwrap(), received)
+    }
+
+    #[test]
+    fn test_transfer() {
+        let (sent, received) = transfer("42-1234-5678", "42-1234-5678");
+        sent.unwrap();
+        assert_eq!(received.unwrap(), b"{\"entries\":{}}");
+    }
+
+    #[test]
+    fn test_wrong_code() {
+        let (sent, received) = transfer("42-1234-5678", "42-1234-0000");
+        assert!(received.unwrap_err().to_string().contains("Wrong code"));
+        assert!(sent.unwrap_err().to_string().contains("wrong code"));
+    }
+
+    #[test]
+    fn test_code_and_beacon() {
+        let code = generate_code();
+        assert_eq!(code.len(), 12, "{}", code);
+        assert_eq!(nameplate(&code).unwrap(), &code[..2]);
+        assert!(nameplate("42").is_err());
+        assert!(nameplate("42-abc").is_err());
+        assert!(nameplate("42--1").is_err());
+
+        let text = beacon("42", 50123);
+        assert_eq!(parse_beacon(&text), Some(("42", 50123)));
+        assert_eq!(parse_beacon("ccm-transfer/1 42"), None);
+        assert_eq!(parse_beacon("other 42 1"), None);
+        assert_eq!(parse_beacon("ccm-transfer/1 42 1 extra"), None);
+    }
+}
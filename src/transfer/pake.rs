@@ -0,0 +1,78 @@
+// SPAKE2 key exchange (the `spake2` crate, over Ed25519)
+//
+// Both sides turn the transfer code into a password and send a blinded
+// Diffie-Hellman share. Only someone who knows the code can unblind the other
+// share, so the shared key is the same on both ends exactly when the codes
+// match, and an attacker gets one guess per connection.
+
+use crate::utils::{CcmError, Result};
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+
+/// Identities bound into the key, so a share cannot be reflected back
+const SENDER_ID: &[u8] = b"ccm-transfer sender";
+const RECEIVER_ID: &[u8] = b"ccm-transfer receiver";
+
+/// Which end of the exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Sender,
+    Receiver,
+}
+
+/// One side's state between sending its share and getting the other's
+pub struct Exchange(Spake2<Ed25519Group>);
+
+impl Exchange {
+    /// Start the exchange; the returned share goes to the other side
+    pub fn start(side: Side, password: &[u8]) -> (Self, Vec<u8>) {
+        let password = Password::new(password);
+        let (sender, receiver) = (Identity::new(SENDER_ID), Identity::new(RECEIVER_ID));
+        let (state, share) = match side {
+            Side::Sender => Spake2::start_a(&password, &sender, &receiver),
+            Side::Receiver => Spake2::start_b(&password, &sender, &receiver),
+        };
+        (Exchange(state), share)
+    }
+
+    /// The shared key, from the other side's share
+    pub fn finish(self, theirs: &[u8]) -> Result<[u8; 32]> {
+        let invalid =
+            || CcmError::Remote("The other side sent an invalid key exchange".to_string());
+        let key = self.0.finish(theirs).map_err(|_| invalid())?;
+        key.try_into().map_err(|_| invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(sender_code: &str, receiver_code: &str) -> ([u8; 32], [u8; 32]) {
+        let (sender, x) = Exchange::start(Side::Sender, sender_code.as_bytes());
+        let (receiver, y) = Exchange::start(Side::Receiver, receiver_code.as_bytes());
+        (sender.finish(&y).unwrap(), receiver.finish(&x).unwrap())
+    }
+
+    #[test]
+    fn test_same_code_same_key() {
+        let (a, b) = exchange("42-1234-5678", "42-1234-5678");
+        assert_eq!(a, b);
+
+        // Fresh secrets every time
+        let (c, _) = exchange("42-1234-5678", "42-1234-5678");
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_wrong_code_different_key() {
+        let (a, b) = exchange("42-1234-5678", "42-1234-5679");
+        assert_ne!(a, b);
+
+        let (sender, _) = Exchange::start(Side::Sender, b"42-1234-5678");
+        assert!(sender.finish(&[0xff; 32]).is_err());
+
+        // A share sent back to its own side
+        let (sender, x) = Exchange::start(Side::Sender, b"42-1234-5678");
+        assert!(sender.finish(&x).is_err());
+    }
+}
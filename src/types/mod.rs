@@ -1,5 +1,6 @@
 // Core type definitions for CCM
 
+use crate::utils::csv_parser::extract_domain;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -30,6 +31,18 @@ pub struct Entry {
     /// Last update timestamp
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<String>,
+
+    /// When the secret was last used (get, use, exec...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+
+    /// Protected by `ccm lock`: delete and secret changes need --force-unlock
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+
+    /// Pinned with `ccm fav`: listed first, and alone with `list --fav`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
 }
 
 impl Entry {
@@ -42,6 +55,9 @@ impl Entry {
             notes: None,
             created_at: None,
             updated_at: None,
+            last_used_at: None,
+            locked: false,
+            favorite: false,
         }
     }
 
@@ -55,6 +71,17 @@ impl Entry {
         self.metadata.insert(key, value);
     }
 
+    /// Domain of the entry's `url` field, lowercased and without `www.`
+    pub fn domain(&self) -> Option<String> {
+        let url = self.metadata.get("url").or_else(|| {
+            self.metadata
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("url"))
+                .map(|(_, v)| v)
+        })?;
+        extract_domain(url).map(|d| d.to_lowercase())
+    }
+
     /// Check if metadata contains the SECRET placeholder
     pub fn has_secret_placeholder(&self) -> bool {
         self.metadata.values().any(|v| v == "SECRET")
@@ -33,6 +33,7 @@ fn copy_windows(text: &str) -> bool {
     let escaped = text.replace("'", "''").replace("$", "`$");
 
     let result = Command::new("powershell")
+        .env_remove(crate::auth::PIN_ENV)
         .args(["-Command", &format!("Set-Clipboard -Value '{}'", escaped)])
         .output();
 
@@ -48,7 +49,10 @@ fn copy_macos(text: &str) -> bool {
     use std::io::Write;
     use std::process::Stdio;
 
-    let child = Command::new("pbcopy").stdin(Stdio::piped()).spawn();
+    let child = Command::new("pbcopy")
+        .env_remove(crate::auth::PIN_ENV)
+        .stdin(Stdio::piped())
+        .spawn();
 
     match child {
         Ok(mut process) => {
@@ -74,6 +78,7 @@ fn copy_linux(text: &str) -> bool {
 
     // Try xclip first
     let xclip = Command::new("xclip")
+        .env_remove(crate::auth::PIN_ENV)
         .args(["-selection", "clipboard"])
         .stdin(Stdio::piped())
         .spawn();
@@ -92,6 +97,7 @@ fn copy_linux(text: &str) -> bool {
 
     // Fall back to xsel
     let xsel = Command::new("xsel")
+        .env_remove(crate::auth::PIN_ENV)
         .args(["--clipboard", "--input"])
         .stdin(Stdio::piped())
         .spawn();
@@ -1,5 +1,6 @@
 // Cryptographic utilities for CCM
 
+use crate::utils::timings::{self, Phase};
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit, OsRng},
     Aes256Gcm, Nonce,
@@ -32,13 +33,16 @@ pub fn derive_key_from_pin(pin: &str, salt: &[u8], iterations: Option<u32>) -> [
     let iterations = iterations.unwrap_or(PBKDF2_ITERATIONS);
     let iterations = NonZeroU32::new(iterations).expect("Iterations must be non-zero");
 
-    pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt, iterations.get(), &mut key);
+    timings::measure(Phase::Kdf, || {
+        pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt, iterations.get(), &mut key)
+    });
 
     key
 }
 
 /// Encrypt data using AES-256-GCM
 pub fn encrypt_aes256_gcm(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let _span = timings::span(Phase::Crypto);
     let cipher = Aes256Gcm::new(key.into());
     let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
 
@@ -56,6 +60,7 @@ pub fn encrypt_aes256_gcm(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
 
 /// Decrypt data using AES-256-GCM
 pub fn decrypt_aes256_gcm(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>> {
+    let _span = timings::span(Phase::Crypto);
     if data.len() < 12 {
         // Nonce length for AES-256-GCM is 12 bytes
         return Err(anyhow::anyhow!("Invalid ciphertext: too short"));
@@ -100,6 +105,29 @@ pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
     mac.finalize().into_bytes().into()
 }
 
+/// Verify an HMAC-SHA256 tag in constant time
+pub fn hmac_sha256_verify(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    type HmacSha256 = Hmac<Sha256>;
+
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(data);
+    mac.verify_slice(tag).is_ok()
+}
+
+/// Derive the key that signs backups from the master key, so the signature
+/// does not expose anything usable against the vault itself
+pub fn derive_backup_signing_key(master_key: &[u8; 32]) -> [u8; 32] {
+    hmac_sha256(master_key, b"ccm-backup-signing-v1")
+}
+
+/// Short public identifier of a signing key (first 8 bytes of its SHA-256)
+pub fn signing_key_id(signing_key: &[u8; 32]) -> String {
+    hex::encode(&sha256_hash(signing_key)[..8])
+}
+
 /// Derive database password from master key (matches TypeScript implementation)
 pub fn derive_database_password(master_key: &[u8; 32]) -> String {
     use base64::Engine;
@@ -111,6 +139,22 @@ pub fn derive_database_password(master_key: &[u8; 32]) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_backup_signing() {
+        let master_key = generate_master_key();
+        let key = derive_backup_signing_key(&master_key);
+        assert_ne!(key, master_key);
+        assert_eq!(signing_key_id(&key).len(), 16);
+
+        let tag = hmac_sha256(&key, b"backup data");
+        assert!(hmac_sha256_verify(&key, b"backup data", &tag));
+        assert!(!hmac_sha256_verify(&key, b"backup dat4", &tag));
+
+        let other = derive_backup_signing_key(&generate_master_key());
+        assert!(!hmac_sha256_verify(&other, b"backup data", &tag));
+        assert_ne!(signing_key_id(&key), signing_key_id(&other));
+    }
+
     #[test]
     fn test_encrypt_decrypt() {
         let key = generate_master_key();
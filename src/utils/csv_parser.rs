@@ -8,6 +8,7 @@ pub enum BrowserFormat {
     Chrome,
     Firefox,
     Safari,
+    LastPass,
     Generic,
 }
 
@@ -18,6 +19,7 @@ impl BrowserFormat {
             BrowserFormat::Chrome => "chrome",
             BrowserFormat::Firefox => "firefox",
             BrowserFormat::Safari => "safari",
+            BrowserFormat::LastPass => "lastpass",
             BrowserFormat::Generic => "generic",
         }
     }
@@ -59,6 +61,13 @@ fn get_browser_specs() -> HashMap<BrowserFormat, BrowserFormatSpec> {
         },
     );
 
+    specs.insert(
+        BrowserFormat::LastPass,
+        BrowserFormatSpec {
+            required_columns: vec!["url", "username", "password", "extra", "name", "grouping"],
+        },
+    );
+
     specs.insert(
         BrowserFormat::Generic,
         BrowserFormatSpec {
@@ -75,6 +84,15 @@ pub fn detect_browser_format(headers: &[String]) -> BrowserFormat {
     let headers_original: Vec<&str> = headers.iter().map(|h| h.as_str()).collect();
     let _specs = get_browser_specs();
 
+    // LastPass format check (has "extra" and "grouping", checked before Edge
+    // since it also has a "name" column)
+    if headers_lower.contains(&"extra".to_string())
+        && headers_lower.contains(&"grouping".to_string())
+        && headers_lower.contains(&"password".to_string())
+    {
+        return BrowserFormat::LastPass;
+    }
+
     // Edge format check (has "name" column)
     if headers_lower.contains(&"name".to_string())
         && headers_lower.contains(&"url".to_string())
@@ -226,6 +244,21 @@ fn parse_csv_line(line: &str) -> Vec<String> {
     values
 }
 
+/// Format values as one CSV line (RFC 4180 quoting, no line terminator)
+pub fn format_csv_row(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|value| {
+            if value.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 /// Decode CSV content, handling UTF-8 BOM
 pub fn decode_csv_content(bytes: &[u8]) -> String {
     // Check for UTF-8 BOM and skip it
@@ -245,6 +278,8 @@ pub struct MappedEntry {
     pub entry_type: String,
     pub secret: String,
     pub metadata: HashMap<String, String>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
 }
 
 /// Map CSV rows to entries based on browser format
@@ -260,6 +295,7 @@ pub fn map_csv_to_entries(
             BrowserFormat::Chrome => map_chrome_row(row, index),
             BrowserFormat::Firefox => map_firefox_row(row, index),
             BrowserFormat::Safari => map_safari_row(row, index),
+            BrowserFormat::LastPass => map_lastpass_row(row, index),
             BrowserFormat::Generic => map_generic_row(row, index),
         };
 
@@ -298,6 +334,8 @@ fn map_edge_row(row: &HashMap<String, String>, index: usize) -> Option<MappedEnt
         entry_type: "password".to_string(),
         secret: password,
         metadata,
+        tags: Vec::new(),
+        notes: None,
     })
 }
 
@@ -330,6 +368,8 @@ fn map_chrome_row(row: &HashMap<String, String>, index: usize) -> Option<MappedE
         entry_type: "password".to_string(),
         secret: password,
         metadata,
+        tags: Vec::new(),
+        notes: None,
     })
 }
 
@@ -365,6 +405,46 @@ fn map_safari_row(row: &HashMap<String, String>, index: usize) -> Option<MappedE
         entry_type: "password".to_string(),
         secret: password,
         metadata,
+        tags: Vec::new(),
+        notes: None,
+    })
+}
+
+/// LastPass: url,username,password,totp,extra,name,grouping,fav
+/// `extra` holds the notes, `grouping` the folder path (`Work\Servers`)
+fn map_lastpass_row(row: &HashMap<String, String>, index: usize) -> Option<MappedEntry> {
+    let password = get_field(row, &["password"])?;
+    if password.is_empty() {
+        return None;
+    }
+
+    let name = get_field(row, &["name"])
+        .filter(|s| !s.is_empty())
+        .or_else(|| get_field(row, &["url"]).and_then(|url| extract_domain(&url)))
+        .unwrap_or_else(|| format!("import-{}", index + 1));
+
+    let mut metadata = HashMap::new();
+    for key in ["url", "username", "totp"] {
+        if let Some(value) = get_field(row, &[key]).filter(|v| !v.is_empty()) {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+
+    let tags = get_field(row, &["grouping"])
+        .map(|g| g.trim().replace('\\', "/"))
+        .filter(|g| !g.is_empty())
+        .into_iter()
+        .collect();
+
+    let notes = get_field(row, &["extra"]).filter(|n| !n.trim().is_empty());
+
+    Some(MappedEntry {
+        name,
+        entry_type: "password".to_string(),
+        secret: password,
+        metadata,
+        tags,
+        notes,
     })
 }
 
@@ -395,6 +475,8 @@ fn map_generic_row(row: &HashMap<String, String>, index: usize) -> Option<Mapped
         entry_type: "password".to_string(),
         secret: password,
         metadata,
+        tags: Vec::new(),
+        notes: None,
     })
 }
 
@@ -416,7 +498,7 @@ fn get_field(row: &HashMap<String, String>, keys: &[&str]) -> Option<String> {
 }
 
 /// Extract domain from URL for use as name
-fn extract_domain(url: &str) -> Option<String> {
+pub fn extract_domain(url: &str) -> Option<String> {
     let url = url.trim();
     if url.is_empty() {
         return None;
@@ -441,17 +523,126 @@ fn extract_domain(url: &str) -> Option<String> {
     }
 }
 
-/// Resolve duplicate names by appending suffixes
-pub fn resolve_duplicate_names(
+/// Key identifying the same login across exports: (domain, username), lowercased
+/// None unless the entry has both a URL and a username
+pub fn login_key(metadata: &HashMap<String, String>) -> Option<(String, String)> {
+    let domain = metadata.get("url").and_then(|url| extract_domain(url))?;
+    let username = metadata.get("username")?.trim();
+    if username.is_empty() {
+        return None;
+    }
+    Some((domain.to_lowercase(), username.to_lowercase()))
+}
+
+/// Merge rows that are the same login (same domain, username and password),
+/// e.g. one site exported from both Chrome and Edge
+/// The first row wins; later rows only fill in missing metadata and tags
+/// Returns the remaining entries and how many rows were merged away
+pub fn dedupe_logins(entries: Vec<MappedEntry>) -> (Vec<MappedEntry>, usize) {
+    let mut result: Vec<MappedEntry> = Vec::new();
+    let mut seen: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    let mut merged = 0;
+
+    for entry in entries {
+        let key = login_key(&entry.metadata);
+        let existing = key.as_ref().and_then(|k| {
+            seen.get(k)?
+                .iter()
+                .copied()
+                .find(|&i| result[i].secret == entry.secret)
+        });
+
+        match existing {
+            Some(index) => {
+                let target = &mut result[index];
+                for (k, v) in entry.metadata {
+                    target.metadata.entry(k).or_insert(v);
+                }
+                for tag in entry.tags {
+                    if !target.tags.contains(&tag) {
+                        target.tags.push(tag);
+                    }
+                }
+                merged += 1;
+            }
+            None => {
+                if let Some(k) = key {
+                    seen.entry(k).or_default().push(result.len());
+                }
+                result.push(entry);
+            }
+        }
+    }
+
+    (result, merged)
+}
+
+/// How to handle an imported entry whose name already exists in the vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Import under a suffixed name (`name-1`, `name-2`, ...)
+    Rename,
+    /// Leave the existing entry alone
+    Skip,
+    /// Replace the existing entry and its secret
+    Overwrite,
+    /// Keep the existing secret, merge in metadata and tags
+    Merge,
+}
+
+impl ConflictStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "rename" => Some(Self::Rename),
+            "skip" => Some(Self::Skip),
+            "overwrite" => Some(Self::Overwrite),
+            "merge" => Some(Self::Merge),
+            _ => None,
+        }
+    }
+}
+
+/// Imported entries sorted by what to do with them
+#[derive(Debug, Default)]
+pub struct ConflictResolution {
+    /// Entries to create (renamed where needed)
+    pub new_entries: Vec<MappedEntry>,
+    /// Entries replacing the existing entry of the same name
+    pub overwrites: Vec<MappedEntry>,
+    /// Entries to merge into the existing entry of the same name
+    pub merges: Vec<MappedEntry>,
+    /// Names left alone because they already exist
+    pub skipped: Vec<String>,
+    /// (original, new) names of renamed entries
+    pub renamed: Vec<(String, String)>,
+}
+
+/// Apply a conflict strategy to names that already exist in the vault
+/// Duplicate names within the import itself are always renamed, since they
+/// are distinct rows
+pub fn resolve_conflicts(
     entries: Vec<MappedEntry>,
     existing_names: &std::collections::HashSet<String>,
-) -> (Vec<MappedEntry>, usize, Vec<(String, String)>) {
-    let mut result = Vec::new();
+    strategy: ConflictStrategy,
+) -> ConflictResolution {
+    let mut resolution = ConflictResolution::default();
     let mut used_names = existing_names.clone();
-    let mut renamed_count = 0;
-    let mut renamed_entries = Vec::new();
+    let mut claimed = std::collections::HashSet::new();
 
     for mut entry in entries {
+        if strategy != ConflictStrategy::Rename
+            && existing_names.contains(&entry.name)
+            && claimed.insert(entry.name.clone())
+        {
+            match strategy {
+                ConflictStrategy::Skip => resolution.skipped.push(entry.name),
+                ConflictStrategy::Overwrite => resolution.overwrites.push(entry),
+                ConflictStrategy::Merge => resolution.merges.push(entry),
+                ConflictStrategy::Rename => unreachable!(),
+            }
+            continue;
+        }
+
         let original_name = entry.name.clone();
         let mut name = original_name.clone();
         let mut suffix = 1;
@@ -462,22 +653,36 @@ pub fn resolve_duplicate_names(
         }
 
         if name != original_name {
-            renamed_count += 1;
-            renamed_entries.push((original_name, name.clone()));
+            resolution.renamed.push((original_name, name.clone()));
         }
 
         used_names.insert(name.clone());
         entry.name = name;
-        result.push(entry);
+        resolution.new_entries.push(entry);
     }
 
-    (result, renamed_count, renamed_entries)
+    resolution
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format_csv_row() {
+        assert_eq!(format_csv_row(&["a", "b c", ""]), "a,b c,");
+        assert_eq!(format_csv_row(&["say \"hi\""]), "\"say \"\"hi\"\"\"");
+
+        let csv = format!(
+            "{}\n{}\n",
+            format_csv_row(&["name", "notes"]),
+            format_csv_row(&["db", "line 1\nline 2, with comma"])
+        );
+        let rows = parse_csv(&csv);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["notes"], "line 1\nline 2, with comma");
+    }
+
     #[test]
     fn test_parse_csv_simple() {
         let csv = "name,url,username,password\nTest,https://example.com,user,pass123";
@@ -519,6 +724,83 @@ mod tests {
         assert_eq!(detect_browser_format(&headers), BrowserFormat::Safari);
     }
 
+    #[test]
+    fn test_map_lastpass_row() {
+        let csv = "url,username,password,totp,extra,name,grouping,fav\n\
+                   https://github.com,alice,pw1,JBSWY3DPEHPK3PXP,\"recovery codes\nin safe\",GitHub,Work\\Dev,1\n\
+                   http://sn,,,,secure note body,Note,Personal,0";
+        let rows = parse_csv(csv);
+        let headers: Vec<String> = rows[0].keys().cloned().collect();
+        assert_eq!(detect_browser_format(&headers), BrowserFormat::LastPass);
+
+        let entries = map_csv_to_entries(&rows, BrowserFormat::LastPass);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.name, "GitHub");
+        assert_eq!(entry.metadata.get("totp").unwrap(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(entry.tags, vec!["Work/Dev".to_string()]);
+        assert_eq!(entry.notes.as_deref(), Some("recovery codes\nin safe"));
+    }
+
+    #[test]
+    fn test_dedupe_logins() {
+        let edge = "name,url,username,password\n\
+                    GitHub,https://github.com/login,Alice,pw1\n\
+                    Other,https://github.com/,bob,pw2";
+        let chrome = "url,username,password\n\
+                      https://www.github.com/session,alice,pw1\n\
+                      https://github.com/session,alice,changed";
+
+        let mut entries = map_csv_to_entries(&parse_csv(edge), BrowserFormat::Edge);
+        entries.extend(map_csv_to_entries(
+            &parse_csv(chrome),
+            BrowserFormat::Chrome,
+        ));
+
+        let (entries, merged) = dedupe_logins(entries);
+        assert_eq!(merged, 1);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].name, "GitHub");
+        // Same login with a different password is kept for the user to resolve
+        assert_eq!(entries[2].secret, "changed");
+    }
+
+    #[test]
+    fn test_resolve_conflicts() {
+        let entry = |name: &str| MappedEntry {
+            name: name.to_string(),
+            entry_type: "password".to_string(),
+            secret: "pw".to_string(),
+            metadata: HashMap::new(),
+            tags: Vec::new(),
+            notes: None,
+        };
+        let existing: std::collections::HashSet<String> =
+            ["site".to_string()].into_iter().collect();
+        let entries = || vec![entry("site"), entry("site"), entry("new")];
+
+        let renamed = resolve_conflicts(entries(), &existing, ConflictStrategy::Rename);
+        let names: Vec<&str> = renamed
+            .new_entries
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["site-1", "site-2", "new"]);
+
+        let skipped = resolve_conflicts(entries(), &existing, ConflictStrategy::Skip);
+        assert_eq!(skipped.skipped, vec!["site".to_string()]);
+        let names: Vec<&str> = skipped
+            .new_entries
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["site-1", "new"]);
+
+        let merged = resolve_conflicts(entries(), &existing, ConflictStrategy::Merge);
+        assert_eq!(merged.merges.len(), 1);
+        assert_eq!(merged.new_entries.len(), 2);
+    }
+
     #[test]
     fn test_extract_domain() {
         assert_eq!(
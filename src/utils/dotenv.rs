@@ -0,0 +1,229 @@
+// .env file parser following common dotenv conventions
+//
+// - `KEY=VALUE`, optionally prefixed with `export `
+// - `#` starts a comment on its own line, or after whitespace in an unquoted value
+// - single-quoted values are literal
+// - double-quoted values may span lines and support \n, \r, \t, \", \\ and \$
+
+use crate::utils::{CcmError, Result};
+
+/// Parse .env content into (key, value) pairs in file order
+/// Later duplicates of a key override earlier ones
+pub fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+    let mut lines = content.lines().enumerate();
+
+    while let Some((index, raw_line)) = lines.next() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line
+            .strip_prefix("export ")
+            .map(str::trim_start)
+            .unwrap_or(line);
+        let (key, rest) = line.split_once('=').ok_or_else(|| {
+            CcmError::InvalidArgument(format!("Line {}: expected KEY=VALUE", index + 1))
+        })?;
+
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(CcmError::InvalidArgument(format!(
+                "Line {}: invalid variable name '{}'",
+                index + 1,
+                key
+            )));
+        }
+
+        let rest = rest.trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            // Double-quoted values may continue on the following lines
+            let mut buffer = quoted.to_string();
+            loop {
+                if let Some(end) = closing_quote(&buffer) {
+                    break unescape(&buffer[..end]);
+                }
+                match lines.next() {
+                    Some((_, next)) => {
+                        buffer.push('\n');
+                        buffer.push_str(next);
+                    }
+                    None => {
+                        return Err(CcmError::InvalidArgument(format!(
+                            "Line {}: unterminated double quote",
+                            index + 1
+                        )))
+                    }
+                }
+            }
+        } else if let Some(quoted) = rest.strip_prefix('\'') {
+            let end = quoted.find('\'').ok_or_else(|| {
+                CcmError::InvalidArgument(format!("Line {}: unterminated single quote", index + 1))
+            })?;
+            quoted[..end].to_string()
+        } else {
+            strip_inline_comment(rest).trim_end().to_string()
+        };
+
+        match vars.iter_mut().find(|(k, _)| k == key) {
+            Some(existing) => existing.1 = value,
+            None => vars.push((key.to_string(), value)),
+        }
+    }
+
+    Ok(vars)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Render (key, value) pairs as .env content that `parse_dotenv` reads back unchanged
+/// Values are left bare when safe, otherwise double-quoted with escapes
+pub fn format_dotenv(vars: &[(String, String)]) -> String {
+    let mut out = String::new();
+    for (key, value) in vars {
+        out.push_str(key);
+        out.push('=');
+        out.push_str(&quote_value(value));
+        out.push('\n');
+    }
+    out
+}
+
+fn quote_value(value: &str) -> String {
+    let bare = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./:@+,=%".contains(c));
+    if bare {
+        return value.to_string();
+    }
+
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '"' | '\\' | '$' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Index of the first unescaped double quote
+fn closing_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(other @ ('"' | '\\' | '$')) => out.push(other),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Drop a trailing ` # comment` from an unquoted value
+fn strip_inline_comment(value: &str) -> &str {
+    let bytes = value.as_bytes();
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'#' && (i == 0 || bytes[i - 1].is_ascii_whitespace()) {
+            return &value[..i];
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dotenv_basic() {
+        let content = "# comment\n\
+                       export API_KEY=sk-123 # inline comment\n\
+                       BASE_URL = https://example.com/#anchor\n\
+                       EMPTY=\n\
+                       API_KEY=sk-456\n";
+        let vars = parse_dotenv(content).unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("API_KEY".to_string(), "sk-456".to_string()),
+                (
+                    "BASE_URL".to_string(),
+                    "https://example.com/#anchor".to_string()
+                ),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv_quotes() {
+        let content = "SINGLE='literal \\n $HOME # not a comment'\n\
+                       DOUBLE=\"line1\\nline2 \\\"quoted\\\"\"\n\
+                       MULTI=\"first\nsecond\"\n";
+        let vars = parse_dotenv(content).unwrap();
+        assert_eq!(vars[0].1, "literal \\n $HOME # not a comment");
+        assert_eq!(vars[1].1, "line1\nline2 \"quoted\"");
+        assert_eq!(vars[2].1, "first\nsecond");
+    }
+
+    #[test]
+    fn test_parse_dotenv_errors() {
+        assert!(parse_dotenv("NOT A LINE").is_err());
+        assert!(parse_dotenv("1BAD=x").is_err());
+        assert!(parse_dotenv("OPEN=\"never closed").is_err());
+    }
+
+    #[test]
+    fn test_format_dotenv_round_trip() {
+        let vars = vec![
+            ("PLAIN".to_string(), "sk-abc_123".to_string()),
+            ("URL".to_string(), "https://example.com/v1".to_string()),
+            (
+                "SPACED".to_string(),
+                "two words # not a comment".to_string(),
+            ),
+            ("TRICKY".to_string(), "a\"b\\c $HOME\nnext".to_string()),
+            ("EMPTY".to_string(), String::new()),
+        ];
+        let content = format_dotenv(&vars);
+        assert!(content.contains("PLAIN=sk-abc_123\n"));
+        assert_eq!(parse_dotenv(&content).unwrap(), vars);
+    }
+}
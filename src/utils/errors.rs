@@ -11,8 +11,12 @@ pub enum CcmError {
     #[error("PIN is required")]
     PinRequired,
 
-    #[error("Invalid PIN")]
-    InvalidPin,
+    /// A wrong PIN, with what is left before a delay when known
+    #[error("Invalid PIN{}", pin_detail(.0))]
+    InvalidPin(Option<String>),
+
+    #[error("Too many failed PIN attempts; try again in {0}")]
+    PinLocked(String),
 
     #[error("Master key not available")]
     MasterKeyNotAvailable,
@@ -41,11 +45,21 @@ pub enum CcmError {
     #[error("Entry not found: {0}")]
     EntryNotFound(String),
 
+    /// An entry name that matches nothing, with close names to suggest
+    #[error("Entry not found: {name}{}", did_you_mean(.suggestions))]
+    EntryNotFoundSuggestions {
+        name: String,
+        suggestions: Vec<String>,
+    },
+
+    #[error("Entry is locked: {0} (run 'ccm unlock {0}' or pass --force-unlock)")]
+    EntryLocked(String),
+
     #[error("Secret not found: {0}")]
     SecretNotFound(String),
 
     #[error("Keyring error: {0}")]
-    Keyring(#[from] keyring::Error),
+    Keyring(keyring::Error),
 
     #[error("Dialoguer error: {0}")]
     Dialoguer(#[from] dialoguer::Error),
@@ -62,6 +76,9 @@ pub enum CcmError {
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
 
+    #[error("Database integrity problem: {0}")]
+    Integrity(String),
+
     #[error("Migration failed: {0}")]
     MigrationFailed(String),
 
@@ -74,15 +91,172 @@ pub enum CcmError {
     #[error("Process error: {0}")]
     Process(String),
 
+    #[error("Remote store error: {0}")]
+    Remote(String),
+
+    #[error("Provider API error: {0}")]
+    Provider(String),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+impl CcmError {
+    /// Stable, machine-readable code for the error (used by --json)
+    pub fn code(&self) -> &'static str {
+        match self {
+            CcmError::OsSecretServiceRequired => "secret_service_required",
+            CcmError::PinRequired => "pin_required",
+            CcmError::InvalidPin(_) => "invalid_pin",
+            CcmError::PinLocked(_) => "pin_locked",
+            CcmError::MasterKeyNotAvailable => "master_key_not_available",
+            CcmError::MasterKeyCacheExpired => "master_key_cache_expired",
+            CcmError::FailedToLoadMasterKey(_) => "master_key_load_failed",
+            CcmError::Database(_) => "database",
+            CcmError::Encryption(_) => "encryption",
+            CcmError::Decryption(_) => "decryption",
+            CcmError::Io(_) => "io",
+            CcmError::Serialization(_) => "serialization",
+            CcmError::EntryNotFound(_) | CcmError::EntryNotFoundSuggestions { .. } => {
+                "entry_not_found"
+            }
+            CcmError::EntryLocked(_) => "entry_locked",
+            CcmError::SecretNotFound(_) => "secret_not_found",
+            CcmError::Keyring(_) => "keyring",
+            CcmError::Dialoguer(_) => "prompt",
+            CcmError::AuthenticationRequired => "authentication_required",
+            CcmError::NotAuthenticated => "not_authenticated",
+            CcmError::InvalidCommand(_) => "invalid_command",
+            CcmError::InvalidArgument(_) => "invalid_argument",
+            CcmError::Integrity(_) => "integrity",
+            CcmError::MigrationFailed(_) => "migration_failed",
+            CcmError::Initialization(_) => "initialization",
+            CcmError::PlatformNotSupported(_) => "platform_not_supported",
+            CcmError::Process(_) => "process",
+            CcmError::Remote(_) => "remote",
+            CcmError::Provider(_) => "provider",
+            CcmError::Unknown(_) => "unknown",
+        }
+    }
+
+    /// Process exit code for the error class, so scripts can branch on it
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CcmError::EntryNotFound(_)
+            | CcmError::EntryNotFoundSuggestions { .. }
+            | CcmError::SecretNotFound(_) => EXIT_NOT_FOUND,
+            CcmError::PinRequired
+            | CcmError::MasterKeyNotAvailable
+            | CcmError::MasterKeyCacheExpired
+            | CcmError::AuthenticationRequired
+            | CcmError::NotAuthenticated => EXIT_AUTH_REQUIRED,
+            CcmError::InvalidPin(_) | CcmError::PinLocked(_) => EXIT_INVALID_PIN,
+            CcmError::Decryption(_) => EXIT_DECRYPTION,
+            CcmError::Integrity(_) => EXIT_INTEGRITY,
+            // InvalidArgument also covers data and state errors, so it stays 1
+            CcmError::InvalidCommand(_) => EXIT_USAGE,
+            _ => EXIT_FAILURE,
+        }
+    }
+}
+
+/// " (4 attempts left)" for an invalid PIN's detail, if any
+fn pin_detail(detail: &Option<String>) -> String {
+    detail
+        .as_ref()
+        .map(|detail| format!(" ({})", detail))
+        .unwrap_or_default()
+}
+
+/// " (did you mean a, b or c?)" for the suggestions, if any
+fn did_you_mean(suggestions: &[String]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [only] => format!(" (did you mean {}?)", only),
+        [rest @ .., last] => format!(" (did you mean {} or {}?)", rest.join(", "), last),
+    }
+}
+
+/// Exit code for errors without a more specific class
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code: entry or secret not found
+pub const EXIT_NOT_FOUND: i32 = 2;
+/// Exit code: the vault is locked and no PIN was given
+pub const EXIT_AUTH_REQUIRED: i32 = 3;
+/// Exit code: wrong PIN, or PIN entry locked after too many attempts
+pub const EXIT_INVALID_PIN: i32 = 4;
+/// Exit code: a secret could not be decrypted
+pub const EXIT_DECRYPTION: i32 = 5;
+/// Exit code: the database failed an integrity check
+pub const EXIT_INTEGRITY: i32 = 6;
+/// Exit code: bad command line (as sysexits' EX_USAGE): clap's parse errors
+/// and unknown subcommands
+pub const EXIT_USAGE: i32 = 64;
+
 /// Result type alias for CCM
 pub type Result<T> = std::result::Result<T, CcmError>;
 
+impl From<keyring::Error> for CcmError {
+    /// A missing or unreachable secret service gets its own error, whichever
+    /// keyring call ran into it first
+    fn from(err: keyring::Error) -> Self {
+        match err {
+            keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_) => {
+                CcmError::OsSecretServiceRequired
+            }
+            err => CcmError::Keyring(err),
+        }
+    }
+}
+
 impl From<anyhow::Error> for CcmError {
     fn from(err: anyhow::Error) -> Self {
         CcmError::Unknown(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(CcmError::EntryNotFound("x".into()).exit_code(), 2);
+        let suggested = CcmError::EntryNotFoundSuggestions {
+            name: "anthropc".into(),
+            suggestions: vec![
+                "anthropic".into(),
+                "anthropic-work".into(),
+                "antigravity".into(),
+            ],
+        };
+        assert_eq!(suggested.exit_code(), 2);
+        assert_eq!(
+            suggested.to_string(),
+            "Entry not found: anthropc (did you mean anthropic, anthropic-work or antigravity?)"
+        );
+        assert_eq!(CcmError::PinRequired.exit_code(), 3);
+        let wrong = CcmError::InvalidPin(Some("4 attempts left".into()));
+        assert_eq!(wrong.exit_code(), 4);
+        assert_eq!(wrong.to_string(), "Invalid PIN (4 attempts left)");
+        assert_eq!(CcmError::InvalidPin(None).to_string(), "Invalid PIN");
+        assert_eq!(CcmError::PinLocked("30s".into()).exit_code(), 4);
+        assert_eq!(CcmError::Decryption("bad tag".into()).exit_code(), 5);
+        assert_eq!(CcmError::InvalidCommand("x".into()).exit_code(), EXIT_USAGE);
+        assert_eq!(CcmError::InvalidArgument("x".into()).exit_code(), 1);
+        assert_eq!(CcmError::Unknown("x".into()).exit_code(), 1);
+    }
+
+    #[test]
+    fn test_keyring_errors() {
+        let unavailable = keyring::Error::PlatformFailure("no dbus session".into());
+        assert!(matches!(
+            CcmError::from(unavailable),
+            CcmError::OsSecretServiceRequired
+        ));
+        assert!(matches!(
+            CcmError::from(keyring::Error::NoEntry),
+            CcmError::Keyring(_)
+        ));
+    }
+}
@@ -0,0 +1,332 @@
+// File helpers
+
+use crate::utils::{CcmError, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Write a file readable only by the current user (0600 on Unix)
+/// Used for files holding key material or plaintext secrets
+/// The write is atomic, see `write_file_atomic`
+pub fn write_private_file(path: &Path, data: &[u8]) -> Result<()> {
+    write_atomic(path, data, Some(0o600))
+}
+
+/// Replace a file's contents atomically: the data goes to a temporary file in
+/// the same directory, is flushed to disk and then renamed over `path`, so a
+/// crash or a full disk leaves either the old or the new contents, never a
+/// truncated file. An existing file keeps its permissions, and a symlink
+/// (e.g. a dotfile-managed `.zshrc`) keeps pointing at its updated target
+pub fn write_file_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    write_atomic(path, data, None)
+}
+
+fn write_atomic(path: &Path, data: &[u8], mode: Option<u32>) -> Result<()> {
+    let target = resolve_symlink(path)?;
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| CcmError::InvalidArgument(format!("Not a file path: {}", path.display())))?
+        .to_string_lossy()
+        .into_owned();
+
+    let (tmp_path, mut file) = create_temp_file(&dir, &file_name, mode)?;
+    let result = (|| -> Result<()> {
+        // Keep the permissions of the file being replaced
+        #[cfg(unix)]
+        if mode.is_none() {
+            if let Ok(metadata) = fs::metadata(&target) {
+                file.set_permissions(metadata.permissions())?;
+            }
+        }
+
+        file.write_all(data)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&tmp_path, &target)?;
+        sync_dir(&dir);
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Follow a symlink to the file it points at (`path` itself otherwise)
+fn resolve_symlink(path: &Path) -> Result<PathBuf> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            let link = fs::read_link(path)?;
+            Ok(match path.parent() {
+                Some(parent) if link.is_relative() => parent.join(link),
+                _ => link,
+            })
+        }
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+/// Create a new temporary file next to the target (`.<name>.tmp-<pid>-<n>`)
+fn create_temp_file(dir: &Path, file_name: &str, mode: Option<u32>) -> Result<(PathBuf, fs::File)> {
+    let mut attempt = 0;
+    loop {
+        let tmp_path = dir.join(format!(
+            ".{}.tmp-{}-{}",
+            file_name,
+            std::process::id(),
+            attempt
+        ));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode.unwrap_or(0o666));
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        match options.open(&tmp_path) {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Flush a directory entry change (the rename) to disk
+/// Best effort: not every platform or filesystem supports it
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+/// Overwrite a file's contents with zeros, flush them to disk and delete it
+/// Best effort against recovery of plaintext; copy-on-write and journaling
+/// filesystems may still keep old blocks
+pub fn shred_file(path: &Path) -> Result<()> {
+    let len = fs::metadata(path)?.len();
+    {
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        let zeros = [0u8; 4096];
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk = remaining.min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        file.sync_all()?;
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Check that a file is safe to trust: a regular file (not a symlink),
+/// owned by the current user and not accessible to group or others
+/// Always succeeds on non-Unix platforms
+pub fn check_private_file(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let metadata = fs::symlink_metadata(path)?;
+        if !metadata.file_type().is_file() {
+            return Err(CcmError::Unknown(format!(
+                "{} is not a regular file",
+                path.display()
+            )));
+        }
+
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        if metadata.uid() != uid {
+            return Err(CcmError::Unknown(format!(
+                "{} is owned by another user (uid {})",
+                path.display(),
+                metadata.uid()
+            )));
+        }
+
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Err(CcmError::Unknown(format!(
+                "{} has insecure permissions {:o} (expected 600)",
+                path.display(),
+                mode
+            )));
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// File name for an entry name: characters other than letters, digits,
+/// dots, dashes and underscores become underscores
+pub fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Directory `ccm-<kind>-<user>` for plaintext files handed to other tools,
+/// readable only by the user: in the runtime directory ($XDG_RUNTIME_DIR,
+/// /run/user/UID, usually memory-backed) when there is one, else in the temp
+/// directory (per-user %TEMP% on Windows)
+pub fn private_runtime_dir(kind: &str) -> Result<PathBuf> {
+    let dir = dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("ccm-{}-{}", kind, whoami()));
+    create_private_dir(&dir)?;
+
+    // A directory someone else made in a shared /tmp is not ours to use
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let metadata = fs::symlink_metadata(&dir)?;
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let uid = unsafe { libc::geteuid() };
+        if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0
+        {
+            return Err(CcmError::Unknown(format!(
+                "{} is not a private directory of the current user",
+                dir.display()
+            )));
+        }
+    }
+    Ok(dir)
+}
+
+fn whoami() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map(|user| safe_file_name(&user))
+        .unwrap_or_else(|_| "user".to_string())
+}
+
+/// Create a directory (and parents) accessible only by the current user (0700 on Unix)
+pub fn create_private_dir(path: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    builder.create(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_private_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.json");
+
+        write_private_file(&path, b"first").unwrap();
+        write_private_file(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_write_file_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".zshrc");
+
+        write_file_atomic(&path, b"export A=1\n").unwrap();
+        write_file_atomic(&path, b"export B=2\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"export B=2\n");
+
+        // No temporary files are left behind
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from(".zshrc")]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            // Existing permissions survive the rename
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+            write_file_atomic(&path, b"x").unwrap();
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+
+            // A symlinked rc file stays a symlink; its target is updated
+            let link = dir.path().join("link-rc");
+            std::os::unix::fs::symlink(".zshrc", &link).unwrap();
+            write_file_atomic(&link, b"via link").unwrap();
+            assert!(fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink());
+            assert_eq!(fs::read(&path).unwrap(), b"via link");
+        }
+    }
+
+    #[test]
+    fn test_shred_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plaintext.pem");
+
+        write_private_file(&path, &[b'x'; 10_000]).unwrap();
+        shred_file(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_check_private_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        write_private_file(&path, b"{}").unwrap();
+        assert!(check_private_file(&path).is_ok());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(check_private_file(&path).is_err());
+
+            let link = dir.path().join("link.json");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            assert!(check_private_file(&link).is_err());
+        }
+    }
+}
@@ -0,0 +1,293 @@
+// Fuzzy matching of entry names (ccm get, ccm pick)
+//
+// `score` ranks a name the way fzf and skim do: the pattern's characters
+// must appear in order, and matches at the start of a word or in a run
+// score more than scattered ones. A name that is not such a match can still
+// match with a few typos (`anthpric` finds anthropic-work); those rank below
+// every in-order match.
+//
+// `unique_prefix` and `suggestions` serve lookups by exact name: `ccm use
+// anth` resolving to the only entry starting with it, and the "did you
+// mean" list of a name that matches nothing.
+
+/// Score of each matched character
+const MATCH: i64 = 16;
+/// Extra score of a match at the start of the name or of a word in it
+const BOUNDARY: i64 = 8;
+/// Extra score of a match right after the previous one (as much as a word
+/// start, so that `open` prefers openai to o-p-e-n)
+const CONSECUTIVE: i64 = 8;
+/// Penalty for skipping characters between two matches, and for each one
+const GAP_START: i64 = 3;
+const GAP_EXTENSION: i64 = 1;
+/// Score of a typo match with no typos; each typo costs `TYPO`
+const TYPO_BASE: i64 = -1_000_000;
+const TYPO: i64 = 1_000;
+
+/// Whether `chars[i]` starts a word: the first character, one after a
+/// separator, or an uppercase letter after a lowercase one
+fn is_boundary(chars: &[char], i: usize) -> bool {
+    i == 0
+        || !chars[i - 1].is_alphanumeric()
+        || (chars[i].is_uppercase() && chars[i - 1].is_lowercase())
+}
+
+/// Best score of `pattern` as an in-order match in `candidate`
+fn subsequence_score(pattern: &[char], candidate: &[char]) -> Option<i64> {
+    let lower: Vec<char> = candidate.iter().flat_map(|c| c.to_lowercase()).collect();
+    if lower.len() != candidate.len() || pattern.len() > candidate.len() {
+        // Case folding changed the length (rare scripts): plain containment
+        let text: String = lower.iter().collect();
+        let needle: String = pattern.iter().collect();
+        return text
+            .contains(&needle)
+            .then_some(MATCH * pattern.len() as i64);
+    }
+
+    // best[j]: best score with the current pattern character matched at j
+    let mut best: Vec<Option<i64>> = vec![None; candidate.len()];
+    for (i, &p) in pattern.iter().enumerate() {
+        let mut next = vec![None; candidate.len()];
+        // Best score of the previous character matched before j, gap included
+        let mut carried: Option<i64> = None;
+        for j in 0..candidate.len() {
+            if lower[j] == p {
+                let bonus = if is_boundary(candidate, j) {
+                    BOUNDARY
+                } else {
+                    0
+                };
+                next[j] = if i == 0 {
+                    Some(MATCH + 2 * bonus)
+                } else {
+                    let run = j
+                        .checked_sub(1)
+                        .and_then(|k| best[k])
+                        .map(|s| s + CONSECUTIVE);
+                    run.max(carried).map(|s| s + MATCH + bonus)
+                };
+            }
+            if i > 0 {
+                // Leaving j unmatched: a match before it pays for the gap
+                carried = match (carried, best[j]) {
+                    (Some(c), Some(b)) => Some((c - GAP_EXTENSION).max(b - GAP_START)),
+                    (Some(c), None) => Some(c - GAP_EXTENSION),
+                    (None, b) => b.map(|b| b - GAP_START),
+                };
+            }
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Fewest edits (insertions, deletions, substitutions, swaps of neighbours)
+/// turning `pattern` into some part of `candidate`
+pub fn typo_distance(pattern: &str, candidate: &str) -> usize {
+    edits(pattern, candidate, true)
+}
+
+/// Fewest edits turning `a` into `b`, ignoring case
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    edits(a, b, false)
+}
+
+/// Edits turning `pattern` into `candidate`, or into some part of it when
+/// `anywhere` is set
+fn edits(pattern: &str, candidate: &str, anywhere: bool) -> usize {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let t: Vec<char> = candidate.to_lowercase().chars().collect();
+
+    // rows[i][j]: edits for pattern[..i] ending at candidate[..j]; with
+    // `anywhere`, starting anywhere in the candidate is free
+    let mut rows = vec![vec![0usize; t.len() + 1]; p.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    if !anywhere {
+        for (j, cell) in rows[0].iter_mut().enumerate() {
+            *cell = j;
+        }
+    }
+    for i in 1..=p.len() {
+        for j in 1..=t.len() {
+            let cost = usize::from(p[i - 1] != t[j - 1]);
+            let mut d = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && p[i - 1] == t[j - 2] && p[i - 2] == t[j - 1] {
+                d = d.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = d;
+        }
+    }
+    if anywhere {
+        rows[p.len()].iter().copied().min().unwrap_or(p.len())
+    } else {
+        rows[p.len()][t.len()]
+    }
+}
+
+/// Typos tolerated in a pattern of `len` characters
+fn allowed_typos(len: usize) -> usize {
+    if len < 4 {
+        0
+    } else {
+        len / 2 - 1
+    }
+}
+
+/// How well `candidate` matches `pattern` (higher is better), or None
+/// Matching ignores case; an empty pattern matches everything
+pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    if let Some(score) = subsequence_score(&pattern, &chars) {
+        return Some(score);
+    }
+
+    let typos = typo_distance(&pattern.iter().collect::<String>(), candidate);
+    (typos <= allowed_typos(pattern.len())).then(|| TYPO_BASE - TYPO * typos as i64)
+}
+
+/// The candidates matching `pattern`, best first (shorter names first among
+/// equals); all of them in their order for an empty pattern
+pub fn rank<'a, I>(pattern: &str, candidates: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut scored: Vec<(i64, &str)> = candidates
+        .into_iter()
+        .filter_map(|c| score(pattern, c).map(|s| (s, c)))
+        .collect();
+    if !pattern.is_empty() {
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| a.1.chars().count().cmp(&b.1.chars().count()))
+                .then_with(|| a.1.cmp(b.1))
+        });
+    }
+    scored.into_iter().map(|(_, c)| c).collect()
+}
+
+/// The only name starting with `prefix` (ignoring case), if exactly one does
+pub fn unique_prefix<'a, I>(prefix: &str, names: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let prefix = prefix.to_lowercase();
+    if prefix.is_empty() {
+        return None;
+    }
+    let mut found = names
+        .into_iter()
+        .filter(|name| name.to_lowercase().starts_with(&prefix));
+    match (found.next(), found.next()) {
+        (Some(name), None) => Some(name),
+        _ => None,
+    }
+}
+
+/// Names to suggest for `name`, which matches none: those starting with or
+/// containing it, then the ones a few edits away; closest first, at most
+/// `limit`
+pub fn suggestions<'a, I>(name: &str, names: I, limit: usize) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let lower = name.to_lowercase();
+    if lower.is_empty() {
+        return Vec::new();
+    }
+    let max_edits = (name.chars().count() / 3).max(2);
+    let mut close: Vec<(usize, &str)> = names
+        .into_iter()
+        .filter_map(|candidate| {
+            let candidate_lower = candidate.to_lowercase();
+            let distance = if candidate_lower.starts_with(&lower) {
+                0
+            } else if candidate_lower.contains(&lower) {
+                1
+            } else {
+                edit_distance(name, candidate)
+            };
+            (distance <= max_edits).then_some((distance, candidate))
+        })
+        .collect();
+    close.sort();
+    close.into_iter().take(limit).map(|(_, c)| c).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 6] = [
+        "anthropic-work",
+        "anthropic-personal",
+        "openai",
+        "openrouter",
+        "github-token",
+        "prod-db",
+    ];
+
+    #[test]
+    fn test_rank_in_order_matches() {
+        assert_eq!(rank("aw", NAMES), ["anthropic-work"]);
+        assert_eq!(
+            rank("ANTH", NAMES),
+            ["anthropic-work", "anthropic-personal"]
+        );
+        assert_eq!(rank("or", NAMES)[0], "openrouter");
+        assert_eq!(rank("gt", NAMES)[0], "github-token");
+        assert_eq!(rank("", NAMES), NAMES);
+        assert!(rank("xyz", NAMES).is_empty());
+
+        // Word starts and runs beat scattered letters
+        assert!(score("db", "prod-db").unwrap() > score("db", "dashboard").unwrap());
+        assert!(score("open", "openai").unwrap() > score("open", "o-p-e-n").unwrap());
+        assert!(score("wt", "WebToken").unwrap() > score("wt", "newt").unwrap());
+    }
+
+    #[test]
+    fn test_typos() {
+        assert_eq!(typo_distance("anthpric", "anthropic-work"), 3);
+        assert_eq!(typo_distance("github", "my-github-token"), 0);
+        assert_eq!(typo_distance("gihtub", "github"), 1);
+        assert_eq!(typo_distance("abc", ""), 3);
+        assert_eq!(edit_distance("anthropic", "anthropic-work"), 5);
+        assert_eq!(edit_distance("OpenAI", "opnai"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+
+        assert_eq!(
+            rank("anthpric", NAMES)[..2],
+            ["anthropic-work", "anthropic-personal"]
+        );
+        assert_eq!(rank("opnerouter", NAMES), ["openrouter"]);
+        // Short patterns need an in-order match
+        assert!(score("xai", "openai").is_none());
+        // Typo matches rank after in-order ones
+        assert!(score("githbu", "my-githbu").unwrap() > score("githbu", "github").unwrap());
+    }
+
+    #[test]
+    fn test_lookup_helpers() {
+        assert_eq!(unique_prefix("open", NAMES), None);
+        assert_eq!(unique_prefix("openr", NAMES), Some("openrouter"));
+        assert_eq!(unique_prefix("GIT", NAMES), Some("github-token"));
+        assert_eq!(unique_prefix("work", NAMES), None);
+        assert_eq!(unique_prefix("", NAMES), None);
+
+        assert_eq!(
+            suggestions("anthropic", NAMES, 3),
+            ["anthropic-personal", "anthropic-work"]
+        );
+        assert_eq!(suggestions("opneai", NAMES, 3), ["openai"]);
+        assert_eq!(suggestions("db", NAMES, 3), ["prod-db"]);
+        assert_eq!(suggestions("token", NAMES, 1), ["github-token"]);
+        assert!(suggestions("kubeconfig", NAMES, 3).is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+// Shell-style glob matching for entry names
+//
+// - `*` matches any run of characters (including none)
+// - `?` matches exactly one character
+// - everything else matches itself (case-sensitive, like entry names)
+
+/// Whether a name argument is a glob pattern rather than an exact name
+pub fn is_pattern(value: &str) -> bool {
+    value.contains(['*', '?'])
+}
+
+/// Match `name` against a glob `pattern`
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name index it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+            }
+            Some(&c) if c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, start)) => {
+                    p = star + 1;
+                    n = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("openai-*", "openai-prod"));
+        assert!(matches("openai-*", "openai-"));
+        assert!(!matches("openai-*", "my-openai-prod"));
+        assert!(matches("*-prod", "openai-prod"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(!matches("a*b*c", "axxbyy"));
+        assert!(matches("db-?", "db-1"));
+        assert!(!matches("db-?", "db-10"));
+        assert!(matches("exact", "exact"));
+        assert!(!matches("Exact", "exact"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn test_is_pattern() {
+        assert!(is_pattern("openai-*"));
+        assert!(is_pattern("db-?"));
+        assert!(!is_pattern("claude-api"));
+    }
+}
@@ -0,0 +1,133 @@
+// Secret input helpers - hidden prompts and stdin
+
+use crate::utils::{parse_bool, CcmError, Result};
+use colored::Colorize;
+use dialoguer::Password;
+use std::io::Read;
+use std::path::Path;
+
+/// Config key that rejects secrets passed as command-line arguments
+pub const FORBID_ARGV_SECRETS_KEY: &str = "security.forbid-argv-secrets";
+
+/// Internal setting recording that the argv warning has been shown
+const ARGV_WARNING_SHOWN_KEY: &str = "__argv_secret_warning_shown__";
+
+/// Resolve a secret supplied on the command line
+/// `None` prompts with hidden input, `-` reads the secret from stdin
+pub fn resolve_secret(value: Option<String>) -> Result<String> {
+    match value {
+        Some(v) if v == "-" => read_secret_from_stdin(),
+        Some(v) => {
+            check_argv_secret()?;
+            Ok(v)
+        }
+        None => prompt_secret("Enter secret"),
+    }
+}
+
+/// Vet a secret that arrived as a command-line argument
+/// Rejected when security.forbid-argv-secrets is on; otherwise a warning about
+/// shell history and process listings is shown the first time
+pub fn check_argv_secret() -> Result<()> {
+    let db = crate::db::get_database()?;
+
+    let forbidden = db
+        .get_setting::<String>(FORBID_ARGV_SECRETS_KEY)?
+        .and_then(|v| parse_bool(&v))
+        .unwrap_or(false);
+    if forbidden {
+        return Err(CcmError::InvalidArgument(format!(
+            "Secrets on the command line are disabled ({}).\n\
+             Omit the value to enter it at a hidden prompt, or pass '-' to read it from stdin.",
+            FORBID_ARGV_SECRETS_KEY
+        )));
+    }
+
+    if db.get_setting::<String>(ARGV_WARNING_SHOWN_KEY)?.is_none() {
+        eprintln!(
+            "{} Secrets passed as arguments end up in shell history and are visible to other processes.",
+            "⚠️".yellow()
+        );
+        eprintln!("   Omit the value for a hidden prompt, or pass '-' and pipe it on stdin.");
+        eprintln!(
+            "   Set 'ccm config {} true' to reject them. (Shown once.)",
+            FORBID_ARGV_SECRETS_KEY
+        );
+        db.save_setting(ARGV_WARNING_SHOWN_KEY, &chrono::Utc::now().to_rfc3339())?;
+    }
+
+    Ok(())
+}
+
+/// Prompt for a secret without echoing it
+pub fn prompt_secret(prompt: &str) -> Result<String> {
+    Ok(Password::new().with_prompt(prompt).interact()?)
+}
+
+/// Read a secret from stdin, dropping a single trailing newline
+pub fn read_secret_from_stdin() -> Result<String> {
+    let mut buffer = String::new();
+    std::io::stdin().read_to_string(&mut buffer)?;
+
+    let secret = strip_trailing_newline(&buffer);
+    if secret.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "No secret received on stdin".to_string(),
+        ));
+    }
+
+    Ok(secret.to_string())
+}
+
+/// Read a secret from a file, unchanged (newlines included)
+pub fn read_secret_file(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| CcmError::InvalidArgument(format!("Cannot read {}: {}", path.display(), e)))?;
+    if bytes.is_empty() {
+        return Err(CcmError::InvalidArgument(format!(
+            "{} is empty",
+            path.display()
+        )));
+    }
+    String::from_utf8(bytes).map_err(|_| {
+        CcmError::InvalidArgument(format!(
+            "{} is not text; store binary files base64-encoded",
+            path.display()
+        ))
+    })
+}
+
+/// Strip one trailing `\n` or `\r\n` (as added by `echo` or heredocs)
+fn strip_trailing_newline(s: &str) -> &str {
+    s.strip_suffix("\r\n")
+        .or_else(|| s.strip_suffix('\n'))
+        .unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_trailing_newline() {
+        assert_eq!(strip_trailing_newline("secret\n"), "secret");
+        assert_eq!(strip_trailing_newline("secret\r\n"), "secret");
+        assert_eq!(strip_trailing_newline("secret"), "secret");
+        assert_eq!(strip_trailing_newline("line1\nline2\n\n"), "line1\nline2\n");
+    }
+
+    #[test]
+    fn test_read_secret_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cert.pem");
+        let pem = "-----BEGIN CERTIFICATE-----\r\nMIIB\n-----END CERTIFICATE-----\n";
+        std::fs::write(&path, pem).unwrap();
+        assert_eq!(read_secret_file(&path).unwrap(), pem);
+
+        std::fs::write(&path, "").unwrap();
+        assert!(read_secret_file(&path).is_err());
+        std::fs::write(&path, [0xff, 0xfe, 0x00]).unwrap();
+        assert!(read_secret_file(&path).is_err());
+        assert!(read_secret_file(&dir.path().join("missing")).is_err());
+    }
+}
@@ -4,7 +4,21 @@ pub mod clipboard;
 pub mod crypto;
 pub mod csv_parser;
 pub mod debug;
+pub mod dotenv;
 pub mod errors;
+pub mod files;
+pub mod fuzzy;
+pub mod glob;
+pub mod input;
+pub mod output;
+pub mod paper_key;
+pub mod password_gen;
+pub mod picker;
+pub mod recipients;
+pub mod ssh;
+pub mod template;
+pub mod timings;
+pub mod tree;
 pub mod validation;
 
 pub use crypto::*;
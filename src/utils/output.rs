@@ -0,0 +1,130 @@
+// Output mode: colored text for people, or JSON for scripts (--json)
+//
+// `--json` or `CCM_OUTPUT=json` makes commands with a result (list, get,
+// search, status, stats, history, version) print one JSON document on stdout,
+// and errors print `{"error": {"code": ..., "message": ...}}` on stderr. The
+// codes come from `CcmError::code` and do not change between releases.
+// Confirmations of the other commands become `{"ok": true, "message": ...}`.
+//
+// `--quiet` drops confirmations and notices, keeping results and errors;
+// `--no-color` (or `NO_COLOR`, see https://no-color.org) turns off colors.
+
+use crate::utils::{CcmError, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Environment variable: `json` to get JSON output without `--json`
+pub const OUTPUT_ENV: &str = "CCM_OUTPUT";
+
+/// Environment variable: any non-empty value turns colors off
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Global output flags from the command line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputOptions {
+    pub json: bool,
+    pub quiet: bool,
+    pub no_color: bool,
+}
+
+/// Set up the output mode from the flags and the environment
+pub fn init(options: OutputOptions) {
+    let json_env = std::env::var(OUTPUT_ENV).is_ok_and(|v| v.trim().eq_ignore_ascii_case("json"));
+    let no_color_env = std::env::var_os(NO_COLOR_ENV).is_some_and(|v| !v.is_empty());
+    let json = options.json || json_env;
+
+    JSON.store(json, Ordering::Relaxed);
+    QUIET.store(options.quiet, Ordering::Relaxed);
+    if options.no_color || no_color_env || json {
+        colored::control::set_override(false);
+    }
+}
+
+/// Whether output should be JSON
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Whether confirmations and notices are suppressed (--quiet)
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a confirmation ("✅ Added entry: ...") unless --quiet; with --json,
+/// a `success_json` document even with --quiet, as it is the command's result
+pub fn success(message: impl std::fmt::Display) {
+    if is_json() {
+        println!("{}", success_json(&message.to_string()));
+    } else if !is_quiet() {
+        println!("{} {}", "✅".green(), message);
+    }
+}
+
+/// JSON document for a confirmation
+pub fn success_json(message: &str) -> serde_json::Value {
+    serde_json::json!({ "ok": true, "message": message })
+}
+
+/// Print a result as pretty JSON on stdout
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// JSON document for an error
+pub fn error_json(error: &CcmError) -> serde_json::Value {
+    let mut document = serde_json::json!({
+        "error": {
+            "code": error.code(),
+            "message": error.to_string(),
+        }
+    });
+    if let CcmError::EntryNotFoundSuggestions { suggestions, .. } = error {
+        document["error"]["suggestions"] = serde_json::json!(suggestions);
+    }
+    document
+}
+
+/// Print an error on stderr in the current output mode
+pub fn print_error(error: &CcmError) {
+    if is_json() {
+        eprintln!("{}", error_json(error));
+    } else {
+        eprintln!("{} {}", "Error:".red(), error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success_json() {
+        assert_eq!(
+            success_json("Added entry: openai").to_string(),
+            r#"{"message":"Added entry: openai","ok":true}"#
+        );
+    }
+
+    #[test]
+    fn test_error_json() {
+        let error = CcmError::EntryNotFound("openai".to_string());
+        assert_eq!(
+            error_json(&error).to_string(),
+            r#"{"error":{"code":"entry_not_found","message":"Entry not found: openai"}}"#
+        );
+
+        let error = CcmError::EntryNotFoundSuggestions {
+            name: "opnai".to_string(),
+            suggestions: vec!["openai".to_string()],
+        };
+        assert_eq!(
+            error_json(&error)["error"]["suggestions"],
+            serde_json::json!(["openai"])
+        );
+    }
+}
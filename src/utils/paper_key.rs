@@ -0,0 +1,147 @@
+// Paper backup of the master key: a BIP39 mnemonic or a base58 string
+//
+// Both carry a checksum (BIP39's own, and the first 4 bytes of SHA-256 for
+// base58, as in Base58Check), so a mistyped word or character is rejected
+// instead of restoring the wrong key.
+
+use crate::utils::{CcmError, Result};
+use sha2::{Digest, Sha256};
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const CHECKSUM_LEN: usize = 4;
+
+/// The key as 24 BIP39 English words
+pub fn to_mnemonic(key: &[u8; 32]) -> String {
+    bip39::Mnemonic::from_entropy(key)
+        .expect("32 bytes is a valid BIP39 entropy length")
+        .to_string()
+}
+
+/// The key plus checksum in base58
+pub fn to_base58(key: &[u8; 32]) -> String {
+    let mut data = key.to_vec();
+    data.extend_from_slice(&Sha256::digest(key)[..CHECKSUM_LEN]);
+    base58_encode(&data)
+}
+
+/// Parse a mnemonic (words separated by whitespace) or a base58 string
+pub fn parse(input: &str) -> Result<[u8; 32]> {
+    let input = input.trim();
+    if input.split_whitespace().count() > 1 {
+        let words = input
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let mnemonic = bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &words)
+            .map_err(|e| CcmError::InvalidArgument(format!("Invalid recovery words: {}", e)))?;
+        return mnemonic
+            .to_entropy()
+            .try_into()
+            .map_err(|_| CcmError::InvalidArgument("Expected 24 recovery words".to_string()));
+    }
+
+    let data = base58_decode(input).ok_or_else(|| {
+        CcmError::InvalidArgument("Invalid paper key: not base58 or recovery words".to_string())
+    })?;
+    if data.len() != 32 + CHECKSUM_LEN {
+        return Err(CcmError::InvalidArgument(
+            "Invalid paper key: wrong length".to_string(),
+        ));
+    }
+    let (key, checksum) = data.split_at(32);
+    if Sha256::digest(key)[..CHECKSUM_LEN] != *checksum {
+        return Err(CcmError::InvalidArgument(
+            "Invalid paper key: checksum mismatch (check for typos)".to_string(),
+        ));
+    }
+    Ok(key.try_into().expect("split at 32"))
+}
+
+fn base58_encode(data: &[u8]) -> String {
+    // Repeated division of the big-endian number by 58
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in data {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    std::iter::repeat_n(BASE58_ALPHABET[0], zeros)
+        .chain(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+fn base58_decode(input: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in input.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros = input
+        .bytes()
+        .take_while(|&c| c == BASE58_ALPHABET[0])
+        .count();
+    Some(
+        std::iter::repeat_n(0, zeros)
+            .chain(bytes.into_iter().rev())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut key = [0u8; 32];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let words = to_mnemonic(&key);
+        assert_eq!(words.split_whitespace().count(), 24);
+        assert_eq!(parse(&words).unwrap(), key);
+        // Extra whitespace and capitals are fine
+        assert_eq!(
+            parse(&format!("  {}\n", words.to_uppercase().replace(' ', "  "))).unwrap(),
+            key
+        );
+
+        let encoded = to_base58(&key);
+        assert!(encoded.starts_with('1'));
+        assert_eq!(parse(&encoded).unwrap(), key);
+    }
+
+    #[test]
+    fn test_typos_rejected() {
+        let key = [7u8; 32];
+        let words = to_mnemonic(&key);
+        let swapped = words.replacen(words.split(' ').next().unwrap(), "zoo", 1);
+        assert!(parse(&swapped).is_err());
+
+        let mut encoded = to_base58(&key).into_bytes();
+        encoded[10] = if encoded[10] == b'2' { b'3' } else { b'2' };
+        assert!(parse(&String::from_utf8(encoded).unwrap()).is_err());
+        assert!(parse("0OIl").is_err());
+    }
+}
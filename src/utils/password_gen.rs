@@ -0,0 +1,183 @@
+// Random password and passphrase generation
+
+use crate::utils::{CcmError, Result};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const SYMBOLS: &[u8] = b"!@#$%^&*()-_=+[]{};:,.<>?/~";
+
+/// Maximum length of a generated password
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+/// Maximum number of words in a generated passphrase
+pub const MAX_PASSPHRASE_WORDS: usize = 64;
+
+/// What kind of secret to generate
+#[derive(Debug, Clone)]
+pub enum GenerateSpec {
+    /// Random characters drawn from the enabled classes
+    Password(PasswordOptions),
+    /// Diceware-style passphrase from the BIP39 English word list
+    Passphrase { words: usize, separator: String },
+}
+
+/// Character classes and length for random passwords
+#[derive(Debug, Clone)]
+pub struct PasswordOptions {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        Self {
+            length: 32,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: false,
+        }
+    }
+}
+
+/// Generate a secret according to the spec
+pub fn generate(spec: &GenerateSpec) -> Result<String> {
+    match spec {
+        GenerateSpec::Password(options) => generate_password(options),
+        GenerateSpec::Passphrase { words, separator } => generate_passphrase(*words, separator),
+    }
+}
+
+/// Generate a random password
+/// Every enabled character class appears at least once
+pub fn generate_password(options: &PasswordOptions) -> Result<String> {
+    let classes: Vec<&[u8]> = [
+        (options.lowercase, LOWERCASE),
+        (options.uppercase, UPPERCASE),
+        (options.digits, DIGITS),
+        (options.symbols, SYMBOLS),
+    ]
+    .iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, chars)| *chars)
+    .collect();
+
+    if classes.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "At least one character class must be enabled".to_string(),
+        ));
+    }
+
+    if options.length < classes.len() || options.length > MAX_PASSWORD_LENGTH {
+        return Err(CcmError::InvalidArgument(format!(
+            "Password length must be between {} and {}",
+            classes.len(),
+            MAX_PASSWORD_LENGTH
+        )));
+    }
+
+    let mut rng = OsRng;
+    let alphabet: Vec<u8> = classes.concat();
+
+    // One character from each class, the rest from the full alphabet
+    let mut chars: Vec<u8> = classes
+        .iter()
+        .map(|class| class[rng.gen_range(0..class.len())])
+        .collect();
+    while chars.len() < options.length {
+        chars.push(alphabet[rng.gen_range(0..alphabet.len())]);
+    }
+    chars.shuffle(&mut rng);
+
+    Ok(chars.into_iter().map(char::from).collect())
+}
+
+/// Generate a passphrase of random words
+pub fn generate_passphrase(words: usize, separator: &str) -> Result<String> {
+    if words == 0 || words > MAX_PASSPHRASE_WORDS {
+        return Err(CcmError::InvalidArgument(format!(
+            "Passphrase word count must be between 1 and {}",
+            MAX_PASSPHRASE_WORDS
+        )));
+    }
+
+    let word_list = bip39::Language::English.word_list();
+    let mut rng = OsRng;
+
+    let chosen: Vec<&str> = (0..words)
+        .map(|_| word_list[rng.gen_range(0..word_list.len())])
+        .collect();
+
+    Ok(chosen.join(separator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_password_length_and_classes() {
+        let options = PasswordOptions {
+            length: 40,
+            symbols: true,
+            ..PasswordOptions::default()
+        };
+        let password = generate_password(&options).unwrap();
+
+        assert_eq!(password.len(), 40);
+        assert!(password.bytes().any(|b| LOWERCASE.contains(&b)));
+        assert!(password.bytes().any(|b| UPPERCASE.contains(&b)));
+        assert!(password.bytes().any(|b| DIGITS.contains(&b)));
+        assert!(password.bytes().any(|b| SYMBOLS.contains(&b)));
+    }
+
+    #[test]
+    fn test_generate_password_respects_disabled_classes() {
+        let options = PasswordOptions {
+            length: 64,
+            uppercase: false,
+            digits: false,
+            ..PasswordOptions::default()
+        };
+        let password = generate_password(&options).unwrap();
+
+        assert!(password.bytes().all(|b| LOWERCASE.contains(&b)));
+    }
+
+    #[test]
+    fn test_generate_password_rejects_invalid_options() {
+        let no_classes = PasswordOptions {
+            lowercase: false,
+            uppercase: false,
+            digits: false,
+            symbols: false,
+            ..PasswordOptions::default()
+        };
+        assert!(generate_password(&no_classes).is_err());
+
+        let too_short = PasswordOptions {
+            length: 2,
+            ..PasswordOptions::default()
+        };
+        assert!(generate_password(&too_short).is_err());
+    }
+
+    #[test]
+    fn test_generate_passphrase() {
+        let passphrase = generate_passphrase(6, "-").unwrap();
+        let words: Vec<&str> = passphrase.split('-').collect();
+
+        assert_eq!(words.len(), 6);
+        let word_list = bip39::Language::English.word_list();
+        assert!(words.iter().all(|w| word_list.contains(w)));
+
+        assert!(generate_passphrase(0, "-").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+// Interactive fuzzy picker (ccm pick, ccm get without a name)
+//
+// Typing filters the entry names live with `fuzzy::rank`; Up/Down move the
+// selection, Enter picks and Esc or Ctrl-C cancels. Drawn on stderr so that
+// the command's own output can still be piped.
+
+use crate::utils::{fuzzy, CcmError, Result};
+use colored::Colorize;
+use console::{Key, Term};
+use std::io;
+
+/// Rows of matches shown at once
+const VISIBLE: usize = 10;
+
+/// Let the user pick one of `items`, starting from `query`
+/// Returns None when they cancel
+pub fn pick(prompt: &str, items: &[String], query: &str) -> Result<Option<String>> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err(CcmError::InvalidArgument(
+            "The picker needs a terminal; pass the entry name instead".to_string(),
+        ));
+    }
+    if items.is_empty() {
+        return Err(CcmError::InvalidArgument(
+            "No entries to pick from".to_string(),
+        ));
+    }
+
+    let mut query = query.to_string();
+    let mut selected = 0usize;
+    let mut drawn = 0usize;
+    term.hide_cursor()?;
+
+    let picked = loop {
+        let matches = fuzzy::rank(&query, items.iter().map(String::as_str));
+        selected = selected.min(matches.len().saturating_sub(1));
+
+        term.clear_last_lines(drawn)?;
+        drawn = draw(&term, prompt, &query, &matches, selected, items.len())?;
+
+        let key = match term.read_key() {
+            Ok(key) => key,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => break None,
+            Err(e) => {
+                term.show_cursor()?;
+                return Err(e.into());
+            }
+        };
+        match key {
+            Key::Enter if !matches.is_empty() => break Some(matches[selected].to_string()),
+            Key::Escape | Key::CtrlC => break None,
+            Key::ArrowUp | Key::BackTab => selected = selected.saturating_sub(1),
+            Key::ArrowDown | Key::Tab if selected + 1 < matches.len() => selected += 1,
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    };
+
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    Ok(picked)
+}
+
+/// Draw the prompt and the visible matches; returns the lines written
+fn draw(
+    term: &Term,
+    prompt: &str,
+    query: &str,
+    matches: &[&str],
+    selected: usize,
+    total: usize,
+) -> Result<usize> {
+    let width = term.size().1 as usize;
+    term.write_line(&format!("{} {}{}", prompt.bold(), query, "█".dimmed()))?;
+
+    // Scroll so the selection stays visible
+    let start = (selected + 1).saturating_sub(VISIBLE);
+    for (i, name) in matches.iter().enumerate().skip(start).take(VISIBLE) {
+        let name = console::truncate_str(name, width.saturating_sub(3), "…");
+        let line = if i == selected {
+            format!("{} {}", "›".cyan(), name.cyan().bold())
+        } else {
+            format!("  {}", name)
+        };
+        term.write_line(&line)?;
+    }
+    term.write_line(
+        &format!(
+            "  {}/{}  ↑↓ move · enter pick · esc cancel",
+            matches.len(),
+            total
+        )
+        .dimmed()
+        .to_string(),
+    )?;
+    Ok(matches.len().min(VISIBLE) + 2)
+}
@@ -0,0 +1,223 @@
+// Backup encryption to public-key recipients (age X25519, GnuPG)
+//
+// Unlike password-encrypted backups, these can be handed to a teammate who
+// only shares their public key; they decrypt with their own identity file
+// (age) or keyring / smartcard (gpg).
+
+use crate::utils::{CcmError, Result};
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Magic line every binary age file starts with
+const AGE_HEADER: &[u8] = b"age-encryption.org/v1";
+
+/// Whether `data` is an age-encrypted file
+pub fn is_age_file(data: &[u8]) -> bool {
+    data.starts_with(AGE_HEADER)
+}
+
+/// Encrypt `data` to one or more age X25519 recipients (`age1...` public keys)
+pub fn age_encrypt(data: &[u8], recipients: &[String]) -> Result<Vec<u8>> {
+    let recipients = recipients
+        .iter()
+        .map(|r| {
+            age::x25519::Recipient::from_str(r.trim())
+                .map(|r| Box::new(r) as Box<dyn age::Recipient + Send>)
+                .map_err(|e| {
+                    CcmError::InvalidArgument(format!("Invalid age recipient '{}': {}", r, e))
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let encryptor = age::Encryptor::with_recipients(recipients)
+        .ok_or_else(|| CcmError::InvalidArgument("No age recipients given".to_string()))?;
+
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut encrypted)
+        .map_err(|e| CcmError::Encryption(e.to_string()))?;
+    writer.write_all(data)?;
+    writer.finish()?;
+
+    Ok(encrypted)
+}
+
+/// Decrypt an age file with the identities in `identity_file`
+/// (the `AGE-SECRET-KEY-1...` file written by `age-keygen`)
+pub fn age_decrypt(data: &[u8], identity_file: &Path) -> Result<Vec<u8>> {
+    let file = std::fs::File::open(identity_file).map_err(|e| {
+        CcmError::InvalidArgument(format!(
+            "Cannot read identity file {}: {}",
+            identity_file.display(),
+            e
+        ))
+    })?;
+    let identities: Vec<age::x25519::Identity> =
+        age::IdentityFile::from_buffer(BufReader::new(file))
+            .map_err(|e| CcmError::InvalidArgument(format!("Invalid identity file: {}", e)))?
+            .into_identities()
+            .into_iter()
+            .map(|entry| match entry {
+                age::IdentityFileEntry::Native(identity) => identity,
+            })
+            .collect();
+
+    let decryptor =
+        match age::Decryptor::new(data).map_err(|e| CcmError::Decryption(e.to_string()))? {
+            age::Decryptor::Recipients(d) => d,
+            age::Decryptor::Passphrase(_) => {
+                return Err(CcmError::Decryption(
+                    "Passphrase-encrypted age files are not supported; use an identity file"
+                        .to_string(),
+                ))
+            }
+        };
+
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(|i| i as &dyn age::Identity))
+        .map_err(|e| CcmError::Decryption(format!("age decryption failed: {}", e)))?;
+    let mut decrypted = Vec::new();
+    reader.read_to_end(&mut decrypted)?;
+
+    Ok(decrypted)
+}
+
+/// Whether `data` looks like an OpenPGP message: ASCII-armored, or binary
+/// starting with a public-key encrypted session key packet (tag 1)
+pub fn is_gpg_file(data: &[u8]) -> bool {
+    data.starts_with(b"-----BEGIN PGP MESSAGE-----")
+        || matches!(data.first(), Some(0x84..=0x87 | 0xc1))
+}
+
+/// Encrypt `data` to GnuPG recipients (key IDs, fingerprints or emails)
+/// with the local `gpg` binary
+pub fn gpg_encrypt(data: &[u8], recipients: &[String]) -> Result<Vec<u8>> {
+    let mut args = vec!["--batch", "--yes", "--encrypt"];
+    for recipient in recipients {
+        args.extend(["--recipient", recipient.as_str()]);
+    }
+    run_gpg(&args, data)
+}
+
+/// Decrypt an OpenPGP message with the local `gpg` (and its agent, so
+/// smartcards and pinentry work as usual)
+pub fn gpg_decrypt(data: &[u8]) -> Result<Vec<u8>> {
+    run_gpg(&["--quiet", "--decrypt"], data)
+}
+
+/// Run gpg with `input` on stdin and return its stdout
+fn run_gpg(args: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+    run_filter("gpg", args, input)
+}
+
+/// Run `program` with `input` on stdin and return its stdout; a failure is
+/// reported with the program's stderr
+fn run_filter(program: &str, args: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .env_remove(crate::auth::PIN_ENV)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            CcmError::Unknown(format!(
+                "Failed to run {}: {}. Is GnuPG installed and on PATH?",
+                program, e
+            ))
+        })?;
+
+    // Feed stdin from another thread so a large output cannot block the pipe
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+    let written = writer
+        .join()
+        .map_err(|_| CcmError::Unknown(format!("{} input thread panicked", program)))?;
+
+    // A program that fails early closes stdin: its stderr says why
+    if !output.status.success() {
+        return Err(CcmError::Encryption(format!(
+            "{} failed ({}): {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    match written {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+        _ => {}
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+
+    #[test]
+    fn test_age_round_trip() {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+
+        let encrypted = age_encrypt(b"{\"entries\":{}}", &[recipient]).unwrap();
+        assert!(is_age_file(&encrypted));
+
+        let dir = tempfile::tempdir().unwrap();
+        let identity_file = dir.path().join("key.txt");
+        std::fs::write(
+            &identity_file,
+            format!("# test key\n{}\n", identity.to_string().expose_secret()),
+        )
+        .unwrap();
+
+        let decrypted = age_decrypt(&encrypted, &identity_file).unwrap();
+        assert_eq!(decrypted, b"{\"entries\":{}}");
+
+        let other = dir.path().join("other.txt");
+        std::fs::write(
+            &other,
+            age::x25519::Identity::generate()
+                .to_string()
+                .expose_secret(),
+        )
+        .unwrap();
+        assert!(age_decrypt(&encrypted, &other).is_err());
+    }
+
+    #[test]
+    fn test_is_gpg_file() {
+        assert!(is_gpg_file(b"-----BEGIN PGP MESSAGE-----\n\nhQEMA..."));
+        assert!(is_gpg_file(&[0x85, 0x01, 0x0c]));
+        assert!(!is_gpg_file(b"{\"entries\":{}}"));
+        assert!(!is_gpg_file(b"age-encryption.org/v1\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_filter_reports_stderr() {
+        // Exits without reading a large input: the failure, not the broken
+        // pipe, is reported
+        let input = vec![b'x'; 1 << 20];
+        let err = run_filter("sh", &["-c", "echo 'no secret key' >&2; exit 2"], &input)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no secret key"), "{}", err);
+
+        // Output that does not need all the input is fine
+        let output = run_filter("sh", &["-c", "echo ok"], &input).unwrap();
+        assert_eq!(output, b"ok\n");
+        assert_eq!(run_filter("cat", &[], b"abc").unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_invalid_recipient() {
+        assert!(age_encrypt(b"x", &["not-a-key".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+// Placeholders of `ccm render` templates
+//
+//     {{ openai.OPENAI_API_KEY }}   variable of an entry (SECRET resolved)
+//     {{ secret("github") }}        secret of an entry
+//     {{ OPENAI_API_KEY }}          variable of the --entry entry
+//
+// The entry name is everything before the last '.'. A backslash before
+// `{{` keeps it literal. Nothing else is interpreted, so the rest of the
+// file comes out byte for byte.
+
+use crate::utils::{CcmError, Result};
+
+/// What a placeholder stands for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Placeholder {
+    /// A variable of an entry; no entry means the default one
+    Var { entry: Option<String>, var: String },
+    /// The secret of an entry
+    Secret(String),
+}
+
+/// Replace each placeholder of `template` with `resolve`'s value for it
+pub fn render<F>(template: &str, mut resolve: F) -> Result<String>
+where
+    F: FnMut(&Placeholder) -> Result<String>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+
+        let line = line_of(template, template.len() - rest.len() + start);
+        let inner = &rest[start + 2..];
+        let end = inner.find("}}").ok_or_else(|| {
+            CcmError::InvalidArgument(format!("Line {}: '{{{{' is never closed", line))
+        })?;
+        let placeholder = parse(inner[..end].trim())
+            .map_err(|message| CcmError::InvalidArgument(format!("Line {}: {}", line, message)))?;
+        out.push_str(&resolve(&placeholder)?);
+        rest = &inner[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// The placeholders of `template`, in order and without repeats
+pub fn placeholders(template: &str) -> Result<Vec<Placeholder>> {
+    let mut found = Vec::new();
+    render(template, |placeholder| {
+        if !found.contains(placeholder) {
+            found.push(placeholder.clone());
+        }
+        Ok(String::new())
+    })?;
+    Ok(found)
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+fn parse(expression: &str) -> std::result::Result<Placeholder, String> {
+    let call = expression
+        .strip_prefix("secret")
+        .map(str::trim)
+        .filter(|args| args.starts_with('('));
+    if let Some(args) = call {
+        let name = args
+            .strip_prefix('(')
+            .and_then(|a| a.strip_suffix(')'))
+            .map(str::trim)
+            .and_then(unquote)
+            .ok_or_else(|| format!("expected secret(\"NAME\"), got '{}'", expression))?;
+        return Ok(Placeholder::Secret(name.to_string()));
+    }
+
+    let (entry, var) = match expression.rsplit_once('.') {
+        Some((entry, var)) => (Some(entry.trim().to_string()), var.trim()),
+        None => (None, expression),
+    };
+    let valid_var = var.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_var || entry.as_deref() == Some("") {
+        return Err(format!(
+            "expected ENTRY.VAR, VAR or secret(\"NAME\"), got '{}'",
+            expression
+        ));
+    }
+    Ok(Placeholder::Var {
+        entry,
+        var: var.to_string(),
+    })
+}
+
+fn unquote(text: &str) -> Option<&str> {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| text.strip_prefix(*q)?.strip_suffix(*q))
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(entry: Option<&str>, var: &str) -> Placeholder {
+        Placeholder::Var {
+            entry: entry.map(str::to_string),
+            var: var.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let template = "key = {{ openai.OPENAI_API_KEY }}\n\
+                        token={{secret('gh')}} {{ secret(\"gh\") }}\n\
+                        model: {{MODEL}} \\{{ kept }}\n";
+        let rendered = render(template, |p| {
+            Ok(match p {
+                Placeholder::Var { entry, var } => {
+                    format!("{}:{}", entry.as_deref().unwrap_or("-"), var)
+                }
+                Placeholder::Secret(name) => format!("<{}>", name),
+            })
+        })
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "key = openai:OPENAI_API_KEY\ntoken=<gh> <gh>\nmodel: -:MODEL {{ kept }}\n"
+        );
+
+        assert_eq!(
+            placeholders(template).unwrap(),
+            [
+                var(Some("openai"), "OPENAI_API_KEY"),
+                Placeholder::Secret("gh".to_string()),
+                var(None, "MODEL"),
+            ]
+        );
+        assert_eq!(
+            placeholders("{{ work/v1.2.API_KEY }} {{ secrets.TOKEN }}").unwrap(),
+            [
+                var(Some("work/v1.2"), "API_KEY"),
+                var(Some("secrets"), "TOKEN")
+            ]
+        );
+        assert_eq!(
+            render("no placeholders", |_| unreachable!()).unwrap(),
+            "no placeholders"
+        );
+    }
+
+    #[test]
+    fn test_render_errors() {
+        for (template, line) in [
+            ("a\nb {{ x.Y", "Line 2"),
+            ("{{ }}", "Line 1"),
+            ("{{ x.1A }}", "Line 1"),
+            ("\n\n{{ secret(gh) }}", "Line 3"),
+            ("{{ .VAR }}", "Line 1"),
+        ] {
+            let err = placeholders(template).unwrap_err().to_string();
+            assert!(err.contains(line), "{}: {}", template, err);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// Per-command timing breakdown (--timings)
+
+use colored::Colorize;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Phases that are timed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// OS keyring / secret service access
+    Keyring,
+    /// PBKDF2 key derivation (PIN, backup passwords)
+    Kdf,
+    /// Opening the database (including the SQLCipher key schedule) and queries
+    Database,
+    /// AES-GCM encryption and decryption of secrets
+    Crypto,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [Phase::Keyring, Phase::Kdf, Phase::Database, Phase::Crypto];
+
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Keyring => "Keyring access",
+            Phase::Kdf => "Key derivation",
+            Phase::Database => "Database",
+            Phase::Crypto => "Crypto",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Accumulated (duration, call count) per phase
+static TOTALS: Mutex<[(Duration, u32); 4]> = Mutex::new([(Duration::ZERO, 0); 4]);
+
+thread_local! {
+    /// Whether a span is running on this thread
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Start collecting timings for this process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether timings are being collected
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A running measurement, recorded when dropped
+pub struct Span {
+    phase: Phase,
+    start: Option<Instant>,
+}
+
+/// Start measuring a phase (no-op unless timings are enabled). A span opened
+/// while another one runs on the same thread is a no-op too: its time is
+/// already counted by the outer phase, and counting it twice would make the
+/// phases add up to more than the command took
+pub fn span(phase: Phase) -> Span {
+    let start = (is_enabled() && !ACTIVE.replace(true)).then(Instant::now);
+    Span { phase, start }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            record(self.phase, start.elapsed());
+            ACTIVE.set(false);
+        }
+    }
+}
+
+/// Measure a closure as one call of the given phase
+pub fn measure<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let _span = span(phase);
+    f()
+}
+
+fn record(phase: Phase, elapsed: Duration) {
+    if let Ok(mut totals) = TOTALS.lock() {
+        let slot = &mut totals[phase.index()];
+        slot.0 += elapsed;
+        slot.1 += 1;
+    }
+}
+
+/// Print the breakdown for a command that ran for `total`
+/// Time not attributed to a phase is shown as prompts/rendering/other
+pub fn print_report(total: Duration) {
+    let totals = match TOTALS.lock() {
+        Ok(totals) => *totals,
+        Err(_) => return,
+    };
+
+    eprintln!();
+    eprintln!("{}", "Timings".bold().underline());
+
+    let mut accounted = Duration::ZERO;
+    for phase in Phase::ALL {
+        let (elapsed, calls) = totals[phase.index()];
+        accounted += elapsed;
+        eprintln!(
+            "  {:<28} {:>10}  ({} {})",
+            phase.label(),
+            format_duration(elapsed),
+            calls,
+            if calls == 1 { "call" } else { "calls" }
+        );
+    }
+
+    eprintln!(
+        "  {:<28} {:>10}",
+        "Prompts, rendering, other",
+        format_duration(total.saturating_sub(accounted))
+    );
+    eprintln!("  {:<28} {:>10}", "Total".bold(), format_duration(total));
+}
+
+fn format_duration(d: Duration) -> String {
+    format!("{:.1} ms", d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_only_record_when_enabled() {
+        // Keyring is never touched by other tests, so the counts are stable
+        measure(Phase::Keyring, || ());
+        assert_eq!(TOTALS.lock().unwrap()[Phase::Keyring.index()].1, 0);
+
+        enable();
+        measure(Phase::Keyring, || {
+            std::thread::sleep(Duration::from_millis(2))
+        });
+        let (elapsed, calls) = TOTALS.lock().unwrap()[Phase::Keyring.index()];
+        assert_eq!(calls, 1);
+        assert!(elapsed >= Duration::from_millis(2));
+
+        // Nested spans are part of the outer one
+        measure(Phase::Keyring, || {
+            assert!(span(Phase::Crypto).start.is_none());
+            assert!(span(Phase::Keyring).start.is_none());
+        });
+        assert_eq!(TOTALS.lock().unwrap()[Phase::Keyring.index()].1, 2);
+        assert!(span(Phase::Crypto).start.is_some());
+    }
+}
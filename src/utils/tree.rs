@@ -0,0 +1,167 @@
+// Entry groups: `/`-separated names (work/anthropic/prod) seen as folders
+//
+// `group_glob` selects a group for `--group` filters, and `Tree` lays the
+// names out for `ccm list --tree`, where `--depth` collapses the groups
+// below a level into one line with their entry count. Names need
+// `ccm config names.allow-slash true`.
+
+use std::collections::BTreeMap;
+
+/// Glob (see `utils::glob`) matching the entries of `group` and its
+/// subgroups; a trailing '/' is optional
+pub fn group_glob(group: &str) -> String {
+    format!("{}/*", group.trim_end_matches('/'))
+}
+
+/// Entry names as a hierarchy of groups
+#[derive(Debug, Default)]
+pub struct Tree {
+    children: BTreeMap<String, Tree>,
+    /// Full name of the entry ending here, if any (a name can be both an
+    /// entry and a group: `work` and `work/github`)
+    entry: Option<String>,
+}
+
+/// One line of the tree view
+#[derive(Debug, PartialEq)]
+pub struct Line {
+    /// Branch drawing before the label ("│   ├── ")
+    pub prefix: String,
+    /// Last part of the name, with a trailing '/' for groups
+    pub label: String,
+    /// Full name when the line is an entry
+    pub entry: Option<String>,
+    /// Entries hidden below a group collapsed by the depth limit
+    pub collapsed: usize,
+}
+
+impl Tree {
+    pub fn build<'a, I>(names: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut root = Tree::default();
+        for name in names {
+            let node = name.split('/').fold(&mut root, |node, part| {
+                node.children.entry(part.to_string()).or_default()
+            });
+            node.entry = Some(name.to_string());
+        }
+        root
+    }
+
+    /// Entries here and below
+    fn count(&self) -> usize {
+        usize::from(self.entry.is_some()) + self.children.values().map(Tree::count).sum::<usize>()
+    }
+
+    /// The lines to print, groups deeper than `depth` levels collapsed
+    pub fn lines(&self, depth: Option<usize>) -> Vec<Line> {
+        let mut lines = Vec::new();
+        self.walk("", 1, depth, &mut lines);
+        lines
+    }
+
+    fn walk(&self, prefix: &str, level: usize, depth: Option<usize>, lines: &mut Vec<Line>) {
+        let last = self.children.len().saturating_sub(1);
+        for (i, (part, child)) in self.children.iter().enumerate() {
+            let (branch, indent) = if i == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let group = !child.children.is_empty();
+            let collapsed = if group && depth.is_some_and(|d| level >= d) {
+                child.count() - usize::from(child.entry.is_some())
+            } else {
+                0
+            };
+
+            lines.push(Line {
+                prefix: format!("{}{}", prefix, branch),
+                label: if group {
+                    format!("{}/", part)
+                } else {
+                    part.clone()
+                },
+                entry: child.entry.clone(),
+                collapsed,
+            });
+            if group && collapsed == 0 {
+                child.walk(&format!("{}{}", prefix, indent), level + 1, depth, lines);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::glob;
+
+    const NAMES: [&str; 5] = [
+        "openai",
+        "work/anthropic/dev",
+        "work/anthropic/prod",
+        "work/github",
+        "work",
+    ];
+
+    fn render(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|l| {
+                let mut line = format!("{}{}", l.prefix, l.label);
+                if l.collapsed > 0 {
+                    line.push_str(&format!(" ({})", l.collapsed));
+                }
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tree_lines() {
+        let tree = Tree::build(NAMES);
+        assert_eq!(
+            render(&tree.lines(None)),
+            [
+                "├── openai",
+                "└── work/",
+                "    ├── anthropic/",
+                "    │   ├── dev",
+                "    │   └── prod",
+                "    └── github",
+            ]
+        );
+        let lines = tree.lines(None);
+        assert_eq!(lines[1].entry.as_deref(), Some("work"));
+        assert_eq!(lines[2].entry, None);
+        assert_eq!(lines[3].entry.as_deref(), Some("work/anthropic/dev"));
+
+        assert_eq!(
+            render(&tree.lines(Some(1))),
+            ["├── openai", "└── work/ (3)"]
+        );
+        assert_eq!(
+            render(&tree.lines(Some(2))),
+            [
+                "├── openai",
+                "└── work/",
+                "    ├── anthropic/ (2)",
+                "    └── github",
+            ]
+        );
+        assert!(Tree::build([]).lines(None).is_empty());
+    }
+
+    #[test]
+    fn test_group_glob() {
+        assert_eq!(group_glob("work/anthropic/"), "work/anthropic/*");
+        let pattern = group_glob("work");
+        assert!(glob::matches(&pattern, "work/anthropic/dev"));
+        assert!(glob::matches(&pattern, "work/github"));
+        assert!(!glob::matches(&pattern, "work"));
+        assert!(!glob::matches(&pattern, "workshop/x"));
+    }
+}
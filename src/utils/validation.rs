@@ -38,28 +38,211 @@ pub fn validate_domain(domain: &str) -> anyhow::Result<()> {
     }
 }
 
-/// Validate a name (entry name, etc.)
+/// Validate a name (entry name, etc.) against the default name policy
 pub fn validate_name(name: &str) -> anyhow::Result<()> {
-    if name.is_empty() {
-        return Err(anyhow::anyhow!("Name cannot be empty"));
+    NamePolicy::default().validate(name)
+}
+
+/// Config keys for the entry name policy
+pub const NAME_POLICY_KEYS: &[&str] = &[
+    "names.allow-slash",
+    "names.allow-at",
+    "names.allow-spaces",
+    "names.max-length",
+    "names.normalize",
+];
+
+/// How names are rewritten before validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameNormalization {
+    /// Only trim surrounding whitespace
+    #[default]
+    None,
+    /// Trim and lowercase
+    Lowercase,
+    /// Lowercase and replace disallowed characters with '-'
+    Slug,
+}
+
+impl NameNormalization {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        match value.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "lowercase" => Ok(Self::Lowercase),
+            "slug" => Ok(Self::Slug),
+            _ => Err(anyhow::anyhow!(
+                "Invalid names.normalize value: {} (use none, lowercase or slug)",
+                value
+            )),
+        }
+    }
+}
+
+/// Rules for entry names
+/// Alphanumerics, '-', '_' and '.' are always allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePolicy {
+    /// Allow '/' for namespaces such as "work/github"
+    pub allow_slash: bool,
+    pub allow_at: bool,
+    /// Allow single inner spaces (names must then be quoted on the command line)
+    pub allow_spaces: bool,
+    pub max_length: usize,
+    pub normalize: NameNormalization,
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        Self {
+            allow_slash: false,
+            allow_at: false,
+            allow_spaces: false,
+            max_length: 100,
+            normalize: NameNormalization::None,
+        }
+    }
+}
+
+impl NamePolicy {
+    /// Build a policy from config values, falling back to defaults for unset keys
+    pub fn from_settings<F>(lookup: F) -> anyhow::Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut policy = Self::default();
+
+        let flag = |key: &str, default: bool| -> anyhow::Result<bool> {
+            match lookup(key) {
+                Some(v) => parse_bool(&v).ok_or_else(|| {
+                    anyhow::anyhow!("Invalid {} value: {} (use true or false)", key, v)
+                }),
+                None => Ok(default),
+            }
+        };
+        policy.allow_slash = flag("names.allow-slash", policy.allow_slash)?;
+        policy.allow_at = flag("names.allow-at", policy.allow_at)?;
+        policy.allow_spaces = flag("names.allow-spaces", policy.allow_spaces)?;
+
+        if let Some(v) = lookup("names.max-length") {
+            policy.max_length = v
+                .trim()
+                .parse()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid names.max-length value: {}", v))?;
+        }
+        if let Some(v) = lookup("names.normalize") {
+            policy.normalize = NameNormalization::parse(v.trim())?;
+        }
+
+        Ok(policy)
+    }
+
+    fn allows_char(&self, c: char) -> bool {
+        c.is_alphanumeric()
+            || c == '-'
+            || c == '_'
+            || c == '.'
+            || (self.allow_slash && c == '/')
+            || (self.allow_at && c == '@')
+            || (self.allow_spaces && c == ' ')
     }
 
-    if name.len() > 100 {
-        return Err(anyhow::anyhow!("Name too long (max 100 characters)"));
+    /// Apply the configured normalization
+    pub fn normalize(&self, name: &str) -> String {
+        let trimmed = name.trim();
+        match self.normalize {
+            NameNormalization::None => trimmed.to_string(),
+            NameNormalization::Lowercase => trimmed.to_lowercase(),
+            NameNormalization::Slug => self.sanitize(&trimmed.to_lowercase()),
+        }
     }
 
-    // Check for valid characters (alphanumeric, hyphen, underscore, dot)
-    let valid_chars = name
-        .chars()
-        .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.');
+    /// Rewrite a name so it satisfies the policy
+    /// Disallowed characters become '-', runs of separators collapse, and the
+    /// result is truncated to the maximum length
+    pub fn sanitize(&self, name: &str) -> String {
+        let mut out = String::new();
+        for c in name.trim().chars() {
+            let c = if self.allows_char(c) { c } else { '-' };
+            let is_sep = c == '-' || c == ' ' || c == '/';
+            if is_sep && out.ends_with(['-', ' ', '/']) {
+                continue;
+            }
+            out.push(c);
+        }
 
-    if !valid_chars {
-        return Err(anyhow::anyhow!(
-            "Name contains invalid characters (only alphanumeric, -, _, . allowed)"
-        ));
+        let mut out: String = out
+            .trim_matches(|c| c == '-' || c == ' ' || c == '/')
+            .chars()
+            .take(self.max_length)
+            .collect();
+        while out.ends_with(['-', ' ', '/']) {
+            out.pop();
+        }
+        out
     }
 
-    Ok(())
+    /// Validate a (normalized) name
+    pub fn validate(&self, name: &str) -> anyhow::Result<()> {
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Name cannot be empty"));
+        }
+
+        if name.chars().count() > self.max_length {
+            return Err(anyhow::anyhow!(
+                "Name too long (max {} characters)",
+                self.max_length
+            ));
+        }
+
+        if !name.chars().all(|c| self.allows_char(c)) {
+            return Err(anyhow::anyhow!(
+                "Name contains invalid characters (only {} allowed)",
+                self.describe_allowed()
+            ));
+        }
+
+        if self.allow_spaces
+            && (name.starts_with(' ') || name.ends_with(' ') || name.contains("  "))
+        {
+            return Err(anyhow::anyhow!(
+                "Name cannot start or end with a space or contain repeated spaces"
+            ));
+        }
+
+        if self.allow_slash && (name.starts_with('/') || name.ends_with('/') || name.contains("//"))
+        {
+            return Err(anyhow::anyhow!(
+                "Name cannot start or end with '/' or contain empty namespace segments"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn describe_allowed(&self) -> String {
+        let mut allowed = vec!["alphanumeric", "-", "_", "."];
+        if self.allow_slash {
+            allowed.push("/");
+        }
+        if self.allow_at {
+            allowed.push("@");
+        }
+        if self.allow_spaces {
+            allowed.push("spaces");
+        }
+        allowed.join(", ")
+    }
+}
+
+/// Parse a boolean config value (true/false, yes/no, on/off, 1/0)
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
@@ -90,4 +273,41 @@ mod tests {
         assert!(validate_name("invalid name").is_err());
         assert!(validate_name("a".repeat(101).as_str()).is_err());
     }
+
+    #[test]
+    fn test_name_policy_from_settings() {
+        let policy = NamePolicy::from_settings(|key| match key {
+            "names.allow-slash" => Some("true".to_string()),
+            "names.max-length" => Some("20".to_string()),
+            "names.normalize" => Some("lowercase".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert!(policy.allow_slash);
+        assert!(!policy.allow_at);
+        assert_eq!(policy.max_length, 20);
+        assert!(policy.validate("work/github").is_ok());
+        assert!(policy.validate("work//github").is_err());
+        assert!(policy.validate("user@host").is_err());
+        assert_eq!(policy.normalize("  Work/GitHub "), "work/github");
+
+        assert!(NamePolicy::from_settings(|_| Some("maybe".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_name_policy_sanitize() {
+        let policy = NamePolicy::default();
+        let sanitized = policy.sanitize("My Bank: Login (old)");
+        assert_eq!(sanitized, "My-Bank-Login-old");
+        assert!(policy.validate(&sanitized).is_ok());
+
+        let spaces = NamePolicy {
+            allow_spaces: true,
+            allow_at: true,
+            normalize: NameNormalization::Slug,
+            ..NamePolicy::default()
+        };
+        assert_eq!(spaces.normalize("Me@Example  Site!"), "me@example site");
+    }
 }